@@ -0,0 +1,68 @@
+// Throughput of the storage engines under writes from several threads at once, the load the
+// sharded keyspace is meant for:
+//
+//   cargo bench --bench keyspace
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use simple_redis_server::{BulkString, MemoryStorage, RespFrame, ShardedStorage, Storage, Value};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// keys each thread works on, and commands it runs per iteration
+const KEYS: u64 = 10_000;
+const OPS: u64 = 1_000;
+
+// Runs `ops` commands as a pipelining client would: mostly reads, and writes setting an expiry
+// time on one key out of four.
+fn run<S: Storage>(storage: &S, thread: u64, ops: u64) {
+    for i in 0..ops {
+        let key = format!("key:{}", (thread * 7919 + i * 104_729) % KEYS).into_bytes();
+        match i % 4 {
+            0 => {
+                let value = Value::String(RespFrame::BulkString(BulkString::from("value")));
+                storage.insert(key.clone(), value);
+                storage.set_expire(key, i as i64);
+            }
+            1 => {
+                storage.upsert(key, |value| (value.is_some(), None));
+            }
+            _ => {
+                std::hint::black_box(storage.get(&key).is_some());
+            }
+        }
+    }
+}
+
+fn bench_engine<S: Storage>(c: &mut Criterion, name: &str, threads: &[u64]) {
+    let mut group = c.benchmark_group(name);
+    for &n in threads {
+        let storage = S::default();
+        group.throughput(Throughput::Elements(n * OPS));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+            b.iter_custom(|iters| {
+                let start = Instant::now();
+                thread::scope(|scope| {
+                    for thread in 0..n {
+                        let storage = &storage;
+                        scope.spawn(move || run(storage, thread, iters * OPS));
+                    }
+                });
+                start.elapsed()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn keyspace(c: &mut Criterion) {
+    let threads = [1, 2, 4, 8];
+    // a single map of the whole keyspace, the engine before sharding
+    bench_engine::<MemoryStorage>(c, "memory", &threads);
+    bench_engine::<ShardedStorage>(c, "sharded", &threads);
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_secs(3));
+    targets = keyspace
+}
+criterion_main!(benches);
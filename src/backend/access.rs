@@ -0,0 +1,85 @@
+use rand::Rng;
+
+// the access counter of a new key, so it isn't evicted before it had a chance to be used
+pub const LFU_INIT_VAL: u8 = 5;
+const MILLIS_PER_MINUTE: i64 = 60_000;
+
+// When a key was last read or written and how often, for the LRU and LFU eviction policies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyAccess {
+    // milliseconds since the unix epoch
+    pub at: i64,
+    // logarithmic access frequency counter like Redis's: incremented with a probability falling
+    // as it grows, so that 255 takes about a million accesses with lfu-log-factor 10, and decayed
+    // as time passes without access
+    pub freq: u8,
+}
+
+impl KeyAccess {
+    pub fn new(now: i64) -> Self {
+        Self {
+            at: now,
+            freq: LFU_INIT_VAL,
+        }
+    }
+
+    // The counter as of `now`, decreased by one per `decay_time` minutes since the last access.
+    // A `decay_time` of 0 never decays it.
+    pub fn decayed_freq(&self, now: i64, decay_time: u64) -> u8 {
+        if decay_time == 0 {
+            return self.freq;
+        }
+        let periods = (now - self.at).max(0) / MILLIS_PER_MINUTE / decay_time as i64;
+        self.freq.saturating_sub(periods.min(u8::MAX as i64) as u8)
+    }
+
+    // The access record after an access at `now`: the counter is decayed first, then
+    // incremented with probability 1 / ((counter - LFU_INIT_VAL) * log_factor + 1).
+    pub fn accessed(&self, now: i64, log_factor: u64, decay_time: u64) -> Self {
+        let mut freq = self.decayed_freq(now, decay_time);
+        if freq < u8::MAX {
+            let base = freq.saturating_sub(LFU_INIT_VAL) as f64;
+            let p = 1.0 / (base * log_factor as f64 + 1.0);
+            if rand::thread_rng().gen::<f64>() < p {
+                freq += 1;
+            }
+        }
+        Self { at: now, freq }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lfu_counter() {
+        let access = KeyAccess::new(0);
+        assert_eq!(access.freq, LFU_INIT_VAL);
+        // a log factor of 0 increments on every access
+        let access = access.accessed(1_000, 0, 1);
+        assert_eq!(access, KeyAccess { at: 1_000, freq: 6 });
+        assert_eq!(access.accessed(2_000, 0, 1).freq, 7);
+
+        // the increments get rarer as the counter grows
+        let mut access = KeyAccess::new(0);
+        for _ in 0..1_000 {
+            access = access.accessed(0, 10, 1);
+        }
+        assert!((12..40).contains(&access.freq), "{}", access.freq);
+        let full = KeyAccess { at: 0, freq: 255 };
+        assert_eq!(full.accessed(0, 0, 1).freq, 255);
+    }
+
+    #[test]
+    fn test_lfu_decay() {
+        let access = KeyAccess { at: 0, freq: 10 };
+        assert_eq!(access.decayed_freq(59_999, 1), 10);
+        assert_eq!(access.decayed_freq(3 * 60_000, 1), 7);
+        assert_eq!(access.decayed_freq(3 * 60_000, 2), 9);
+        assert_eq!(access.decayed_freq(60 * 60_000, 1), 0);
+        assert_eq!(access.decayed_freq(60 * 60_000, 0), 10);
+        // decayed before counting the new access
+        assert_eq!(access.accessed(3 * 60_000, 0, 1).freq, 8);
+    }
+}
@@ -0,0 +1,520 @@
+use super::{DatasetCopy, SaveStatus, Value};
+use crate::{RespArray, RespEncoder, RespFrame};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// elements per command when a rewrite turns a collection back into commands, like Redis
+const REWRITE_ITEMS_PER_COMMAND: usize = 64;
+// how often appendfsync everysec flushes the file to the disk
+const FSYNC_INTERVAL: Duration = Duration::from_secs(1);
+// how long a failed rewrite waits before the next attempt
+const REWRITE_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+// Commands that may give their key a relative expiry. It is logged again as an absolute one
+// right after them, so that replaying the log later doesn't push it back.
+pub const EXPIRY_COMMANDS: &[&str] = &[
+    "expire", "pexpire", "setex", "psetex", "set", "getex", "restore",
+];
+
+// The arguments of a logged command.
+pub type LogCommand = Vec<Vec<u8>>;
+
+// Commands encoded the way they are written to the append only file, as RESP arrays of bulk
+// strings. A SELECT is added whenever a command runs in another database than the previous one.
+#[derive(Debug, Default)]
+pub struct CommandLog {
+    pub data: Vec<u8>,
+    // the database the last command ran in, None before the first one
+    db: Option<usize>,
+}
+
+impl CommandLog {
+    pub fn append(&mut self, db: usize, args: &[Vec<u8>]) {
+        if self.db != Some(db) {
+            self.command(&[b"SELECT".to_vec(), db.to_string().into_bytes()]);
+            self.db = Some(db);
+        }
+        self.command(args);
+    }
+
+    // Appends a command that doesn't depend on the selected database.
+    pub fn command(&mut self, args: &[Vec<u8>]) {
+        // not through RespArray, which would encode empty arguments as null bulk strings
+        self.data
+            .extend_from_slice(format!("*{}\r\n", args.len()).as_bytes());
+        for arg in args {
+            self.data
+                .extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+            self.data.extend_from_slice(arg);
+            self.data.extend_from_slice(b"\r\n");
+        }
+    }
+}
+
+// The append only file: every write command is logged to it, so that the dataset can be rebuilt
+// at startup by running them again. The log grows with every write, a rewrite replaces it with
+// the fewest commands that recreate the current dataset.
+#[derive(Debug)]
+pub struct AppendLog {
+    state: Mutex<LogState>,
+    rewrite_status: SaveStatus,
+}
+
+#[derive(Debug, Default)]
+struct LogState {
+    // None until the log holds the whole dataset: it was loaded at startup or rewritten since
+    file: Option<File>,
+    // the commands written to the file, only tracking the database selected last
+    written: CommandLog,
+    size: u64,
+    // the size right after the last rewrite, automatic rewrites are based on the growth since
+    base_size: u64,
+    last_fsync: Option<Instant>,
+    // the log needs a rewrite, because the file isn't open yet or grew too much
+    rewrite_due: bool,
+    // the commands logged while a rewrite runs, appended to the rewritten file
+    rewrite_buffer: Option<CommandLog>,
+    rewrite_failed_at: Option<Instant>,
+    // why the last write to the file failed, None if it succeeded
+    write_error: Option<String>,
+}
+
+// What makes the log grow large enough for an automatic rewrite.
+#[derive(Debug, Clone, Copy)]
+pub struct LogPolicy<'a> {
+    pub fsync: &'a str,
+    // growth since the last rewrite in percent of its size, 0 to never rewrite automatically
+    pub rewrite_percentage: u64,
+    pub rewrite_min_size: u64,
+}
+
+impl AppendLog {
+    pub fn new(now: i64) -> Self {
+        Self {
+            state: Mutex::new(LogState::default()),
+            rewrite_status: SaveStatus::new(now),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, LogState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn rewrite_status(&self) -> &SaveStatus {
+        &self.rewrite_status
+    }
+
+    // Whether a rewrite should start. After a failed one, only once REWRITE_RETRY_INTERVAL
+    // elapsed.
+    pub fn rewrite_due(&self) -> bool {
+        let state = self.state();
+        let retrying = state
+            .rewrite_failed_at
+            .is_some_and(|at| at.elapsed() < REWRITE_RETRY_INTERVAL);
+        state.rewrite_due && !retrying && !self.rewrite_status.in_progress()
+    }
+
+    pub fn size(&self) -> u64 {
+        self.state().size
+    }
+
+    pub fn base_size(&self) -> u64 {
+        self.state().base_size
+    }
+
+    pub fn write_error(&self) -> Option<String> {
+        self.state().write_error.clone()
+    }
+
+    // Continues the log in `path` after loading it at startup: the file is cut at `len`,
+    // dropping a command left incomplete, and `db` is where its commands ran last.
+    pub fn open(&self, path: &Path, len: u64, db: usize) -> io::Result<()> {
+        let file = OpenOptions::new().append(true).open(path)?;
+        file.set_len(len)?;
+        let mut state = self.state();
+        state.file = Some(file);
+        state.written = CommandLog {
+            data: vec![],
+            db: Some(db),
+        };
+        state.size = len;
+        state.base_size = len;
+        Ok(())
+    }
+
+    // Flushes what was logged to the disk, whatever appendfsync, as the server shuts down.
+    pub fn sync(&self) -> io::Result<()> {
+        match self.state().file.as_ref() {
+            Some(file) => file.sync_data(),
+            None => Ok(()),
+        }
+    }
+
+    // Stops logging, as appendonly was turned off. Turning it on again takes a rewrite.
+    pub fn close(&self) {
+        let mut state = self.state();
+        state.file = None;
+        state.rewrite_due = false;
+    }
+
+    // Logs the commands a write that ran in database `db` amounts to.
+    pub fn append(&self, db: usize, commands: &[LogCommand], policy: LogPolicy) {
+        let mut state = self.state();
+        if let Some(buffer) = state.rewrite_buffer.as_mut() {
+            for args in commands {
+                buffer.append(db, args);
+            }
+        }
+        let state = &mut *state;
+        let Some(file) = state.file.as_mut() else {
+            // the next rewrite writes the file, this command included
+            state.rewrite_due = state.rewrite_buffer.is_none();
+            return;
+        };
+        for args in commands {
+            state.written.append(db, args);
+        }
+        let result = file.write_all(&state.written.data).and_then(|_| {
+            let fsync = match policy.fsync {
+                "always" => true,
+                "everysec" => state
+                    .last_fsync
+                    .is_none_or(|at| at.elapsed() >= FSYNC_INTERVAL),
+                _ => false,
+            };
+            if fsync {
+                file.sync_data()?;
+                state.last_fsync = Some(Instant::now());
+            }
+            Ok(())
+        });
+        state.size += state.written.data.len() as u64;
+        state.written.data.clear();
+        state.write_error = result.err().map(|e| e.to_string());
+
+        let growth = state.size.saturating_sub(state.base_size);
+        if policy.rewrite_percentage > 0
+            && state.size >= policy.rewrite_min_size
+            && growth * 100 >= state.base_size.max(1) * policy.rewrite_percentage
+        {
+            state.rewrite_due = true;
+        }
+    }
+
+    // Starts buffering the commands logged from now on, for the rewrite about to run. Returns
+    // false if a rewrite is already running.
+    pub fn start_rewrite(&self) -> bool {
+        if !self.rewrite_status.start() {
+            return false;
+        }
+        self.state().rewrite_buffer = Some(CommandLog::default());
+        true
+    }
+
+    // Writes `rewritten` followed by the commands buffered meanwhile to a temporary file, which
+    // then replaces the log at `path` in one rename. With `keep_logging`, the writes that follow
+    // go to the new file. The outcome is published in the rewrite status, `now` and `duration`
+    // in seconds.
+    pub fn finish_rewrite(
+        &self,
+        path: &Path,
+        rewritten: &[u8],
+        keep_logging: bool,
+        now: i64,
+        duration: i64,
+    ) -> io::Result<()> {
+        let temp = path.with_file_name(format!("temp-rewriteaof-{}.aof", std::process::id()));
+        let result = File::create(&temp).and_then(|mut file| {
+            file.write_all(rewritten)?;
+            // the buffer keeps growing until the file is swapped, which writers wait for
+            let mut state = self.state();
+            let buffer = state.rewrite_buffer.take().unwrap_or_default();
+            file.write_all(&buffer.data)?;
+            file.sync_all()?;
+            fs::rename(&temp, path)?;
+            let size = (rewritten.len() + buffer.data.len()) as u64;
+            state.file = keep_logging.then_some(file);
+            state.written = CommandLog {
+                data: vec![],
+                db: buffer.db,
+            };
+            state.size = size;
+            state.base_size = size;
+            state.rewrite_due = false;
+            state.rewrite_failed_at = None;
+            Ok(())
+        });
+        if result.is_err() {
+            let mut state = self.state();
+            state.rewrite_buffer = None;
+            state.rewrite_failed_at = Some(Instant::now());
+            let _ = fs::remove_file(&temp);
+        }
+        self.rewrite_status.finish(
+            result.as_ref().err().map(|e| e.to_string()),
+            now,
+            duration,
+            0,
+        );
+        result
+    }
+}
+
+// The commands to log for a write command that ran with `args` and replied `reply`: the command
+// itself, unless it made a random choice, which is logged as what it did.
+pub fn effects(name: &str, args: Vec<Vec<u8>>, reply: &RespFrame) -> Vec<LogCommand> {
+    match (name, args.get(1)) {
+        ("spop", Some(key)) => {
+            let members = match reply {
+                RespFrame::BulkString(member) => vec![member.to_vec()],
+                RespFrame::Array(members) => members.iter().map(frame_bytes).collect(),
+                _ => vec![],
+            };
+            if members.is_empty() {
+                return vec![];
+            }
+            let mut srem = vec![b"SREM".to_vec(), key.clone()];
+            srem.extend(members);
+            vec![srem]
+        }
+        // the keys left for the target instance are deleted here, unless COPY
+        ("migrate", _) => {
+            let mut args = args.into_iter().skip(3);
+            let key = args.next().filter(|key| !key.is_empty());
+            let mut del = vec![b"DEL".to_vec()];
+            let mut copy = false;
+            let mut rest = args.skip(2);
+            while let Some(arg) = rest.next() {
+                match arg.to_ascii_lowercase().as_slice() {
+                    b"copy" => copy = true,
+                    b"keys" => del.extend(rest.by_ref()),
+                    _ => {}
+                }
+            }
+            del.extend(key);
+            let moved = matches!(reply, RespFrame::SimpleString(s) if s.0 == "OK");
+            if copy || !moved {
+                return vec![];
+            }
+            vec![del]
+        }
+        _ => vec![args],
+    }
+}
+
+// The commands recreating a copy of the dataset.
+pub fn rewrite(copy: DatasetCopy) -> Vec<u8> {
+    let mut log = CommandLog::default();
+    for library in &copy.functions {
+        log.command(&[
+            b"FUNCTION".to_vec(),
+            b"LOAD".to_vec(),
+            b"REPLACE".to_vec(),
+            library.code.clone().into_bytes(),
+        ]);
+    }
+    copy.for_each_key(|index, key, value, expire_at| {
+        for args in value_commands(key, value.clone()) {
+            log.append(index, &args);
+        }
+        if let Some(when) = expire_at {
+            let pexpireat = [
+                b"PEXPIREAT".to_vec(),
+                key.to_vec(),
+                when.to_string().into_bytes(),
+            ];
+            log.append(index, &pexpireat);
+        }
+    });
+    log.data
+}
+
+// The commands creating `key` with `value`, collections in batches of
+// REWRITE_ITEMS_PER_COMMAND elements.
+fn value_commands(key: &[u8], value: Value) -> Vec<LogCommand> {
+    let (command, items): (&[u8], Vec<Vec<Vec<u8>>>) = match value {
+        Value::String(value) => {
+            return vec![vec![b"SET".to_vec(), key.to_vec(), frame_bytes(&value)]]
+        }
+        Value::List(list) => (
+            b"RPUSH",
+            list.into_iter().map(|e| vec![frame_bytes(&e)]).collect(),
+        ),
+        Value::Set(set) => (
+            b"SADD",
+            set.into_iter().map(|m| vec![m.into_bytes()]).collect(),
+        ),
+        Value::Hash(hash) => (
+            b"HSET",
+            hash.into_iter()
+                .map(|(field, value)| vec![field.into_bytes(), frame_bytes(&value)])
+                .collect(),
+        ),
+        Value::ZSet(zset) => (
+            b"ZADD",
+            zset.iter()
+                .map(|(member, score)| vec![score.to_string().into_bytes(), member.into()])
+                .collect(),
+        ),
+        // consumer groups have no command recreating them as they are, a stream is restored
+        // from its DUMP payload
+        stream @ Value::Stream(_) => {
+            return vec![vec![
+                b"RESTORE".to_vec(),
+                key.to_vec(),
+                b"0".to_vec(),
+                stream.serialize(),
+                b"REPLACE".to_vec(),
+            ]]
+        }
+    };
+    items
+        .chunks(REWRITE_ITEMS_PER_COMMAND)
+        .map(|chunk| {
+            let mut args = vec![command.to_vec(), key.to_vec()];
+            args.extend(chunk.iter().flatten().cloned());
+            args
+        })
+        .collect()
+}
+
+// the bytes of a string value or collection element
+fn frame_bytes(frame: &RespFrame) -> Vec<u8> {
+    match frame {
+        RespFrame::BulkString(s) => s.to_vec(),
+        RespFrame::SimpleString(s) => s.as_bytes().to_vec(),
+        RespFrame::Integer(n) => n.to_string().into_bytes(),
+        frame => RespArray::new(vec![frame.clone()]).encode(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Storage;
+    use crate::{Backend, BulkString};
+    use std::collections::{HashSet, VecDeque};
+
+    fn commands(data: &[u8]) -> String {
+        String::from_utf8_lossy(data).replace("\r\n", " ")
+    }
+
+    #[test]
+    fn test_command_log() {
+        let mut log = CommandLog::default();
+        log.append(0, &[b"SET".to_vec(), b"k".to_vec(), vec![]]);
+        log.append(0, &[b"DEL".to_vec(), b"k".to_vec()]);
+        log.append(2, &[b"DEL".to_vec(), b"k".to_vec()]);
+        assert_eq!(
+            commands(&log.data),
+            "*2 $6 SELECT $1 0 *3 $3 SET $1 k $0  *2 $3 DEL $1 k \
+             *2 $6 SELECT $1 2 *2 $3 DEL $1 k "
+        );
+    }
+
+    #[test]
+    fn test_effects() {
+        let args = |args: &[&str]| args.iter().map(|a| a.as_bytes().to_vec()).collect();
+        let spop: LogCommand = args(&["SPOP", "s", "2"]);
+        let popped = RespArray::new(vec![
+            BulkString::from("a").into(),
+            BulkString::from("b").into(),
+        ]);
+        assert_eq!(
+            effects("spop", spop.clone(), &popped.into()),
+            [args(&["SREM", "s", "a", "b"])]
+        );
+        assert!(effects("spop", spop, &RespFrame::Null(crate::RespNull)).is_empty());
+        let set: LogCommand = args(&["SET", "k", "v"]);
+        assert_eq!(effects("set", set.clone(), &RespFrame::Integer(1)), [set]);
+
+        let migrate = |line: &str| -> LogCommand {
+            line.split(' ').map(|arg| arg.as_bytes().to_vec()).collect()
+        };
+        let ok = crate::SimpleString::new("OK").into();
+        assert_eq!(
+            effects("migrate", migrate("MIGRATE h 6379  0 10 KEYS a b"), &ok),
+            [migrate("DEL a b")]
+        );
+        assert_eq!(
+            effects("migrate", migrate("MIGRATE h 6379 a 0 10 REPLACE"), &ok),
+            [migrate("DEL a")]
+        );
+        assert!(effects("migrate", migrate("MIGRATE h 6379 a 0 10 COPY"), &ok).is_empty());
+        let nokey = crate::SimpleString::new("NOKEY").into();
+        assert!(effects("migrate", migrate("MIGRATE h 6379 a 0 10"), &nokey).is_empty());
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let list = (0..70)
+            .map(|i| BulkString::from(i.to_string()).into())
+            .collect::<VecDeque<RespFrame>>();
+        // year 2100
+        let later = 4_102_444_800_000;
+        let backend = Backend::new();
+        let db = |index| backend.database(index).expect("database exists");
+        db(0)
+            .keyspace
+            .insert(b"s".to_vec(), Value::String(RespFrame::Integer(7)));
+        db(0).keyspace.set_expire(b"s".to_vec(), later);
+        db(3)
+            .keyspace
+            .insert(b"l".to_vec(), Value::List(list.into()));
+        db(5).keyspace.insert(
+            b"set".to_vec(),
+            Value::Set(HashSet::from(["m".to_string()]).into()),
+        );
+        let data = commands(&rewrite(backend.copy_dataset()));
+        let expected_list = (0..64).map(|i| format!("${} {i}", i.to_string().len()));
+        let expected = format!(
+            "*2 $6 SELECT $1 0 *3 $3 SET $1 s $1 7 *3 $9 PEXPIREAT $1 s $13 {later} \
+             *2 $6 SELECT $1 3 *66 $5 RPUSH $1 l {} \
+             *8 $5 RPUSH $1 l $2 64 $2 65 $2 66 $2 67 $2 68 $2 69 \
+             *2 $6 SELECT $1 5 *3 $4 SADD $3 set $1 m ",
+            expected_list.collect::<Vec<_>>().join(" ")
+        );
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_append_and_rewrite() -> io::Result<()> {
+        let dir = std::env::temp_dir().join(format!("aof-test-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("appendonly.aof");
+        let policy = LogPolicy {
+            fsync: "always",
+            rewrite_percentage: 50,
+            rewrite_min_size: 0,
+        };
+        let log = AppendLog::new(0);
+        let set = vec![b"SET".to_vec(), b"k".to_vec(), b"v".to_vec()];
+
+        // nothing is written before a rewrite created the file
+        log.append(0, std::slice::from_ref(&set), policy);
+        assert!(log.rewrite_due());
+        assert!(log.start_rewrite());
+        assert!(!log.start_rewrite());
+        log.append(1, std::slice::from_ref(&set), policy);
+        log.finish_rewrite(&path, b"", true, 0, 0)?;
+        assert!(!log.rewrite_due());
+        let rewritten = fs::read(&path)?;
+        assert_eq!(
+            commands(&rewritten),
+            "*2 $6 SELECT $1 1 *3 $3 SET $1 k $1 v "
+        );
+        assert_eq!(log.size(), rewritten.len() as u64);
+
+        // the same database is still selected, the log grew by half
+        log.append(1, &[set], policy);
+        assert_eq!(
+            commands(&fs::read(&path)?),
+            "*2 $6 SELECT $1 1 *3 $3 SET $1 k $1 v *3 $3 SET $1 k $1 v "
+        );
+        assert!(log.rewrite_due());
+        fs::remove_dir_all(&dir)
+    }
+}
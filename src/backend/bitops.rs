@@ -0,0 +1,183 @@
+// The bit operations over string values. Bit 0 is the most significant bit of the first byte.
+
+// Whether the indexes of a range count bytes or bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitUnit {
+    #[default]
+    Byte,
+    Bit,
+}
+
+// The bitwise operations of BITOP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOp {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+// A range of BITCOUNT or BITPOS: inclusive indexes, negative ones counting from the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitRange {
+    pub start: i64,
+    // None up to the end of the string
+    pub end: Option<i64>,
+    pub unit: BitUnit,
+}
+
+impl BitRange {
+    // The first and last bit of the range in a string of `len` bytes, None if it is empty.
+    pub fn bits(&self, len: usize) -> Option<(usize, usize)> {
+        let scale = match self.unit {
+            BitUnit::Byte => 8,
+            BitUnit::Bit => 1,
+        };
+        let len = (len * 8 / scale) as i64;
+        let index = |i: i64| if i < 0 { (i + len).max(0) } else { i };
+        let (start, end) = (
+            index(self.start),
+            index(self.end.unwrap_or(-1)).min(len - 1),
+        );
+        if len == 0 || start > end {
+            return None;
+        }
+        let (start, end) = (start as usize * scale, end as usize * scale + scale - 1);
+        Some((start, end))
+    }
+}
+
+// Number of set bits of `bytes` between the bits `start` and `end` included.
+pub fn bitcount(bytes: &[u8], start: usize, end: usize) -> usize {
+    let (first, last) = (start / 8, end / 8);
+    // the bits of the range in its first and last byte
+    let head = 0xffu8 >> (start % 8);
+    let tail = 0xffu8 << (7 - end % 8);
+    if first == last {
+        return (bytes[first] & head & tail).count_ones() as usize;
+    }
+    let middle = &bytes[first + 1..last];
+    let mut chunks = middle.chunks_exact(8);
+    let mut count = (&mut chunks)
+        .map(|chunk| u64::from_ne_bytes(chunk.try_into().expect("8 bytes")).count_ones() as usize)
+        .sum::<usize>();
+    count += chunks
+        .remainder()
+        .iter()
+        .map(|byte| byte.count_ones() as usize)
+        .sum::<usize>();
+    count + (bytes[first] & head).count_ones() as usize + (bytes[last] & tail).count_ones() as usize
+}
+
+// Position of the first bit set to `bit` between the bits `start` and `end` included.
+pub fn bitpos(bytes: &[u8], bit: bool, start: usize, end: usize) -> Option<usize> {
+    // look for a set bit in the bytes, inverted when looking for a clear one
+    let byte = |i: usize| if bit { bytes[i] } else { !bytes[i] };
+    let (first, last) = (start / 8, end / 8);
+    let mut i = first;
+    while i <= last {
+        // skip 8 bytes at once while they hold nothing
+        let skip = if bit { 0 } else { u64::MAX };
+        if i > first && i + 8 <= last {
+            let chunk = u64::from_ne_bytes(bytes[i..i + 8].try_into().expect("8 bytes"));
+            if chunk == skip {
+                i += 8;
+                continue;
+            }
+        }
+        let mut found = byte(i);
+        if i == first {
+            found &= 0xff >> (start % 8);
+        }
+        if i == last {
+            found &= 0xff << (7 - end % 8);
+        }
+        if found != 0 {
+            return Some(i * 8 + found.leading_zeros() as usize);
+        }
+        i += 1;
+    }
+    None
+}
+
+// Combines the strings with `op`, the shorter ones padded with zero bytes up to the length of
+// the longest one. NOT only inverts the first string.
+pub fn bitop(op: BitOp, values: &[Vec<u8>]) -> Vec<u8> {
+    let len = values.iter().map(Vec::len).max().unwrap_or(0);
+    let byte = |value: &Vec<u8>, i: usize| value.get(i).copied().unwrap_or(0);
+    let Some((first, others)) = values.split_first() else {
+        return Vec::new();
+    };
+    let mut result = first.clone();
+    result.resize(len, 0);
+    if op == BitOp::Not {
+        result.iter_mut().for_each(|b| *b = !*b);
+        return result;
+    }
+    for value in others {
+        for (i, b) in result.iter_mut().enumerate() {
+            match op {
+                BitOp::And => *b &= byte(value, i),
+                BitOp::Or => *b |= byte(value, i),
+                BitOp::Xor => *b ^= byte(value, i),
+                BitOp::Not => unreachable!(),
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: i64, end: Option<i64>, unit: BitUnit) -> BitRange {
+        BitRange { start, end, unit }
+    }
+
+    #[test]
+    fn test_bit_range() {
+        assert_eq!(range(0, None, BitUnit::Byte).bits(3), Some((0, 23)));
+        assert_eq!(range(1, Some(-1), BitUnit::Byte).bits(3), Some((8, 23)));
+        assert_eq!(range(-2, Some(100), BitUnit::Byte).bits(3), Some((8, 23)));
+        assert_eq!(range(5, Some(-3), BitUnit::Bit).bits(1), Some((5, 5)));
+        assert_eq!(range(2, Some(1), BitUnit::Byte).bits(3), None);
+        assert_eq!(range(0, None, BitUnit::Byte).bits(0), None);
+    }
+
+    #[test]
+    fn test_bitcount() {
+        let bytes = b"foobar";
+        assert_eq!(bitcount(bytes, 0, 47), 26);
+        assert_eq!(bitcount(bytes, 8, 15), 6);
+        assert_eq!(bitcount(bytes, 5, 30), 17);
+        // over the chunks of 8 bytes
+        let bytes = [0xffu8; 21];
+        assert_eq!(bitcount(&bytes, 3, 165), 163);
+    }
+
+    #[test]
+    fn test_bitpos() {
+        let bytes = [0xff, 0xf0, 0x00];
+        assert_eq!(bitpos(&bytes, false, 0, 23), Some(12));
+        assert_eq!(bitpos(&bytes, true, 2, 23), Some(2));
+        assert_eq!(bitpos(&bytes, true, 12, 23), None);
+        assert_eq!(bitpos(&bytes, false, 0, 11), None);
+
+        let mut bytes = [0u8; 30];
+        bytes[25] = 0x10;
+        assert_eq!(bitpos(&bytes, true, 0, 239), Some(203));
+        assert_eq!(bitpos(&bytes, false, 3, 239), Some(3));
+    }
+
+    #[test]
+    fn test_bitop() {
+        let values = [vec![0xff, 0x0f], vec![0xf0], vec![0x01, 0x01, 0x01]];
+        assert_eq!(bitop(BitOp::And, &values), [0x00, 0x00, 0x00]);
+        assert_eq!(bitop(BitOp::And, &values[..2]), [0xf0, 0x00]);
+        assert_eq!(bitop(BitOp::Or, &values), [0xff, 0x0f, 0x01]);
+        assert_eq!(bitop(BitOp::Xor, &values), [0x0e, 0x0e, 0x01]);
+        assert_eq!(bitop(BitOp::Not, &values[1..2]), [0x0f]);
+        assert!(bitop(BitOp::Or, &[vec![], vec![]]).is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::Notify;
+
+// What a blocked client waits on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BlockKey {
+    // a key of the database at an index, for the pops and reads waiting for data
+    Key(usize, Vec<u8>),
+    // the replicas acknowledging the replication stream, for WAIT
+    ReplicaAcks,
+}
+
+// The clients blocked on each key, in the order they blocked. A signal on a key wakes the
+// first of them only, which passes it on to the next once it had its chance: the client that
+// has been waiting longest is served first.
+#[derive(Debug, Default)]
+pub struct Blocking {
+    state: Mutex<BlockingState>,
+}
+
+#[derive(Debug, Default)]
+struct BlockingState {
+    next_id: u64,
+    queues: HashMap<BlockKey, VecDeque<(u64, Arc<Notify>)>>,
+    // the keys each client was woken up for and didn't pass on yet
+    woken: HashMap<u64, Vec<BlockKey>>,
+}
+
+impl BlockingState {
+    // Wakes up the client at `index` in the queue of `key`, if there is one.
+    fn wake(&mut self, key: &BlockKey, index: usize) {
+        let Some((id, notify)) = self.queues.get(key).and_then(|queue| queue.get(index)) else {
+            return;
+        };
+        let woken = self.woken.entry(*id).or_default();
+        if !woken.contains(key) {
+            woken.push(key.clone());
+        }
+        notify.notify_one();
+    }
+
+    fn position(&self, key: &BlockKey, id: u64) -> Option<usize> {
+        self.queues
+            .get(key)?
+            .iter()
+            .position(|(other, _)| *other == id)
+    }
+}
+
+impl Blocking {
+    // Queues a client up on `keys` until the returned handle is dropped.
+    pub fn block(&self, keys: Vec<BlockKey>) -> Blocked<'_> {
+        let mut state = self.state();
+        let id = state.next_id;
+        state.next_id += 1;
+        let notify = Arc::new(Notify::new());
+        for key in &keys {
+            let queue = state.queues.entry(key.clone()).or_default();
+            queue.push_back((id, notify.clone()));
+        }
+        Blocked {
+            blocking: self,
+            id,
+            keys,
+            notify,
+        }
+    }
+
+    // Wakes up the client blocked the longest on `key`.
+    pub fn signal(&self, key: &BlockKey) {
+        self.state().wake(key, 0);
+    }
+
+    // Wakes up the clients blocked the longest on each key of the database at `db`, whose
+    // contents changed all at once.
+    pub fn signal_db(&self, db: usize) {
+        let mut state = self.state();
+        let keys = state
+            .queues
+            .keys()
+            .filter(|key| matches!(key, BlockKey::Key(index, _) if *index == db))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in keys {
+            state.wake(&key, 0);
+        }
+    }
+
+    // The number of clients blocked on something.
+    pub fn blocked_clients(&self) -> usize {
+        let state = self.state();
+        let mut ids = state
+            .queues
+            .values()
+            .flatten()
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        ids.len()
+    }
+
+    fn state(&self) -> MutexGuard<'_, BlockingState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// A client's place in the queues of the keys it blocks on. Dropping it leaves them, passing on
+// the signals it was woken up with.
+#[derive(Debug)]
+pub struct Blocked<'a> {
+    blocking: &'a Blocking,
+    id: u64,
+    keys: Vec<BlockKey>,
+    notify: Arc<Notify>,
+}
+
+impl Blocked<'_> {
+    // Waits for a signal on one of the keys, returning right away if one came in meanwhile.
+    pub async fn wait(&self) {
+        self.notify.notified().await;
+    }
+
+    // Wakes up the clients queued right after this one on the keys it was woken up for, once
+    // it had its chance at them and stays blocked.
+    pub fn pass(&self) {
+        let mut state = self.blocking.state();
+        for key in state.woken.remove(&self.id).unwrap_or_default() {
+            if let Some(index) = state.position(&key, self.id) {
+                state.wake(&key, index + 1);
+            }
+        }
+    }
+}
+
+impl Drop for Blocked<'_> {
+    fn drop(&mut self) {
+        let mut state = self.blocking.state();
+        let woken = state.woken.remove(&self.id).unwrap_or_default();
+        let mut next = vec![];
+        for key in &self.keys {
+            let Some(index) = state.position(key, self.id) else {
+                continue;
+            };
+            let queue = state
+                .queues
+                .get_mut(key)
+                .expect("the queue the client is in");
+            queue.remove(index);
+            if queue.is_empty() {
+                state.queues.remove(key);
+            } else if woken.contains(key) {
+                next.push((key, index));
+            }
+        }
+        // the client that took this one's place is the next in line
+        for (key, index) in next {
+            state.wake(key, index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    async fn woken(blocked: &Blocked<'_>) -> bool {
+        timeout(Duration::from_millis(20), blocked.wait())
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_blocking_fifo() {
+        let blocking = Blocking::default();
+        let key = BlockKey::Key(0, b"list".to_vec());
+        let first = blocking.block(vec![key.clone()]);
+        let second = blocking.block(vec![BlockKey::Key(0, b"other".to_vec()), key.clone()]);
+        let third = blocking.block(vec![key.clone()]);
+        assert_eq!(blocking.blocked_clients(), 3);
+
+        // the first one in line has its chance before the others
+        blocking.signal(&key);
+        assert!(woken(&first).await);
+        assert!(!woken(&second).await);
+        first.pass();
+        assert!(woken(&second).await);
+        assert!(!woken(&third).await);
+
+        // a client served leaves the queue, the next one takes its turn
+        drop(second);
+        assert!(woken(&third).await);
+        drop(third);
+        blocking.signal(&key);
+        assert!(woken(&first).await);
+        drop(first);
+        assert_eq!(blocking.blocked_clients(), 0);
+        assert!(blocking.state().woken.is_empty());
+
+        let blocked = blocking.block(vec![BlockKey::Key(1, b"a".to_vec())]);
+        blocking.signal_db(0);
+        assert!(!woken(&blocked).await);
+        blocking.signal_db(1);
+        assert!(woken(&blocked).await);
+    }
+}
@@ -0,0 +1,197 @@
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::watch;
+
+// What the server knows about a connected client, as reported by CLIENT LIST and CLIENT INFO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub id: u64,
+    // remote and local address of the connection
+    pub addr: String,
+    pub laddr: String,
+    pub name: String,
+    // the user the client is authenticated as, "default" unless its TLS certificate names one
+    pub user: String,
+    // milliseconds since the unix epoch
+    pub created_at: i64,
+    pub last_interaction: i64,
+    // lowercase name of the last command, "NULL" before the first one
+    pub last_command: String,
+    pub resp: u8,
+    pub db: usize,
+    // set once the client issued MONITOR
+    pub monitor: bool,
+    // set while the client waits in a blocking command
+    pub blocked: bool,
+    // set by ASKING, for the next request on a slot being imported
+    pub asking: bool,
+}
+
+impl ClientInfo {
+    // One line of CLIENT LIST, `now` in milliseconds since the unix epoch.
+    pub fn line(&self, now: i64) -> String {
+        format!(
+            "id={} addr={} laddr={} name={} age={} idle={} flags={} db={} cmd={} user={} resp={}\n",
+            self.id,
+            self.addr,
+            self.laddr,
+            self.name,
+            (now - self.created_at).max(0) / 1000,
+            (now - self.last_interaction).max(0) / 1000,
+            match (self.monitor, self.blocked) {
+                (true, _) => "O",
+                (false, true) => "b",
+                (false, false) => "N",
+            },
+            self.db,
+            self.last_command,
+            self.user,
+            self.resp,
+        )
+    }
+}
+
+// Which commands CLIENT PAUSE holds back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseMode {
+    Write,
+    All,
+}
+
+// A CLIENT PAUSE in effect until `until`, in milliseconds since the unix epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientPause {
+    pub mode: PauseMode,
+    pub until: i64,
+}
+
+// Every open connection, by client id. Ids start at 1 and are never reused.
+#[derive(Debug, Default)]
+pub struct ClientRegistry {
+    last_id: AtomicU64,
+    clients: DashMap<u64, ClientInfo>,
+    // flipped to true by CLIENT KILL, the connection task closes the connection when it sees it
+    kill_switches: DashMap<u64, watch::Sender<bool>>,
+}
+
+impl ClientRegistry {
+    // Adds a client connected at `now` and returns its id.
+    pub fn register(&self, addr: String, laddr: String, now: i64) -> u64 {
+        let id = self.last_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let info = ClientInfo {
+            id,
+            addr,
+            laddr,
+            name: String::new(),
+            user: "default".to_string(),
+            created_at: now,
+            last_interaction: now,
+            last_command: "NULL".to_string(),
+            resp: 2,
+            db: 0,
+            monitor: false,
+            blocked: false,
+            asking: false,
+        };
+        self.clients.insert(id, info);
+        self.kill_switches.insert(id, watch::channel(false).0);
+        id
+    }
+
+    pub fn unregister(&self, id: u64) {
+        self.clients.remove(&id);
+        self.kill_switches.remove(&id);
+    }
+
+    // Asks the connection of client `id` to close. Returns false if there is no such client.
+    pub fn kill(&self, id: u64) -> bool {
+        match self.kill_switches.get(&id) {
+            Some(switch) => {
+                switch.send_replace(true);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Changes to true once client `id` is killed, None if there is no such client.
+    pub fn kill_signal(&self, id: u64) -> Option<watch::Receiver<bool>> {
+        self.kill_switches.get(&id).map(|switch| switch.subscribe())
+    }
+
+    pub fn update(&self, id: u64, f: impl FnOnce(&mut ClientInfo)) {
+        if let Some(mut info) = self.clients.get_mut(&id) {
+            f(&mut info);
+        }
+    }
+
+    pub fn get(&self, id: u64) -> Option<ClientInfo> {
+        self.clients.get(&id).map(|info| info.value().clone())
+    }
+
+    // Every client, in connection order.
+    pub fn list(&self) -> Vec<ClientInfo> {
+        let mut clients = self
+            .clients
+            .iter()
+            .map(|e| e.value().clone())
+            .collect::<Vec<_>>();
+        clients.sort_by_key(|info| info.id);
+        clients
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_registry() {
+        let registry = ClientRegistry::default();
+        let first = registry.register(
+            "127.0.0.1:5000".to_string(),
+            "127.0.0.1:6379".to_string(),
+            0,
+        );
+        let second = registry.register(
+            "127.0.0.1:5001".to_string(),
+            "127.0.0.1:6379".to_string(),
+            0,
+        );
+        assert_eq!((first, second), (1, 2));
+
+        registry.update(second, |info| {
+            info.last_command = "get".to_string();
+            info.last_interaction = 2_000;
+            info.db = 3;
+        });
+        let info = registry.get(second).expect("registered");
+        assert_eq!(
+            info.line(5_000),
+            "id=2 addr=127.0.0.1:5001 laddr=127.0.0.1:6379 name= age=5 idle=3 flags=N db=3 cmd=get user=default resp=2\n"
+        );
+
+        let signal = registry.kill_signal(first).expect("registered");
+        assert!(registry.kill(first));
+        assert!(*signal.borrow());
+        assert!(!registry.kill(3));
+
+        registry.unregister(first);
+        assert!(registry.kill_signal(first).is_none());
+        let ids = registry
+            .list()
+            .iter()
+            .map(|info| info.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![2]);
+        assert_eq!(registry.len(), 1);
+    }
+}
@@ -0,0 +1,664 @@
+use super::replication::random_id;
+use std::collections::HashMap;
+use std::io;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use thiserror::Error;
+
+// the keyspace of a cluster is split in this many hash slots
+pub const CLUSTER_SLOTS: usize = 16384;
+
+// this node's index in ClusterState::nodes
+const MYSELF: usize = 0;
+
+// CRC16-CCITT (XMODEM) lookup table, by the top byte of the running checksum
+const CRC16_TABLE: [u16; 256] = crc16_table();
+
+const fn crc16_table() -> [u16; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0, |crc, byte| {
+        (crc << 8) ^ CRC16_TABLE[((crc >> 8) as u8 ^ byte) as usize]
+    })
+}
+
+// The hash slot of `key`. When the key has a hash tag, a non-empty part between the first `{`
+// and the first `}` after it, only the tag is hashed, so that `{user1}.name` and `{user1}.age`
+// are in the same slot.
+pub fn key_hash_slot(key: &[u8]) -> u16 {
+    let tag = key.iter().position(|b| *b == b'{').and_then(|open| {
+        let rest = &key[open + 1..];
+        let close = rest.iter().position(|b| *b == b'}')?;
+        Some(&rest[..close]).filter(|tag| !tag.is_empty())
+    });
+    crc16(tag.unwrap_or(key)) & (CLUSTER_SLOTS as u16 - 1)
+}
+
+// A node of the cluster, where clients are redirected to for the slots it serves.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClusterNode {
+    // 40 hexadecimal digits
+    pub id: String,
+    // empty for this node until another one tells it how it reaches it
+    pub host: String,
+    pub port: u16,
+    // the epoch of the node's claim on its slots, of two claims on a slot the latest wins
+    pub config_epoch: u64,
+    // set for a node met with CLUSTER MEET until it tells its id, `id` is made up until then
+    pub handshake: bool,
+    // whether the last exchange with the node succeeded
+    pub connected: bool,
+}
+
+impl ClusterNode {
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    // Its line in CLUSTER NODES, without the slots. There is no cluster bus, the nodes gossip
+    // over the port of their clients, its port is given as 0.
+    fn line(&self, myself: bool) -> String {
+        let flags = match (myself, self.handshake) {
+            (true, _) => "myself,master",
+            (false, true) => "handshake",
+            (false, false) => "master",
+        };
+        let link = match myself || self.connected {
+            true => "connected",
+            false => "disconnected",
+        };
+        format!(
+            "{} {}@0 {flags} - 0 0 {} {link}",
+            self.id,
+            self.addr(),
+            self.config_epoch
+        )
+    }
+}
+
+// A line of CLUSTER NODES, or of the cluster config file, read back.
+#[derive(Debug)]
+struct NodeLine {
+    node: ClusterNode,
+    myself: bool,
+    // ranges of slots, both ends included
+    slots: Vec<(u16, u16)>,
+    // (slot, node id) pairs, only listed by the node they are about
+    migrating: Vec<(u16, String)>,
+    importing: Vec<(u16, String)>,
+}
+
+impl NodeLine {
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        let id = fields.next()?.to_string();
+        let addr = fields.next()?;
+        let addr = addr.split_once('@').map_or(addr, |(addr, _)| addr);
+        let (host, port) = addr.rsplit_once(':')?;
+        let flags = fields.next()?.split(',').collect::<Vec<_>>();
+        // master, ping sent, pong received
+        let mut fields = fields.skip(3);
+        let config_epoch = fields.next()?.parse().ok()?;
+        let connected = fields.next()? == "connected";
+        let mut line = NodeLine {
+            node: ClusterNode {
+                id,
+                host: host.to_string(),
+                port: port.parse().ok()?,
+                config_epoch,
+                handshake: flags.contains(&"handshake"),
+                connected,
+            },
+            myself: flags.contains(&"myself"),
+            slots: vec![],
+            migrating: vec![],
+            importing: vec![],
+        };
+        for field in fields {
+            if let Some(migration) = field.strip_prefix('[').and_then(|f| f.strip_suffix(']')) {
+                if let Some((slot, id)) = migration.split_once("->-") {
+                    line.migrating.push((parse_slot(slot)?, id.to_string()));
+                } else if let Some((slot, id)) = migration.split_once("-<-") {
+                    line.importing.push((parse_slot(slot)?, id.to_string()));
+                }
+                continue;
+            }
+            let (start, end) = field.split_once('-').unwrap_or((field, field));
+            line.slots.push((parse_slot(start)?, parse_slot(end)?));
+        }
+        Some(line)
+    }
+}
+
+fn parse_slot(slot: &str) -> Option<u16> {
+    slot.parse()
+        .ok()
+        .filter(|slot| *slot < CLUSTER_SLOTS as u16)
+}
+
+fn invalid_line(line: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("bad cluster config line: {line}"),
+    )
+}
+
+// Why this node doesn't serve a request, the error the request is replied with.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ClusterRedirect {
+    #[error("CROSSSLOT Keys in request don't hash to the same slot")]
+    CrossSlot,
+    #[error("CLUSTERDOWN Hash slot not served")]
+    Unassigned,
+    // the slot is served by the node at this address
+    #[error("MOVED {0} {1}")]
+    Moved(u16, String),
+    // the slot is being migrated to the node at this address, which has the keys
+    #[error("ASK {0} {1}")]
+    Ask(u16, String),
+    // the keys are split between both nodes of a migration
+    #[error("TRYAGAIN Multiple keys request during rehashing of slot")]
+    TryAgain,
+}
+
+// The nodes of the cluster and which of them serves each hash slot.
+#[derive(Debug)]
+pub struct Cluster {
+    state: RwLock<ClusterState>,
+}
+
+#[derive(Debug)]
+struct ClusterState {
+    // this node first
+    nodes: Vec<ClusterNode>,
+    // the index in `nodes` of the node serving each slot
+    slots: Vec<Option<usize>>,
+    // slots this node hands over, with the index of the node each goes to
+    migrating: HashMap<u16, usize>,
+    // slots this node takes over, with the index of the node each comes from
+    importing: HashMap<u16, usize>,
+    // the greatest epoch known in the cluster
+    current_epoch: u64,
+}
+
+impl ClusterState {
+    fn node(&self, id: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.id == id)
+    }
+
+    // The ranges of slots node `node` serves, both ends included.
+    fn slot_ranges(&self, node: usize) -> Vec<(u16, u16)> {
+        let mut ranges: Vec<(u16, u16)> = vec![];
+        let slots = self.slots.iter().enumerate();
+        for (slot, _) in slots.filter(|(_, owner)| **owner == Some(node)) {
+            let slot = slot as u16;
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == slot => *end = slot,
+                _ => ranges.push((slot, slot)),
+            }
+        }
+        ranges
+    }
+
+    // Removes node `node`, which must serve no slot.
+    fn remove_node(&mut self, node: usize) {
+        self.nodes.remove(node);
+        let shift = |index: &mut usize| *index -= (*index > node) as usize;
+        self.slots.iter_mut().flatten().for_each(shift);
+        self.migrating.retain(|_, index| *index != node);
+        self.importing.retain(|_, index| *index != node);
+        self.migrating.values_mut().for_each(shift);
+        self.importing.values_mut().for_each(shift);
+    }
+
+    // Makes node `node` serve the slots it claims with `config_epoch`, unless a later claim on
+    // them is known. Returns whether any changed hands.
+    fn claim(&mut self, node: usize, slots: &[(u16, u16)], config_epoch: u64) -> bool {
+        let mut changed = false;
+        for slot in slots.iter().flat_map(|(start, end)| *start..=*end) {
+            let owner = &mut self.slots[slot as usize];
+            let stale = owner
+                .is_none_or(|owner| owner != node && self.nodes[owner].config_epoch < config_epoch);
+            if stale {
+                *owner = Some(node);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    // The CLUSTER NODES lines, this node first.
+    fn lines(&self) -> String {
+        let mut text = String::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            text.push_str(&node.line(index == MYSELF));
+            for (start, end) in self.slot_ranges(index) {
+                match start == end {
+                    true => text.push_str(&format!(" {start}")),
+                    false => text.push_str(&format!(" {start}-{end}")),
+                }
+            }
+            if index == MYSELF {
+                let mut migrations = self
+                    .migrating
+                    .iter()
+                    .map(|(slot, node)| (*slot, "->-", node))
+                    .chain(
+                        self.importing
+                            .iter()
+                            .map(|(slot, node)| (*slot, "-<-", node)),
+                    )
+                    .collect::<Vec<_>>();
+                migrations.sort();
+                for (slot, arrow, node) in migrations {
+                    text.push_str(&format!(" [{slot}{arrow}{}]", self.nodes[*node].id));
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+impl Cluster {
+    // A cluster of this node alone, serving no slot, its clients connect to `port`.
+    pub fn new(port: u16) -> Self {
+        let myself = ClusterNode {
+            id: random_id(),
+            port,
+            ..Default::default()
+        };
+        let state = ClusterState {
+            nodes: vec![myself],
+            slots: vec![None; CLUSTER_SLOTS],
+            migrating: HashMap::new(),
+            importing: HashMap::new(),
+            current_epoch: 0,
+        };
+        Self {
+            state: RwLock::new(state),
+        }
+    }
+
+    fn state(&self) -> RwLockReadGuard<'_, ClusterState> {
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn state_mut(&self) -> RwLockWriteGuard<'_, ClusterState> {
+        self.state.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn myself(&self) -> ClusterNode {
+        self.state().nodes[MYSELF].clone()
+    }
+
+    // Every known node, this one first.
+    pub fn nodes(&self) -> Vec<ClusterNode> {
+        self.state().nodes.clone()
+    }
+
+    // The ranges of slots node `id` serves, both ends included.
+    pub fn slot_ranges(&self, id: &str) -> Vec<(u16, u16)> {
+        let state = self.state();
+        state
+            .node(id)
+            .map_or(vec![], |node| state.slot_ranges(node))
+    }
+
+    pub fn current_epoch(&self) -> u64 {
+        self.state().current_epoch
+    }
+
+    // Gives this node's claim on its slots a new epoch, later than any other, and returns it.
+    pub fn bump_epoch(&self) -> u64 {
+        let mut state = self.state_mut();
+        state.current_epoch += 1;
+        state.nodes[MYSELF].config_epoch = state.current_epoch;
+        state.current_epoch
+    }
+
+    // Starts a handshake with the node at host:port, unless it is known already. Returns
+    // whether it wasn't.
+    pub fn meet(&self, host: &str, port: u16) -> bool {
+        let mut state = self.state_mut();
+        let known = state.nodes[1..]
+            .iter()
+            .any(|node| node.host == host && node.port == port);
+        if !known {
+            state.nodes.push(ClusterNode {
+                id: random_id(),
+                host: host.to_string(),
+                port,
+                handshake: true,
+                ..Default::default()
+            });
+        }
+        !known
+    }
+
+    pub fn set_connected(&self, id: &str, connected: bool) {
+        let mut state = self.state_mut();
+        if let Some(node) = state.node(id) {
+            state.nodes[node].connected = connected;
+        }
+    }
+
+    // Takes in what the node at host:port replied to CLUSTER NODES: its id, the nodes it knows
+    // and the slots they claim. Returns whether anything changed.
+    pub fn merge(&self, host: &str, port: u16, text: &str) -> io::Result<bool> {
+        let lines = text
+            .lines()
+            .map(|line| NodeLine::parse(line).ok_or_else(|| invalid_line(line)))
+            .collect::<io::Result<Vec<_>>>()?;
+        let sender = lines
+            .iter()
+            .find(|line| line.myself)
+            .ok_or_else(|| invalid_line(text))?;
+        let mut state = self.state_mut();
+        let before = state.lines();
+        // the node met with CLUSTER MEET now has its id, or turns out to be known already
+        let addressed = state.nodes[1..]
+            .iter()
+            .position(|node| node.handshake && node.host == host && node.port == port);
+        if let Some(index) = addressed.map(|index| index + 1) {
+            match state.node(&sender.node.id) {
+                Some(_) => state.remove_node(index),
+                None => state.nodes[index].id = sender.node.id.clone(),
+            }
+        }
+        for line in &lines {
+            let node = &line.node;
+            if line.node.id == state.nodes[MYSELF].id {
+                // how the others reach this node
+                if state.nodes[MYSELF].host.is_empty() && !node.host.is_empty() {
+                    state.nodes[MYSELF].host = node.host.clone();
+                }
+                continue;
+            }
+            if node.handshake || (node.host.is_empty() && !line.myself) {
+                continue;
+            }
+            let index = match state.node(&node.id) {
+                Some(index) => index,
+                None => {
+                    state.nodes.push(ClusterNode {
+                        connected: false,
+                        ..node.clone()
+                    });
+                    state.nodes.len() - 1
+                }
+            };
+            let known = &mut state.nodes[index];
+            known.config_epoch = known.config_epoch.max(node.config_epoch);
+            if line.myself {
+                // reached at the address it was asked at
+                known.host = host.to_string();
+                known.port = port;
+                known.handshake = false;
+                known.connected = true;
+            }
+            state.claim(index, &line.slots, node.config_epoch);
+            state.current_epoch = state.current_epoch.max(node.config_epoch);
+        }
+        Ok(state.lines() != before)
+    }
+
+    // What CLUSTER NODES replies, a line per node.
+    pub fn nodes_text(&self) -> String {
+        self.state().lines()
+    }
+
+    // The contents of the cluster config file: the lines of CLUSTER NODES, then the epoch.
+    pub fn config(&self) -> String {
+        let state = self.state();
+        let epoch = state.current_epoch;
+        format!(
+            "{}vars currentEpoch {epoch} lastVoteEpoch 0\n",
+            state.lines()
+        )
+    }
+
+    // Replaces the state with the one of a cluster config file, this node taking on the id
+    // it had.
+    pub fn load_config(&self, text: &str) -> io::Result<()> {
+        let mut lines = vec![];
+        let mut current_epoch = 0;
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            if let Some(vars) = line.strip_prefix("vars ") {
+                let vars = vars.split_whitespace().collect::<Vec<_>>();
+                for pair in vars.chunks(2) {
+                    if let ["currentEpoch", epoch] = pair {
+                        current_epoch = epoch.parse().map_err(|_| invalid_line(line))?;
+                    }
+                }
+                continue;
+            }
+            lines.push(NodeLine::parse(line).ok_or_else(|| invalid_line(line))?);
+        }
+        let myself = lines
+            .iter()
+            .position(|line| line.myself)
+            .ok_or_else(|| invalid_line("no line is about this node"))?;
+        let first = lines.remove(myself);
+        lines.insert(MYSELF, first);
+
+        let mut state = self.state_mut();
+        // this node keeps the port it listens on
+        let port = state.nodes[MYSELF].port;
+        state.nodes = lines.iter().map(|line| line.node.clone()).collect();
+        state.nodes[MYSELF].port = port;
+        state.slots = vec![None; CLUSTER_SLOTS];
+        for (index, line) in lines.iter().enumerate() {
+            for slot in line.slots.iter().flat_map(|(start, end)| *start..=*end) {
+                state.slots[slot as usize] = Some(index);
+            }
+        }
+        let migrations = |pairs: &[(u16, String)]| {
+            let pairs = pairs
+                .iter()
+                .filter_map(|(slot, id)| Some((*slot, state.node(id)?)));
+            pairs.collect::<HashMap<_, _>>()
+        };
+        let (migrating, importing) = (
+            migrations(&lines[MYSELF].migrating),
+            migrations(&lines[MYSELF].importing),
+        );
+        state.migrating = migrating;
+        state.importing = importing;
+        state.current_epoch = current_epoch;
+        Ok(())
+    }
+
+    // Adds a node, or updates the address of the node with the same id.
+    pub fn add_node(&self, node: ClusterNode) {
+        let mut state = self.state_mut();
+        match state.node(&node.id) {
+            Some(index) => state.nodes[index] = node,
+            None => state.nodes.push(node),
+        }
+    }
+
+    // Makes node `id` serve `slots`. Returns false, changing nothing, if there is no such node.
+    pub fn assign_slots(&self, slots: &[u16], id: &str) -> bool {
+        let mut state = self.state_mut();
+        let Some(node) = state.node(id) else {
+            return false;
+        };
+        for slot in slots {
+            state.slots[*slot as usize] = Some(node);
+            state.migrating.remove(slot);
+            state.importing.remove(slot);
+        }
+        true
+    }
+
+    // The node serving `slot`, if any.
+    pub fn slot_owner(&self, slot: u16) -> Option<ClusterNode> {
+        let state = self.state();
+        state.slots[slot as usize].map(|node| state.nodes[node].clone())
+    }
+
+    // Starts handing `slot` over to node `id`, false if there is no such node.
+    pub fn set_migrating(&self, slot: u16, id: &str) -> bool {
+        let mut state = self.state_mut();
+        let Some(node) = state.node(id) else {
+            return false;
+        };
+        state.migrating.insert(slot, node);
+        true
+    }
+
+    // Starts taking `slot` over from node `id`, false if there is no such node.
+    pub fn set_importing(&self, slot: u16, id: &str) -> bool {
+        let mut state = self.state_mut();
+        let Some(node) = state.node(id) else {
+            return false;
+        };
+        state.importing.insert(slot, node);
+        true
+    }
+
+    // Ends the migration of `slot`, whichever way it went.
+    pub fn set_stable(&self, slot: u16) {
+        let mut state = self.state_mut();
+        state.migrating.remove(&slot);
+        state.importing.remove(&slot);
+    }
+
+    // Checks that this node serves a request on `keys`, which must all be in the same slot.
+    // While a slot migrates, the keys still here are served and the others asked for on the
+    // node taking the slot over, which serves them only to clients that sent ASKING.
+    pub fn route(
+        &self,
+        keys: &[Vec<u8>],
+        asking: bool,
+        exists: impl Fn(&[u8]) -> bool,
+    ) -> Result<(), ClusterRedirect> {
+        let mut slots = keys.iter().map(|key| key_hash_slot(key));
+        let Some(slot) = slots.next() else {
+            return Ok(());
+        };
+        if slots.any(|other| other != slot) {
+            return Err(ClusterRedirect::CrossSlot);
+        }
+        let state = self.state();
+        let owner = state.slots[slot as usize].ok_or(ClusterRedirect::Unassigned)?;
+        let missing = || keys.iter().filter(|key| !exists(key)).count();
+        if owner == MYSELF {
+            if let Some(target) = state.migrating.get(&slot) {
+                match missing() {
+                    0 => {}
+                    missing if missing == keys.len() => {
+                        return Err(ClusterRedirect::Ask(slot, state.nodes[*target].addr()))
+                    }
+                    _ => return Err(ClusterRedirect::TryAgain),
+                }
+            }
+            return Ok(());
+        }
+        if asking && state.importing.contains_key(&slot) {
+            if keys.len() > 1 && missing() > 0 {
+                return Err(ClusterRedirect::TryAgain);
+            }
+            return Ok(());
+        }
+        Err(ClusterRedirect::Moved(slot, state.nodes[owner].addr()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keys: &[&str]) -> Vec<Vec<u8>> {
+        keys.iter().map(|key| key.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_key_hash_slot() {
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        assert_eq!(key_hash_slot(b"foo"), 12182);
+        assert_eq!(key_hash_slot(b"bar"), 5061);
+        assert_eq!(key_hash_slot(b"{foo}.bar"), 12182);
+        assert_eq!(key_hash_slot(b"x{foo}{bar}"), 12182);
+        // empty and unclosed tags don't count
+        assert_ne!(key_hash_slot(b"{}foo"), key_hash_slot(b"{}bar"));
+        assert_ne!(key_hash_slot(b"{foo"), 12182);
+    }
+
+    #[test]
+    fn test_cluster_route() {
+        let cluster = Cluster::new(7000);
+        let myself = cluster.myself().id;
+        let other = ClusterNode {
+            id: "b".repeat(40),
+            host: "127.0.0.1".to_string(),
+            port: 7001,
+            ..Default::default()
+        };
+        cluster.add_node(other.clone());
+        let none = |_: &[u8]| false;
+        assert_eq!(cluster.route(&[], false, none), Ok(()));
+        assert_eq!(
+            cluster.route(&keys(&["foo"]), false, none),
+            Err(ClusterRedirect::Unassigned)
+        );
+
+        assert!(cluster.assign_slots(&[12182], &myself));
+        assert!(cluster.assign_slots(&[5061], &other.id));
+        assert!(!cluster.assign_slots(&[0], "unknown"));
+        assert_eq!(cluster.slot_owner(5061), Some(other.clone()));
+        assert_eq!(
+            cluster.route(&keys(&["foo", "{foo}2"]), false, none),
+            Ok(())
+        );
+        assert_eq!(
+            cluster.route(&keys(&["foo", "bar"]), false, none),
+            Err(ClusterRedirect::CrossSlot)
+        );
+        let moved = ClusterRedirect::Moved(5061, "127.0.0.1:7001".to_string());
+        assert_eq!(moved.to_string(), "MOVED 5061 127.0.0.1:7001");
+        assert_eq!(cluster.route(&keys(&["bar"]), false, none), Err(moved));
+
+        // the keys of a migrating slot that are gone were moved already
+        assert!(cluster.set_migrating(12182, &other.id));
+        let only_foo = |key: &[u8]| key == b"foo";
+        assert_eq!(cluster.route(&keys(&["foo"]), false, only_foo), Ok(()));
+        assert_eq!(
+            cluster.route(&keys(&["{foo}2"]), false, only_foo),
+            Err(ClusterRedirect::Ask(12182, "127.0.0.1:7001".to_string()))
+        );
+        assert_eq!(
+            cluster.route(&keys(&["foo", "{foo}2"]), false, only_foo),
+            Err(ClusterRedirect::TryAgain)
+        );
+
+        // a slot being imported is only served after ASKING
+        assert!(cluster.set_importing(5061, &other.id));
+        assert!(cluster.route(&keys(&["bar"]), false, none).is_err());
+        assert_eq!(cluster.route(&keys(&["bar"]), true, none), Ok(()));
+        assert_eq!(
+            cluster.route(&keys(&["bar", "{bar}2"]), true, |key| key == b"bar"),
+            Err(ClusterRedirect::TryAgain)
+        );
+        cluster.set_stable(5061);
+        assert!(cluster.route(&keys(&["bar"]), true, none).is_err());
+    }
+}
@@ -0,0 +1,677 @@
+use super::{glob_match, NotifyFlags};
+use std::sync::{RwLock, RwLockReadGuard};
+use thiserror::Error;
+
+pub const MAXMEMORY_POLICIES: [&str; 8] = [
+    "volatile-lru",
+    "allkeys-lru",
+    "volatile-lfu",
+    "allkeys-lfu",
+    "volatile-random",
+    "allkeys-random",
+    "volatile-ttl",
+    "noeviction",
+];
+const APPENDFSYNC_MODES: [&str; 3] = ["always", "everysec", "no"];
+const TLS_AUTH_CLIENTS_MODES: [&str; 3] = ["yes", "optional", "no"];
+const TLS_AUTH_CLIENTS_USER_MODES: [&str; 3] = ["off", "cn", "san"];
+
+// The typed values of every configuration parameter. Read them through ServerConfig::read so
+// changes made by CONFIG SET take effect immediately.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigValues {
+    pub bind: String,
+    pub port: u16,
+    // the port of the TLS listener, served besides the plaintext one, 0 to disable it
+    pub tls_port: u16,
+    // PEM files of the certificate chain and private key the TLS listener presents
+    pub tls_cert_file: String,
+    pub tls_key_file: String,
+    // PEM file of the certificates of the CAs client certificates are checked against
+    pub tls_ca_cert_file: String,
+    // whether TLS clients have to present a certificate: "yes", "optional" to check it only when
+    // they do, or "no" to never ask for one
+    pub tls_auth_clients: String,
+    // the name of a client certificate the client is authenticated as: "off", "cn" for its
+    // common name or "san" for its first DNS subject alternative name
+    pub tls_auth_clients_user: String,
+    // the users a client certificate may name, a certificate naming another one is rejected
+    pub tls_auth_clients_users: Vec<String>,
+    // milliseconds a TLS client has to complete its handshake, 0 to wait as long as it takes
+    pub tls_handshake_timeout: u64,
+    pub databases: usize,
+    // in bytes, 0 means no limit
+    pub maxmemory: u64,
+    pub maxmemory_policy: String,
+    // keys sampled per database to pick the one to evict
+    pub maxmemory_samples: usize,
+    // how slowly the access frequency counters of the LFU policies grow, see KeyAccess
+    pub lfu_log_factor: u64,
+    // minutes without access for an access frequency counter to decrease by one, 0 to never
+    pub lfu_decay_time: u64,
+    pub maxclients: u64,
+    // close connections idle for this many seconds, 0 to never close them
+    pub timeout: u64,
+    // seconds the requests in flight, blocked ones included, have to complete once the server
+    // shuts down, 0 to close every connection right away
+    pub shutdown_timeout: u64,
+    pub appendonly: bool,
+    pub appendfsync: String,
+    pub appendfilename: String,
+    // rewrite the append only file once it grew by this percentage of its size after the last
+    // rewrite, 0 to never rewrite it automatically
+    pub auto_aof_rewrite_percentage: u64,
+    // in bytes, the append only file is never rewritten automatically while smaller
+    pub auto_aof_rewrite_min_size: u64,
+    // in bytes, how much of the replication stream is kept for replicas
+    pub repl_backlog_size: u64,
+    // whether a replica rejects writes from its clients
+    pub replica_read_only: bool,
+    // snapshot after `seconds` if at least `changes` writes happened
+    pub save: Vec<(u64, u64)>,
+    pub dir: String,
+    pub dbfilename: String,
+    pub hz: u64,
+    // whether DEL frees values in the background like UNLINK
+    pub lazyfree_lazy_user_del: bool,
+    // whether FLUSHDB and FLUSHALL without SYNC or ASYNC free values in the background
+    pub lazyfree_lazy_user_flush: bool,
+    // whether the values of evicted keys are freed in the background
+    pub lazyfree_lazy_eviction: bool,
+    // whether the values of expired keys are freed in the background
+    pub lazyfree_lazy_expire: bool,
+    // whether values a command overwrites or deletes implicitly, like SET, RESTORE REPLACE or
+    // COPY REPLACE, are freed in the background
+    pub lazyfree_lazy_server_del: bool,
+    // in milliseconds, events taking at least this long are recorded by LATENCY, 0 to disable
+    pub latency_monitor_threshold: u64,
+    // which keyspace events are published, see NotifyFlags
+    pub notify_keyspace_events: NotifyFlags,
+    // in milliseconds, how long a script runs before other clients get BUSY errors and SCRIPT
+    // KILL may stop it
+    pub busy_reply_threshold: u64,
+    // entries per node of a stream, which approximate trimming removes whole
+    pub stream_node_max_entries: usize,
+    // hashes with more fields, or a longer field or value (in bytes), are no longer listpacks
+    pub hash_max_listpack_entries: usize,
+    pub hash_max_listpack_value: usize,
+    // lists longer than this many elements when positive, or larger than 4 KiB << (-n - 1)
+    // for -1 to -5, are no longer listpacks
+    pub list_max_listpack_size: i64,
+    // sets of integers with more members are no longer intsets
+    pub set_max_intset_entries: usize,
+    // whether the server is a node of a cluster, serving only the keys of its hash slots
+    pub cluster_enabled: bool,
+    // where a cluster node keeps its id, the nodes it knows and their slots, under `dir`
+    pub cluster_config_file: String,
+}
+
+impl Default for ConfigValues {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0".to_string(),
+            port: 6379,
+            tls_port: 0,
+            tls_cert_file: String::new(),
+            tls_key_file: String::new(),
+            tls_ca_cert_file: String::new(),
+            tls_auth_clients: "optional".to_string(),
+            tls_auth_clients_user: "off".to_string(),
+            tls_auth_clients_users: vec![],
+            tls_handshake_timeout: 10000,
+            databases: 16,
+            maxmemory: 0,
+            maxmemory_policy: "noeviction".to_string(),
+            maxmemory_samples: 5,
+            lfu_log_factor: 10,
+            lfu_decay_time: 1,
+            maxclients: 10000,
+            timeout: 0,
+            shutdown_timeout: 10,
+            appendonly: false,
+            appendfsync: "everysec".to_string(),
+            appendfilename: "appendonly.aof".to_string(),
+            auto_aof_rewrite_percentage: 100,
+            auto_aof_rewrite_min_size: 64 * 1024 * 1024,
+            repl_backlog_size: 1024 * 1024,
+            replica_read_only: true,
+            save: vec![(3600, 1), (300, 100), (60, 10000)],
+            dir: ".".to_string(),
+            dbfilename: "dump.rdb".to_string(),
+            hz: 10,
+            lazyfree_lazy_user_del: false,
+            lazyfree_lazy_user_flush: false,
+            lazyfree_lazy_eviction: false,
+            lazyfree_lazy_expire: false,
+            lazyfree_lazy_server_del: false,
+            latency_monitor_threshold: 0,
+            notify_keyspace_events: NotifyFlags::default(),
+            busy_reply_threshold: 5000,
+            stream_node_max_entries: 100,
+            hash_max_listpack_entries: 128,
+            hash_max_listpack_value: 64,
+            list_max_listpack_size: -2,
+            set_max_intset_entries: 512,
+            cluster_enabled: false,
+            cluster_config_file: "nodes.conf".to_string(),
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("Unknown option or number of arguments for CONFIG SET - '{0}'")]
+    UnknownParameter(String),
+    #[error("CONFIG SET failed (possibly related to argument '{0}') - can't set immutable config")]
+    Immutable(String),
+    #[error("CONFIG SET failed (possibly related to argument '{0}') - {1}")]
+    InvalidValue(String, String),
+}
+
+struct Param {
+    name: &'static str,
+    mutable: bool,
+    get: fn(&ConfigValues) -> String,
+    set: fn(&mut ConfigValues, &str) -> Result<(), String>,
+}
+
+const PARAMS: &[Param] = &[
+    Param {
+        name: "bind",
+        mutable: false,
+        get: |c| c.bind.clone(),
+        set: |c, v| {
+            c.bind = v.to_string();
+            Ok(())
+        },
+    },
+    Param {
+        name: "port",
+        mutable: false,
+        get: |c| c.port.to_string(),
+        set: |c, v| parse_number(v).map(|n| c.port = n),
+    },
+    Param {
+        name: "tls-port",
+        mutable: false,
+        get: |c| c.tls_port.to_string(),
+        set: |c, v| parse_number(v).map(|n| c.tls_port = n),
+    },
+    Param {
+        name: "tls-cert-file",
+        mutable: false,
+        get: |c| c.tls_cert_file.clone(),
+        set: |c, v| {
+            c.tls_cert_file = v.to_string();
+            Ok(())
+        },
+    },
+    Param {
+        name: "tls-key-file",
+        mutable: false,
+        get: |c| c.tls_key_file.clone(),
+        set: |c, v| {
+            c.tls_key_file = v.to_string();
+            Ok(())
+        },
+    },
+    Param {
+        name: "tls-ca-cert-file",
+        mutable: false,
+        get: |c| c.tls_ca_cert_file.clone(),
+        set: |c, v| {
+            c.tls_ca_cert_file = v.to_string();
+            Ok(())
+        },
+    },
+    Param {
+        name: "tls-auth-clients",
+        mutable: false,
+        get: |c| c.tls_auth_clients.clone(),
+        set: |c, v| parse_enum(v, &TLS_AUTH_CLIENTS_MODES).map(|m| c.tls_auth_clients = m),
+    },
+    Param {
+        name: "tls-auth-clients-user",
+        mutable: false,
+        get: |c| c.tls_auth_clients_user.clone(),
+        set: |c, v| {
+            parse_enum(v, &TLS_AUTH_CLIENTS_USER_MODES).map(|m| c.tls_auth_clients_user = m)
+        },
+    },
+    Param {
+        name: "tls-auth-clients-users",
+        mutable: true,
+        get: |c| c.tls_auth_clients_users.join(" "),
+        set: |c, v| {
+            c.tls_auth_clients_users = v.split_whitespace().map(str::to_string).collect();
+            Ok(())
+        },
+    },
+    Param {
+        name: "tls-handshake-timeout",
+        mutable: true,
+        get: |c| c.tls_handshake_timeout.to_string(),
+        set: |c, v| parse_number(v).map(|n| c.tls_handshake_timeout = n),
+    },
+    Param {
+        name: "databases",
+        mutable: false,
+        get: |c| c.databases.to_string(),
+        set: |c, v| parse_number(v).map(|n| c.databases = n),
+    },
+    Param {
+        name: "maxmemory",
+        mutable: true,
+        get: |c| c.maxmemory.to_string(),
+        set: |c, v| parse_memory(v).map(|n| c.maxmemory = n),
+    },
+    Param {
+        name: "maxmemory-policy",
+        mutable: true,
+        get: |c| c.maxmemory_policy.clone(),
+        set: |c, v| parse_enum(v, &MAXMEMORY_POLICIES).map(|p| c.maxmemory_policy = p),
+    },
+    Param {
+        name: "maxmemory-samples",
+        mutable: true,
+        get: |c| c.maxmemory_samples.to_string(),
+        set: |c, v| match parse_number(v)? {
+            samples @ 1..=64 => {
+                c.maxmemory_samples = samples;
+                Ok(())
+            }
+            _ => Err("argument must be between 1 and 64 inclusive".to_string()),
+        },
+    },
+    Param {
+        name: "lfu-log-factor",
+        mutable: true,
+        get: |c| c.lfu_log_factor.to_string(),
+        set: |c, v| parse_number(v).map(|n| c.lfu_log_factor = n),
+    },
+    Param {
+        name: "lfu-decay-time",
+        mutable: true,
+        get: |c| c.lfu_decay_time.to_string(),
+        set: |c, v| parse_number(v).map(|n| c.lfu_decay_time = n),
+    },
+    Param {
+        name: "maxclients",
+        mutable: true,
+        get: |c| c.maxclients.to_string(),
+        set: |c, v| parse_number(v).map(|n| c.maxclients = n),
+    },
+    Param {
+        name: "timeout",
+        mutable: true,
+        get: |c| c.timeout.to_string(),
+        set: |c, v| parse_number(v).map(|n| c.timeout = n),
+    },
+    Param {
+        name: "shutdown-timeout",
+        mutable: true,
+        get: |c| c.shutdown_timeout.to_string(),
+        set: |c, v| parse_number(v).map(|n| c.shutdown_timeout = n),
+    },
+    Param {
+        name: "appendonly",
+        mutable: true,
+        get: |c| yes_no(c.appendonly),
+        set: |c, v| parse_bool(v).map(|b| c.appendonly = b),
+    },
+    Param {
+        name: "appendfsync",
+        mutable: true,
+        get: |c| c.appendfsync.clone(),
+        set: |c, v| parse_enum(v, &APPENDFSYNC_MODES).map(|m| c.appendfsync = m),
+    },
+    Param {
+        name: "appendfilename",
+        mutable: false,
+        get: |c| c.appendfilename.clone(),
+        set: |c, v| {
+            c.appendfilename = v.to_string();
+            Ok(())
+        },
+    },
+    Param {
+        name: "auto-aof-rewrite-percentage",
+        mutable: true,
+        get: |c| c.auto_aof_rewrite_percentage.to_string(),
+        set: |c, v| parse_number(v).map(|n| c.auto_aof_rewrite_percentage = n),
+    },
+    Param {
+        name: "auto-aof-rewrite-min-size",
+        mutable: true,
+        get: |c| c.auto_aof_rewrite_min_size.to_string(),
+        set: |c, v| parse_memory(v).map(|n| c.auto_aof_rewrite_min_size = n),
+    },
+    Param {
+        name: "repl-backlog-size",
+        mutable: true,
+        get: |c| c.repl_backlog_size.to_string(),
+        set: |c, v| parse_memory(v).map(|n| c.repl_backlog_size = n),
+    },
+    Param {
+        name: "replica-read-only",
+        mutable: true,
+        get: |c| yes_no(c.replica_read_only),
+        set: |c, v| parse_bool(v).map(|b| c.replica_read_only = b),
+    },
+    Param {
+        name: "save",
+        mutable: true,
+        get: |c| {
+            let rules = c
+                .save
+                .iter()
+                .map(|(secs, changes)| format!("{secs} {changes}"));
+            rules.collect::<Vec<_>>().join(" ")
+        },
+        set: |c, v| parse_save(v).map(|rules| c.save = rules),
+    },
+    Param {
+        name: "dir",
+        mutable: true,
+        get: |c| c.dir.clone(),
+        set: |c, v| {
+            c.dir = v.to_string();
+            Ok(())
+        },
+    },
+    Param {
+        name: "dbfilename",
+        mutable: true,
+        get: |c| c.dbfilename.clone(),
+        set: |c, v| {
+            c.dbfilename = v.to_string();
+            Ok(())
+        },
+    },
+    Param {
+        name: "hz",
+        mutable: true,
+        get: |c| c.hz.to_string(),
+        set: |c, v| match parse_number(v)? {
+            hz @ 1..=500 => {
+                c.hz = hz;
+                Ok(())
+            }
+            _ => Err("argument must be between 1 and 500 inclusive".to_string()),
+        },
+    },
+    Param {
+        name: "lazyfree-lazy-user-del",
+        mutable: true,
+        get: |c| yes_no(c.lazyfree_lazy_user_del),
+        set: |c, v| parse_bool(v).map(|b| c.lazyfree_lazy_user_del = b),
+    },
+    Param {
+        name: "lazyfree-lazy-user-flush",
+        mutable: true,
+        get: |c| yes_no(c.lazyfree_lazy_user_flush),
+        set: |c, v| parse_bool(v).map(|b| c.lazyfree_lazy_user_flush = b),
+    },
+    Param {
+        name: "lazyfree-lazy-eviction",
+        mutable: true,
+        get: |c| yes_no(c.lazyfree_lazy_eviction),
+        set: |c, v| parse_bool(v).map(|b| c.lazyfree_lazy_eviction = b),
+    },
+    Param {
+        name: "lazyfree-lazy-expire",
+        mutable: true,
+        get: |c| yes_no(c.lazyfree_lazy_expire),
+        set: |c, v| parse_bool(v).map(|b| c.lazyfree_lazy_expire = b),
+    },
+    Param {
+        name: "lazyfree-lazy-server-del",
+        mutable: true,
+        get: |c| yes_no(c.lazyfree_lazy_server_del),
+        set: |c, v| parse_bool(v).map(|b| c.lazyfree_lazy_server_del = b),
+    },
+    Param {
+        name: "latency-monitor-threshold",
+        mutable: true,
+        get: |c| c.latency_monitor_threshold.to_string(),
+        set: |c, v| parse_number(v).map(|ms| c.latency_monitor_threshold = ms),
+    },
+    Param {
+        name: "notify-keyspace-events",
+        mutable: true,
+        get: |c| c.notify_keyspace_events.to_string(),
+        set: |c, v| v.parse().map(|flags| c.notify_keyspace_events = flags),
+    },
+    Param {
+        name: "busy-reply-threshold",
+        mutable: true,
+        get: |c| c.busy_reply_threshold.to_string(),
+        set: |c, v| parse_number(v).map(|ms| c.busy_reply_threshold = ms),
+    },
+    Param {
+        name: "stream-node-max-entries",
+        mutable: true,
+        get: |c| c.stream_node_max_entries.to_string(),
+        set: |c, v| parse_number(v).map(|n| c.stream_node_max_entries = n),
+    },
+    Param {
+        name: "hash-max-listpack-entries",
+        mutable: true,
+        get: |c| c.hash_max_listpack_entries.to_string(),
+        set: |c, v| parse_number(v).map(|n| c.hash_max_listpack_entries = n),
+    },
+    Param {
+        name: "hash-max-listpack-value",
+        mutable: true,
+        get: |c| c.hash_max_listpack_value.to_string(),
+        set: |c, v| parse_number(v).map(|n| c.hash_max_listpack_value = n),
+    },
+    Param {
+        name: "list-max-listpack-size",
+        mutable: true,
+        get: |c| c.list_max_listpack_size.to_string(),
+        set: |c, v| match parse_number(v)? {
+            size @ (-5..=-1 | 1..) => {
+                c.list_max_listpack_size = size;
+                Ok(())
+            }
+            _ => Err("argument must be positive or between -5 and -1".to_string()),
+        },
+    },
+    Param {
+        name: "set-max-intset-entries",
+        mutable: true,
+        get: |c| c.set_max_intset_entries.to_string(),
+        set: |c, v| parse_number(v).map(|n| c.set_max_intset_entries = n),
+    },
+    Param {
+        name: "cluster-enabled",
+        mutable: false,
+        get: |c| yes_no(c.cluster_enabled),
+        set: |c, v| parse_bool(v).map(|b| c.cluster_enabled = b),
+    },
+    Param {
+        name: "cluster-config-file",
+        mutable: false,
+        get: |c| c.cluster_config_file.clone(),
+        set: |c, v| {
+            c.cluster_config_file = v.to_string();
+            Ok(())
+        },
+    },
+];
+
+// The runtime configuration store, shared by every module of the server.
+#[derive(Debug, Default)]
+pub struct ServerConfig {
+    values: RwLock<ConfigValues>,
+}
+
+impl ServerConfig {
+    pub fn new(values: ConfigValues) -> Self {
+        Self {
+            values: RwLock::new(values),
+        }
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, ConfigValues> {
+        self.values.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // (name, value) of every parameter matching one of the glob patterns, case-insensitively.
+    pub fn get(&self, patterns: &[String]) -> Vec<(&'static str, String)> {
+        let values = self.read();
+        PARAMS
+            .iter()
+            .filter(|p| {
+                patterns
+                    .iter()
+                    .any(|pattern| glob_match(pattern.to_ascii_lowercase(), p.name))
+            })
+            .map(|p| (p.name, (p.get)(&values)))
+            .collect()
+    }
+
+    // Sets all the parameters, or none of them if any name or value is invalid.
+    pub fn set(&self, pairs: &[(String, String)]) -> Result<(), ConfigError> {
+        let mut values = self.read().clone();
+        for (name, value) in pairs {
+            let param = PARAMS
+                .iter()
+                .find(|p| p.name.eq_ignore_ascii_case(name))
+                .ok_or_else(|| ConfigError::UnknownParameter(name.clone()))?;
+            if !param.mutable {
+                return Err(ConfigError::Immutable(param.name.to_string()));
+            }
+            (param.set)(&mut values, value)
+                .map_err(|reason| ConfigError::InvalidValue(param.name.to_string(), reason))?;
+        }
+        *self.values.write().unwrap_or_else(|e| e.into_inner()) = values;
+        Ok(())
+    }
+}
+
+fn yes_no(b: bool) -> String {
+    if b { "yes" } else { "no" }.to_string()
+}
+
+fn parse_bool(v: &str) -> Result<bool, String> {
+    match v.to_ascii_lowercase().as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err("argument must be 'yes' or 'no'".to_string()),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(v: &str) -> Result<T, String> {
+    v.parse()
+        .map_err(|_| "argument couldn't be parsed into an integer".to_string())
+}
+
+// a number of bytes with an optional unit: 1k, 1kb, 1m, 1mb, 1g, 1gb (k is 1000, kb is 1024)
+fn parse_memory(v: &str) -> Result<u64, String> {
+    let v = v.to_ascii_lowercase();
+    let split = v.find(|c: char| !c.is_ascii_digit()).unwrap_or(v.len());
+    let (number, unit) = v.split_at(split);
+    let multiplier = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return Err("argument must be a memory value".to_string()),
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| "argument must be a memory value".to_string())?;
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| "argument must be a memory value".to_string())
+}
+
+fn parse_enum(v: &str, allowed: &[&str]) -> Result<String, String> {
+    let v = v.to_ascii_lowercase();
+    if allowed.contains(&v.as_str()) {
+        Ok(v)
+    } else {
+        Err("argument(s) must be one of the following: ".to_string() + &allowed.join(", "))
+    }
+}
+
+// "<seconds> <changes> [<seconds> <changes> ...]", or an empty string to disable snapshots
+fn parse_save(v: &str) -> Result<Vec<(u64, u64)>, String> {
+    let numbers = v
+        .split_whitespace()
+        .map(parse_number::<u64>)
+        .collect::<Result<Vec<_>, _>>()?;
+    if numbers.len() % 2 != 0 {
+        return Err("Invalid save parameters".to_string());
+    }
+    Ok(numbers.chunks(2).map(|pair| (pair[0], pair[1])).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_get() {
+        let config = ServerConfig::default();
+        assert_eq!(
+            config.get(&["maxmemory*".to_string()]),
+            vec![
+                ("maxmemory", "0".to_string()),
+                ("maxmemory-policy", "noeviction".to_string()),
+                ("maxmemory-samples", "5".to_string()),
+            ]
+        );
+        assert_eq!(
+            config.get(&["SAVE".to_string()]),
+            vec![("save", "3600 1 300 100 60 10000".to_string())]
+        );
+        assert!(config.get(&["nosuchparam".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn test_config_set() {
+        let config = ServerConfig::default();
+        let pairs = [
+            ("maxmemory".to_string(), "100mb".to_string()),
+            ("appendonly".to_string(), "yes".to_string()),
+            ("save".to_string(), "".to_string()),
+        ];
+        assert_eq!(config.set(&pairs), Ok(()));
+        assert_eq!(config.read().maxmemory, 100 * 1024 * 1024);
+        assert!(config.read().appendonly);
+        assert!(config.read().save.is_empty());
+
+        // nothing is applied when one of the parameters is invalid
+        let pairs = [
+            ("timeout".to_string(), "30".to_string()),
+            ("maxmemory-policy".to_string(), "sometimes".to_string()),
+        ];
+        assert!(matches!(
+            config.set(&pairs),
+            Err(ConfigError::InvalidValue(..))
+        ));
+        assert_eq!(config.read().timeout, 0);
+
+        let pairs = [("port".to_string(), "6380".to_string())];
+        assert_eq!(
+            config.set(&pairs),
+            Err(ConfigError::Immutable("port".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("1024"), Ok(1024));
+        assert_eq!(parse_memory("1k"), Ok(1000));
+        assert_eq!(parse_memory("1KB"), Ok(1024));
+        assert_eq!(parse_memory("2gb"), Ok(2 * 1024 * 1024 * 1024));
+        assert!(parse_memory("1tb").is_err());
+        assert!(parse_memory("mb").is_err());
+    }
+}
@@ -0,0 +1,322 @@
+use super::{ConsumerGroup, PendingEntry, Stream, StreamId, Value, ZSet};
+use crate::{BulkString, RespArray, RespDecoder, RespEncoder, RespFrame};
+use bytes::BytesMut;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use thiserror::Error;
+
+// Bumped whenever the layout of the payload changes, payloads of newer versions are rejected.
+pub const DUMP_VERSION: u16 = 1;
+
+// DUMP payload layout:
+//
+//   <type: u8> <value: RESP frame> <version: u16 LE> <checksum: u64 LE>
+//
+// The value is the string frame itself, or an array holding the elements of a list or set, the
+// member/score pairs of a sorted set or the field/value pairs of a hash. A stream is an array of
+// its last ID, the number of entries ever added, the greatest deleted ID, an array of the ID and
+// the field/value array of each entry, and an array of its consumer groups. A group is an array
+// of its name, its last delivered ID, its pending entries as [id, consumer, delivery time,
+// delivery count] and its consumers as [name, seen time]. The checksum is the 64-bit FNV-1a hash
+// of everything before it.
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+const TYPE_SET: u8 = 2;
+const TYPE_ZSET: u8 = 3;
+const TYPE_HASH: u8 = 4;
+const TYPE_STREAM: u8 = 5;
+const FOOTER_LEN: usize = 2 + 8;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum DumpError {
+    #[error("DUMP payload version or checksum are wrong")]
+    VersionOrChecksum,
+    #[error("Bad data format")]
+    BadFormat,
+    #[error("Target key name already exists.")]
+    BusyKey,
+}
+
+// A value detached from the keyspace, as serialized by DUMP.
+impl Value {
+    pub fn serialize(self) -> Vec<u8> {
+        let (tag, frame): (u8, RespFrame) = match self {
+            Value::String(value) => (TYPE_STRING, value),
+            Value::List(list) => (
+                TYPE_LIST,
+                RespArray::new(list.into_iter().collect::<Vec<_>>()).into(),
+            ),
+            Value::Set(set) => (TYPE_SET, bulk_array(set.into_iter())),
+            Value::ZSet(zset) => {
+                let pairs = zset
+                    .iter()
+                    .flat_map(|(member, score)| [member.to_string(), score.to_string()])
+                    .collect::<Vec<_>>();
+                (TYPE_ZSET, bulk_array(pairs.into_iter()))
+            }
+            Value::Hash(hash) => {
+                let mut pairs = Vec::with_capacity(hash.len() * 2);
+                for (field, value) in hash {
+                    pairs.push(BulkString::from(field).into());
+                    pairs.push(value);
+                }
+                (TYPE_HASH, RespArray::new(pairs).into())
+            }
+            Value::Stream(stream) => {
+                let mut entries = vec![];
+                for (id, fields) in stream.iter() {
+                    let mut pairs = Vec::with_capacity(fields.len() * 2);
+                    for (field, value) in fields {
+                        pairs.push(BulkString::from(field.clone()).into());
+                        pairs.push(value.clone());
+                    }
+                    entries.push(BulkString::from(id.to_string()).into());
+                    entries.push(RespArray::new(pairs).into());
+                }
+                let groups = stream.groups().map(|(name, group)| {
+                    let mut pending = vec![];
+                    for (id, entry) in &group.pending {
+                        pending.push(BulkString::from(id.to_string()).into());
+                        pending.push(BulkString::from(entry.consumer.clone()).into());
+                        pending.push(RespFrame::Integer(entry.delivered_at as i64));
+                        pending.push(RespFrame::Integer(entry.delivery_count as i64));
+                    }
+                    let mut consumers = vec![];
+                    for (name, consumer) in &group.consumers {
+                        consumers.push(BulkString::from(name.clone()).into());
+                        consumers.push(RespFrame::Integer(consumer.seen_at as i64));
+                    }
+                    RespArray::new(vec![
+                        BulkString::from(name.clone()).into(),
+                        BulkString::from(group.last_delivered.to_string()).into(),
+                        RespArray::new(pending).into(),
+                        RespArray::new(consumers).into(),
+                    ])
+                    .into()
+                });
+                let items = vec![
+                    BulkString::from(stream.last_id().to_string()).into(),
+                    RespFrame::Integer(stream.entries_added() as i64),
+                    BulkString::from(stream.max_deleted_id().to_string()).into(),
+                    RespArray::new(entries).into(),
+                    RespArray::new(groups.collect::<Vec<_>>()).into(),
+                ];
+                (TYPE_STREAM, RespArray::new(items).into())
+            }
+        };
+
+        let mut buf = vec![tag];
+        buf.extend_from_slice(&frame.encode());
+        buf.extend_from_slice(&DUMP_VERSION.to_le_bytes());
+        let checksum = fnv1a(&buf);
+        buf.extend_from_slice(&checksum.to_le_bytes());
+        buf
+    }
+
+    pub fn deserialize(payload: &[u8]) -> Result<Self, DumpError> {
+        if payload.len() < 1 + FOOTER_LEN {
+            return Err(DumpError::VersionOrChecksum);
+        }
+        let (body, checksum) = payload.split_at(payload.len() - 8);
+        let checksum = u64::from_le_bytes(checksum.try_into().expect("8 bytes"));
+        let (data, version) = body.split_at(body.len() - 2);
+        let version = u16::from_le_bytes(version.try_into().expect("2 bytes"));
+        if version > DUMP_VERSION || checksum != fnv1a(body) {
+            return Err(DumpError::VersionOrChecksum);
+        }
+
+        let mut buf = BytesMut::from(&data[1..]);
+        let frame = RespFrame::decode(&mut buf).map_err(|_| DumpError::BadFormat)?;
+        if !buf.is_empty() {
+            return Err(DumpError::BadFormat);
+        }
+
+        let value = match (data[0], frame) {
+            (TYPE_STRING, frame) => Value::String(frame),
+            (TYPE_LIST, RespFrame::Array(items)) => Value::List(VecDeque::from(items.0).into()),
+            (TYPE_SET, RespFrame::Array(items)) => {
+                Value::Set(strings(items)?.into_iter().collect::<HashSet<_>>().into())
+            }
+            (TYPE_ZSET, RespFrame::Array(items)) => {
+                let mut zset = ZSet::new();
+                for pair in strings(items)?.chunks(2) {
+                    match pair {
+                        [member, score] => {
+                            let score = score
+                                .parse::<f64>()
+                                .ok()
+                                .filter(|score| !score.is_nan())
+                                .ok_or(DumpError::BadFormat)?;
+                            zset.insert(member.clone(), score);
+                        }
+                        _ => return Err(DumpError::BadFormat),
+                    }
+                }
+                Value::ZSet(zset)
+            }
+            (TYPE_HASH, RespFrame::Array(items)) => {
+                let mut hash = HashMap::new();
+                let mut items = items.0.into_iter();
+                while let Some(field) = items.next() {
+                    let field = string(field)?;
+                    let value = items.next().ok_or(DumpError::BadFormat)?;
+                    hash.insert(field, value);
+                }
+                Value::Hash(hash.into())
+            }
+            (TYPE_STREAM, RespFrame::Array(items)) => {
+                let mut items = items.0.into_iter();
+                let last_id = stream_id(items.next())?;
+                let entries_added = integer(items.next())?;
+                let max_deleted_id = stream_id(items.next())?;
+                let mut entries = BTreeMap::new();
+                let mut items_of_entries = array(items.next())?.into_iter();
+                while let Some(id) = items_of_entries.next() {
+                    let id = stream_id(Some(id))?;
+                    let mut pairs = array(items_of_entries.next())?.into_iter();
+                    let mut fields = vec![];
+                    while let Some(field) = pairs.next() {
+                        let value = pairs.next().ok_or(DumpError::BadFormat)?;
+                        fields.push((string(field)?, value));
+                    }
+                    entries.insert(id, fields);
+                }
+                let mut groups = BTreeMap::new();
+                for group in array(items.next())? {
+                    let mut group = array(Some(group))?.into_iter();
+                    let name = string(group.next().ok_or(DumpError::BadFormat)?)?;
+                    let mut consumer_group = ConsumerGroup {
+                        last_delivered: stream_id(group.next())?,
+                        ..Default::default()
+                    };
+                    let mut pending = array(group.next())?.into_iter();
+                    while let Some(id) = pending.next() {
+                        let id = stream_id(Some(id))?;
+                        let entry = PendingEntry {
+                            consumer: string(pending.next().ok_or(DumpError::BadFormat)?)?,
+                            delivered_at: integer(pending.next())?,
+                            delivery_count: integer(pending.next())?,
+                        };
+                        let consumers = &mut consumer_group.consumers;
+                        let consumer = consumers.entry(entry.consumer.clone()).or_default();
+                        consumer.pending.insert(id);
+                        consumer_group.pending.insert(id, entry);
+                    }
+                    let mut consumers = array(group.next())?.into_iter();
+                    while let Some(name) = consumers.next() {
+                        let name = string(name)?;
+                        let seen_at = integer(consumers.next())?;
+                        consumer_group.consumers.entry(name).or_default().seen_at = seen_at;
+                    }
+                    groups.insert(name, consumer_group);
+                }
+                Value::Stream(Stream::from_parts(
+                    entries,
+                    last_id,
+                    entries_added,
+                    max_deleted_id,
+                    groups,
+                ))
+            }
+            _ => return Err(DumpError::BadFormat),
+        };
+        Ok(value)
+    }
+}
+
+fn bulk_array(items: impl Iterator<Item = String>) -> RespFrame {
+    let items = items
+        .map(|item| BulkString::from(item).into())
+        .collect::<Vec<_>>();
+    RespArray::new(items).into()
+}
+
+fn strings(items: RespArray) -> Result<Vec<String>, DumpError> {
+    items.0.into_iter().map(string).collect()
+}
+
+fn string(frame: RespFrame) -> Result<String, DumpError> {
+    match frame {
+        RespFrame::BulkString(s) => String::from_utf8(s.0).map_err(|_| DumpError::BadFormat),
+        _ => Err(DumpError::BadFormat),
+    }
+}
+
+fn integer(frame: Option<RespFrame>) -> Result<u64, DumpError> {
+    match frame {
+        Some(RespFrame::Integer(n)) if n >= 0 => Ok(n as u64),
+        _ => Err(DumpError::BadFormat),
+    }
+}
+
+fn array(frame: Option<RespFrame>) -> Result<Vec<RespFrame>, DumpError> {
+    match frame {
+        Some(RespFrame::Array(items)) => Ok(items.0),
+        _ => Err(DumpError::BadFormat),
+    }
+}
+
+fn stream_id(frame: Option<RespFrame>) -> Result<StreamId, DumpError> {
+    let id = string(frame.ok_or(DumpError::BadFormat)?)?;
+    id.parse().map_err(|_| DumpError::BadFormat)
+}
+
+pub(super) fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump_round_trip() -> anyhow::Result<()> {
+        let mut zset = ZSet::new();
+        zset.insert("one".to_string(), 1.5);
+        zset.insert("inf".to_string(), f64::INFINITY);
+        let payload = Value::ZSet(zset).serialize();
+        match Value::deserialize(&payload)? {
+            Value::ZSet(zset) => {
+                let members = zset.iter().collect::<Vec<_>>();
+                assert_eq!(members, vec![("one", 1.5), ("inf", f64::INFINITY)]);
+            }
+            value => panic!("unexpected value: {value:?}"),
+        }
+
+        let mut hash = HashMap::new();
+        hash.insert("field".to_string(), BulkString::from("value").into());
+        let payload = Value::Hash(hash.into()).serialize();
+        match Value::deserialize(&payload)? {
+            Value::Hash(hash) => {
+                let value = hash.get("field");
+                assert_eq!(value, Some(BulkString::from("value").into()));
+            }
+            value => panic!("unexpected value: {value:?}"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_dump_rejects_corrupted_payload() {
+        let mut payload = Value::String(BulkString::from("hello").into()).serialize();
+        payload[3] ^= 0xff;
+        assert_eq!(
+            Value::deserialize(&payload).unwrap_err(),
+            DumpError::VersionOrChecksum
+        );
+        assert_eq!(
+            Value::deserialize(b"short").unwrap_err(),
+            DumpError::VersionOrChecksum
+        );
+
+        // a sorted set can't hold a NaN score
+        let mut zset = ZSet::new();
+        zset.insert("nan".to_string(), f64::NAN);
+        let payload = Value::ZSet(zset).serialize();
+        assert_eq!(
+            Value::deserialize(&payload).unwrap_err(),
+            DumpError::BadFormat
+        );
+    }
+}
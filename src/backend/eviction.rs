@@ -0,0 +1,238 @@
+use super::{Backend, NotifyClass, Storage};
+use rand::Rng;
+
+// How keys are picked for eviction once the used memory exceeds maxmemory, after
+// maxmemory-policy. The volatile policies only evict keys with an expiry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    // the least recently used keys first
+    AllKeysLru,
+    VolatileLru,
+    AllKeysRandom,
+    VolatileRandom,
+    // the least frequently used keys first
+    AllKeysLfu,
+    VolatileLfu,
+    // the keys closest to expiring first
+    VolatileTtl,
+    // nothing is evicted, commands that may add data fail instead
+    NoEviction,
+}
+
+impl EvictionPolicy {
+    // Parses one of MAXMEMORY_POLICIES.
+    pub fn parse(policy: &str) -> Self {
+        match policy {
+            "allkeys-lru" => EvictionPolicy::AllKeysLru,
+            "volatile-lru" => EvictionPolicy::VolatileLru,
+            "allkeys-random" => EvictionPolicy::AllKeysRandom,
+            "volatile-random" => EvictionPolicy::VolatileRandom,
+            "allkeys-lfu" => EvictionPolicy::AllKeysLfu,
+            "volatile-lfu" => EvictionPolicy::VolatileLfu,
+            "volatile-ttl" => EvictionPolicy::VolatileTtl,
+            _ => EvictionPolicy::NoEviction,
+        }
+    }
+
+    // Whether access frequencies matter, which OBJECT FREQ then reports instead of idle times.
+    pub fn is_lfu(&self) -> bool {
+        matches!(
+            self,
+            EvictionPolicy::AllKeysLfu | EvictionPolicy::VolatileLfu
+        )
+    }
+
+    fn volatile(&self) -> bool {
+        matches!(
+            self,
+            EvictionPolicy::VolatileLru
+                | EvictionPolicy::VolatileRandom
+                | EvictionPolicy::VolatileLfu
+                | EvictionPolicy::VolatileTtl
+        )
+    }
+}
+
+impl Backend {
+    // Estimated bytes taken by the keys and values of every database and by their hash tables.
+    pub fn used_memory(&self) -> usize {
+        (0..self.database_count())
+            .filter_map(|index| self.database(index))
+            .map(|db| {
+                let (main, expires) = db.overhead();
+                db.dataset_bytes() + main + expires
+            })
+            .sum()
+    }
+
+    // Evicts keys as maxmemory-policy says until the used memory is back under maxmemory, if
+    // any. Returns false when that's not possible: the policy is noeviction or there is nothing
+    // left it may evict. Each eviction fires the evicted keyspace event.
+    pub fn perform_evictions(&self) -> bool {
+        let (maxmemory, policy, samples, lazy) = {
+            let config = self.config().read();
+            let policy = EvictionPolicy::parse(&config.maxmemory_policy);
+            (
+                config.maxmemory as usize,
+                policy,
+                config.maxmemory_samples,
+                config.lazyfree_lazy_eviction,
+            )
+        };
+        if maxmemory == 0 {
+            return true;
+        }
+        while self.used_memory() > maxmemory {
+            let Some((index, key)) = self.eviction_candidate(policy, samples) else {
+                return false;
+            };
+            let Some(db) = self.database(index) else {
+                return false;
+            };
+            if let Some(value) = db.take(&key) {
+                self.free(value, lazy);
+                self.stats().key_evicted();
+                self.publish_keyspace_event(index, NotifyClass::Evicted, "evicted", &key);
+                if self.propagating() {
+                    self.propagate_in(index, &[vec![b"DEL".to_vec(), key]]);
+                }
+            }
+        }
+        true
+    }
+
+    // The (database index, key) to evict next: the best of `samples` keys sampled at random in
+    // each database, like Redis approximates LRU. None if there is no key the policy may evict.
+    fn eviction_candidate(
+        &self,
+        policy: EvictionPolicy,
+        samples: usize,
+    ) -> Option<(usize, Vec<u8>)> {
+        let mut rng = rand::thread_rng();
+        let (now, decay_time) = (self.now_ms(), self.config().read().lfu_decay_time);
+        // the key with the lowest rank goes
+        let mut best: Option<(i64, usize, Vec<u8>)> = None;
+        for index in 0..self.database_count() {
+            let Some(db) = self.database(index) else {
+                continue;
+            };
+            for key in db.keyspace.sample_keys(samples, policy.volatile()) {
+                let rank = match policy {
+                    EvictionPolicy::AllKeysLru | EvictionPolicy::VolatileLru => {
+                        db.access.get(&key).map_or(0, |v| v.at)
+                    }
+                    EvictionPolicy::AllKeysLfu | EvictionPolicy::VolatileLfu => db
+                        .access
+                        .get(&key)
+                        .map_or(0, |v| v.decayed_freq(now, decay_time) as i64),
+                    EvictionPolicy::VolatileTtl => db.keyspace.expire_at(&key).unwrap_or(i64::MAX),
+                    EvictionPolicy::AllKeysRandom | EvictionPolicy::VolatileRandom => rng.gen(),
+                    EvictionPolicy::NoEviction => return None,
+                };
+                if best.as_ref().is_none_or(|(best, ..)| rank < *best) {
+                    best = Some((rank, index, key));
+                }
+            }
+        }
+        best.map(|(_, index, key)| (index, key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, ConfigValues, ExpireCondition, ManualClock};
+    use anyhow::Result;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    // ten keys written a millisecond apart, every key sampled when evicting
+    fn filled_backend(policy: &str) -> (Backend, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new(Duration::from_secs(1_000)));
+        let config = ConfigValues {
+            maxmemory_policy: policy.to_string(),
+            maxmemory_samples: 64,
+            ..Default::default()
+        };
+        let backend = Backend::with_clock(config, clock.clone());
+        for i in 0..10 {
+            backend.set(
+                format!("key{i}").into_bytes(),
+                BulkString::from("value").into(),
+            );
+            clock.advance(Duration::from_millis(1));
+        }
+        (backend, clock)
+    }
+
+    fn set_maxmemory(backend: &Backend, maxmemory: usize) -> Result<()> {
+        backend
+            .config()
+            .set(&[("maxmemory".to_string(), maxmemory.to_string())])?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_evict_lru() -> Result<()> {
+        let (backend, _) = filled_backend("allkeys-lru");
+        assert!(backend.perform_evictions());
+        backend.get(b"key0")?;
+        set_maxmemory(&backend, backend.used_memory() - 1)?;
+
+        assert!(backend.perform_evictions());
+        assert!(!backend.exists(b"key1"));
+        assert!(backend.exists(b"key0"));
+        assert_eq!(backend.key_count().0, 9);
+        assert_eq!(backend.stats().evicted_keys(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_evict_lfu() -> Result<()> {
+        let (backend, _) = filled_backend("allkeys-lfu");
+        backend
+            .config()
+            .set(&[("lfu-log-factor".to_string(), "0".to_string())])?;
+        // every key but key3 gets read
+        for i in (0..10).filter(|i| *i != 3) {
+            backend.get(format!("key{i}").as_bytes())?;
+        }
+        set_maxmemory(&backend, backend.used_memory() - 1)?;
+        assert!(backend.perform_evictions());
+        assert!(!backend.exists(b"key3"));
+        assert_eq!(backend.key_count().0, 9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_evict_volatile() -> Result<()> {
+        let (backend, _) = filled_backend("volatile-ttl");
+        let now = backend.now_ms();
+        assert!(backend.expire_at(b"key5", now + 200, ExpireCondition::Always));
+        assert!(backend.expire_at(b"key7", now + 100, ExpireCondition::Always));
+        set_maxmemory(&backend, backend.used_memory() - 1)?;
+        assert!(backend.perform_evictions());
+        assert!(!backend.exists(b"key7"));
+
+        // only keys with an expiry go, and there are none left
+        set_maxmemory(&backend, 1)?;
+        assert!(!backend.perform_evictions());
+        assert_eq!(backend.key_count(), (8, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_noeviction() -> Result<()> {
+        let (backend, _) = filled_backend("noeviction");
+        set_maxmemory(&backend, 1)?;
+        assert!(!backend.perform_evictions());
+        assert_eq!(backend.key_count().0, 10);
+
+        backend
+            .config()
+            .set(&[("maxmemory-policy".to_string(), "allkeys-random".to_string())])?;
+        assert!(!backend.perform_evictions());
+        assert_eq!(backend.key_count().0, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use super::{Backend, Storage};
+use std::time::{Duration, Instant};
+
+// volatile keys looked at per database and round of the active expiry cycle
+const ACTIVE_EXPIRE_SAMPLE: usize = 20;
+// share of each period between two cycles (1 / hz) a cycle may spend removing keys, in percent
+const ACTIVE_EXPIRE_TIME_PERCENT: u32 = 25;
+
+// Condition under which EXPIRE and friends update the expiry of a key. For GT and LT a key
+// without an expiry counts as having an infinite one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExpireCondition {
+    #[default]
+    Always,
+    // only when the key has no expiry
+    Nx,
+    // only when the key already has an expiry
+    Xx,
+    // only when the new expiry is greater than the current one
+    Gt,
+    // only when the new expiry is less than the current one
+    Lt,
+}
+
+impl ExpireCondition {
+    // Whether an expiry at `when` may replace `current` (None for a key without expiry).
+    pub fn allows(&self, current: Option<i64>, when: i64) -> bool {
+        match (self, current) {
+            (ExpireCondition::Always, _) => true,
+            (ExpireCondition::Nx, current) => current.is_none(),
+            (ExpireCondition::Xx, current) => current.is_some(),
+            (ExpireCondition::Gt, Some(current)) => when > current,
+            (ExpireCondition::Gt, None) => false,
+            (ExpireCondition::Lt, Some(current)) => when < current,
+            (ExpireCondition::Lt, None) => true,
+        }
+    }
+}
+
+// Expiry state of a key as reported by TTL and friends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyExpiry {
+    Missing,
+    Persistent,
+    // milliseconds since the unix epoch
+    At(i64),
+}
+
+impl Backend {
+    // Runs the active expiry cycle `hz` times per second until the server shuts down, skipping
+    // it while disabled with DEBUG SET-ACTIVE-EXPIRE. Keys nobody reads again would otherwise
+    // never be removed.
+    pub async fn run_active_expire(&self) {
+        let mut shutdown = self.shutdown_signal();
+        loop {
+            let period = Duration::from_millis(1000 / self.config().read().hz.max(1));
+            tokio::select! {
+                _ = tokio::time::sleep(period) => {}
+                _ = shutdown.changed() => return,
+            }
+            // a replica leaves expiring keys to its master, which streams their removal
+            if !self.active_expire_enabled() || self.replication().master().is_some() {
+                continue;
+            }
+            // never in the middle of a transaction or a script, nor while one is busy
+            if let Some(_access) = self.shared_access().await {
+                self.active_expire_cycle(period * ACTIVE_EXPIRE_TIME_PERCENT / 100);
+            }
+        }
+    }
+
+    // Removes expired keys of every database: samples its volatile keys at random and removes
+    // the expired ones, sampling again as long as more than a quarter of the sample had expired
+    // and `budget` isn't spent. Returns the number of keys removed.
+    pub fn active_expire_cycle(&self, budget: Duration) -> usize {
+        let deadline = Instant::now() + budget;
+        let mut removed = 0;
+        for index in 0..self.database_count() {
+            let Some(db) = self.database(index) else {
+                continue;
+            };
+            loop {
+                // the keys are cloned so no lock is held while removing them
+                let sample = db.keyspace.sample_keys(ACTIVE_EXPIRE_SAMPLE, true);
+                let expired = sample
+                    .iter()
+                    .filter(|key| self.expire_in(index, db, key))
+                    .count();
+                removed += expired;
+                if expired * 4 <= sample.len() || Instant::now() >= deadline {
+                    break;
+                }
+            }
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expire_condition() {
+        assert!(ExpireCondition::Always.allows(Some(10), 5));
+        assert!(ExpireCondition::Nx.allows(None, 5));
+        assert!(!ExpireCondition::Nx.allows(Some(10), 5));
+        assert!(ExpireCondition::Xx.allows(Some(10), 5));
+        assert!(!ExpireCondition::Xx.allows(None, 5));
+        assert!(ExpireCondition::Gt.allows(Some(10), 20));
+        assert!(!ExpireCondition::Gt.allows(None, 20));
+        assert!(ExpireCondition::Lt.allows(Some(10), 5));
+        assert!(ExpireCondition::Lt.allows(None, 5));
+    }
+}
@@ -0,0 +1,181 @@
+use super::glob_match;
+use std::collections::BTreeMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use thiserror::Error;
+
+// The flags a function can be registered with, see redis.register_function.
+pub const FUNCTION_FLAGS: &[&str] = &[
+    "no-writes",
+    "allow-oom",
+    "allow-stale",
+    "no-cluster",
+    "allow-cross-slot-keys",
+];
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum FunctionError {
+    #[error("Library '{0}' already exists")]
+    LibraryExists(String),
+    #[error("Function {0} already exists")]
+    FunctionExists(String),
+}
+
+// A function a library registered, FCALL runs it by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionInfo {
+    pub name: String,
+    pub description: Option<String>,
+    pub flags: Vec<String>,
+}
+
+impl FunctionInfo {
+    // Whether the function promised not to write, FCALL_RO only runs those.
+    pub fn is_read_only(&self) -> bool {
+        self.flags.iter().any(|flag| flag == "no-writes")
+    }
+}
+
+// A library loaded with FUNCTION LOAD: its code, which registers the functions when it runs,
+// and what it registered the time it was loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionLibrary {
+    pub name: String,
+    pub code: String,
+    pub functions: Vec<FunctionInfo>,
+}
+
+// The function libraries, shared by every connection and saved along with the databases. A
+// function name is unique across all libraries.
+#[derive(Debug, Default)]
+pub struct FunctionLibraries {
+    // by library name, so that FUNCTION LIST is sorted
+    libraries: RwLock<BTreeMap<String, FunctionLibrary>>,
+}
+
+impl FunctionLibraries {
+    // Adds `library`, replacing the one with the same name only when `replace` is set.
+    pub fn insert(&self, library: FunctionLibrary, replace: bool) -> Result<(), FunctionError> {
+        let mut libraries = self.write();
+        if libraries.contains_key(&library.name) && !replace {
+            return Err(FunctionError::LibraryExists(library.name));
+        }
+        let taken = libraries
+            .values()
+            .filter(|other| other.name != library.name)
+            .flat_map(|other| other.functions.iter())
+            .find(|function| library.functions.iter().any(|f| f.name == function.name));
+        if let Some(function) = taken {
+            return Err(FunctionError::FunctionExists(function.name.clone()));
+        }
+        libraries.insert(library.name.clone(), library);
+        Ok(())
+    }
+
+    // Removes the library `name` with its functions, false if there is no such library.
+    pub fn delete(&self, name: &str) -> bool {
+        self.write().remove(name).is_some()
+    }
+
+    // The library registering the function `name`, along with that function.
+    pub fn find(&self, name: &str) -> Option<(FunctionLibrary, FunctionInfo)> {
+        self.read().values().find_map(|library| {
+            let function = library.functions.iter().find(|f| f.name == name)?;
+            Some((library.clone(), function.clone()))
+        })
+    }
+
+    // The libraries sorted by name, only those matching the glob-style `pattern` if given.
+    pub fn list(&self, pattern: Option<&str>) -> Vec<FunctionLibrary> {
+        self.read()
+            .values()
+            .filter(|library| pattern.is_none_or(|pattern| glob_match(pattern, &library.name)))
+            .cloned()
+            .collect()
+    }
+
+    pub fn flush(&self) {
+        self.write().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    // Number of functions across all libraries.
+    pub fn function_count(&self) -> usize {
+        self.read()
+            .values()
+            .map(|library| library.functions.len())
+            .sum()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, BTreeMap<String, FunctionLibrary>> {
+        self.libraries.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, BTreeMap<String, FunctionLibrary>> {
+        self.libraries.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(name: &str, functions: &[&str]) -> FunctionLibrary {
+        FunctionLibrary {
+            name: name.to_string(),
+            code: format!("#!lua name={name}"),
+            functions: functions
+                .iter()
+                .map(|function| FunctionInfo {
+                    name: function.to_string(),
+                    description: None,
+                    flags: vec![],
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_function_libraries() {
+        let libraries = FunctionLibraries::default();
+        assert_eq!(libraries.insert(library("lib", &["f", "g"]), false), Ok(()));
+        assert_eq!(
+            libraries.insert(library("lib", &["h"]), false),
+            Err(FunctionError::LibraryExists("lib".to_string()))
+        );
+        assert_eq!(
+            libraries.insert(library("other", &["g"]), false),
+            Err(FunctionError::FunctionExists("g".to_string()))
+        );
+        assert_eq!(libraries.insert(library("another", &["h"]), false), Ok(()));
+        assert_eq!(libraries.function_count(), 3);
+
+        // the functions of the replaced library don't clash
+        assert_eq!(libraries.insert(library("lib", &["g"]), true), Ok(()));
+        assert_eq!(libraries.find("f"), None);
+        let (found, function) = libraries.find("g").expect("g is registered");
+        assert_eq!((found.name.as_str(), function.name.as_str()), ("lib", "g"));
+
+        let names = |pattern| {
+            libraries
+                .list(pattern)
+                .into_iter()
+                .map(|library| library.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(None), ["another", "lib"]);
+        assert_eq!(names(Some("l*")), ["lib"]);
+
+        assert!(libraries.delete("lib"));
+        assert!(!libraries.delete("lib"));
+        assert_eq!(libraries.len(), 1);
+        libraries.flush();
+        assert!(libraries.is_empty());
+    }
+}
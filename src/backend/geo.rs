@@ -0,0 +1,279 @@
+use super::WrongType;
+use thiserror::Error;
+
+// The geospatial indexes of GEOADD and friends: sorted sets whose scores are the 52 bits geohash
+// of the members' positions, interleaving 26 bits of latitude and 26 bits of longitude like Redis
+// so that nearby positions get close scores.
+const STEP: u32 = 26;
+pub const LON_MIN: f64 = -180.0;
+pub const LON_MAX: f64 = 180.0;
+// the limits of the Web Mercator projection, as the poles can't be indexed
+pub const LAT_MIN: f64 = -85.05112878;
+pub const LAT_MAX: f64 = 85.05112878;
+const EARTH_RADIUS_IN_METERS: f64 = 6372797.560856;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum GeoError {
+    #[error("ERR could not decode requested zset member")]
+    NoMember,
+    #[error(transparent)]
+    WrongType(#[from] WrongType),
+}
+
+// Where a GEOSEARCH is centered.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoOrigin {
+    Member(String),
+    LonLat(f64, f64),
+}
+
+// The area of a GEOSEARCH around its center, in meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    Radius(f64),
+    Box { width: f64, height: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoQuery {
+    pub origin: GeoOrigin,
+    pub shape: GeoShape,
+    // the unit of the shape, which the distances are reported in
+    pub unit: GeoUnit,
+    // unsorted unless given, but COUNT without ANY sorts from the nearest
+    pub order: Option<GeoOrder>,
+    pub count: Option<usize>,
+    // stop at the first `count` matches rather than finding the nearest ones
+    pub any: bool,
+}
+
+// A member found by a GEOSEARCH, with its distance in meters from the center.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoMatch {
+    pub member: String,
+    pub hash: u64,
+    pub lon: f64,
+    pub lat: f64,
+    pub distance: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GeoUnit {
+    #[default]
+    Meters,
+    Kilometers,
+    Miles,
+    Feet,
+}
+
+impl GeoUnit {
+    // Parses "m", "km", "mi" or "ft", case-insensitively.
+    pub fn parse(unit: &str) -> Option<Self> {
+        match unit.to_ascii_lowercase().as_str() {
+            "m" => Some(GeoUnit::Meters),
+            "km" => Some(GeoUnit::Kilometers),
+            "mi" => Some(GeoUnit::Miles),
+            "ft" => Some(GeoUnit::Feet),
+            _ => None,
+        }
+    }
+
+    pub fn meters(&self) -> f64 {
+        match self {
+            GeoUnit::Meters => 1.0,
+            GeoUnit::Kilometers => 1000.0,
+            GeoUnit::Miles => 1609.34,
+            GeoUnit::Feet => 0.3048,
+        }
+    }
+}
+
+// The geohash of a position, None if it is out of the indexable area.
+pub fn geohash_encode(lon: f64, lat: f64) -> Option<u64> {
+    if !(LON_MIN..=LON_MAX).contains(&lon) || !(LAT_MIN..=LAT_MAX).contains(&lat) {
+        return None;
+    }
+    let scale = (1u64 << STEP) as f64;
+    // the maximum falls in the last cell rather than past it
+    let cell = |offset: f64| ((offset * scale) as u64).min((1 << STEP) - 1);
+    let lat = cell((lat - LAT_MIN) / (LAT_MAX - LAT_MIN));
+    let lon = cell((lon - LON_MIN) / (LON_MAX - LON_MIN));
+    Some(interleave(lat, lon))
+}
+
+// The longitude and latitude of the center of the cell of a geohash.
+pub fn geohash_decode(hash: u64) -> (f64, f64) {
+    let (lat, lon) = deinterleave(hash);
+    let scale = (1u64 << STEP) as f64;
+    let center = |cell: u64, min: f64, max: f64| {
+        let low = min + (cell as f64 / scale) * (max - min);
+        let high = min + ((cell + 1) as f64 / scale) * (max - min);
+        ((low + high) / 2.0).clamp(min, max)
+    };
+    (center(lon, LON_MIN, LON_MAX), center(lat, LAT_MIN, LAT_MAX))
+}
+
+// The great-circle distance in meters between two positions, with the haversine formula.
+pub fn geo_distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let u = ((lat2 - lat1) / 2.0).sin();
+    let v = ((lon2 - lon1).to_radians() / 2.0).sin();
+    let a = u * u + lat1.cos() * lat2.cos() * v * v;
+    2.0 * EARTH_RADIUS_IN_METERS * a.sqrt().asin()
+}
+
+impl GeoShape {
+    // The distance of the position from the center if it lies in the shape.
+    pub fn distance(&self, center: (f64, f64), lon: f64, lat: f64) -> Option<f64> {
+        let (center_lon, center_lat) = center;
+        match *self {
+            GeoShape::Radius(radius) => {
+                let distance = geo_distance(center_lon, center_lat, lon, lat);
+                (distance <= radius).then_some(distance)
+            }
+            GeoShape::Box { width, height } => {
+                // the distances along the meridian and the parallel of the position
+                let lat_distance =
+                    EARTH_RADIUS_IN_METERS * (lat.to_radians() - center_lat.to_radians()).abs();
+                if lat_distance > height / 2.0 {
+                    return None;
+                }
+                if geo_distance(lon, lat, center_lon, lat) > width / 2.0 {
+                    return None;
+                }
+                Some(geo_distance(center_lon, center_lat, lon, lat))
+            }
+        }
+    }
+}
+
+impl GeoQuery {
+    // Filters the (member, score) pairs of a geospatial index centered on `center`.
+    pub fn search<'a>(
+        &self,
+        center: (f64, f64),
+        members: impl Iterator<Item = (&'a str, f64)>,
+    ) -> Vec<GeoMatch> {
+        let mut matches = Vec::new();
+        for (member, score) in members {
+            let hash = score as u64;
+            let (lon, lat) = geohash_decode(hash);
+            if let Some(distance) = self.shape.distance(center, lon, lat) {
+                matches.push(GeoMatch {
+                    member: member.to_string(),
+                    hash,
+                    lon,
+                    lat,
+                    distance,
+                });
+                if self.any && Some(matches.len()) == self.count {
+                    break;
+                }
+            }
+        }
+        let order = match self.order {
+            None if self.count.is_some() && !self.any => Some(GeoOrder::Asc),
+            order => order,
+        };
+        match order {
+            Some(GeoOrder::Asc) => matches.sort_by(|a, b| a.distance.total_cmp(&b.distance)),
+            Some(GeoOrder::Desc) => matches.sort_by(|a, b| b.distance.total_cmp(&a.distance)),
+            None => {}
+        }
+        if let Some(count) = self.count {
+            matches.truncate(count);
+        }
+        matches
+    }
+}
+
+// The bits of the latitude go to the even positions and those of the longitude to the odd ones.
+fn interleave(lat: u64, lon: u64) -> u64 {
+    (0..STEP).fold(0, |hash, i| {
+        hash | ((lat >> i) & 1) << (2 * i) | ((lon >> i) & 1) << (2 * i + 1)
+    })
+}
+
+fn deinterleave(hash: u64) -> (u64, u64) {
+    (0..STEP).fold((0, 0), |(lat, lon), i| {
+        (
+            lat | ((hash >> (2 * i)) & 1) << i,
+            lon | ((hash >> (2 * i + 1)) & 1) << i,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geohash() {
+        // the score Redis gives Palermo
+        let hash = geohash_encode(13.361389, 38.115556).unwrap();
+        assert_eq!(hash, 3479099956230698);
+        let (lon, lat) = geohash_decode(hash);
+        assert!((lon - 13.361389).abs() < 1e-5 && (lat - 38.115556).abs() < 1e-5);
+
+        assert!(geohash_encode(LON_MAX, LAT_MAX).is_some());
+        assert_eq!(geohash_encode(181.0, 0.0), None);
+        assert_eq!(geohash_encode(0.0, 86.0), None);
+    }
+
+    #[test]
+    fn test_geo_distance() {
+        // Palermo to Catania
+        let distance = geo_distance(13.361389, 38.115556, 15.087269, 37.502669);
+        assert!((distance - 166274.1516).abs() < 1.0, "{distance}");
+        assert_eq!(GeoUnit::parse("KM"), Some(GeoUnit::Kilometers));
+        assert_eq!(GeoUnit::parse("yd"), None);
+    }
+
+    #[test]
+    fn test_geo_search() {
+        let members = [
+            ("Palermo", 3479099956230698.0),
+            ("Catania", 3479447370796909.0),
+        ];
+        let mut query = GeoQuery {
+            origin: GeoOrigin::LonLat(15.0, 37.0),
+            shape: GeoShape::Radius(200_000.0),
+            unit: GeoUnit::Kilometers,
+            order: Some(GeoOrder::Desc),
+            count: None,
+            any: false,
+        };
+        let found = |query: &GeoQuery| {
+            query
+                .search((15.0, 37.0), members.iter().copied())
+                .into_iter()
+                .map(|m| m.member)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(found(&query), ["Palermo", "Catania"]);
+        query.shape = GeoShape::Radius(100_000.0);
+        assert_eq!(found(&query), ["Catania"]);
+
+        // COUNT alone sorts from the nearest
+        query.shape = GeoShape::Box {
+            width: 400_000.0,
+            height: 400_000.0,
+        };
+        query.order = None;
+        query.count = Some(1);
+        assert_eq!(found(&query), ["Catania"]);
+        query.any = true;
+        assert_eq!(found(&query), ["Palermo"]);
+        query.shape = GeoShape::Box {
+            width: 400_000.0,
+            height: 120_000.0,
+        };
+        assert_eq!(found(&query), ["Catania"]);
+    }
+}
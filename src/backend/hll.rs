@@ -0,0 +1,259 @@
+use super::WrongType;
+use thiserror::Error;
+
+// The HyperLogLog of PFADD and friends, kept in a string value with the dense layout of Redis: a
+// 16 bytes header ("HYLL", the encoding, 3 unused bytes and the cached cardinality) followed by
+// 16384 registers of 6 bits.
+const MAGIC: &[u8] = b"HYLL";
+const DENSE: u8 = 0;
+const HEADER_SIZE: usize = 16;
+// the low bits of a hash select the register, the others give the run of zeros
+const P: u32 = 14;
+const Q: u32 = 64 - P;
+const REGISTERS: usize = 1 << P;
+const REGISTER_BITS: usize = 6;
+const REGISTER_MAX: u8 = (1 << REGISTER_BITS) - 1;
+const DENSE_SIZE: usize = HEADER_SIZE + (REGISTERS * REGISTER_BITS).div_ceil(8);
+// set in the last byte of the cached cardinality once a register changed
+const CACHE_INVALID: u8 = 1 << 7;
+const SEED: u64 = 0xadc83b19;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum HllError {
+    #[error("WRONGTYPE Key is not a valid HyperLogLog string value.")]
+    InvalidValue,
+    #[error(transparent)]
+    WrongType(#[from] WrongType),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog(Vec<u8>);
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        let mut bytes = vec![0; DENSE_SIZE];
+        bytes[..MAGIC.len()].copy_from_slice(MAGIC);
+        bytes[MAGIC.len()] = DENSE;
+        HyperLogLog(bytes)
+    }
+}
+
+impl TryFrom<Vec<u8>> for HyperLogLog {
+    type Error = HllError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        Self::validate(&bytes)?;
+        Ok(HyperLogLog(bytes))
+    }
+}
+
+impl HyperLogLog {
+    // Whether `bytes` hold a HyperLogLog, so it can be updated in place.
+    pub fn validate(bytes: &[u8]) -> Result<(), HllError> {
+        if bytes.len() != DENSE_SIZE || !bytes.starts_with(MAGIC) || bytes[MAGIC.len()] != DENSE {
+            return Err(HllError::InvalidValue);
+        }
+        Ok(())
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    // Adds an element, returns whether a register changed and so the estimate may have.
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        add(&mut self.0, element)
+    }
+
+    // Raises each register to the one of `other`, making this the HyperLogLog of the union.
+    pub fn merge(&mut self, other: &[u8]) {
+        let registers = &mut self.0[HEADER_SIZE..];
+        let other = &other[HEADER_SIZE..];
+        for i in 0..REGISTERS {
+            let value = get_register(other, i);
+            if value > get_register(registers, i) {
+                set_register(registers, i, value);
+            }
+        }
+        invalidate(&mut self.0);
+    }
+
+    pub fn count(&mut self) -> u64 {
+        count(&mut self.0)
+    }
+}
+
+// Adds an element to the HyperLogLog in `bytes`, see HyperLogLog::add.
+pub fn add(bytes: &mut [u8], element: &[u8]) -> bool {
+    let hash = murmur_hash64a(element, SEED);
+    let index = (hash & (REGISTERS as u64 - 1)) as usize;
+    // the sentinel bit bounds the run to Q + 1
+    let run = ((hash >> P) | (1 << Q)).trailing_zeros() as u8 + 1;
+    let registers = &mut bytes[HEADER_SIZE..];
+    if run <= get_register(registers, index) {
+        return false;
+    }
+    set_register(registers, index, run);
+    invalidate(bytes);
+    true
+}
+
+// The estimated cardinality of the HyperLogLog in `bytes`, cached in its header until it changes.
+pub fn count(bytes: &mut [u8]) -> u64 {
+    let cache = &mut bytes[8..HEADER_SIZE];
+    if cache[7] & CACHE_INVALID == 0 {
+        return u64::from_le_bytes(cache.try_into().expect("8 bytes"));
+    }
+    let count = estimate(&bytes[HEADER_SIZE..]);
+    bytes[8..HEADER_SIZE].copy_from_slice(&count.to_le_bytes());
+    count
+}
+
+fn invalidate(bytes: &mut [u8]) {
+    bytes[HEADER_SIZE - 1] |= CACHE_INVALID;
+}
+
+fn get_register(registers: &[u8], index: usize) -> u8 {
+    let bit = index * REGISTER_BITS;
+    let (byte, shift) = (bit / 8, bit % 8);
+    let low = registers[byte] as u16;
+    let high = registers.get(byte + 1).copied().unwrap_or(0) as u16;
+    (((low | high << 8) >> shift) as u8) & REGISTER_MAX
+}
+
+fn set_register(registers: &mut [u8], index: usize, value: u8) {
+    let bit = index * REGISTER_BITS;
+    let (byte, shift) = (bit / 8, bit % 8);
+    let mask = (REGISTER_MAX as u16) << shift;
+    let value = (value as u16) << shift;
+    registers[byte] = (registers[byte] & !(mask as u8)) | value as u8;
+    if let Some(high) = registers.get_mut(byte + 1) {
+        *high = (*high & !((mask >> 8) as u8)) | (value >> 8) as u8;
+    }
+}
+
+// The cardinality estimator of "New cardinality estimation algorithms for HyperLogLog sketches"
+// (Otmar Ertl), which needs no bias correction at either end of the range.
+fn estimate(registers: &[u8]) -> u64 {
+    let mut histogram = [0u32; Q as usize + 2];
+    for i in 0..REGISTERS {
+        histogram[get_register(registers, i) as usize] += 1;
+    }
+    let m = REGISTERS as f64;
+    let mut z = m * tau((m - histogram[Q as usize + 1] as f64) / m);
+    for j in (1..=Q as usize).rev() {
+        z += histogram[j] as f64;
+        z *= 0.5;
+    }
+    z += m * sigma(histogram[0] as f64 / m);
+    let alpha = 0.5 / std::f64::consts::LN_2;
+    (alpha * m * m / z).round() as u64
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let (mut y, mut z) = (1.0, x);
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y += y;
+        if z == previous {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let (mut y, mut z) = (1.0, 1.0 - x);
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if z == previous {
+            return z / 3.0;
+        }
+    }
+}
+
+// MurmurHash64A, the hash Redis gives the elements so the registers match its own.
+fn murmur_hash64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4a7935bd1e995;
+    const R: u32 = 47;
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+    let mut chunks = key.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().expect("8 bytes"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, byte) in tail.iter().enumerate() {
+            h ^= (*byte as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registers() {
+        let mut registers = vec![0; DENSE_SIZE - HEADER_SIZE];
+        for i in [0, 1, 2, 3, 4, 5, 1000, REGISTERS - 1] {
+            set_register(&mut registers, i, (i % 64) as u8);
+        }
+        set_register(&mut registers, 3, REGISTER_MAX);
+        assert_eq!(get_register(&registers, 2), 2);
+        assert_eq!(get_register(&registers, 3), REGISTER_MAX);
+        assert_eq!(get_register(&registers, 4), 4);
+        assert_eq!(get_register(&registers, 1000), 40);
+        assert_eq!(get_register(&registers, REGISTERS - 1), 63);
+    }
+
+    #[test]
+    fn test_count() {
+        let mut hll = HyperLogLog::default();
+        assert_eq!(hll.count(), 0);
+        for i in 0..10_000 {
+            hll.add(format!("element:{i}").as_bytes());
+        }
+        assert!(!hll.add(b"element:1"));
+        let count = hll.count();
+        assert!((9_800..=10_200).contains(&count), "{count}");
+
+        let mut other = HyperLogLog::default();
+        for i in 5_000..20_000 {
+            other.add(format!("element:{i}").as_bytes());
+        }
+        hll.merge(&other.0);
+        let count = hll.count();
+        assert!((19_600..=20_400).contains(&count), "{count}");
+    }
+
+    #[test]
+    fn test_validate() {
+        let hll = HyperLogLog::default().into_bytes();
+        assert!(HyperLogLog::validate(&hll).is_ok());
+        assert_eq!(
+            HyperLogLog::try_from(b"HYLL".to_vec()),
+            Err(HllError::InvalidValue)
+        );
+    }
+}
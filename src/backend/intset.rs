@@ -0,0 +1,198 @@
+use super::memory::MemoryUsage;
+use super::EncodingLimits;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::mem::size_of;
+
+// A set, kept as a sorted array of integers while all its members are integers and it is small.
+// Another member converts it to a hash table, which it stays even once it shrinks back.
+#[derive(Debug, Clone)]
+pub enum SetValue {
+    IntSet(Vec<i64>),
+    HashTable(HashSet<String>),
+}
+
+impl Default for SetValue {
+    fn default() -> Self {
+        SetValue::IntSet(vec![])
+    }
+}
+
+impl From<HashSet<String>> for SetValue {
+    fn from(set: HashSet<String>) -> Self {
+        SetValue::HashTable(set)
+    }
+}
+
+impl SetValue {
+    pub fn len(&self) -> usize {
+        match self {
+            SetValue::IntSet(ints) => ints.len(),
+            SetValue::HashTable(set) => set.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The encoding reported by OBJECT ENCODING.
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            SetValue::IntSet(_) => "intset",
+            SetValue::HashTable(_) => "hashtable",
+        }
+    }
+
+    pub fn contains(&self, member: &str) -> bool {
+        match self {
+            SetValue::IntSet(ints) => {
+                integer(member).is_some_and(|n| ints.binary_search(&n).is_ok())
+            }
+            SetValue::HashTable(set) => set.contains(member),
+        }
+    }
+
+    // Adds the member. Returns whether it is new.
+    pub fn insert(&mut self, member: String, limits: &EncodingLimits) -> bool {
+        if let SetValue::IntSet(ints) = self {
+            if let Some(n) = integer(&member) {
+                match ints.binary_search(&n) {
+                    Ok(_) => return false,
+                    Err(at) if ints.len() < limits.set_entries => {
+                        ints.insert(at, n);
+                        return true;
+                    }
+                    Err(_) => {}
+                }
+            }
+            self.convert();
+        }
+        match self {
+            SetValue::HashTable(set) => set.insert(member),
+            SetValue::IntSet(_) => unreachable!("converted above"),
+        }
+    }
+
+    // Removes the member. Returns whether it was there.
+    pub fn remove(&mut self, member: &str) -> bool {
+        match self {
+            SetValue::IntSet(ints) => match integer(member).map(|n| ints.binary_search(&n)) {
+                Some(Ok(at)) => {
+                    ints.remove(at);
+                    true
+                }
+                _ => false,
+            },
+            SetValue::HashTable(set) => set.remove(member),
+        }
+    }
+
+    // The members, borrowed unless they are integers that have to be formatted.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = Cow<'_, str>> + '_> {
+        match self {
+            SetValue::IntSet(ints) => Box::new(ints.iter().map(|n| Cow::Owned(n.to_string()))),
+            SetValue::HashTable(set) => Box::new(set.iter().map(|m| Cow::Borrowed(m.as_str()))),
+        }
+    }
+
+    // Turns a set that was built whole (by RESTORE or the STORE variants of the set operations)
+    // into an intset if it is small enough and holds only integers.
+    pub fn compact(self, limits: &EncodingLimits) -> Self {
+        let SetValue::HashTable(set) = &self else {
+            return self;
+        };
+        if set.len() > limits.set_entries {
+            return self;
+        }
+        match set.iter().map(|m| integer(m)).collect::<Option<Vec<_>>>() {
+            Some(mut ints) => {
+                ints.sort_unstable();
+                SetValue::IntSet(ints)
+            }
+            None => self,
+        }
+    }
+
+    fn convert(&mut self) {
+        if let SetValue::IntSet(ints) = self {
+            *self = SetValue::HashTable(ints.iter().map(i64::to_string).collect());
+        }
+    }
+}
+
+impl IntoIterator for SetValue {
+    type Item = String;
+    type IntoIter = std::vec::IntoIter<String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let members = match self {
+            SetValue::IntSet(ints) => ints.iter().map(i64::to_string).collect::<Vec<_>>(),
+            SetValue::HashTable(set) => set.into_iter().collect(),
+        };
+        members.into_iter()
+    }
+}
+
+impl MemoryUsage for SetValue {
+    fn memory_usage(&self, samples: usize) -> usize {
+        match self {
+            SetValue::IntSet(ints) => size_of::<Self>() + ints.capacity() * size_of::<i64>(),
+            SetValue::HashTable(set) => set.memory_usage(samples),
+        }
+    }
+}
+
+// The member as an integer if it is one written the way it would be formatted back, so that
+// members read back from an intset are the ones that were added.
+fn integer(member: &str) -> Option<i64> {
+    member
+        .parse::<i64>()
+        .ok()
+        .filter(|n| n.to_string() == member)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intset() {
+        let limits = EncodingLimits {
+            set_entries: 3,
+            ..Default::default()
+        };
+        let mut set = SetValue::default();
+        for member in ["3", "-1", "2"] {
+            assert!(set.insert(member.to_string(), &limits));
+        }
+        assert!(!set.insert("2".to_string(), &limits));
+        assert_eq!(set.encoding(), "intset");
+        let members = set.iter().map(Cow::into_owned).collect::<Vec<_>>();
+        assert_eq!(members, vec!["-1", "2", "3"]);
+        assert!(set.contains("-1"));
+        assert!(!set.contains("02"));
+
+        assert!(set.remove("2"));
+        assert!(!set.remove("2"));
+        assert!(!set.remove("a"));
+        assert_eq!(set.len(), 2);
+
+        // "07" would be read back as "7"
+        assert!(set.insert("07".to_string(), &limits));
+        assert_eq!(set.encoding(), "hashtable");
+        assert!(set.contains("-1"));
+        assert!(set.contains("07"));
+
+        let mut set = SetValue::default();
+        for n in 0..4 {
+            set.insert(n.to_string(), &limits);
+        }
+        assert_eq!(set.encoding(), "hashtable");
+        assert_eq!(set.len(), 4);
+        let set = SetValue::from(HashSet::from(["5".to_string(), "1".to_string()]));
+        let set = set.compact(&limits);
+        assert_eq!(set.encoding(), "intset");
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), vec!["1", "5"]);
+    }
+}
@@ -0,0 +1,128 @@
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::hash::BuildHasher;
+use std::sync::{Mutex, MutexGuard};
+
+// number of locks the keys are spread over
+const KEY_LOCK_STRIPES: usize = 1024;
+
+thread_local! {
+    // the stripes this thread holds, by the address of their `KeyLocks`
+    static HELD: RefCell<HashSet<(usize, usize)>> = RefCell::new(HashSet::new());
+}
+
+// Mutual exclusion between the commands writing the same keys. Keys are spread over a fixed set
+// of locks by hash, whatever their database, and a command takes the locks of all its keys in
+// ascending order: two commands sharing a key run one after the other, and none of them can
+// deadlock waiting for the other. A thread holding the locks of a key can take them again, so a
+// backend operation locking its keys also runs inside a command that already locked them.
+#[derive(Debug)]
+pub struct KeyLocks {
+    stripes: Vec<Mutex<()>>,
+    hasher: RandomState,
+}
+
+// The locks of a set of keys, released once dropped.
+pub struct KeysGuard<'a> {
+    owner: usize,
+    stripes: Vec<usize>,
+    _guards: Vec<MutexGuard<'a, ()>>,
+}
+
+impl KeyLocks {
+    pub fn new() -> Self {
+        Self {
+            stripes: (0..KEY_LOCK_STRIPES).map(|_| Mutex::new(())).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    // Blocks until no other thread holds a lock of `keys`. Only the locks this thread doesn't
+    // hold yet are taken, and only those are released with the guard.
+    pub fn lock<K: AsRef<[u8]>>(&self, keys: &[K]) -> KeysGuard<'_> {
+        let owner = self as *const Self as usize;
+        let mut stripes = keys
+            .iter()
+            .map(|key| self.hasher.hash_one(key.as_ref()) as usize % self.stripes.len())
+            .collect::<Vec<_>>();
+        stripes.sort_unstable();
+        stripes.dedup();
+        HELD.with_borrow(|held| stripes.retain(|&i| !held.contains(&(owner, i))));
+        let guards = stripes
+            .iter()
+            .map(|&i| self.stripes[i].lock().unwrap_or_else(|e| e.into_inner()))
+            .collect();
+        HELD.with_borrow_mut(|held| held.extend(stripes.iter().map(|&i| (owner, i))));
+        KeysGuard {
+            owner,
+            stripes,
+            _guards: guards,
+        }
+    }
+}
+
+impl Drop for KeysGuard<'_> {
+    fn drop(&mut self) {
+        HELD.with_borrow_mut(|held| {
+            for &i in &self.stripes {
+                held.remove(&(self.owner, i));
+            }
+        });
+    }
+}
+
+impl Default for KeyLocks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Backend, BulkString, RespFrame};
+    use std::thread;
+
+    #[test]
+    fn test_atomically() {
+        let backend = Backend::new();
+        let counter = |backend: &Backend| match backend.get(b"counter") {
+            Ok(Some(RespFrame::BulkString(n))) => String::from_utf8_lossy(&n).parse::<u64>().ok(),
+            _ => None,
+        };
+        backend.set(b"counter".to_vec(), BulkString::from("0").into());
+        // a read then a write of the key, which would lose increments if they interleaved
+        let keys = [b"counter".to_vec(), b"other".to_vec()];
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..250 {
+                        backend.atomically(&keys, || {
+                            let n = counter(&backend).unwrap_or_default();
+                            backend.set(
+                                b"counter".to_vec(),
+                                BulkString::from((n + 1).to_string()).into(),
+                            );
+                        });
+                    }
+                });
+            }
+        });
+        assert_eq!(counter(&backend), Some(1000));
+    }
+
+    #[test]
+    fn test_atomically_reentrant() {
+        let backend = Backend::new();
+        let keys = [b"a".to_vec(), b"b".to_vec()];
+        // the inner call takes locks of keys the outer one already holds
+        let inner = backend.atomically(&keys, || {
+            backend.atomically(&[b"b".to_vec(), b"c".to_vec()], || 1)
+        });
+        assert_eq!(inner, 1);
+        // every lock was released, another thread can take them
+        thread::scope(|scope| {
+            scope.spawn(|| backend.atomically(&keys, || {}));
+        });
+    }
+}
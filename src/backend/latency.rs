@@ -0,0 +1,132 @@
+use dashmap::DashMap;
+use std::collections::VecDeque;
+
+// Number of samples kept per event, older ones are dropped.
+const HISTORY_LEN: usize = 160;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencySample {
+    // seconds since the unix epoch
+    pub time: i64,
+    // milliseconds
+    pub latency: u64,
+}
+
+#[derive(Debug, Default)]
+struct EventSeries {
+    samples: VecDeque<LatencySample>,
+    // highest latency ever recorded, it survives the samples falling out of the history
+    max: u64,
+}
+
+// Latency spikes recorded per event class ("command", "fast-command", "snapshot"), as reported by
+// the LATENCY command. Which spikes are worth recording is up to the caller.
+#[derive(Debug, Default)]
+pub struct LatencyMonitor {
+    events: DashMap<String, EventSeries>,
+}
+
+impl LatencyMonitor {
+    // Records that `event` took `latency` ms at `time`. Samples within the same second are merged
+    // into one keeping the highest latency.
+    pub fn record(&self, event: &str, time: i64, latency: u64) {
+        let mut series = self.events.entry(event.to_string()).or_default();
+        series.max = series.max.max(latency);
+        match series.samples.back_mut() {
+            Some(last) if last.time == time => last.latency = last.latency.max(latency),
+            _ => {
+                if series.samples.len() == HISTORY_LEN {
+                    series.samples.pop_front();
+                }
+                series.samples.push_back(LatencySample { time, latency });
+            }
+        }
+    }
+
+    // The latest sample and the maximum latency of every event with samples, by event name.
+    pub fn latest(&self) -> Vec<(String, LatencySample, u64)> {
+        let mut latest = self
+            .events
+            .iter()
+            .filter_map(|e| Some((e.key().clone(), *e.samples.back()?, e.max)))
+            .collect::<Vec<_>>();
+        latest.sort_by(|a, b| a.0.cmp(&b.0));
+        latest
+    }
+
+    // Samples of `event`, oldest first.
+    pub fn history(&self, event: &str) -> Vec<LatencySample> {
+        self.events
+            .get(event)
+            .map(|series| series.samples.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    // Forgets the samples of `events`, or of every event when empty. Returns how many events had
+    // samples.
+    pub fn reset(&self, events: &[String]) -> usize {
+        if events.is_empty() {
+            let count = self.events.len();
+            self.events.clear();
+            return count;
+        }
+        events
+            .iter()
+            .filter(|event| self.events.remove(event.as_str()).is_some())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_monitor() {
+        let monitor = LatencyMonitor::default();
+        monitor.record("command", 100, 20);
+        monitor.record("command", 100, 50);
+        monitor.record("command", 101, 30);
+        monitor.record("snapshot", 100, 250);
+
+        assert_eq!(
+            monitor.history("command"),
+            vec![
+                LatencySample {
+                    time: 100,
+                    latency: 50
+                },
+                LatencySample {
+                    time: 101,
+                    latency: 30
+                },
+            ]
+        );
+        let latest = monitor.latest();
+        assert_eq!(latest.len(), 2);
+        assert_eq!(
+            latest[0],
+            (
+                "command".to_string(),
+                LatencySample {
+                    time: 101,
+                    latency: 30
+                },
+                50
+            )
+        );
+
+        for time in 0..HISTORY_LEN as i64 + 10 {
+            monitor.record("snapshot", 200 + time, 1);
+        }
+        assert_eq!(monitor.history("snapshot").len(), HISTORY_LEN);
+        assert_eq!(monitor.latest()[1].2, 250);
+
+        assert_eq!(
+            monitor.reset(&["snapshot".to_string(), "fork".to_string()]),
+            1
+        );
+        assert_eq!(monitor.reset(&[]), 1);
+        assert!(monitor.latest().is_empty());
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+
+// Values with more elements than this are handed to the background thread, smaller ones are
+// cheaper to drop in place than to send over.
+pub const LAZYFREE_THRESHOLD: usize = 64;
+
+type Garbage = Box<dyn Send>;
+
+// Drops values on a dedicated thread so freeing huge collections never stalls a connection task.
+// The thread exits once the backend (and with it the sender) is dropped.
+#[derive(Debug)]
+pub struct LazyFree {
+    sender: Sender<Garbage>,
+    pending: Arc<AtomicUsize>,
+}
+
+impl LazyFree {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel::<Garbage>();
+        let pending = Arc::new(AtomicUsize::new(0));
+        let counter = pending.clone();
+        thread::Builder::new()
+            .name("lazyfree".to_string())
+            .spawn(move || {
+                for garbage in receiver {
+                    drop(garbage);
+                    counter.fetch_sub(1, Ordering::Relaxed);
+                }
+            })
+            .expect("failed to spawn the lazyfree thread");
+        Self { sender, pending }
+    }
+
+    // Frees `value` in the background when it holds more than LAZYFREE_THRESHOLD elements.
+    pub fn free<T: Send + 'static>(&self, value: T, len: usize) {
+        if len > LAZYFREE_THRESHOLD {
+            self.free_in_background(value);
+        }
+    }
+
+    // Frees `value` in the background whatever its size.
+    pub fn free_in_background<T: Send + 'static>(&self, value: T) {
+        self.pending.fetch_add(1, Ordering::Relaxed);
+        if let Err(mpsc::SendError(garbage)) = self.sender.send(Box::new(value)) {
+            // the thread is gone, free it here instead
+            drop(garbage);
+            self.pending.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    // number of values waiting to be freed
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for LazyFree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
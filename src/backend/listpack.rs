@@ -0,0 +1,572 @@
+use super::memory::MemoryUsage;
+use super::EncodingLimits;
+use crate::{BulkString, RespFrame};
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::mem::size_of;
+use std::ops::Range;
+
+// Strings laid out one after the other in a single buffer, the compact encoding of small hashes
+// and lists. Each entry is its length as a varint, its bytes, then the size of those two as a
+// varint written backwards, so that the entries can be walked from either end.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListPack {
+    data: Vec<u8>,
+    len: usize,
+}
+
+impl ListPack {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Bytes taken by the entries.
+    pub fn bytes(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn push_back(&mut self, entry: &[u8]) {
+        self.data.extend(encode(entry));
+        self.len += 1;
+    }
+
+    pub fn push_front(&mut self, entry: &[u8]) {
+        self.data.splice(..0, encode(entry));
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<Vec<u8>> {
+        let (payload, end) = self.entry_after(0)?;
+        let entry = self.data[payload].to_vec();
+        self.data.drain(..end);
+        self.len -= 1;
+        Some(entry)
+    }
+
+    pub fn pop_back(&mut self) -> Option<Vec<u8>> {
+        let (start, payload) = self.entry_before(self.data.len())?;
+        let entry = self.data[payload].to_vec();
+        self.data.truncate(start);
+        self.len -= 1;
+        Some(entry)
+    }
+
+    // Replaces the entry at `index`, which must exist.
+    pub fn set(&mut self, index: usize, entry: &[u8]) {
+        let mut start = 0;
+        for _ in 0..index {
+            start = self.entry_after(start).expect("index out of range").1;
+        }
+        let (_, end) = self.entry_after(start).expect("index out of range");
+        self.data.splice(start..end, encode(entry));
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            pack: self,
+            front: 0,
+            back: self.data.len(),
+        }
+    }
+
+    // The bytes of the entry starting at `start` and where the next one starts, None past the
+    // last entry.
+    fn entry_after(&self, start: usize) -> Option<(Range<usize>, usize)> {
+        if start >= self.data.len() {
+            return None;
+        }
+        let (len, header) = read_varint(self.data[start..].iter());
+        let payload = start + header..start + header + len;
+        let end = payload.end + varint_len(header + len);
+        Some((payload, end))
+    }
+
+    // Where the entry ending at `end` starts and its bytes, None before the first entry.
+    fn entry_before(&self, end: usize) -> Option<(usize, Range<usize>)> {
+        if end == 0 {
+            return None;
+        }
+        let (size, backlen) = read_varint(self.data[..end].iter().rev());
+        let start = end - backlen - size;
+        let (len, header) = read_varint(self.data[start..].iter());
+        Some((start, start + header..start + header + len))
+    }
+}
+
+pub struct Iter<'a> {
+    pack: &'a ListPack,
+    // the entries left are between these byte offsets
+    front: usize,
+    back: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        if self.front >= self.back {
+            return None;
+        }
+        let (payload, end) = self.pack.entry_after(self.front)?;
+        self.front = end;
+        Some(&self.pack.data[payload])
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        let (start, payload) = self.pack.entry_before(self.back)?;
+        self.back = start;
+        Some(&self.pack.data[payload])
+    }
+}
+
+impl MemoryUsage for ListPack {
+    fn memory_usage(&self, _samples: usize) -> usize {
+        size_of::<Self>() + self.data.capacity()
+    }
+}
+
+fn encode(entry: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(entry.len() + 4);
+    write_varint(&mut out, entry.len());
+    let size = out.len() + entry.len();
+    out.extend_from_slice(entry);
+    let backlen = out.len();
+    write_varint(&mut out, size);
+    out[backlen..].reverse();
+    out
+}
+
+// 7 bits per byte, lowest first, the high bit set on all bytes but the last
+fn write_varint(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        out.push((n & 0x7f) as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+// Reads a varint from the bytes, returning it with the number of bytes it took.
+fn read_varint<'a>(bytes: impl Iterator<Item = &'a u8>) -> (usize, usize) {
+    let mut n = 0;
+    let mut taken = 0;
+    for byte in bytes {
+        n |= ((byte & 0x7f) as usize) << (7 * taken);
+        taken += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    (n, taken)
+}
+
+fn varint_len(n: usize) -> usize {
+    let mut len = 1;
+    let mut n = n >> 7;
+    while n > 0 {
+        len += 1;
+        n >>= 7;
+    }
+    len
+}
+
+// A list, packed while it is small. Only bulk strings are packed, another element converts the
+// list to a quicklist, which it stays even once it shrinks back.
+#[derive(Debug, Clone)]
+pub enum ListValue {
+    ListPack(ListPack),
+    QuickList(VecDeque<RespFrame>),
+}
+
+impl Default for ListValue {
+    fn default() -> Self {
+        ListValue::ListPack(ListPack::default())
+    }
+}
+
+impl From<VecDeque<RespFrame>> for ListValue {
+    fn from(list: VecDeque<RespFrame>) -> Self {
+        ListValue::QuickList(list)
+    }
+}
+
+impl ListValue {
+    pub fn len(&self) -> usize {
+        match self {
+            ListValue::ListPack(pack) => pack.len(),
+            ListValue::QuickList(list) => list.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The encoding reported by OBJECT ENCODING.
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            ListValue::ListPack(_) => "listpack",
+            ListValue::QuickList(_) => "quicklist",
+        }
+    }
+
+    // Appends the values to the tail.
+    pub fn extend(&mut self, values: impl IntoIterator<Item = RespFrame>, limits: &EncodingLimits) {
+        for value in values {
+            self.push_back(value, limits);
+        }
+    }
+
+    pub fn push_back(&mut self, value: RespFrame, limits: &EncodingLimits) {
+        if let ListValue::ListPack(pack) = self {
+            if let RespFrame::BulkString(entry) = &value {
+                pack.push_back(entry);
+                if !limits.list_fits(pack) {
+                    self.convert();
+                }
+                return;
+            }
+            self.convert();
+        }
+        if let ListValue::QuickList(list) = self {
+            list.push_back(value);
+        }
+    }
+
+    pub fn push_front(&mut self, value: RespFrame, limits: &EncodingLimits) {
+        if let ListValue::ListPack(pack) = self {
+            if let RespFrame::BulkString(entry) = &value {
+                pack.push_front(entry);
+                if !limits.list_fits(pack) {
+                    self.convert();
+                }
+                return;
+            }
+            self.convert();
+        }
+        if let ListValue::QuickList(list) = self {
+            list.push_front(value);
+        }
+    }
+
+    pub fn pop_front(&mut self) -> Option<RespFrame> {
+        match self {
+            ListValue::ListPack(pack) => pack.pop_front().map(bulk),
+            ListValue::QuickList(list) => list.pop_front(),
+        }
+    }
+
+    pub fn pop_back(&mut self) -> Option<RespFrame> {
+        match self {
+            ListValue::ListPack(pack) => pack.pop_back().map(bulk),
+            ListValue::QuickList(list) => list.pop_back(),
+        }
+    }
+
+    // The elements from the head, borrowed unless they have to be unpacked.
+    pub fn iter(&self) -> Box<dyn DoubleEndedIterator<Item = Cow<'_, RespFrame>> + '_> {
+        match self {
+            ListValue::ListPack(pack) => Box::new(pack.iter().map(|e| Cow::Owned(bulk(e)))),
+            ListValue::QuickList(list) => Box::new(list.iter().map(Cow::Borrowed)),
+        }
+    }
+
+    // Packs a list that was built whole (by RESTORE) if it is small enough.
+    pub fn compact(self, limits: &EncodingLimits) -> Self {
+        let ListValue::QuickList(list) = &self else {
+            return self;
+        };
+        let mut pack = ListPack::default();
+        for value in list {
+            match value {
+                RespFrame::BulkString(entry) => pack.push_back(entry),
+                _ => return self,
+            }
+        }
+        if limits.list_fits(&pack) {
+            ListValue::ListPack(pack)
+        } else {
+            self
+        }
+    }
+
+    fn convert(&mut self) {
+        if let ListValue::ListPack(pack) = self {
+            *self = ListValue::QuickList(pack.iter().map(bulk).collect());
+        }
+    }
+}
+
+impl IntoIterator for ListValue {
+    type Item = RespFrame;
+    type IntoIter = std::vec::IntoIter<RespFrame>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            ListValue::ListPack(pack) => pack.iter().map(bulk).collect::<Vec<_>>().into_iter(),
+            ListValue::QuickList(list) => Vec::from(list).into_iter(),
+        }
+    }
+}
+
+impl MemoryUsage for ListValue {
+    fn memory_usage(&self, samples: usize) -> usize {
+        match self {
+            ListValue::ListPack(pack) => pack.memory_usage(samples),
+            ListValue::QuickList(list) => list.memory_usage(samples),
+        }
+    }
+}
+
+// A hash, packed as alternating fields and values while it is small. Only bulk string values
+// are packed, another value converts the hash to a hash table, which it stays even once it
+// shrinks back.
+#[derive(Debug, Clone)]
+pub enum HashValue {
+    ListPack(ListPack),
+    HashTable(HashMap<String, RespFrame>),
+}
+
+impl Default for HashValue {
+    fn default() -> Self {
+        HashValue::ListPack(ListPack::default())
+    }
+}
+
+impl From<HashMap<String, RespFrame>> for HashValue {
+    fn from(hash: HashMap<String, RespFrame>) -> Self {
+        HashValue::HashTable(hash)
+    }
+}
+
+impl HashValue {
+    pub fn len(&self) -> usize {
+        match self {
+            HashValue::ListPack(pack) => pack.len() / 2,
+            HashValue::HashTable(hash) => hash.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The encoding reported by OBJECT ENCODING.
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            HashValue::ListPack(_) => "listpack",
+            HashValue::HashTable(_) => "hashtable",
+        }
+    }
+
+    pub fn get(&self, field: &str) -> Option<RespFrame> {
+        match self {
+            HashValue::ListPack(pack) => pairs(pack)
+                .find(|(f, _)| *f == field.as_bytes())
+                .map(|(_, value)| bulk(value)),
+            HashValue::HashTable(hash) => hash.get(field).cloned(),
+        }
+    }
+
+    // Sets the field. Returns whether the field is new.
+    pub fn insert(&mut self, field: String, value: RespFrame, limits: &EncodingLimits) -> bool {
+        if let HashValue::ListPack(pack) = self {
+            if let RespFrame::BulkString(entry) = &value {
+                let index = pairs(pack).position(|(f, _)| f == field.as_bytes());
+                if limits.hash_fits(&field, &value) {
+                    match index {
+                        Some(i) => {
+                            pack.set(2 * i + 1, entry);
+                            return false;
+                        }
+                        None if pack.len() / 2 < limits.hash_entries => {
+                            pack.push_back(field.as_bytes());
+                            pack.push_back(entry);
+                            return true;
+                        }
+                        None => {}
+                    }
+                }
+            }
+            self.convert();
+        }
+        match self {
+            HashValue::HashTable(hash) => hash.insert(field, value).is_none(),
+            HashValue::ListPack(_) => unreachable!("converted above"),
+        }
+    }
+
+    // Packs a hash that was built whole (by RESTORE) if it is small enough.
+    pub fn compact(self, limits: &EncodingLimits) -> Self {
+        let HashValue::HashTable(hash) = &self else {
+            return self;
+        };
+        if hash.len() > limits.hash_entries
+            || !hash
+                .iter()
+                .all(|(field, value)| limits.hash_fits(field, value))
+        {
+            return self;
+        }
+        let mut pack = ListPack::default();
+        for (field, value) in hash {
+            if let RespFrame::BulkString(value) = value {
+                pack.push_back(field.as_bytes());
+                pack.push_back(value);
+            }
+        }
+        HashValue::ListPack(pack)
+    }
+
+    fn convert(&mut self) {
+        if let HashValue::ListPack(pack) = self {
+            let hash = pairs(pack)
+                .map(|(field, value)| (String::from_utf8_lossy(field).into_owned(), bulk(value)))
+                .collect();
+            *self = HashValue::HashTable(hash);
+        }
+    }
+}
+
+impl IntoIterator for HashValue {
+    type Item = (String, RespFrame);
+    type IntoIter = std::vec::IntoIter<(String, RespFrame)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let pairs = match self {
+            HashValue::ListPack(pack) => pairs(&pack)
+                .map(|(field, value)| (String::from_utf8_lossy(field).into_owned(), bulk(value)))
+                .collect::<Vec<_>>(),
+            HashValue::HashTable(hash) => hash.into_iter().collect(),
+        };
+        pairs.into_iter()
+    }
+}
+
+impl MemoryUsage for HashValue {
+    fn memory_usage(&self, samples: usize) -> usize {
+        match self {
+            HashValue::ListPack(pack) => pack.memory_usage(samples),
+            HashValue::HashTable(hash) => hash.memory_usage(samples),
+        }
+    }
+}
+
+// the fields and values of a packed hash
+fn pairs(pack: &ListPack) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut entries = pack.iter();
+    std::iter::from_fn(move || Some((entries.next()?, entries.next()?)))
+}
+
+fn bulk(entry: impl Into<Vec<u8>>) -> RespFrame {
+    BulkString::new(entry.into()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listpack() {
+        let mut pack = ListPack::default();
+        let long = vec![b'x'; 300];
+        for entry in [b"a".as_slice(), b"", &long, b"bc"] {
+            pack.push_back(entry);
+        }
+        assert_eq!(pack.len(), 4);
+        let entries = pack.iter().collect::<Vec<_>>();
+        assert_eq!(entries, vec![b"a".as_slice(), b"", &long, b"bc"]);
+        let reversed = pack.iter().rev().collect::<Vec<_>>();
+        assert_eq!(reversed, vec![b"bc".as_slice(), &long, b"", b"a"]);
+
+        pack.set(2, b"short");
+        assert_eq!(pack.iter().nth(2), Some(b"short".as_slice()));
+        assert_eq!(pack.pop_back(), Some(b"bc".to_vec()));
+        assert_eq!(pack.pop_front(), Some(b"a".to_vec()));
+        assert_eq!(pack.pop_front(), Some(b"".to_vec()));
+        assert_eq!(pack.pop_back(), Some(b"short".to_vec()));
+        assert_eq!(pack.pop_back(), None);
+        assert!(pack.is_empty());
+        assert_eq!(pack.bytes(), 0);
+    }
+
+    #[test]
+    fn test_list_conversion() {
+        let limits = EncodingLimits {
+            list_size: 3,
+            ..Default::default()
+        };
+        let mut list = ListValue::default();
+        list.extend((0..3).map(|i| bulk(i.to_string())), &limits);
+        assert_eq!(list.encoding(), "listpack");
+        assert_eq!(list.pop_back(), Some(bulk("2")));
+        list.extend([bulk("2"), bulk("3")], &limits);
+        assert_eq!(list.encoding(), "quicklist");
+        let elements = list.iter().map(Cow::into_owned).collect::<Vec<_>>();
+        assert_eq!(elements, ["0", "1", "2", "3"].map(bulk));
+
+        // a byte limit, 4 KiB for -1
+        let limits = EncodingLimits {
+            list_size: -1,
+            ..Default::default()
+        };
+        let mut list = ListValue::default();
+        list.push_back(bulk(vec![b'x'; 4000]), &limits);
+        assert_eq!(list.encoding(), "listpack");
+        list.push_back(bulk(vec![b'x'; 100]), &limits);
+        assert_eq!(list.encoding(), "quicklist");
+
+        let mut list = ListValue::default();
+        list.push_back(RespFrame::Integer(1), &limits);
+        assert_eq!(list.encoding(), "quicklist");
+        let list = ListValue::from(VecDeque::from([bulk("a")])).compact(&limits);
+        assert_eq!(list.encoding(), "listpack");
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), vec![bulk("a")]);
+    }
+
+    #[test]
+    fn test_hash_conversion() {
+        let limits = EncodingLimits {
+            hash_entries: 2,
+            hash_value: 8,
+            ..Default::default()
+        };
+        let mut hash = HashValue::default();
+        assert!(hash.insert("a".to_string(), bulk("1"), &limits));
+        assert!(hash.insert("b".to_string(), bulk("2"), &limits));
+        assert!(!hash.insert("a".to_string(), bulk("3"), &limits));
+        assert_eq!(hash.encoding(), "listpack");
+        assert_eq!(hash.len(), 2);
+        assert_eq!(hash.get("a"), Some(bulk("3")));
+        assert_eq!(hash.get("c"), None);
+
+        // too many fields
+        assert!(hash.insert("c".to_string(), bulk("4"), &limits));
+        assert_eq!(hash.encoding(), "hashtable");
+        assert_eq!(hash.get("b"), Some(bulk("2")));
+
+        // a value too long
+        let mut hash = HashValue::default();
+        hash.insert("a".to_string(), bulk("123456789"), &limits);
+        assert_eq!(hash.encoding(), "hashtable");
+        let hash = hash.compact(&limits);
+        assert_eq!(hash.encoding(), "hashtable");
+
+        let mut table = HashMap::new();
+        table.insert("a".to_string(), bulk("1"));
+        let hash = HashValue::from(table).compact(&limits);
+        assert_eq!(hash.encoding(), "listpack");
+        assert_eq!(
+            hash.into_iter().collect::<Vec<_>>(),
+            vec![("a".to_string(), bulk("1"))]
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use super::{Db, Storage, Value};
+use crate::RespFrame;
+use dashmap::DashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::mem::size_of;
+use std::sync::atomic::Ordering;
+
+// How many elements of a collection MEMORY USAGE measures when SAMPLES isn't given.
+pub const DEFAULT_SAMPLES: usize = 5;
+
+// Approximate number of bytes a stored value takes: its inline size plus the heap allocations it
+// owns, by capacity rather than length since that is what was actually allocated.
+pub trait MemoryUsage {
+    // Collections measure `samples` of their elements and extrapolate to the others, all of them
+    // when `samples` is 0.
+    fn memory_usage(&self, samples: usize) -> usize;
+}
+
+impl MemoryUsage for String {
+    fn memory_usage(&self, _samples: usize) -> usize {
+        size_of::<String>() + self.capacity()
+    }
+}
+
+impl MemoryUsage for RespFrame {
+    fn memory_usage(&self, samples: usize) -> usize {
+        let heap = match self {
+            RespFrame::SimpleString(s) => s.0.capacity(),
+            RespFrame::Error(e) => e.0.capacity(),
+            RespFrame::BulkString(s) => s.0.capacity(),
+            RespFrame::VerbatimString(s) => s.data.capacity(),
+            RespFrame::Array(a) => {
+                sampled(a.0.len(), a.0.iter(), samples, |v| v.memory_usage(samples))
+            }
+            RespFrame::Push(p) => {
+                sampled(p.0.len(), p.0.iter(), samples, |v| v.memory_usage(samples))
+            }
+            RespFrame::Set(s) => {
+                sampled(s.0.len(), s.0.iter(), samples, |v| v.memory_usage(samples))
+            }
+            RespFrame::Map(m) => sampled(m.0.len(), m.0.iter(), samples, |(k, v)| {
+                k.memory_usage(samples) + v.memory_usage(samples)
+            }),
+            RespFrame::Integer(_)
+            | RespFrame::Null(_)
+            | RespFrame::Boolean(_)
+            | RespFrame::Double(_) => 0,
+        };
+        size_of::<RespFrame>() + heap
+    }
+}
+
+impl MemoryUsage for HashSet<String> {
+    fn memory_usage(&self, samples: usize) -> usize {
+        size_of::<Self>()
+            + spare_slots::<String>(self.capacity(), self.len())
+            + sampled(self.len(), self.iter(), samples, |m| {
+                m.memory_usage(samples)
+            })
+    }
+}
+
+impl MemoryUsage for VecDeque<RespFrame> {
+    fn memory_usage(&self, samples: usize) -> usize {
+        size_of::<Self>()
+            + (self.capacity() - self.len()) * size_of::<RespFrame>()
+            + sampled(self.len(), self.iter(), samples, |v| {
+                v.memory_usage(samples)
+            })
+    }
+}
+
+impl MemoryUsage for HashMap<String, RespFrame> {
+    fn memory_usage(&self, samples: usize) -> usize {
+        size_of::<Self>()
+            + spare_slots::<(String, RespFrame)>(self.capacity(), self.len())
+            + sampled(self.len(), self.iter(), samples, |(field, value)| {
+                field.memory_usage(samples) + value.memory_usage(samples)
+            })
+    }
+}
+
+impl MemoryUsage for Value {
+    fn memory_usage(&self, samples: usize) -> usize {
+        match self {
+            Value::String(v) => v.memory_usage(samples),
+            Value::Hash(v) => v.memory_usage(samples),
+            Value::List(v) => v.memory_usage(samples),
+            Value::Set(v) => v.memory_usage(samples),
+            Value::ZSet(v) => v.memory_usage(samples),
+            Value::Stream(v) => v.memory_usage(samples),
+        }
+    }
+}
+
+// Sums `size` over the first `samples` of the `len` items (all of them when 0) and scales the
+// total up to all `len` items.
+pub(super) fn sampled<T>(
+    len: usize,
+    items: impl Iterator<Item = T>,
+    samples: usize,
+    size: impl Fn(T) -> usize,
+) -> usize {
+    let measured = if samples == 0 { len } else { samples.min(len) };
+    if measured == 0 {
+        return 0;
+    }
+    let total = items.take(measured).map(size).sum::<usize>();
+    total * len / measured
+}
+
+// unused slots of a hash table holding `len` of `capacity` entries, plus a control byte per slot
+pub(super) fn spare_slots<T>(capacity: usize, len: usize) -> usize {
+    capacity.saturating_sub(len) * size_of::<T>() + capacity
+}
+
+impl Db {
+    // Estimated bytes taken by the key and its value, None if the key doesn't exist.
+    pub fn memory_usage(&self, key: &[u8], samples: usize) -> Option<usize> {
+        let value = self.keyspace.get(key)?.memory_usage(samples);
+        Some(value + self.key_overhead(key))
+    }
+
+    // Bytes taken by the key itself, which is held by the keyspace and again by the expiry and
+    // access time maps.
+    pub(super) fn key_overhead(&self, key: &[u8]) -> usize {
+        let copies = 1
+            + self.keyspace.expire_at(key).is_some() as usize
+            + self.access.contains_key(key) as usize;
+        copies * (size_of::<Vec<u8>>() + key.len())
+    }
+
+    // Estimated bytes taken by all the keys and values, sampling collections as MEMORY USAGE
+    // does by default. A running total, so it costs nothing to ask.
+    pub fn dataset_bytes(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    // Measures the key again after it was written, see resize.
+    pub(super) fn account(&self, key: &[u8]) {
+        match self.memory_usage(key, DEFAULT_SAMPLES) {
+            Some(size) => self.resize(key, size),
+            None => self.forget(key),
+        }
+    }
+
+    // Records `size` as the estimated bytes taken by the key, adjusting the running total. Called
+    // after every write of the key, which gets a new version.
+    pub(super) fn resize(&self, key: &[u8], size: usize) {
+        self.bump_version(key);
+        let old = self.sizes.insert(key.to_vec(), size).unwrap_or_default();
+        self.used.fetch_add(size, Ordering::Relaxed);
+        self.used.fetch_sub(old, Ordering::Relaxed);
+    }
+
+    // Takes the key out of the running total, once it is removed.
+    pub(super) fn forget(&self, key: &[u8]) {
+        self.drop_version(Some(key));
+        if let Some((_, size)) = self.sizes.remove(key) {
+            self.used.fetch_sub(size, Ordering::Relaxed);
+        }
+    }
+
+    // Bytes taken by the hash tables of the keyspace itself, not counting keys and values: the
+    // keyspace (with its access, size and version maps) on one hand and the expiry map on the
+    // other.
+    pub fn overhead(&self) -> (usize, usize) {
+        let (values, expires) = self.keyspace.overhead();
+        let maps = table(&self.access) + table(&self.sizes) + table(&self.versions);
+        (values + maps, expires)
+    }
+}
+
+// Bytes taken by a hash map itself, with its unused slots.
+pub(super) fn table<K: Eq + Hash, V>(map: &DashMap<K, V>) -> usize {
+    size_of::<DashMap<K, V>>() + spare_slots::<(K, V)>(map.capacity(), 0)
+}
+
+// Resident set size of the process in bytes, 0 where it can't be read.
+pub fn used_memory_rss() -> usize {
+    const PAGE_SIZE: usize = 4096;
+    std::fs::read_to_string("/proc/self/statm")
+        .ok()
+        .and_then(|statm| statm.split_whitespace().nth(1)?.parse::<usize>().ok())
+        .map_or(0, |pages| pages * PAGE_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, BulkString};
+    use anyhow::Result;
+
+    #[test]
+    fn test_sampled() {
+        let items = [1, 2, 3, 4, 5, 6];
+        assert_eq!(sampled(6, items.iter(), 0, |v| *v), 21);
+        // the first two average 1.5, times 6
+        assert_eq!(sampled(6, items.iter(), 2, |v| *v), 9);
+        assert_eq!(sampled(0, items.iter().take(0), 5, |v| *v), 0);
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let db = Db::default();
+        assert_eq!(db.memory_usage(b"missing", 0), None);
+
+        db.keyspace.insert(
+            b"small".to_vec(),
+            Value::String(BulkString::from("v").into()),
+        );
+        db.keyspace.insert(
+            b"large".to_vec(),
+            Value::String(BulkString::from("v".repeat(1000)).into()),
+        );
+        let short = db.memory_usage(b"small", 0).expect("exists");
+        let long = db.memory_usage(b"large", 0).expect("exists");
+        assert!(long >= short + 999);
+
+        let list = (0..100)
+            .map(|_| BulkString::from("element").into())
+            .collect::<VecDeque<RespFrame>>();
+        db.keyspace
+            .insert(b"list".to_vec(), Value::List(list.into()));
+        let all = db.memory_usage(b"list", 0).expect("exists");
+        let estimate = db.memory_usage(b"list", 5).expect("exists");
+        assert!(all > 100 * size_of::<RespFrame>());
+        // the sampled elements are as large as the others
+        assert_eq!(all, estimate);
+
+        // the keys were inserted behind the running total's back
+        assert_eq!(db.dataset_bytes(), 0);
+        for key in ["small", "large", "list"].map(str::as_bytes) {
+            db.account(key);
+        }
+        assert_eq!(db.dataset_bytes(), short + long + all);
+        db.del(b"large");
+        assert_eq!(db.dataset_bytes(), short + all);
+    }
+
+    #[test]
+    fn test_running_total() -> Result<()> {
+        let backend = Backend::new();
+        let total = |backend: &Backend| {
+            backend
+                .keys()
+                .iter()
+                .filter_map(|key| backend.memory_usage(key, DEFAULT_SAMPLES))
+                .sum::<usize>()
+        };
+        backend.set(b"string".to_vec(), BulkString::from("v").into());
+        backend.hset(
+            b"hash".to_vec(),
+            "f".to_string(),
+            BulkString::from("v").into(),
+        )?;
+        backend.rpush("list".to_string(), vec![BulkString::from("v").into()])?;
+        let before = backend.dataset_bytes();
+        assert!(before > 0);
+
+        backend.rpush(
+            "list".to_string(),
+            vec![BulkString::from("x".repeat(1000)).into()],
+        )?;
+        // less the few bytes the packed list had spare
+        assert!(backend.dataset_bytes() >= before + 990);
+        backend.set(b"string".to_vec(), BulkString::from("v").into());
+        assert_eq!(backend.dataset_bytes(), total(&backend));
+
+        backend.del(b"list");
+        backend.unlink(b"hash");
+        assert_eq!(backend.dataset_bytes(), total(&backend));
+        backend.flush(false);
+        assert_eq!(backend.dataset_bytes(), 0);
+        Ok(())
+    }
+}
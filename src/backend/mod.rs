@@ -1,96 +1,3459 @@
-use crate::RespFrame;
-use dashmap::{DashMap, DashSet};
-use std::ops::Deref;
-use std::sync::Arc;
+mod access;
+mod aof;
+mod bitops;
+mod blocking;
+mod clients;
+mod clock;
+mod cluster;
+mod config;
+mod dump;
+mod eviction;
+mod expire;
+mod functions;
+mod geo;
+mod hll;
+mod intset;
+mod keylock;
+mod latency;
+mod lazyfree;
+mod listpack;
+mod memory;
+mod monitor;
+mod notify;
+mod persistence;
+mod pubsub;
+mod rdb;
+mod replication;
+mod scan;
+mod scripts;
+mod stats;
+mod storage;
+mod stream;
+mod value;
+mod zset;
 
-#[derive(Debug, Clone)]
-pub struct Backend(Arc<BackendInner>);
+use crate::{cmd::CommandRegistry, BulkString, RespArray, RespFrame, RespPush};
+use dashmap::DashMap;
+use rand::seq::IteratorRandom;
+use rand::Rng;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::future::Future;
+use std::io::{self, Write};
+use std::ops::{Bound, Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::{watch, RwLock as AsyncRwLock};
+use tokio::sync::{RwLockReadGuard as AsyncReadGuard, RwLockWriteGuard as AsyncWriteGuard};
+
+pub use access::{KeyAccess, LFU_INIT_VAL};
+pub use aof::AppendLog;
+use aof::{LogCommand, LogPolicy};
+pub use bitops::{BitOp, BitRange, BitUnit};
+pub use blocking::{BlockKey, Blocked, Blocking};
+pub use clients::{ClientInfo, ClientPause, ClientRegistry, PauseMode};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use cluster::{key_hash_slot, Cluster, ClusterNode, ClusterRedirect, CLUSTER_SLOTS};
+pub use config::{ConfigError, ConfigValues, ServerConfig, MAXMEMORY_POLICIES};
+pub use dump::DumpError;
+pub use eviction::EvictionPolicy;
+pub use expire::{ExpireCondition, KeyExpiry};
+pub use functions::{
+    FunctionError, FunctionInfo, FunctionLibraries, FunctionLibrary, FUNCTION_FLAGS,
+};
+pub use geo::{
+    geo_distance, geohash_decode, geohash_encode, GeoError, GeoMatch, GeoOrder, GeoOrigin,
+    GeoQuery, GeoShape, GeoUnit,
+};
+pub use hll::{HllError, HyperLogLog};
+pub use intset::SetValue;
+use keylock::KeyLocks;
+pub use latency::{LatencyMonitor, LatencySample};
+use lazyfree::LazyFree;
+pub use listpack::{HashValue, ListPack, ListValue};
+pub use memory::{used_memory_rss, MemoryUsage, DEFAULT_SAMPLES};
+pub use monitor::{monitor_line, MonitorFeed};
+pub use notify::{NotifyClass, NotifyFlags};
+pub use persistence::SaveStatus;
+pub use pubsub::{PubSub, PubSubMessage, Subscription};
+pub use rdb::DatasetCopy;
+pub use replication::{MasterInfo, ReplicaInfo, ReplicaStream, Replication};
+pub use scan::glob_match;
+pub use scripts::{ScriptCache, ScriptRun};
+pub use stats::{error_message, CommandStats, Stats};
+pub use storage::{Keyspace, MemoryStorage, ShardedStorage, Storage};
+pub use stream::{
+    Consumer, ConsumerGroup, ConsumerInfo, GroupEntry, GroupInfo, PendingEntry, Stream,
+    StreamError, StreamFields, StreamId, StreamInfo, TrimOptions, TrimStrategy, XAddId,
+};
+pub use value::{EncodingLimits, Value, WrongType};
+pub use zset::{Aggregate, LexBound, ZAddFlags, ZAddOutcome, ZRangeSpec, ZSet};
+
+// how often a scheduled BGSAVE checks whether the running save finished
+const SAVE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+// strings up to this length are reported as "embstr", longer ones as "raw"
+const EMBSTR_SIZE_LIMIT: usize = 44;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOp {
+    Inter,
+    Union,
+    Diff,
+}
+
+// A handle on the shared server state. Every connection owns its own handle, which remembers
+// the database the connection selected and derefs to it, so commands always operate on the
+// connection's current database.
+#[derive(Debug)]
+pub struct Backend {
+    inner: Arc<BackendInner>,
+    // the selected database index
+    db: AtomicUsize,
+    // the database in `dbs` behind that index when it was last resolved, see refresh_db
+    slot: AtomicUsize,
+    // id of the client owning this handle in the registry, 0 for handles not tied to one
+    client_id: u64,
+}
 
 #[derive(Debug)]
 pub struct BackendInner {
-    pub(crate) map: DashMap<String, RespFrame>,
-    pub(crate) hset: DashMap<String, DashSet<String>>,
-    pub(crate) hmap: DashMap<String, DashMap<String, RespFrame>>,
+    pub(crate) dbs: Vec<Db>,
+    // the position in `dbs` of the database behind each index, permuted by SWAPDB
+    pub(crate) slots: RwLock<Vec<usize>>,
+    // the clients blocked on list pops, stream reads and WAIT, woken up by the writes
+    pub(crate) blocking: Blocking,
+    pub(crate) lazy_free: LazyFree,
+    // whether expired keys are removed in the background, toggled by DEBUG SET-ACTIVE-EXPIRE
+    pub(crate) active_expire: AtomicBool,
+    pub(crate) stats: Stats,
+    pub(crate) config: ServerConfig,
+    pub(crate) clock: Arc<dyn Clock>,
+    // set once SHUTDOWN succeeded, the server then stops accepting and closes every connection
+    pub(crate) shutdown: watch::Sender<bool>,
+    pub(crate) save_status: SaveStatus,
+    pub(crate) append_log: AppendLog,
+    // the epoch of the next snapshot of the databases, see copy_dataset
+    pub(crate) snapshot_epoch: AtomicU64,
+    pub(crate) replication: Replication,
+    pub(crate) cluster: Cluster,
+    pub(crate) clients: ClientRegistry,
+    // set by CLIENT PAUSE, cleared by CLIENT UNPAUSE
+    pub(crate) pause: watch::Sender<Option<ClientPause>>,
+    pub(crate) latency: LatencyMonitor,
+    pub(crate) monitors: MonitorFeed,
+    pub(crate) pubsub: PubSub,
+    // held shared by every command while it runs and exclusively by EXEC, see shared_access
+    pub(crate) exec_lock: AsyncRwLock<()>,
+    // held by the commands running with shared access on the keys they write, see atomically
+    pub(crate) key_locks: KeyLocks,
+    pub(crate) scripts: ScriptCache,
+    pub(crate) functions: FunctionLibraries,
+    pub(crate) running_script: Mutex<Option<Arc<ScriptRun>>>,
+    // the commands the embedding application added to the builtin ones
+    pub(crate) commands: CommandRegistry,
+}
+
+// A logical database, selected with SELECT.
+#[derive(Debug, Default)]
+pub struct Db {
+    // the values and the absolute expiry times of volatile keys
+    pub(crate) keyspace: Keyspace,
+    // when each key was last read or written and how often
+    pub(crate) access: DashMap<Vec<u8>, KeyAccess>,
+    // estimated bytes taken by each key and its value, kept up to date on every write
+    pub(crate) sizes: DashMap<Vec<u8>, usize>,
+    // the sum of `sizes`
+    pub(crate) used: AtomicUsize,
+    // the version of each key, see Db::version
+    pub(crate) versions: DashMap<Vec<u8>, u64>,
+    // the last version handed out
+    pub(crate) last_version: AtomicU64,
+    // the version of the last key removed, which missing keys have
+    pub(crate) removed_version: AtomicU64,
 }
 
 impl Deref for Backend {
-    type Target = BackendInner;
+    type Target = Db;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.inner.dbs[self.slot.load(Ordering::Relaxed)]
+    }
+}
+
+// the clone starts out with the same database selected
+impl Clone for Backend {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            db: AtomicUsize::new(self.selected_db()),
+            slot: AtomicUsize::new(self.slot.load(Ordering::Relaxed)),
+            client_id: self.client_id,
+        }
     }
 }
 
 impl Default for Backend {
     fn default() -> Self {
-        Self(Arc::new(BackendInner::default()))
+        Self::new()
     }
 }
 
-impl Default for BackendInner {
-    fn default() -> Self {
-        Self {
-            map: DashMap::new(),
-            hset: DashMap::new(),
-            hmap: DashMap::new(),
+// A value of the keyspace as the type a command works on, see Backend::get_as.
+type TypedRef<'a, T> = <Keyspace as Storage>::Ref<'a, T>;
+
+// The key a pop was served from and the elements it took.
+pub type Popped = (Vec<u8>, Vec<RespFrame>);
+
+// The key a sorted set pop was served from and the members it took, with their scores.
+pub type ZPopped = (Vec<u8>, Vec<(String, f64)>);
+
+// Like TypedRef, for writing. Dropping it accounts for the new size of the value.
+struct TypedRefMut<'a, T: MemoryUsage> {
+    value: <Keyspace as Storage>::RefMut<'a, T>,
+    db: &'a Db,
+}
+
+impl<T: MemoryUsage> Deref for TypedRefMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: MemoryUsage> DerefMut for TypedRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: MemoryUsage> Drop for TypedRefMut<'_, T> {
+    fn drop(&mut self) {
+        let key = self.value.key();
+        let size = self.value.memory_usage(DEFAULT_SAMPLES) + self.db.key_overhead(key);
+        self.db.resize(key, size);
+    }
+}
+
+impl Db {
+    pub fn exists(&self, key: &[u8]) -> bool {
+        self.keyspace.contains_key(key)
+    }
+
+    // Removes the key whatever the type of its value. Returns whether the key existed.
+    pub fn del(&self, key: &[u8]) -> bool {
+        self.take(key).is_some()
+    }
+
+    // Like del, handing the value over to be freed by the caller.
+    fn take(&self, key: &[u8]) -> Option<Value> {
+        self.keyspace.remove_expire(key);
+        self.access.remove(key);
+        self.forget(key);
+        self.keyspace.remove(key)
+    }
+
+    // Serializes the value at `key` in the DUMP format, None if the key doesn't exist.
+    pub fn dump(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let value = self.keyspace.get(key)?.clone();
+        Some(value.serialize())
+    }
+
+    // Every key, whatever the type of its value.
+    pub fn keys(&self) -> Vec<Vec<u8>> {
+        self.keyspace.keys()
+    }
+
+    // Number of keys and of keys with an expiry, as reported in the keyspace section of INFO.
+    pub fn key_count(&self) -> (usize, usize) {
+        (self.keyspace.len(), self.keyspace.volatile_len())
+    }
+
+    // A number that grows every time the key is written, gets or loses an expiry, or is
+    // removed: a key whose version is the same as before wasn't modified in between, which is
+    // what WATCH needs. A missing key has the version of the last key removed from the database,
+    // so that a key removed and created again has another version, at the cost of missing keys
+    // looking modified whenever any key is removed.
+    pub fn version(&self, key: &[u8]) -> u64 {
+        match self.versions.get(key) {
+            Some(version) => *version,
+            None => self.removed_version.load(Ordering::Acquire),
+        }
+    }
+
+    // Gives the key a new version, once it was modified.
+    pub(super) fn bump_version(&self, key: &[u8]) {
+        let version = self.last_version.fetch_add(1, Ordering::AcqRel) + 1;
+        self.versions.insert(key.to_vec(), version);
+    }
+
+    // Drops the version of a key once removed, of every key when None. Missing keys all have
+    // the version of the last removal, deleting a key that was already missing changes nothing.
+    pub(super) fn drop_version(&self, key: Option<&[u8]>) {
+        match key {
+            Some(key) if self.versions.remove(key).is_none() => return,
+            Some(_) => {}
+            None => self.versions.clear(),
+        }
+        let version = self.last_version.fetch_add(1, Ordering::AcqRel) + 1;
+        self.removed_version.store(version, Ordering::Release);
+    }
+
+    // Removes the key if its expiry time is at or before `now`. Returns its value if it did.
+    fn remove_expired(&self, key: &[u8], now: i64) -> Option<Value> {
+        self.keyspace.remove_expire_if(key, |when| when <= now)?;
+        self.access.remove(key);
+        self.forget(key);
+        self.keyspace.remove(key)
+    }
+
+    // Removes the key if it holds a collection left empty, see Value::is_empty.
+    fn remove_if_empty(&self, key: &[u8]) {
+        if self
+            .keyspace
+            .remove_if(key, |value| value.is_empty())
+            .is_some()
+        {
+            self.forget(key);
         }
     }
 }
 
 impl Backend {
     pub fn new() -> Self {
-        Self::default()
+        Self::with_config(ConfigValues::default())
     }
 
-    pub fn get(&self, key: &str) -> Option<RespFrame> {
-        self.map.get(key).map(|v| v.value().clone())
+    // A backend with `config.databases` logical databases (at least one), database 0 selected.
+    pub fn with_config(config: ConfigValues) -> Self {
+        Self::with_clock(config, Arc::new(SystemClock))
     }
 
-    pub fn set(&self, key: String, value: RespFrame) {
-        self.map.insert(key, value);
+    // Like with_config, telling the time with `clock` instead of the system clock.
+    pub fn with_clock(config: ConfigValues, clock: Arc<dyn Clock>) -> Self {
+        let count = config.databases.max(1);
+        let cluster = Cluster::new(config.port);
+        let inner = BackendInner {
+            dbs: (0..count).map(|_| Db::default()).collect(),
+            slots: RwLock::new((0..count).collect()),
+            blocking: Blocking::default(),
+            lazy_free: LazyFree::new(),
+            active_expire: AtomicBool::new(true),
+            stats: Stats::new(),
+            config: ServerConfig::new(config),
+            shutdown: watch::channel(false).0,
+            save_status: SaveStatus::new(clock.now().as_secs() as i64),
+            append_log: AppendLog::new(clock.now().as_secs() as i64),
+            snapshot_epoch: AtomicU64::new(0),
+            replication: Replication::new(),
+            cluster,
+            clients: ClientRegistry::default(),
+            pause: watch::channel(None).0,
+            latency: LatencyMonitor::default(),
+            monitors: MonitorFeed::new(),
+            pubsub: PubSub::default(),
+            exec_lock: AsyncRwLock::new(()),
+            key_locks: KeyLocks::new(),
+            scripts: ScriptCache::default(),
+            functions: FunctionLibraries::default(),
+            running_script: Mutex::new(None),
+            commands: CommandRegistry::default(),
+            clock,
+        };
+        Self {
+            inner: Arc::new(inner),
+            db: AtomicUsize::new(0),
+            slot: AtomicUsize::new(0),
+            client_id: 0,
+        }
     }
 
-    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
-        self.hmap
-            .get(key)
-            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+    // Registers a new client and returns the handle of its connection, with database 0
+    // selected. `addr` and `laddr` are the remote and local addresses of the connection.
+    pub fn connect(&self, addr: String, laddr: String) -> Self {
+        let client_id = self.clients().register(addr, laddr, self.now_ms());
+        Self {
+            inner: self.inner.clone(),
+            db: AtomicUsize::new(0),
+            slot: AtomicUsize::new(self.slots()[0]),
+            client_id,
+        }
     }
 
-    pub fn hset(&self, key: String, field: String, value: RespFrame) {
-        let hmap = self.hmap.entry(key).or_default();
-        hmap.insert(field, value);
+    // Removes the client owning this handle from the registry, dropping its subscriptions.
+    pub fn disconnect(&self) {
+        self.pubsub().detach(self.client_id);
+        self.replication().detach(self.client_id);
+        self.clients().unregister(self.client_id);
     }
 
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.clone())
+    pub fn client_id(&self) -> u64 {
+        self.client_id
     }
 
-    // Inserts a key into the set. Returns true if the key was not already in the set.
-    pub fn sadd(&self, key: impl Into<String>, field: impl Into<String>) -> bool {
-        self.hset
-            .entry(key.into())
-            .or_default()
-            .insert(field.into())
+    pub fn clients(&self) -> &ClientRegistry {
+        &self.inner.clients
     }
 
-    // Checks if the set contains a specific key.
-    pub fn sismember(&self, key: &str, member: &str) -> bool {
-        self.hset.get(key).map_or(false, |v| v.contains(member))
+    // Records in the registry that the client owning this handle just sent a request: it isn't
+    // idle while the request runs, however long that takes.
+    pub fn record_client_interaction(&self) {
+        let now = self.now_ms();
+        self.clients()
+            .update(self.client_id, |info| info.last_interaction = now);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use anyhow::Result;
+    // Records in the registry that the client owning this handle just ran `command`.
+    pub fn record_client_command(&self, command: &str) {
+        let now = self.now_ms();
+        let db = self.selected_db();
+        self.clients().update(self.client_id, |info| {
+            info.last_command = command.to_string();
+            info.last_interaction = now;
+            info.db = db;
+        });
+    }
 
-    #[test]
-    fn test_sadd() -> Result<()> {
-        let backend = Backend::new();
-        let result = backend.sadd("myset", "Hello");
-        assert!(result);
-        let result = backend.sadd("myset", "Hello");
-        assert!(!result);
-        Ok(())
+    // Closes the connections idle for longer than the timeout parameter, if set, returning how
+    // many. Subscribers, blocked clients, monitors and replicas are left alone, only waiting
+    // for the server to send them something.
+    pub fn close_idle_clients(&self) -> usize {
+        let timeout = self.config().read().timeout;
+        if timeout == 0 {
+            return 0;
+        }
+        let oldest = self.now_ms() - (timeout as i64).saturating_mul(1000);
+        self.clients()
+            .list()
+            .into_iter()
+            .filter(|info| info.last_interaction < oldest && !info.blocked && !info.monitor)
+            .filter(|info| {
+                !self.pubsub().is_subscribed(info.id) && !self.replication().is_replica(info.id)
+            })
+            .filter(|info| self.clients().kill(info.id))
+            .count()
+    }
+
+    // Checks for idle connections every second until the server shuts down, see
+    // close_idle_clients.
+    pub async fn run_client_timeouts(&self) {
+        let mut shutdown = self.shutdown_signal();
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+                _ = shutdown.changed() => return,
+            }
+            self.close_idle_clients();
+        }
+    }
+
+    // Holds back the commands `mode` covers from every client for `timeout` milliseconds. A
+    // pause already in effect takes the new mode and keeps its end if that is later.
+    pub fn pause_clients(&self, mode: PauseMode, timeout: i64) {
+        let until = self.now_ms().saturating_add(timeout);
+        self.inner.pause.send_modify(|pause| {
+            let until = pause.map_or(until, |p| p.until.max(until));
+            *pause = Some(ClientPause { mode, until });
+        });
+    }
+
+    pub fn unpause_clients(&self) {
+        self.inner.pause.send_replace(None);
+    }
+
+    // Waits until a command, writing or not, may run, which is right away unless clients
+    // are paused.
+    pub async fn wait_unpaused(&self, write: bool) {
+        let mut pause = self.inner.pause.subscribe();
+        loop {
+            let remaining = match *pause.borrow_and_update() {
+                Some(ClientPause { mode, until }) if write || mode == PauseMode::All => {
+                    until - self.now_ms()
+                }
+                _ => return,
+            };
+            if remaining <= 0 {
+                return;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(remaining as u64)) => {}
+                _ = pause.changed() => {}
+            }
+        }
+    }
+
+    pub fn monitors(&self) -> &MonitorFeed {
+        &self.inner.monitors
+    }
+
+    // Shows the command `args` about to be run by this handle's client to every monitor.
+    pub fn feed_monitors(&self, args: &[Vec<u8>]) {
+        let addr = self
+            .clients()
+            .get(self.client_id)
+            .map_or_else(String::new, |info| info.addr);
+        let line = monitor_line(self.now(), self.selected_db(), &addr, args);
+        self.monitors().publish(line);
+    }
+
+    // Held while a command runs, any number of commands run at the same time. EXEC and scripts
+    // take exclusive_access instead so that no other client's command interleaves with them.
+    // None once a script has been running for longer than busy-reply-threshold, the command
+    // is then answered with a BUSY error instead of waiting for it.
+    pub async fn shared_access(&self) -> Option<AsyncReadGuard<'_, ()>> {
+        self.unless_busy(self.inner.exec_lock.read()).await
+    }
+
+    pub async fn exclusive_access(&self) -> Option<AsyncWriteGuard<'_, ()>> {
+        self.unless_busy(self.inner.exec_lock.write()).await
+    }
+
+    // Runs `f` with no other command writing to `keys` meanwhile, so that a command reading
+    // some of them before writing others (SMOVE, COPY, the STORE variants) sees none of them
+    // change in between. Every write run with shared access goes through it, holders of
+    // exclusive access need not. Not reentrant, `f` must not call it again.
+    pub fn atomically<T>(&self, keys: &[Vec<u8>], f: impl FnOnce() -> T) -> T {
+        let _locked = self.inner.key_locks.lock(keys);
+        f()
+    }
+
+    async fn unless_busy<T>(&self, access: impl Future<Output = T>) -> Option<T> {
+        tokio::pin!(access);
+        loop {
+            // a script may start while waiting for another one or for a transaction
+            let wait = self.busy_in().unwrap_or(Duration::from_millis(10));
+            tokio::select! {
+                biased;
+                guard = &mut access => return Some(guard),
+                _ = tokio::time::sleep(wait) => {}
+            }
+            if self.busy_in() == Some(Duration::ZERO) {
+                return None;
+            }
+        }
+    }
+
+    // How long until the running script, if any, passes busy-reply-threshold.
+    fn busy_in(&self) -> Option<Duration> {
+        let run = self.running_script()?;
+        let threshold = self.config().read().busy_reply_threshold as i64;
+        let remaining = threshold - (self.now_ms() - run.started());
+        Some(Duration::from_millis(remaining.max(0) as u64))
+    }
+
+    // Records that a script starts running, until end_script.
+    pub fn start_script(&self) -> Arc<ScriptRun> {
+        let run = Arc::new(ScriptRun::new(self.now_ms()));
+        *self.lock_running_script() = Some(run.clone());
+        run
+    }
+
+    pub fn end_script(&self) {
+        *self.lock_running_script() = None;
+    }
+
+    // The script running, for SCRIPT KILL to stop it.
+    pub fn running_script(&self) -> Option<Arc<ScriptRun>> {
+        self.lock_running_script().clone()
+    }
+
+    fn lock_running_script(&self) -> std::sync::MutexGuard<'_, Option<Arc<ScriptRun>>> {
+        self.inner
+            .running_script
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn commands(&self) -> &CommandRegistry {
+        &self.inner.commands
+    }
+
+    pub fn scripts(&self) -> &ScriptCache {
+        &self.inner.scripts
+    }
+
+    pub fn functions(&self) -> &FunctionLibraries {
+        &self.inner.functions
+    }
+
+    pub fn pubsub(&self) -> &PubSub {
+        &self.inner.pubsub
+    }
+
+    // The protocol version this handle's client speaks, 2 until it switches with HELLO.
+    pub fn protocol(&self) -> u8 {
+        self.clients()
+            .get(self.client_id)
+            .map_or(2, |info| info.resp)
+    }
+
+    pub fn set_protocol(&self, resp: u8) {
+        self.clients()
+            .update(self.client_id, |info| info.resp = resp);
+    }
+
+    // A pub/sub message or confirmation the way this handle's client reads it, a push frame
+    // in RESP3 and an array in RESP2.
+    pub fn pubsub_frame(&self, message: PubSubMessage) -> RespFrame {
+        if self.protocol() >= 3 {
+            RespPush::new(message).into()
+        } else {
+            RespArray::new(message).into()
+        }
+    }
+
+    // Whether this handle's client is in subscriber mode: a RESP2 connection with active
+    // subscriptions, which can then only manage them. RESP3 mixes pushes with replies freely.
+    pub fn subscriber_mode(&self) -> bool {
+        self.protocol() < 3 && self.pubsub().is_subscribed(self.client_id)
+    }
+
+    // Publishes keyspace event `event` of `class` on `key` of the selected database, when
+    // notify-keyspace-events enables the class: to __keyspace@<db>__:<key> with the event as
+    // message, and to __keyevent@<db>__:<event> with the key as message.
+    pub fn notify_keyspace_event(&self, class: NotifyClass, event: &str, key: &[u8]) {
+        self.publish_keyspace_event(self.selected_db(), class, event, key);
+    }
+
+    // Like notify_keyspace_event, for a key of the database at index `db`.
+    fn publish_keyspace_event(&self, db: usize, class: NotifyClass, event: &str, key: &[u8]) {
+        let flags = self.config().read().notify_keyspace_events;
+        if !flags.publishes(class) {
+            return;
+        }
+        if flags.keyspace() {
+            let channel = format!("__keyspace@{db}__:{}", String::from_utf8_lossy(key));
+            self.pubsub().publish(&channel, BulkString::from(event));
+        }
+        if flags.keyevent() {
+            let channel = format!("__keyevent@{db}__:{event}");
+            self.pubsub()
+                .publish(&channel, BulkString::new(key.to_vec()));
+        }
+    }
+
+    pub fn selected_db(&self) -> usize {
+        self.db.load(Ordering::Relaxed)
+    }
+
+    // Selects the database at `index` for this handle. Returns false if it doesn't exist.
+    pub fn select(&self, index: usize) -> bool {
+        if index >= self.database_count() {
+            return false;
+        }
+        self.db.store(index, Ordering::Relaxed);
+        self.refresh_db();
+        true
+    }
+
+    // Resolves the selected index again, to pick up a SWAPDB made by another connection. Done
+    // once before each command so a command never straddles two databases.
+    pub fn refresh_db(&self) {
+        let slot = self.slots()[self.selected_db()];
+        self.slot.store(slot, Ordering::Relaxed);
+    }
+
+    // Exchanges the contents of the databases at two indexes, for every connection at once.
+    // Returns false if one of them doesn't exist.
+    pub fn swap_db(&self, index1: usize, index2: usize) -> bool {
+        if index1.max(index2) >= self.database_count() {
+            return false;
+        }
+        self.inner
+            .slots
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .swap(index1, index2);
+        self.refresh_db();
+        // the lists and streams behind both indexes changed, let blocked clients check again
+        self.inner.blocking.signal_db(index1);
+        self.inner.blocking.signal_db(index2);
+        true
+    }
+
+    pub fn database_count(&self) -> usize {
+        self.inner.dbs.len()
+    }
+
+    // The database currently behind `index`.
+    pub fn database(&self, index: usize) -> Option<&Db> {
+        let slot = *self.slots().get(index)?;
+        Some(&self.inner.dbs[slot])
+    }
+
+    fn slots(&self) -> RwLockReadGuard<'_, Vec<usize>> {
+        self.inner.slots.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn blocking(&self) -> &Blocking {
+        &self.inner.blocking
+    }
+
+    // Wakes up the client blocked the longest on `key` of the selected database.
+    fn signal_key(&self, key: &[u8]) {
+        let key = BlockKey::Key(self.selected_db(), key.to_vec());
+        self.inner.blocking.signal(&key);
+    }
+
+    // Blocks until `attempt` returns a result, trying again whenever one of `keys` is signaled,
+    // or until `deadline` passes and None is returned. Clients blocked on the same key have
+    // their attempts in the order they blocked.
+    pub async fn block_on<T>(
+        &self,
+        keys: Vec<BlockKey>,
+        deadline: Option<tokio::time::Instant>,
+        mut attempt: impl FnMut() -> Option<T>,
+    ) -> Option<T> {
+        // queued up before the first attempt so a write in between is not missed
+        let blocked = self.inner.blocking.block(keys);
+        self.clients()
+            .update(self.client_id, |info| info.blocked = true);
+        let result = async {
+            loop {
+                if let Some(result) = attempt() {
+                    return Some(result);
+                }
+                blocked.pass();
+                match deadline {
+                    Some(deadline) => tokio::time::timeout_at(deadline, blocked.wait())
+                        .await
+                        .ok()?,
+                    None => blocked.wait().await,
+                }
+            }
+        }
+        .await;
+        self.clients()
+            .update(self.client_id, |info| info.blocked = false);
+        result
+    }
+
+    // time elapsed since the unix epoch
+    pub fn now(&self) -> Duration {
+        self.inner.clock.now()
+    }
+
+    // Milliseconds since the unix epoch, the unit all expiry and access times are stored in.
+    pub fn now_ms(&self) -> i64 {
+        self.now().as_millis() as i64
+    }
+
+    // Writes a snapshot of every database to `dir/dbfilename` and publishes the outcome in the
+    // save status. Fails right away if another save is running. The caller holds exclusive
+    // access, see copy_dataset.
+    pub fn save(&self) -> io::Result<()> {
+        if !self.inner.save_status.start() {
+            return Err(io::Error::other("a save is already in progress"));
+        }
+        let changes = self.inner.save_status.changes();
+        self.run_save(self.copy_dataset(), changes)
+    }
+
+    // Like save, on a background thread so that no connection waits for the file to be written:
+    // the databases are frozen as of the call, and written out while writes go on. Returns false
+    // if a save is already running. With `schedule`, the save then starts once the running one
+    // finished instead, still with the dataset as of the call.
+    pub fn bgsave(&self, schedule: bool) -> bool {
+        let started = self.inner.save_status.start();
+        if !started && !schedule {
+            return false;
+        }
+        let changes = self.inner.save_status.changes();
+        let copy = self.copy_dataset();
+        let backend = self.clone();
+        thread::spawn(move || {
+            if !started {
+                while !backend.inner.save_status.start() {
+                    thread::sleep(SAVE_POLL_INTERVAL);
+                }
+            }
+            // the outcome is published in the save status
+            let _ = backend.run_save(copy, changes);
+        });
+        true
+    }
+
+    // Writes the snapshot of a save marked as started in the save status, then publishes its
+    // outcome there, with the number of `changes` made before the dataset was copied.
+    fn run_save(&self, copy: DatasetCopy, changes: u64) -> io::Result<()> {
+        let started = self.now();
+        let result = self.write_snapshot(copy);
+        let now = self.now();
+        let elapsed = now.saturating_sub(started);
+        self.inner.save_status.finish(
+            result.as_ref().err().map(|e| e.to_string()),
+            now.as_secs() as i64,
+            elapsed.as_secs() as i64,
+            changes,
+        );
+        self.record_latency("snapshot", elapsed);
+        result
+    }
+
+    pub fn save_status(&self) -> &SaveStatus {
+        &self.inner.save_status
+    }
+
+    // The file is written under a temporary name first, so a failed save never clobbers the
+    // previous snapshot.
+    fn write_snapshot(&self, copy: DatasetCopy) -> io::Result<()> {
+        let path = {
+            let config = self.config().read();
+            Path::new(&config.dir).join(&config.dbfilename)
+        };
+        let data = copy.encode();
+
+        let temp = path.with_file_name(format!("temp-{}.rdb", std::process::id()));
+        let mut file = File::create(&temp)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        fs::rename(&temp, &path)
+    }
+
+    // Loads the snapshot at `dir/dbfilename` into the databases and the function libraries,
+    // replacing what they hold, and returns the number of keys loaded. Nothing is loaded when
+    // there is no snapshot yet, keys of databases beyond `databases` are left out.
+    pub fn load(&self) -> io::Result<usize> {
+        let path = {
+            let config = self.config().read();
+            Path::new(&config.dir).join(&config.dbfilename)
+        };
+        match fs::read(&path) {
+            Ok(data) => self.load_snapshot(&data),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Loads a snapshot like load, from the contents of the file.
+    pub fn load_snapshot(&self, data: &[u8]) -> io::Result<usize> {
+        let snapshot = rdb::decode(data)?;
+        for library in snapshot.functions {
+            self.functions()
+                .insert(library, true)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+        let selected = self.selected_db();
+        let mut loaded = 0;
+        let result = snapshot.keys.into_iter().try_for_each(|key| {
+            if self.select(key.db) {
+                self.restore(&key.key, &key.payload, key.expire_at, true, None)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                loaded += 1;
+            }
+            Ok(())
+        });
+        self.select(selected);
+        result.map(|_| loaded)
+    }
+
+    pub fn append_log(&self) -> &AppendLog {
+        &self.inner.append_log
+    }
+
+    // Whether write commands are logged to the append only file.
+    pub fn append_only(&self) -> bool {
+        self.config().read().appendonly
+    }
+
+    fn append_log_path(&self) -> PathBuf {
+        let config = self.config().read();
+        Path::new(&config.dir).join(&config.appendfilename)
+    }
+
+    pub fn replication(&self) -> &Replication {
+        &self.inner.replication
+    }
+
+    // Whether the server is a replica that rejects writes from its clients.
+    pub fn read_only_replica(&self) -> bool {
+        self.inner.replication.master().is_some() && self.config().read().replica_read_only
+    }
+
+    pub fn cluster(&self) -> &Cluster {
+        &self.inner.cluster
+    }
+
+    pub fn cluster_enabled(&self) -> bool {
+        self.config().read().cluster_enabled
+    }
+
+    fn cluster_config_path(&self) -> PathBuf {
+        let config = self.config().read();
+        Path::new(&config.dir).join(&config.cluster_config_file)
+    }
+
+    // Writes the cluster state to `dir/cluster-config-file`, under a temporary name first like
+    // snapshots.
+    pub fn save_cluster_config(&self) -> io::Result<()> {
+        let path = self.cluster_config_path();
+        let temp = path.with_file_name(format!("temp-{}.conf", std::process::id()));
+        let mut file = File::create(&temp)?;
+        file.write_all(self.inner.cluster.config().as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temp, &path)
+    }
+
+    // Loads the cluster state saved by save_cluster_config, if any. Returns whether there was
+    // one, a node without it starts a cluster of its own with a new id.
+    pub fn load_cluster_config(&self) -> io::Result<bool> {
+        match fs::read_to_string(self.cluster_config_path()) {
+            Ok(text) => self.inner.cluster.load_config(&text).map(|_| true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Checks that this node serves a request on `keys` of the selected database, see
+    // Cluster::route.
+    pub fn cluster_route(&self, keys: &[Vec<u8>], asking: bool) -> Result<(), ClusterRedirect> {
+        self.inner
+            .cluster
+            .route(keys, asking, |key| self.exists(key))
+    }
+
+    // How many keys of the selected database are in hash slot `slot`.
+    pub fn count_keys_in_slot(&self, slot: u16) -> usize {
+        let mut count = 0;
+        self.keyspace.for_each(|key, _| {
+            if key_hash_slot(key) == slot {
+                count += 1;
+            }
+        });
+        count
+    }
+
+    // Whether the client owning this handle sent ASKING, which it then no longer did if `clear`.
+    pub fn asking(&self, clear: bool) -> bool {
+        let mut asking = false;
+        self.clients().update(self.client_id, |info| {
+            asking = info.asking;
+            info.asking &= !clear;
+        });
+        asking
+    }
+
+    // Whether writes are propagated, to the append only file or to replicas.
+    pub fn propagating(&self) -> bool {
+        self.append_only() || self.inner.replication.is_active()
+    }
+
+    // Propagates a write command that ran on the selected database with `args`, given its reply:
+    // to the append only file and the replicas. Commands that failed are left out.
+    pub fn propagate(&self, args: Vec<Vec<u8>>, reply: &RespFrame) {
+        if matches!(reply, RespFrame::Error(_)) || !self.propagating() {
+            return;
+        }
+        let Some(name) = args.first() else {
+            return;
+        };
+        let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+        let key = args.get(1).cloned();
+        let mut commands = aof::effects(&name, args, reply);
+        if let Some(key) = key.filter(|_| aof::EXPIRY_COMMANDS.contains(&name.as_str())) {
+            if let Some(when) = self.keyspace.expire_at(&key) {
+                let when = when.to_string().into_bytes();
+                commands.push(vec![b"PEXPIREAT".to_vec(), key, when]);
+            }
+        }
+        self.propagate_in(self.selected_db(), &commands);
+    }
+
+    // Propagates the commands a write that ran in database `db` amounts to.
+    fn propagate_in(&self, db: usize, commands: &[LogCommand]) {
+        let config = self.config().read();
+        if config.appendonly {
+            let policy = LogPolicy {
+                fsync: &config.appendfsync,
+                rewrite_percentage: config.auto_aof_rewrite_percentage,
+                rewrite_min_size: config.auto_aof_rewrite_min_size,
+            };
+            self.inner.append_log.append(db, commands, policy);
+        }
+        let backlog_size = config.repl_backlog_size as usize;
+        self.inner
+            .replication
+            .feed(Some(db), commands, backlog_size);
+    }
+
+    // Makes the client owning this handle a replica. Returns the offset its stream starts at,
+    // the stream, and the copy of the dataset to send it first. The caller holds exclusive
+    // access, so that the copy is the dataset as of that offset.
+    pub fn attach_replica(&self) -> (u64, ReplicaStream, DatasetCopy) {
+        let addr = self.clients().get(self.client_id).map(|info| info.addr);
+        let (offset, stream) =
+            self.inner
+                .replication
+                .attach(self.client_id, addr.unwrap_or_default(), self.now_ms());
+        (offset, stream, self.copy_dataset())
+    }
+
+    // Makes the client owning this handle a replica that resumes the history `replid` at
+    // `psync_offset`, if the backlog still has everything it missed. Returns its stream, which
+    // starts with what it missed.
+    pub fn resume_replica(&self, replid: &str, psync_offset: u64) -> Option<ReplicaStream> {
+        let addr = self.clients().get(self.client_id).map(|info| info.addr);
+        self.inner.replication.attach_partial(
+            self.client_id,
+            addr.unwrap_or_default(),
+            replid,
+            psync_offset,
+            self.now_ms(),
+        )
+    }
+
+    // Records that the replica of this connection processed the stream up to `offset`, letting
+    // WAIT count it.
+    pub fn replica_ack(&self, offset: u64) {
+        let now = self.now_ms();
+        self.inner.replication.ack(self.client_id(), offset, now);
+        self.inner.blocking.signal(&BlockKey::ReplicaAcks);
+    }
+
+    // Asks every replica for the offset it processed, which they answer with REPLCONF ACK.
+    pub fn request_replica_acks(&self) {
+        let getack = vec![b"REPLCONF".to_vec(), b"GETACK".to_vec(), b"*".to_vec()];
+        let backlog_size = self.config().read().repl_backlog_size as usize;
+        self.inner.replication.feed(None, &[getack], backlog_size);
+    }
+
+    // Rewrites the append only file on a background thread, as the commands recreating the
+    // current dataset followed by the writes logged while the rewrite runs. The dataset is
+    // frozen up front, the caller holds exclusive access for it so that no write lands both in
+    // the copy and among the commands logged meanwhile. Returns false if a rewrite is already
+    // running.
+    pub fn rewrite_append_log(&self) -> bool {
+        let started = self.now();
+        if !self.inner.append_log.start_rewrite() {
+            return false;
+        }
+        let copy = self.copy_dataset();
+        let backend = self.clone();
+        thread::spawn(move || {
+            let rewritten = aof::rewrite(copy);
+            let path = backend.append_log_path();
+            let now = backend.now();
+            let elapsed = now.saturating_sub(started);
+            // the outcome is published in the rewrite status
+            let _ = backend.inner.append_log.finish_rewrite(
+                &path,
+                &rewritten,
+                backend.append_only(),
+                now.as_secs() as i64,
+                elapsed.as_secs() as i64,
+            );
+            backend.record_latency("aof-rewrite", elapsed);
+        });
+        true
+    }
+
+    // Freezes the keys of every database as they are now, leaving out expired ones, and copies
+    // the function libraries. Only the keys written while the copy is alive are copied, when
+    // they are written. The caller holds exclusive access so that no command is halfway through
+    // its writes.
+    pub fn copy_dataset(&self) -> DatasetCopy {
+        let epoch = self.inner.snapshot_epoch.fetch_add(1, Ordering::Relaxed);
+        let dbs = {
+            let slots = self.slots();
+            slots.iter().copied().enumerate().collect::<Vec<_>>()
+        };
+        for &(_, slot) in &dbs {
+            self.inner.dbs[slot].keyspace.begin_snapshot(epoch);
+        }
+        let functions = self.functions().list(None);
+        DatasetCopy::new(self.inner.clone(), epoch, dbs, functions, self.now_ms())
+    }
+
+    // The content of the append only file, None if there is none yet.
+    pub fn read_append_log(&self) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.append_log_path()) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Continues logging to the append only file loaded at startup, the commands it holds taking
+    // `len` bytes and the last of them running in database `db`.
+    pub fn open_append_log(&self, len: u64, db: usize) -> io::Result<()> {
+        self.inner.append_log.open(&self.append_log_path(), len, db)
+    }
+
+    // Tells the server to stop accepting connections and close the open ones.
+    pub fn shutdown(&self) {
+        self.inner.shutdown.send_replace(true);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        *self.inner.shutdown.borrow()
+    }
+
+    // Changes to true once the server is shutting down.
+    pub fn shutdown_signal(&self) -> watch::Receiver<bool> {
+        self.inner.shutdown.subscribe()
+    }
+
+    pub fn latency(&self) -> &LatencyMonitor {
+        &self.inner.latency
+    }
+
+    // Records that `event` took `elapsed`, if that reaches the latency-monitor-threshold.
+    pub fn record_latency(&self, event: &str, elapsed: Duration) {
+        let threshold = self.config().read().latency_monitor_threshold;
+        let latency = elapsed.as_millis() as u64;
+        if threshold > 0 && latency >= threshold {
+            self.latency()
+                .record(event, self.now().as_secs() as i64, latency);
+        }
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.inner.stats
+    }
+
+    pub fn config(&self) -> &ServerConfig {
+        &self.inner.config
+    }
+
+    // How large hashes, lists and sets grow in their compact encodings, from the current
+    // configuration.
+    pub fn encoding_limits(&self) -> EncodingLimits {
+        EncodingLimits::new(&self.config().read())
+    }
+
+    // The value at `key` as the type `pick` takes out of it, None if the key doesn't exist and
+    // WrongType if it holds another type.
+    fn get_as<T>(
+        &self,
+        key: &[u8],
+        pick: impl FnOnce(&Value) -> Option<&T>,
+    ) -> Result<Option<TypedRef<'_, T>>, WrongType> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(value) => Keyspace::map_ref(value, pick).map(Some).ok_or(WrongType),
+            None => Ok(None),
+        }
+    }
+
+    fn get_mut_as<T: MemoryUsage>(
+        &self,
+        key: &[u8],
+        pick: impl FnOnce(&mut Value) -> Option<&mut T>,
+    ) -> Result<Option<TypedRefMut<'_, T>>, WrongType> {
+        self.expire_if_needed(key);
+        match self.keyspace.get_mut(key) {
+            Some(value) => match Keyspace::map_mut(value, pick) {
+                Some(value) => Ok(Some(TypedRefMut { value, db: self })),
+                None => Err(WrongType),
+            },
+            None => Ok(None),
+        }
+    }
+
+    // Like get_mut_as, creating the key with `create` when it doesn't exist. Callers remove the
+    // key again with remove_if_empty if nothing ends up in it.
+    fn entry_as<T: MemoryUsage>(
+        &self,
+        key: Vec<u8>,
+        create: impl FnOnce() -> Value,
+        pick: impl FnOnce(&mut Value) -> Option<&mut T>,
+    ) -> Result<TypedRefMut<'_, T>, WrongType> {
+        self.expire_if_needed(&key);
+        let value = self.keyspace.get_or_insert_with(key, create);
+        match Keyspace::map_mut(value, pick) {
+            Some(value) => Ok(TypedRefMut { value, db: self }),
+            None => Err(WrongType),
+        }
+    }
+
+    // Like Db::exists, removing the key first if it expired.
+    pub fn exists(&self, key: &[u8]) -> bool {
+        self.expire_if_needed(key);
+        self.keyspace.contains_key(key)
+    }
+
+    // Like Db::version, an expired key is removed first.
+    pub fn version(&self, key: &[u8]) -> u64 {
+        self.expire_if_needed(key);
+        Db::version(self, key)
+    }
+
+    // Like Db::del, an expired key doesn't count as deleted.
+    pub fn del(&self, key: &[u8]) -> bool {
+        self.expire_if_needed(key);
+        Db::del(self, key)
+    }
+
+    // Like Db::dump, None for an expired key.
+    pub fn dump(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.expire_if_needed(key);
+        Db::dump(self, key)
+    }
+
+    // Like Db::memory_usage, None for an expired key.
+    pub fn memory_usage(&self, key: &[u8], samples: usize) -> Option<usize> {
+        self.expire_if_needed(key);
+        Db::memory_usage(self, key, samples)
+    }
+
+    pub fn get(&self, key: &[u8]) -> Result<Option<RespFrame>, WrongType> {
+        let value = self.get_as(key, Value::as_string)?.map(|v| v.clone());
+        self.record_read(key, value.is_some());
+        Ok(value)
+    }
+
+    // Number of set bits of the string in the range, 0 if the key doesn't exist.
+    pub fn bitcount(&self, key: &[u8], range: BitRange) -> Result<usize, WrongType> {
+        let value = self.get_as(key, Value::as_string)?;
+        self.record_read(key, value.is_some());
+        let Some(RespFrame::BulkString(value)) = value.as_deref() else {
+            return Ok(0);
+        };
+        Ok(range
+            .bits(value.len())
+            .map_or(0, |(start, end)| bitops::bitcount(value, start, end)))
+    }
+
+    // Position of the first bit of the string set to `bit` in the range, -1 if there is none.
+    // Looking for a clear bit without the end of the range finds the one right after the string,
+    // the bits of a missing key are all clear.
+    pub fn bitpos(&self, key: &[u8], bit: bool, range: BitRange) -> Result<i64, WrongType> {
+        let value = self.get_as(key, Value::as_string)?;
+        self.record_read(key, value.is_some());
+        let Some(RespFrame::BulkString(value)) = value.as_deref() else {
+            return Ok(if bit { -1 } else { 0 });
+        };
+        let Some((start, end)) = range.bits(value.len()) else {
+            return Ok(-1);
+        };
+        Ok(match bitops::bitpos(value, bit, start, end) {
+            Some(pos) => pos as i64,
+            None if !bit && range.end.is_none() => (value.len() * 8) as i64,
+            None => -1,
+        })
+    }
+
+    // Combines the strings stored at `keys` with `op` and overwrites `destination` with the
+    // result, removing it when the result is empty. Missing keys are treated as empty strings.
+    // Returns the length of the stored string.
+    pub fn bitop(
+        &self,
+        op: BitOp,
+        destination: Vec<u8>,
+        keys: &[Vec<u8>],
+    ) -> Result<usize, WrongType> {
+        // clone one value at a time so no two shard locks are ever held together
+        let values = keys
+            .iter()
+            .map(|key| {
+                Ok(match self.get_as(key, Value::as_string)?.as_deref() {
+                    Some(RespFrame::BulkString(value)) => value.to_vec(),
+                    _ => Vec::new(),
+                })
+            })
+            .collect::<Result<Vec<_>, WrongType>>()?;
+        let result = bitops::bitop(op, &values);
+        let len = result.len();
+        if result.is_empty() {
+            self.del(&destination);
+        } else {
+            self.set(destination, BulkString::new(result).into());
+        }
+        Ok(len)
+    }
+
+    // Adds the elements to the HyperLogLog at `key`, creating it if needed. Returns whether the
+    // estimated cardinality may have changed.
+    pub fn pfadd(&self, key: &[u8], elements: &[String]) -> Result<bool, HllError> {
+        let mut changed = false;
+        let create = || {
+            changed = true;
+            Value::String(BulkString::new(HyperLogLog::default().into_bytes()).into())
+        };
+        let mut value = self.entry_as(key.to_vec(), create, Value::as_string_mut)?;
+        let RespFrame::BulkString(bytes) = &mut *value else {
+            return Err(HllError::InvalidValue);
+        };
+        HyperLogLog::validate(bytes)?;
+        for element in elements {
+            changed |= hll::add(&mut bytes.0, element.as_bytes());
+        }
+        drop(value);
+        self.record_access(key, true);
+        Ok(changed)
+    }
+
+    // The estimated cardinality of the union of the HyperLogLogs at `keys`, missing keys are
+    // empty. The cardinality of a single key is cached in its value.
+    pub fn pfcount(&self, keys: &[Vec<u8>]) -> Result<u64, HllError> {
+        if let [key] = keys {
+            let mut value = self.get_mut_as(key, Value::as_string_mut)?;
+            self.record_read(key, value.is_some());
+            return match value.as_deref_mut() {
+                None => Ok(0),
+                Some(RespFrame::BulkString(bytes)) => {
+                    HyperLogLog::validate(bytes)?;
+                    Ok(hll::count(&mut bytes.0))
+                }
+                Some(_) => Err(HllError::InvalidValue),
+            };
+        }
+        Ok(self.hll_union(keys)?.count())
+    }
+
+    // Overwrites `destination` with the union of its HyperLogLog and the ones at `keys`, keeping
+    // its expiry.
+    pub fn pfmerge(&self, destination: &[u8], keys: &[Vec<u8>]) -> Result<(), HllError> {
+        let mut keys = keys.to_vec();
+        keys.push(destination.to_vec());
+        let merged = self.hll_union(&keys)?;
+        self.record_access(destination, true);
+        self.keyspace.insert(
+            destination.to_vec(),
+            Value::String(BulkString::new(merged.into_bytes()).into()),
+        );
+        self.account(destination);
+        Ok(())
+    }
+
+    fn hll_union(&self, keys: &[Vec<u8>]) -> Result<HyperLogLog, HllError> {
+        let mut merged = HyperLogLog::default();
+        for key in keys {
+            let value = self.get_as(key, Value::as_string)?;
+            self.record_read(key, value.is_some());
+            match value.as_deref() {
+                None => continue,
+                Some(RespFrame::BulkString(bytes)) => {
+                    HyperLogLog::validate(bytes)?;
+                    merged.merge(bytes);
+                }
+                Some(_) => return Err(HllError::InvalidValue),
+            }
+        }
+        Ok(merged)
+    }
+
+    // Overwrites the key, discarding any expiry it had.
+    pub fn set(&self, key: Vec<u8>, value: RespFrame) {
+        self.store(key, Value::String(value));
+    }
+
+    // Like set, for a value of any type.
+    fn store(&self, key: Vec<u8>, value: Value) {
+        self.expire_if_needed(&key);
+        self.keyspace.remove_expire(&key);
+        self.record_access(&key, true);
+        let old = self.keyspace.insert(key.clone(), value);
+        self.account(&key);
+        if let Some(old) = old {
+            self.free(old, self.config().read().lazyfree_lazy_server_del);
+        }
+        self.signal_key(&key);
+    }
+
+    // Accounts for a read of the key in the keyspace hit/miss statistics.
+    fn record_read(&self, key: &[u8], hit: bool) {
+        self.stats().keyspace_lookup(hit);
+        self.record_access(key, hit);
+    }
+
+    // Updates the last access time and the access frequency of the key, when `hit` (i.e. the
+    // key was found or written).
+    fn record_access(&self, key: &[u8], hit: bool) {
+        if !hit {
+            return;
+        }
+        let now = self.now_ms();
+        let (log_factor, decay_time) = {
+            let config = self.config().read();
+            (config.lfu_log_factor, config.lfu_decay_time)
+        };
+        self.access
+            .entry(key.to_vec())
+            .and_modify(|access| *access = access.accessed(now, log_factor, decay_time))
+            .or_insert_with(|| KeyAccess::new(now));
+    }
+
+    // Updates the last access time of the key. Returns false if the key doesn't exist.
+    pub fn touch(&self, key: &[u8]) -> bool {
+        let exists = self.exists(key);
+        self.record_access(key, exists);
+        exists
+    }
+
+    // Milliseconds since the key was last read or written, None if the key doesn't exist.
+    pub fn idle_time(&self, key: &[u8]) -> Option<i64> {
+        if !self.exists(key) {
+            return None;
+        }
+        let last = self.access.get(key).map_or(self.now_ms(), |v| v.at);
+        Some((self.now_ms() - last).max(0))
+    }
+
+    // The access frequency counter of the key as of now, see KeyAccess. None if the key
+    // doesn't exist.
+    pub fn freq(&self, key: &[u8]) -> Option<u8> {
+        if !self.exists(key) {
+            return None;
+        }
+        let decay_time = self.config().read().lfu_decay_time;
+        let freq = self
+            .access
+            .get(key)
+            .map(|v| v.decayed_freq(self.now_ms(), decay_time));
+        Some(freq.unwrap_or(LFU_INIT_VAL))
+    }
+
+    // Like del, but large values are freed on the lazy-free thread instead of in place.
+    pub fn unlink(&self, key: &[u8]) -> bool {
+        self.expire_if_needed(key);
+        match self.take(key) {
+            Some(value) => {
+                self.free(value, true);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Drops a value taken out of the keyspace, on the lazy-free thread when `lazy` and it is
+    // large enough to be worth it.
+    fn free(&self, value: Value, lazy: bool) {
+        if lazy {
+            let len = value.len();
+            self.inner.lazy_free.free(value, len);
+        }
+    }
+
+    // Removes every key. When `lazy`, the values are moved out of the maps and freed on the
+    // lazy-free thread so flushing a large dataset doesn't stall other connections.
+    pub fn flush(&self, lazy: bool) {
+        self.flush_db(self, lazy);
+    }
+
+    // Like flush, for every database.
+    pub fn flush_all(&self, lazy: bool) {
+        for db in &self.inner.dbs {
+            self.flush_db(db, lazy);
+        }
+    }
+
+    fn flush_db(&self, db: &Db, lazy: bool) {
+        if lazy {
+            // the maps of the keys are swapped with empty ones, the old ones dropped elsewhere
+            let garbage = db.keyspace.drain();
+            self.inner.lazy_free.free_in_background(garbage);
+        } else {
+            db.keyspace.clear();
+        }
+        // the bookkeeping of the keys goes once they are gone
+        db.access.clear();
+        db.sizes.clear();
+        db.drop_version(None);
+        db.used.store(0, Ordering::Relaxed);
+    }
+
+    // Sets the key to expire at `when` (milliseconds since the unix epoch) if `condition` allows
+    // it. A time in the past deletes the key right away. Returns false when the key doesn't exist
+    // or the condition isn't met.
+    pub fn expire_at(&self, key: &[u8], when: i64, condition: ExpireCondition) -> bool {
+        if !self.exists(key) {
+            return false;
+        }
+        let current = self.keyspace.expire_at(key);
+        if !condition.allows(current, when) {
+            return false;
+        }
+        if when <= self.now_ms() {
+            self.del(key);
+        } else {
+            self.keyspace.set_expire(key.to_vec(), when);
+            self.bump_version(key);
+        }
+        true
+    }
+
+    pub fn expiry(&self, key: &[u8]) -> KeyExpiry {
+        self.expire_if_needed(key);
+        match self.keyspace.expire_at(key) {
+            Some(when) => KeyExpiry::At(when),
+            None if self.exists(key) => KeyExpiry::Persistent,
+            None => KeyExpiry::Missing,
+        }
+    }
+
+    // Removes the expiry of the key. Returns false when the key doesn't exist or has no expiry.
+    pub fn persist(&self, key: &[u8]) -> bool {
+        let persisted = !self.expire_if_needed(key) && self.keyspace.remove_expire(key).is_some();
+        if persisted {
+            self.bump_version(key);
+        }
+        persisted
+    }
+
+    // Removes the key if its expiry time has passed, counting it in the expired_keys statistic
+    // and firing the expired keyspace event. Called before every access to a key so an expired
+    // key is never seen, even when the active expiry cycle didn't get to it yet. Returns whether
+    // the key expired.
+    fn expire_if_needed(&self, key: &[u8]) -> bool {
+        self.expire_in(self.selected_db(), self, key)
+    }
+
+    // Like expire_if_needed, for a key of the database `db` at `index`.
+    fn expire_in(&self, index: usize, db: &Db, key: &[u8]) -> bool {
+        let Some(value) = db.remove_expired(key, self.now_ms()) else {
+            return false;
+        };
+        self.free(value, self.config().read().lazyfree_lazy_expire);
+        self.stats().key_expired();
+        self.publish_keyspace_event(index, NotifyClass::Expired, "expired", key);
+        // replicas and the append only file see the key go, like an eviction
+        if self.propagating() {
+            self.propagate_in(index, &[vec![b"DEL".to_vec(), key.to_vec()]]);
+        }
+        true
+    }
+
+    // Copies the value at `source` (and its expiry) to `destination` in the database at index
+    // `db`. Returns false when the source or that database doesn't exist, or the destination
+    // exists and `replace` isn't set.
+    pub fn copy(&self, source: &[u8], destination: &[u8], db: usize, replace: bool) -> bool {
+        // clone first and insert after, holding a guard while inserting could deadlock
+        let Some(target) = self.database(db) else {
+            return false;
+        };
+        if !self.exists(source) || (target.exists(destination) && !replace) {
+            return false;
+        }
+        if let Some(old) = target.take(destination) {
+            self.free(old, self.config().read().lazyfree_lazy_server_del);
+        }
+        if let Some(value) = self.keyspace.get(source).map(|value| value.clone()) {
+            target.keyspace.insert(destination.to_vec(), value);
+        }
+        if let Some(when) = self.keyspace.expire_at(source) {
+            target.keyspace.set_expire(destination.to_vec(), when);
+        }
+        target
+            .access
+            .insert(destination.to_vec(), KeyAccess::new(self.now_ms()));
+        target.account(destination);
+        true
+    }
+
+    // Recreates the key from a DUMP payload. `expire_at` is an absolute time in milliseconds, a
+    // time in the past leaves the key deleted. `idle` (in milliseconds) backdates its last access.
+    pub fn restore(
+        &self,
+        key: &[u8],
+        payload: &[u8],
+        expire_at: Option<i64>,
+        replace: bool,
+        idle: Option<i64>,
+    ) -> Result<(), DumpError> {
+        let value = Value::deserialize(payload)?.compact(&self.encoding_limits());
+        if self.exists(key) && !replace {
+            return Err(DumpError::BusyKey);
+        }
+        if let Some(old) = self.take(key) {
+            self.free(old, self.config().read().lazyfree_lazy_server_del);
+        }
+        if expire_at.is_some_and(|when| when <= self.now_ms()) {
+            return Ok(());
+        }
+
+        let name = key.to_vec();
+        self.keyspace.insert(name.clone(), value);
+        // blocked pops and reads wait for lists and streams to show up
+        self.signal_key(key);
+        if let Some(when) = expire_at {
+            self.keyspace.set_expire(name.clone(), when);
+        }
+        let access = KeyAccess::new(self.now_ms() - idle.unwrap_or_default());
+        self.access.insert(name.clone(), access);
+        self.account(&name);
+        Ok(())
+    }
+
+    pub fn set_active_expire(&self, enabled: bool) {
+        self.inner.active_expire.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn active_expire_enabled(&self) -> bool {
+        self.inner.active_expire.load(Ordering::Relaxed)
+    }
+
+    // Type name of the value at `key` as reported by TYPE and the SCAN TYPE filter.
+    pub fn key_type(&self, key: &[u8]) -> Option<&'static str> {
+        self.expire_if_needed(key);
+        self.keyspace.get(key).map(|v| v.type_name())
+    }
+
+    // Internal representation of the value at `key` as reported by OBJECT ENCODING.
+    pub fn encoding(&self, key: &[u8]) -> Option<&'static str> {
+        self.expire_if_needed(key);
+        let value = self.keyspace.get(key)?;
+        Some(match &*value {
+            Value::String(RespFrame::Integer(_)) => "int",
+            Value::String(RespFrame::BulkString(s))
+                if std::str::from_utf8(s).is_ok_and(|s| s.parse::<i64>().is_ok()) =>
+            {
+                "int"
+            }
+            Value::String(RespFrame::BulkString(s)) if s.len() <= EMBSTR_SIZE_LIMIT => "embstr",
+            Value::String(_) => "raw",
+            Value::List(list) => list.encoding(),
+            Value::Hash(hash) => hash.encoding(),
+            Value::ZSet(_) => "skiplist",
+            Value::Stream(_) => "stream",
+            Value::Set(set) => set.encoding(),
+        })
+    }
+
+    // Returns one page of keys and the cursor to continue from, 0 when the iteration is complete.
+    // See Storage::scan for the guarantees.
+    pub fn scan(
+        &self,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+        key_type: Option<&str>,
+    ) -> (u64, Vec<Vec<u8>>) {
+        let (mut page, mut visited) = (vec![], 0);
+        let next = self.keyspace.scan(cursor, |key, value| {
+            // keys not matching the pattern are dropped after the visit, like in redis
+            if pattern.is_none_or(|p| scan::glob_match(p, key)) {
+                page.push((key.to_vec(), value.type_name()));
+            }
+            visited += 1;
+            visited < count.max(1)
+        });
+        let keys = page
+            .into_iter()
+            .filter(|(key, _)| !self.expire_if_needed(key))
+            .filter(|(_, name)| key_type.is_none_or(|t| t.eq_ignore_ascii_case(name)))
+            .map(|(key, _)| key)
+            .collect();
+        (next, keys)
+    }
+
+    // A key picked at random, None when the database is empty. An expired key picked is removed
+    // and another one picked instead.
+    pub fn random_key(&self) -> Option<Vec<u8>> {
+        loop {
+            let key = self.keyspace.random_key(false)?;
+            if !self.expire_if_needed(&key) {
+                return Some(key);
+            }
+        }
+    }
+
+    // Number of keys, not counting those already expired but not removed yet.
+    pub fn db_size(&self) -> usize {
+        let now = self.now_ms();
+        let mut expired = 0;
+        self.keyspace
+            .for_each_expire(|_, when| expired += (when <= now) as usize);
+        self.key_count().0.saturating_sub(expired)
+    }
+
+    // number of unlinked values still waiting to be freed
+    pub fn lazyfree_pending(&self) -> usize {
+        self.inner.lazy_free.pending()
+    }
+
+    pub fn hget(&self, key: &[u8], field: &str) -> Result<Option<RespFrame>, WrongType> {
+        let value = self
+            .get_as(key, Value::as_hash)?
+            .map(|hmap| hmap.get(field));
+        self.record_read(key, value.is_some());
+        Ok(value.flatten())
+    }
+
+    pub fn hset(&self, key: Vec<u8>, field: String, value: RespFrame) -> Result<(), WrongType> {
+        self.record_access(&key, true);
+        let limits = self.encoding_limits();
+        let mut hmap = self.entry_as(
+            key,
+            || Value::Hash(HashValue::default()),
+            Value::as_hash_mut,
+        )?;
+        hmap.insert(field, value, &limits);
+        Ok(())
+    }
+
+    pub fn hgetall(&self, key: &[u8]) -> Result<Option<HashValue>, WrongType> {
+        let hmap = self.get_as(key, Value::as_hash)?.map(|v| v.clone());
+        self.record_read(key, hmap.is_some());
+        Ok(hmap)
+    }
+
+    // Inserts the members into the set. Returns the number of members that were not already in
+    // the set.
+    pub fn sadd(
+        &self,
+        key: impl Into<Vec<u8>>,
+        members: impl IntoIterator<Item = String>,
+    ) -> Result<usize, WrongType> {
+        let key = key.into();
+        self.record_access(&key, true);
+        let limits = self.encoding_limits();
+        let mut set = self.entry_as(key, || Value::Set(SetValue::default()), Value::as_set_mut)?;
+        Ok(members
+            .into_iter()
+            .filter(|m| set.insert(m.clone(), &limits))
+            .count())
+    }
+
+    // Removes the members from the set, deleting the key once the set is empty.
+    // Returns the number of members that were removed.
+    pub fn srem(&self, key: &[u8], members: &[String]) -> Result<usize, WrongType> {
+        let removed = match self.get_mut_as(key, Value::as_set_mut)? {
+            Some(mut set) => members.iter().filter(|m| set.remove(m)).count(),
+            None => return Ok(0),
+        };
+        self.remove_if_empty(key);
+        Ok(removed)
+    }
+
+    pub fn smembers(&self, key: &[u8]) -> Result<Vec<String>, WrongType> {
+        let members = self
+            .get_as(key, Value::as_set)?
+            .map(|v| v.iter().map(Cow::into_owned).collect());
+        self.record_read(key, members.is_some());
+        Ok(members.unwrap_or_default())
+    }
+
+    pub fn scard(&self, key: &[u8]) -> Result<usize, WrongType> {
+        let set = self.get_as(key, Value::as_set)?;
+        self.record_read(key, set.is_some());
+        Ok(set.map_or(0, |v| v.len()))
+    }
+
+    // Checks if the set contains a specific member.
+    pub fn sismember(&self, key: &[u8], member: &str) -> Result<bool, WrongType> {
+        let set = self.get_as(key, Value::as_set)?;
+        self.record_read(key, set.is_some());
+        Ok(set.is_some_and(|v| v.contains(member)))
+    }
+
+    // Moves `member` from `source` to `destination`. Returns false if it was not in `source`.
+    pub fn smove(
+        &self,
+        source: &[u8],
+        destination: Vec<u8>,
+        member: String,
+    ) -> Result<bool, WrongType> {
+        if source == destination {
+            let set = self.get_as(source, Value::as_set)?;
+            return Ok(set.is_some_and(|v| v.contains(&member)));
+        }
+        // no other writer gets between the checks and the move, or the member may be lost
+        let keys = [source.to_vec(), destination.clone()];
+        self.atomically(&keys, || {
+            // nothing is removed from the source when the destination can't take the member
+            self.get_as(&destination, Value::as_set)?;
+
+            let removed = match self.get_mut_as(source, Value::as_set_mut)? {
+                Some(mut set) => set.remove(&member),
+                None => false,
+            };
+            if removed {
+                self.remove_if_empty(source);
+                let limits = self.encoding_limits();
+                self.entry_as(
+                    destination,
+                    || Value::Set(SetValue::default()),
+                    Value::as_set_mut,
+                )?
+                .insert(member, &limits);
+            }
+            Ok(removed)
+        })
+    }
+
+    // Combines the sets stored at `keys` with `op`. Missing keys are treated as empty sets.
+    pub fn scombine(&self, op: SetOp, keys: &[Vec<u8>]) -> Result<HashSet<String>, WrongType> {
+        // clone one set at a time so no two shard locks are ever held together
+        let sets = keys
+            .iter()
+            .map(|key| {
+                let set = self.get_as(key, Value::as_set)?;
+                Ok(set.map(|v| v.iter().map(Cow::into_owned).collect::<HashSet<_>>()))
+            })
+            .collect::<Result<Vec<_>, WrongType>>()?;
+        let mut sets = sets.into_iter().map(Option::unwrap_or_default);
+        let first = sets.next().unwrap_or_default();
+        Ok(sets.fold(first, |acc, set| match op {
+            SetOp::Inter => acc.intersection(&set).cloned().collect(),
+            SetOp::Union => acc.union(&set).cloned().collect(),
+            SetOp::Diff => acc.difference(&set).cloned().collect(),
+        }))
+    }
+
+    // Counts the members of the intersection of the sets at `keys`, stopping as soon as `limit`
+    // is reached (0 means unlimited). Only the smallest set is copied, the others are probed.
+    // Each key counts as one read however many times it is probed.
+    pub fn sintercard(&self, keys: &[Vec<u8>], limit: usize) -> Result<usize, WrongType> {
+        let cards = keys
+            .iter()
+            .map(|key| self.scard(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        let smallest = match keys.iter().zip(cards).min_by_key(|(_, card)| *card) {
+            Some((key, _)) => key,
+            None => return Ok(0),
+        };
+        let candidates = self
+            .get_as(smallest, Value::as_set)?
+            .map_or_else(Vec::new, |v| v.iter().map(Cow::into_owned).collect());
+        let contains = |key: &[u8], member: &str| {
+            self.get_as(key, Value::as_set)
+                .is_ok_and(|set| set.is_some_and(|v| v.contains(member)))
+        };
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        Ok(candidates
+            .iter()
+            .filter(|member| {
+                keys.iter()
+                    .filter(|key| *key != smallest)
+                    .all(|key| contains(key, member))
+            })
+            .take(limit)
+            .count())
+    }
+
+    // Combines the sets stored at `keys` and overwrites `destination` with the result, removing
+    // it when the result is empty. Returns the size of the stored set.
+    pub fn scombine_store(
+        &self,
+        op: SetOp,
+        destination: Vec<u8>,
+        keys: &[Vec<u8>],
+    ) -> Result<usize, WrongType> {
+        let result = self.scombine(op, keys)?;
+        let len = result.len();
+        if result.is_empty() {
+            self.del(&destination);
+        } else {
+            let set = SetValue::from(result).compact(&self.encoding_limits());
+            self.store(destination, Value::Set(set));
+        }
+        Ok(len)
+    }
+
+    // Removes and returns up to `count` random members, deleting the key once the set is empty.
+    pub fn spop(&self, key: &[u8], count: usize) -> Result<Vec<String>, WrongType> {
+        let popped = match self.get_mut_as(key, Value::as_set_mut)? {
+            Some(mut set) => {
+                let mut rng = rand::thread_rng();
+                let popped = set
+                    .iter()
+                    .map(Cow::into_owned)
+                    .choose_multiple(&mut rng, count);
+                for member in popped.iter() {
+                    set.remove(member);
+                }
+                popped
+            }
+            None => return Ok(vec![]),
+        };
+        self.remove_if_empty(key);
+        Ok(popped)
+    }
+
+    // Returns random members without removing them. A positive `count` returns up to `count`
+    // distinct members, a negative one returns exactly `-count` members which may repeat.
+    pub fn srandmember(&self, key: &[u8], count: i64) -> Result<Vec<String>, WrongType> {
+        let set = self.get_as(key, Value::as_set)?;
+        self.record_read(key, set.is_some());
+        let Some(set) = set else {
+            return Ok(vec![]);
+        };
+
+        let mut rng = rand::thread_rng();
+        if count >= 0 {
+            return Ok(set
+                .iter()
+                .map(Cow::into_owned)
+                .choose_multiple(&mut rng, count as usize));
+        }
+
+        let members = set.iter().collect::<Vec<_>>();
+        Ok((0..count.unsigned_abs())
+            .map(|_| members[rng.gen_range(0..members.len())].to_string())
+            .collect())
+    }
+
+    // Adds or updates the members of the sorted set according to `flags`, returning the outcome
+    // for each member. The key is not left behind if nothing could be added.
+    pub fn zadd(
+        &self,
+        key: impl Into<Vec<u8>>,
+        members: Vec<(f64, String)>,
+        flags: ZAddFlags,
+    ) -> Result<Vec<ZAddOutcome>, WrongType> {
+        let key = key.into();
+        self.record_access(&key, true);
+        let outcomes = {
+            let mut zset =
+                self.entry_as(key.clone(), || Value::ZSet(ZSet::new()), Value::as_zset_mut)?;
+            members
+                .into_iter()
+                .map(|(score, member)| zset.add(member, score, flags))
+                .collect()
+        };
+        self.remove_if_empty(&key);
+        self.signal_key(&key);
+        Ok(outcomes)
+    }
+
+    pub fn zscore(&self, key: &[u8], member: &str) -> Result<Option<f64>, WrongType> {
+        let zset = self.get_as(key, Value::as_zset)?;
+        self.record_read(key, zset.is_some());
+        Ok(zset.and_then(|v| v.score(member)))
+    }
+
+    // Removes the members from the sorted set, deleting the key once it is empty.
+    // Returns the number of members that were removed.
+    pub fn zrem(&self, key: &[u8], members: &[String]) -> Result<usize, WrongType> {
+        let removed = match self.get_mut_as(key, Value::as_zset_mut)? {
+            Some(mut zset) => members.iter().filter(|m| zset.remove(m)).count(),
+            None => return Ok(0),
+        };
+        self.remove_if_empty(key);
+        Ok(removed)
+    }
+
+    // Removes every member within `range` and returns how many were removed.
+    pub fn zremrange(&self, key: &[u8], range: &ZRangeSpec) -> Result<usize, WrongType> {
+        let removed = match self.get_mut_as(key, Value::as_zset_mut)? {
+            Some(mut zset) => {
+                let members = zset.range(range, false, 0, None);
+                members.iter().filter(|(m, _)| zset.remove(m)).count()
+            }
+            None => return Ok(0),
+        };
+        self.remove_if_empty(key);
+        Ok(removed)
+    }
+
+    // Pops up to `count` members from the first non-empty sorted set among `keys`, those with
+    // the lowest scores when `min` is true, otherwise those with the highest. Emptied sorted
+    // sets are removed.
+    pub fn zmpop(
+        &self,
+        keys: &[Vec<u8>],
+        min: bool,
+        count: usize,
+    ) -> Result<Option<ZPopped>, WrongType> {
+        for key in keys {
+            let popped = match self.get_mut_as(key, Value::as_zset_mut)? {
+                Some(mut zset) if !zset.is_empty() => {
+                    let owned = |(member, score): (&str, f64)| (member.to_string(), score);
+                    let popped = match min {
+                        true => zset.iter().take(count).map(owned).collect::<Vec<_>>(),
+                        false => zset.iter().rev().take(count).map(owned).collect(),
+                    };
+                    for (member, _) in &popped {
+                        zset.remove(member);
+                    }
+                    popped
+                }
+                _ => continue,
+            };
+            self.record_access(key, true);
+            self.remove_if_empty(key);
+            return Ok(Some((key.clone(), popped)));
+        }
+        Ok(None)
+    }
+
+    pub fn zcard(&self, key: &[u8]) -> Result<usize, WrongType> {
+        let zset = self.get_as(key, Value::as_zset)?;
+        self.record_read(key, zset.is_some());
+        Ok(zset.map_or(0, |v| v.len()))
+    }
+
+    pub fn zcount(&self, key: &[u8], range: &ZRangeSpec) -> Result<usize, WrongType> {
+        let zset = self.get_as(key, Value::as_zset)?;
+        self.record_read(key, zset.is_some());
+        Ok(zset.map_or(0, |v| v.count(range)))
+    }
+
+    // Combines the sorted sets at `keys` with `op`. Each input's scores are multiplied by its
+    // weight (1 if missing) and scores of common members are merged with `aggregate`. Plain sets
+    // are accepted as inputs with a score of 1 for every member, missing keys are empty.
+    pub fn zcombine(
+        &self,
+        op: SetOp,
+        keys: &[Vec<u8>],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> Result<ZSet, WrongType> {
+        let inputs = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let weight = weights.get(i).copied().unwrap_or(1.0);
+                Ok(self
+                    .zmembers(key)?
+                    .into_iter()
+                    .map(|(member, score)| (member, zset::zero_if_nan(score * weight)))
+                    .collect::<HashMap<_, _>>())
+            })
+            .collect::<Result<Vec<_>, WrongType>>()?;
+
+        let mut inputs = inputs.into_iter();
+        let mut acc = inputs.next().unwrap_or_default();
+        for input in inputs {
+            match op {
+                SetOp::Inter => {
+                    acc.retain(|member, score| match input.get(member) {
+                        Some(other) => {
+                            *score = aggregate.apply(*score, *other);
+                            true
+                        }
+                        None => false,
+                    });
+                }
+                SetOp::Union => {
+                    for (member, other) in input {
+                        acc.entry(member)
+                            .and_modify(|score| *score = aggregate.apply(*score, other))
+                            .or_insert(other);
+                    }
+                }
+                SetOp::Diff => acc.retain(|member, _| !input.contains_key(member)),
+            }
+        }
+
+        let mut result = ZSet::new();
+        for (member, score) in acc {
+            result.insert(member, score);
+        }
+        Ok(result)
+    }
+
+    // Like zcombine, but overwrites `destination` with the result (removing it when the result
+    // is empty). Returns the size of the stored sorted set.
+    pub fn zcombine_store(
+        &self,
+        op: SetOp,
+        destination: Vec<u8>,
+        keys: &[Vec<u8>],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> Result<usize, WrongType> {
+        let result = self.zcombine(op, keys, weights, aggregate)?;
+        let len = result.len();
+        if result.is_empty() {
+            self.del(&destination);
+        } else {
+            self.store(destination, Value::ZSet(result));
+        }
+        Ok(len)
+    }
+
+    // The members of the geospatial index at `key` matching the query, see GeoQuery::search.
+    pub fn geosearch(&self, key: &[u8], query: &GeoQuery) -> Result<Vec<GeoMatch>, GeoError> {
+        let zset = self.get_as(key, Value::as_zset)?;
+        self.record_read(key, zset.is_some());
+        let Some(zset) = zset else {
+            return Ok(vec![]);
+        };
+        let center = match &query.origin {
+            GeoOrigin::Member(member) => {
+                geohash_decode(zset.score(member).ok_or(GeoError::NoMember)? as u64)
+            }
+            GeoOrigin::LonLat(lon, lat) => (*lon, *lat),
+        };
+        Ok(query.search(center, zset.iter()))
+    }
+
+    // Like geosearch, but overwrites `destination` with the matches (removing it when there are
+    // none), scored by their geohash or by their distance in the unit of the query. Returns how
+    // many were stored.
+    pub fn geosearch_store(
+        &self,
+        destination: Vec<u8>,
+        key: &[u8],
+        query: &GeoQuery,
+        store_dist: bool,
+    ) -> Result<usize, GeoError> {
+        let matches = self.geosearch(key, query)?;
+        let mut result = ZSet::new();
+        for found in matches {
+            let score = if store_dist {
+                found.distance / query.unit.meters()
+            } else {
+                found.hash as f64
+            };
+            result.insert(found.member, score);
+        }
+        let len = result.len();
+        if result.is_empty() {
+            self.del(&destination);
+        } else {
+            self.store(destination, Value::ZSet(result));
+        }
+        Ok(len)
+    }
+
+    // Copies the (member, score) pairs of a sorted set, or of a plain set with all scores at 1.
+    fn zmembers(&self, key: &[u8]) -> Result<Vec<(String, f64)>, WrongType> {
+        self.expire_if_needed(key);
+        let Some(value) = self.keyspace.get(key) else {
+            return Ok(vec![]);
+        };
+        match &*value {
+            Value::ZSet(zset) => Ok(zset.iter().map(|(m, s)| (m.to_string(), s)).collect()),
+            Value::Set(set) => Ok(set.iter().map(|m| (m.into_owned(), 1.0)).collect()),
+            _ => Err(WrongType),
+        }
+    }
+
+    // Returns one page of (member, score) pairs of the sorted set and the cursor to continue from,
+    // 0 when the iteration is complete.
+    pub fn zscan(
+        &self,
+        key: &[u8],
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+    ) -> Result<(u64, Vec<(String, f64)>), WrongType> {
+        Ok(match self.get_as(key, Value::as_zset)? {
+            Some(zset) => {
+                let (next, page) = scan::scan_page(zset.iter(), cursor, count, pattern);
+                let page = page.into_iter().map(|(m, s)| (m.to_string(), s));
+                (next, page.collect())
+            }
+            None => (0, vec![]),
+        })
+    }
+
+    pub fn zrange(
+        &self,
+        key: &[u8],
+        range: &ZRangeSpec,
+        rev: bool,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Result<Vec<(String, f64)>, WrongType> {
+        let members = self
+            .get_as(key, Value::as_zset)?
+            .map(|v| v.range(range, rev, offset, count));
+        self.record_read(key, members.is_some());
+        Ok(members.unwrap_or_default())
+    }
+
+    // Appends an entry to the stream, creating it unless `nomkstream` is set. Returns the ID of
+    // the entry, None when there is no stream and it wasn't created.
+    pub fn xadd(
+        &self,
+        key: &[u8],
+        id: XAddId,
+        fields: StreamFields,
+        nomkstream: bool,
+    ) -> Result<Option<StreamId>, StreamError> {
+        let now = self.now_ms().max(0) as u64;
+        self.expire_if_needed(key);
+        let added = self.keyspace.upsert(key.to_vec(), |value| match value {
+            Some(value) => match value.as_stream_mut() {
+                Some(stream) => (stream.add(id, fields, now).map(Some), None),
+                None => (Err(WrongType.into()), None),
+            },
+            None if nomkstream => (Ok(None), None),
+            // nothing is created when the ID is rejected
+            None => {
+                let mut stream = Stream::new();
+                match stream.add(id, fields, now) {
+                    Ok(id) => (Ok(Some(id)), Some(Value::Stream(stream))),
+                    Err(e) => (Err(e), None),
+                }
+            }
+        });
+        let Some(id) = added? else {
+            return Ok(None);
+        };
+        self.account(key);
+        self.record_access(key, true);
+        self.signal_key(key);
+        Ok(Some(id))
+    }
+
+    pub fn xlen(&self, key: &[u8]) -> Result<usize, WrongType> {
+        let stream = self.get_as(key, Value::as_stream)?;
+        self.record_read(key, stream.is_some());
+        Ok(stream.map_or(0, |v| v.len()))
+    }
+
+    // Removes the oldest entries of the stream, see Stream::trim. Returns how many.
+    pub fn xtrim(&self, key: &[u8], options: TrimOptions) -> Result<usize, WrongType> {
+        let node_size = self.config().read().stream_node_max_entries;
+        let Some(mut stream) = self.get_mut_as(key, Value::as_stream_mut)? else {
+            return Ok(0);
+        };
+        let trimmed = stream.trim(options, node_size);
+        drop(stream);
+        self.record_access(key, true);
+        Ok(trimmed)
+    }
+
+    // Deletes the entries of the stream, returning how many existed.
+    pub fn xdel(&self, key: &[u8], ids: &[StreamId]) -> Result<usize, WrongType> {
+        let Some(mut stream) = self.get_mut_as(key, Value::as_stream_mut)? else {
+            return Ok(0);
+        };
+        let deleted = ids.iter().filter(|id| stream.delete(**id)).count();
+        drop(stream);
+        self.record_access(key, true);
+        Ok(deleted)
+    }
+
+    // The greatest ID ever added to the stream, what "$" stands for in XREAD.
+    pub fn xlast_id(&self, key: &[u8]) -> Result<Option<StreamId>, WrongType> {
+        Ok(self.get_as(key, Value::as_stream)?.map(|v| v.last_id()))
+    }
+
+    // Up to `count` entries of the stream with IDs in `range`, see Stream::range.
+    pub fn xrange(
+        &self,
+        key: &[u8],
+        range: (Bound<StreamId>, Bound<StreamId>),
+        count: Option<usize>,
+        rev: bool,
+    ) -> Result<Vec<(StreamId, StreamFields)>, WrongType> {
+        let entries = self
+            .get_as(key, Value::as_stream)?
+            .map(|v| v.range(range, count, rev));
+        self.record_read(key, entries.is_some());
+        Ok(entries.unwrap_or_default())
+    }
+
+    // Adds a consumer group delivering the entries after `id`, or after the last ID when None
+    // ("$"). Unless `mkstream` is set the stream has to exist.
+    pub fn xgroup_create(
+        &self,
+        key: &[u8],
+        group: &str,
+        id: Option<StreamId>,
+        mkstream: bool,
+    ) -> Result<(), StreamError> {
+        self.expire_if_needed(key);
+        let created = self.keyspace.upsert(key.to_vec(), |value| match value {
+            Some(value) => match value.as_stream_mut() {
+                Some(stream) => {
+                    let id = id.unwrap_or(stream.last_id());
+                    (Ok(stream.create_group(group, id)), None)
+                }
+                None => (Err(WrongType.into()), None),
+            },
+            None if !mkstream => (Err(StreamError::NoKey), None),
+            None => {
+                let mut stream = Stream::new();
+                stream.create_group(group, id.unwrap_or(StreamId::MIN));
+                (Ok(true), Some(Value::Stream(stream)))
+            }
+        })?;
+        if !created {
+            return Err(StreamError::GroupExists);
+        }
+        self.account(key);
+        self.record_access(key, true);
+        Ok(())
+    }
+
+    // Sets the last delivered ID of the group, the last ID of the stream when None ("$").
+    pub fn xgroup_setid(
+        &self,
+        key: &[u8],
+        group: &str,
+        id: Option<StreamId>,
+    ) -> Result<(), StreamError> {
+        self.with_stream(key, |stream| {
+            let last_id = stream.last_id();
+            let group = stream
+                .group_mut(group)
+                .ok_or_else(|| no_group(key, group))?;
+            group.last_delivered = id.unwrap_or(last_id);
+            Ok(())
+        })
+    }
+
+    pub fn xgroup_destroy(&self, key: &[u8], group: &str) -> Result<bool, StreamError> {
+        self.with_stream(key, |stream| Ok(stream.destroy_group(group)))
+    }
+
+    pub fn xgroup_create_consumer(
+        &self,
+        key: &[u8],
+        group: &str,
+        consumer: &str,
+    ) -> Result<bool, StreamError> {
+        let now = self.now_ms().max(0) as u64;
+        self.with_stream(key, |stream| {
+            let group = stream
+                .group_mut(group)
+                .ok_or_else(|| no_group(key, group))?;
+            Ok(group.create_consumer(consumer, now))
+        })
+    }
+
+    // Removes the consumer from the group, returning the number of entries it had pending.
+    pub fn xgroup_delete_consumer(
+        &self,
+        key: &[u8],
+        group: &str,
+        consumer: &str,
+    ) -> Result<usize, StreamError> {
+        self.with_stream(key, |stream| {
+            let group = stream
+                .group_mut(group)
+                .ok_or_else(|| no_group(key, group))?;
+            Ok(group.delete_consumer(consumer).unwrap_or(0))
+        })
+    }
+
+    // Reads from the stream as `consumer` of `group`, see Stream::read_group.
+    pub fn xreadgroup(
+        &self,
+        key: &[u8],
+        group: &str,
+        consumer: &str,
+        after: Option<StreamId>,
+        count: Option<usize>,
+        noack: bool,
+    ) -> Result<Vec<GroupEntry>, StreamError> {
+        let now = self.now_ms().max(0) as u64;
+        let entries = self
+            .get_mut_as(key, Value::as_stream_mut)?
+            .and_then(|mut stream| stream.read_group(group, consumer, after, count, noack, now));
+        let entries = entries.ok_or_else(|| no_group(key, group))?;
+        self.record_read(key, true);
+        Ok(entries)
+    }
+
+    // Acknowledges the entries pending in the group, returning how many were pending.
+    pub fn xack(&self, key: &[u8], group: &str, ids: &[StreamId]) -> Result<usize, WrongType> {
+        let Some(mut stream) = self.get_mut_as(key, Value::as_stream_mut)? else {
+            return Ok(0);
+        };
+        let Some(group) = stream.group_mut(group) else {
+            return Ok(0);
+        };
+        let acked = ids.iter().filter(|id| group.ack(**id)).count();
+        drop(stream);
+        self.record_access(key, true);
+        Ok(acked)
+    }
+
+    pub fn xinfo_stream(&self, key: &[u8]) -> Result<StreamInfo, StreamError> {
+        let info = self.get_as(key, Value::as_stream)?.map(|v| v.info());
+        self.record_read(key, info.is_some());
+        info.ok_or(StreamError::NoSuchKey)
+    }
+
+    pub fn xinfo_groups(&self, key: &[u8]) -> Result<Vec<GroupInfo>, StreamError> {
+        let groups = self.get_as(key, Value::as_stream)?.map(|v| v.group_infos());
+        self.record_read(key, groups.is_some());
+        groups.ok_or(StreamError::NoSuchKey)
+    }
+
+    pub fn xinfo_consumers(
+        &self,
+        key: &[u8],
+        group: &str,
+    ) -> Result<Vec<ConsumerInfo>, StreamError> {
+        let now = self.now_ms().max(0) as u64;
+        let stream = self
+            .get_as(key, Value::as_stream)?
+            .ok_or(StreamError::NoSuchKey)?;
+        let group = stream.group(group).ok_or_else(|| no_group(key, group))?;
+        let consumers = group.consumer_infos(now);
+        drop(stream);
+        self.record_read(key, true);
+        Ok(consumers)
+    }
+
+    // Runs `f` on the stream at `key`, which XGROUP requires to exist.
+    fn with_stream<T>(
+        &self,
+        key: &[u8],
+        f: impl FnOnce(&mut Stream) -> Result<T, StreamError>,
+    ) -> Result<T, StreamError> {
+        let result = match self.get_mut_as(key, Value::as_stream_mut)? {
+            Some(mut stream) => f(&mut stream)?,
+            None => return Err(StreamError::NoKey),
+        };
+        self.record_access(key, true);
+        Ok(result)
+    }
+
+    // Appends the values to the tail of the list. Returns the length of the list after the push.
+    pub fn rpush(
+        &self,
+        key: impl Into<Vec<u8>>,
+        values: impl IntoIterator<Item = RespFrame>,
+    ) -> Result<usize, WrongType> {
+        let key = key.into();
+        self.record_access(&key, true);
+        let limits = self.encoding_limits();
+        let len = {
+            let mut list = self.entry_as(
+                key.clone(),
+                || Value::List(ListValue::default()),
+                Value::as_list_mut,
+            )?;
+            list.extend(values, &limits);
+            list.len()
+        };
+        self.signal_key(&key);
+        Ok(len)
+    }
+
+    // Pops up to `count` elements from the first non-empty list among `keys`, from the head
+    // when `left` is true, otherwise from the tail. Emptied lists are removed.
+    pub fn lmpop(
+        &self,
+        keys: &[Vec<u8>],
+        left: bool,
+        count: usize,
+    ) -> Result<Option<Popped>, WrongType> {
+        for key in keys {
+            let popped = match self.get_mut_as(key, Value::as_list_mut)? {
+                Some(mut list) if !list.is_empty() => {
+                    let n = count.min(list.len());
+                    (0..n)
+                        .filter_map(|_| {
+                            if left {
+                                list.pop_front()
+                            } else {
+                                list.pop_back()
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                }
+                _ => continue,
+            };
+            self.remove_if_empty(key);
+            return Ok(Some((key.clone(), popped)));
+        }
+        Ok(None)
+    }
+
+    // Pops an element from the head of `source` when `from_left` is true, otherwise from its
+    // tail, and pushes it to the head of `destination` when `to_left` is true, otherwise to its
+    // tail. Returns None, moving nothing, when the source list is empty.
+    pub fn lmove(
+        &self,
+        source: &[u8],
+        destination: &[u8],
+        from_left: bool,
+        to_left: bool,
+    ) -> Result<Option<RespFrame>, WrongType> {
+        // the destination is checked first so that a failing move leaves the source untouched
+        self.get_as(destination, Value::as_list)?;
+        let element = match self.get_mut_as(source, Value::as_list_mut)? {
+            Some(mut list) => match from_left {
+                true => list.pop_front(),
+                false => list.pop_back(),
+            },
+            None => None,
+        };
+        let Some(element) = element else {
+            return Ok(None);
+        };
+        self.remove_if_empty(source);
+        self.record_access(destination, true);
+        let limits = self.encoding_limits();
+        {
+            let mut list = self.entry_as(
+                destination.to_vec(),
+                || Value::List(ListValue::default()),
+                Value::as_list_mut,
+            )?;
+            match to_left {
+                true => list.push_front(element.clone(), &limits),
+                false => list.push_back(element.clone(), &limits),
+            }
+        }
+        self.signal_key(destination);
+        Ok(Some(element))
+    }
+
+    // Returns the indexes of the elements equal to `element`, scanning from the head for a
+    // positive rank and from the tail for a negative one. Indexes are always counted from the head.
+    pub fn lpos(
+        &self,
+        key: &[u8],
+        element: &RespFrame,
+        rank: i64,
+        count: usize,
+        maxlen: usize,
+    ) -> Result<Vec<usize>, WrongType> {
+        let list = self.get_as(key, Value::as_list)?;
+        self.record_read(key, list.is_some());
+        let Some(list) = list else {
+            return Ok(vec![]);
+        };
+
+        let len = list.len();
+        let maxlen = if maxlen == 0 { len } else { maxlen.min(len) };
+        let skip = rank.unsigned_abs() as usize - 1;
+        let indexed: Box<dyn Iterator<Item = (usize, _)>> = if rank > 0 {
+            Box::new(list.iter().enumerate().take(maxlen))
+        } else {
+            Box::new(
+                list.iter()
+                    .rev()
+                    .enumerate()
+                    .map(|(i, e)| (len - 1 - i, e))
+                    .take(maxlen),
+            )
+        };
+
+        let matches = indexed
+            .filter(|(_, e)| **e == *element)
+            .map(|(i, _)| i)
+            .skip(skip);
+        Ok(if count == 0 {
+            matches.collect()
+        } else {
+            matches.take(count).collect()
+        })
+    }
+}
+
+fn no_group(key: &[u8], group: &str) -> StreamError {
+    StreamError::NoGroup {
+        key: String::from_utf8_lossy(key).into_owned(),
+        group: group.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, SimpleError};
+    use anyhow::Result;
+    use std::ops::Bound;
+
+    #[test]
+    fn test_del_exists() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"string".to_vec(), BulkString::from("v").into());
+        backend.hset(
+            b"hash".to_vec(),
+            "f".to_string(),
+            BulkString::from("v").into(),
+        )?;
+        backend.sadd("set", ["m".to_string()])?;
+
+        for key in ["string", "hash", "set"].map(str::as_bytes) {
+            assert!(backend.exists(key));
+            assert!(backend.del(key));
+            assert!(!backend.exists(key));
+            assert!(!backend.del(key));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_versions() -> Result<()> {
+        let backend = Backend::new();
+        let missing = backend.version(b"key");
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        let written = backend.version(b"key");
+        assert!(written > missing);
+        backend.get(b"key")?;
+        assert_eq!(backend.version(b"key"), written);
+
+        // any write, in place or of the expiry
+        backend.sadd("set", ["a".to_string()])?;
+        let set = backend.version(b"set");
+        backend.sadd("set", ["b".to_string()])?;
+        assert!(backend.version(b"set") > set);
+        assert_eq!(backend.version(b"key"), written);
+        backend.expire_at(b"key", backend.now_ms() + 10_000, ExpireCondition::Always);
+        let volatile = backend.version(b"key");
+        assert!(volatile > written);
+        assert!(backend.persist(b"key"));
+        assert!(backend.version(b"key") > volatile);
+
+        // removing the key and creating it again still shows
+        let before = backend.version(b"key");
+        backend.del(b"key");
+        let removed = backend.version(b"key");
+        assert!(removed > before);
+        assert!(!backend.del(b"key"));
+        assert_eq!(backend.version(b"key"), removed);
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        assert!(backend.version(b"key") > removed);
+
+        let before = backend.version(b"set");
+        backend.flush(false);
+        assert!(backend.version(b"set") > before);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unlink() -> Result<()> {
+        let backend = Backend::new();
+        let members = (0..10_000).map(|i| i.to_string());
+        backend.sadd("big", members)?;
+        backend.sadd("small", ["m".to_string()])?;
+
+        assert!(backend.unlink(b"big"));
+        assert!(backend.unlink(b"small"));
+        assert!(!backend.exists(b"big"));
+        assert!(!backend.unlink(b"big"));
+
+        // values overwritten or expired go the same way when configured to
+        backend.config().set(&[
+            ("lazyfree-lazy-server-del".to_string(), "yes".to_string()),
+            ("lazyfree-lazy-expire".to_string(), "yes".to_string()),
+        ])?;
+        let members = (0..10_000).map(|i| i.to_string());
+        backend.sadd("big", members.clone())?;
+        backend.set(b"big".to_vec(), BulkString::from("v").into());
+        assert_eq!(backend.get(b"big")?, Some(BulkString::from("v").into()));
+        backend.sadd("volatile", members)?;
+        backend.expire_at(b"volatile", backend.now_ms() - 1, ExpireCondition::Always);
+        assert!(!backend.exists(b"volatile"));
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while backend.lazyfree_pending() > 0 {
+            assert!(std::time::Instant::now() < deadline);
+            std::thread::yield_now();
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan() -> Result<()> {
+        let backend = Backend::new();
+        for i in 0..50 {
+            backend.set(
+                format!("str:{i}").into_bytes(),
+                BulkString::from("v").into(),
+            );
+            backend.sadd(format!("set:{i}"), ["m".to_string()])?;
+        }
+        assert_eq!(backend.key_type(b"str:0"), Some("string"));
+        assert_eq!(backend.key_type(b"set:0"), Some("set"));
+        assert_eq!(backend.key_type(b"nokey"), None);
+
+        let mut cursor = 0;
+        let mut seen = HashSet::new();
+        loop {
+            let (next, keys) = backend.scan(cursor, 7, None, Some("set"));
+            // keys added in the middle of the scan must not break it
+            backend.set(
+                format!("new:{cursor}").into_bytes(),
+                BulkString::from("v").into(),
+            );
+            for key in keys {
+                assert!(key.starts_with(b"set:"));
+                seen.insert(key);
+            }
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert_eq!(seen.len(), 50);
+
+        let (next, keys) = backend.scan(0, 1000, Some(b"str:1?"), None);
+        assert_eq!(next, 0);
+        assert_eq!(keys.len(), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn test_expire_at() {
+        let backend = Backend::new();
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        let later = backend.now_ms() + 10_000;
+
+        assert!(!backend.expire_at(b"nokey", later, ExpireCondition::Always));
+        assert!(!backend.expire_at(b"key", later, ExpireCondition::Xx));
+        assert!(!backend.expire_at(b"key", later, ExpireCondition::Gt));
+        assert!(backend.expire_at(b"key", later, ExpireCondition::Nx));
+        assert!(!backend.expire_at(b"key", later - 1, ExpireCondition::Gt));
+        assert!(backend.expire_at(b"key", later - 1, ExpireCondition::Lt));
+        assert_eq!(backend.keyspace.expire_at(b"key"), Some(later - 1));
+
+        // overwriting the value clears the expiry
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        assert_eq!(backend.keyspace.expire_at(b"key"), None);
+
+        assert!(backend.expire_at(b"key", backend.now_ms() - 1, ExpireCondition::Always));
+        assert!(!backend.exists(b"key"));
+    }
+
+    #[test]
+    fn test_expiry_with_manual_clock() {
+        let clock = Arc::new(ManualClock::new(Duration::from_secs(1_000)));
+        let backend = Backend::with_clock(ConfigValues::default(), clock.clone());
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        assert!(backend.expire_at(b"key", 1_000_500, ExpireCondition::Always));
+        assert_eq!(backend.expiry(b"key"), KeyExpiry::At(1_000_500));
+
+        clock.advance(Duration::from_millis(499));
+        assert_eq!(backend.idle_time(b"key"), Some(499));
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(backend.expiry(b"key"), KeyExpiry::Missing);
+        assert_eq!(backend.idle_time(b"key"), None);
+    }
+
+    #[test]
+    fn test_lazy_expire() -> Result<()> {
+        let clock = Arc::new(ManualClock::new(Duration::from_secs(1_000)));
+        let backend = Backend::with_clock(ConfigValues::default(), clock.clone());
+        backend.set(b"string".to_vec(), BulkString::from("v").into());
+        backend.sadd("set", ["a".to_string()])?;
+        backend.set(b"persistent".to_vec(), BulkString::from("v").into());
+        for key in ["string", "set"].map(str::as_bytes) {
+            assert!(backend.expire_at(key, 1_000_100, ExpireCondition::Always));
+        }
+
+        clock.advance(Duration::from_millis(100));
+        // still stored until accessed
+        assert_eq!(backend.key_count(), (3, 2));
+        assert_eq!(backend.db_size(), 1);
+        assert_eq!(backend.get(b"string")?, None);
+        assert_eq!(backend.key_count(), (2, 1));
+        // a write starts over from an empty value, without the old expiry
+        assert_eq!(backend.sadd("set", ["b".to_string()])?, 1);
+        assert_eq!(backend.smembers(b"set")?, ["b"]);
+        assert_eq!(backend.expiry(b"set"), KeyExpiry::Persistent);
+        assert_eq!(backend.stats().expired_keys(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_active_expire_cycle() {
+        let clock = Arc::new(ManualClock::new(Duration::from_secs(1_000)));
+        let backend = Backend::with_clock(ConfigValues::default(), clock.clone());
+        for i in 0..100 {
+            let key = format!("key{i}").into_bytes();
+            backend.set(key.clone(), BulkString::from("v").into());
+            // every other key expires
+            if i % 2 == 0 {
+                assert!(backend.expire_at(&key, 1_000_100, ExpireCondition::Always));
+            }
+        }
+        let other = backend.clone();
+        other.select(1);
+        other.set(b"key".to_vec(), BulkString::from("v").into());
+        assert!(other.expire_at(b"key", 1_000_100, ExpireCondition::Always));
+
+        assert_eq!(backend.active_expire_cycle(Duration::from_secs(1)), 0);
+        clock.advance(Duration::from_millis(100));
+        // the volatile keys all expired, so sampling goes on until none is left
+        assert_eq!(backend.active_expire_cycle(Duration::from_secs(1)), 51);
+        assert_eq!(backend.key_count(), (50, 0));
+        assert_eq!(other.key_count(), (0, 0));
+        assert_eq!(backend.stats().expired_keys(), 51);
+    }
+
+    #[test]
+    fn test_random_key() {
+        let backend = Backend::new();
+        assert_eq!(backend.random_key(), None);
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        backend.set(b"expired".to_vec(), BulkString::from("v").into());
+        backend
+            .keyspace
+            .set_expire(b"expired".to_vec(), backend.now_ms() - 1);
+        for _ in 0..10 {
+            assert_eq!(backend.random_key().as_deref(), Some(&b"key"[..]));
+        }
+        backend
+            .keyspace
+            .set_expire(b"key".to_vec(), backend.now_ms() - 1);
+        assert_eq!(backend.random_key(), None);
+        assert_eq!(backend.key_count(), (0, 0));
+    }
+
+    #[test]
+    fn test_expiry_persist() {
+        let backend = Backend::new();
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        assert_eq!(backend.expiry(b"nokey"), KeyExpiry::Missing);
+        assert_eq!(backend.expiry(b"key"), KeyExpiry::Persistent);
+        assert!(!backend.persist(b"key"));
+
+        let later = backend.now_ms() + 10_000;
+        backend.expire_at(b"key", later, ExpireCondition::Always);
+        assert_eq!(backend.expiry(b"key"), KeyExpiry::At(later));
+        assert!(backend.persist(b"key"));
+        assert_eq!(backend.expiry(b"key"), KeyExpiry::Persistent);
+
+        // expired but not removed yet, which the next access does
+        backend
+            .keyspace
+            .set_expire(b"key".to_vec(), backend.now_ms() - 1);
+        assert!(!backend.persist(b"key"));
+        assert_eq!(backend.expiry(b"key"), KeyExpiry::Missing);
+        assert!(!backend.keyspace.contains_key(b"key"));
+    }
+
+    #[test]
+    fn test_copy() -> Result<()> {
+        let backend = Backend::new();
+        backend.hset(
+            b"src".to_vec(),
+            "f".to_string(),
+            BulkString::from("v").into(),
+        )?;
+        backend.expire_at(b"src", backend.now_ms() + 10_000, ExpireCondition::Always);
+        backend.set(b"dst".to_vec(), BulkString::from("v").into());
+
+        assert!(!backend.copy(b"nokey", b"dst", 0, true));
+        assert!(!backend.copy(b"src", b"dst", 0, false));
+        assert!(backend.copy(b"src", b"dst", 0, true));
+        assert_eq!(backend.key_type(b"dst"), Some("hash"));
+        assert_eq!(backend.expiry(b"dst"), backend.expiry(b"src"));
+
+        // the copy is independent of the source
+        backend.hset(
+            b"src".to_vec(),
+            "g".to_string(),
+            BulkString::from("v").into(),
+        )?;
+        assert_eq!(backend.hget(b"dst", "g")?, None);
+
+        // to another database, under the same name
+        assert!(backend.copy(b"src", b"src", 1, false));
+        assert!(backend.select(1));
+        assert_eq!(backend.key_type(b"src"), Some("hash"));
+        assert!(!backend.exists(b"dst"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_select() {
+        let backend = Backend::new();
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        assert!(backend.select(15));
+        assert!(!backend.select(16));
+        assert_eq!(backend.selected_db(), 15);
+        assert!(!backend.exists(b"key"));
+
+        // every handle selects its own database
+        let other = Backend::new();
+        let cloned = other.clone();
+        assert!(cloned.select(3));
+        assert_eq!(other.selected_db(), 0);
+
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        backend.flush(false);
+        assert!(backend.select(0));
+        assert!(backend.exists(b"key"));
+        backend.flush_all(false);
+        assert!(!backend.exists(b"key"));
+    }
+
+    #[test]
+    fn test_touch_idle_time() {
+        let backend = Backend::new();
+        assert!(!backend.touch(b"key"));
+        assert_eq!(backend.idle_time(b"key"), None);
+
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        backend
+            .access
+            .insert(b"key".to_vec(), KeyAccess::new(backend.now_ms() - 5_000));
+        assert!(backend.idle_time(b"key").is_some_and(|idle| idle >= 5_000));
+        assert!(backend.touch(b"key"));
+        assert!(backend.idle_time(b"key").is_some_and(|idle| idle < 5_000));
+
+        backend.del(b"key");
+        assert!(!backend.access.contains_key(&b"key"[..]));
+    }
+
+    #[test]
+    fn test_encoding() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"int".to_vec(), BulkString::from("12345").into());
+        backend.set(b"short".to_vec(), BulkString::from("hello").into());
+        backend.set(b"long".to_vec(), BulkString::from("x".repeat(45)).into());
+        backend.sadd("set", ["m".to_string()])?;
+        backend.sadd("ints", ["1".to_string(), "-2".to_string()])?;
+
+        assert_eq!(backend.encoding(b"int"), Some("int"));
+        assert_eq!(backend.encoding(b"short"), Some("embstr"));
+        assert_eq!(backend.encoding(b"long"), Some("raw"));
+        assert_eq!(backend.encoding(b"set"), Some("hashtable"));
+        assert_eq!(backend.encoding(b"ints"), Some("intset"));
+        backend.scombine_store(SetOp::Union, b"both".to_vec(), &[b"ints".to_vec()])?;
+        assert_eq!(backend.encoding(b"both"), Some("intset"));
+        backend.sadd("ints", ["m".to_string()])?;
+        assert_eq!(backend.encoding(b"ints"), Some("hashtable"));
+        assert_eq!(backend.encoding(b"nokey"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_listpack_encoding() -> Result<()> {
+        let backend = Backend::new();
+        backend.config().set(&[
+            ("hash-max-listpack-entries".to_string(), "2".to_string()),
+            ("list-max-listpack-size".to_string(), "2".to_string()),
+        ])?;
+        let value = |v: &str| RespFrame::from(BulkString::from(v));
+        backend.hset(b"hash".to_vec(), "a".to_string(), value("1"))?;
+        backend.hset(b"hash".to_vec(), "b".to_string(), value("2"))?;
+        backend.rpush("list", [value("a"), value("b")])?;
+        assert_eq!(backend.encoding(b"hash"), Some("listpack"));
+        assert_eq!(backend.encoding(b"list"), Some("listpack"));
+        let payload = backend.dump(b"list").expect("list exists");
+
+        backend.hset(b"hash".to_vec(), "c".to_string(), value("3"))?;
+        backend.rpush("list", [value("c")])?;
+        assert_eq!(backend.encoding(b"hash"), Some("hashtable"));
+        assert_eq!(backend.encoding(b"list"), Some("quicklist"));
+        assert_eq!(backend.hget(b"hash", "a")?, Some(value("1")));
+        assert_eq!(backend.lpos(b"list", &value("a"), 1, 0, 0)?, vec![0]);
+
+        // restored values are packed again when small enough
+        backend.restore(b"copy", &payload, None, false, None)?;
+        assert_eq!(backend.encoding(b"copy"), Some("listpack"));
+        assert_eq!(backend.lpos(b"copy", &value("b"), -1, 0, 0)?, vec![1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dump_restore() -> Result<()> {
+        let backend = Backend::new();
+        backend.rpush("list", ["a", "b"].map(|v| BulkString::from(v).into()))?;
+        let payload = backend.dump(b"list").expect("list exists");
+        assert_eq!(backend.dump(b"nokey"), None);
+
+        assert_eq!(
+            backend.restore(b"list", &payload, None, false, None),
+            Err(DumpError::BusyKey)
+        );
+        let later = backend.now_ms() + 10_000;
+        assert_eq!(
+            backend.restore(b"copy", &payload, Some(later), false, Some(5_000)),
+            Ok(())
+        );
+        assert_eq!(backend.expiry(b"copy"), KeyExpiry::At(later));
+        assert!(backend.idle_time(b"copy").is_some_and(|idle| idle >= 5_000));
+        assert_eq!(
+            backend.lpos(b"copy", &BulkString::from("b").into(), 1, 1, 0)?,
+            vec![1]
+        );
+
+        assert_eq!(
+            backend.restore(b"list", &payload, Some(backend.now_ms() - 1), true, None),
+            Ok(())
+        );
+        assert!(!backend.exists(b"list"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush() -> Result<()> {
+        for lazy in [false, true] {
+            let backend = Backend::new();
+            backend.set(b"key".to_vec(), BulkString::from("v").into());
+            backend.sadd("set", (0..1000).map(|i| i.to_string()))?;
+            backend.expire_at(b"set", backend.now_ms() + 10_000, ExpireCondition::Always);
+            backend.flush(lazy);
+            assert_eq!(backend.key_count(), (0, 0));
+            assert!(!backend.exists(b"set"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_save() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("simple-redis-save-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let backend = Backend::new();
+        backend.config().set(&[
+            ("dir".to_string(), dir.to_string_lossy().to_string()),
+            ("dbfilename".to_string(), "test.rdb".to_string()),
+        ])?;
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        backend.record_write(&RespFrame::Integer(1));
+        backend.record_write(&SimpleError::new("ERR failed").into());
+        assert_eq!(backend.save_status().changes(), 1);
+        backend.save()?;
+        assert_eq!(backend.save_status().changes(), 0);
+
+        let data = fs::read(dir.join("test.rdb"))?;
+        assert!(data.starts_with(b"SREDIS"));
+        assert_eq!(fs::read_dir(&dir)?.count(), 1);
+
+        // a restarted server picks up the keys and the function libraries
+        let library = FunctionLibrary {
+            name: "lib".to_string(),
+            code: "#!lua name=lib".to_string(),
+            functions: vec![],
+        };
+        backend.functions().insert(library.clone(), false)?;
+        backend.select(1);
+        backend.set(b"other".to_vec(), BulkString::from("v").into());
+        backend.save()?;
+        let restarted = Backend::new();
+        restarted.config().set(&[
+            ("dir".to_string(), dir.to_string_lossy().to_string()),
+            ("dbfilename".to_string(), "test.rdb".to_string()),
+        ])?;
+        assert_eq!(restarted.load()?, 2);
+        assert_eq!(restarted.get(b"key")?, Some(BulkString::from("v").into()));
+        assert!(restarted.database(1).is_some_and(|db| db.exists(b"other")));
+        assert_eq!(restarted.selected_db(), 0);
+        assert_eq!(restarted.functions().list(None), [library]);
+
+        // in the background, waiting for the running save when scheduled
+        backend.set(b"bg".to_vec(), BulkString::from("v").into());
+        assert!(backend.save_status().start());
+        assert!(!backend.bgsave(false));
+        assert!(backend.bgsave(true));
+        backend.save_status().finish(None, 0, 0, 0);
+        let mut loaded = 0;
+        for _ in 0..1000 {
+            loaded = restarted.load()?;
+            if loaded == 3 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(loaded, 3);
+        while backend.save_status().in_progress() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_keyspace_stats() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        backend.get(b"key")?;
+        backend.get(b"nokey")?;
+        backend.hget(b"nokey", "field")?;
+        assert_eq!(backend.stats().keyspace_hits(), 1);
+        assert_eq!(backend.stats().keyspace_misses(), 2);
+
+        // a read is a hit or a miss whatever the type, writes are neither
+        backend.sadd("s1", ["a".to_string(), "b".to_string()])?;
+        backend.sadd("s2", ["b".to_string()])?;
+        backend.smove(b"s1", b"s1".to_vec(), "a".to_string())?;
+        backend.scard(b"s1")?;
+        backend.zcard(b"nozset")?;
+        backend.xlen(b"nostream")?;
+        assert_eq!(backend.stats().keyspace_hits(), 2);
+        assert_eq!(backend.stats().keyspace_misses(), 4);
+        // however many members are probed
+        backend.sintercard(&[b"s1".to_vec(), b"s2".to_vec()], 0)?;
+        assert_eq!(backend.stats().keyspace_hits(), 4);
+        backend.del(b"s1");
+        backend.del(b"s2");
+
+        backend.expire_at(b"key", backend.now_ms() + 10_000, ExpireCondition::Always);
+        backend.sadd("set", ["m".to_string()])?;
+        assert_eq!(backend.key_count(), (2, 1));
+        assert_eq!(backend.db_size(), 2);
+
+        // not removed yet, but already logically gone
+        backend
+            .keyspace
+            .set_expire(b"set".to_vec(), backend.now_ms() - 1);
+        assert_eq!(backend.key_count(), (2, 2));
+        assert_eq!(backend.db_size(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_type() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"string".to_vec(), BulkString::from("v").into());
+        backend.sadd("set", ["m".to_string()])?;
+
+        assert_eq!(backend.get(b"set"), Err(WrongType));
+        assert_eq!(backend.sadd("string", ["m".to_string()]), Err(WrongType));
+        assert_eq!(backend.hget(b"set", "f"), Err(WrongType));
+        assert_eq!(
+            backend.lmpop(&[b"string".to_vec()], true, 1),
+            Err(WrongType)
+        );
+        assert_eq!(backend.xlen(b"set"), Err(WrongType));
+        // sorted sets combine with plain sets, not with strings
+        let keys = [b"set".to_vec(), b"string".to_vec()];
+        let result = backend.zcombine(SetOp::Union, &keys, &[], Aggregate::Sum);
+        assert_eq!(result.map(|zset| zset.len()), Err(WrongType));
+        // nothing leaves the source when the destination holds another type
+        let moved = backend.smove(b"set", b"string".to_vec(), "m".to_string());
+        assert_eq!(moved, Err(WrongType));
+        assert_eq!(backend.scard(b"set")?, 1);
+
+        // a string overwrites any type
+        backend.set(b"set".to_vec(), BulkString::from("v").into());
+        assert_eq!(backend.key_type(b"set"), Some("string"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sadd() -> Result<()> {
+        let backend = Backend::new();
+        let result = backend.sadd("myset", ["Hello".to_string()])?;
+        assert_eq!(result, 1);
+        let result = backend.sadd("myset", ["Hello".to_string(), "World".to_string()])?;
+        assert_eq!(result, 1);
+        assert_eq!(backend.scard(b"myset")?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scombine() -> Result<()> {
+        let backend = Backend::new();
+        backend.sadd("key1", ["a", "b", "c"].map(String::from))?;
+        backend.sadd("key2", ["c", "d", "e"].map(String::from))?;
+        let keys = [b"key1".to_vec(), b"key2".to_vec()];
+
+        let result = backend.scombine(SetOp::Inter, &keys)?;
+        assert_eq!(result, HashSet::from(["c".to_string()]));
+        let result = backend.scombine(SetOp::Union, &keys)?;
+        assert_eq!(result.len(), 5);
+        let result = backend.scombine(SetOp::Diff, &keys)?;
+        assert_eq!(result, HashSet::from(["a".to_string(), "b".to_string()]));
+
+        let len = backend.scombine_store(SetOp::Union, b"key1".to_vec(), &keys)?;
+        assert_eq!(len, 5);
+        assert_eq!(backend.scard(b"key1")?, 5);
+
+        let keys = [b"key1".to_vec(), b"nokey".to_vec()];
+        let len = backend.scombine_store(SetOp::Inter, b"key2".to_vec(), &keys)?;
+        assert_eq!(len, 0);
+        assert!(!backend.exists(b"key2"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_spop_srandmember() -> Result<()> {
+        let backend = Backend::new();
+        backend.sadd("myset", ["one", "two", "three"].map(String::from))?;
+
+        let members = backend.srandmember(b"myset", 5)?;
+        assert_eq!(members.len(), 3);
+        let members = backend.srandmember(b"myset", -5)?;
+        assert_eq!(members.len(), 5);
+        assert!(members
+            .iter()
+            .all(|m| backend.sismember(b"myset", m) == Ok(true)));
+
+        let popped = backend.spop(b"myset", 2)?;
+        assert_eq!(popped.len(), 2);
+        assert_eq!(backend.scard(b"myset")?, 1);
+        assert!(popped
+            .iter()
+            .all(|m| backend.sismember(b"myset", m) == Ok(false)));
+
+        let popped = backend.spop(b"myset", 2)?;
+        assert_eq!(popped.len(), 1);
+        assert!(!backend.exists(b"myset"));
+        assert!(backend.srandmember(b"myset", -1)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sintercard() -> Result<()> {
+        let backend = Backend::new();
+        backend.sadd("key1", ["a", "b", "c", "d"].map(String::from))?;
+        backend.sadd("key2", ["c", "d", "e"].map(String::from))?;
+        let keys = [b"key1".to_vec(), b"key2".to_vec()];
+
+        assert_eq!(backend.sintercard(&keys, 0)?, 2);
+        assert_eq!(backend.sintercard(&keys, 1)?, 1);
+        assert_eq!(backend.sintercard(&keys, 5)?, 2);
+
+        let keys = [b"key1".to_vec(), b"nokey".to_vec()];
+        assert_eq!(backend.sintercard(&keys, 0)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_smove() -> Result<()> {
+        let backend = Backend::new();
+        backend.sadd("myset", ["one", "two"].map(String::from))?;
+        backend.sadd("myotherset", ["three".to_string()])?;
+
+        assert!(backend.smove(b"myset", b"myotherset".to_vec(), "two".to_string())?);
+        assert!(backend.sismember(b"myotherset", "two")?);
+        assert!(!backend.sismember(b"myset", "two")?);
+        assert!(!backend.smove(b"myset", b"myotherset".to_vec(), "four".to_string())?);
+
+        assert!(backend.smove(b"myset", b"newset".to_vec(), "one".to_string())?);
+        assert!(!backend.exists(b"myset"));
+        assert_eq!(backend.smembers(b"newset")?, vec!["one".to_string()]);
+
+        // moves back and forth between two sets never lose or duplicate the member
+        thread::scope(|scope| {
+            for (from, to) in [("newset", "myset"), ("myset", "newset")] {
+                let backend = &backend;
+                scope.spawn(move || {
+                    for _ in 0..500 {
+                        backend
+                            .smove(from.as_bytes(), to.into(), "one".to_string())
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        let count = backend.scard(b"myset")? + backend.scard(b"newset")?;
+        assert_eq!(count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_srem() -> Result<()> {
+        let backend = Backend::new();
+        backend.sadd("myset", ["one".to_string(), "two".to_string()])?;
+        let result = backend.srem(b"myset", &["one".to_string(), "three".to_string()])?;
+        assert_eq!(result, 1);
+        assert_eq!(backend.smembers(b"myset")?, vec!["two".to_string()]);
+
+        backend.srem(b"myset", &["two".to_string()])?;
+        assert!(!backend.exists(b"myset"));
+        assert_eq!(backend.srem(b"myset", &["two".to_string()])?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zadd_zrem() -> Result<()> {
+        let backend = Backend::new();
+        let members = vec![(1.0, "one".to_string()), (2.0, "two".to_string())];
+        let outcomes = backend.zadd("myzset", members, ZAddFlags::default())?;
+        assert_eq!(
+            outcomes,
+            vec![ZAddOutcome::Added(1.0), ZAddOutcome::Added(2.0)]
+        );
+        let members = vec![(3.0, "two".to_string())];
+        let outcomes = backend.zadd("myzset", members, ZAddFlags::default())?;
+        assert_eq!(outcomes, vec![ZAddOutcome::Updated(3.0)]);
+        assert_eq!(backend.zscore(b"myzset", "two")?, Some(3.0));
+        assert_eq!(backend.zcard(b"myzset")?, 2);
+
+        let members = ["one".to_string(), "three".to_string()];
+        assert_eq!(backend.zrem(b"myzset", &members)?, 1);
+        assert_eq!(backend.zrem(b"myzset", &["two".to_string()])?, 1);
+        assert!(!backend.exists(b"myzset"));
+        assert_eq!(backend.zcard(b"myzset")?, 0);
+
+        let flags = ZAddFlags {
+            xx: true,
+            ..Default::default()
+        };
+        let outcomes = backend.zadd("myzset", vec![(1.0, "one".to_string())], flags)?;
+        assert_eq!(outcomes, vec![ZAddOutcome::Skipped]);
+        assert!(!backend.exists(b"myzset"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zcombine() -> Result<()> {
+        let backend = Backend::new();
+        let members = vec![(1.0, "one".to_string()), (2.0, "two".to_string())];
+        backend.zadd("zset1", members, ZAddFlags::default())?;
+        let members = vec![
+            (1.0, "one".to_string()),
+            (2.0, "two".to_string()),
+            (3.0, "three".to_string()),
+        ];
+        backend.zadd("zset2", members, ZAddFlags::default())?;
+        let keys = [b"zset1".to_vec(), b"zset2".to_vec()];
+
+        let result = backend.zcombine(SetOp::Inter, &keys, &[2.0, 3.0], Aggregate::Sum)?;
+        let members = result.iter().collect::<Vec<_>>();
+        assert_eq!(members, vec![("one", 5.0), ("two", 10.0)]);
+
+        let result = backend.zcombine(SetOp::Union, &keys, &[], Aggregate::Max)?;
+        let members = result.iter().collect::<Vec<_>>();
+        assert_eq!(members, vec![("one", 1.0), ("two", 2.0), ("three", 3.0)]);
+
+        let keys = [b"zset2".to_vec(), b"zset1".to_vec()];
+        let result = backend.zcombine(SetOp::Diff, &keys, &[], Aggregate::Sum)?;
+        let members = result.iter().collect::<Vec<_>>();
+        assert_eq!(members, vec![("three", 3.0)]);
+
+        backend.sadd("set", ["one".to_string()])?;
+        let keys = [b"zset2".to_vec(), b"set".to_vec()];
+        let len =
+            backend.zcombine_store(SetOp::Inter, b"out".to_vec(), &keys, &[], Aggregate::Sum)?;
+        assert_eq!(len, 1);
+        assert_eq!(backend.zscore(b"out", "one")?, Some(2.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zremrange() -> Result<()> {
+        let backend = Backend::new();
+        let members = (1..=5).map(|i| (i as f64, format!("m{i}"))).collect();
+        backend.zadd("zset", members, ZAddFlags::default())?;
+
+        assert_eq!(backend.zremrange(b"zset", &ZRangeSpec::Rank(0, 1))?, 2);
+        assert_eq!(backend.zcard(b"zset")?, 3);
+        let range = ZRangeSpec::Score(Bound::Excluded(3.0), Bound::Unbounded);
+        assert_eq!(backend.zremrange(b"zset", &range)?, 2);
+        assert_eq!(backend.zremrange(b"zset", &ZRangeSpec::Rank(0, -1))?, 1);
+        assert!(!backend.exists(b"zset"));
+        assert_eq!(backend.zremrange(b"zset", &ZRangeSpec::Rank(0, -1))?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zscan() -> Result<()> {
+        let backend = Backend::new();
+        let members = (0..20).map(|i| (i as f64, format!("m{i}"))).collect();
+        backend.zadd("zset", members, ZAddFlags::default())?;
+
+        let mut cursor = 0;
+        let mut seen = HashSet::new();
+        loop {
+            let (next, page) = backend.zscan(b"zset", cursor, 3, None)?;
+            for (member, score) in page {
+                assert_eq!(backend.zscore(b"zset", &member)?, Some(score));
+                seen.insert(member);
+            }
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert_eq!(seen.len(), 20);
+        assert_eq!(backend.zscan(b"nokey", 0, 10, None)?, (0, vec![]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lpos() -> Result<()> {
+        let backend = Backend::new();
+        let values = ["a", "b", "c", "1", "2", "3", "c", "c"];
+        backend.rpush("mylist", values.iter().map(|v| BulkString::from(*v).into()))?;
+
+        let c: RespFrame = BulkString::from("c").into();
+        assert_eq!(backend.lpos(b"mylist", &c, 1, 1, 0)?, vec![2]);
+        assert_eq!(backend.lpos(b"mylist", &c, 2, 1, 0)?, vec![6]);
+        assert_eq!(backend.lpos(b"mylist", &c, -1, 1, 0)?, vec![7]);
+        assert_eq!(backend.lpos(b"mylist", &c, 1, 0, 0)?, vec![2, 6, 7]);
+        assert_eq!(backend.lpos(b"mylist", &c, -1, 2, 0)?, vec![7, 6]);
+        assert_eq!(backend.lpos(b"mylist", &c, 1, 0, 3)?, vec![2]);
+        assert_eq!(backend.lpos(b"mylist", &c, -1, 0, 3)?, vec![7, 6]);
+        assert!(backend.lpos(b"nolist", &c, 1, 1, 0)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmpop() -> Result<()> {
+        let backend = Backend::new();
+        let values = ["a", "b", "c"];
+        backend.rpush("list2", values.iter().map(|v| BulkString::from(*v).into()))?;
+
+        let keys = [b"list1".to_vec(), b"list2".to_vec()];
+        let (key, popped) = backend.lmpop(&keys, true, 1)?.unwrap();
+        assert_eq!(key, b"list2");
+        assert_eq!(popped, vec![BulkString::from("a").into()]);
+
+        let (_, popped) = backend.lmpop(&keys, false, 10)?.unwrap();
+        assert_eq!(
+            popped,
+            vec![BulkString::from("c").into(), BulkString::from("b").into()]
+        );
+        assert!(!backend.exists(b"list2"));
+        assert!(backend.lmpop(&keys, true, 1)?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_busy_script() {
+        let clock = Arc::new(ManualClock::new(Duration::from_secs(1_000)));
+        let backend = Backend::with_clock(ConfigValues::default(), clock.clone());
+        let exclusive = backend.exclusive_access().await;
+        assert!(exclusive.is_some());
+
+        // commands wait for the script until it passes busy-reply-threshold
+        backend.start_script();
+        clock.advance(Duration::from_millis(4_999));
+        let waiting = tokio::time::timeout(Duration::from_millis(50), backend.shared_access());
+        assert!(waiting.await.is_err());
+        clock.advance(Duration::from_millis(1));
+        assert!(backend.shared_access().await.is_none());
+
+        backend.end_script();
+        drop(exclusive);
+        assert!(backend.shared_access().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_close_idle_clients() {
+        let clock = Arc::new(ManualClock::new(Duration::from_secs(1_000)));
+        let config = ConfigValues {
+            timeout: 10,
+            ..Default::default()
+        };
+        let backend = Backend::with_clock(config, clock.clone());
+        let connect = || backend.connect("127.0.0.1:5000".to_string(), "".to_string());
+        let (idle, active, subscriber, blocked) = (connect(), connect(), connect(), connect());
+        backend
+            .pubsub()
+            .subscribe(subscriber.client_id(), Subscription::Channel, "news");
+        let killed = |client: &Backend| {
+            let signal = backend.clients().kill_signal(client.client_id());
+            signal.is_some_and(|signal| *signal.borrow())
+        };
+
+        clock.advance(Duration::from_secs(5));
+        active.record_client_command("ping");
+        clock.advance(Duration::from_secs(6));
+        let keys = vec![BlockKey::Key(0, b"list".to_vec())];
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(50);
+        let waiting = blocked.block_on(keys, Some(deadline), || None::<()>);
+        let closed = async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            backend.close_idle_clients()
+        };
+        let (_, closed) = tokio::join!(waiting, closed);
+        assert_eq!(closed, 1);
+        assert!(killed(&idle));
+        assert!(!killed(&active) && !killed(&subscriber) && !killed(&blocked));
+        idle.disconnect();
+
+        // a blocked client times out like the others once it is no longer blocked
+        assert!(!backend.clients().get(blocked.client_id()).unwrap().blocked);
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(backend.close_idle_clients(), 2);
+        assert!(killed(&active) && killed(&blocked) && !killed(&subscriber));
+    }
+
+    #[test]
+    fn test_close_idle_clients_running_command() {
+        let clock = Arc::new(ManualClock::new(Duration::from_secs(1_000)));
+        let config = ConfigValues {
+            timeout: 10,
+            ..Default::default()
+        };
+        let backend = Backend::with_clock(config, clock.clone());
+        let client = backend.connect("127.0.0.1:5000".to_string(), "".to_string());
+
+        // a request comes in after a while, and runs past the timeout counted from the last one
+        clock.advance(Duration::from_secs(8));
+        client.record_client_interaction();
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(backend.close_idle_clients(), 0);
+        client.record_client_command("eval");
+
+        clock.advance(Duration::from_secs(11));
+        assert_eq!(backend.close_idle_clients(), 1);
     }
 }
@@ -0,0 +1,104 @@
+use std::fmt::Write;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+// Lines buffered for each monitor, one falling further behind misses the oldest ones.
+const MONITOR_BACKLOG: usize = 1024;
+
+// Fans the commands processed by the server out to the clients that issued MONITOR.
+#[derive(Debug)]
+pub struct MonitorFeed {
+    sender: broadcast::Sender<String>,
+}
+
+impl MonitorFeed {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(MONITOR_BACKLOG).0,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.sender.subscribe()
+    }
+
+    // Whether anyone is monitoring, so lines are only formatted when they will be read.
+    pub fn has_monitors(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    pub fn publish(&self, line: String) {
+        // fails only when the last monitor has just gone away
+        let _ = self.sender.send(line);
+    }
+}
+
+impl Default for MonitorFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A MONITOR line: `<seconds>.<microseconds> [<db> <client address>] "arg" "arg" ...`, `now`
+// being the time since the unix epoch.
+pub fn monitor_line(now: Duration, db: usize, addr: &str, args: &[Vec<u8>]) -> String {
+    let mut line = format!("{}.{:06} [{db} {addr}]", now.as_secs(), now.subsec_micros());
+    for arg in args {
+        line.push(' ');
+        quote(&mut line, arg);
+    }
+    line
+}
+
+// double quoted, with quotes, backslashes, control and non-ASCII bytes escaped
+fn quote(out: &mut String, arg: &[u8]) {
+    out.push('"');
+    for &b in arg {
+        match b {
+            b'\\' => out.push_str("\\\\"),
+            b'"' => out.push_str("\\\""),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            0x07 => out.push_str("\\a"),
+            0x08 => out.push_str("\\b"),
+            b if b.is_ascii_graphic() || b == b' ' => out.push(b as char),
+            b => {
+                let _ = write!(out, "\\x{b:02x}");
+            }
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monitor_line() {
+        let args = [b"SET".to_vec(), b"key".to_vec(), b"a \"b\"\n\xff".to_vec()];
+        let line = monitor_line(
+            Duration::from_micros(1_339_518_083_107_412),
+            2,
+            "127.0.0.1:60866",
+            &args,
+        );
+        assert_eq!(
+            line,
+            r#"1339518083.107412 [2 127.0.0.1:60866] "SET" "key" "a \"b\"\n\xff""#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_monitor_feed() {
+        let feed = MonitorFeed::new();
+        assert!(!feed.has_monitors());
+        feed.publish("lost".to_string());
+
+        let mut monitor = feed.subscribe();
+        assert!(feed.has_monitors());
+        feed.publish("seen".to_string());
+        assert_eq!(monitor.recv().await.ok(), Some("seen".to_string()));
+    }
+}
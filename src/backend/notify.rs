@@ -0,0 +1,170 @@
+use std::fmt;
+use std::str::FromStr;
+
+// The class of a keyspace event, each enabled by its own notify-keyspace-events flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyClass {
+    // commands not specific to a type, like DEL, EXPIRE or RENAME
+    Generic,
+    String,
+    List,
+    Set,
+    Hash,
+    ZSet,
+    Stream,
+    // a key removed when its expiry time passed
+    Expired,
+    // a key removed to make room under maxmemory
+    Evicted,
+    // a key accessed but missing
+    KeyMiss,
+    // a key added to the database
+    New,
+}
+
+const KEYSPACE: u32 = 1 << 0;
+const KEYEVENT: u32 = 1 << 1;
+const GENERIC: u32 = 1 << 2;
+const STRING: u32 = 1 << 3;
+const LIST: u32 = 1 << 4;
+const SET: u32 = 1 << 5;
+const HASH: u32 = 1 << 6;
+const ZSET: u32 = 1 << 7;
+const EXPIRED: u32 = 1 << 8;
+const EVICTED: u32 = 1 << 9;
+const STREAM: u32 = 1 << 10;
+const KEY_MISS: u32 = 1 << 11;
+const MODULE: u32 = 1 << 12;
+const NEW: u32 = 1 << 13;
+// what "A" stands for, key misses and new keys have to be asked for
+const ALL: u32 = GENERIC | STRING | LIST | SET | HASH | ZSET | EXPIRED | EVICTED | STREAM | MODULE;
+
+// The classes flags in the order they are shown, "A" replacing the whole group of ALL.
+const CLASS_FLAGS: [(char, u32); 10] = [
+    ('g', GENERIC),
+    ('$', STRING),
+    ('l', LIST),
+    ('s', SET),
+    ('h', HASH),
+    ('z', ZSET),
+    ('x', EXPIRED),
+    ('e', EVICTED),
+    ('t', STREAM),
+    ('d', MODULE),
+];
+
+impl NotifyClass {
+    const fn bit(self) -> u32 {
+        match self {
+            NotifyClass::Generic => GENERIC,
+            NotifyClass::String => STRING,
+            NotifyClass::List => LIST,
+            NotifyClass::Set => SET,
+            NotifyClass::Hash => HASH,
+            NotifyClass::ZSet => ZSET,
+            NotifyClass::Stream => STREAM,
+            NotifyClass::Expired => EXPIRED,
+            NotifyClass::Evicted => EVICTED,
+            NotifyClass::KeyMiss => KEY_MISS,
+            NotifyClass::New => NEW,
+        }
+    }
+}
+
+// The value of notify-keyspace-events: whether events are published to the keyspace (K)
+// and keyevent (E) channels, and for which classes. Nothing is published unless K or E is
+// set along with some class.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NotifyFlags(u32);
+
+impl NotifyFlags {
+    // Whether events of `class` are published at all.
+    pub fn publishes(&self, class: NotifyClass) -> bool {
+        self.0 & class.bit() != 0 && self.0 & (KEYSPACE | KEYEVENT) != 0
+    }
+
+    // to __keyspace@<db>__:<key>, with the event as message
+    pub fn keyspace(&self) -> bool {
+        self.0 & KEYSPACE != 0
+    }
+
+    // to __keyevent@<db>__:<event>, with the key as message
+    pub fn keyevent(&self) -> bool {
+        self.0 & KEYEVENT != 0
+    }
+}
+
+impl FromStr for NotifyFlags {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut flags = 0;
+        for c in s.chars() {
+            flags |= match c {
+                'A' => ALL,
+                'K' => KEYSPACE,
+                'E' => KEYEVENT,
+                'm' => KEY_MISS,
+                'n' => NEW,
+                c => match CLASS_FLAGS.iter().find(|(flag, _)| *flag == c) {
+                    Some((_, bit)) => *bit,
+                    None => {
+                        return Err(
+                            "Invalid event class character. Use 'Ag$lshzxeKEtmdn'.".to_string()
+                        )
+                    }
+                },
+            };
+        }
+        Ok(NotifyFlags(flags))
+    }
+}
+
+// in the canonical form CONFIG GET shows, like "AKE" or "g$K"
+impl fmt::Display for NotifyFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 & ALL == ALL {
+            f.write_str("A")?;
+        } else {
+            for (flag, bit) in CLASS_FLAGS {
+                if self.0 & bit != 0 {
+                    write!(f, "{flag}")?;
+                }
+            }
+        }
+        for (flag, bit) in [
+            ('K', KEYSPACE),
+            ('E', KEYEVENT),
+            ('m', KEY_MISS),
+            ('n', NEW),
+        ] {
+            if self.0 & bit != 0 {
+                write!(f, "{flag}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notify_flags() {
+        let flags: NotifyFlags = "Kl".parse().expect("valid flags");
+        assert!(flags.publishes(NotifyClass::List));
+        assert!(!flags.publishes(NotifyClass::Generic));
+        assert!(flags.keyspace() && !flags.keyevent());
+
+        // classes alone publish nothing
+        let flags: NotifyFlags = "g$".parse().expect("valid flags");
+        assert!(!flags.publishes(NotifyClass::Generic));
+
+        let flags: NotifyFlags = "EKnlgszxeh$td".parse().expect("valid flags");
+        assert_eq!(flags.to_string(), "AKEn");
+        assert!(!flags.publishes(NotifyClass::KeyMiss));
+        assert_eq!(NotifyFlags::default().to_string(), "");
+        assert!("Kq".parse::<NotifyFlags>().is_err());
+    }
+}
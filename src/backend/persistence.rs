@@ -0,0 +1,185 @@
+use super::Backend;
+use crate::RespFrame;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// how often the save rules are checked
+const SAVE_RULES_INTERVAL: Duration = Duration::from_secs(1);
+// seconds a failed save waits before the save rules may start another one
+const SAVE_RETRY_DELAY: i64 = 5;
+
+// Outcome of snapshot saves, published by Backend::save for LASTSAVE and INFO persistence.
+#[derive(Debug)]
+pub struct SaveStatus {
+    // unix time in seconds of the last successful save, the startup time until the first one
+    last_save: AtomicI64,
+    // unix time in seconds the last save finished, successful or not
+    last_attempt: AtomicI64,
+    in_progress: AtomicBool,
+    // how long the last save took in seconds, -1 before the first attempt
+    last_duration: AtomicI64,
+    // why the last save failed, None if it succeeded
+    last_error: Mutex<Option<String>>,
+    // writes since the dataset of the last successful save was copied
+    changes: AtomicU64,
+}
+
+impl SaveStatus {
+    pub fn new(now: i64) -> Self {
+        Self {
+            last_save: AtomicI64::new(now),
+            last_attempt: AtomicI64::new(now),
+            in_progress: AtomicBool::new(false),
+            last_duration: AtomicI64::new(-1),
+            last_error: Mutex::new(None),
+            changes: AtomicU64::new(0),
+        }
+    }
+
+    // Marks a save as started. Returns false if one is already running.
+    pub fn start(&self) -> bool {
+        self.in_progress
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    // Records the outcome of the running save, `now` and `duration` in seconds. A successful
+    // save took care of the `saved` changes made before its dataset was copied.
+    pub fn finish(&self, error: Option<String>, now: i64, duration: i64, saved: u64) {
+        if error.is_none() {
+            self.last_save.store(now, Ordering::Relaxed);
+            self.changes.fetch_sub(saved, Ordering::Relaxed);
+        }
+        self.last_attempt.store(now, Ordering::Relaxed);
+        self.last_duration.store(duration, Ordering::Relaxed);
+        *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = error;
+        self.in_progress.store(false, Ordering::Release);
+    }
+
+    pub fn last_save(&self) -> i64 {
+        self.last_save.load(Ordering::Relaxed)
+    }
+
+    pub fn in_progress(&self) -> bool {
+        self.in_progress.load(Ordering::Acquire)
+    }
+
+    pub fn last_duration(&self) -> i64 {
+        self.last_duration.load(Ordering::Relaxed)
+    }
+
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn record_change(&self) {
+        self.changes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn changes(&self) -> u64 {
+        self.changes.load(Ordering::Relaxed)
+    }
+
+    // Whether one of the `save` rules is met at `now`: at least `changes` writes and `seconds`
+    // since the last successful save. After a failed save, not before SAVE_RETRY_DELAY either.
+    pub fn save_due(&self, rules: &[(u64, u64)], now: i64) -> bool {
+        let changes = self.changes();
+        let elapsed = now - self.last_save();
+        let retry = self.last_error().is_none()
+            || now - self.last_attempt.load(Ordering::Relaxed) >= SAVE_RETRY_DELAY;
+        retry
+            && !self.in_progress()
+            && rules
+                .iter()
+                .any(|&(seconds, min)| changes >= min && elapsed >= seconds as i64)
+    }
+}
+
+impl Backend {
+    // Counts a write command that ran, given its reply, in the changes the `save` rules look
+    // at. Commands that failed changed nothing.
+    pub fn record_write(&self, reply: &RespFrame) {
+        if !matches!(reply, RespFrame::Error(_)) {
+            self.save_status().record_change();
+        }
+    }
+
+    // Starts a BGSAVE whenever one of the `save` rules is met, until the server shuts down.
+    pub async fn run_save_rules(&self) {
+        let mut shutdown = self.shutdown_signal();
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(SAVE_RULES_INTERVAL) => {}
+                _ = shutdown.changed() => return,
+            }
+            let rules = self.config().read().save.clone();
+            let now = self.now().as_secs() as i64;
+            if !self.save_status().save_due(&rules, now) {
+                continue;
+            }
+            // the dataset is copied with no command running, none waits for it to be written
+            if let Some(_exclusive) = self.exclusive_access().await {
+                self.bgsave(false);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_status() {
+        let status = SaveStatus::new(100);
+        assert_eq!(status.last_save(), 100);
+        assert!(status.start());
+        assert!(!status.start());
+        assert!(status.in_progress());
+
+        status.finish(Some("disk full".to_string()), 110, 2, 0);
+        assert!(!status.in_progress());
+        assert_eq!(status.last_save(), 100);
+        assert_eq!(status.last_error().as_deref(), Some("disk full"));
+
+        assert!(status.start());
+        status.finish(None, 120, 1, 0);
+        assert_eq!(status.last_save(), 120);
+        assert_eq!(status.last_duration(), 1);
+        assert_eq!(status.last_error(), None);
+    }
+
+    #[test]
+    fn test_save_rules() {
+        let status = SaveStatus::new(100);
+        let rules = [(60, 1), (10, 3)];
+        assert!(!status.save_due(&rules, 200));
+        status.record_change();
+        assert!(!status.save_due(&rules, 159));
+        assert!(status.save_due(&rules, 160));
+        status.record_change();
+        status.record_change();
+        assert!(status.save_due(&rules, 110));
+        assert!(!status.save_due(&[], 1000));
+
+        // the changes made while the dataset was written out are left
+        assert!(status.start());
+        assert!(!status.save_due(&rules, 160));
+        status.record_change();
+        status.finish(None, 120, 1, 3);
+        assert_eq!(status.changes(), 1);
+        assert!(!status.save_due(&rules, 179));
+        assert!(status.save_due(&rules, 180));
+
+        // a failed save isn't retried right away
+        assert!(status.start());
+        status.finish(Some("disk full".to_string()), 200, 0, 1);
+        assert_eq!(status.changes(), 1);
+        assert!(!status.save_due(&rules, 204));
+        assert!(status.save_due(&rules, 205));
+    }
+}
@@ -0,0 +1,351 @@
+use super::glob_match;
+use crate::{BulkString, RespFrame};
+use dashmap::DashMap;
+use std::collections::HashSet;
+use tokio::sync::mpsc;
+
+// The elements of what a subscriber receives, like ["message", channel, payload]. The
+// connection writes it as a push frame in RESP3 and as an array in RESP2.
+pub type PubSubMessage = Vec<RespFrame>;
+
+// What a client subscribes to: channels by name with SUBSCRIBE, every channel matching a
+// glob-style pattern with PSUBSCRIBE, or shard channels with SSUBSCRIBE. Shard channels are a
+// namespace of their own, a cluster routes their messages only within the shard owning them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subscription {
+    Channel,
+    Pattern,
+    ShardChannel,
+}
+
+// The pub/sub broker: who subscribed to which channel or pattern, and the queue delivering
+// messages to each subscribed connection.
+#[derive(Debug, Default)]
+pub struct PubSub {
+    // subscriber ids by channel, channels without subscribers are removed
+    channels: DashMap<String, HashSet<u64>>,
+    // subscriber ids by pattern and by shard channel, likewise
+    patterns: DashMap<String, HashSet<u64>>,
+    shard_channels: DashMap<String, HashSet<u64>>,
+    subscribers: DashMap<u64, Subscriber>,
+}
+
+#[derive(Debug, Default)]
+struct Subscriber {
+    // where messages for the connection are queued, None until it is attached
+    queue: Option<mpsc::UnboundedSender<PubSubMessage>>,
+    // in subscription order
+    channels: Vec<String>,
+    patterns: Vec<String>,
+    shard_channels: Vec<String>,
+}
+
+impl PubSub {
+    // Creates the queue of the connection of client `id` and returns its receiving end.
+    pub fn attach(&self, id: u64) -> mpsc::UnboundedReceiver<PubSubMessage> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.subscribers.entry(id).or_default().queue = Some(sender);
+        receiver
+    }
+
+    // Drops the queue and every subscription of client `id`.
+    pub fn detach(&self, id: u64) {
+        if let Some((_, subscriber)) = self.subscribers.remove(&id) {
+            for channel in subscriber.channels {
+                self.remove_subscriber(Subscription::Channel, &channel, id);
+            }
+            for pattern in subscriber.patterns {
+                self.remove_subscriber(Subscription::Pattern, &pattern, id);
+            }
+            for channel in subscriber.shard_channels {
+                self.remove_subscriber(Subscription::ShardChannel, &channel, id);
+            }
+        }
+    }
+
+    // Subscribes client `id` to the channel, pattern or shard channel `name`. Returns its
+    // number of subscriptions of the kind afterwards, see subscription_count.
+    pub fn subscribe(&self, id: u64, kind: Subscription, name: &str) -> usize {
+        let (added, count) = {
+            let mut subscriber = self.subscribers.entry(id).or_default();
+            let names = subscriber.names_mut(kind);
+            let added = !names.iter().any(|n| n == name);
+            if added {
+                names.push(name.to_string());
+            }
+            (added, subscriber.count(kind))
+        };
+        if added {
+            self.registry(kind)
+                .entry(name.to_string())
+                .or_default()
+                .insert(id);
+        }
+        count
+    }
+
+    // Unsubscribes client `id` from the channel, pattern or shard channel `name`. Returns its
+    // number of subscriptions of the kind afterwards, see subscription_count.
+    pub fn unsubscribe(&self, id: u64, kind: Subscription, name: &str) -> usize {
+        let (removed, count) = match self.subscribers.get_mut(&id) {
+            Some(mut subscriber) => {
+                let names = subscriber.names_mut(kind);
+                let before = names.len();
+                names.retain(|n| n != name);
+                let removed = names.len() < before;
+                (removed, subscriber.count(kind))
+            }
+            None => (false, 0),
+        };
+        if removed {
+            self.remove_subscriber(kind, name, id);
+        }
+        count
+    }
+
+    // The channels, patterns or shard channels client `id` subscribed to, in subscription
+    // order.
+    pub fn subscriptions(&self, id: u64, kind: Subscription) -> Vec<String> {
+        self.subscribers
+            .get(&id)
+            .map_or_else(Vec::new, |subscriber| match kind {
+                Subscription::Channel => subscriber.channels.clone(),
+                Subscription::Pattern => subscriber.patterns.clone(),
+                Subscription::ShardChannel => subscriber.shard_channels.clone(),
+            })
+    }
+
+    // The channels or shard channels with subscribers, all of them or those matching
+    // `pattern`, sorted.
+    pub fn active_channels(&self, kind: Subscription, pattern: Option<&str>) -> Vec<String> {
+        let mut channels = self
+            .registry(kind)
+            .iter()
+            .filter(|entry| pattern.is_none_or(|p| glob_match(p, entry.key())))
+            .map(|entry| entry.key().clone())
+            .collect::<Vec<_>>();
+        channels.sort();
+        channels
+    }
+
+    // How many clients subscribed to the channel or shard channel `name`, pattern
+    // subscriptions aside.
+    pub fn subscriber_count(&self, kind: Subscription, name: &str) -> usize {
+        self.registry(kind).get(name).map_or(0, |ids| ids.len())
+    }
+
+    // How many distinct patterns clients subscribed to.
+    pub fn pattern_count(&self) -> usize {
+        self.patterns.len()
+    }
+
+    // The count client `id` sees in its confirmations: its channels and patterns together,
+    // or its shard channels alone.
+    pub fn subscription_count(&self, id: u64, kind: Subscription) -> usize {
+        self.subscribers
+            .get(&id)
+            .map_or(0, |subscriber| subscriber.count(kind))
+    }
+
+    // Whether client `id` subscribed to any channel, pattern or shard channel.
+    pub fn is_subscribed(&self, id: u64) -> bool {
+        self.subscribers.get(&id).is_some_and(|subscriber| {
+            !subscriber.channels.is_empty()
+                || !subscriber.patterns.is_empty()
+                || !subscriber.shard_channels.is_empty()
+        })
+    }
+
+    // Sends `message` to the subscribers of `channel` and to those of the patterns matching
+    // it. Returns how many messages were sent, a client subscribed both to the channel and
+    // to a matching pattern receiving it twice.
+    pub fn publish(&self, channel: &str, message: BulkString) -> usize {
+        let mut sent = 0;
+        let ids = self
+            .channels
+            .get(channel)
+            .map_or_else(Vec::new, |ids| ids.iter().copied().collect());
+        for id in ids {
+            self.deliver(
+                id,
+                vec![
+                    BulkString::from("message").into(),
+                    BulkString::from(channel).into(),
+                    message.clone().into(),
+                ],
+            );
+            sent += 1;
+        }
+
+        let matching = self
+            .patterns
+            .iter()
+            .filter(|entry| glob_match(entry.key(), channel))
+            .map(|entry| {
+                (
+                    entry.key().clone(),
+                    entry.iter().copied().collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        for (pattern, ids) in matching {
+            for id in ids {
+                self.deliver(
+                    id,
+                    vec![
+                        BulkString::from("pmessage").into(),
+                        BulkString::from(pattern.as_str()).into(),
+                        BulkString::from(channel).into(),
+                        message.clone().into(),
+                    ],
+                );
+                sent += 1;
+            }
+        }
+        sent
+    }
+
+    // Sends `message` to the subscribers of the shard channel `channel`. Returns how many
+    // there are.
+    pub fn spublish(&self, channel: &str, message: BulkString) -> usize {
+        let ids = self
+            .shard_channels
+            .get(channel)
+            .map_or_else(Vec::new, |ids| ids.iter().copied().collect());
+        for &id in &ids {
+            self.deliver(
+                id,
+                vec![
+                    BulkString::from("smessage").into(),
+                    BulkString::from(channel).into(),
+                    message.clone().into(),
+                ],
+            );
+        }
+        ids.len()
+    }
+
+    fn deliver(&self, id: u64, message: PubSubMessage) {
+        let queue = self
+            .subscribers
+            .get(&id)
+            .and_then(|subscriber| subscriber.queue.clone());
+        if let Some(queue) = queue {
+            // fails only when the connection is going away
+            let _ = queue.send(message);
+        }
+    }
+
+    fn registry(&self, kind: Subscription) -> &DashMap<String, HashSet<u64>> {
+        match kind {
+            Subscription::Channel => &self.channels,
+            Subscription::Pattern => &self.patterns,
+            Subscription::ShardChannel => &self.shard_channels,
+        }
+    }
+
+    fn remove_subscriber(&self, kind: Subscription, name: &str, id: u64) {
+        let registry = self.registry(kind);
+        if let Some(mut ids) = registry.get_mut(name) {
+            ids.remove(&id);
+        }
+        registry.remove_if(name, |_, ids| ids.is_empty());
+    }
+}
+
+impl Subscriber {
+    fn names_mut(&mut self, kind: Subscription) -> &mut Vec<String> {
+        match kind {
+            Subscription::Channel => &mut self.channels,
+            Subscription::Pattern => &mut self.patterns,
+            Subscription::ShardChannel => &mut self.shard_channels,
+        }
+    }
+
+    fn count(&self, kind: Subscription) -> usize {
+        match kind {
+            Subscription::Channel | Subscription::Pattern => {
+                self.channels.len() + self.patterns.len()
+            }
+            Subscription::ShardChannel => self.shard_channels.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pubsub_subscribe() {
+        let pubsub = PubSub::default();
+        assert_eq!(pubsub.subscribe(1, Subscription::Channel, "news"), 1);
+        assert_eq!(pubsub.subscribe(1, Subscription::Channel, "news"), 1);
+        assert_eq!(pubsub.subscribe(1, Subscription::Channel, "sports"), 2);
+        assert_eq!(pubsub.subscribe(1, Subscription::Pattern, "news"), 3);
+        assert_eq!(
+            pubsub.subscriptions(1, Subscription::Channel),
+            ["news", "sports"]
+        );
+
+        pubsub.subscribe(2, Subscription::Channel, "news");
+        pubsub.subscribe(2, Subscription::Pattern, "news");
+        assert_eq!(
+            pubsub.active_channels(Subscription::Channel, None),
+            ["news", "sports"]
+        );
+        assert_eq!(
+            pubsub.active_channels(Subscription::Channel, Some("n*")),
+            ["news"]
+        );
+        assert_eq!(pubsub.subscriber_count(Subscription::Channel, "news"), 2);
+        assert_eq!(pubsub.pattern_count(), 1);
+        pubsub.detach(2);
+
+        assert_eq!(pubsub.unsubscribe(1, Subscription::Channel, "missing"), 3);
+        assert_eq!(pubsub.unsubscribe(1, Subscription::Channel, "news"), 2);
+        assert_eq!(pubsub.publish("sports", BulkString::from("hi")), 1);
+
+        // shard channels are counted apart
+        assert_eq!(pubsub.subscribe(1, Subscription::ShardChannel, "orders"), 1);
+        assert_eq!(pubsub.subscription_count(1, Subscription::Channel), 2);
+        assert_eq!(pubsub.publish("orders", BulkString::from("hi")), 0);
+        assert_eq!(pubsub.spublish("orders", BulkString::from("hi")), 1);
+
+        assert!(pubsub.is_subscribed(1));
+        pubsub.detach(1);
+        assert!(!pubsub.is_subscribed(1));
+        assert_eq!(pubsub.subscription_count(1, Subscription::Channel), 0);
+        assert!(pubsub.shard_channels.is_empty());
+        assert!(pubsub.channels.is_empty());
+        assert!(pubsub.patterns.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pubsub_publish() {
+        let pubsub = PubSub::default();
+        let mut queue = pubsub.attach(1);
+        pubsub.subscribe(1, Subscription::Channel, "news");
+        pubsub.subscribe(1, Subscription::Pattern, "n*");
+        pubsub.subscribe(2, Subscription::Channel, "news");
+        pubsub.subscribe(2, Subscription::Pattern, "s*");
+
+        assert_eq!(pubsub.publish("news", BulkString::from("hi")), 3);
+        let expected: PubSubMessage = vec![
+            BulkString::from("message").into(),
+            BulkString::from("news").into(),
+            BulkString::from("hi").into(),
+        ];
+        assert_eq!(queue.recv().await, Some(expected));
+        let expected: PubSubMessage = vec![
+            BulkString::from("pmessage").into(),
+            BulkString::from("n*").into(),
+            BulkString::from("news").into(),
+            BulkString::from("hi").into(),
+        ];
+        assert_eq!(queue.recv().await, Some(expected));
+
+        pubsub.detach(1);
+        assert_eq!(queue.recv().await, None);
+    }
+}
@@ -0,0 +1,343 @@
+use super::{dump::fnv1a, BackendInner, FunctionInfo, FunctionLibrary, Storage, Value};
+use std::io;
+use std::sync::Arc;
+
+// Bumped whenever the layout of the file changes, files of older versions still load.
+pub const RDB_VERSION: u16 = 2;
+
+// Snapshot file layout:
+//
+//   "SREDIS" <version: u16 LE>
+//   per function library (since version 2):
+//     FUNCTION <name: blob> <code: blob> <functions: u32 LE>
+//     per function: <name: blob> <description: blob> <flags: blob, separated by spaces>
+//   per non-empty database:
+//     SELECTDB <index: u32 LE>
+//     per key: KEY <expire at: i64 LE, -1 without expiry> <key: blob> <value: blob>
+//   EOF <checksum: u64 LE>
+//
+// A blob is a u32 LE length followed by that many bytes, values are DUMP payloads. The checksum
+// is the 64-bit FNV-1a hash of everything before it.
+const MAGIC: &[u8] = b"SREDIS";
+const OP_SELECTDB: u8 = 0xfe;
+const OP_KEY: u8 = 0x00;
+const OP_FUNCTION: u8 = 0xf5;
+const OP_EOF: u8 = 0xff;
+
+// A snapshot file read back, the keys still as DUMP payloads.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    pub functions: Vec<FunctionLibrary>,
+    pub keys: Vec<SnapshotKey>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SnapshotKey {
+    pub db: usize,
+    pub key: Vec<u8>,
+    // absolute time in milliseconds
+    pub expire_at: Option<i64>,
+    pub payload: Vec<u8>,
+}
+
+// The function libraries and the keys of every database as they were at one point in time, see
+// Backend::copy_dataset. The functions are copied, the keys are a snapshot of the databases
+// (see Storage::begin_snapshot) that writes keep going on behind, ended once this is dropped.
+#[derive(Debug)]
+pub struct DatasetCopy {
+    pub functions: Vec<FunctionLibrary>,
+    inner: Arc<BackendInner>,
+    epoch: u64,
+    // (database index, position in the backend's databases) pairs
+    dbs: Vec<(usize, usize)>,
+    // in milliseconds, keys expired by then are left out
+    now: i64,
+}
+
+impl DatasetCopy {
+    // Takes over the snapshot `epoch` of the databases, already begun.
+    pub(super) fn new(
+        inner: Arc<BackendInner>,
+        epoch: u64,
+        dbs: Vec<(usize, usize)>,
+        functions: Vec<FunctionLibrary>,
+        now: i64,
+    ) -> Self {
+        Self {
+            functions,
+            inner,
+            epoch,
+            dbs,
+            now,
+        }
+    }
+
+    // Calls `f` with the index of the database, the name, the value and the absolute expiry time
+    // in milliseconds of every key, database after database.
+    pub fn for_each_key(&self, mut f: impl FnMut(usize, &[u8], &Value, Option<i64>)) {
+        for &(index, slot) in &self.dbs {
+            let keyspace = &self.inner.dbs[slot].keyspace;
+            keyspace.for_each_snapshot(self.epoch, |key, value, expire_at| {
+                if expire_at.is_none_or(|when| when > self.now) {
+                    f(index, key, value, expire_at);
+                }
+            });
+        }
+    }
+
+    // Serializes the copy in the snapshot file layout.
+    pub fn encode(self) -> Vec<u8> {
+        let mut buf = header(&self.functions);
+        let mut selected = None;
+        self.for_each_key(|index, key, value, expire_at| {
+            // empty databases are left out
+            if selected != Some(index) {
+                buf.push(OP_SELECTDB);
+                buf.extend_from_slice(&(index as u32).to_le_bytes());
+                selected = Some(index);
+            }
+            buf.push(OP_KEY);
+            buf.extend_from_slice(&expire_at.unwrap_or(-1).to_le_bytes());
+            put_blob(&mut buf, key);
+            put_blob(&mut buf, &value.clone().serialize());
+        });
+        buf.push(OP_EOF);
+        let checksum = fnv1a(&buf);
+        buf.extend_from_slice(&checksum.to_le_bytes());
+        buf
+    }
+}
+
+impl Drop for DatasetCopy {
+    fn drop(&mut self) {
+        for &(_, slot) in &self.dbs {
+            self.inner.dbs[slot].keyspace.end_snapshot(self.epoch);
+        }
+    }
+}
+
+// The magic, the version and the function libraries.
+fn header(functions: &[FunctionLibrary]) -> Vec<u8> {
+    let mut buf = MAGIC.to_vec();
+    buf.extend_from_slice(&RDB_VERSION.to_le_bytes());
+    for library in functions {
+        buf.push(OP_FUNCTION);
+        put_blob(&mut buf, library.name.as_bytes());
+        put_blob(&mut buf, library.code.as_bytes());
+        buf.extend_from_slice(&(library.functions.len() as u32).to_le_bytes());
+        for function in &library.functions {
+            put_blob(&mut buf, function.name.as_bytes());
+            let description = function.description.as_deref().unwrap_or_default();
+            put_blob(&mut buf, description.as_bytes());
+            put_blob(&mut buf, function.flags.join(" ").as_bytes());
+        }
+    }
+    buf
+}
+
+fn put_blob(buf: &mut Vec<u8>, blob: &[u8]) {
+    buf.extend_from_slice(&(blob.len() as u32).to_le_bytes());
+    buf.extend_from_slice(blob);
+}
+
+// Reads back a file DatasetCopy::encode wrote, checking its version and checksum.
+pub fn decode(data: &[u8]) -> io::Result<Snapshot> {
+    let body_len = data
+        .len()
+        .checked_sub(8)
+        .filter(|len| *len > MAGIC.len())
+        .ok_or_else(|| invalid("the file is truncated"))?;
+    let (body, checksum) = data.split_at(body_len);
+    if fnv1a(body).to_le_bytes() != checksum {
+        return Err(invalid("wrong checksum"));
+    }
+    let mut reader = Reader(body);
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(invalid("not a snapshot file"));
+    }
+    let version = u16::from_le_bytes(reader.array()?);
+    if version > RDB_VERSION {
+        return Err(invalid(&format!("can't handle version {version}")));
+    }
+
+    let mut snapshot = Snapshot::default();
+    let mut db = 0;
+    loop {
+        match reader.take(1)?[0] {
+            OP_FUNCTION => {
+                let name = reader.string()?;
+                let code = reader.string()?;
+                let count = u32::from_le_bytes(reader.array()?);
+                let functions = (0..count)
+                    .map(|_| {
+                        let name = reader.string()?;
+                        let description = Some(reader.string()?).filter(|d| !d.is_empty());
+                        let flags = reader.string()?;
+                        let flags = flags.split_whitespace().map(String::from).collect();
+                        Ok(FunctionInfo {
+                            name,
+                            description,
+                            flags,
+                        })
+                    })
+                    .collect::<io::Result<Vec<_>>>()?;
+                snapshot.functions.push(FunctionLibrary {
+                    name,
+                    code,
+                    functions,
+                });
+            }
+            OP_SELECTDB => db = u32::from_le_bytes(reader.array()?) as usize,
+            OP_KEY => {
+                let expire_at = i64::from_le_bytes(reader.array()?);
+                let key = reader.blob()?.to_vec();
+                let payload = reader.blob()?.to_vec();
+                snapshot.keys.push(SnapshotKey {
+                    db,
+                    key,
+                    expire_at: (expire_at >= 0).then_some(expire_at),
+                    payload,
+                });
+            }
+            OP_EOF if reader.0.is_empty() => return Ok(snapshot),
+            op => return Err(invalid(&format!("unexpected opcode {op:#04x}"))),
+        }
+    }
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("bad snapshot: {reason}"),
+    )
+}
+
+// The part of a snapshot not read yet.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(invalid("the file is truncated"));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn blob(&mut self) -> io::Result<&'a [u8]> {
+        let len = u32::from_le_bytes(self.array()?);
+        self.take(len as usize)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        String::from_utf8(self.blob()?.to_vec()).map_err(|_| invalid("a string isn't UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, BulkString};
+
+    #[test]
+    fn test_encode() {
+        let backend = Backend::new();
+        let db = backend.database(3).expect("database 3 exists");
+        db.keyspace
+            .insert(b"key".to_vec(), Value::String(BulkString::from("v").into()));
+        db.keyspace.insert(
+            b"gone".to_vec(),
+            Value::String(BulkString::from("v").into()),
+        );
+        db.keyspace.set_expire(b"gone".to_vec(), 5);
+
+        let data = backend.copy_dataset().encode();
+        let payload = db.dump(b"key").expect("key exists");
+        let mut expected = b"SREDIS\x02\x00\xfe\x03\x00\x00\x00\x00".to_vec();
+        expected.extend_from_slice(&(-1i64).to_le_bytes());
+        expected.extend_from_slice(b"\x03\x00\x00\x00key");
+        expected.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        expected.extend_from_slice(&payload);
+        expected.push(OP_EOF);
+        assert_eq!(data[..data.len() - 8], expected[..]);
+        assert_eq!(data[data.len() - 8..], fnv1a(&expected).to_le_bytes());
+    }
+
+    #[test]
+    fn test_decode() -> io::Result<()> {
+        let backend = Backend::new();
+        let db = backend.database(2).expect("database 2 exists");
+        let later = backend.now_ms() + 60_000;
+        db.keyspace
+            .insert(b"key".to_vec(), Value::String(BulkString::from("v").into()));
+        db.keyspace.set_expire(b"key".to_vec(), later);
+        let library = FunctionLibrary {
+            name: "lib".to_string(),
+            code: "#!lua name=lib".to_string(),
+            functions: vec![FunctionInfo {
+                name: "f".to_string(),
+                description: None,
+                flags: vec!["no-writes".to_string(), "allow-oom".to_string()],
+            }],
+        };
+
+        let mut copy = backend.copy_dataset();
+        copy.functions = vec![library.clone()];
+        let data = copy.encode();
+        let snapshot = decode(&data)?;
+        assert_eq!(snapshot.functions, [library]);
+        assert_eq!(
+            snapshot.keys,
+            [SnapshotKey {
+                db: 2,
+                key: b"key".to_vec(),
+                expire_at: Some(later),
+                payload: db.dump(b"key").expect("key exists"),
+            }]
+        );
+
+        let mut corrupted = data.clone();
+        corrupted[10] ^= 1;
+        assert!(decode(&corrupted).is_err());
+        assert!(decode(&data[..data.len() - 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_is_frozen() -> io::Result<()> {
+        let backend = Backend::new();
+        let string = |v: &str| Value::String(BulkString::from(v).into());
+        for key in ["kept", "changed", "removed"] {
+            backend
+                .keyspace
+                .insert(key.as_bytes().to_vec(), string("before"));
+        }
+        let later = backend.now_ms() + 60_000;
+        let copy = backend.copy_dataset();
+
+        backend
+            .keyspace
+            .insert(b"changed".to_vec(), string("after"));
+        backend.keyspace.set_expire(b"kept".to_vec(), later);
+        backend.keyspace.remove(b"removed");
+        backend.keyspace.insert(b"added".to_vec(), string("after"));
+        backend.flush(false);
+
+        let snapshot = decode(&copy.encode())?;
+        let mut keys = snapshot
+            .keys
+            .into_iter()
+            .map(|key| (key.key, key.expire_at, key.payload))
+            .collect::<Vec<_>>();
+        keys.sort();
+        let names = [b"changed".to_vec(), b"kept".to_vec(), b"removed".to_vec()];
+        let before = string("before").serialize();
+        assert_eq!(keys, names.map(|key| (key, None, before.clone())));
+        Ok(())
+    }
+}
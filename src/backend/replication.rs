@@ -0,0 +1,483 @@
+use super::aof::{CommandLog, LogCommand};
+use rand::Rng;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::{mpsc, watch};
+
+// A replica reading its stream too slowly is dropped once this many bytes wait to be sent to it,
+// like the replica class of client-output-buffer-limit.
+pub const REPLICA_OUTPUT_LIMIT: usize = 256 * 1024 * 1024;
+
+// The master side of replication: replicas get a snapshot of the dataset and then the stream of
+// the writes made since, the same commands the append only file logs. The offset counts the
+// bytes of the stream, which replicas acknowledge having processed. The latest part of the
+// stream is kept in the backlog, created along with the first replica, so that a replica that
+// lost its link only misses what it is sent again when it reconnects in time.
+//
+// The server is itself a replica once REPLICAOF named a master, whose stream the link to it
+// applies, see the replica module. A replica takes on the replication ID and the offset of its
+// master and passes the stream on as is to its own replicas.
+#[derive(Debug)]
+pub struct Replication {
+    state: Mutex<ReplState>,
+    // the host and port of the master set by REPLICAOF, None for a master
+    master: watch::Sender<Option<(String, u16)>>,
+}
+
+#[derive(Debug, Default)]
+struct ReplState {
+    // identifies the history of the dataset, replicas track their offset in it
+    replid: String,
+    // the history the dataset continues, up to `second_offset`, once a replica was promoted
+    replid2: Option<(String, u64)>,
+    offset: u64,
+    // the database the stream selected last
+    stream: CommandLog,
+    // a ring buffer of the latest bytes of the stream
+    backlog: Option<VecDeque<u8>>,
+    replicas: BTreeMap<u64, Replica>,
+    // the listening ports replicas announced with REPLCONF before PSYNC, by client id
+    announced: HashMap<u64, u16>,
+    // the link to the master, while there is one
+    link: MasterLink,
+}
+
+#[derive(Debug, Default)]
+struct MasterLink {
+    // connect, connecting, sync or connected, like ROLE reports it
+    state: &'static str,
+    // milliseconds since the unix epoch, when the master last sent anything
+    io_at: Option<i64>,
+}
+
+#[derive(Debug)]
+struct Replica {
+    addr: String,
+    port: u16,
+    // set once the replica was sent its snapshot
+    online: bool,
+    ack_offset: u64,
+    // milliseconds since the unix epoch
+    ack_at: i64,
+    sender: mpsc::UnboundedSender<Vec<u8>>,
+    pending: Arc<AtomicUsize>,
+}
+
+// A replica as INFO and ROLE report it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicaInfo {
+    pub ip: String,
+    pub port: u16,
+    pub state: &'static str,
+    pub offset: u64,
+    // seconds since the last acknowledgement
+    pub lag: i64,
+}
+
+// The master of a replica as INFO and ROLE report it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MasterInfo {
+    pub host: String,
+    pub port: u16,
+    pub state: &'static str,
+    // seconds since the master last sent anything, None before it did
+    pub last_io: Option<i64>,
+}
+
+// The stream a replica is sent, starting at the offset of its snapshot.
+#[derive(Debug)]
+pub struct ReplicaStream {
+    receiver: mpsc::UnboundedReceiver<Vec<u8>>,
+    pending: Arc<AtomicUsize>,
+}
+
+impl ReplicaStream {
+    // The next part of the stream, None once the replica was dropped.
+    pub async fn recv(&mut self) -> Option<Vec<u8>> {
+        let data = self.receiver.recv().await?;
+        self.pending.fetch_sub(data.len(), Ordering::Relaxed);
+        Some(data)
+    }
+}
+
+impl Replication {
+    pub fn new() -> Self {
+        let state = ReplState {
+            replid: random_id(),
+            ..ReplState::default()
+        };
+        Self {
+            state: Mutex::new(state),
+            master: watch::Sender::new(None),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, ReplState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn replid(&self) -> String {
+        self.state().replid.clone()
+    }
+
+    // The previous replication ID of a promoted replica and the offset up to which its history
+    // is shared with that ID.
+    pub fn replid2(&self) -> Option<(String, u64)> {
+        self.state().replid2.clone()
+    }
+
+    // The number of bytes of the stream produced so far.
+    pub fn offset(&self) -> u64 {
+        self.state().offset
+    }
+
+    // Whether writes are streamed, which they are once a replica attached.
+    pub fn is_active(&self) -> bool {
+        self.state().backlog.is_some()
+    }
+
+    // The offset of the first byte in the backlog and the number of bytes it holds.
+    pub fn backlog(&self) -> Option<(u64, usize)> {
+        let state = self.state();
+        let backlog = state.backlog.as_ref()?;
+        Some((state.offset + 1 - backlog.len() as u64, backlog.len()))
+    }
+
+    pub fn announce(&self, client_id: u64, port: u16) {
+        self.state().announced.insert(client_id, port);
+    }
+
+    // Makes the client a replica, which is streamed the writes made from now on. Returns the
+    // offset the stream starts at. The caller copies the dataset for its snapshot at the same
+    // time, with no write happening in between.
+    pub fn attach(&self, client_id: u64, addr: String, now: i64) -> (u64, ReplicaStream) {
+        let mut state = self.state();
+        state.backlog.get_or_insert_with(VecDeque::new);
+        // the replica's stream selects a database before the first command that needs one
+        state.stream = CommandLog::default();
+        let offset = state.offset;
+        let stream = state.add_replica(client_id, addr, false, offset, now);
+        (offset, stream)
+    }
+
+    // Makes the client a replica that continues the history `replid` from `psync_offset`, the
+    // offset of the first byte it misses, if the backlog still holds every byte it missed. It
+    // is streamed those right away, then the writes made from now on.
+    pub fn attach_partial(
+        &self,
+        client_id: u64,
+        addr: String,
+        replid: &str,
+        psync_offset: u64,
+        now: i64,
+    ) -> Option<ReplicaStream> {
+        let mut state = self.state();
+        let end = state.offset + 1;
+        let shared = match &state.replid2 {
+            _ if replid == state.replid => end,
+            Some((replid2, second_offset)) if replid == replid2 => *second_offset,
+            _ => return None,
+        };
+        let backlog = state.backlog.as_ref()?;
+        let first = end - backlog.len() as u64;
+        if psync_offset < first || psync_offset > shared {
+            return None;
+        }
+        let missed = backlog
+            .range((psync_offset - first) as usize..)
+            .copied()
+            .collect::<Vec<_>>();
+        let stream = state.add_replica(client_id, addr, true, psync_offset - 1, now);
+        if let Some(replica) = state
+            .replicas
+            .get(&client_id)
+            .filter(|_| !missed.is_empty())
+        {
+            replica.send(missed);
+        }
+        Some(stream)
+    }
+
+    // Marks the replica as having received its snapshot.
+    pub fn set_online(&self, client_id: u64) {
+        if let Some(replica) = self.state().replicas.get_mut(&client_id) {
+            replica.online = true;
+        }
+    }
+
+    // Whether client `id` is a replica, synchronized or not.
+    pub fn is_replica(&self, client_id: u64) -> bool {
+        self.state().replicas.contains_key(&client_id)
+    }
+
+    pub fn detach(&self, client_id: u64) {
+        let mut state = self.state();
+        state.replicas.remove(&client_id);
+        state.announced.remove(&client_id);
+    }
+
+    // Records that the replica processed the stream up to `offset`.
+    pub fn ack(&self, client_id: u64, offset: u64, now: i64) {
+        if let Some(replica) = self.state().replicas.get_mut(&client_id) {
+            replica.ack_offset = replica.ack_offset.max(offset);
+            replica.ack_at = now;
+        }
+    }
+
+    // The number of replicas that acknowledged `offset`.
+    pub fn acked(&self, offset: u64) -> usize {
+        let state = self.state();
+        let replicas = state.replicas.values();
+        replicas
+            .filter(|r| r.online && r.ack_offset >= offset)
+            .count()
+    }
+
+    // Streams the commands a write that ran in database `db` amounts to, or commands that don't
+    // depend on the database without one. The backlog keeps the last `backlog_size` bytes. A
+    // replica streams what its master sent instead, see feed_raw.
+    pub fn feed(&self, db: Option<usize>, commands: &[LogCommand], backlog_size: usize) {
+        if self.master.borrow().is_some() {
+            return;
+        }
+        let mut state = self.state();
+        let state = &mut *state;
+        if state.backlog.is_none() {
+            return;
+        }
+        for args in commands {
+            match db {
+                Some(db) => state.stream.append(db, args),
+                None => state.stream.command(args),
+            }
+        }
+        let data = std::mem::take(&mut state.stream.data);
+        state.extend(data, backlog_size);
+    }
+
+    // Streams bytes of the stream of the master as they were received, once they were
+    // processed. Returns the offset reached.
+    pub fn feed_raw(&self, data: Vec<u8>, backlog_size: usize, now: i64) -> u64 {
+        let mut state = self.state();
+        state.link.io_at = Some(now);
+        state.extend(data, backlog_size);
+        state.offset
+    }
+
+    // Makes the server a replica of `master`, or a master again with None. Returns false if it
+    // already replicates that master. A promoted replica starts a history of its own, which
+    // continues the one of its master.
+    pub fn set_master(&self, master: Option<(String, u16)>) -> bool {
+        if *self.master.borrow() == master {
+            return false;
+        }
+        let mut state = self.state();
+        if master.is_none() {
+            state.shift_replid(random_id());
+            // the writes made from now on select their database
+            state.stream = CommandLog::default();
+        }
+        state.link = MasterLink {
+            state: "connect",
+            io_at: None,
+        };
+        self.master.send_replace(master);
+        true
+    }
+
+    pub fn master(&self) -> Option<(String, u16)> {
+        self.master.borrow().clone()
+    }
+
+    // Changes whenever REPLICAOF sets another master.
+    pub fn watch_master(&self) -> watch::Receiver<Option<(String, u16)>> {
+        self.master.subscribe()
+    }
+
+    pub fn set_link_state(&self, state: &'static str) {
+        self.state().link.state = state;
+    }
+
+    // Records that the replica loaded the snapshot of its master, taking on its history at
+    // `offset`. Replicas of the replica are dropped, they need the new snapshot.
+    pub fn full_synced(&self, replid: String, offset: u64, now: i64) {
+        let mut state = self.state();
+        state.replid = replid;
+        state.replid2 = None;
+        state.offset = offset;
+        state.backlog = Some(VecDeque::new());
+        state.replicas.clear();
+        state.link = MasterLink {
+            state: "connected",
+            io_at: Some(now),
+        };
+    }
+
+    // Records that the master continues the stream the replica already has, now under the
+    // replication ID `replid`, which changes when the master was promoted meanwhile.
+    pub fn partial_synced(&self, replid: String, now: i64) {
+        let mut state = self.state();
+        if replid != state.replid {
+            state.shift_replid(replid);
+        }
+        state.backlog.get_or_insert_with(VecDeque::new);
+        state.link = MasterLink {
+            state: "connected",
+            io_at: Some(now),
+        };
+    }
+
+    // The master the server replicates, `now` in milliseconds since the unix epoch.
+    pub fn master_info(&self, now: i64) -> Option<MasterInfo> {
+        let (host, port) = self.master()?;
+        let state = self.state();
+        Some(MasterInfo {
+            host,
+            port,
+            state: state.link.state,
+            last_io: state.link.io_at.map(|at| (now - at) / 1000),
+        })
+    }
+
+    // The replicas in the order they attached, `now` in milliseconds since the unix epoch.
+    pub fn replicas(&self, now: i64) -> Vec<ReplicaInfo> {
+        let state = self.state();
+        let replicas = state.replicas.values().map(|replica| {
+            // the address the replica connected from, without its port
+            let ip = match replica.addr.rsplit_once(':') {
+                Some((ip, _)) => ip.to_string(),
+                None => replica.addr.clone(),
+            };
+            ReplicaInfo {
+                ip,
+                port: replica.port,
+                state: if replica.online {
+                    "online"
+                } else {
+                    "wait_bgsave"
+                },
+                offset: replica.ack_offset,
+                lag: (now - replica.ack_at) / 1000,
+            }
+        });
+        replicas.collect()
+    }
+}
+
+impl ReplState {
+    fn add_replica(
+        &mut self,
+        client_id: u64,
+        addr: String,
+        online: bool,
+        ack_offset: u64,
+        now: i64,
+    ) -> ReplicaStream {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let pending = Arc::new(AtomicUsize::new(0));
+        let replica = Replica {
+            addr,
+            port: self.announced.get(&client_id).copied().unwrap_or(0),
+            online,
+            ack_offset,
+            ack_at: now,
+            sender,
+            pending: pending.clone(),
+        };
+        self.replicas.insert(client_id, replica);
+        ReplicaStream { receiver, pending }
+    }
+
+    // Appends to the stream, trimming the backlog to its last `backlog_size` bytes.
+    fn extend(&mut self, data: Vec<u8>, backlog_size: usize) {
+        let Some(backlog) = self.backlog.as_mut() else {
+            return;
+        };
+        self.offset += data.len() as u64;
+        backlog.extend(&data);
+        let excess = backlog.len().saturating_sub(backlog_size);
+        backlog.drain(..excess);
+        self.replicas
+            .retain(|_, replica| replica.send(data.clone()));
+    }
+
+    // Starts the history `replid`, which continues the current one: replicas of the current one
+    // may still resume it from the backlog.
+    fn shift_replid(&mut self, replid: String) {
+        let previous = std::mem::replace(&mut self.replid, replid);
+        self.replid2 = Some((previous, self.offset + 1));
+    }
+}
+
+impl Replica {
+    // Queues data to send, returns false if the replica is gone or too far behind.
+    fn send(&self, data: Vec<u8>) -> bool {
+        let pending = self.pending.fetch_add(data.len(), Ordering::Relaxed);
+        pending + data.len() <= REPLICA_OUTPUT_LIMIT && self.sender.send(data).is_ok()
+    }
+}
+
+impl Default for Replication {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 40 random hexadecimal digits, replication IDs and cluster node IDs
+pub(super) fn random_id() -> String {
+    let mut rng = rand::thread_rng();
+    (0..40)
+        .map(|_| char::from_digit(rng.gen_range(0..16), 16).unwrap_or('0'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> LogCommand {
+        args.iter().map(|a| a.as_bytes().to_vec()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_replication_stream() {
+        let replication = Replication::new();
+        assert_eq!(replication.replid().len(), 40);
+        // nothing is streamed before a replica attached
+        replication.feed(Some(0), &[args(&["SET", "k", "v"])], 100);
+        assert!(!replication.is_active());
+        assert_eq!(replication.offset(), 0);
+
+        replication.announce(7, 6380);
+        let (offset, mut stream) = replication.attach(7, "127.0.0.1:5000".to_string(), 0);
+        assert_eq!(offset, 0);
+        replication.feed(Some(1), &[args(&["DEL", "k"])], 100);
+        let expected = b"*2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n*2\r\n$3\r\nDEL\r\n$1\r\nk\r\n";
+        assert_eq!(stream.recv().await.as_deref(), Some(&expected[..]));
+        assert_eq!(replication.offset(), expected.len() as u64);
+        assert_eq!(replication.backlog(), Some((1, expected.len())));
+
+        // the backlog keeps the latest bytes only
+        replication.feed(None, &[args(&["PING"])], 20);
+        let offset = replication.offset();
+        assert_eq!(replication.backlog(), Some((offset - 19, 20)));
+
+        assert_eq!(replication.acked(offset), 0);
+        replication.set_online(7);
+        replication.ack(7, offset, 3000);
+        assert_eq!(replication.acked(offset), 1);
+        assert_eq!(
+            replication.replicas(5000),
+            [ReplicaInfo {
+                ip: "127.0.0.1".to_string(),
+                port: 6380,
+                state: "online",
+                offset,
+                lag: 2,
+            }]
+        );
+        replication.detach(7);
+        assert!(replication.replicas(5000).is_empty());
+    }
+}
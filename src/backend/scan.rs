@@ -0,0 +1,139 @@
+// Cursors are positions in the order the elements of a collection are iterated in, which only
+// changes when elements are added or removed. A page visits the elements from the cursor on and
+// stops after COUNT of them, without going over the ones before the cursor again; an element
+// added or removed in between may make the next page skip an element or return one again.
+
+// Returns one page of `items` starting at `cursor` together with the cursor of the next page
+// (0 once the iteration is complete). At most `count` elements are visited, elements not matching
+// `pattern` are dropped after the visit, like in redis.
+pub(crate) fn scan_page<M: AsRef<[u8]>, T>(
+    items: impl IntoIterator<Item = (M, T)>,
+    cursor: u64,
+    count: usize,
+    pattern: Option<&[u8]>,
+) -> (u64, Vec<(M, T)>) {
+    let mut items = items.into_iter().skip(cursor as usize);
+    let mut visited = 0;
+    let page = items
+        .by_ref()
+        .take(count.max(1))
+        .inspect(|_| visited += 1)
+        .filter(|(member, _)| pattern.is_none_or(|p| glob_match(p, member)))
+        .collect();
+    let next = match items.next() {
+        Some(_) => cursor + visited,
+        None => 0,
+    };
+    (next, page)
+}
+
+// Glob-style matching as used by KEYS and the SCAN family: `*`, `?`, `[abc]`, `[^abc]`,
+// `[a-z]` and `\` to escape the next character, byte by byte as keys are binary.
+pub fn glob_match(pattern: impl AsRef<[u8]>, s: impl AsRef<[u8]>) -> bool {
+    glob_match_bytes(pattern.as_ref(), s.as_ref())
+}
+
+fn glob_match_bytes(pattern: &[u8], s: &[u8]) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // where to resume when the last `*` has to swallow one more character
+    let mut backtrack = None;
+    while i < s.len() {
+        let step = match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, i));
+                p += 1;
+                continue;
+            }
+            Some(b'?') => Some(p + 1),
+            Some(b'[') => match_class(pattern, p, s[i]),
+            Some(b'\\') if p + 1 < pattern.len() => (pattern[p + 1] == s[i]).then_some(p + 2),
+            Some(&c) => (c == s[i]).then_some(p + 1),
+            None => None,
+        };
+        match (step, backtrack) {
+            (Some(next), _) => {
+                p = next;
+                i += 1;
+            }
+            (None, Some((star, pos))) => {
+                backtrack = Some((star, pos + 1));
+                p = star + 1;
+                i = pos + 1;
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+// Matches `c` against the class starting at `pattern[start] == '['`, returning the position right
+// after the class on success.
+fn match_class(pattern: &[u8], start: usize, c: u8) -> Option<usize> {
+    let mut p = start + 1;
+    let negate = pattern.get(p) == Some(&b'^');
+    if negate {
+        p += 1;
+    }
+
+    let mut matched = false;
+    while p < pattern.len() && pattern[p] != b']' {
+        if pattern[p] == b'\\' && p + 1 < pattern.len() {
+            matched |= pattern[p + 1] == c;
+            p += 2;
+        } else if p + 2 < pattern.len() && pattern[p + 1] == b'-' && pattern[p + 2] != b']' {
+            let (lo, hi) = (
+                pattern[p].min(pattern[p + 2]),
+                pattern[p].max(pattern[p + 2]),
+            );
+            matched |= (lo..=hi).contains(&c);
+            p += 3;
+        } else {
+            matched |= pattern[p] == c;
+            p += 1;
+        }
+    }
+    // an unterminated class runs to the end of the pattern
+    (matched != negate).then_some((p + 1).min(pattern.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("h?llo", "hello"));
+        assert!(glob_match("h*llo", "heeeello"));
+        assert!(glob_match("h[ae]llo", "hallo"));
+        assert!(!glob_match("h[ae]llo", "hillo"));
+        assert!(glob_match("h[^e]llo", "hallo"));
+        assert!(!glob_match("h[^e]llo", "hello"));
+        assert!(glob_match("h[a-b]llo", "hbllo"));
+        assert!(glob_match("h\\*llo", "h*llo"));
+        assert!(!glob_match("h\\*llo", "hello"));
+        assert!(glob_match("*b*c", "abxbc"));
+        assert!(!glob_match("a*b", "acbd"));
+    }
+
+    #[test]
+    fn test_scan_page_visits_everything() {
+        let items = (0..100).map(|i| (i.to_string(), i)).collect::<Vec<_>>();
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        loop {
+            let (next, page) = scan_page(items.clone(), cursor, 7, None);
+            seen.extend(page.into_iter().map(|(member, _)| member));
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert_eq!(seen.len(), 100);
+
+        let (next, page) = scan_page(items, 0, 1000, Some(&b"1?"[..]));
+        assert_eq!(next, 0);
+        assert_eq!(page.len(), 10);
+    }
+}
@@ -0,0 +1,107 @@
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// The scripts EVAL ran or SCRIPT LOAD loaded, shared by every connection so that EVALSHA can
+// run them by the SHA1 digest of their body. It lives until SCRIPT FLUSH.
+#[derive(Debug, Default)]
+pub struct ScriptCache {
+    // bodies by digest in lowercase hex
+    scripts: DashMap<String, String>,
+}
+
+impl ScriptCache {
+    // Caches the script `body` and returns its SHA1 digest.
+    pub fn insert(&self, body: &str) -> String {
+        let sha1 = digest(body);
+        self.scripts.insert(sha1.clone(), body.to_string());
+        sha1
+    }
+
+    // The body of the script whose digest is `sha1`, in any case.
+    pub fn get(&self, sha1: &str) -> Option<String> {
+        self.scripts
+            .get(&sha1.to_ascii_lowercase())
+            .map(|body| body.clone())
+    }
+
+    pub fn contains(&self, sha1: &str) -> bool {
+        self.scripts.contains_key(&sha1.to_ascii_lowercase())
+    }
+
+    pub fn flush(&self) {
+        self.scripts.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.scripts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+}
+
+// A script being run. SCRIPT KILL can stop it as long as it didn't write: the Lua integration
+// checks `killed` every so many instructions.
+#[derive(Debug)]
+pub struct ScriptRun {
+    // milliseconds since the unix epoch
+    started: i64,
+    killed: AtomicBool,
+    wrote: AtomicBool,
+}
+
+impl ScriptRun {
+    pub fn new(started: i64) -> Self {
+        Self {
+            started,
+            killed: AtomicBool::new(false),
+            wrote: AtomicBool::new(false),
+        }
+    }
+
+    pub fn started(&self) -> i64 {
+        self.started
+    }
+
+    pub fn kill(&self) {
+        self.killed.store(true, Ordering::Relaxed);
+    }
+
+    pub fn killed(&self) -> bool {
+        self.killed.load(Ordering::Relaxed)
+    }
+
+    // Records that the script ran a write command, it can't be killed anymore.
+    pub fn record_write(&self) {
+        self.wrote.store(true, Ordering::Relaxed);
+    }
+
+    pub fn wrote(&self) -> bool {
+        self.wrote.load(Ordering::Relaxed)
+    }
+}
+
+// The SHA1 digest of a script body in lowercase hex, as SCRIPT LOAD returns it.
+fn digest(body: &str) -> String {
+    sha1_smol::Sha1::from(body).digest().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_cache() {
+        let cache = ScriptCache::default();
+        let sha1 = cache.insert("return 1");
+        assert_eq!(sha1, "e0e1f9fabfc9d4800c877a703b823ac0578ff8db");
+        assert_eq!(cache.get(&sha1.to_uppercase()).as_deref(), Some("return 1"));
+        assert!(cache.contains(&sha1));
+        assert_eq!(cache.len(), 1);
+
+        cache.flush();
+        assert!(cache.is_empty());
+        assert_eq!(cache.get(&sha1), None);
+    }
+}
@@ -0,0 +1,261 @@
+use crate::RespFrame;
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+// Runtime counters reported by INFO. The network layer accounts for connections and executed
+// commands, the backend for keyspace lookups and expired or evicted keys.
+#[derive(Debug)]
+pub struct Stats {
+    pub(crate) started_at: Instant,
+    pub(crate) connected_clients: AtomicUsize,
+    pub(crate) total_connections_received: AtomicU64,
+    pub(crate) total_commands_processed: AtomicU64,
+    pub(crate) keyspace_hits: AtomicU64,
+    pub(crate) keyspace_misses: AtomicU64,
+    // keys removed because their expiry time passed, lazily or by the active expiry cycle
+    pub(crate) expired_keys: AtomicU64,
+    // keys removed to make room under maxmemory
+    pub(crate) evicted_keys: AtomicU64,
+    // per command name (lowercase)
+    pub(crate) commands: DashMap<String, CommandStats>,
+    pub(crate) total_error_replies: AtomicU64,
+    // error replies per error code, the first word of the error
+    pub(crate) errors: DashMap<String, u64>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CommandStats {
+    pub calls: u64,
+    // total execution time in microseconds
+    pub usec: u64,
+    // refused before running, e.g. over maxmemory or on a read only replica
+    pub rejected_calls: u64,
+    // ran and replied with an error
+    pub failed_calls: u64,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            connected_clients: AtomicUsize::new(0),
+            total_connections_received: AtomicU64::new(0),
+            total_commands_processed: AtomicU64::new(0),
+            keyspace_hits: AtomicU64::new(0),
+            keyspace_misses: AtomicU64::new(0),
+            expired_keys: AtomicU64::new(0),
+            evicted_keys: AtomicU64::new(0),
+            commands: DashMap::new(),
+            total_error_replies: AtomicU64::new(0),
+            errors: DashMap::new(),
+        }
+    }
+
+    pub fn client_connected(&self) {
+        self.connected_clients.fetch_add(1, Ordering::Relaxed);
+        self.total_connections_received
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn client_disconnected(&self) {
+        self.connected_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    // Counts a run of the command, failed if it replied with `error`.
+    pub fn command_executed(&self, name: &str, elapsed: Duration, error: Option<&str>) {
+        self.total_commands_processed
+            .fetch_add(1, Ordering::Relaxed);
+        {
+            let mut stats = self.commands.entry(name.to_string()).or_default();
+            stats.calls += 1;
+            stats.usec += elapsed.as_micros() as u64;
+            stats.failed_calls += error.is_some() as u64;
+        }
+        if let Some(error) = error {
+            self.error_replied(error);
+        }
+    }
+
+    // Counts a command refused with `error` before it could run.
+    pub fn command_rejected(&self, name: &str, error: &str) {
+        self.commands
+            .entry(name.to_string())
+            .or_default()
+            .rejected_calls += 1;
+        self.error_replied(error);
+    }
+
+    pub fn error_replied(&self, error: &str) {
+        self.total_error_replies.fetch_add(1, Ordering::Relaxed);
+        let code = error.split(' ').next().unwrap_or_default();
+        *self.errors.entry(code.to_string()).or_default() += 1;
+    }
+
+    // Starts every counter over, as CONFIG RESETSTAT does. The connected clients are still
+    // connected.
+    pub fn reset(&self) {
+        let counters = [
+            &self.total_connections_received,
+            &self.total_commands_processed,
+            &self.keyspace_hits,
+            &self.keyspace_misses,
+            &self.expired_keys,
+            &self.evicted_keys,
+            &self.total_error_replies,
+        ];
+        for counter in counters {
+            counter.store(0, Ordering::Relaxed);
+        }
+        self.commands.clear();
+        self.errors.clear();
+    }
+
+    pub fn keyspace_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.keyspace_hits
+        } else {
+            &self.keyspace_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn key_expired(&self) {
+        self.expired_keys.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn key_evicted(&self) {
+        self.evicted_keys.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    pub fn connected_clients(&self) -> usize {
+        self.connected_clients.load(Ordering::Relaxed)
+    }
+
+    pub fn total_connections_received(&self) -> u64 {
+        self.total_connections_received.load(Ordering::Relaxed)
+    }
+
+    pub fn total_commands_processed(&self) -> u64 {
+        self.total_commands_processed.load(Ordering::Relaxed)
+    }
+
+    pub fn keyspace_hits(&self) -> u64 {
+        self.keyspace_hits.load(Ordering::Relaxed)
+    }
+
+    pub fn keyspace_misses(&self) -> u64 {
+        self.keyspace_misses.load(Ordering::Relaxed)
+    }
+
+    pub fn expired_keys(&self) -> u64 {
+        self.expired_keys.load(Ordering::Relaxed)
+    }
+
+    pub fn evicted_keys(&self) -> u64 {
+        self.evicted_keys.load(Ordering::Relaxed)
+    }
+
+    // (name, stats) of every command executed so far, sorted by name
+    pub fn command_stats(&self) -> Vec<(String, CommandStats)> {
+        let mut stats = self
+            .commands
+            .iter()
+            .map(|e| (e.key().clone(), *e.value()))
+            .collect::<Vec<_>>();
+        stats.sort_by(|a, b| a.0.cmp(&b.0));
+        stats
+    }
+
+    pub fn total_error_replies(&self) -> u64 {
+        self.total_error_replies.load(Ordering::Relaxed)
+    }
+
+    // (error code, count) of every error replied so far, sorted by code
+    pub fn error_stats(&self) -> Vec<(String, u64)> {
+        let mut stats = self
+            .errors
+            .iter()
+            .map(|e| (e.key().clone(), *e.value()))
+            .collect::<Vec<_>>();
+        stats.sort();
+        stats
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The message of a reply that is an error, which is what errorstats counts.
+pub fn error_message(reply: &RespFrame) -> Option<&str> {
+    match reply {
+        RespFrame::Error(e) => Some(&e.0),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let stats = Stats::new();
+        stats.client_connected();
+        stats.client_connected();
+        stats.client_disconnected();
+        assert_eq!(stats.connected_clients(), 1);
+        assert_eq!(stats.total_connections_received(), 2);
+
+        stats.command_executed("get", Duration::from_micros(10), None);
+        stats.command_executed(
+            "get",
+            Duration::from_micros(20),
+            Some("WRONGTYPE Operation"),
+        );
+        stats.command_executed("set", Duration::from_micros(5), None);
+        stats.command_rejected("set", "OOM command not allowed");
+        assert_eq!(stats.total_commands_processed(), 3);
+        let get = CommandStats {
+            calls: 2,
+            usec: 30,
+            rejected_calls: 0,
+            failed_calls: 1,
+        };
+        let set = CommandStats {
+            calls: 1,
+            usec: 5,
+            rejected_calls: 1,
+            failed_calls: 0,
+        };
+        assert_eq!(
+            stats.command_stats(),
+            vec![("get".to_string(), get), ("set".to_string(), set)]
+        );
+        assert_eq!(stats.total_error_replies(), 2);
+        assert_eq!(
+            stats.error_stats(),
+            vec![("OOM".to_string(), 1), ("WRONGTYPE".to_string(), 1)]
+        );
+
+        stats.keyspace_lookup(true);
+        stats.keyspace_lookup(false);
+        stats.keyspace_lookup(false);
+        assert_eq!(stats.keyspace_hits(), 1);
+        assert_eq!(stats.keyspace_misses(), 2);
+
+        stats.reset();
+        assert_eq!(stats.connected_clients(), 1);
+        assert_eq!(stats.total_commands_processed(), 0);
+        assert_eq!(stats.keyspace_misses(), 0);
+        assert!(stats.command_stats().is_empty());
+        assert!(stats.error_stats().is_empty());
+    }
+}
@@ -0,0 +1,908 @@
+use super::memory::table;
+use super::Value;
+use crate::{RespFrame, RespNull};
+use dashmap::mapref::entry::Entry;
+use dashmap::mapref::one::{MappedRef, MappedRefMut, RefMut};
+use dashmap::DashMap;
+use rand::Rng;
+use std::collections::hash_map::RandomState;
+use std::fmt::Debug;
+use std::hash::BuildHasher;
+use std::mem::size_of;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+
+// The engine every database keeps its keys in: ShardedStorage, or a single MemoryStorage with
+// the unsharded-storage feature.
+#[cfg(not(feature = "unsharded-storage"))]
+pub type Keyspace = ShardedStorage;
+#[cfg(feature = "unsharded-storage")]
+pub type Keyspace = MemoryStorage;
+
+// Number of shards of a ShardedStorage, and of locks each shard splits its maps in.
+const SHARDS: usize = 16;
+const SHARD_LOCKS: usize = 4;
+
+// A scan cursor of a MemoryStorage is the lock index above these bits and the position of the
+// key among those under that lock below them; a ShardedStorage puts the shard index above the
+// cursor of the shard.
+const LOCK_CURSOR_BITS: u32 = 32;
+const SHARD_CURSOR_BITS: u32 = 48;
+
+// Where the keys of a database, their values and their expiry times live. Backend implements the
+// commands on top of it, another engine (e.g. one persisting to disk) can take the place of the
+// in-memory one without the commands noticing.
+//
+// Values are handed out behind guards; the callbacks of the methods iterating over the keys must
+// not write to the storage they iterate over.
+pub trait Storage: Debug + Default + Send + Sync {
+    // A guard on a value, or on the part of it picked with map_ref.
+    type Ref<'a, T>: Deref<Target = T>
+    where
+        Self: 'a;
+    // Like Ref, for writing.
+    type RefMut<'a, T>: DerefMut<Target = T>
+    where
+        Self: 'a;
+
+    fn get(&self, key: &[u8]) -> Option<Self::Ref<'_, Value>>;
+
+    fn get_mut(&self, key: &[u8]) -> Option<Self::RefMut<'_, Value>>;
+
+    // The value at `key`, created with `create` first if the key doesn't exist.
+    fn get_or_insert_with(
+        &self,
+        key: Vec<u8>,
+        create: impl FnOnce() -> Value,
+    ) -> Self::RefMut<'_, Value>;
+
+    // Narrows the guard down to what `pick` takes out of the value, None if it takes nothing.
+    fn map_ref<'a, T, U>(
+        value: Self::Ref<'a, T>,
+        pick: impl FnOnce(&T) -> Option<&U>,
+    ) -> Option<Self::Ref<'a, U>>;
+
+    fn map_mut<'a, T, U>(
+        value: Self::RefMut<'a, T>,
+        pick: impl FnOnce(&mut T) -> Option<&mut U>,
+    ) -> Option<Self::RefMut<'a, U>>;
+
+    // Calls `f` with the value at `key`, or with None when the key doesn't exist in which case
+    // the value `f` returns besides its result (if any) is inserted. No other write to the key
+    // lands in between.
+    fn upsert<R>(
+        &self,
+        key: Vec<u8>,
+        f: impl FnOnce(Option<&mut Value>) -> (R, Option<Value>),
+    ) -> R;
+
+    // Returns the value the key had.
+    fn insert(&self, key: Vec<u8>, value: Value) -> Option<Value>;
+
+    fn remove(&self, key: &[u8]) -> Option<Value>;
+
+    // Removes the key if `condition` holds for its value.
+    fn remove_if(&self, key: &[u8], condition: impl FnOnce(&Value) -> bool) -> Option<Value>;
+
+    fn contains_key(&self, key: &[u8]) -> bool;
+
+    // Number of keys.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn for_each(&self, f: impl FnMut(&[u8], &Value));
+
+    // Calls `f` with the keys from `cursor` on, until it returns false. Returns the cursor of the
+    // key after the last one visited, 0 once there is none. The keys come in an order which
+    // only changes when keys are added or removed, so a scan may then skip a key or repeat one.
+    fn scan(&self, cursor: u64, f: impl FnMut(&[u8], &Value) -> bool) -> u64;
+
+    // Every key, whatever the type of its value.
+    fn keys(&self) -> Vec<Vec<u8>> {
+        let mut keys = Vec::with_capacity(self.len());
+        self.for_each(|key, _| keys.push(key.to_vec()));
+        keys
+    }
+
+    // Up to `count` keys picked at random, among those with an expiry time only if `volatile`.
+    // Only the keys kept in the sample are copied.
+    fn sample_keys(&self, count: usize, volatile: bool) -> Vec<Vec<u8>> {
+        let mut rng = rand::thread_rng();
+        let (mut keys, mut seen) = (Vec::with_capacity(count), 0);
+        let mut visit = |key: &[u8]| {
+            // each key seen so far ends up in the sample with the same odds
+            seen += 1;
+            if keys.len() < count {
+                keys.push(key.to_vec());
+            } else if let Some(kept) = keys.get_mut(rng.gen_range(0..seen)) {
+                *kept = key.to_vec();
+            }
+        };
+        if volatile {
+            self.for_each_expire(|key, _| visit(key));
+        } else {
+            self.for_each(|key, _| visit(key));
+        }
+        keys
+    }
+
+    // A key picked at random, among those with an expiry time only if `volatile`. None if there
+    // is no such key.
+    fn random_key(&self, volatile: bool) -> Option<Vec<u8>>;
+
+    // Removes every key and their expiry times, returning what held them for the caller to drop
+    // wherever it suits it.
+    fn drain(&self) -> Box<dyn Send>;
+
+    fn clear(&self);
+
+    // The absolute expiry time of the key in milliseconds since the unix epoch, None if it
+    // doesn't expire (or doesn't exist).
+    fn expire_at(&self, key: &[u8]) -> Option<i64>;
+
+    fn set_expire(&self, key: Vec<u8>, when: i64);
+
+    // Makes the key persistent. Returns the expiry time it had.
+    fn remove_expire(&self, key: &[u8]) -> Option<i64>;
+
+    // Removes the expiry time of the key if `condition` holds for it, returning it.
+    fn remove_expire_if(&self, key: &[u8], condition: impl FnOnce(i64) -> bool) -> Option<i64>;
+
+    // Number of keys with an expiry time.
+    fn volatile_len(&self) -> usize;
+
+    fn for_each_expire(&self, f: impl FnMut(&[u8], i64));
+
+    // Bytes taken by the engine's own structures, not counting keys and values: for the values
+    // on one hand and for the expiry times on the other.
+    fn overhead(&self) -> (usize, usize);
+
+    // Starts a snapshot named `epoch`: from now on, a key about to be written first has its
+    // value and expiry time set aside, so that for_each_snapshot goes over the keys as they are
+    // at this point while writes proceed. Several snapshots can run at once.
+    fn begin_snapshot(&self, epoch: u64);
+
+    // Calls `f` with every key as it was when the snapshot began, its value and expiry time.
+    // Writes to the key visited wait for `f` to return, the others go ahead.
+    fn for_each_snapshot(&self, epoch: u64, f: impl FnMut(&[u8], &Value, Option<i64>));
+
+    // Drops what was set aside for the snapshot.
+    fn end_snapshot(&self, epoch: u64);
+}
+
+// What the keys written since a snapshot began held then: their value and expiry time, None for
+// the keys that didn't exist.
+type Frozen = DashMap<Vec<u8>, Option<(Value, Option<i64>)>>;
+type Snapshots = Vec<(u64, Arc<Frozen>)>;
+
+// The values and expiry times in concurrent hash maps, sharded so that connections working on
+// different keys don't wait for each other.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    values: DashMap<Vec<u8>, Value>,
+    expires: DashMap<Vec<u8>, i64>,
+    // the running snapshots by epoch
+    snapshots: RwLock<Snapshots>,
+}
+
+impl MemoryStorage {
+    // A storage whose maps are split in `locks` shards, a power of two greater than 1.
+    pub fn with_locks(locks: usize) -> Self {
+        Self {
+            values: DashMap::with_shard_amount(locks),
+            expires: DashMap::with_shard_amount(locks),
+            snapshots: RwLock::default(),
+        }
+    }
+
+    // A key of `map` picked at random, going through the keys under a single lock only.
+    fn random_in<V>(map: &DashMap<Vec<u8>, V>) -> Option<Vec<u8>> {
+        let mut rng = rand::thread_rng();
+        loop {
+            let lens = map.shards().iter().map(|lock| lock.read().len());
+            let lens = lens.collect::<Vec<_>>();
+            let total = lens.iter().sum::<usize>();
+            if total == 0 {
+                return None;
+            }
+            let mut picked = rng.gen_range(0..total);
+            for (lock, len) in map.shards().iter().zip(lens) {
+                if picked >= len {
+                    picked -= len;
+                    continue;
+                }
+                // keys removed meanwhile may leave nothing at that position, then pick again
+                if let Some((key, _)) = lock.read().iter().nth(picked) {
+                    return Some(key.clone());
+                }
+                break;
+            }
+        }
+    }
+
+    // Up to `count` keys of `map` following each other from a random position, like the samples
+    // Redis takes: only the keys before that position under its lock are gone through besides.
+    fn sample_in<V>(map: &DashMap<Vec<u8>, V>, count: usize) -> Vec<Vec<u8>> {
+        let locks = map.shards();
+        let first = rand::thread_rng().gen_range(0..locks.len());
+        let (mut keys, mut offset) = (Vec::with_capacity(count), 0);
+        // the keys of the first lock before the position come last
+        for i in 0..=locks.len() {
+            let left = count - keys.len();
+            if left == 0 {
+                break;
+            }
+            let lock = locks[(first + i) % locks.len()].read();
+            let (skip, take) = match i {
+                0 => {
+                    offset = rand::thread_rng().gen_range(0..lock.len().max(1));
+                    (offset, left)
+                }
+                i if i == locks.len() => (0, left.min(offset)),
+                _ => (0, left),
+            };
+            keys.extend(
+                lock.iter()
+                    .skip(skip)
+                    .take(take)
+                    .map(|(key, _)| key.clone()),
+            );
+        }
+        keys
+    }
+
+    fn snapshots(&self) -> RwLockReadGuard<'_, Snapshots> {
+        self.snapshots.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Sets what the key holds now aside for the running snapshots that don't have it yet, right
+    // before it is written. The caller holds the lock on the key's value, so that no other write
+    // to the key lands in between and no snapshot reads it meanwhile.
+    fn preserve(&self, key: &[u8], value: Option<&Value>) {
+        for (_, frozen) in self.snapshots().iter() {
+            if !frozen.contains_key(key) {
+                let kept = value.map(|value| (value.clone(), self.expire_at(key)));
+                frozen.insert(key.to_vec(), kept);
+            }
+        }
+    }
+
+    // While snapshots are running, the lock on the key's value once it is set aside: expiry
+    // times are written under it so that the snapshots see them change along with the values.
+    fn lock_for_expire(&self, key: &[u8]) -> Option<RefMut<'_, Vec<u8>, Value>> {
+        if self.snapshots().is_empty() {
+            return None;
+        }
+        let value = self.values.get_mut(key)?;
+        self.preserve(key, Some(value.value()));
+        Some(value)
+    }
+}
+
+impl Storage for MemoryStorage {
+    type Ref<'a, T> = MappedRef<'a, Vec<u8>, Value, T>;
+    type RefMut<'a, T> = MappedRefMut<'a, Vec<u8>, Value, T>;
+
+    fn get(&self, key: &[u8]) -> Option<Self::Ref<'_, Value>> {
+        Some(self.values.get(key)?.map(|value| value))
+    }
+
+    fn get_mut(&self, key: &[u8]) -> Option<Self::RefMut<'_, Value>> {
+        let value = self.values.get_mut(key)?;
+        self.preserve(key, Some(value.value()));
+        Some(value.map(|value| value))
+    }
+
+    fn get_or_insert_with(
+        &self,
+        key: Vec<u8>,
+        create: impl FnOnce() -> Value,
+    ) -> Self::RefMut<'_, Value> {
+        let value = match self.values.entry(key) {
+            Entry::Occupied(entry) => {
+                self.preserve(entry.key(), Some(entry.get()));
+                entry.into_ref()
+            }
+            Entry::Vacant(entry) => {
+                self.preserve(entry.key(), None);
+                entry.insert(create())
+            }
+        };
+        value.map(|value| value)
+    }
+
+    fn map_ref<'a, T, U>(
+        value: Self::Ref<'a, T>,
+        pick: impl FnOnce(&T) -> Option<&U>,
+    ) -> Option<Self::Ref<'a, U>> {
+        value.try_map(pick).ok()
+    }
+
+    fn map_mut<'a, T, U>(
+        value: Self::RefMut<'a, T>,
+        pick: impl FnOnce(&mut T) -> Option<&mut U>,
+    ) -> Option<Self::RefMut<'a, U>> {
+        value.try_map(pick).ok()
+    }
+
+    fn upsert<R>(
+        &self,
+        key: Vec<u8>,
+        f: impl FnOnce(Option<&mut Value>) -> (R, Option<Value>),
+    ) -> R {
+        match self.values.entry(key) {
+            Entry::Occupied(mut entry) => {
+                self.preserve(entry.key(), Some(entry.get()));
+                f(Some(entry.get_mut())).0
+            }
+            Entry::Vacant(entry) => {
+                let (result, value) = f(None);
+                if let Some(value) = value {
+                    self.preserve(entry.key(), None);
+                    entry.insert(value);
+                }
+                result
+            }
+        }
+    }
+
+    fn insert(&self, key: Vec<u8>, value: Value) -> Option<Value> {
+        match self.values.entry(key) {
+            Entry::Occupied(mut entry) => {
+                self.preserve(entry.key(), Some(entry.get()));
+                Some(entry.insert(value))
+            }
+            Entry::Vacant(entry) => {
+                self.preserve(entry.key(), None);
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    fn remove(&self, key: &[u8]) -> Option<Value> {
+        self.remove_if(key, |_| true)
+    }
+
+    fn remove_if(&self, key: &[u8], condition: impl FnOnce(&Value) -> bool) -> Option<Value> {
+        self.values
+            .remove_if(key, |key, value| {
+                let remove = condition(value);
+                if remove {
+                    self.preserve(key, Some(value));
+                }
+                remove
+            })
+            .map(|(_, value)| value)
+    }
+
+    fn contains_key(&self, key: &[u8]) -> bool {
+        self.values.contains_key(key)
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn for_each(&self, mut f: impl FnMut(&[u8], &Value)) {
+        for entry in self.values.iter() {
+            f(entry.key(), entry.value());
+        }
+    }
+
+    fn scan(&self, cursor: u64, mut f: impl FnMut(&[u8], &Value) -> bool) -> u64 {
+        let locks = self.values.shards();
+        let first = (cursor >> LOCK_CURSOR_BITS) as usize;
+        let mut skip = (cursor & ((1 << LOCK_CURSOR_BITS) - 1)) as usize;
+        for (i, lock) in locks.iter().enumerate().skip(first) {
+            let keys = lock.read();
+            for (position, (key, value)) in keys.iter().enumerate().skip(skip) {
+                if f(key, value.get()) {
+                    continue;
+                }
+                return if position + 1 < keys.len() {
+                    ((i as u64) << LOCK_CURSOR_BITS) | (position + 1) as u64
+                } else if i + 1 < locks.len() {
+                    ((i + 1) as u64) << LOCK_CURSOR_BITS
+                } else {
+                    0
+                };
+            }
+            skip = 0;
+        }
+        0
+    }
+
+    fn sample_keys(&self, count: usize, volatile: bool) -> Vec<Vec<u8>> {
+        if volatile {
+            Self::sample_in(&self.expires, count)
+        } else {
+            Self::sample_in(&self.values, count)
+        }
+    }
+
+    fn random_key(&self, volatile: bool) -> Option<Vec<u8>> {
+        if volatile {
+            Self::random_in(&self.expires)
+        } else {
+            Self::random_in(&self.values)
+        }
+    }
+
+    fn drain(&self) -> Box<dyn Send> {
+        if !self.snapshots().is_empty() {
+            // the running snapshots set every key aside, one after the other
+            let mut values = Vec::with_capacity(self.values.len());
+            self.values.retain(|key, value| {
+                self.preserve(key, Some(value));
+                values.push(std::mem::replace(
+                    value,
+                    Value::String(RespFrame::Null(RespNull)),
+                ));
+                false
+            });
+            self.expires.clear();
+            return Box::new(values);
+        }
+        // each lock is only held to swap its map with an empty one
+        let values = self
+            .values
+            .shards()
+            .iter()
+            .map(|lock| std::mem::take(&mut *lock.write()))
+            .collect::<Vec<_>>();
+        let expires = self
+            .expires
+            .shards()
+            .iter()
+            .map(|lock| std::mem::take(&mut *lock.write()))
+            .collect::<Vec<_>>();
+        Box::new((values, expires))
+    }
+
+    fn clear(&self) {
+        if !self.snapshots().is_empty() {
+            self.values.retain(|key, value| {
+                self.preserve(key, Some(value));
+                false
+            });
+        }
+        self.values.clear();
+        self.expires.clear();
+    }
+
+    fn expire_at(&self, key: &[u8]) -> Option<i64> {
+        self.expires.get(key).map(|when| *when)
+    }
+
+    fn set_expire(&self, key: Vec<u8>, when: i64) {
+        let _value = self.lock_for_expire(&key);
+        self.expires.insert(key, when);
+    }
+
+    fn remove_expire(&self, key: &[u8]) -> Option<i64> {
+        let _value = self.lock_for_expire(key);
+        self.expires.remove(key).map(|(_, when)| when)
+    }
+
+    fn remove_expire_if(&self, key: &[u8], condition: impl FnOnce(i64) -> bool) -> Option<i64> {
+        let _value = self.lock_for_expire(key);
+        self.expires
+            .remove_if(key, |_, when| condition(*when))
+            .map(|(_, when)| when)
+    }
+
+    fn volatile_len(&self) -> usize {
+        self.expires.len()
+    }
+
+    fn for_each_expire(&self, mut f: impl FnMut(&[u8], i64)) {
+        for entry in self.expires.iter() {
+            f(entry.key(), *entry.value());
+        }
+    }
+
+    fn overhead(&self) -> (usize, usize) {
+        (table(&self.values), table(&self.expires))
+    }
+
+    fn begin_snapshot(&self, epoch: u64) {
+        let mut snapshots = self.snapshots.write().unwrap_or_else(|e| e.into_inner());
+        snapshots.push((epoch, Arc::default()));
+    }
+
+    fn for_each_snapshot(&self, epoch: u64, mut f: impl FnMut(&[u8], &Value, Option<i64>)) {
+        let frozen = self
+            .snapshots()
+            .iter()
+            .find(|(e, _)| *e == epoch)
+            .map(|(_, frozen)| frozen.clone());
+        let Some(frozen) = frozen else {
+            return;
+        };
+        // the keys there are now, then those set aside: a key existing when the snapshot began
+        // and removed since is in the second list if it isn't in the first
+        let mut keys = self.keys();
+        keys.extend(frozen.iter().map(|entry| entry.key().clone()));
+        keys.sort_unstable();
+        keys.dedup();
+        for key in keys {
+            let value = self.values.get(&key);
+            match frozen.get(&key) {
+                Some(kept) => {
+                    if let Some((value, expire_at)) = kept.value() {
+                        f(&key, value, *expire_at);
+                    }
+                }
+                None => {
+                    if let Some(value) = value {
+                        f(&key, value.value(), self.expire_at(&key));
+                    }
+                }
+            }
+        }
+    }
+
+    fn end_snapshot(&self, epoch: u64) {
+        let mut snapshots = self.snapshots.write().unwrap_or_else(|e| e.into_inner());
+        snapshots.retain(|(e, _)| *e != epoch);
+    }
+}
+
+// Independent in-memory storages the keys are spread over by their hash, each keeping the
+// values and the expiry times of its keys: commands on keys of different shards never wait for
+// the same lock, and the scans over every key go through the shards one after the other.
+#[derive(Debug)]
+pub struct ShardedStorage {
+    shards: Box<[MemoryStorage]>,
+    hasher: RandomState,
+}
+
+impl ShardedStorage {
+    pub fn new(shards: usize) -> Self {
+        Self {
+            shards: (0..shards.max(1))
+                .map(|_| MemoryStorage::with_locks(SHARD_LOCKS))
+                .collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    // The shard `key` lives in.
+    fn shard(&self, key: &[u8]) -> &MemoryStorage {
+        let hash = self.hasher.hash_one(key) as usize;
+        &self.shards[hash % self.shards.len()]
+    }
+}
+
+impl Default for ShardedStorage {
+    fn default() -> Self {
+        Self::new(SHARDS)
+    }
+}
+
+impl Storage for ShardedStorage {
+    type Ref<'a, T> = <MemoryStorage as Storage>::Ref<'a, T>;
+    type RefMut<'a, T> = <MemoryStorage as Storage>::RefMut<'a, T>;
+
+    fn get(&self, key: &[u8]) -> Option<Self::Ref<'_, Value>> {
+        self.shard(key).get(key)
+    }
+
+    fn get_mut(&self, key: &[u8]) -> Option<Self::RefMut<'_, Value>> {
+        self.shard(key).get_mut(key)
+    }
+
+    fn get_or_insert_with(
+        &self,
+        key: Vec<u8>,
+        create: impl FnOnce() -> Value,
+    ) -> Self::RefMut<'_, Value> {
+        self.shard(&key).get_or_insert_with(key, create)
+    }
+
+    fn map_ref<'a, T, U>(
+        value: Self::Ref<'a, T>,
+        pick: impl FnOnce(&T) -> Option<&U>,
+    ) -> Option<Self::Ref<'a, U>> {
+        MemoryStorage::map_ref(value, pick)
+    }
+
+    fn map_mut<'a, T, U>(
+        value: Self::RefMut<'a, T>,
+        pick: impl FnOnce(&mut T) -> Option<&mut U>,
+    ) -> Option<Self::RefMut<'a, U>> {
+        MemoryStorage::map_mut(value, pick)
+    }
+
+    fn upsert<R>(
+        &self,
+        key: Vec<u8>,
+        f: impl FnOnce(Option<&mut Value>) -> (R, Option<Value>),
+    ) -> R {
+        self.shard(&key).upsert(key, f)
+    }
+
+    fn insert(&self, key: Vec<u8>, value: Value) -> Option<Value> {
+        self.shard(&key).insert(key, value)
+    }
+
+    fn remove(&self, key: &[u8]) -> Option<Value> {
+        self.shard(key).remove(key)
+    }
+
+    fn remove_if(&self, key: &[u8], condition: impl FnOnce(&Value) -> bool) -> Option<Value> {
+        self.shard(key).remove_if(key, condition)
+    }
+
+    fn contains_key(&self, key: &[u8]) -> bool {
+        self.shard(key).contains_key(key)
+    }
+
+    fn len(&self) -> usize {
+        self.shards.iter().map(MemoryStorage::len).sum()
+    }
+
+    fn for_each(&self, mut f: impl FnMut(&[u8], &Value)) {
+        for shard in self.shards.iter() {
+            shard.for_each(&mut f);
+        }
+    }
+
+    fn scan(&self, cursor: u64, mut f: impl FnMut(&[u8], &Value) -> bool) -> u64 {
+        let first = (cursor >> SHARD_CURSOR_BITS) as usize;
+        let mut inner = cursor & ((1 << SHARD_CURSOR_BITS) - 1);
+        for (i, shard) in self.shards.iter().enumerate().skip(first) {
+            let mut stopped = false;
+            let next = shard.scan(inner, |key, value| {
+                stopped = !f(key, value);
+                !stopped
+            });
+            if stopped {
+                return if next != 0 {
+                    ((i as u64) << SHARD_CURSOR_BITS) | next
+                } else if i + 1 < self.shards.len() {
+                    ((i + 1) as u64) << SHARD_CURSOR_BITS
+                } else {
+                    0
+                };
+            }
+            inner = 0;
+        }
+        0
+    }
+
+    fn sample_keys(&self, count: usize, volatile: bool) -> Vec<Vec<u8>> {
+        // the shards from a random one on, each adding what the sample still misses
+        let first = rand::thread_rng().gen_range(0..self.shards.len());
+        let mut keys = Vec::with_capacity(count);
+        for i in 0..self.shards.len() {
+            if keys.len() == count {
+                break;
+            }
+            let shard = &self.shards[(first + i) % self.shards.len()];
+            keys.extend(shard.sample_keys(count - keys.len(), volatile));
+        }
+        keys
+    }
+
+    fn random_key(&self, volatile: bool) -> Option<Vec<u8>> {
+        let len = |shard: &MemoryStorage| {
+            if volatile {
+                shard.volatile_len()
+            } else {
+                shard.len()
+            }
+        };
+        let mut rng = rand::thread_rng();
+        loop {
+            let lens = self.shards.iter().map(len).collect::<Vec<_>>();
+            let total = lens.iter().sum::<usize>();
+            if total == 0 {
+                return None;
+            }
+            // a shard is picked as often as it has keys, so every key has the same odds
+            let mut picked = rng.gen_range(0..total);
+            for (shard, len) in self.shards.iter().zip(lens) {
+                if picked >= len {
+                    picked -= len;
+                    continue;
+                }
+                // the shard may have lost its keys meanwhile, then pick again
+                if let Some(key) = shard.random_key(volatile) {
+                    return Some(key);
+                }
+                break;
+            }
+        }
+    }
+
+    fn drain(&self) -> Box<dyn Send> {
+        Box::new(
+            self.shards
+                .iter()
+                .map(MemoryStorage::drain)
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn clear(&self) {
+        self.shards.iter().for_each(MemoryStorage::clear);
+    }
+
+    fn expire_at(&self, key: &[u8]) -> Option<i64> {
+        self.shard(key).expire_at(key)
+    }
+
+    fn set_expire(&self, key: Vec<u8>, when: i64) {
+        self.shard(&key).set_expire(key, when)
+    }
+
+    fn remove_expire(&self, key: &[u8]) -> Option<i64> {
+        self.shard(key).remove_expire(key)
+    }
+
+    fn remove_expire_if(&self, key: &[u8], condition: impl FnOnce(i64) -> bool) -> Option<i64> {
+        self.shard(key).remove_expire_if(key, condition)
+    }
+
+    fn volatile_len(&self) -> usize {
+        self.shards.iter().map(MemoryStorage::volatile_len).sum()
+    }
+
+    fn for_each_expire(&self, mut f: impl FnMut(&[u8], i64)) {
+        for shard in self.shards.iter() {
+            shard.for_each_expire(&mut f);
+        }
+    }
+
+    fn overhead(&self) -> (usize, usize) {
+        let shards = size_of::<Self>() + self.shards.len() * size_of::<MemoryStorage>();
+        self.shards
+            .iter()
+            .map(MemoryStorage::overhead)
+            .fold((shards, 0), |(values, expires), (v, e)| {
+                (values + v, expires + e)
+            })
+    }
+
+    fn begin_snapshot(&self, epoch: u64) {
+        for shard in self.shards.iter() {
+            shard.begin_snapshot(epoch);
+        }
+    }
+
+    fn for_each_snapshot(&self, epoch: u64, mut f: impl FnMut(&[u8], &Value, Option<i64>)) {
+        for shard in self.shards.iter() {
+            shard.for_each_snapshot(epoch, &mut f);
+        }
+    }
+
+    fn end_snapshot(&self, epoch: u64) {
+        for shard in self.shards.iter() {
+            shard.end_snapshot(epoch);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    // The behavior every engine shares, so that either can be the Keyspace.
+    fn check_storage<S: Storage>(storage: S) {
+        let string = || Value::String(RespFrame::BulkString(BulkString::from("v")));
+        assert!(storage.insert(b"a".to_vec(), string()).is_none());
+        storage.set_expire(b"a".to_vec(), 10);
+        assert_eq!((storage.len(), storage.volatile_len()), (1, 1));
+        assert_eq!(storage.expire_at(b"a"), Some(10));
+
+        // the closure creates the key, or sees the value already there
+        let created = storage.upsert(b"b".to_vec(), |value| (value.is_none(), Some(string())));
+        assert!(created);
+        let created = storage.upsert(b"b".to_vec(), |value| (value.is_none(), None));
+        assert!(!created);
+        storage.upsert(b"c".to_vec(), |_| ((), None));
+        assert!(!storage.contains_key(b"c"));
+
+        let mut keys = storage.keys();
+        keys.sort();
+        assert_eq!(keys, [b"a", b"b"]);
+        assert_eq!(storage.remove_expire_if(b"a", |when| when > 10), None);
+        assert_eq!(storage.remove_expire_if(b"a", |when| when <= 10), Some(10));
+
+        // a snapshot sees the keys as they were when it began
+        storage.begin_snapshot(1);
+        storage.remove(b"a");
+        storage.insert(b"d".to_vec(), string());
+        let mut seen = vec![];
+        storage.for_each_snapshot(1, |key, _, _| seen.push(key.to_vec()));
+        seen.sort();
+        assert_eq!(seen, [b"a", b"b"]);
+        storage.end_snapshot(1);
+
+        // a scan goes over every key once, a page stopping after the key the closure refuses
+        for i in 0..100 {
+            storage.insert(format!("key{i}").into_bytes(), string());
+        }
+        let (mut cursor, mut seen) = (0, vec![]);
+        loop {
+            let mut page = 0;
+            cursor = storage.scan(cursor, |key, _| {
+                seen.push(key.to_vec());
+                page += 1;
+                page < 7
+            });
+            assert!(page <= 7);
+            if cursor == 0 {
+                break;
+            }
+        }
+        seen.sort();
+        let visited = seen.len();
+        seen.dedup();
+        assert_eq!((visited, seen.len()), (102, 102));
+
+        // keys picked at random, among the volatile ones only if asked
+        storage.set_expire(b"key7".to_vec(), 5);
+        assert_eq!(storage.random_key(true), Some(b"key7".to_vec()));
+        assert!(storage.random_key(false).is_some());
+        assert_eq!(storage.sample_keys(10, true), [b"key7"]);
+        for (count, sampled) in [(10, 10), (1000, 102)] {
+            let mut sample = storage.sample_keys(count, false);
+            sample.sort();
+            sample.dedup();
+            assert_eq!(sample.len(), sampled);
+        }
+
+        storage.drain();
+        assert!(storage.is_empty());
+        assert_eq!(storage.volatile_len(), 0);
+        assert_eq!(storage.random_key(false), None);
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        check_storage(MemoryStorage::default());
+    }
+
+    #[test]
+    fn test_storage_engines() {
+        check_storage(ShardedStorage::new(4));
+        check_storage(Keyspace::default());
+    }
+
+    #[test]
+    fn test_sharded_storage() {
+        let storage = ShardedStorage::new(4);
+        let string = || Value::String(RespFrame::BulkString(BulkString::from("v")));
+        for i in 0..100 {
+            let key = format!("key{i}").into_bytes();
+            storage.insert(key.clone(), string());
+            if i % 2 == 0 {
+                storage.set_expire(key, i);
+            }
+        }
+        // the keys spread over every shard, each keeping the expiry times of its own keys
+        for shard in storage.shards.iter() {
+            assert!(!shard.is_empty());
+            shard.for_each_expire(|key, _| assert!(shard.contains_key(key)));
+        }
+        assert_eq!((storage.len(), storage.volatile_len()), (100, 50));
+        assert_eq!(storage.expire_at(b"key42"), Some(42));
+        assert_eq!(storage.remove_expire(b"key42"), Some(42));
+        assert!(storage.get_mut(b"key42").is_some());
+
+        let mut keys = 0;
+        storage.for_each(|_, _| keys += 1);
+        assert_eq!(keys, 100);
+        assert_eq!(storage.sample_keys(10, true).len(), 10);
+        assert!(storage.remove(b"key1").is_some());
+        storage.drain();
+        assert_eq!((storage.len(), storage.volatile_len()), (0, 0));
+    }
+}
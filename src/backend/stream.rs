@@ -0,0 +1,721 @@
+use super::memory::{sampled, MemoryUsage};
+use super::WrongType;
+use crate::RespFrame;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::mem::size_of;
+use std::ops::{Bound, RangeBounds};
+use std::str::FromStr;
+use thiserror::Error;
+
+// The ID of a stream entry: the milliseconds time it was added at and a sequence number telling
+// apart the entries of the same millisecond. IDs only grow within a stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+// The fields and values of an entry, in the order they were given.
+pub type StreamFields = Vec<(String, RespFrame)>;
+
+// What XADD was told the ID of the new entry is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XAddId {
+    // "*": the current time, or the last ID plus one when the clock is behind
+    Auto,
+    // "<ms>-*": the next sequence number within that millisecond
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+// The errors of the stream commands, with their error codes.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum StreamError {
+    #[error("ERR The ID specified in XADD is equal or smaller than the target stream top item")]
+    IdTooSmall,
+    #[error("ERR The ID specified in XADD must be greater than 0-0")]
+    ZeroId,
+    #[error("ERR The stream has exhausted the last possible ID, unable to add more items")]
+    Exhausted,
+    #[error("ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.")]
+    NoKey,
+    #[error("BUSYGROUP Consumer Group name already exists")]
+    GroupExists,
+    #[error("NOGROUP No such key '{key}' or consumer group '{group}'")]
+    NoGroup { key: String, group: String },
+    #[error("ERR no such key")]
+    NoSuchKey,
+    #[error(transparent)]
+    WrongType(#[from] WrongType),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, StreamFields>,
+    // the greatest ID ever added, which new entries must exceed even once it was deleted
+    last_id: StreamId,
+    // number of entries added over the lifetime of the stream
+    entries_added: u64,
+    // the greatest ID deleted by XDEL or trimmed away
+    max_deleted_id: StreamId,
+    groups: BTreeMap<String, ConsumerGroup>,
+}
+
+// How XTRIM or XADD prune a stream: down to a length, or up to a minimum ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimStrategy {
+    MaxLen(usize),
+    MinId(StreamId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrimOptions {
+    pub strategy: TrimStrategy,
+    // "~": only remove whole nodes, possibly leaving more entries than asked for
+    pub approximate: bool,
+    // most entries removed at once when approximate, 0 for no limit and None for 100 nodes
+    pub limit: Option<usize>,
+}
+
+// A consumer group reads a stream on behalf of its consumers, each entry going to one of them.
+// It remembers the last entry it delivered and, until they are acknowledged, the entries
+// delivered to each consumer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConsumerGroup {
+    pub last_delivered: StreamId,
+    // the pending entries list (PEL) of the group
+    pub pending: BTreeMap<StreamId, PendingEntry>,
+    pub consumers: BTreeMap<String, Consumer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingEntry {
+    pub consumer: String,
+    // unix time in milliseconds of the last delivery
+    pub delivered_at: u64,
+    pub delivery_count: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Consumer {
+    // unix time in milliseconds of the last read
+    pub seen_at: u64,
+    // the IDs of its entries in the PEL of the group
+    pub pending: BTreeSet<StreamId>,
+}
+
+// What XINFO STREAM tells about a stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamInfo {
+    pub length: usize,
+    pub last_id: StreamId,
+    pub max_deleted_id: StreamId,
+    pub entries_added: u64,
+    pub groups: usize,
+    pub first_entry: Option<(StreamId, StreamFields)>,
+    pub last_entry: Option<(StreamId, StreamFields)>,
+}
+
+// What XINFO GROUPS tells about a consumer group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupInfo {
+    pub name: String,
+    pub consumers: usize,
+    pub pending: usize,
+    pub last_delivered: StreamId,
+    // number of entries in the stream the group has yet to deliver
+    pub lag: usize,
+}
+
+// What XINFO CONSUMERS tells about a consumer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsumerInfo {
+    pub name: String,
+    pub pending: usize,
+    // milliseconds since its last read
+    pub idle: u64,
+}
+
+// An entry read by a consumer, without fields when it was deleted since it was delivered.
+pub type GroupEntry = (StreamId, Option<StreamFields>);
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        Self { ms, seq }
+    }
+
+    // The smallest ID greater than this one.
+    pub fn next(self) -> Option<Self> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(Self::new(self.ms, seq)),
+            None => Some(Self::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+
+    // The greatest ID smaller than this one.
+    pub fn prev(self) -> Option<Self> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(Self::new(self.ms, seq)),
+            None => Some(Self::new(self.ms.checked_sub(1)?, u64::MAX)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+// "<ms>-<seq>", or "<ms>" alone with a sequence number of 0.
+impl FromStr for StreamId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('-') {
+            Some((ms, seq)) => Ok(Self::new(ms.parse()?, seq.parse()?)),
+            None => Ok(Self::new(s.parse()?, 0)),
+        }
+    }
+}
+
+impl MemoryUsage for Stream {
+    fn memory_usage(&self, samples: usize) -> usize {
+        let entry = size_of::<(StreamId, StreamFields)>();
+        size_of::<Self>()
+            + sampled(
+                self.entries.len(),
+                self.entries.values(),
+                samples,
+                |fields| {
+                    entry
+                        + fields.capacity() * size_of::<(String, RespFrame)>()
+                        + fields
+                            .iter()
+                            .map(|(field, value)| {
+                                field.capacity() + value.memory_usage(samples)
+                                    - size_of::<RespFrame>()
+                            })
+                            .sum::<usize>()
+                },
+            )
+            + self
+                .groups
+                .iter()
+                .map(|(name, group)| {
+                    let pending = size_of::<(StreamId, PendingEntry)>() + size_of::<StreamId>();
+                    name.capacity()
+                        + size_of::<(String, ConsumerGroup)>()
+                        + group.pending.len() * pending
+                        + group
+                            .consumers
+                            .keys()
+                            .map(|name| name.capacity() + size_of::<(String, Consumer)>())
+                            .sum::<usize>()
+                })
+                .sum::<usize>()
+    }
+}
+
+impl Stream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Appends an entry, returning its ID. The ID has to be greater than any added before.
+    pub fn add(
+        &mut self,
+        id: XAddId,
+        fields: StreamFields,
+        now_ms: u64,
+    ) -> Result<StreamId, StreamError> {
+        let id = match id {
+            XAddId::Auto if now_ms > self.last_id.ms => StreamId::new(now_ms, 0),
+            XAddId::Auto => self.last_id.next().ok_or(StreamError::Exhausted)?,
+            XAddId::AutoSeq(ms) if ms == self.last_id.ms => {
+                let seq = self.last_id.seq.checked_add(1);
+                StreamId::new(ms, seq.ok_or(StreamError::IdTooSmall)?)
+            }
+            XAddId::AutoSeq(ms) => StreamId::new(ms, (ms == 0) as u64),
+            XAddId::Explicit(id) => id,
+        };
+        if id == StreamId::MIN {
+            return Err(StreamError::ZeroId);
+        }
+        if id <= self.last_id {
+            return Err(StreamError::IdTooSmall);
+        }
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        self.entries_added += 1;
+        Ok(id)
+    }
+
+    // Up to `count` entries with IDs in `range`, in ascending order or descending when `rev`.
+    pub fn range(
+        &self,
+        range: impl RangeBounds<StreamId>,
+        count: Option<usize>,
+        rev: bool,
+    ) -> Vec<(StreamId, StreamFields)> {
+        let (start, end) = (range.start_bound(), range.end_bound());
+        // BTreeMap::range panics on an empty or inverted range
+        let empty = match (start, end) {
+            (Bound::Included(s), Bound::Included(e)) => s > e,
+            (Bound::Included(s), Bound::Excluded(e))
+            | (Bound::Excluded(s), Bound::Included(e))
+            | (Bound::Excluded(s), Bound::Excluded(e)) => s >= e,
+            _ => false,
+        };
+        if empty {
+            return vec![];
+        }
+        let entries = self
+            .entries
+            .range((start.cloned(), end.cloned()))
+            .map(|(id, fields)| (*id, fields.clone()));
+        let count = count.unwrap_or(usize::MAX);
+        if rev {
+            entries.rev().take(count).collect()
+        } else {
+            entries.take(count).collect()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+
+    pub fn max_deleted_id(&self) -> StreamId {
+        self.max_deleted_id
+    }
+
+    // Deletes the entry, false if there is no such entry. Its ID is never reused.
+    pub fn delete(&mut self, id: StreamId) -> bool {
+        if self.entries.remove(&id).is_none() {
+            return false;
+        }
+        self.max_deleted_id = self.max_deleted_id.max(id);
+        true
+    }
+
+    // Removes the oldest entries as `options` ask, returning how many. The entries are stored
+    // as if in nodes of `node_size` entries, the approximate form only removing whole nodes
+    // like a radix tree of listpacks would.
+    pub fn trim(&mut self, options: TrimOptions, node_size: usize) -> usize {
+        let mut count = match options.strategy {
+            TrimStrategy::MaxLen(len) => self.entries.len().saturating_sub(len),
+            TrimStrategy::MinId(id) => self.entries.range(..id).count(),
+        };
+        if options.approximate {
+            let node_size = node_size.max(1);
+            let limit = match options.limit {
+                Some(0) => usize::MAX,
+                Some(limit) => limit,
+                None => node_size * 100,
+            };
+            count = count.min(limit) / node_size * node_size;
+        }
+        for _ in 0..count {
+            if let Some((id, _)) = self.entries.pop_first() {
+                self.max_deleted_id = self.max_deleted_id.max(id);
+            }
+        }
+        count
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&StreamId, &StreamFields)> {
+        self.entries.iter()
+    }
+
+    // Adds a group that delivers the entries after `last_delivered`, false if it exists.
+    pub fn create_group(&mut self, name: &str, last_delivered: StreamId) -> bool {
+        if self.groups.contains_key(name) {
+            return false;
+        }
+        let group = ConsumerGroup {
+            last_delivered,
+            ..Default::default()
+        };
+        self.groups.insert(name.to_string(), group);
+        true
+    }
+
+    pub fn destroy_group(&mut self, name: &str) -> bool {
+        self.groups.remove(name).is_some()
+    }
+
+    pub fn group(&self, name: &str) -> Option<&ConsumerGroup> {
+        self.groups.get(name)
+    }
+
+    pub fn group_mut(&mut self, name: &str) -> Option<&mut ConsumerGroup> {
+        self.groups.get_mut(name)
+    }
+
+    pub fn groups(&self) -> impl Iterator<Item = (&String, &ConsumerGroup)> {
+        self.groups.iter()
+    }
+
+    pub fn info(&self) -> StreamInfo {
+        let entry = |(id, fields): (&StreamId, &StreamFields)| (*id, fields.clone());
+        StreamInfo {
+            length: self.entries.len(),
+            last_id: self.last_id,
+            max_deleted_id: self.max_deleted_id,
+            entries_added: self.entries_added,
+            groups: self.groups.len(),
+            first_entry: self.entries.first_key_value().map(entry),
+            last_entry: self.entries.last_key_value().map(entry),
+        }
+    }
+
+    pub fn group_infos(&self) -> Vec<GroupInfo> {
+        self.groups
+            .iter()
+            .map(|(name, group)| GroupInfo {
+                name: name.clone(),
+                consumers: group.consumers.len(),
+                pending: group.pending.len(),
+                last_delivered: group.last_delivered,
+                lag: self
+                    .entries
+                    .range((Bound::Excluded(group.last_delivered), Bound::Unbounded))
+                    .count(),
+            })
+            .collect()
+    }
+
+    // Reads up to `count` entries as `consumer` of `group`, None if there is no such group.
+    // Without `after` these are the entries never delivered to the group, which then become
+    // pending for the consumer unless `noack`. Otherwise they are the entries pending for the
+    // consumer with IDs greater than `after`.
+    pub fn read_group(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        after: Option<StreamId>,
+        count: Option<usize>,
+        noack: bool,
+        now_ms: u64,
+    ) -> Option<Vec<GroupEntry>> {
+        let group = self.groups.get_mut(group)?;
+        group.create_consumer(consumer, now_ms);
+        let count = count.unwrap_or(usize::MAX);
+        let entries = match after {
+            None => {
+                let range = (Bound::Excluded(group.last_delivered), Bound::Unbounded);
+                let entries = self
+                    .entries
+                    .range(range)
+                    .take(count)
+                    .map(|(id, fields)| (*id, Some(fields.clone())))
+                    .collect::<Vec<_>>();
+                if let Some((id, _)) = entries.last() {
+                    group.last_delivered = *id;
+                }
+                if !noack {
+                    for (id, _) in &entries {
+                        group.deliver(*id, consumer, now_ms);
+                    }
+                }
+                entries
+            }
+            Some(after) => group.consumers[consumer]
+                .pending
+                .range((Bound::Excluded(after), Bound::Unbounded))
+                .take(count)
+                .map(|id| (*id, self.entries.get(id).cloned()))
+                .collect(),
+        };
+        if let Some(consumer) = group.consumers.get_mut(consumer) {
+            consumer.seen_at = now_ms;
+        }
+        Some(entries)
+    }
+
+    // A stream read back from a DUMP payload.
+    pub(super) fn from_parts(
+        entries: BTreeMap<StreamId, StreamFields>,
+        last_id: StreamId,
+        entries_added: u64,
+        max_deleted_id: StreamId,
+        groups: BTreeMap<String, ConsumerGroup>,
+    ) -> Self {
+        let last_id = entries
+            .keys()
+            .next_back()
+            .map_or(last_id, |id| last_id.max(*id));
+        Self {
+            entries,
+            last_id,
+            entries_added,
+            max_deleted_id,
+            groups,
+        }
+    }
+}
+
+impl ConsumerGroup {
+    // Adds the consumer, false if it exists.
+    pub fn create_consumer(&mut self, name: &str, now_ms: u64) -> bool {
+        if self.consumers.contains_key(name) {
+            return false;
+        }
+        let consumer = Consumer {
+            seen_at: now_ms,
+            ..Default::default()
+        };
+        self.consumers.insert(name.to_string(), consumer);
+        true
+    }
+
+    // Removes the consumer along with its pending entries, returning how many it had.
+    pub fn delete_consumer(&mut self, name: &str) -> Option<usize> {
+        let consumer = self.consumers.remove(name)?;
+        for id in &consumer.pending {
+            self.pending.remove(id);
+        }
+        Some(consumer.pending.len())
+    }
+
+    pub fn consumer_infos(&self, now_ms: u64) -> Vec<ConsumerInfo> {
+        self.consumers
+            .iter()
+            .map(|(name, consumer)| ConsumerInfo {
+                name: name.clone(),
+                pending: consumer.pending.len(),
+                idle: now_ms.saturating_sub(consumer.seen_at),
+            })
+            .collect()
+    }
+
+    // Acknowledges the entry, false if it wasn't pending.
+    pub fn ack(&mut self, id: StreamId) -> bool {
+        let Some(entry) = self.pending.remove(&id) else {
+            return false;
+        };
+        if let Some(consumer) = self.consumers.get_mut(&entry.consumer) {
+            consumer.pending.remove(&id);
+        }
+        true
+    }
+
+    // Makes the entry pending for `consumer`, taking it from the consumer it was pending for.
+    fn deliver(&mut self, id: StreamId, consumer: &str, now_ms: u64) {
+        let delivery_count = match self.pending.get(&id) {
+            Some(entry) => {
+                if let Some(owner) = self.consumers.get_mut(&entry.consumer) {
+                    owner.pending.remove(&id);
+                }
+                entry.delivery_count + 1
+            }
+            None => 1,
+        };
+        let entry = PendingEntry {
+            consumer: consumer.to_string(),
+            delivered_at: now_ms,
+            delivery_count,
+        };
+        self.pending.insert(id, entry);
+        self.consumers
+            .entry(consumer.to_string())
+            .or_default()
+            .pending
+            .insert(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn fields(value: &str) -> StreamFields {
+        vec![("field".to_string(), BulkString::from(value).into())]
+    }
+
+    #[test]
+    fn test_stream_add() {
+        let mut stream = Stream::new();
+        assert_eq!(
+            stream.add(XAddId::Auto, fields("a"), 1000),
+            Ok(StreamId::new(1000, 0))
+        );
+        // the clock went back
+        assert_eq!(
+            stream.add(XAddId::Auto, fields("b"), 900),
+            Ok(StreamId::new(1000, 1))
+        );
+        assert_eq!(
+            stream.add(XAddId::AutoSeq(1000), fields("c"), 0),
+            Ok(StreamId::new(1000, 2))
+        );
+        assert_eq!(
+            stream.add(XAddId::AutoSeq(999), fields("d"), 0),
+            Err(StreamError::IdTooSmall)
+        );
+        assert_eq!(
+            stream.add(XAddId::Explicit(StreamId::new(1000, 2)), fields("d"), 0),
+            Err(StreamError::IdTooSmall)
+        );
+        assert_eq!(
+            stream.add(XAddId::Explicit(StreamId::new(2000, 5)), fields("d"), 0),
+            Ok(StreamId::new(2000, 5))
+        );
+        assert_eq!(stream.len(), 4);
+        assert_eq!(stream.last_id(), StreamId::new(2000, 5));
+
+        let mut stream = Stream::new();
+        assert_eq!(
+            stream.add(XAddId::Explicit(StreamId::MIN), fields("a"), 0),
+            Err(StreamError::ZeroId)
+        );
+        assert_eq!(
+            stream.add(XAddId::AutoSeq(0), fields("a"), 0),
+            Ok(StreamId::new(0, 1))
+        );
+    }
+
+    #[test]
+    fn test_stream_range() {
+        let mut stream = Stream::new();
+        for ms in 1..=5 {
+            stream
+                .add(XAddId::Explicit(StreamId::new(ms, 0)), fields("v"), 0)
+                .expect("IDs grow");
+        }
+        let ids = |entries: Vec<(StreamId, StreamFields)>| {
+            entries.into_iter().map(|(id, _)| id.ms).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(stream.range(.., None, false)), [1, 2, 3, 4, 5]);
+        assert_eq!(ids(stream.range(.., Some(2), true)), [5, 4]);
+        let range = (
+            Bound::Excluded(StreamId::new(2, 0)),
+            Bound::Included(StreamId::new(4, 0)),
+        );
+        assert_eq!(ids(stream.range(range, None, false)), [3, 4]);
+        let range = StreamId::new(4, 0)..=StreamId::new(2, 0);
+        assert!(stream.range(range, None, false).is_empty());
+    }
+
+    #[test]
+    fn test_stream_id() {
+        assert_eq!("5-3".parse(), Ok(StreamId::new(5, 3)));
+        assert_eq!("5".parse(), Ok(StreamId::new(5, 0)));
+        assert!("5-".parse::<StreamId>().is_err());
+        assert_eq!(StreamId::new(5, 3).to_string(), "5-3");
+        assert_eq!(StreamId::new(5, u64::MAX).next(), Some(StreamId::new(6, 0)));
+        assert_eq!(StreamId::MAX.next(), None);
+        assert_eq!(StreamId::new(5, 0).prev(), Some(StreamId::new(4, u64::MAX)));
+    }
+
+    #[test]
+    fn test_stream_read_group() {
+        let mut stream = Stream::new();
+        for ms in 1..=3 {
+            stream
+                .add(XAddId::Explicit(StreamId::new(ms, 0)), fields("v"), 0)
+                .expect("IDs grow");
+        }
+        assert!(stream.create_group("g", StreamId::MIN));
+        assert!(!stream.create_group("g", StreamId::MAX));
+        assert_eq!(
+            stream.read_group("missing", "alice", None, None, false, 0),
+            None
+        );
+
+        let ids = |entries: Option<Vec<GroupEntry>>| {
+            let entries = entries.expect("the group exists");
+            entries.into_iter().map(|(id, _)| id.ms).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(stream.read_group("g", "alice", None, Some(2), false, 10)),
+            [1, 2]
+        );
+        assert_eq!(
+            ids(stream.read_group("g", "bob", None, None, false, 20)),
+            [3]
+        );
+        assert!(ids(stream.read_group("g", "bob", None, None, false, 30)).is_empty());
+        // the history of a consumer is its pending entries
+        let history = Some(StreamId::MIN);
+        assert_eq!(
+            ids(stream.read_group("g", "alice", history, None, false, 40)),
+            [1, 2]
+        );
+
+        let group = stream.group_mut("g").expect("g exists");
+        assert_eq!(group.last_delivered, StreamId::new(3, 0));
+        assert_eq!(group.consumers["bob"].seen_at, 30);
+        assert_eq!(group.pending[&StreamId::new(3, 0)].consumer, "bob");
+        assert!(group.ack(StreamId::new(1, 0)));
+        assert!(!group.ack(StreamId::new(1, 0)));
+        assert_eq!(group.pending.len(), 2);
+        assert_eq!(group.delete_consumer("alice"), Some(1));
+        assert_eq!(group.pending.len(), 1);
+        assert!(group.create_consumer("alice", 50));
+        assert!(!group.create_consumer("bob", 50));
+
+        assert!(stream.destroy_group("g"));
+        assert!(stream.group("g").is_none());
+    }
+
+    #[test]
+    fn test_stream_trim() {
+        let mut stream = Stream::new();
+        for ms in 1..=10 {
+            stream
+                .add(XAddId::Explicit(StreamId::new(ms, 0)), fields("v"), 0)
+                .expect("IDs grow");
+        }
+        let trim = |strategy, approximate, limit| TrimOptions {
+            strategy,
+            approximate,
+            limit,
+        };
+        // only whole nodes of 3 entries go
+        assert_eq!(stream.trim(trim(TrimStrategy::MaxLen(6), true, None), 3), 3);
+        assert_eq!(stream.len(), 7);
+        assert_eq!(stream.trim(trim(TrimStrategy::MaxLen(6), true, None), 3), 0);
+        assert_eq!(
+            stream.trim(trim(TrimStrategy::MaxLen(6), false, None), 3),
+            1
+        );
+        assert_eq!(stream.max_deleted_id(), StreamId::new(4, 0));
+
+        let min_id = TrimStrategy::MinId(StreamId::new(9, 0));
+        assert_eq!(stream.trim(trim(min_id, true, Some(2)), 1), 2);
+        assert_eq!(stream.trim(trim(min_id, false, None), 1), 2);
+        assert_eq!(stream.len(), 2);
+
+        assert!(stream.delete(StreamId::new(10, 0)));
+        assert!(!stream.delete(StreamId::new(10, 0)));
+        assert_eq!(stream.len(), 1);
+        assert_eq!(stream.max_deleted_id(), StreamId::new(10, 0));
+        // a deleted ID isn't reused
+        assert_eq!(
+            stream.add(XAddId::AutoSeq(10), fields("v"), 0),
+            Ok(StreamId::new(10, 1))
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use super::{ConfigValues, HashValue, ListPack, ListValue, SetValue, Stream, ZSet};
+use crate::RespFrame;
+use thiserror::Error;
+
+// The error of a command run on a key holding another type of value than the one it works on.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+pub struct WrongType;
+
+// The value of a key. A key holds a single value of a single type: writing a value of another
+// type replaces it (SET, RESTORE, the STORE variants) or fails with WrongType.
+#[derive(Debug, Clone)]
+pub enum Value {
+    String(RespFrame),
+    Hash(HashValue),
+    List(ListValue),
+    Set(SetValue),
+    ZSet(ZSet),
+    Stream(Stream),
+}
+
+impl Value {
+    // Type name as reported by TYPE and the SCAN TYPE filter.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Hash(_) => "hash",
+            Value::List(_) => "list",
+            Value::Set(_) => "set",
+            Value::ZSet(_) => "zset",
+            Value::Stream(_) => "stream",
+        }
+    }
+
+    // Number of elements of a collection, 1 for a string.
+    pub fn len(&self) -> usize {
+        match self {
+            Value::String(_) => 1,
+            Value::Hash(v) => v.len(),
+            Value::List(v) => v.len(),
+            Value::Set(v) => v.len(),
+            Value::ZSet(v) => v.len(),
+            Value::Stream(v) => v.len(),
+        }
+    }
+
+    // Whether the value is a collection left without elements, which takes its key away with it.
+    // A stream stays even once its entries are deleted, like its consumer groups do.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Value::String(_) | Value::Stream(_) => false,
+            Value::Hash(v) => v.is_empty(),
+            Value::List(v) => v.is_empty(),
+            Value::Set(v) => v.is_empty(),
+            Value::ZSet(v) => v.is_empty(),
+        }
+    }
+
+    // Packs a hash or list that was built whole, rather than written element by element, if it is
+    // small enough. See EncodingLimits.
+    pub fn compact(self, limits: &EncodingLimits) -> Self {
+        match self {
+            Value::Hash(v) => Value::Hash(v.compact(limits)),
+            Value::List(v) => Value::List(v.compact(limits)),
+            Value::Set(v) => Value::Set(v.compact(limits)),
+            value => value,
+        }
+    }
+
+    pub fn as_string(&self) -> Option<&RespFrame> {
+        match self {
+            Value::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_string_mut(&mut self) -> Option<&mut RespFrame> {
+        match self {
+            Value::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_hash(&self) -> Option<&HashValue> {
+        match self {
+            Value::Hash(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_hash_mut(&mut self) -> Option<&mut HashValue> {
+        match self {
+            Value::Hash(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&ListValue> {
+        match self {
+            Value::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_list_mut(&mut self) -> Option<&mut ListValue> {
+        match self {
+            Value::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_set(&self) -> Option<&SetValue> {
+        match self {
+            Value::Set(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_set_mut(&mut self) -> Option<&mut SetValue> {
+        match self {
+            Value::Set(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_zset(&self) -> Option<&ZSet> {
+        match self {
+            Value::ZSet(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_zset_mut(&mut self) -> Option<&mut ZSet> {
+        match self {
+            Value::ZSet(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_stream(&self) -> Option<&Stream> {
+        match self {
+            Value::Stream(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_stream_mut(&mut self) -> Option<&mut Stream> {
+        match self {
+            Value::Stream(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+// How large hashes, lists and sets grow before they are converted from their compact encodings
+// to their full structures, see the hash-max-listpack-*, list-max-listpack-size and
+// set-max-intset-entries parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodingLimits {
+    pub hash_entries: usize,
+    pub hash_value: usize,
+    pub list_size: i64,
+    pub set_entries: usize,
+}
+
+impl EncodingLimits {
+    pub fn new(config: &ConfigValues) -> Self {
+        Self {
+            hash_entries: config.hash_max_listpack_entries,
+            hash_value: config.hash_max_listpack_value,
+            list_size: config.list_max_listpack_size,
+            set_entries: config.set_max_intset_entries,
+        }
+    }
+
+    pub(super) fn list_fits(&self, pack: &ListPack) -> bool {
+        match self.list_size {
+            entries @ 0.. => pack.len() <= entries as usize,
+            n => pack.bytes() <= 4096 << (n.unsigned_abs().min(5) - 1),
+        }
+    }
+
+    pub(super) fn hash_fits(&self, field: &str, value: &RespFrame) -> bool {
+        matches!(value, RespFrame::BulkString(v) if v.len() <= self.hash_value)
+            && field.len() <= self.hash_value
+    }
+}
+
+impl Default for EncodingLimits {
+    fn default() -> Self {
+        Self::new(&ConfigValues::default())
+    }
+}
@@ -0,0 +1,596 @@
+use super::memory::{sampled, spare_slots, MemoryUsage};
+use std::cmp::Ordering;
+use std::collections::{btree_set, BTreeSet, HashMap};
+use std::ops::Bound;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZAddFlags {
+    // only add new members
+    pub nx: bool,
+    // only update existing members
+    pub xx: bool,
+    // only update existing members when the new score is greater
+    pub gt: bool,
+    // only update existing members when the new score is less
+    pub lt: bool,
+    // treat the score as an increment, like ZINCRBY
+    pub incr: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZAddOutcome {
+    Added(f64),
+    Updated(f64),
+    Unchanged(f64),
+    // the update was prevented by NX/XX/GT/LT
+    Skipped,
+    // the increment resulted in NaN, e.g. +inf plus -inf
+    NotANumber,
+}
+
+// A lexicographical range endpoint: "-", "+", "[member" or "(member".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexBound {
+    Min,
+    Max,
+    Inclusive(String),
+    Exclusive(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZRangeSpec {
+    // start and stop ranks, negative ranks count from the end
+    Rank(i64, i64),
+    // min and max scores
+    Score(Bound<f64>, Bound<f64>),
+    // min and max members, only meaningful when all members share the same score
+    Lex(LexBound, LexBound),
+}
+
+// how scores of the same member in different sorted sets are combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    pub fn apply(&self, a: f64, b: f64) -> f64 {
+        match self {
+            // +inf plus -inf is NaN, which is treated as 0 like in redis
+            Aggregate::Sum => zero_if_nan(a + b),
+            Aggregate::Min => a.min(b),
+            Aggregate::Max => a.max(b),
+        }
+    }
+}
+
+pub(crate) fn zero_if_nan(score: f64) -> f64 {
+    if score.is_nan() {
+        0.0
+    } else {
+        score
+    }
+}
+
+// an endpoint of a range of the ordered set
+type Endpoint = Bound<(Score, String)>;
+
+// f64 wrapper with a total order so scores can be used as BTreeSet keys
+#[derive(Debug, Clone, Copy)]
+pub struct Score(pub f64);
+
+// Members ordered by (score, member), with a member -> score index for O(1) lookups.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ZSet {
+    scores: HashMap<String, f64>,
+    ordered: BTreeSet<(Score, String)>,
+}
+
+impl MemoryUsage for ZSet {
+    // every member is held twice, by the score index and by the ordered set
+    fn memory_usage(&self, samples: usize) -> usize {
+        let entry = size_of::<(String, f64)>();
+        size_of::<Self>()
+            + spare_slots::<(String, f64)>(self.scores.capacity(), self.scores.len())
+            + sampled(self.scores.len(), self.scores.keys(), samples, |member| {
+                2 * (entry + member.capacity())
+            })
+    }
+}
+
+impl ZSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Sets the score of `member`. Returns true if the member was newly added.
+    pub fn insert(&mut self, member: String, score: f64) -> bool {
+        match self.scores.insert(member.clone(), score) {
+            Some(old) => {
+                self.ordered.remove(&(Score(old), member.clone()));
+                self.ordered.insert((Score(score), member));
+                false
+            }
+            None => {
+                self.ordered.insert((Score(score), member));
+                true
+            }
+        }
+    }
+
+    // Adds or updates `member` following the ZADD flag rules.
+    pub fn add(&mut self, member: String, score: f64, flags: ZAddFlags) -> ZAddOutcome {
+        let old = match self.score(&member) {
+            Some(old) => old,
+            None if flags.xx => return ZAddOutcome::Skipped,
+            None => {
+                self.insert(member, score);
+                return ZAddOutcome::Added(score);
+            }
+        };
+
+        if flags.nx {
+            return ZAddOutcome::Skipped;
+        }
+        let score = if flags.incr { old + score } else { score };
+        if score.is_nan() {
+            return ZAddOutcome::NotANumber;
+        }
+        if (flags.gt && score <= old) || (flags.lt && score >= old) {
+            return ZAddOutcome::Skipped;
+        }
+        if score == old {
+            return ZAddOutcome::Unchanged(score);
+        }
+        self.insert(member, score);
+        ZAddOutcome::Updated(score)
+    }
+
+    // Removes `member`. Returns true if it was present.
+    pub fn remove(&mut self, member: &str) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => {
+                self.ordered.remove(&(Score(score), member.to_string()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    // Iterates members in ascending (score, member) order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&str, f64)> {
+        self.ordered
+            .iter()
+            .map(|(score, member)| (member.as_str(), score.0))
+    }
+
+    // Counts the members within `range`.
+    pub fn count(&self, range: &ZRangeSpec) -> usize {
+        match range {
+            ZRangeSpec::Rank(start, stop) => normalize_rank(*start, *stop, self.len())
+                .map_or(0, |(start, stop)| stop - start + 1),
+            range => self.entries(range).count(),
+        }
+    }
+
+    // The members within a score or lex range, found without walking the members before it.
+    fn entries(&self, range: &ZRangeSpec) -> btree_set::Range<'_, (Score, String)> {
+        let endpoints = match range {
+            ZRangeSpec::Rank(..) => unreachable!("ranks are not ordered set endpoints"),
+            ZRangeSpec::Score(min, max) => (score_start(*min), Some(score_end(*max))),
+            ZRangeSpec::Lex(min, max) => {
+                // the members are only ordered by name among the ones sharing a score
+                let Some((score, _)) = self.ordered.first() else {
+                    return btree_set::Range::default();
+                };
+                (lex_start(*score, min), lex_end(*score, max))
+            }
+        };
+        // no entry at all lies past "+" or before "-"
+        let (Some(start), Some(end)) = endpoints else {
+            return btree_set::Range::default();
+        };
+        match (&start, &end) {
+            (Bound::Included(start) | Bound::Excluded(start), Bound::Included(end))
+            | (Bound::Included(start), Bound::Excluded(end))
+                if start > end =>
+            {
+                btree_set::Range::default()
+            }
+            (Bound::Excluded(start), Bound::Excluded(end)) if start >= end => {
+                btree_set::Range::default()
+            }
+            _ => self.ordered.range((start, end)),
+        }
+    }
+
+    // Returns the members within `range`, in descending order when `rev` is set, after
+    // skipping `offset` members and keeping at most `count` (all if None).
+    pub fn range(
+        &self,
+        range: &ZRangeSpec,
+        rev: bool,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Vec<(String, f64)> {
+        let iter: Box<dyn Iterator<Item = (&str, f64)>> = match range {
+            ZRangeSpec::Rank(start, stop) => {
+                let (start, stop) = match normalize_rank(*start, *stop, self.len()) {
+                    Some(ranks) => ranks,
+                    None => return vec![],
+                };
+                if rev {
+                    Box::new(self.iter().rev().skip(start).take(stop - start + 1))
+                } else {
+                    Box::new(self.iter().skip(start).take(stop - start + 1))
+                }
+            }
+            range => {
+                let entries = self
+                    .entries(range)
+                    .map(|(score, member)| (member.as_str(), score.0));
+                if rev {
+                    Box::new(entries.rev())
+                } else {
+                    Box::new(entries)
+                }
+            }
+        };
+        collect(iter, offset, count)
+    }
+}
+
+fn collect<'a>(
+    iter: impl Iterator<Item = (&'a str, f64)>,
+    offset: usize,
+    count: Option<usize>,
+) -> Vec<(String, f64)> {
+    iter.skip(offset)
+        .take(count.unwrap_or(usize::MAX))
+        .map(|(member, score)| (member.to_string(), score))
+        .collect()
+}
+
+// Converts possibly negative start/stop ranks into an inclusive index range within `len`.
+pub(crate) fn normalize_rank(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop || start >= len {
+        return None;
+    }
+    Some((start as usize, stop as usize))
+}
+
+// The first entry a score range can start at, the empty member sorting before any other. Scores
+// are ordered with -0 before 0 while the range compares them as equal.
+fn score_start(min: Bound<f64>) -> Option<Endpoint> {
+    let start = match min {
+        Bound::Included(0.0) => Bound::Included((Score(-0.0), String::new())),
+        Bound::Included(min) => Bound::Included((Score(min), String::new())),
+        Bound::Excluded(min) if min == f64::INFINITY => return None,
+        Bound::Excluded(min) => {
+            let min = if min == 0.0 { 0.0 } else { min };
+            Bound::Included((Score(min.next_up()), String::new()))
+        }
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    Some(start)
+}
+
+// The first entry past the end of a score range.
+fn score_end(max: Bound<f64>) -> Endpoint {
+    match max {
+        Bound::Included(max) if max == f64::INFINITY => Bound::Unbounded,
+        Bound::Included(max) => {
+            let max = if max == 0.0 { 0.0 } else { max };
+            Bound::Excluded((Score(max.next_up()), String::new()))
+        }
+        Bound::Excluded(0.0) => Bound::Excluded((Score(-0.0), String::new())),
+        Bound::Excluded(max) => Bound::Excluded((Score(max), String::new())),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+fn lex_start(score: Score, min: &LexBound) -> Option<Endpoint> {
+    let start = match min {
+        LexBound::Min => Bound::Unbounded,
+        LexBound::Max => return None,
+        LexBound::Inclusive(min) => Bound::Included((score, min.clone())),
+        LexBound::Exclusive(min) => Bound::Excluded((score, min.clone())),
+    };
+    Some(start)
+}
+
+fn lex_end(score: Score, max: &LexBound) -> Option<Endpoint> {
+    let end = match max {
+        LexBound::Min => return None,
+        LexBound::Max => Bound::Unbounded,
+        LexBound::Inclusive(max) => Bound::Included((score, max.clone())),
+        LexBound::Exclusive(max) => Bound::Excluded((score, max.clone())),
+    };
+    Some(end)
+}
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zset_insert_remove() {
+        let mut zset = ZSet::new();
+        assert!(zset.insert("one".to_string(), 1.0));
+        assert!(zset.insert("two".to_string(), 2.0));
+        assert!(!zset.insert("one".to_string(), 3.0));
+        assert_eq!(zset.len(), 2);
+        assert_eq!(zset.score("one"), Some(3.0));
+
+        let members = zset.iter().collect::<Vec<_>>();
+        assert_eq!(members, vec![("two", 2.0), ("one", 3.0)]);
+
+        assert!(zset.remove("one"));
+        assert!(!zset.remove("one"));
+        assert_eq!(zset.score("one"), None);
+        assert_eq!(zset.iter().count(), 1);
+    }
+
+    #[test]
+    fn test_zset_add_with_flags() {
+        let mut zset = ZSet::new();
+        let nx = ZAddFlags {
+            nx: true,
+            ..Default::default()
+        };
+        let xx = ZAddFlags {
+            xx: true,
+            ..Default::default()
+        };
+        let gt = ZAddFlags {
+            gt: true,
+            ..Default::default()
+        };
+        let lt_incr = ZAddFlags {
+            lt: true,
+            incr: true,
+            ..Default::default()
+        };
+
+        assert_eq!(zset.add("a".to_string(), 1.0, xx), ZAddOutcome::Skipped);
+        assert_eq!(zset.add("a".to_string(), 1.0, nx), ZAddOutcome::Added(1.0));
+        assert_eq!(zset.add("a".to_string(), 2.0, nx), ZAddOutcome::Skipped);
+        assert_eq!(zset.add("a".to_string(), 0.5, gt), ZAddOutcome::Skipped);
+        assert_eq!(
+            zset.add("a".to_string(), 3.0, gt),
+            ZAddOutcome::Updated(3.0)
+        );
+        assert_eq!(
+            zset.add("a".to_string(), 3.0, xx),
+            ZAddOutcome::Unchanged(3.0)
+        );
+        assert_eq!(
+            zset.add("a".to_string(), 1.0, lt_incr),
+            ZAddOutcome::Skipped
+        );
+        assert_eq!(
+            zset.add("a".to_string(), -1.0, lt_incr),
+            ZAddOutcome::Updated(2.0)
+        );
+        assert_eq!(zset.add("b".to_string(), 5.0, gt), ZAddOutcome::Added(5.0));
+
+        let incr = ZAddFlags {
+            incr: true,
+            ..Default::default()
+        };
+        zset.add("c".to_string(), f64::INFINITY, incr);
+        assert_eq!(
+            zset.add("c".to_string(), f64::NEG_INFINITY, incr),
+            ZAddOutcome::NotANumber
+        );
+    }
+
+    #[test]
+    fn test_zset_range_by_rank() {
+        let zset = sample();
+        let members = |range, rev, offset, count| {
+            zset.range(&range, rev, offset, count)
+                .into_iter()
+                .map(|(m, _)| m)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            members(ZRangeSpec::Rank(0, -1), false, 0, None),
+            ["a", "b", "c", "d"]
+        );
+        assert_eq!(members(ZRangeSpec::Rank(1, 2), false, 0, None), ["b", "c"]);
+        assert_eq!(
+            members(ZRangeSpec::Rank(-2, 10), false, 0, None),
+            ["c", "d"]
+        );
+        assert_eq!(members(ZRangeSpec::Rank(0, 1), true, 0, None), ["d", "c"]);
+        assert!(members(ZRangeSpec::Rank(3, 1), false, 0, None).is_empty());
+        assert!(members(ZRangeSpec::Rank(5, 10), false, 0, None).is_empty());
+    }
+
+    #[test]
+    fn test_zset_range_by_score() {
+        let zset = sample();
+        let range = ZRangeSpec::Score(Bound::Excluded(1.0), Bound::Included(3.0));
+        let result = zset.range(&range, false, 0, None);
+        assert_eq!(result, vec![("b".to_string(), 2.0), ("c".to_string(), 3.0)]);
+
+        let result = zset.range(&range, true, 0, Some(1));
+        assert_eq!(result, vec![("c".to_string(), 3.0)]);
+
+        let range = ZRangeSpec::Score(Bound::Unbounded, Bound::Unbounded);
+        let result = zset.range(&range, false, 1, Some(2));
+        assert_eq!(result, vec![("b".to_string(), 2.0), ("c".to_string(), 3.0)]);
+
+        // min past max, or both excluded at the same score, is an empty range
+        let range = ZRangeSpec::Score(Bound::Included(3.0), Bound::Included(2.0));
+        assert!(zset.range(&range, false, 0, None).is_empty());
+        let range = ZRangeSpec::Score(Bound::Excluded(2.0), Bound::Excluded(2.0));
+        assert!(zset.range(&range, true, 0, None).is_empty());
+    }
+
+    #[test]
+    fn test_zset_range_by_score_edges() {
+        let mut zset = ZSet::new();
+        for (member, score) in [("a", f64::NEG_INFINITY), ("b", -0.0), ("c", 0.0)] {
+            zset.insert(member.to_string(), score);
+        }
+        zset.insert("d".to_string(), f64::INFINITY);
+        zset.insert("e".to_string(), f64::INFINITY);
+        let members = |min, max| {
+            let range = ZRangeSpec::Score(min, max);
+            zset.range(&range, false, 0, None)
+                .into_iter()
+                .map(|(m, _)| m)
+                .collect::<Vec<_>>()
+        };
+
+        // -0 and 0 are the same score
+        assert_eq!(
+            members(Bound::Included(0.0), Bound::Included(0.0)),
+            ["b", "c"]
+        );
+        assert_eq!(
+            members(Bound::Included(-0.0), Bound::Excluded(1.0)),
+            ["b", "c"]
+        );
+        assert_eq!(members(Bound::Excluded(-0.0), Bound::Unbounded), ["d", "e"]);
+        assert_eq!(members(Bound::Unbounded, Bound::Excluded(0.0)), ["a"]);
+        assert_eq!(
+            members(
+                Bound::Included(f64::INFINITY),
+                Bound::Included(f64::INFINITY)
+            ),
+            ["d", "e"]
+        );
+        assert!(members(Bound::Excluded(f64::INFINITY), Bound::Unbounded).is_empty());
+        assert!(members(Bound::Unbounded, Bound::Excluded(f64::NEG_INFINITY)).is_empty());
+        let range = ZRangeSpec::Score(Bound::Excluded(f64::NEG_INFINITY), Bound::Unbounded);
+        assert_eq!(zset.count(&range), 4);
+    }
+
+    #[test]
+    fn test_zset_count() {
+        let zset = sample();
+        let range = ZRangeSpec::Score(Bound::Excluded(1.0), Bound::Included(3.0));
+        assert_eq!(zset.count(&range), 2);
+        let range = ZRangeSpec::Score(Bound::Included(5.0), Bound::Unbounded);
+        assert_eq!(zset.count(&range), 0);
+        assert_eq!(zset.count(&ZRangeSpec::Rank(1, -1)), 3);
+    }
+
+    #[test]
+    fn test_zset_range_by_lex() {
+        let mut zset = ZSet::new();
+        for member in ["a", "b", "c", "d", "e"] {
+            zset.insert(member.to_string(), 0.0);
+        }
+        let members = |range, rev| {
+            zset.range(&range, rev, 0, None)
+                .into_iter()
+                .map(|(m, _)| m)
+                .collect::<Vec<_>>()
+        };
+
+        let range = ZRangeSpec::Lex(LexBound::Min, LexBound::Inclusive("c".to_string()));
+        assert_eq!(members(range, false), ["a", "b", "c"]);
+        let range = ZRangeSpec::Lex(LexBound::Exclusive("b".to_string()), LexBound::Max);
+        assert_eq!(members(range.clone(), false), ["c", "d", "e"]);
+        assert_eq!(members(range, true), ["e", "d", "c"]);
+        let range = ZRangeSpec::Lex(LexBound::Max, LexBound::Min);
+        assert!(members(range, false).is_empty());
+        let range = ZRangeSpec::Lex(LexBound::Max, LexBound::Max);
+        assert!(members(range, false).is_empty());
+
+        // min past max, or both excluded at the same member, is an empty range
+        let range = ZRangeSpec::Lex(
+            LexBound::Inclusive("d".to_string()),
+            LexBound::Inclusive("b".to_string()),
+        );
+        assert!(members(range, true).is_empty());
+        let range = ZRangeSpec::Lex(
+            LexBound::Exclusive("c".to_string()),
+            LexBound::Exclusive("c".to_string()),
+        );
+        assert!(members(range, false).is_empty());
+        let range = ZRangeSpec::Lex(
+            LexBound::Inclusive("bb".to_string()),
+            LexBound::Exclusive("d".to_string()),
+        );
+        assert_eq!(zset.count(&range), 1);
+    }
+
+    fn sample() -> ZSet {
+        let mut zset = ZSet::new();
+        for (member, score) in [("a", 1.0), ("b", 2.0), ("c", 3.0), ("d", 4.0)] {
+            zset.insert(member.to_string(), score);
+        }
+        zset
+    }
+
+    #[test]
+    fn test_aggregate() {
+        assert_eq!(Aggregate::Sum.apply(1.0, 2.0), 3.0);
+        assert_eq!(Aggregate::Sum.apply(f64::INFINITY, f64::NEG_INFINITY), 0.0);
+        assert_eq!(Aggregate::Min.apply(1.0, 2.0), 1.0);
+        assert_eq!(Aggregate::Max.apply(1.0, 2.0), 2.0);
+    }
+
+    #[test]
+    fn test_zset_order_by_member_on_equal_score() {
+        let mut zset = ZSet::new();
+        zset.insert("b".to_string(), 1.0);
+        zset.insert("a".to_string(), 1.0);
+        zset.insert("c".to_string(), -1.0);
+
+        let members = zset.iter().map(|(m, _)| m).collect::<Vec<_>>();
+        assert_eq!(members, vec!["c", "a", "b"]);
+    }
+}
@@ -0,0 +1,325 @@
+use super::{
+    command::CommandSpec, extract_args, extract_integer, extract_key, extract_string,
+    validate_variadic_command, BitCount, BitOpStore, BitPos, CommandError, CommandExecutor,
+};
+use crate::{BitOp, BitRange, BitUnit, NotifyClass, RespArray, RespFrame, SimpleError};
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new(
+        "bitcount",
+        -2,
+        "bitmap",
+        "Counts the number of set bits (population counting) in a string.",
+    )
+    .flags(&["readonly"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "bitpos",
+        -3,
+        "bitmap",
+        "Finds the first set (1) or clear (0) bit in a string.",
+    )
+    .flags(&["readonly"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "bitop",
+        -4,
+        "bitmap",
+        "Performs bitwise operations on multiple strings, and stores the result.",
+    )
+    .flags(&["write", "denyoom"])
+    .keys(2, -1, 1),
+];
+
+impl CommandExecutor for BitCount {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.bitcount(&self.key, self.range) {
+            Ok(count) => RespFrame::Integer(count as i64),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for BitPos {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.bitpos(&self.key, self.bit, self.range) {
+            Ok(pos) => RespFrame::Integer(pos),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for BitOpStore {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        if self.op == BitOp::Not && self.keys.len() != 1 {
+            return SimpleError::new("ERR BITOP NOT must be called with a single source key.")
+                .into();
+        }
+        let existed = backend.exists(&self.destination);
+        let len = match backend.bitop(self.op, self.destination.clone(), &self.keys) {
+            Ok(len) => len,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        if len > 0 {
+            self.notify(backend, NotifyClass::String, "set", &self.destination);
+        } else if existed {
+            // an empty result removes the destination
+            self.notify(backend, NotifyClass::Generic, "del", &self.destination);
+        }
+        RespFrame::Integer(len as i64)
+    }
+}
+
+impl TryFrom<RespArray> for BitCount {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "bitcount", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next())?;
+        let range = match args.next() {
+            None => BitRange {
+                start: 0,
+                end: None,
+                unit: BitUnit::Byte,
+            },
+            // the start of the range goes with its end
+            Some(start) => BitRange {
+                start: extract_integer(Some(start))?,
+                end: Some(extract_integer(args.next())?),
+                unit: extract_unit(&mut args)?,
+            },
+        };
+        Ok(BitCount { key, range })
+    }
+}
+
+impl TryFrom<RespArray> for BitPos {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "bitpos", 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next())?;
+        let bit = match extract_string(args.next())?.as_str() {
+            "0" => false,
+            "1" => true,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "The bit argument must be 1 or 0.".to_string(),
+                ))
+            }
+        };
+        let start = args.next().map(|v| extract_integer(Some(v))).transpose()?;
+        let end = args.next().map(|v| extract_integer(Some(v))).transpose()?;
+        let range = BitRange {
+            start: start.unwrap_or(0),
+            end,
+            unit: extract_unit(&mut args)?,
+        };
+        Ok(BitPos { key, bit, range })
+    }
+}
+
+impl TryFrom<RespArray> for BitOpStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "bitop", 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let op = match extract_string(args.next())?.to_ascii_lowercase().as_str() {
+            "and" => BitOp::And,
+            "or" => BitOp::Or,
+            "xor" => BitOp::Xor,
+            "not" => BitOp::Not,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        let destination = extract_key(args.next())?;
+        let keys = args
+            .map(|key| extract_key(Some(key)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BitOpStore {
+            op,
+            destination,
+            keys,
+        })
+    }
+}
+
+// The optional unit closing the arguments of a bit range.
+fn extract_unit(args: &mut impl Iterator<Item = RespFrame>) -> Result<BitUnit, CommandError> {
+    let unit = match args.next() {
+        None => BitUnit::Byte,
+        Some(unit) => match extract_string(Some(unit))?.to_ascii_lowercase().as_str() {
+            "byte" => BitUnit::Byte,
+            "bit" => BitUnit::Bit,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        },
+    };
+    if args.next().is_some() {
+        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+    }
+    Ok(unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Command, Backend, BulkString, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn command(buf: &[u8]) -> Result<Command> {
+        let mut buf = BytesMut::from(buf);
+        let frame = RespArray::decode(&mut buf)?;
+        Ok(frame.try_into()?)
+    }
+
+    fn run(backend: &Backend, buf: &[u8]) -> Result<RespFrame> {
+        Ok(command(buf)?.execute(backend))
+    }
+
+    #[test]
+    fn test_bitcount_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$8\r\nBITCOUNT\r\n$1\r\nk\r\n$1\r\n1\r\n$2\r\n-1\r\n$3\r\nbit\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: BitCount = frame.try_into()?;
+        assert_eq!(cmd.key, b"k");
+        assert_eq!(
+            cmd.range,
+            BitRange {
+                start: 1,
+                end: Some(-1),
+                unit: BitUnit::Bit
+            }
+        );
+
+        // the start of a range needs its end
+        assert!(command(b"*3\r\n$8\r\nBITCOUNT\r\n$1\r\nk\r\n$1\r\n1\r\n").is_err());
+        assert!(command(
+            b"*5\r\n$8\r\nBITCOUNT\r\n$1\r\nk\r\n$1\r\n1\r\n$1\r\n2\r\n$4\r\nbits\r\n"
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitpos_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nBITPOS\r\n$1\r\nk\r\n$1\r\n0\r\n$1\r\n2\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: BitPos = frame.try_into()?;
+        assert!(!cmd.bit);
+        assert_eq!(
+            cmd.range,
+            BitRange {
+                start: 2,
+                end: None,
+                unit: BitUnit::Byte
+            }
+        );
+
+        assert!(command(b"*3\r\n$6\r\nBITPOS\r\n$1\r\nk\r\n$1\r\n2\r\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitcount_bitpos() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"k".to_vec(), BulkString::from("foobar").into());
+        let bitcount = |buf: &[u8]| run(&backend, buf);
+        assert_eq!(
+            bitcount(b"*2\r\n$8\r\nBITCOUNT\r\n$1\r\nk\r\n")?,
+            RespFrame::Integer(26)
+        );
+        assert_eq!(
+            bitcount(b"*4\r\n$8\r\nBITCOUNT\r\n$1\r\nk\r\n$1\r\n1\r\n$1\r\n1\r\n")?,
+            RespFrame::Integer(6)
+        );
+        assert_eq!(
+            bitcount(b"*5\r\n$8\r\nBITCOUNT\r\n$1\r\nk\r\n$1\r\n5\r\n$2\r\n30\r\n$3\r\nBIT\r\n")?,
+            RespFrame::Integer(17)
+        );
+        assert_eq!(
+            bitcount(b"*2\r\n$8\r\nBITCOUNT\r\n$7\r\nmissing\r\n")?,
+            RespFrame::Integer(0)
+        );
+
+        backend.set(
+            b"p".to_vec(),
+            BulkString::new(vec![0xff, 0xf0, 0x00]).into(),
+        );
+        let bitpos = |buf: &[u8]| run(&backend, buf);
+        assert_eq!(
+            bitpos(b"*3\r\n$6\r\nBITPOS\r\n$1\r\np\r\n$1\r\n0\r\n")?,
+            RespFrame::Integer(12)
+        );
+        assert_eq!(
+            bitpos(b"*4\r\n$6\r\nBITPOS\r\n$1\r\np\r\n$1\r\n1\r\n$1\r\n2\r\n")?,
+            RespFrame::Integer(-1)
+        );
+        // no clear bit in the range, the string is padded with them without an end
+        backend.set(b"f".to_vec(), BulkString::new(vec![0xff, 0xff]).into());
+        assert_eq!(
+            bitpos(b"*3\r\n$6\r\nBITPOS\r\n$1\r\nf\r\n$1\r\n0\r\n")?,
+            RespFrame::Integer(16)
+        );
+        assert_eq!(
+            bitpos(b"*5\r\n$6\r\nBITPOS\r\n$1\r\nf\r\n$1\r\n0\r\n$1\r\n0\r\n$2\r\n-1\r\n")?,
+            RespFrame::Integer(-1)
+        );
+        assert_eq!(
+            bitpos(b"*3\r\n$6\r\nBITPOS\r\n$1\r\nm\r\n$1\r\n0\r\n")?,
+            RespFrame::Integer(0)
+        );
+        assert_eq!(
+            bitpos(b"*3\r\n$6\r\nBITPOS\r\n$1\r\nm\r\n$1\r\n1\r\n")?,
+            RespFrame::Integer(-1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitop() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"key1".to_vec(), BulkString::from("foobar").into());
+        backend.set(b"key2".to_vec(), BulkString::from("abcdef").into());
+        assert_eq!(
+            run(
+                &backend,
+                b"*5\r\n$5\r\nBITOP\r\n$3\r\nAND\r\n$4\r\ndest\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n"
+            )?,
+            RespFrame::Integer(6)
+        );
+        assert_eq!(
+            backend.get(b"dest")?,
+            Some(BulkString::from("`bc`ab").into())
+        );
+
+        // NOT takes a single key
+        assert_eq!(
+            run(
+                &backend,
+                b"*5\r\n$5\r\nBITOP\r\n$3\r\nNOT\r\n$4\r\ndest\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n"
+            )?,
+            SimpleError::new("ERR BITOP NOT must be called with a single source key.").into()
+        );
+        // an empty result removes the destination
+        assert_eq!(
+            run(
+                &backend,
+                b"*4\r\n$5\r\nBITOP\r\n$2\r\nOR\r\n$4\r\ndest\r\n$7\r\nmissing\r\n"
+            )?,
+            RespFrame::Integer(0)
+        );
+        assert!(!backend.exists(b"dest"));
+        assert!(command(b"*4\r\n$5\r\nBITOP\r\n$4\r\nNAND\r\n$1\r\nd\r\n$1\r\nk\r\n").is_err());
+        Ok(())
+    }
+}
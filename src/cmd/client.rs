@@ -0,0 +1,657 @@
+use super::{
+    command::CommandSpec, extract_args, extract_string, validate_command,
+    validate_variadic_command, Client, ClientKillFilter, ClientSubcommand, CommandError,
+    CommandExecutor, Hello, Monitor, RESP_OK,
+};
+use crate::{
+    Backend, BulkString, ClientInfo, PauseMode, RespArray, RespFrame, RespMap, RespNull,
+    SimpleError,
+};
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new(
+        "client",
+        -2,
+        "connection",
+        "A container for client connection commands.",
+    )
+    .flags(&["noscript", "loading", "stale"]),
+    CommandSpec::new(
+        "hello",
+        -1,
+        "connection",
+        "Handshakes with the Redis server.",
+    )
+    .flags(&[
+        "noscript",
+        "loading",
+        "stale",
+        "fast",
+        "no_auth",
+        "allow_busy",
+    ]),
+    CommandSpec::new(
+        "monitor",
+        1,
+        "server",
+        "Listens for all requests received by the server in real-time.",
+    )
+    .flags(&["admin", "noscript", "loading", "stale"]),
+];
+
+const CLIENT_TYPES: [&str; 4] = ["normal", "master", "replica", "pubsub"];
+
+impl CommandExecutor for Client {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let now = backend.now_ms();
+        match self.subcommand {
+            ClientSubcommand::List { client_type, ids } => {
+                let lines = backend
+                    .clients()
+                    .list()
+                    .into_iter()
+                    // every client is a normal one so far
+                    .filter(|_| client_type.as_deref().is_none_or(|t| t == "normal"))
+                    .filter(|info| ids.is_empty() || ids.contains(&info.id))
+                    .map(|info| info.line(now))
+                    .collect::<String>();
+                BulkString::new(lines).into()
+            }
+            ClientSubcommand::Info => match backend.clients().get(backend.client_id()) {
+                Some(info) => BulkString::new(info.line(now)).into(),
+                None => SimpleError::new("ERR no such client").into(),
+            },
+            ClientSubcommand::Id => RespFrame::Integer(backend.client_id() as i64),
+            ClientSubcommand::SetName(name) => match set_name(backend, name) {
+                Ok(()) => RESP_OK.clone(),
+                Err(e) => e.into(),
+            },
+            ClientSubcommand::GetName => match backend.clients().get(backend.client_id()) {
+                Some(info) if !info.name.is_empty() => BulkString::new(info.name).into(),
+                _ => RespFrame::Null(RespNull),
+            },
+            ClientSubcommand::Kill { filter, legacy } => {
+                let killed = backend
+                    .clients()
+                    .list()
+                    .into_iter()
+                    .filter(|info| !filter.skip_me || info.id != backend.client_id())
+                    .filter(|info| filter.matches(info, now))
+                    .filter(|info| backend.clients().kill(info.id))
+                    .count();
+                match (legacy, killed) {
+                    (false, killed) => RespFrame::Integer(killed as i64),
+                    (true, 0) => SimpleError::new("ERR No such client").into(),
+                    (true, _) => RESP_OK.clone(),
+                }
+            }
+            ClientSubcommand::Pause { timeout, mode } => {
+                backend.pause_clients(mode, timeout);
+                RESP_OK.clone()
+            }
+            ClientSubcommand::Unpause => {
+                backend.unpause_clients();
+                RESP_OK.clone()
+            }
+        }
+    }
+}
+
+// Names the client owning `backend`, an empty name removing it.
+fn set_name(backend: &Backend, name: String) -> Result<(), SimpleError> {
+    // the name goes unquoted in CLIENT LIST, where spaces separate the fields
+    if name.chars().any(|c| !c.is_ascii_graphic()) {
+        return Err(SimpleError::new(
+            "ERR Client names cannot contain spaces, newlines or special characters.",
+        ));
+    }
+    backend
+        .clients()
+        .update(backend.client_id(), |info| info.name = name);
+    Ok(())
+}
+
+impl CommandExecutor for Hello {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let resp = match self.protover {
+            None => backend.protocol(),
+            Some(protover @ (2 | 3)) => protover as u8,
+            Some(_) => return SimpleError::new("NOPROTO unsupported protocol version").into(),
+        };
+        if let Some(name) = self.name {
+            if let Err(e) = set_name(backend, name) {
+                return e.into();
+            }
+        }
+        backend.set_protocol(resp);
+
+        let mode = if backend.cluster_enabled() {
+            "cluster"
+        } else {
+            "standalone"
+        };
+        let role = if backend.replication().master().is_some() {
+            "replica"
+        } else {
+            "master"
+        };
+        let fields: [(&str, RespFrame); 7] = [
+            ("server", BulkString::from("redis").into()),
+            (
+                "version",
+                BulkString::from(env!("CARGO_PKG_VERSION")).into(),
+            ),
+            ("proto", RespFrame::Integer(resp as i64)),
+            ("id", RespFrame::Integer(backend.client_id() as i64)),
+            ("mode", BulkString::from(mode).into()),
+            ("role", BulkString::from(role).into()),
+            ("modules", RespArray::new([]).into()),
+        ];
+        if resp >= 3 {
+            let mut map = RespMap::new();
+            for (name, value) in fields {
+                map.insert(name.to_string(), value);
+            }
+            map.into()
+        } else {
+            let pairs = fields
+                .into_iter()
+                .flat_map(|(name, value)| [BulkString::from(name).into(), value]);
+            RespArray::new(pairs.collect::<Vec<_>>()).into()
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Hello {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "hello", 0)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let protover = match args.next() {
+            Some(arg) => Some(extract_string(Some(arg))?.parse::<i64>().map_err(|_| {
+                CommandError::InvalidArgument(
+                    "Protocol version is not an integer or out of range".to_string(),
+                )
+            })?),
+            None => None,
+        };
+        let mut name = None;
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg))?.to_ascii_lowercase().as_str() {
+                "setname" => name = Some(extract_string(args.next())?),
+                option => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "Syntax error in HELLO option '{option}'"
+                    )))
+                }
+            }
+        }
+        Ok(Hello { protover, name })
+    }
+}
+
+// Marks the client as a monitor, the connection loop then switches it to streaming commands.
+impl CommandExecutor for Monitor {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        backend
+            .clients()
+            .update(backend.client_id(), |info| info.monitor = true);
+        RESP_OK.clone()
+    }
+}
+
+impl TryFrom<RespArray> for Monitor {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["monitor"], 0)?;
+        Ok(Monitor)
+    }
+}
+
+impl TryFrom<RespArray> for Client {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "client", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_lowercase();
+        let args = args
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let subcommand = match subcommand.as_str() {
+            "list" => extract_list_filters(args)?,
+            "info" if args.is_empty() => ClientSubcommand::Info,
+            "id" if args.is_empty() => ClientSubcommand::Id,
+            "setname" if args.len() == 1 => ClientSubcommand::SetName(args[0].clone()),
+            "getname" if args.is_empty() => ClientSubcommand::GetName,
+            "pause" if matches!(args.len(), 1 | 2) => extract_pause(args)?,
+            "unpause" if args.is_empty() => ClientSubcommand::Unpause,
+            "kill" if args.len() == 1 => ClientSubcommand::Kill {
+                filter: ClientKillFilter {
+                    addr: Some(args[0].clone()),
+                    ..Default::default()
+                },
+                legacy: true,
+            },
+            "kill" if !args.is_empty() && args.len() % 2 == 0 => ClientSubcommand::Kill {
+                filter: extract_kill_filter(args)?,
+                legacy: false,
+            },
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for '{subcommand}'"
+                )))
+            }
+        };
+        Ok(Client { subcommand })
+    }
+}
+
+// [TYPE type] [ID client-id [client-id ...]], the ids last as they take the rest of the arguments
+fn extract_list_filters(args: Vec<String>) -> Result<ClientSubcommand, CommandError> {
+    let mut client_type = None;
+    let mut ids = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match (arg.to_ascii_lowercase().as_str(), args.next()) {
+            ("type", Some(t)) if CLIENT_TYPES.contains(&t.to_ascii_lowercase().as_str()) => {
+                client_type = Some(t.to_ascii_lowercase());
+            }
+            ("type", Some(t)) => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Unknown client type '{t}'"
+                )))
+            }
+            ("id", Some(first)) => {
+                for id in std::iter::once(first).chain(args.by_ref()) {
+                    match id.parse::<u64>() {
+                        Ok(id) if id > 0 => ids.push(id),
+                        _ => {
+                            return Err(CommandError::InvalidArgument(
+                                "Invalid client ID".to_string(),
+                            ))
+                        }
+                    }
+                }
+            }
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+    Ok(ClientSubcommand::List { client_type, ids })
+}
+
+// timeout [WRITE | ALL], pausing all commands by default
+fn extract_pause(args: Vec<String>) -> Result<ClientSubcommand, CommandError> {
+    let timeout = match args[0].parse::<i64>() {
+        Ok(timeout) if timeout < 0 => {
+            return Err(CommandError::InvalidArgument(
+                "timeout is negative".to_string(),
+            ))
+        }
+        Ok(timeout) => timeout,
+        Err(_) => {
+            return Err(CommandError::InvalidArgument(
+                "timeout is not an integer or out of range".to_string(),
+            ))
+        }
+    };
+    let mode = match args.get(1).map(|mode| mode.to_ascii_lowercase()).as_deref() {
+        None | Some("all") => PauseMode::All,
+        Some("write") => PauseMode::Write,
+        Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+    };
+    Ok(ClientSubcommand::Pause { timeout, mode })
+}
+
+// [ID client-id] [TYPE type] [ADDR ip:port] [LADDR ip:port] [SKIPME yes | no] [MAXAGE maxage]
+// in pairs, SKIPME defaulting to yes
+fn extract_kill_filter(args: Vec<String>) -> Result<ClientKillFilter, CommandError> {
+    let mut filter = ClientKillFilter {
+        skip_me: true,
+        ..Default::default()
+    };
+    let mut args = args.into_iter();
+    while let (Some(name), Some(value)) = (args.next(), args.next()) {
+        match name.to_ascii_lowercase().as_str() {
+            "id" => match value.parse::<u64>() {
+                Ok(id) if id > 0 => filter.id = Some(id),
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "client-id should be greater than 0".to_string(),
+                    ))
+                }
+            },
+            "type" if CLIENT_TYPES.contains(&value.to_ascii_lowercase().as_str()) => {
+                filter.client_type = Some(value.to_ascii_lowercase());
+            }
+            "type" => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Unknown client type '{value}'"
+                )))
+            }
+            "addr" => filter.addr = Some(value),
+            "laddr" => filter.laddr = Some(value),
+            "skipme" => match value.to_ascii_lowercase().as_str() {
+                "yes" => filter.skip_me = true,
+                "no" => filter.skip_me = false,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            "maxage" => match value.parse::<u64>() {
+                Ok(max_age) => filter.max_age = Some(max_age),
+                Err(_) => {
+                    return Err(CommandError::InvalidArgument(
+                        "value is not an integer or out of range".to_string(),
+                    ))
+                }
+            },
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+    Ok(filter)
+}
+
+impl ClientKillFilter {
+    fn matches(&self, info: &ClientInfo, now: i64) -> bool {
+        let age = (now - info.created_at).max(0) as u64 / 1000;
+        self.id.is_none_or(|id| id == info.id)
+            && self.addr.as_ref().is_none_or(|addr| *addr == info.addr)
+            && self.laddr.as_ref().is_none_or(|laddr| *laddr == info.laddr)
+            // every client is a normal one so far
+            && self.client_type.as_deref().is_none_or(|t| t == "normal")
+            && self.max_age.is_none_or(|max_age| age >= max_age)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, ConfigValues, ManualClock, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_client_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*7\r\n$6\r\nCLIENT\r\n$4\r\nLIST\r\n$4\r\nTYPE\r\n$6\r\nNORMAL\r\n$2\r\nID\r\n$1\r\n1\r\n$1\r\n3\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Client = frame.try_into()?;
+        assert_eq!(
+            result.subcommand,
+            ClientSubcommand::List {
+                client_type: Some("normal".to_string()),
+                ids: vec![1, 3],
+            }
+        );
+
+        buf.extend_from_slice(b"*6\r\n$6\r\nclient\r\n$4\r\nkill\r\n$2\r\nID\r\n$1\r\n4\r\n$6\r\nSKIPME\r\n$2\r\nno\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Client = frame.try_into()?;
+        assert_eq!(
+            result.subcommand,
+            ClientSubcommand::Kill {
+                filter: ClientKillFilter {
+                    id: Some(4),
+                    ..Default::default()
+                },
+                legacy: false,
+            }
+        );
+
+        buf.extend_from_slice(b"*3\r\n$6\r\nclient\r\n$2\r\nid\r\n$1\r\n1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Client, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_command() -> Result<()> {
+        let clock = Arc::new(ManualClock::new(Duration::from_secs(1_000)));
+        let backend = Backend::with_clock(ConfigValues::default(), clock.clone());
+        let first = backend.connect("127.0.0.1:5000".to_string(), "127.0.0.1:6379".to_string());
+        let second = backend.connect("127.0.0.1:5001".to_string(), "127.0.0.1:6379".to_string());
+        clock.advance(Duration::from_secs(3));
+        second.select(2);
+        second.record_client_command("select");
+
+        let cmd = Client {
+            subcommand: ClientSubcommand::Id,
+        };
+        assert_eq!(cmd.execute(&second), RespFrame::Integer(2));
+
+        let cmd = Client {
+            subcommand: ClientSubcommand::Info,
+        };
+        assert_eq!(
+            cmd.execute(&second),
+            BulkString::from("id=2 addr=127.0.0.1:5001 laddr=127.0.0.1:6379 name= age=3 idle=0 flags=N db=2 cmd=select user=default resp=2\n").into()
+        );
+
+        let cmd = Client {
+            subcommand: ClientSubcommand::List {
+                client_type: None,
+                ids: vec![],
+            },
+        };
+        let expected = "id=1 addr=127.0.0.1:5000 laddr=127.0.0.1:6379 name= age=3 idle=3 flags=N db=0 cmd=NULL user=default resp=2\n\
+             id=2 addr=127.0.0.1:5001 laddr=127.0.0.1:6379 name= age=3 idle=0 flags=N db=2 cmd=select user=default resp=2\n";
+        assert_eq!(cmd.execute(&first), BulkString::from(expected).into());
+
+        let cmd = Client {
+            subcommand: ClientSubcommand::GetName,
+        };
+        assert_eq!(cmd.execute(&first), RespFrame::Null(RespNull));
+        let cmd = Client {
+            subcommand: ClientSubcommand::SetName("bad name".to_string()),
+        };
+        assert!(matches!(cmd.execute(&first), RespFrame::Error(_)));
+        let cmd = Client {
+            subcommand: ClientSubcommand::SetName("worker-1".to_string()),
+        };
+        assert_eq!(cmd.execute(&first), RESP_OK.clone());
+        let cmd = Client {
+            subcommand: ClientSubcommand::GetName,
+        };
+        assert_eq!(cmd.execute(&first), BulkString::from("worker-1").into());
+        let info = backend.clients().get(1).expect("connected");
+        assert!(info.line(0).contains(" name=worker-1 "));
+
+        first.disconnect();
+        let cmd = Client {
+            subcommand: ClientSubcommand::List {
+                client_type: Some("pubsub".to_string()),
+                ids: vec![],
+            },
+        };
+        assert_eq!(cmd.execute(&second), BulkString::from("").into());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_pause() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nCLIENT\r\n$5\r\nPAUSE\r\n$3\r\n100\r\n$5\r\nWRITE\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Client = frame.try_into()?;
+        assert_eq!(
+            result.subcommand,
+            ClientSubcommand::Pause {
+                timeout: 100,
+                mode: PauseMode::Write,
+            }
+        );
+
+        let backend = Backend::new();
+        let cmd = Client {
+            subcommand: ClientSubcommand::Pause {
+                timeout: 60_000,
+                mode: PauseMode::Write,
+            },
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        // reads go on while writes wait
+        backend.wait_unpaused(false).await;
+        let writer = backend.clone();
+        let write = tokio::spawn(async move { writer.wait_unpaused(true).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!write.is_finished());
+
+        let cmd = Client {
+            subcommand: ClientSubcommand::Unpause,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        tokio::time::timeout(Duration::from_secs(1), write).await??;
+
+        // a pause ends on its own once the timeout elapses
+        backend.pause_clients(PauseMode::All, 20);
+        tokio::time::timeout(Duration::from_secs(1), backend.wait_unpaused(false)).await?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_hello() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\nHELLO\r\n$1\r\n3\r\n$7\r\nSETNAME\r\n$2\r\nw1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Hello = frame.try_into()?;
+        assert_eq!(result.protover, Some(3));
+        assert_eq!(result.name.as_deref(), Some("w1"));
+        buf.extend_from_slice(b"*2\r\n$5\r\nHELLO\r\n$5\r\nthree\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(Hello::try_from(frame).is_err());
+
+        let backend = Backend::new();
+        let client = backend.connect("127.0.0.1:5000".to_string(), "127.0.0.1:6379".to_string());
+        let cmd = Hello {
+            protover: Some(4),
+            name: None,
+        };
+        assert_eq!(
+            cmd.execute(&client),
+            SimpleError::new("NOPROTO unsupported protocol version").into()
+        );
+        assert_eq!(client.protocol(), 2);
+
+        // without a version, the reply is in the version the connection speaks
+        let cmd = Hello {
+            protover: None,
+            name: None,
+        };
+        let RespFrame::Array(reply) = cmd.execute(&client) else {
+            panic!("RESP2 replies with an array");
+        };
+        assert_eq!(reply.len(), 14);
+        assert_eq!(reply[4], BulkString::from("proto").into());
+        assert_eq!(reply[5], RespFrame::Integer(2));
+
+        let cmd = Hello {
+            protover: Some(3),
+            name: Some("worker-1".to_string()),
+        };
+        let RespFrame::Map(reply) = cmd.execute(&client) else {
+            panic!("RESP3 replies with a map");
+        };
+        assert_eq!(reply.get("proto"), Some(&RespFrame::Integer(3)));
+        assert_eq!(reply.get("id"), Some(&RespFrame::Integer(1)));
+        assert_eq!(reply.get("role"), Some(&BulkString::from("master").into()));
+        assert_eq!(client.protocol(), 3);
+        let info = backend
+            .clients()
+            .get(client.client_id())
+            .expect("connected");
+        assert!(info
+            .line(0)
+            .ends_with(" name=worker-1 age=0 idle=0 flags=N db=0 cmd=NULL user=default resp=3\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_monitor() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$7\r\nMONITOR\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let _: Monitor = frame.try_into()?;
+
+        let backend = Backend::new();
+        let client = backend.connect("127.0.0.1:5000".to_string(), "127.0.0.1:6379".to_string());
+        assert_eq!(Monitor.execute(&client), RESP_OK.clone());
+        let info = backend
+            .clients()
+            .get(client.client_id())
+            .expect("connected");
+        assert!(info.line(0).contains(" flags=O "));
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_kill() {
+        let clock = Arc::new(ManualClock::new(Duration::from_secs(1_000)));
+        let backend = Backend::with_clock(ConfigValues::default(), clock.clone());
+        let first = backend.connect("127.0.0.1:5000".to_string(), "127.0.0.1:6379".to_string());
+        clock.advance(Duration::from_secs(10));
+        let second = backend.connect("127.0.0.1:5001".to_string(), "127.0.0.1:6379".to_string());
+        let third = backend.connect("127.0.0.1:5002".to_string(), "127.0.0.1:6380".to_string());
+        let signal = |handle: &Backend| {
+            let signal = backend.clients().kill_signal(handle.client_id());
+            *signal.expect("connected").borrow()
+        };
+
+        let cmd = Client {
+            subcommand: ClientSubcommand::Kill {
+                filter: ClientKillFilter {
+                    addr: Some("127.0.0.1:9999".to_string()),
+                    ..Default::default()
+                },
+                legacy: true,
+            },
+        };
+        assert_eq!(
+            cmd.execute(&first),
+            SimpleError::new("ERR No such client").into()
+        );
+
+        // the caller itself is skipped by default
+        let cmd = Client {
+            subcommand: ClientSubcommand::Kill {
+                filter: ClientKillFilter {
+                    laddr: Some("127.0.0.1:6379".to_string()),
+                    skip_me: true,
+                    ..Default::default()
+                },
+                legacy: false,
+            },
+        };
+        assert_eq!(cmd.execute(&first), RespFrame::Integer(1));
+        assert!(!signal(&first) && signal(&second) && !signal(&third));
+
+        let cmd = Client {
+            subcommand: ClientSubcommand::Kill {
+                filter: ClientKillFilter {
+                    max_age: Some(5),
+                    ..Default::default()
+                },
+                legacy: false,
+            },
+        };
+        assert_eq!(cmd.execute(&third), RespFrame::Integer(1));
+        assert!(signal(&first) && !signal(&third));
+
+        let cmd = Client {
+            subcommand: ClientSubcommand::Kill {
+                filter: ClientKillFilter {
+                    addr: Some("127.0.0.1:5002".to_string()),
+                    ..Default::default()
+                },
+                legacy: true,
+            },
+        };
+        assert_eq!(cmd.execute(&third), RESP_OK.clone());
+        assert!(signal(&third));
+    }
+}
@@ -0,0 +1,479 @@
+use super::{
+    command::CommandSpec, extract_args, extract_integer, extract_string, validate_command,
+    validate_variadic_command, Asking, Cluster, ClusterSubcommand, CommandError, CommandExecutor,
+    SlotState, RESP_OK,
+};
+use crate::{Backend, BulkString, RespArray, RespFrame, SimpleError, CLUSTER_SLOTS};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new(
+        "asking",
+        1,
+        "cluster",
+        "Signals that a cluster client is following an -ASK redirect.",
+    )
+    .flags(&["fast"]),
+    CommandSpec::new(
+        "cluster",
+        -2,
+        "cluster",
+        "A container for Redis Cluster commands.",
+    )
+    .flags(&["loading", "stale"]),
+];
+
+// what cluster commands reply when the server isn't a cluster node
+pub const CLUSTER_DISABLED_ERROR: &str = "ERR This instance has cluster support disabled";
+
+impl CommandExecutor for Asking {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if !backend.cluster_enabled() {
+            return SimpleError::new(CLUSTER_DISABLED_ERROR).into();
+        }
+        backend
+            .clients()
+            .update(backend.client_id(), |info| info.asking = true);
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for Cluster {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if !backend.cluster_enabled() {
+            return SimpleError::new(CLUSTER_DISABLED_ERROR).into();
+        }
+        let cluster = backend.cluster();
+        match self.subcommand {
+            ClusterSubcommand::Info => BulkString::new(cluster_info(backend)).into(),
+            ClusterSubcommand::MyId => BulkString::new(cluster.myself().id).into(),
+            ClusterSubcommand::Slots => {
+                let mut ranges = vec![];
+                for node in cluster.nodes() {
+                    for (start, end) in cluster.slot_ranges(&node.id) {
+                        ranges.push((start, end, node.clone()));
+                    }
+                }
+                ranges.sort_by_key(|(start, _, _)| *start);
+                let ranges = ranges.into_iter().map(|(start, end, node)| {
+                    let node = RespArray::new([
+                        BulkString::new(node.host).into(),
+                        RespFrame::Integer(node.port as i64),
+                        BulkString::new(node.id).into(),
+                    ]);
+                    RespArray::new([
+                        RespFrame::Integer(start as i64),
+                        RespFrame::Integer(end as i64),
+                        node.into(),
+                    ])
+                    .into()
+                });
+                RespArray::new(ranges.collect::<Vec<_>>()).into()
+            }
+            ClusterSubcommand::Shards => {
+                let myself = cluster.myself().id;
+                // every node is a master, alone in its shard
+                let nodes = cluster.nodes().into_iter().filter(|node| !node.handshake);
+                let shards = nodes.map(|node| {
+                    let slots = cluster.slot_ranges(&node.id).into_iter();
+                    let slots = slots.flat_map(|(start, end)| {
+                        [
+                            RespFrame::Integer(start as i64),
+                            RespFrame::Integer(end as i64),
+                        ]
+                    });
+                    let (offset, health) = match node.id == myself {
+                        true => (backend.replication().offset(), "online"),
+                        false => (0, if node.connected { "online" } else { "fail" }),
+                    };
+                    let node = RespArray::new([
+                        BulkString::from("id").into(),
+                        BulkString::new(node.id).into(),
+                        BulkString::from("port").into(),
+                        RespFrame::Integer(node.port as i64),
+                        BulkString::from("ip").into(),
+                        BulkString::new(node.host.clone()).into(),
+                        BulkString::from("endpoint").into(),
+                        BulkString::new(node.host).into(),
+                        BulkString::from("role").into(),
+                        BulkString::from("master").into(),
+                        BulkString::from("replication-offset").into(),
+                        RespFrame::Integer(offset as i64),
+                        BulkString::from("health").into(),
+                        BulkString::from(health).into(),
+                    ]);
+                    RespArray::new([
+                        BulkString::from("slots").into(),
+                        RespArray::new(slots.collect::<Vec<_>>()).into(),
+                        BulkString::from("nodes").into(),
+                        RespArray::new([node.into()]).into(),
+                    ])
+                    .into()
+                });
+                RespArray::new(shards.collect::<Vec<_>>()).into()
+            }
+            ClusterSubcommand::Nodes => BulkString::new(cluster.nodes_text()).into(),
+            ClusterSubcommand::Meet { host, port } => {
+                let Ok(port @ 1..) = u16::try_from(port) else {
+                    let error = format!("ERR Invalid node address specified: {host}:{port}");
+                    return SimpleError::new(error).into();
+                };
+                // the handshake completes in the background, see gossip
+                if cluster.meet(&host, port) {
+                    return save_config(backend);
+                }
+                RESP_OK.clone()
+            }
+            ClusterSubcommand::AddSlots(slots) => {
+                let slots = match check_slots(&slots) {
+                    Ok(slots) => slots,
+                    Err(error) => return error,
+                };
+                let mut seen = HashSet::new();
+                for slot in &slots {
+                    if !seen.insert(slot) {
+                        let error = format!("ERR Slot {slot} specified multiple times");
+                        return SimpleError::new(error).into();
+                    }
+                    if cluster.slot_owner(*slot).is_some() {
+                        return SimpleError::new(format!("ERR Slot {slot} is already busy")).into();
+                    }
+                }
+                cluster.assign_slots(&slots, &cluster.myself().id);
+                save_config(backend)
+            }
+            ClusterSubcommand::SetSlot { slot, state } => {
+                let slot = match check_slots(&[slot]) {
+                    Ok(slots) => slots[0],
+                    Err(error) => return error,
+                };
+                let myself = cluster.myself().id;
+                let owned = cluster
+                    .slot_owner(slot)
+                    .is_some_and(|owner| owner.id == myself);
+                let known = |id: &str| cluster.nodes().iter().any(|node| node.id == id);
+                match state {
+                    SlotState::Stable => cluster.set_stable(slot),
+                    SlotState::Importing(id) | SlotState::Migrating(id) | SlotState::Node(id)
+                        if !known(&id) =>
+                    {
+                        let error = format!("ERR I don't know about node {id}");
+                        return SimpleError::new(error).into();
+                    }
+                    SlotState::Migrating(_) if !owned => {
+                        let error = format!("ERR I'm not the owner of hash slot {slot}");
+                        return SimpleError::new(error).into();
+                    }
+                    SlotState::Migrating(id) => {
+                        cluster.set_migrating(slot, &id);
+                    }
+                    SlotState::Importing(_) if owned => {
+                        let error = format!("ERR I'm already the owner of hash slot {slot}");
+                        return SimpleError::new(error).into();
+                    }
+                    SlotState::Importing(id) => {
+                        cluster.set_importing(slot, &id);
+                    }
+                    SlotState::Node(id) if owned && id != myself => {
+                        if backend.count_keys_in_slot(slot) > 0 {
+                            let error = format!("ERR Can't assign hashslot {slot} to a different node while I still hold keys for this hash slot.");
+                            return SimpleError::new(error).into();
+                        }
+                        cluster.assign_slots(&[slot], &id);
+                    }
+                    SlotState::Node(id) => {
+                        // the end of an import, the other nodes learn of it from the new epoch
+                        let imported = id == myself && !owned;
+                        cluster.assign_slots(&[slot], &id);
+                        if imported {
+                            cluster.bump_epoch();
+                        }
+                    }
+                }
+                save_config(backend)
+            }
+        }
+    }
+}
+
+fn cluster_info(backend: &Backend) -> String {
+    let cluster = backend.cluster();
+    let nodes = cluster.nodes();
+    let served = nodes
+        .iter()
+        .map(|node| cluster.slot_ranges(&node.id))
+        .filter(|ranges| !ranges.is_empty())
+        .collect::<Vec<_>>();
+    let assigned = served
+        .iter()
+        .flatten()
+        .map(|(start, end)| (end - start + 1) as usize)
+        .sum::<usize>();
+    let state = if assigned == CLUSTER_SLOTS {
+        "ok"
+    } else {
+        "fail"
+    };
+    let mut info = String::new();
+    let fields = [
+        ("cluster_state", state.to_string()),
+        ("cluster_slots_assigned", assigned.to_string()),
+        ("cluster_slots_ok", assigned.to_string()),
+        ("cluster_slots_pfail", "0".to_string()),
+        ("cluster_slots_fail", "0".to_string()),
+        ("cluster_known_nodes", nodes.len().to_string()),
+        ("cluster_size", served.len().to_string()),
+        ("cluster_current_epoch", cluster.current_epoch().to_string()),
+        ("cluster_my_epoch", nodes[0].config_epoch.to_string()),
+    ];
+    for (name, value) in fields {
+        let _ = write!(info, "{name}:{value}\r\n");
+    }
+    info
+}
+
+fn check_slots(slots: &[i64]) -> Result<Vec<u16>, RespFrame> {
+    slots
+        .iter()
+        .map(|slot| match u16::try_from(*slot) {
+            Ok(slot) if (slot as usize) < CLUSTER_SLOTS => Ok(slot),
+            _ => Err(SimpleError::new("ERR Invalid or out of range slot").into()),
+        })
+        .collect()
+}
+
+// Replies OK once the cluster state changed by a command is saved.
+fn save_config(backend: &Backend) -> RespFrame {
+    match backend.save_cluster_config() {
+        Ok(()) => RESP_OK.clone(),
+        Err(e) => SimpleError::new(format!("ERR Error saving the cluster node config: {e}")).into(),
+    }
+}
+
+impl TryFrom<RespArray> for Asking {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["asking"], 0)?;
+        Ok(Asking)
+    }
+}
+
+impl TryFrom<RespArray> for Cluster {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "cluster", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_lowercase();
+        let args = args.collect::<Vec<_>>();
+        let arity_error = || {
+            CommandError::InvalidArgument(format!(
+                "unknown subcommand or wrong number of arguments for '{subcommand}'"
+            ))
+        };
+        let mut args = args.into_iter();
+        let subcommand = match (subcommand.as_str(), args.len()) {
+            ("info", 0) => ClusterSubcommand::Info,
+            ("myid", 0) => ClusterSubcommand::MyId,
+            ("slots", 0) => ClusterSubcommand::Slots,
+            ("shards", 0) => ClusterSubcommand::Shards,
+            ("nodes", 0) => ClusterSubcommand::Nodes,
+            ("meet", 2) => ClusterSubcommand::Meet {
+                host: extract_string(args.next())?,
+                port: extract_integer(args.next())?,
+            },
+            ("addslots", 1..) => ClusterSubcommand::AddSlots(
+                args.map(|arg| extract_integer(Some(arg)))
+                    .collect::<Result<_, _>>()?,
+            ),
+            ("setslot", 2 | 3) => {
+                let slot = extract_integer(args.next())?;
+                let state = extract_string(args.next())?.to_ascii_lowercase();
+                let node = args.next().map(|arg| extract_string(Some(arg)));
+                let state = match (state.as_str(), node) {
+                    ("importing", Some(node)) => SlotState::Importing(node?),
+                    ("migrating", Some(node)) => SlotState::Migrating(node?),
+                    ("node", Some(node)) => SlotState::Node(node?),
+                    ("stable", None) => SlotState::Stable,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "Invalid CLUSTER SETSLOT action or number of arguments".to_string(),
+                        ))
+                    }
+                };
+                ClusterSubcommand::SetSlot { slot, state }
+            }
+            _ => return Err(arity_error()),
+        };
+        Ok(Cluster { subcommand })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConfigValues, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn cluster_node(dir: &std::path::Path) -> Backend {
+        Backend::with_config(ConfigValues {
+            cluster_enabled: true,
+            port: 7000,
+            dir: dir.to_string_lossy().into_owned(),
+            ..Default::default()
+        })
+    }
+
+    fn run(backend: &Backend, subcommand: ClusterSubcommand) -> RespFrame {
+        Cluster { subcommand }.execute(backend)
+    }
+
+    fn text(frame: RespFrame) -> String {
+        match frame {
+            RespFrame::BulkString(text) => String::from_utf8_lossy(&text).into_owned(),
+            frame => panic!("unexpected reply: {frame:?}"),
+        }
+    }
+
+    #[test]
+    fn test_cluster_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$7\r\ncluster\r\n$7\r\nSETSLOT\r\n$1\r\n7\r\n$9\r\nMIGRATING\r\n$1\r\na\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Cluster = frame.try_into()?;
+        assert_eq!(
+            result.subcommand,
+            ClusterSubcommand::SetSlot {
+                slot: 7,
+                state: SlotState::Migrating("a".to_string())
+            }
+        );
+
+        buf.extend_from_slice(b"*3\r\n$7\r\ncluster\r\n$7\r\nsetslot\r\n$1\r\n7\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(Cluster::try_from(frame).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_asking() -> Result<()> {
+        let backend = Backend::new();
+        let client = backend.connect("127.0.0.1:5000".to_string(), "127.0.0.1:6379".to_string());
+        let frame = RespArray::new([BulkString::from("asking").into()]);
+        assert!(Asking::try_from(frame).is_ok());
+        assert_eq!(
+            Asking.execute(&client),
+            SimpleError::new(CLUSTER_DISABLED_ERROR).into()
+        );
+        assert_eq!(
+            run(&client, ClusterSubcommand::MyId),
+            SimpleError::new(CLUSTER_DISABLED_ERROR).into()
+        );
+
+        let backend = Backend::with_config(ConfigValues {
+            cluster_enabled: true,
+            ..Default::default()
+        });
+        let client = backend.connect("127.0.0.1:5000".to_string(), "127.0.0.1:6379".to_string());
+        assert_eq!(Asking.execute(&client), RESP_OK.clone());
+        // it holds until a command uses it
+        assert!(client.asking(false));
+        assert!(client.asking(true));
+        assert!(!client.asking(true));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cluster_slots() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("simple-redis-cluster-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let backend = cluster_node(&dir);
+        let myself = text(run(&backend, ClusterSubcommand::MyId));
+        assert_eq!(myself, backend.cluster().myself().id);
+        assert!(text(run(&backend, ClusterSubcommand::Info)).starts_with("cluster_state:fail\r\n"));
+
+        let addslots = ClusterSubcommand::AddSlots((0..CLUSTER_SLOTS as i64).collect());
+        assert_eq!(run(&backend, addslots), RESP_OK.clone());
+        let addslots = ClusterSubcommand::AddSlots(vec![3]);
+        assert_eq!(
+            run(&backend, addslots),
+            SimpleError::new("ERR Slot 3 is already busy").into()
+        );
+        let info = text(run(&backend, ClusterSubcommand::Info));
+        assert!(info.starts_with("cluster_state:ok\r\ncluster_slots_assigned:16384\r\n"));
+        let expected = RespArray::new([RespArray::new([
+            RespFrame::Integer(0),
+            RespFrame::Integer(16383),
+            RespArray::new([
+                BulkString::from("").into(),
+                RespFrame::Integer(7000),
+                BulkString::new(myself.clone()).into(),
+            ])
+            .into(),
+        ])
+        .into()]);
+        assert_eq!(run(&backend, ClusterSubcommand::Slots), expected.into());
+
+        // hand slot 5 over to another node
+        let other = "b".repeat(40);
+        let setslot = |slot, state| run(&backend, ClusterSubcommand::SetSlot { slot, state });
+        assert_eq!(
+            setslot(5, SlotState::Migrating(other.clone())),
+            SimpleError::new(format!("ERR I don't know about node {other}")).into()
+        );
+        let meet = ClusterSubcommand::Meet {
+            host: "127.0.0.1".to_string(),
+            port: 7001,
+        };
+        assert_eq!(run(&backend, meet), RESP_OK.clone());
+        let nodes = backend.cluster().nodes();
+        assert!(nodes[1].handshake);
+        let other = nodes[1].id.clone();
+        assert_eq!(
+            setslot(5, SlotState::Migrating(other.clone())),
+            RESP_OK.clone()
+        );
+        let nodes = text(run(&backend, ClusterSubcommand::Nodes));
+        assert_eq!(
+            nodes,
+            format!(
+                "{myself} :7000@0 myself,master - 0 0 0 connected 0-16383 [5->-{other}]\n\
+                 {other} 127.0.0.1:7001@0 handshake - 0 0 0 disconnected\n"
+            )
+        );
+        // which the config file keeps
+        let config = std::fs::read_to_string(dir.join("nodes.conf"))?;
+        assert_eq!(
+            config,
+            format!("{nodes}vars currentEpoch 0 lastVoteEpoch 0\n")
+        );
+        let restarted = cluster_node(&dir);
+        assert!(restarted.load_cluster_config()?);
+        assert_eq!(restarted.cluster().nodes_text(), nodes);
+
+        backend.set(b"{a}".to_vec(), BulkString::from("1").into());
+        let slot = crate::key_hash_slot(b"a") as i64;
+        assert!(matches!(
+            setslot(slot, SlotState::Node(other.clone())),
+            RespFrame::Error(_)
+        ));
+        assert_eq!(setslot(5, SlotState::Node(other.clone())), RESP_OK.clone());
+        assert_eq!(backend.cluster().slot_ranges(&other), [(5, 5)]);
+        assert_eq!(
+            setslot(5, SlotState::Importing(other.clone())),
+            RESP_OK.clone()
+        );
+        // taking it back ends the import with a new epoch
+        assert_eq!(setslot(5, SlotState::Node(myself.clone())), RESP_OK.clone());
+        assert_eq!(backend.cluster().current_epoch(), 1);
+        assert_eq!(backend.cluster().myself().config_epoch, 1);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,508 @@
+use super::{
+    bitmap, client, cluster, config, debug, dump, expire, extract_args, extract_string, geo, hmap,
+    hyperloglog, info, keys, latency, list, lolwut, map, memory, pubsub, replication, scripting,
+    server, set, stream, transaction, zset, CommandError, CommandExecutor, CommandMeta,
+    CommandQuery,
+};
+use crate::{Backend, BulkString, RespArray, RespFrame, RespNull, SimpleError, SimpleString};
+use lazy_static::lazy_static;
+
+// Metadata of a command as reported by COMMAND INFO and COMMAND DOCS. Each command module
+// declares the specs of the commands it implements in a `COMMANDS` table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    // number of arguments including the command name, negative when it is a minimum
+    pub arity: i64,
+    pub flags: &'static [&'static str],
+    // positions of the first and last key arguments and the step between keys, a negative last
+    // key counts from the end and all three are 0 for commands without (fixed position) keys
+    pub first_key: i64,
+    pub last_key: i64,
+    pub step: i64,
+    // where the keys that the fixed positions can't describe are, for "movablekeys" commands
+    pub movable_keys: Option<KeySearch>,
+    pub group: &'static str,
+    pub summary: &'static str,
+}
+
+// How the keys of a command whose key positions depend on its other arguments are found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySearch {
+    // the argument at this position counts the keys right after it, like `EVAL script numkeys
+    // key [key ...]`
+    KeyNum(usize),
+    // the argument after the last occurrence of any of these keywords, like `GEORADIUS ... STORE
+    // key`
+    Keyword(&'static [&'static str]),
+    // the first half of the arguments after this keyword, like `XREAD ... STREAMS key [key ...]
+    // id [id ...]`
+    KeywordHalf(&'static str),
+    // the argument at this position unless it is empty, then the arguments after the keyword,
+    // like `MIGRATE host port key|"" db timeout [KEYS key [key ...]]`
+    KeyOrKeywordRest(usize, &'static str),
+}
+
+impl CommandSpec {
+    pub const fn new(
+        name: &'static str,
+        arity: i64,
+        group: &'static str,
+        summary: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            arity,
+            flags: &[],
+            first_key: 0,
+            last_key: 0,
+            step: 0,
+            movable_keys: None,
+            group,
+            summary,
+        }
+    }
+
+    pub const fn flags(mut self, flags: &'static [&'static str]) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub const fn keys(mut self, first_key: i64, last_key: i64, step: i64) -> Self {
+        self.first_key = first_key;
+        self.last_key = last_key;
+        self.step = step;
+        self
+    }
+
+    pub const fn movable_keys(mut self, search: KeySearch) -> Self {
+        self.movable_keys = Some(search);
+        self
+    }
+
+    pub fn has_keys(&self) -> bool {
+        self.first_key > 0 || self.movable_keys.is_some()
+    }
+
+    // Positions of the keys in `args`, the full command with its name at 0. None when the
+    // arguments don't hold the keys they should, e.g. when numkeys runs past the end.
+    pub fn key_positions(&self, args: &[String]) -> Option<Vec<usize>> {
+        let mut positions = vec![];
+        let mut next = 1;
+        if self.first_key > 0 {
+            let last = if self.last_key < 0 {
+                args.len() as i64 + self.last_key
+            } else {
+                self.last_key
+            };
+            if last as usize >= args.len() {
+                return None;
+            }
+            let keys = (self.first_key..=last).step_by(self.step.max(1) as usize);
+            positions.extend(keys.map(|i| i as usize));
+            next = last as usize + 1;
+        }
+        match self.movable_keys {
+            Some(KeySearch::KeyNum(index)) => {
+                let numkeys = args.get(index)?.parse::<usize>().ok()?;
+                if numkeys == 0 || index + numkeys >= args.len() {
+                    return None;
+                }
+                positions.extend(index + 1..=index + numkeys);
+            }
+            Some(KeySearch::Keyword(keywords)) => {
+                // the last argument can't be a keyword, nothing follows it
+                let found = args[next.min(args.len())..args.len().saturating_sub(1)]
+                    .iter()
+                    .rposition(|arg| keywords.iter().any(|k| arg.eq_ignore_ascii_case(k)));
+                if let Some(i) = found {
+                    positions.push(next + i + 1);
+                }
+            }
+            Some(KeySearch::KeywordHalf(keyword)) => {
+                let found = args[next.min(args.len())..]
+                    .iter()
+                    .position(|arg| arg.eq_ignore_ascii_case(keyword))?;
+                let first = next + found + 1;
+                let rest = args.len() - first;
+                if rest == 0 || !rest.is_multiple_of(2) {
+                    return None;
+                }
+                positions.extend(first..first + rest / 2);
+            }
+            Some(KeySearch::KeyOrKeywordRest(index, keyword)) => {
+                if !args.get(index)?.is_empty() {
+                    positions.push(index);
+                } else {
+                    let found = args[index + 1..]
+                        .iter()
+                        .position(|arg| arg.eq_ignore_ascii_case(keyword))?;
+                    positions.extend(index + found + 2..args.len());
+                }
+            }
+            None => {}
+        }
+        Some(positions)
+    }
+
+    // [name, arity, flags, first key, last key, step, acl categories]
+    fn info_reply(&self) -> RespFrame {
+        let flags = self
+            .flags
+            .iter()
+            .map(|flag| SimpleString::new(*flag).into())
+            .collect::<Vec<_>>();
+        RespArray::new([
+            BulkString::from(self.name).into(),
+            RespFrame::Integer(self.arity),
+            RespArray::new(flags).into(),
+            RespFrame::Integer(self.first_key),
+            RespFrame::Integer(self.last_key),
+            RespFrame::Integer(self.step),
+            RespArray::new([SimpleString::new(format!("@{}", self.group)).into()]).into(),
+        ])
+        .into()
+    }
+
+    fn docs_reply(&self) -> RespFrame {
+        RespArray::new([
+            BulkString::from("summary").into(),
+            BulkString::from(self.summary).into(),
+            BulkString::from("group").into(),
+            BulkString::from(self.group).into(),
+        ])
+        .into()
+    }
+}
+
+pub(super) const COMMANDS: &[CommandSpec] = &[CommandSpec::new(
+    "command",
+    -1,
+    "server",
+    "Returns detailed information about all commands.",
+)
+.flags(&["loading", "stale"])];
+
+lazy_static! {
+    static ref REGISTRY: Vec<CommandSpec> = [
+        map::COMMANDS,
+        hmap::COMMANDS,
+        set::COMMANDS,
+        list::COMMANDS,
+        zset::COMMANDS,
+        stream::COMMANDS,
+        bitmap::COMMANDS,
+        hyperloglog::COMMANDS,
+        geo::COMMANDS,
+        keys::COMMANDS,
+        expire::COMMANDS,
+        dump::COMMANDS,
+        debug::COMMANDS,
+        info::COMMANDS,
+        config::COMMANDS,
+        server::COMMANDS,
+        client::COMMANDS,
+        memory::COMMANDS,
+        latency::COMMANDS,
+        lolwut::COMMANDS,
+        pubsub::COMMANDS,
+        replication::COMMANDS,
+        cluster::COMMANDS,
+        transaction::COMMANDS,
+        scripting::COMMANDS,
+        COMMANDS,
+    ]
+    .concat();
+}
+
+// Looks a command up by name, case-insensitively, among the builtin ones and the ones
+// registered with the backend.
+pub fn lookup_command(backend: &Backend, name: &str) -> Option<CommandSpec> {
+    builtin_command(name)
+        .cloned()
+        .or_else(|| backend.commands().lookup(name))
+}
+
+pub(super) fn builtin_command(name: &str) -> Option<&'static CommandSpec> {
+    REGISTRY
+        .iter()
+        .find(|spec| spec.name.eq_ignore_ascii_case(name))
+}
+
+// The builtin commands followed by the ones registered with the backend.
+pub fn all_commands(backend: &Backend) -> Vec<CommandSpec> {
+    REGISTRY
+        .iter()
+        .cloned()
+        .chain(backend.commands().specs())
+        .collect()
+}
+
+impl CommandExecutor for CommandMeta {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match self.query {
+            CommandQuery::List => {
+                let specs = all_commands(backend).into_iter();
+                let specs = specs.map(|spec| spec.info_reply());
+                RespArray::new(specs.collect::<Vec<_>>()).into()
+            }
+            CommandQuery::Count => RespFrame::Integer(all_commands(backend).len() as i64),
+            CommandQuery::Info(names) => {
+                let specs = names
+                    .iter()
+                    .map(|name| match lookup_command(backend, name) {
+                        Some(spec) => spec.info_reply(),
+                        None => RespFrame::Null(RespNull),
+                    });
+                RespArray::new(specs.collect::<Vec<_>>()).into()
+            }
+            CommandQuery::Docs(names) => {
+                let specs: Vec<CommandSpec> = if names.is_empty() {
+                    all_commands(backend)
+                } else {
+                    names
+                        .iter()
+                        .filter_map(|name| lookup_command(backend, name))
+                        .collect()
+                };
+                let mut docs = Vec::with_capacity(specs.len() * 2);
+                for spec in specs {
+                    docs.push(BulkString::from(spec.name).into());
+                    docs.push(spec.docs_reply());
+                }
+                RespArray::new(docs).into()
+            }
+            CommandQuery::GetKeys(args) => {
+                let spec = match lookup_command(backend, &args[0]) {
+                    Some(spec) => spec,
+                    None => return SimpleError::new("ERR Invalid command specified").into(),
+                };
+                if !spec.has_keys() {
+                    return SimpleError::new("ERR The command has no key arguments").into();
+                }
+                let argc = args.len() as i64;
+                if (spec.arity > 0 && spec.arity != argc) || argc < -spec.arity {
+                    return SimpleError::new(
+                        "ERR Invalid number of arguments specified for command",
+                    )
+                    .into();
+                }
+                match spec.key_positions(&args) {
+                    Some(positions) if !positions.is_empty() => {
+                        let keys = positions
+                            .into_iter()
+                            .map(|i| BulkString::from(args[i].clone()).into());
+                        RespArray::new(keys.collect::<Vec<_>>()).into()
+                    }
+                    _ => SimpleError::new("ERR Invalid arguments specified for command").into(),
+                }
+            }
+        }
+    }
+}
+
+impl TryFrom<RespArray> for CommandMeta {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = match args.next() {
+            Some(subcommand) => extract_string(Some(subcommand))?.to_ascii_lowercase(),
+            None => {
+                return Ok(CommandMeta {
+                    query: CommandQuery::List,
+                })
+            }
+        };
+        let names = args
+            .map(|name| extract_string(Some(name)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let query = match subcommand.as_str() {
+            "count" if names.is_empty() => CommandQuery::Count,
+            "info" => CommandQuery::Info(names),
+            "docs" => CommandQuery::Docs(names),
+            "getkeys" if !names.is_empty() => CommandQuery::GetKeys(names),
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for '{subcommand}'"
+                )))
+            }
+        };
+        Ok(CommandMeta { query })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::Command;
+    use crate::{Backend, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_registry_matches_dispatch() {
+        let commands = all_commands(&Backend::new());
+        for spec in &commands {
+            let frame = RespArray::new([BulkString::from(spec.name).into()]);
+            let result = Command::try_from(frame);
+            assert!(
+                !matches!(result, Ok(Command::Unrecognized(_))),
+                "{} is registered but not dispatched",
+                spec.name
+            );
+            assert!(!spec.flags.is_empty(), "{} has no flags", spec.name);
+            assert_eq!(
+                spec.flags.contains(&"movablekeys"),
+                spec.movable_keys.is_some(),
+                "{} has movable keys but no key search, or the other way round",
+                spec.name
+            );
+        }
+        let mut names = commands.iter().map(|s| s.name).collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), commands.len());
+    }
+
+    #[test]
+    fn test_command_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$7\r\nCOMMAND\r\n$4\r\nINFO\r\n$3\r\nget\r\n$3\r\nset\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: CommandMeta = frame.try_into()?;
+        assert_eq!(
+            result.query,
+            CommandQuery::Info(vec!["get".to_string(), "set".to_string()])
+        );
+
+        buf.extend_from_slice(b"*1\r\n$7\r\nCOMMAND\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: CommandMeta = frame.try_into()?;
+        assert_eq!(result.query, CommandQuery::List);
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_command() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = CommandMeta {
+            query: CommandQuery::Count,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespFrame::Integer(all_commands(&backend).len() as i64)
+        );
+
+        let cmd = CommandMeta {
+            query: CommandQuery::Info(vec!["GET".to_string(), "nosuchcommand".to_string()]),
+        };
+        let expected = RespArray::new([
+            RespArray::new([
+                BulkString::from("get").into(),
+                RespFrame::Integer(2),
+                RespArray::new([
+                    SimpleString::new("readonly").into(),
+                    SimpleString::new("fast").into(),
+                ])
+                .into(),
+                RespFrame::Integer(1),
+                RespFrame::Integer(1),
+                RespFrame::Integer(1),
+                RespArray::new([SimpleString::new("@string").into()]).into(),
+            ])
+            .into(),
+            RespFrame::Null(RespNull),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = CommandMeta {
+            query: CommandQuery::Docs(vec!["get".to_string()]),
+        };
+        let expected = RespArray::new([
+            BulkString::from("get").into(),
+            RespArray::new([
+                BulkString::from("summary").into(),
+                BulkString::from("Returns the string value of a key.").into(),
+                BulkString::from("group").into(),
+                BulkString::from("string").into(),
+            ])
+            .into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+        Ok(())
+    }
+
+    fn getkeys(args: &str) -> RespFrame {
+        let args = args.split(' ').map(|s| s.to_string()).collect();
+        let cmd = CommandMeta {
+            query: CommandQuery::GetKeys(args),
+        };
+        cmd.execute(&Backend::new())
+    }
+
+    fn keys(keys: &[&str]) -> RespFrame {
+        let keys = keys.iter().map(|k| BulkString::from(*k).into());
+        RespArray::new(keys.collect::<Vec<_>>()).into()
+    }
+
+    #[test]
+    fn test_command_getkeys() {
+        assert_eq!(
+            getkeys("mset a 1 b 2"),
+            SimpleError::new("ERR Invalid command specified").into()
+        );
+        assert_eq!(getkeys("set key value"), keys(&["key"]));
+        assert_eq!(getkeys("del a b c"), keys(&["a", "b", "c"]));
+        assert_eq!(getkeys("object encoding key"), keys(&["key"]));
+        assert_eq!(
+            getkeys("zunionstore out 2 a b weights 1 2"),
+            keys(&["out", "a", "b"])
+        );
+        assert_eq!(getkeys("blmpop 0 2 a b left"), keys(&["a", "b"]));
+        assert_eq!(getkeys("blpop a b 0"), keys(&["a", "b"]));
+        assert_eq!(getkeys("blmove a b left right 0"), keys(&["a", "b"]));
+        assert_eq!(getkeys("bzpopmin a b 0"), keys(&["a", "b"]));
+        assert_eq!(getkeys("xread count 2 streams a b 0 $"), keys(&["a", "b"]));
+        assert_eq!(getkeys("migrate h 6379 a 0 10 copy"), keys(&["a"]));
+        // the key is empty, between the two spaces
+        assert_eq!(
+            getkeys("migrate h 6379  0 10 replace keys a b"),
+            keys(&["a", "b"])
+        );
+        assert_eq!(
+            getkeys("georadius src 0 0 1 km STORE dst"),
+            keys(&["src", "dst"])
+        );
+        assert_eq!(
+            getkeys("georadiusbymember src m 1 km storedist dst"),
+            keys(&["src", "dst"])
+        );
+        assert_eq!(getkeys("georadius src 0 0 1 km withdist"), keys(&["src"]));
+        assert_eq!(
+            getkeys("ping"),
+            SimpleError::new("ERR The command has no key arguments").into()
+        );
+        assert_eq!(
+            getkeys("get"),
+            SimpleError::new("ERR Invalid number of arguments specified for command").into()
+        );
+        assert_eq!(
+            getkeys("lmpop 4 a b left"),
+            SimpleError::new("ERR Invalid arguments specified for command").into()
+        );
+    }
+
+    #[test]
+    fn test_keyword_key_search() {
+        let spec = CommandSpec::new("georadius", -6, "geo", "")
+            .keys(1, 1, 1)
+            .movable_keys(KeySearch::Keyword(&["store", "storedist"]));
+        let args = "georadius key 0 0 1 km STORE out"
+            .split(' ')
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(spec.key_positions(&args), Some(vec![1, 7]));
+        assert_eq!(spec.key_positions(&args[..6]), Some(vec![1]));
+    }
+}
@@ -0,0 +1,148 @@
+use super::{
+    command::CommandSpec, extract_args, extract_string, validate_variadic_command, CommandError,
+    CommandExecutor, Config, ConfigSubcommand, RESP_OK,
+};
+use crate::{BulkString, RespArray, RespFrame, SimpleError};
+
+pub(super) const COMMANDS: &[CommandSpec] = &[CommandSpec::new(
+    "config",
+    -2,
+    "server",
+    "A container for server configuration commands.",
+)
+.flags(&["admin", "noscript", "loading", "stale"])];
+
+impl CommandExecutor for Config {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match self.subcommand {
+            ConfigSubcommand::Get(patterns) => {
+                let params = backend.config().get(&patterns);
+                let mut reply = Vec::with_capacity(params.len() * 2);
+                for (name, value) in params {
+                    reply.push(BulkString::from(name).into());
+                    reply.push(BulkString::from(value).into());
+                }
+                RespArray::new(reply).into()
+            }
+            ConfigSubcommand::Set(pairs) => match backend.config().set(&pairs) {
+                Ok(()) => {
+                    // turning appendonly on again starts over from a rewrite
+                    if !backend.append_only() {
+                        backend.append_log().close();
+                    }
+                    RESP_OK.clone()
+                }
+                Err(e) => SimpleError::new(format!("ERR {e}")).into(),
+            },
+            ConfigSubcommand::ResetStat => {
+                backend.stats().reset();
+                RESP_OK.clone()
+            }
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Config {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "config", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_lowercase();
+        let args = args
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let subcommand = match subcommand.as_str() {
+            "get" if !args.is_empty() => ConfigSubcommand::Get(args),
+            "set" if !args.is_empty() && args.len() % 2 == 0 => ConfigSubcommand::Set(
+                args.chunks(2)
+                    .map(|pair| (pair[0].clone(), pair[1].clone()))
+                    .collect(),
+            ),
+            "resetstat" if args.is_empty() => ConfigSubcommand::ResetStat,
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for '{subcommand}'"
+                )))
+            }
+        };
+        Ok(Config { subcommand })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_config_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\nCONFIG\r\n$3\r\nSET\r\n$7\r\ntimeout\r\n$2\r\n30\r\n$2\r\nhz\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Config, _> = frame.try_into();
+        assert!(result.is_err());
+
+        buf.extend_from_slice(b"*4\r\n$6\r\nconfig\r\n$3\r\nget\r\n$2\r\nhz\r\n$4\r\nsave\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Config = frame.try_into()?;
+        assert_eq!(
+            result.subcommand,
+            ConfigSubcommand::Get(vec!["hz".to_string(), "save".to_string()])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_command() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = Config {
+            subcommand: ConfigSubcommand::Set(vec![
+                ("maxmemory".to_string(), "1mb".to_string()),
+                ("maxmemory-policy".to_string(), "allkeys-lru".to_string()),
+            ]),
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+
+        let cmd = Config {
+            subcommand: ConfigSubcommand::Get(vec!["maxmemory*".to_string()]),
+        };
+        let expected = RespArray::new([
+            BulkString::from("maxmemory").into(),
+            BulkString::from("1048576").into(),
+            BulkString::from("maxmemory-policy").into(),
+            BulkString::from("allkeys-lru").into(),
+            BulkString::from("maxmemory-samples").into(),
+            BulkString::from("5").into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = Config {
+            subcommand: ConfigSubcommand::Set(vec![("nosuchparam".to_string(), "1".to_string())]),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new(
+                "ERR Unknown option or number of arguments for CONFIG SET - 'nosuchparam'"
+            )
+            .into()
+        );
+        assert_eq!(backend.stats().total_error_replies(), 0);
+
+        backend
+            .stats()
+            .command_rejected("set", "OOM command not allowed");
+        let cmd = Config {
+            subcommand: ConfigSubcommand::ResetStat,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert!(backend.stats().command_stats().is_empty());
+        assert_eq!(backend.stats().total_error_replies(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+use super::{
+    command::CommandSpec, extract_args, extract_float, extract_integer, extract_key,
+    extract_string, validate_variadic_command, CommandError, CommandExecutor, DebugCommand,
+    DebugSubcommand, RESP_OK,
+};
+use crate::{RespArray, RespFrame, SimpleError, SimpleString};
+use std::time::Duration;
+
+pub(super) const COMMANDS: &[CommandSpec] =
+    &[
+        CommandSpec::new("debug", -2, "server", "A container for debugging commands.")
+            .flags(&["admin", "noscript"]),
+    ];
+
+impl CommandExecutor for DebugCommand {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match self.subcommand {
+            DebugSubcommand::Object(key) => {
+                let (Some(encoding), Some(payload)) = (backend.encoding(&key), backend.dump(&key))
+                else {
+                    return SimpleError::new("ERR no such key").into();
+                };
+                let idle = backend.idle_time(&key).unwrap_or_default() / 1000;
+                SimpleString::new(format!(
+                    "refcount:1 encoding:{} serializedlength:{} lru_seconds_idle:{}",
+                    encoding,
+                    payload.len(),
+                    idle
+                ))
+                .into()
+            }
+            // blocks the worker thread on purpose, to simulate a slow server
+            DebugSubcommand::Sleep(secs) => {
+                std::thread::sleep(Duration::from_secs_f64(secs));
+                RESP_OK.clone()
+            }
+            DebugSubcommand::SetActiveExpire(enabled) => {
+                backend.set_active_expire(enabled);
+                RESP_OK.clone()
+            }
+        }
+    }
+}
+
+impl TryFrom<RespArray> for DebugCommand {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "debug", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_lowercase();
+        let subcommand = match (subcommand.as_str(), args.next(), args.next()) {
+            ("object", Some(key), None) => DebugSubcommand::Object(extract_key(Some(key))?),
+            ("sleep", Some(secs), None) => match extract_float(Some(secs))? {
+                secs if secs >= 0.0 && secs.is_finite() => DebugSubcommand::Sleep(secs),
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "sleep time is out of range".to_string(),
+                    ))
+                }
+            },
+            ("set-active-expire", Some(flag), None) => {
+                DebugSubcommand::SetActiveExpire(extract_integer(Some(flag))? != 0)
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for '{subcommand}'"
+                )))
+            }
+        };
+        Ok(DebugCommand { subcommand })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, BulkString, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_debug_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$5\r\nDEBUG\r\n$5\r\nSLEEP\r\n$3\r\n0.5\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: DebugCommand = frame.try_into()?;
+        assert_eq!(result.subcommand, DebugSubcommand::Sleep(0.5));
+
+        buf.extend_from_slice(b"*3\r\n$5\r\nDEBUG\r\n$17\r\nSET-ACTIVE-EXPIRE\r\n$1\r\n0\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: DebugCommand = frame.try_into()?;
+        assert_eq!(result.subcommand, DebugSubcommand::SetActiveExpire(false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"mykey".to_vec(), BulkString::from("Hello").into());
+
+        let cmd = DebugCommand {
+            subcommand: DebugSubcommand::Object(b"mykey".to_vec()),
+        };
+        match cmd.execute(&backend) {
+            RespFrame::SimpleString(s) => {
+                assert!(s.contains("encoding:embstr"));
+                assert!(s.contains("lru_seconds_idle:0"));
+            }
+            frame => panic!("unexpected reply: {frame:?}"),
+        }
+
+        let cmd = DebugCommand {
+            subcommand: DebugSubcommand::Object(b"nokey".to_vec()),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR no such key").into()
+        );
+
+        let cmd = DebugCommand {
+            subcommand: DebugSubcommand::SetActiveExpire(false),
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert!(!backend.active_expire_enabled());
+        Ok(())
+    }
+}
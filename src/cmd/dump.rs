@@ -0,0 +1,440 @@
+use super::{
+    command::{CommandSpec, KeySearch},
+    extract_args, extract_integer, extract_key, extract_string, validate_command,
+    validate_variadic_command, CommandError, CommandExecutor, Dump, Migrate, Restore, RESP_OK,
+};
+use crate::{
+    network::RespFrameCodec, replica::command, Backend, BulkString, DumpError, KeyExpiry,
+    NotifyClass, RespArray, RespFrame, RespNull, SimpleError, SimpleString,
+};
+use futures::SinkExt;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_stream::StreamExt;
+use tokio_util::codec::Framed;
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new(
+        "dump",
+        2,
+        "generic",
+        "Returns a serialized representation of the value stored at a key.",
+    )
+    .flags(&["readonly"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "restore",
+        -4,
+        "generic",
+        "Creates a key from the serialized representation of a value.",
+    )
+    .flags(&["write"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "migrate",
+        -6,
+        "generic",
+        "Atomically transfers a key from one Redis instance to another.",
+    )
+    .flags(&["write", "noscript", "movablekeys"])
+    .movable_keys(KeySearch::KeyOrKeywordRest(3, "keys")),
+];
+
+// a timeout of 0 or less stands for this many milliseconds
+const DEFAULT_MIGRATE_TIMEOUT: u64 = 1000;
+
+impl CommandExecutor for Dump {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.dump(&self.key) {
+            Some(payload) => BulkString::new(payload).into(),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for Restore {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let expire_at = match (self.ttl, self.absttl) {
+            (0, _) => None,
+            (ttl, true) => Some(ttl),
+            (ttl, false) => Some(ttl.saturating_add(backend.now_ms())),
+        };
+        let idle = self.idletime.map(|secs| secs.saturating_mul(1000));
+        match backend.restore(&self.key, &self.payload, expire_at, self.replace, idle) {
+            Ok(()) => {
+                self.notify(backend, NotifyClass::Generic, "restore", &self.key);
+                RESP_OK.clone()
+            }
+            Err(e @ DumpError::BusyKey) => SimpleError::new(format!("BUSYKEY {e}")).into(),
+            Err(e) => SimpleError::new(format!("ERR {e}")).into(),
+        }
+    }
+}
+
+// The network layer runs MIGRATE itself, with exclusive access to the backend, see
+// execute_blocking.
+impl CommandExecutor for Migrate {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        SimpleError::new("ERR MIGRATE can't wait for the target inside a transaction").into()
+    }
+}
+
+impl Migrate {
+    // Sends the keys that exist to the target with RESTORE, then deletes those it took unless
+    // COPY. Nothing else runs meanwhile, no client sees a key in both places or in neither.
+    pub async fn execute_blocking(self, backend: &Backend) -> RespFrame {
+        let Ok(port @ 1..) = u16::try_from(self.port) else {
+            return SimpleError::new("ERR Invalid port").into();
+        };
+        let Ok(db) = usize::try_from(self.db) else {
+            return SimpleError::new("ERR invalid DB index").into();
+        };
+        let now = backend.now_ms();
+        let dumps = self
+            .keys
+            .iter()
+            .filter_map(|key| {
+                let payload = backend.dump(key)?;
+                let ttl = match backend.expiry(key) {
+                    KeyExpiry::At(when) => (when - now).max(1),
+                    _ => 0,
+                };
+                Some((key, ttl, payload))
+            })
+            .collect::<Vec<_>>();
+        if dumps.is_empty() {
+            return SimpleString::new("NOKEY").into();
+        }
+        let limit = match u64::try_from(self.timeout) {
+            Ok(ms @ 1..) => Duration::from_millis(ms),
+            _ => Duration::from_millis(DEFAULT_MIGRATE_TIMEOUT),
+        };
+        let connected = timeout(limit, TcpStream::connect((self.host.as_str(), port))).await;
+        let Ok(Ok(stream)) = connected else {
+            return SimpleError::new("IOERR error or timeout connecting to the client").into();
+        };
+        let mut framed = Framed::new(stream, RespFrameCodec);
+        if db != 0 {
+            let select = command(&["SELECT", &db.to_string()]);
+            match exchange(&mut framed, vec![select], limit).await {
+                Ok(replies) => {
+                    if let Some(RespFrame::Error(e)) = replies.into_iter().next() {
+                        return target_error(e.0);
+                    }
+                }
+                Err(error) => return error,
+            }
+        }
+        let restores = dumps.iter().map(|(key, ttl, payload)| {
+            let mut args = vec![
+                BulkString::from("RESTORE").into(),
+                BulkString::from(key.as_slice()).into(),
+                BulkString::from(ttl.to_string()).into(),
+                BulkString::new(payload.clone()).into(),
+            ];
+            if self.replace {
+                args.push(BulkString::from("REPLACE").into());
+            }
+            RespArray::new(args).into()
+        });
+        let replies = match exchange(&mut framed, restores.collect(), limit).await {
+            Ok(replies) => replies,
+            Err(error) => return error,
+        };
+        // on an error every key stays here, a failed MIGRATE is neither logged nor replicated
+        for reply in replies {
+            if let RespFrame::Error(e) = reply {
+                return target_error(e.0);
+            }
+        }
+        if !self.copy {
+            for (key, _, _) in dumps {
+                if backend.del(key) {
+                    self.notify(backend, NotifyClass::Generic, "del", key);
+                }
+            }
+        }
+        RESP_OK.clone()
+    }
+}
+
+// Sends the requests to the target in one go and returns its replies, or the error to reply
+// with when it doesn't answer within `limit` each time.
+async fn exchange(
+    framed: &mut Framed<TcpStream, RespFrameCodec>,
+    requests: Vec<RespFrame>,
+    limit: Duration,
+) -> Result<Vec<RespFrame>, RespFrame> {
+    let count = requests.len();
+    for request in requests {
+        if !matches!(timeout(limit, framed.send(request)).await, Ok(Ok(()))) {
+            return Err(
+                SimpleError::new("IOERR error or timeout writing to target instance").into(),
+            );
+        }
+    }
+    let mut replies = vec![];
+    for _ in 0..count {
+        match timeout(limit, framed.next()).await {
+            Ok(Some(Ok(reply))) => replies.push(reply),
+            _ => {
+                return Err(
+                    SimpleError::new("IOERR error or timeout reading to target instance").into(),
+                )
+            }
+        }
+    }
+    Ok(replies)
+}
+
+fn target_error(error: String) -> RespFrame {
+    SimpleError::new(format!("ERR Target instance replied with error: {error}")).into()
+}
+
+impl TryFrom<RespArray> for Dump {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["dump"], 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(Dump {
+            key: extract_key(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Restore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "restore", 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next())?;
+        let ttl = extract_integer(args.next())?;
+        if ttl < 0 {
+            return Err(CommandError::InvalidArgument(
+                "Invalid TTL value, must be >= 0".to_string(),
+            ));
+        }
+        // the payload is binary, it is not necessarily valid UTF-8
+        let payload = match args.next() {
+            Some(RespFrame::BulkString(payload)) => payload.0,
+            _ => return Err(CommandError::InvalidArgument("Invalid payload".to_string())),
+        };
+
+        let mut cmd = Restore {
+            key,
+            ttl,
+            payload,
+            replace: false,
+            absttl: false,
+            idletime: None,
+        };
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg))?.to_ascii_lowercase().as_str() {
+                "replace" => cmd.replace = true,
+                "absttl" => cmd.absttl = true,
+                "idletime" => match extract_integer(args.next())? {
+                    secs if secs >= 0 => cmd.idletime = Some(secs),
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "Invalid IDLETIME value, must be >= 0".to_string(),
+                        ))
+                    }
+                },
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(cmd)
+    }
+}
+
+impl TryFrom<RespArray> for Migrate {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "migrate", 5)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let host = extract_string(args.next())?;
+        let port = extract_integer(args.next())?;
+        let key = extract_key(args.next())?;
+        let db = extract_integer(args.next())?;
+        let timeout = extract_integer(args.next())?;
+
+        let mut cmd = Migrate {
+            host,
+            port,
+            keys: vec![],
+            db,
+            timeout,
+            copy: false,
+            replace: false,
+        };
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg))?.to_ascii_lowercase().as_str() {
+                "copy" => cmd.copy = true,
+                "replace" => cmd.replace = true,
+                "keys" if !key.is_empty() => {
+                    return Err(CommandError::InvalidArgument(
+                        "When using MIGRATE KEYS option, the key argument must be set to the empty string"
+                            .to_string(),
+                    ))
+                }
+                "keys" => {
+                    cmd.keys = args
+                        .by_ref()
+                        .map(|arg| extract_key(Some(arg)))
+                        .collect::<Result<_, _>>()?
+                }
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        if !key.is_empty() {
+            cmd.keys.push(key);
+        }
+        Ok(cmd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{network, Backend, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_restore_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*7\r\n$7\r\nRESTORE\r\n$5\r\nmykey\r\n$3\r\n100\r\n$3\r\n\x00\xff\x01\r\n$7\r\nREPLACE\r\n$8\r\nIDLETIME\r\n$2\r\n10\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Restore = frame.try_into()?;
+        assert_eq!(result.key, b"mykey");
+        assert_eq!(result.ttl, 100);
+        assert_eq!(result.payload, vec![0x00, 0xff, 0x01]);
+        assert!(result.replace);
+        assert!(!result.absttl);
+        assert_eq!(result.idletime, Some(10));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dump_restore_commands() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"mykey".to_vec(), BulkString::from("10").into());
+
+        let cmd = Dump {
+            key: b"mykey".to_vec(),
+        };
+        let payload = match cmd.execute(&backend) {
+            RespFrame::BulkString(payload) => payload.0,
+            frame => panic!("unexpected reply: {frame:?}"),
+        };
+
+        let restore = |payload: Vec<u8>, replace| {
+            Restore {
+                key: b"mykey".to_vec(),
+                ttl: 0,
+                payload,
+                replace,
+                absttl: false,
+                idletime: None,
+            }
+            .execute(&backend)
+        };
+        assert_eq!(
+            restore(payload.clone(), false),
+            SimpleError::new("BUSYKEY Target key name already exists.").into()
+        );
+        assert_eq!(restore(payload.clone(), true), RESP_OK.clone());
+        assert_eq!(backend.get(b"mykey")?, Some(BulkString::from("10").into()));
+        assert_eq!(
+            restore(b"garbage".to_vec(), true),
+            SimpleError::new("ERR DUMP payload version or checksum are wrong").into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*10\r\n$7\r\nMIGRATE\r\n$9\r\n127.0.0.1\r\n$4\r\n6380\r\n$0\r\n\r\n$1\r\n2\r\n$4\r\n5000\r\n$4\r\nCOPY\r\n$4\r\nKEYS\r\n$1\r\na\r\n$1\r\nb\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Migrate = frame.try_into()?;
+        assert_eq!((result.host.as_str(), result.port), ("127.0.0.1", 6380));
+        assert_eq!(result.keys, [b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!((result.db, result.timeout), (2, 5000));
+        assert!(result.copy && !result.replace);
+
+        buf.extend_from_slice(b"*8\r\n$7\r\nMIGRATE\r\n$9\r\n127.0.0.1\r\n$4\r\n6380\r\n$1\r\nk\r\n$1\r\n0\r\n$1\r\n0\r\n$4\r\nKEYS\r\n$1\r\na\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(Migrate::try_from(frame).is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_migrate_command() -> Result<()> {
+        let target = Backend::new();
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let accepting = target.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(network::handle_stream(stream, accepting.clone()));
+            }
+        });
+
+        let backend = Backend::new();
+        for key in ["a", "b", "c"].map(str::as_bytes) {
+            backend.set(key.to_vec(), BulkString::from(key).into());
+        }
+        let migrate = |keys: &[&str], copy| Migrate {
+            host: "127.0.0.1".to_string(),
+            port: port.into(),
+            keys: keys.iter().map(|key| key.as_bytes().to_vec()).collect(),
+            db: 0,
+            timeout: 1000,
+            copy,
+            replace: false,
+        };
+        assert_eq!(
+            migrate(&["a"], false).execute_blocking(&backend).await,
+            RESP_OK.clone()
+        );
+        assert_eq!(backend.get(b"a")?, None);
+        assert_eq!(target.get(b"a")?, Some(BulkString::from("a").into()));
+
+        assert_eq!(
+            migrate(&["b", "missing"], true)
+                .execute_blocking(&backend)
+                .await,
+            RESP_OK.clone()
+        );
+        assert_eq!(backend.get(b"b")?, Some(BulkString::from("b").into()));
+        assert_eq!(target.get(b"b")?, Some(BulkString::from("b").into()));
+
+        // without REPLACE, the key the target already has is kept on both sides
+        let reply = migrate(&["b", "c"], false).execute_blocking(&backend).await;
+        assert!(matches!(reply, RespFrame::Error(e) if e.0.contains("BUSYKEY")));
+        assert_eq!(backend.get(b"b")?, Some(BulkString::from("b").into()));
+        assert_eq!(
+            migrate(&["missing"], false)
+                .execute_blocking(&backend)
+                .await,
+            SimpleString::new("NOKEY").into()
+        );
+
+        let mut cmd = migrate(&["c"], false);
+        cmd.db = 3;
+        assert_eq!(cmd.execute_blocking(&backend).await, RESP_OK.clone());
+        assert_eq!(backend.get(b"c")?, None);
+        assert!(target.select(3));
+        assert_eq!(target.get(b"c")?, Some(BulkString::from("c").into()));
+        Ok(())
+    }
+}
@@ -0,0 +1,394 @@
+use super::{
+    command::CommandSpec, extract_args, extract_integer, extract_key, extract_string,
+    validate_command, validate_variadic_command, CommandError, CommandExecutor, Expire, Persist,
+    Ttl,
+};
+use crate::{ExpireCondition, KeyExpiry, NotifyClass, RespArray, RespFrame, SimpleError};
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new(
+        "expire",
+        -3,
+        "generic",
+        "Sets the expiration time of a key in seconds.",
+    )
+    .flags(&["write", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "pexpire",
+        -3,
+        "generic",
+        "Sets the expiration time of a key in milliseconds.",
+    )
+    .flags(&["write", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "expireat",
+        -3,
+        "generic",
+        "Sets the expiration time of a key to a Unix timestamp.",
+    )
+    .flags(&["write", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "pexpireat",
+        -3,
+        "generic",
+        "Sets the expiration time of a key to a Unix milliseconds timestamp.",
+    )
+    .flags(&["write", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "ttl",
+        2,
+        "generic",
+        "Returns the expiration time in seconds of a key.",
+    )
+    .flags(&["readonly", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "pttl",
+        2,
+        "generic",
+        "Returns the expiration time in milliseconds of a key.",
+    )
+    .flags(&["readonly", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "expiretime",
+        2,
+        "generic",
+        "Returns the expiration time of a key as a Unix timestamp.",
+    )
+    .flags(&["readonly", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "pexpiretime",
+        2,
+        "generic",
+        "Returns the expiration time of a key as a Unix milliseconds timestamp.",
+    )
+    .flags(&["readonly", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "persist",
+        2,
+        "generic",
+        "Removes the expiration time of a key.",
+    )
+    .flags(&["write", "fast"])
+    .keys(1, 1, 1),
+];
+
+impl CommandExecutor for Expire {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let when = if self.absolute {
+            Some(self.time)
+        } else {
+            self.time.checked_add(backend.now_ms())
+        };
+        match when {
+            Some(when) => {
+                let updated = backend.expire_at(&self.key, when, self.condition);
+                if updated {
+                    // an expiry time already passed deletes the key
+                    let event = if backend.exists(&self.key) {
+                        "expire"
+                    } else {
+                        "del"
+                    };
+                    self.notify(backend, NotifyClass::Generic, event, &self.key);
+                }
+                RespFrame::Integer(updated as i64)
+            }
+            None => invalid_expire_time(self.name),
+        }
+    }
+}
+
+impl CommandExecutor for Ttl {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let reply = match backend.expiry(&self.key) {
+            KeyExpiry::Missing => -2,
+            KeyExpiry::Persistent => -1,
+            KeyExpiry::At(when) if self.absolute => when / self.unit,
+            // rounded to the closest unit like in redis
+            KeyExpiry::At(when) => ((when - backend.now_ms()).max(0) + self.unit / 2) / self.unit,
+        };
+        RespFrame::Integer(reply)
+    }
+}
+
+impl CommandExecutor for Persist {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let persisted = backend.persist(&self.key);
+        if persisted {
+            self.notify(backend, NotifyClass::Generic, "persist", &self.key);
+        }
+        RespFrame::Integer(persisted as i64)
+    }
+}
+
+fn invalid_expire_time(name: &str) -> RespFrame {
+    SimpleError::new(format!("ERR invalid expire time in '{name}' command")).into()
+}
+
+// (name, milliseconds per unit, absolute)
+const EXPIRE_COMMANDS: [(&str, i64, bool); 4] = [
+    ("expire", 1000, false),
+    ("pexpire", 1, false),
+    ("expireat", 1000, true),
+    ("pexpireat", 1, true),
+];
+
+impl TryFrom<RespArray> for Expire {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = match value.first() {
+            Some(RespFrame::BulkString(cmd)) => String::from_utf8_lossy(cmd).to_ascii_lowercase(),
+            _ => String::new(),
+        };
+        let (name, unit, absolute) = EXPIRE_COMMANDS
+            .into_iter()
+            .find(|(n, _, _)| *n == name)
+            .ok_or_else(|| CommandError::InvalidCommand(format!("Invalid command: {name}")))?;
+        validate_variadic_command(&value, name, 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next())?;
+        let time = extract_integer(args.next())?
+            .checked_mul(unit)
+            .ok_or_else(|| {
+                CommandError::InvalidArgument(format!("invalid expire time in '{name}' command"))
+            })?;
+        let condition = extract_expire_condition(args)?;
+        Ok(Expire {
+            key,
+            time,
+            absolute,
+            condition,
+            name,
+        })
+    }
+}
+
+// (name, milliseconds per unit, absolute)
+const TTL_COMMANDS: [(&str, i64, bool); 4] = [
+    ("ttl", 1000, false),
+    ("pttl", 1, false),
+    ("expiretime", 1000, true),
+    ("pexpiretime", 1, true),
+];
+
+impl TryFrom<RespArray> for Ttl {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = match value.first() {
+            Some(RespFrame::BulkString(cmd)) => String::from_utf8_lossy(cmd).to_ascii_lowercase(),
+            _ => String::new(),
+        };
+        let (name, unit, absolute) = TTL_COMMANDS
+            .into_iter()
+            .find(|(n, _, _)| *n == name)
+            .ok_or_else(|| CommandError::InvalidCommand(format!("Invalid command: {name}")))?;
+        validate_command(&value, &[name], 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(Ttl {
+            key: extract_key(args.next())?,
+            unit,
+            absolute,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Persist {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["persist"], 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(Persist {
+            key: extract_key(args.next())?,
+        })
+    }
+}
+
+// [NX | XX | GT | LT], where only GT and LT can be combined with XX (which they imply anyway)
+fn extract_expire_condition(
+    args: impl Iterator<Item = RespFrame>,
+) -> Result<ExpireCondition, CommandError> {
+    let mut condition = ExpireCondition::Always;
+    for arg in args {
+        let next = match extract_string(Some(arg))?.to_ascii_lowercase().as_str() {
+            "nx" => ExpireCondition::Nx,
+            "xx" => ExpireCondition::Xx,
+            "gt" => ExpireCondition::Gt,
+            "lt" => ExpireCondition::Lt,
+            option => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Unsupported option {option}"
+                )))
+            }
+        };
+        condition = match (condition, next) {
+            (ExpireCondition::Always, next) => next,
+            (current, next) if current == next => current,
+            (ExpireCondition::Xx, gt_or_lt @ (ExpireCondition::Gt | ExpireCondition::Lt)) => {
+                gt_or_lt
+            }
+            (gt_or_lt @ (ExpireCondition::Gt | ExpireCondition::Lt), ExpireCondition::Xx) => {
+                gt_or_lt
+            }
+            (ExpireCondition::Gt, ExpireCondition::Lt)
+            | (ExpireCondition::Lt, ExpireCondition::Gt) => {
+                return Err(CommandError::InvalidArgument(
+                    "GT and LT options at the same time are not compatible".to_string(),
+                ))
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "NX and XX, GT or LT options at the same time are not compatible".to_string(),
+                ))
+            }
+        };
+    }
+    Ok(condition)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, BulkString, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_expire_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\nEXPIRE\r\n$5\r\nmykey\r\n$2\r\n10\r\n$2\r\nXX\r\n$2\r\nGT\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Expire = frame.try_into()?;
+        assert_eq!(result.key, b"mykey");
+        assert_eq!(result.time, 10_000);
+        assert!(!result.absolute);
+        assert_eq!(result.condition, ExpireCondition::Gt);
+
+        buf.extend_from_slice(
+            b"*4\r\n$9\r\nPEXPIREAT\r\n$5\r\nmykey\r\n$13\r\n1555555555005\r\n$2\r\nnx\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Expire = frame.try_into()?;
+        assert_eq!(result.time, 1555555555005);
+        assert!(result.absolute);
+        assert_eq!(result.condition, ExpireCondition::Nx);
+
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\nEXPIRE\r\n$5\r\nmykey\r\n$2\r\n10\r\n$2\r\nNX\r\n$2\r\nGT\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Expire, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_expire_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"mykey".to_vec(), BulkString::from("Hello").into());
+
+        let cmd = Expire {
+            key: b"mykey".to_vec(),
+            time: 10_000,
+            absolute: false,
+            condition: ExpireCondition::Xx,
+            name: "expire",
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+
+        let cmd = Expire {
+            key: b"mykey".to_vec(),
+            time: 10_000,
+            absolute: false,
+            condition: ExpireCondition::Nx,
+            name: "expire",
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert!(backend.exists(b"mykey"));
+
+        let cmd = Expire {
+            key: b"mykey".to_vec(),
+            time: i64::MAX,
+            absolute: false,
+            condition: ExpireCondition::Always,
+            name: "pexpire",
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR invalid expire time in 'pexpire' command").into()
+        );
+
+        let cmd = Expire {
+            key: b"mykey".to_vec(),
+            time: 1555555555005,
+            absolute: true,
+            condition: ExpireCondition::Always,
+            name: "pexpireat",
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert!(!backend.exists(b"mykey"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ttl_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$10\r\nEXPIRETIME\r\n$5\r\nmykey\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Ttl = frame.try_into()?;
+        assert_eq!(result.key, b"mykey");
+        assert_eq!(result.unit, 1000);
+        assert!(result.absolute);
+
+        buf.extend_from_slice(b"*2\r\n$7\r\nPERSIST\r\n$5\r\nmykey\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Persist = frame.try_into()?;
+        assert_eq!(result.key, b"mykey");
+        Ok(())
+    }
+
+    #[test]
+    fn test_ttl_persist_commands() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"mykey".to_vec(), BulkString::from("Hello").into());
+        let ttl = |key: &str, unit, absolute| {
+            Ttl {
+                key: key.as_bytes().to_vec(),
+                unit,
+                absolute,
+            }
+            .execute(&backend)
+        };
+
+        assert_eq!(ttl("nokey", 1000, false), RespFrame::Integer(-2));
+        assert_eq!(ttl("mykey", 1, true), RespFrame::Integer(-1));
+
+        let when = backend.now_ms() + 10_000;
+        backend.expire_at(b"mykey", when, ExpireCondition::Always);
+        assert_eq!(ttl("mykey", 1000, false), RespFrame::Integer(10));
+        assert_eq!(ttl("mykey", 1000, true), RespFrame::Integer(when / 1000));
+        assert_eq!(ttl("mykey", 1, true), RespFrame::Integer(when));
+
+        let cmd = Persist {
+            key: b"mykey".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(ttl("mykey", 1000, false), RespFrame::Integer(-1));
+        Ok(())
+    }
+}
@@ -0,0 +1,746 @@
+use super::{
+    command::{CommandSpec, KeySearch},
+    extract_args, extract_float, extract_integer, extract_key, extract_string,
+    validate_variadic_command, Command, CommandError, CommandExecutor, GeoAdd, GeoDist, GeoPos,
+    GeoSearch, GeoSearchStore,
+};
+use crate::{
+    geo_distance, geohash_decode, geohash_encode, BulkString, GeoMatch, GeoOrder, GeoOrigin,
+    GeoQuery, GeoShape, GeoUnit, NotifyClass, RespArray, RespFrame, RespNull, SimpleError,
+    WrongType, ZAddFlags, ZAddOutcome,
+};
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new(
+        "geoadd",
+        -5,
+        "geo",
+        "Adds one or more members to a geospatial index. The key is created if it doesn't exist.",
+    )
+    .flags(&["write", "denyoom"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "geopos",
+        -2,
+        "geo",
+        "Returns the longitude and latitude of members from a geospatial index.",
+    )
+    .flags(&["readonly"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "geodist",
+        -4,
+        "geo",
+        "Returns the distance between two members of a geospatial index.",
+    )
+    .flags(&["readonly"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "geosearch",
+        -7,
+        "geo",
+        "Queries a geospatial index for members inside an area of a box or a circle.",
+    )
+    .flags(&["readonly"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "geosearchstore",
+        -8,
+        "geo",
+        "Queries a geospatial index for members inside an area of a box or a circle, optionally stores the result.",
+    )
+    .flags(&["write", "denyoom"])
+    .keys(1, 2, 1),
+    CommandSpec::new(
+        "georadius",
+        -6,
+        "geo",
+        "Queries a geospatial index for members within a distance from a coordinate, optionally stores the result.",
+    )
+    .flags(&["write", "denyoom", "movablekeys"])
+    .keys(1, 1, 1)
+    .movable_keys(KeySearch::Keyword(&["store", "storedist"])),
+    CommandSpec::new(
+        "georadiusbymember",
+        -5,
+        "geo",
+        "Queries a geospatial index for members within a distance from a member, optionally stores the result.",
+    )
+    .flags(&["write", "denyoom", "movablekeys"])
+    .keys(1, 1, 1)
+    .movable_keys(KeySearch::Keyword(&["store", "storedist"])),
+];
+
+impl CommandExecutor for GeoAdd {
+    fn execute(mut self, backend: &crate::Backend) -> RespFrame {
+        let members = std::mem::take(&mut self.members)
+            .into_iter()
+            .filter_map(|(lon, lat, member)| Some((geohash_encode(lon, lat)? as f64, member)))
+            .collect();
+        let outcomes = match backend.zadd(self.key.clone(), members, self.flags) {
+            Ok(outcomes) => outcomes,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        let count = outcomes
+            .iter()
+            .filter(|outcome| match outcome {
+                ZAddOutcome::Added(_) => true,
+                ZAddOutcome::Updated(_) => self.ch,
+                _ => false,
+            })
+            .count();
+        let changed = outcomes
+            .iter()
+            .any(|outcome| matches!(outcome, ZAddOutcome::Added(_) | ZAddOutcome::Updated(_)));
+        if changed {
+            // a geospatial index is a sorted set, GEOADD reports the events of ZADD
+            self.notify(backend, NotifyClass::ZSet, "zadd", &self.key);
+        }
+        RespFrame::Integer(count as i64)
+    }
+}
+
+impl CommandExecutor for GeoPos {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let positions = self
+            .members
+            .iter()
+            .map(|member| {
+                Ok(match backend.zscore(&self.key, member)? {
+                    Some(score) => position_frame(score),
+                    None => RespFrame::Null(RespNull),
+                })
+            })
+            .collect::<Result<Vec<_>, WrongType>>();
+        match positions {
+            Ok(positions) => RespArray::new(positions).into(),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for GeoDist {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let scores = backend.zscore(&self.key, &self.member1).and_then(|score1| {
+            let score2 = backend.zscore(&self.key, &self.member2)?;
+            Ok((score1, score2))
+        });
+        let (score1, score2) = match scores {
+            Ok((Some(score1), Some(score2))) => (score1, score2),
+            Ok(_) => return RespFrame::Null(RespNull),
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        let (lon1, lat1) = geohash_decode(score1 as u64);
+        let (lon2, lat2) = geohash_decode(score2 as u64);
+        let distance = geo_distance(lon1, lat1, lon2, lat2) / self.unit.meters();
+        BulkString::new(format!("{distance:.4}")).into()
+    }
+}
+
+impl CommandExecutor for GeoSearch {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let matches = match backend.geosearch(&self.key, &self.query) {
+            Ok(matches) => matches,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        let items = matches
+            .into_iter()
+            .map(|found| self.match_frame(found))
+            .collect::<Vec<_>>();
+        RespArray::new(items).into()
+    }
+}
+
+impl GeoSearch {
+    // The member alone, or an array of the member and what the WITH options asked for.
+    fn match_frame(&self, found: GeoMatch) -> RespFrame {
+        if !(self.with_dist || self.with_hash || self.with_coord) {
+            return BulkString::new(found.member).into();
+        }
+        let mut item = vec![BulkString::new(found.member).into()];
+        if self.with_dist {
+            let distance = found.distance / self.query.unit.meters();
+            item.push(BulkString::new(format!("{distance:.4}")).into());
+        }
+        if self.with_hash {
+            item.push(RespFrame::Integer(found.hash as i64));
+        }
+        if self.with_coord {
+            item.push(position_frame(found.hash as f64));
+        }
+        RespArray::new(item).into()
+    }
+}
+
+impl CommandExecutor for GeoSearchStore {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let existed = backend.exists(&self.destination);
+        let stored = backend.geosearch_store(
+            self.destination.clone(),
+            &self.source,
+            &self.query,
+            self.store_dist,
+        );
+        match stored {
+            Ok(len) => {
+                if len > 0 {
+                    self.notify(
+                        backend,
+                        NotifyClass::ZSet,
+                        "geosearchstore",
+                        &self.destination,
+                    );
+                } else if existed && !backend.exists(&self.destination) {
+                    // an empty result removes the destination
+                    self.notify(backend, NotifyClass::Generic, "del", &self.destination);
+                }
+                RespFrame::Integer(len as i64)
+            }
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for GeoAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "geoadd", 4)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_key(args.next())?;
+
+        let mut flags = ZAddFlags::default();
+        let mut ch = false;
+        while let Some(RespFrame::BulkString(arg)) = args.peek() {
+            match arg.to_ascii_lowercase().as_slice() {
+                b"nx" => flags.nx = true,
+                b"xx" => flags.xx = true,
+                b"ch" => ch = true,
+                _ => break,
+            }
+            args.next();
+        }
+        if flags.nx && flags.xx {
+            return Err(CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+
+        let args = args.collect::<Vec<_>>();
+        if args.is_empty() || args.len() % 3 != 0 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let mut members = Vec::with_capacity(args.len() / 3);
+        let mut args = args.into_iter();
+        while let Some(lon) = args.next() {
+            let lon = extract_float(Some(lon))?;
+            let lat = extract_float(args.next())?;
+            if geohash_encode(lon, lat).is_none() {
+                return Err(CommandError::InvalidArgument(format!(
+                    "invalid longitude,latitude pair {lon:.6},{lat:.6}"
+                )));
+            }
+            members.push((lon, lat, extract_string(args.next())?));
+        }
+        Ok(GeoAdd {
+            key,
+            flags,
+            ch,
+            members,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GeoPos {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "geopos", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next())?;
+        let members = args
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(GeoPos { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for GeoDist {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "geodist", 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next())?;
+        let member1 = extract_string(args.next())?;
+        let member2 = extract_string(args.next())?;
+        let unit = match args.next() {
+            None => GeoUnit::default(),
+            Some(unit) => extract_unit(Some(unit))?,
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(GeoDist {
+            key,
+            member1,
+            member2,
+            unit,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GeoSearch {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "geosearch", 6)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next())?;
+        let search = extract_search(args, false)?;
+        Ok(GeoSearch {
+            key,
+            query: search.query,
+            with_coord: search.with_coord,
+            with_dist: search.with_dist,
+            with_hash: search.with_hash,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GeoSearchStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "geosearchstore", 7)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let destination = extract_key(args.next())?;
+        let source = extract_key(args.next())?;
+        let search = extract_search(args, true)?;
+        Ok(GeoSearchStore {
+            destination,
+            source,
+            query: search.query,
+            store_dist: search.store_dist,
+        })
+    }
+}
+
+// GEORADIUS key longitude latitude radius <M | KM | FT | MI> [WITHCOORD] [WITHDIST] [WITHHASH]
+//   [COUNT count [ANY]] [ASC | DESC] [STORE key | STOREDIST key]
+// GEORADIUSBYMEMBER key member radius <M | KM | FT | MI> ...
+pub(super) fn georadius(value: RespArray, by_member: bool) -> Result<Command, CommandError> {
+    let (name, min_args) = if by_member {
+        ("georadiusbymember", 4)
+    } else {
+        ("georadius", 5)
+    };
+    validate_variadic_command(&value, name, min_args)?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next())?;
+    // the same search in the words of GEOSEARCH, the arguments are there as they were counted
+    let origin = if by_member { 1 } else { 2 };
+    let mut search = vec![BulkString::from(if by_member {
+        "frommember"
+    } else {
+        "fromlonlat"
+    })
+    .into()];
+    search.extend(args.by_ref().take(origin));
+    search.push(BulkString::from("byradius").into());
+    search.extend(args.by_ref().take(2));
+
+    let (mut destination, mut with) = (None, false);
+    while let Some(arg) = args.next() {
+        match extract_string(Some(arg.clone()))?
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "store" => destination = Some((extract_key(args.next())?, false)),
+            "storedist" => destination = Some((extract_key(args.next())?, true)),
+            option => {
+                with |= option.starts_with("with");
+                search.push(arg);
+            }
+        }
+    }
+    match destination {
+        None => {
+            let search = extract_search(search.into_iter(), false)?;
+            Ok(GeoSearch {
+                key,
+                query: search.query,
+                with_coord: search.with_coord,
+                with_dist: search.with_dist,
+                with_hash: search.with_hash,
+            }
+            .into())
+        }
+        Some(_) if with => Err(CommandError::InvalidArgument(format!(
+            "STORE option in {} is not compatible with WITHDIST, WITHHASH and WITHCOORD options",
+            name.to_ascii_uppercase()
+        ))),
+        Some((destination, store_dist)) => {
+            let search = extract_search(search.into_iter(), true)?;
+            Ok(GeoSearchStore {
+                destination,
+                source: key,
+                query: search.query,
+                store_dist,
+            }
+            .into())
+        }
+    }
+}
+
+struct SearchArgs {
+    query: GeoQuery,
+    with_coord: bool,
+    with_dist: bool,
+    with_hash: bool,
+    store_dist: bool,
+}
+
+// Parses the arguments of GEOSEARCH after the key, or of GEOSEARCHSTORE after the source when
+// `store` is set, which takes STOREDIST instead of the WITH options.
+fn extract_search(
+    args: impl Iterator<Item = RespFrame>,
+    store: bool,
+) -> Result<SearchArgs, CommandError> {
+    let mut args = args.peekable();
+    let (mut origin, mut shape) = (None, None);
+    let mut unit = GeoUnit::default();
+    let (mut order, mut count, mut any) = (None, None, false);
+    let (mut with_coord, mut with_dist, mut with_hash, mut store_dist) =
+        (false, false, false, false);
+    let origin_error = || {
+        CommandError::InvalidArgument(
+            "exactly one of FROMMEMBER or FROMLONLAT can be specified".to_string(),
+        )
+    };
+    let shape_error = || {
+        CommandError::InvalidArgument(
+            "exactly one of BYRADIUS and BYBOX can be specified".to_string(),
+        )
+    };
+
+    while let Some(arg) = args.next() {
+        match extract_string(Some(arg))?.to_ascii_lowercase().as_str() {
+            "frommember" | "fromlonlat" if origin.is_some() => return Err(origin_error()),
+            "frommember" => origin = Some(GeoOrigin::Member(extract_string(args.next())?)),
+            "fromlonlat" => {
+                let lon = extract_float(args.next())?;
+                let lat = extract_float(args.next())?;
+                if geohash_encode(lon, lat).is_none() {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "invalid longitude,latitude pair {lon:.6},{lat:.6}"
+                    )));
+                }
+                origin = Some(GeoOrigin::LonLat(lon, lat));
+            }
+            "byradius" | "bybox" if shape.is_some() => return Err(shape_error()),
+            "byradius" => {
+                let radius = extract_float(args.next())?;
+                if radius < 0.0 {
+                    return Err(CommandError::InvalidArgument(
+                        "radius cannot be negative".to_string(),
+                    ));
+                }
+                unit = extract_unit(args.next())?;
+                shape = Some(GeoShape::Radius(radius * unit.meters()));
+            }
+            "bybox" => {
+                let width = extract_float(args.next())?;
+                let height = extract_float(args.next())?;
+                if width < 0.0 || height < 0.0 {
+                    return Err(CommandError::InvalidArgument(
+                        "height or width cannot be negative".to_string(),
+                    ));
+                }
+                unit = extract_unit(args.next())?;
+                shape = Some(GeoShape::Box {
+                    width: width * unit.meters(),
+                    height: height * unit.meters(),
+                });
+            }
+            "asc" => order = Some(GeoOrder::Asc),
+            "desc" => order = Some(GeoOrder::Desc),
+            "count" => {
+                let n = extract_integer(args.next())?;
+                if n <= 0 {
+                    return Err(CommandError::InvalidArgument(
+                        "COUNT must be > 0".to_string(),
+                    ));
+                }
+                count = Some(n as usize);
+                if let Some(RespFrame::BulkString(next)) = args.peek() {
+                    if next.eq_ignore_ascii_case(b"any") {
+                        any = true;
+                        args.next();
+                    }
+                }
+            }
+            "withcoord" if !store => with_coord = true,
+            "withdist" if !store => with_dist = true,
+            "withhash" if !store => with_hash = true,
+            "storedist" if store => store_dist = true,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+
+    let origin = origin.ok_or_else(origin_error)?;
+    let shape = shape.ok_or_else(shape_error)?;
+    Ok(SearchArgs {
+        query: GeoQuery {
+            origin,
+            shape,
+            unit,
+            order,
+            count,
+            any,
+        },
+        with_coord,
+        with_dist,
+        with_hash,
+        store_dist,
+    })
+}
+
+fn extract_unit(frame: Option<RespFrame>) -> Result<GeoUnit, CommandError> {
+    GeoUnit::parse(&extract_string(frame)?).ok_or_else(|| {
+        CommandError::InvalidArgument(
+            "unsupported unit provided. please use M, KM, FT, MI".to_string(),
+        )
+    })
+}
+
+// The longitude and latitude of the geohash score of a member.
+fn position_frame(score: f64) -> RespFrame {
+    let (lon, lat) = geohash_decode(score as u64);
+    RespArray::new([
+        BulkString::new(lon.to_string()).into(),
+        BulkString::new(lat.to_string()).into(),
+    ])
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Command, Backend, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn command(buf: &[u8]) -> Result<Command> {
+        let mut buf = BytesMut::from(buf);
+        let frame = RespArray::decode(&mut buf)?;
+        Ok(frame.try_into()?)
+    }
+
+    fn sicily(backend: &Backend) -> RespFrame {
+        GeoAdd {
+            key: b"Sicily".to_vec(),
+            flags: ZAddFlags::default(),
+            ch: false,
+            members: vec![
+                (13.361389, 38.115556, "Palermo".to_string()),
+                (15.087269, 37.502669, "Catania".to_string()),
+            ],
+        }
+        .execute(backend)
+    }
+
+    #[test]
+    fn test_geoadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$6\r\nGEOADD\r\n$1\r\nk\r\n$2\r\nXX\r\n$2\r\nCH\r\n$1\r\n1\r\n$1\r\n2\r\n$1\r\nm\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: GeoAdd = frame.try_into()?;
+        assert!(cmd.flags.xx && cmd.ch);
+        assert_eq!(cmd.members, [(1.0, 2.0, "m".to_string())]);
+
+        // out of the indexable area
+        assert!(
+            command(b"*5\r\n$6\r\nGEOADD\r\n$1\r\nk\r\n$3\r\n200\r\n$2\r\n38\r\n$1\r\nm\r\n")
+                .is_err()
+        );
+        assert!(
+            command(b"*5\r\n$7\r\nGEODIST\r\n$1\r\nk\r\n$1\r\na\r\n$1\r\nb\r\n$2\r\nyd\r\n")
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_geoadd_geopos_geodist() -> Result<()> {
+        let backend = Backend::new();
+        assert_eq!(sicily(&backend), RespFrame::Integer(2));
+        assert_eq!(sicily(&backend), RespFrame::Integer(0));
+        assert_eq!(
+            backend.zscore(b"Sicily", "Palermo")?,
+            Some(3479099956230698.0)
+        );
+
+        let reply = GeoPos {
+            key: b"Sicily".to_vec(),
+            members: vec!["Palermo".to_string(), "missing".to_string()],
+        }
+        .execute(&backend);
+        assert_eq!(
+            reply,
+            RespArray::new([
+                position_frame(3479099956230698.0),
+                RespFrame::Null(RespNull)
+            ])
+            .into()
+        );
+
+        let geodist = |unit| {
+            GeoDist {
+                key: b"Sicily".to_vec(),
+                member1: "Palermo".to_string(),
+                member2: "Catania".to_string(),
+                unit,
+            }
+            .execute(&backend)
+        };
+        assert_eq!(
+            geodist(GeoUnit::Meters),
+            BulkString::from("166274.1516").into()
+        );
+        assert_eq!(
+            geodist(GeoUnit::Kilometers),
+            BulkString::from("166.2742").into()
+        );
+        assert_eq!(geodist(GeoUnit::Miles), BulkString::from("103.3182").into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_geosearch_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*13\r\n$9\r\nGEOSEARCH\r\n$1\r\nk\r\n$10\r\nFROMMEMBER\r\n$1\r\nm\r\n$5\r\nBYBOX\r\n$1\r\n2\r\n$1\r\n4\r\n$2\r\nkm\r\n$4\r\nDESC\r\n$5\r\nCOUNT\r\n$1\r\n3\r\n$3\r\nANY\r\n$8\r\nWITHHASH\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: GeoSearch = frame.try_into()?;
+        assert_eq!(
+            cmd.query,
+            GeoQuery {
+                origin: GeoOrigin::Member("m".to_string()),
+                shape: GeoShape::Box {
+                    width: 2000.0,
+                    height: 4000.0
+                },
+                unit: GeoUnit::Kilometers,
+                order: Some(GeoOrder::Desc),
+                count: Some(3),
+                any: true,
+            }
+        );
+        assert!(cmd.with_hash && !cmd.with_dist && !cmd.with_coord);
+
+        // one shape only, and no WITH options when storing
+        assert!(command(b"*10\r\n$9\r\nGEOSEARCH\r\n$1\r\nk\r\n$10\r\nFROMMEMBER\r\n$1\r\nm\r\n$8\r\nBYRADIUS\r\n$1\r\n1\r\n$1\r\nm\r\n$8\r\nBYRADIUS\r\n$1\r\n1\r\n$1\r\nm\r\n").is_err());
+        assert!(command(b"*9\r\n$14\r\nGEOSEARCHSTORE\r\n$1\r\nd\r\n$1\r\nk\r\n$10\r\nFROMMEMBER\r\n$1\r\nm\r\n$8\r\nBYRADIUS\r\n$1\r\n1\r\n$1\r\nm\r\n$8\r\nWITHDIST\r\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_geosearch_geosearchstore() -> Result<()> {
+        let backend = Backend::new();
+        sicily(&backend);
+        let search = |buf: &[u8]| -> Result<RespFrame> { Ok(command(buf)?.execute(&backend)) };
+        assert_eq!(
+            search(b"*9\r\n$9\r\nGEOSEARCH\r\n$6\r\nSicily\r\n$10\r\nFROMLONLAT\r\n$2\r\n15\r\n$2\r\n37\r\n$8\r\nBYRADIUS\r\n$3\r\n200\r\n$2\r\nkm\r\n$3\r\nASC\r\n")?,
+            RespArray::new([
+                BulkString::from("Catania").into(),
+                BulkString::from("Palermo").into()
+            ])
+            .into()
+        );
+        assert_eq!(
+            search(b"*11\r\n$9\r\nGEOSEARCH\r\n$6\r\nSicily\r\n$10\r\nFROMMEMBER\r\n$7\r\nPalermo\r\n$5\r\nBYBOX\r\n$3\r\n400\r\n$3\r\n400\r\n$2\r\nkm\r\n$4\r\nDESC\r\n$8\r\nWITHDIST\r\n$8\r\nWITHHASH\r\n")?,
+            RespArray::new([
+                RespArray::new([
+                    BulkString::from("Catania").into(),
+                    BulkString::from("166.2742").into(),
+                    RespFrame::Integer(3479447370796909),
+                ])
+                .into(),
+                RespArray::new([
+                    BulkString::from("Palermo").into(),
+                    BulkString::from("0.0000").into(),
+                    RespFrame::Integer(3479099956230698),
+                ])
+                .into(),
+            ])
+            .into()
+        );
+        assert_eq!(
+            search(b"*8\r\n$9\r\nGEOSEARCH\r\n$6\r\nSicily\r\n$10\r\nFROMMEMBER\r\n$7\r\nmissing\r\n$8\r\nBYRADIUS\r\n$1\r\n1\r\n$1\r\nm\r\n$3\r\nASC\r\n")?,
+            SimpleError::new("ERR could not decode requested zset member").into()
+        );
+
+        assert_eq!(
+            search(b"*10\r\n$14\r\nGEOSEARCHSTORE\r\n$4\r\nnear\r\n$6\r\nSicily\r\n$10\r\nFROMLONLAT\r\n$2\r\n15\r\n$2\r\n37\r\n$8\r\nBYRADIUS\r\n$3\r\n100\r\n$2\r\nkm\r\n$9\r\nSTOREDIST\r\n")?,
+            RespFrame::Integer(1)
+        );
+        let distance = backend.zscore(b"near", "Catania")?.unwrap_or_default();
+        assert!((distance - 56.4413).abs() < 1e-3, "{distance}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_georadius() -> Result<()> {
+        let backend = Backend::new();
+        sicily(&backend);
+        let run = |words: &str| -> Result<RespFrame> {
+            let args = words.split(' ').map(|w| BulkString::from(w).into());
+            let cmd: Command = RespArray::new(args.collect::<Vec<_>>()).try_into()?;
+            Ok(cmd.execute(&backend))
+        };
+        assert_eq!(
+            run("GEORADIUS Sicily 15 37 200 km WITHDIST ASC")?,
+            RespArray::new([
+                RespArray::new([
+                    BulkString::from("Catania").into(),
+                    BulkString::from("56.4413").into(),
+                ])
+                .into(),
+                RespArray::new([
+                    BulkString::from("Palermo").into(),
+                    BulkString::from("190.4424").into(),
+                ])
+                .into(),
+            ])
+            .into()
+        );
+        assert_eq!(
+            run("GEORADIUSBYMEMBER Sicily Palermo 100 km COUNT 1")?,
+            RespArray::new([BulkString::from("Palermo").into()]).into()
+        );
+
+        assert_eq!(
+            run("GEORADIUS Sicily 15 37 100 km STORE near")?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            backend.zscore(b"near", "Catania")?,
+            backend.zscore(b"Sicily", "Catania")?
+        );
+        assert_eq!(
+            run("GEORADIUSBYMEMBER Sicily Palermo 200 km STOREDIST dist")?,
+            RespFrame::Integer(2)
+        );
+        assert_eq!(backend.zscore(b"dist", "Palermo")?, Some(0.0));
+        assert!(run("GEORADIUS Sicily 15 37 100 km WITHDIST STORE near").is_err());
+        Ok(())
+    }
+}
@@ -1,28 +1,51 @@
 use super::{
-    extract_args, validate_command, CommandError, CommandExecutor, HGet, HGetAll, HMGet, HSet,
-    RESP_OK,
+    command::CommandSpec, extract_args, validate_command, CommandError, CommandExecutor, HGet,
+    HGetAll, HMGet, HSet, RESP_OK,
 };
-use crate::{BulkString, RespArray, RespFrame};
+use crate::{BulkString, NotifyClass, RespArray, RespFrame, RespNull, SimpleError, WrongType};
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("hget", 3, "hash", "Returns the value of a field in a hash.")
+        .flags(&["readonly", "fast"])
+        .keys(1, 1, 1),
+    CommandSpec::new("hset", 4, "hash", "Sets the value of a field in a hash.")
+        .flags(&["write", "fast"])
+        .keys(1, 1, 1),
+    CommandSpec::new(
+        "hmget",
+        -3,
+        "hash",
+        "Returns the values of all fields in a hash.",
+    )
+    .flags(&["readonly", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "hgetall",
+        2,
+        "hash",
+        "Returns all fields and values in a hash.",
+    )
+    .flags(&["readonly"])
+    .keys(1, 1, 1),
+];
 
 impl CommandExecutor for HGet {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
         match backend.hget(&self.key, &self.field) {
-            Some(value) => value,
-            None => RespFrame::Null(crate::RespNull),
+            Ok(Some(value)) => value,
+            Ok(None) => RespFrame::Null(crate::RespNull),
+            Err(e) => SimpleError::new(e.to_string()).into(),
         }
     }
 }
 
 impl CommandExecutor for HGetAll {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
-        let hmap = backend.hmap.get(&self.key);
-
-        match hmap {
-            Some(hmap) => {
+        match backend.hgetall(&self.key) {
+            Ok(Some(hmap)) => {
                 let mut data = Vec::with_capacity(hmap.len() * 2);
-                for v in hmap.iter() {
-                    let key = v.key().to_owned();
-                    data.push((key, v.value().clone()));
+                for (field, value) in hmap {
+                    data.push((field, value));
                 }
                 if self.sort {
                     data.sort_by(|a, b| a.0.cmp(&b.0));
@@ -34,7 +57,8 @@ impl CommandExecutor for HGetAll {
 
                 RespArray::new(ret).into()
             }
-            None => RespArray::new([]).into(),
+            Ok(None) => RespArray::new([]).into(),
+            Err(e) => SimpleError::new(e.to_string()).into(),
         }
     }
 }
@@ -44,18 +68,28 @@ impl CommandExecutor for HMGet {
         let fields = self
             .fields
             .iter()
-            .map(|f| match backend.hget(&self.hash, f) {
-                Some(value) => value,
-                None => RespFrame::Null(crate::RespNull),
+            .map(|f| {
+                Ok(match backend.hget(&self.hash, f)? {
+                    Some(value) => value,
+                    None => RespFrame::Null(crate::RespNull),
+                })
             })
-            .collect::<Vec<_>>();
-        RespFrame::Array(RespArray(fields))
+            .collect::<Result<Vec<_>, WrongType>>();
+        match fields {
+            Ok(fields) => RespFrame::Array(RespArray(fields)),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
     }
 }
 
 impl CommandExecutor for HSet {
-    fn execute(self, backend: &crate::Backend) -> RespFrame {
-        backend.hset(self.key, self.field, self.value);
+    fn execute(mut self, backend: &crate::Backend) -> RespFrame {
+        // the value is taken, the command is still needed to report the event
+        let value = std::mem::replace(&mut self.value, RespFrame::Null(RespNull));
+        if let Err(e) = backend.hset(self.key.clone(), std::mem::take(&mut self.field), value) {
+            return SimpleError::new(e.to_string()).into();
+        }
+        self.notify(backend, NotifyClass::Hash, "hset", &self.key);
         RESP_OK.clone()
     }
 }
@@ -68,7 +102,7 @@ impl TryFrom<RespArray> for HGet {
         let mut args = extract_args(value, 1)?.into_iter();
         match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field))) => Ok(HGet {
-                key: String::from_utf8(key.0)?,
+                key: key.0,
                 field: String::from_utf8(field.0)?,
             }),
             _ => Err(CommandError::InvalidArgument(
@@ -86,7 +120,7 @@ impl TryFrom<RespArray> for HGetAll {
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(key)) => Ok(HGetAll {
-                key: String::from_utf8(key.0)?,
+                key: key.0,
                 sort: false,
             }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
@@ -114,7 +148,7 @@ impl TryFrom<RespArray> for HMGet {
 
         let mut args = extract_args(value, 1)?.into_iter();
         let hash = match args.next() {
-            Some(RespFrame::BulkString(key)) => String::from_utf8(key.0)?,
+            Some(RespFrame::BulkString(key)) => key.0,
             _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
         };
         let mut fields = vec![];
@@ -138,7 +172,7 @@ impl TryFrom<RespArray> for HSet {
         match (args.next(), args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field)), Some(value)) => {
                 Ok(HSet {
-                    key: String::from_utf8(key.0)?,
+                    key: key.0,
                     field: String::from_utf8(field.0)?,
                     value,
                 })
@@ -163,7 +197,7 @@ mod tests {
         buf.extend_from_slice(b"*3\r\n$4\r\nhget\r\n$3\r\nmap\r\n$5\r\nhello\r\n");
         let frame = RespArray::decode(&mut buf)?;
         let result: HGet = frame.try_into()?;
-        assert_eq!(result.key, "map");
+        assert_eq!(result.key, b"map");
         assert_eq!(result.field, "hello");
         Ok(())
     }
@@ -174,7 +208,7 @@ mod tests {
         buf.extend_from_slice(b"*2\r\n$7\r\nhgetall\r\n$3\r\nmap\r\n");
         let frame = RespArray::decode(&mut buf)?;
         let result: HGetAll = frame.try_into()?;
-        assert_eq!(result.key, "map");
+        assert_eq!(result.key, b"map");
         Ok(())
     }
 
@@ -184,7 +218,7 @@ mod tests {
         buf.extend_from_slice(b"*4\r\n$4\r\nhset\r\n$3\r\nmap\r\n$5\r\nhello\r\n$5\r\nworld\r\n");
         let frame = RespArray::decode(&mut buf)?;
         let result: HSet = frame.try_into()?;
-        assert_eq!(result.key, "map");
+        assert_eq!(result.key, b"map");
         assert_eq!(result.field, "hello");
         assert_eq!(result.value, RespFrame::BulkString(b"world".into()));
         Ok(())
@@ -194,7 +228,7 @@ mod tests {
     fn test_hset_hget_hgetall_commands() -> Result<()> {
         let backend = crate::Backend::new();
         let cmd = HSet {
-            key: "map".to_string(),
+            key: b"map".to_vec(),
             field: "hello".to_string(),
             value: RespFrame::BulkString(b"world".into()),
         };
@@ -202,21 +236,21 @@ mod tests {
         assert_eq!(result, RESP_OK.clone());
 
         let cmd = HSet {
-            key: "map".to_string(),
+            key: b"map".to_vec(),
             field: "hello1".to_string(),
             value: RespFrame::BulkString(b"world1".into()),
         };
         cmd.execute(&backend);
 
         let cmd = HGet {
-            key: "map".to_string(),
+            key: b"map".to_vec(),
             field: "hello".to_string(),
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RespFrame::BulkString(b"world".into()));
 
         let cmd = HGetAll {
-            key: "map".to_string(),
+            key: b"map".to_vec(),
             sort: true,
         };
         let result = cmd.execute(&backend);
@@ -237,7 +271,7 @@ mod tests {
         buf.extend_from_slice(b"*5\r\n$5\r\nHMGET\r\n$6\r\nmyhash\r\n$6\r\nfield1\r\n$6\r\nfield2\r\n$7\r\nnofield\r\n");
         let frame = RespArray::decode(&mut buf)?;
         let result: HMGet = frame.try_into()?;
-        assert_eq!(result.hash, "myhash");
+        assert_eq!(result.hash, b"myhash");
         assert_eq!(result.fields.len(), 3);
         assert_eq!(result.fields[0], "field1");
         assert_eq!(result.fields[1], "field2");
@@ -0,0 +1,191 @@
+use super::{
+    command::CommandSpec, extract_args, extract_key, extract_keys, extract_string,
+    validate_variadic_command, CommandError, CommandExecutor, PfAdd, PfCount, PfMerge, RESP_OK,
+};
+use crate::{NotifyClass, RespArray, RespFrame, SimpleError};
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new(
+        "pfadd",
+        -2,
+        "hyperloglog",
+        "Adds elements to a HyperLogLog key. Creates the key if it doesn't exist.",
+    )
+    .flags(&["write", "denyoom", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "pfcount",
+        -2,
+        "hyperloglog",
+        "Returns the approximated cardinality of the set(s) observed by the HyperLogLog key(s).",
+    )
+    .flags(&["readonly"])
+    .keys(1, -1, 1),
+    CommandSpec::new(
+        "pfmerge",
+        -2,
+        "hyperloglog",
+        "Merges one or more HyperLogLog values into a single key.",
+    )
+    .flags(&["write", "denyoom"])
+    .keys(1, -1, 1),
+];
+
+impl CommandExecutor for PfAdd {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.pfadd(&self.key, &self.elements) {
+            Ok(changed) => {
+                if changed {
+                    self.notify(backend, NotifyClass::String, "pfadd", &self.key);
+                }
+                RespFrame::Integer(changed as i64)
+            }
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for PfCount {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.pfcount(&self.keys) {
+            Ok(count) => RespFrame::Integer(count as i64),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for PfMerge {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.pfmerge(&self.destination, &self.sources) {
+            Ok(()) => {
+                self.notify(backend, NotifyClass::String, "pfadd", &self.destination);
+                RESP_OK.clone()
+            }
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for PfAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "pfadd", 1)?;
+
+        let (key, elements) = extract_key_values(value)?;
+        Ok(PfAdd { key, elements })
+    }
+}
+
+impl TryFrom<RespArray> for PfCount {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "pfcount", 1)?;
+
+        let keys = extract_keys(value)?;
+        Ok(PfCount { keys })
+    }
+}
+
+impl TryFrom<RespArray> for PfMerge {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "pfmerge", 1)?;
+
+        let mut sources = extract_keys(value)?;
+        let destination = sources.remove(0);
+        Ok(PfMerge {
+            destination,
+            sources,
+        })
+    }
+}
+
+fn extract_key_values(value: RespArray) -> Result<(Vec<u8>, Vec<String>), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next())?;
+    let values = args
+        .map(|arg| extract_string(Some(arg)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((key, values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, BulkString, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn pfadd(backend: &Backend, key: &str, elements: &[&str]) -> RespFrame {
+        PfAdd {
+            key: key.as_bytes().to_vec(),
+            elements: elements.iter().map(|e| e.to_string()).collect(),
+        }
+        .execute(backend)
+    }
+
+    fn pfcount(backend: &Backend, keys: &[&str]) -> RespFrame {
+        PfCount {
+            keys: keys.iter().map(|k| k.as_bytes().to_vec()).collect(),
+        }
+        .execute(backend)
+    }
+
+    #[test]
+    fn test_pfadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\nPFADD\r\n$3\r\nhll\r\n$1\r\na\r\n$1\r\nb\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: PfAdd = frame.try_into()?;
+        assert_eq!(cmd.key, b"hll");
+        assert_eq!(cmd.elements, ["a", "b"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pfadd_pfcount_pfmerge() -> Result<()> {
+        let backend = Backend::new();
+        // an empty HyperLogLog is still created
+        assert_eq!(pfadd(&backend, "empty", &[]), RespFrame::Integer(1));
+        assert_eq!(pfadd(&backend, "empty", &[]), RespFrame::Integer(0));
+        assert_eq!(pfcount(&backend, &["empty"]), RespFrame::Integer(0));
+
+        assert_eq!(
+            pfadd(&backend, "hll1", &["foo", "bar", "zap", "a"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(pfadd(&backend, "hll1", &["foo"]), RespFrame::Integer(0));
+        assert_eq!(
+            pfadd(&backend, "hll2", &["a", "b", "c", "foo"]),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(pfcount(&backend, &["hll1"]), RespFrame::Integer(4));
+        assert_eq!(
+            pfcount(&backend, &["hll1", "hll2", "missing"]),
+            RespFrame::Integer(6)
+        );
+
+        let merge = PfMerge {
+            destination: b"hll3".to_vec(),
+            sources: vec![b"hll1".to_vec(), b"hll2".to_vec()],
+        };
+        assert_eq!(merge.execute(&backend), RESP_OK.clone());
+        assert_eq!(pfcount(&backend, &["hll3"]), RespFrame::Integer(6));
+
+        backend.set(b"string".to_vec(), BulkString::from("value").into());
+        let wrong_type =
+            SimpleError::new("WRONGTYPE Key is not a valid HyperLogLog string value.").into();
+        assert_eq!(pfadd(&backend, "string", &["a"]), wrong_type);
+        assert_eq!(pfcount(&backend, &["hll1", "string"]), wrong_type);
+        backend.sadd("set", ["a".to_string()])?;
+        assert_eq!(
+            pfadd(&backend, "set", &["a"]),
+            SimpleError::new("WRONGTYPE Operation against a key holding the wrong kind of value")
+                .into()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,377 @@
+use super::{
+    command::CommandSpec, extract_args, extract_string, CommandError, CommandExecutor, Info,
+};
+use crate::{used_memory_rss, Backend, BulkString, RespArray, RespFrame};
+use std::fmt::Write;
+
+pub(super) const COMMANDS: &[CommandSpec] = &[CommandSpec::new(
+    "info",
+    -1,
+    "server",
+    "Returns information and statistics about the server.",
+)
+.flags(&["loading", "stale"])];
+
+const SECTIONS: [&str; 10] = [
+    "server",
+    "clients",
+    "memory",
+    "persistence",
+    "stats",
+    "replication",
+    "commandstats",
+    "errorstats",
+    "cluster",
+    "keyspace",
+];
+const DEFAULT_SECTIONS: [&str; 9] = [
+    "server",
+    "clients",
+    "memory",
+    "persistence",
+    "stats",
+    "replication",
+    "errorstats",
+    "cluster",
+    "keyspace",
+];
+
+impl CommandExecutor for Info {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let sections = if self.sections.is_empty() {
+            vec!["default".to_string()]
+        } else {
+            self.sections
+        };
+        let selected = sections
+            .iter()
+            .flat_map(|section| match section.as_str() {
+                "default" => DEFAULT_SECTIONS.to_vec(),
+                "all" | "everything" => SECTIONS.to_vec(),
+                section => SECTIONS.into_iter().filter(|s| *s == section).collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut info = String::new();
+        // keep the canonical order whatever the order of the arguments
+        for section in SECTIONS.iter().filter(|s| selected.contains(s)) {
+            if !info.is_empty() {
+                info.push_str("\r\n");
+            }
+            write_section(&mut info, section, backend);
+        }
+        BulkString::new(info).into()
+    }
+}
+
+fn write_section(info: &mut String, section: &str, backend: &Backend) {
+    let stats = backend.stats();
+    let mut title = section.to_string();
+    title[..1].make_ascii_uppercase();
+    let _ = write!(info, "# {title}\r\n");
+
+    let fields: Vec<(&str, String)> = match section {
+        "server" => {
+            let uptime = stats.uptime().as_secs();
+            let config = backend.config().read();
+            vec![
+                ("redis_version", env!("CARGO_PKG_VERSION").to_string()),
+                (
+                    "redis_mode",
+                    if config.cluster_enabled {
+                        "cluster"
+                    } else {
+                        "standalone"
+                    }
+                    .to_string(),
+                ),
+                ("os", std::env::consts::OS.to_string()),
+                ("arch_bits", (usize::BITS).to_string()),
+                ("process_id", std::process::id().to_string()),
+                ("tcp_port", config.port.to_string()),
+                ("hz", config.hz.to_string()),
+                ("uptime_in_seconds", uptime.to_string()),
+                ("uptime_in_days", (uptime / 86400).to_string()),
+            ]
+        }
+        "clients" => vec![("connected_clients", stats.connected_clients().to_string())],
+        "memory" => {
+            let rss = used_memory_rss();
+            let config = backend.config().read();
+            vec![
+                // the keyspace as accounted for by maxmemory, without allocator overhead
+                ("used_memory", backend.used_memory().to_string()),
+                ("used_memory_rss", rss.to_string()),
+                (
+                    "lazyfree_pending_objects",
+                    backend.lazyfree_pending().to_string(),
+                ),
+                (
+                    "number_of_cached_scripts",
+                    backend.scripts().len().to_string(),
+                ),
+                (
+                    "number_of_functions",
+                    backend.functions().function_count().to_string(),
+                ),
+                ("number_of_libraries", backend.functions().len().to_string()),
+                ("maxmemory", config.maxmemory.to_string()),
+                ("maxmemory_policy", config.maxmemory_policy.clone()),
+            ]
+        }
+        "persistence" => {
+            let status = backend.save_status();
+            let mut fields = vec![
+                ("loading", "0".to_string()),
+                ("rdb_changes_since_last_save", status.changes().to_string()),
+                (
+                    "rdb_bgsave_in_progress",
+                    (status.in_progress() as u8).to_string(),
+                ),
+                ("rdb_last_save_time", status.last_save().to_string()),
+                (
+                    "rdb_last_bgsave_status",
+                    if status.last_error().is_some() {
+                        "err"
+                    } else {
+                        "ok"
+                    }
+                    .to_string(),
+                ),
+                (
+                    "rdb_last_bgsave_time_sec",
+                    status.last_duration().to_string(),
+                ),
+            ];
+            if let Some(error) = status.last_error() {
+                fields.push(("rdb_last_bgsave_error", error));
+            }
+            let aof_enabled = backend.config().read().appendonly;
+            fields.push(("aof_enabled", (aof_enabled as u8).to_string()));
+            let log = backend.append_log();
+            let rewrite = log.rewrite_status();
+            let ok_err = |error: Option<String>| if error.is_some() { "err" } else { "ok" };
+            fields.extend([
+                (
+                    "aof_rewrite_in_progress",
+                    (rewrite.in_progress() as u8).to_string(),
+                ),
+                (
+                    "aof_last_rewrite_time_sec",
+                    rewrite.last_duration().to_string(),
+                ),
+                (
+                    "aof_last_bgrewrite_status",
+                    ok_err(rewrite.last_error()).to_string(),
+                ),
+                (
+                    "aof_last_write_status",
+                    ok_err(log.write_error()).to_string(),
+                ),
+            ]);
+            if aof_enabled {
+                fields.push(("aof_current_size", log.size().to_string()));
+                fields.push(("aof_base_size", log.base_size().to_string()));
+            }
+            fields
+        }
+        "stats" => vec![
+            (
+                "total_connections_received",
+                stats.total_connections_received().to_string(),
+            ),
+            (
+                "total_commands_processed",
+                stats.total_commands_processed().to_string(),
+            ),
+            ("expired_keys", stats.expired_keys().to_string()),
+            ("evicted_keys", stats.evicted_keys().to_string()),
+            ("keyspace_hits", stats.keyspace_hits().to_string()),
+            ("keyspace_misses", stats.keyspace_misses().to_string()),
+            (
+                "total_error_replies",
+                stats.total_error_replies().to_string(),
+            ),
+        ],
+        "replication" => {
+            let replication = backend.replication();
+            let master = replication.master_info(backend.now_ms());
+            if let Some(master) = master {
+                let link = if master.state == "connected" {
+                    "up"
+                } else {
+                    "down"
+                };
+                let read_only = backend.config().read().replica_read_only;
+                let _ = write!(
+                    info,
+                    "role:slave\r\nmaster_host:{}\r\nmaster_port:{}\r\nmaster_link_status:{link}\r\n\
+                     master_last_io_seconds_ago:{}\r\nmaster_sync_in_progress:{}\r\n\
+                     slave_repl_offset:{}\r\nslave_read_only:{}\r\n",
+                    master.host,
+                    master.port,
+                    master.last_io.unwrap_or(-1),
+                    (master.state == "sync") as u8,
+                    replication.offset(),
+                    read_only as u8,
+                );
+            } else {
+                let _ = write!(info, "role:master\r\n");
+            }
+            let replicas = replication.replicas(backend.now_ms());
+            let _ = write!(info, "connected_slaves:{}\r\n", replicas.len());
+            for (i, replica) in replicas.into_iter().enumerate() {
+                let _ = write!(
+                    info,
+                    "slave{i}:ip={},port={},state={},offset={},lag={}\r\n",
+                    replica.ip, replica.port, replica.state, replica.offset, replica.lag
+                );
+            }
+            let (first_byte, histlen) = replication.backlog().unwrap_or((0, 0));
+            let (replid2, second_offset) = match replication.replid2() {
+                Some((replid2, offset)) => (replid2, offset as i64),
+                None => ("0".repeat(40), -1),
+            };
+            vec![
+                ("master_replid", replication.replid()),
+                ("master_replid2", replid2),
+                ("master_repl_offset", replication.offset().to_string()),
+                ("second_repl_offset", second_offset.to_string()),
+                (
+                    "repl_backlog_active",
+                    (replication.is_active() as u8).to_string(),
+                ),
+                (
+                    "repl_backlog_size",
+                    backend.config().read().repl_backlog_size.to_string(),
+                ),
+                ("repl_backlog_first_byte_offset", first_byte.to_string()),
+                ("repl_backlog_histlen", histlen.to_string()),
+            ]
+        }
+        "commandstats" => {
+            for (name, cmd) in stats.command_stats() {
+                let per_call = cmd.usec as f64 / cmd.calls.max(1) as f64;
+                let _ = write!(
+                    info,
+                    "cmdstat_{name}:calls={},usec={},usec_per_call={per_call:.2},\
+                     rejected_calls={},failed_calls={}\r\n",
+                    cmd.calls, cmd.usec, cmd.rejected_calls, cmd.failed_calls
+                );
+            }
+            vec![]
+        }
+        "errorstats" => {
+            for (code, count) in stats.error_stats() {
+                let _ = write!(info, "errorstat_{code}:count={count}\r\n");
+            }
+            vec![]
+        }
+        "cluster" => vec![(
+            "cluster_enabled",
+            (backend.cluster_enabled() as u8).to_string(),
+        )],
+        "keyspace" => {
+            let dbs = (0..backend.database_count()).filter_map(|i| Some((i, backend.database(i)?)));
+            for (index, db) in dbs {
+                if let (keys @ 1.., expires) = db.key_count() {
+                    let _ = write!(
+                        info,
+                        "db{index}:keys={keys},expires={expires},avg_ttl=0\r\n"
+                    );
+                }
+            }
+            vec![]
+        }
+        _ => vec![],
+    };
+    for (name, value) in fields {
+        let _ = write!(info, "{name}:{value}\r\n");
+    }
+}
+
+impl TryFrom<RespArray> for Info {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        match value.first() {
+            Some(RespFrame::BulkString(cmd)) if cmd.eq_ignore_ascii_case(b"info") => {}
+            _ => {
+                return Err(CommandError::InvalidCommand(
+                    "Invalid command: expected info".to_string(),
+                ))
+            }
+        }
+        let sections = extract_args(value, 1)?
+            .into_iter()
+            .map(|section| Ok(extract_string(Some(section))?.to_ascii_lowercase()))
+            .collect::<Result<_, CommandError>>()?;
+        Ok(Info { sections })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecoder;
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::time::Duration;
+
+    fn info(backend: &Backend, sections: &[&str]) -> String {
+        let cmd = Info {
+            sections: sections.iter().map(|s| s.to_string()).collect(),
+        };
+        match cmd.execute(backend) {
+            RespFrame::BulkString(info) => String::from_utf8(info.0).expect("utf8"),
+            frame => panic!("unexpected reply: {frame:?}"),
+        }
+    }
+
+    #[test]
+    fn test_info_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$4\r\nINFO\r\n$6\r\nSERVER\r\n$8\r\nkeyspace\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Info = frame.try_into()?;
+        assert_eq!(result.sections, vec!["server", "keyspace"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_info_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        backend
+            .stats()
+            .command_executed("set", Duration::from_micros(4), None);
+        backend.stats().command_rejected(
+            "set",
+            "OOM command not allowed when used memory > 'maxmemory'.",
+        );
+
+        let text = info(&backend, &["keyspace", "clients"]);
+        assert_eq!(
+            text,
+            "# Clients\r\nconnected_clients:0\r\n\r\n# Keyspace\r\ndb0:keys=1,expires=0,avg_ttl=0\r\n"
+        );
+
+        let text = info(&backend, &[]);
+        assert!(text.starts_with("# Server\r\n"));
+        assert!(text.contains("total_commands_processed:1\r\n"));
+        assert!(text.contains("total_error_replies:1\r\n"));
+        assert!(!text.contains("# Commandstats"));
+        assert!(text.contains("# Errorstats\r\nerrorstat_OOM:count=1\r\n"));
+
+        let text = info(&backend, &["persistence"]);
+        assert!(text.contains("rdb_bgsave_in_progress:0\r\nrdb_last_save_time:"));
+        assert!(text.contains("rdb_last_bgsave_status:ok\r\n"));
+        assert!(text.contains("aof_enabled:0\r\naof_rewrite_in_progress:0\r\n"));
+
+        let text = info(&backend, &["all"]);
+        assert!(text.contains(
+            "cmdstat_set:calls=1,usec=4,usec_per_call=4.00,rejected_calls=1,failed_calls=0\r\n"
+        ));
+        Ok(())
+    }
+}
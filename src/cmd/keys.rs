@@ -0,0 +1,628 @@
+use super::{
+    command::CommandSpec, extract_args, extract_integer, extract_key, extract_keys,
+    extract_scan_args, extract_string, validate_command, validate_variadic_command, CommandError,
+    CommandExecutor, Copy, DbSize, Del, Exists, FlushAll, FlushDb, Object, ObjectSubcommand,
+    RandomKey, Scan, Touch, Unlink, RESP_OK,
+};
+use crate::{
+    BulkString, EvictionPolicy, NotifyClass, RespArray, RespFrame, RespNull, SimpleError,
+    SimpleString,
+};
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("del", -2, "generic", "Deletes one or more keys.")
+        .flags(&["write"])
+        .keys(1, -1, 1),
+    CommandSpec::new("exists", -2, "generic", "Determines whether one or more keys exist.")
+        .flags(&["readonly", "fast"])
+        .keys(1, -1, 1),
+    CommandSpec::new("unlink", -2, "generic", "Asynchronously deletes one or more keys.")
+        .flags(&["write", "fast"])
+        .keys(1, -1, 1),
+    CommandSpec::new("scan", -2, "generic", "Iterates over the key names in the database.")
+        .flags(&["readonly"]),
+    CommandSpec::new("copy", -3, "generic", "Copies the value of a key to a new key.")
+        .flags(&["write"])
+        .keys(1, 2, 1),
+    CommandSpec::new("touch", -2, "generic", "Returns the number of existing keys out of those specified after updating the time they were last accessed.")
+        .flags(&["readonly", "fast"])
+        .keys(1, -1, 1),
+    CommandSpec::new("object", -2, "generic", "A container for object introspection commands.")
+        .flags(&["readonly"])
+        .keys(2, 2, 1),
+    CommandSpec::new("randomkey", 1, "generic", "Returns a random key name from the database.")
+        .flags(&["readonly"]),
+    CommandSpec::new("dbsize", 1, "server", "Returns the number of keys in the database.")
+        .flags(&["readonly", "fast"]),
+    CommandSpec::new("flushdb", -1, "server", "Removes all keys from the current database.")
+        .flags(&["write"]),
+    CommandSpec::new("flushall", -1, "server", "Removes all keys from all databases.")
+        .flags(&["write"]),
+];
+
+impl CommandExecutor for Del {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let lazy = backend.config().read().lazyfree_lazy_user_del;
+        let removed = self
+            .keys
+            .iter()
+            .filter(|key| {
+                let removed = if lazy {
+                    backend.unlink(key)
+                } else {
+                    backend.del(key)
+                };
+                if removed {
+                    self.notify(backend, NotifyClass::Generic, "del", key);
+                }
+                removed
+            })
+            .count();
+        RespFrame::Integer(removed as i64)
+    }
+}
+
+impl CommandExecutor for Exists {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let found = self.keys.iter().filter(|key| backend.exists(key)).count();
+        RespFrame::Integer(found as i64)
+    }
+}
+
+impl CommandExecutor for Unlink {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let removed = self
+            .keys
+            .iter()
+            .filter(|key| {
+                let removed = backend.unlink(key);
+                if removed {
+                    self.notify(backend, NotifyClass::Generic, "del", key);
+                }
+                removed
+            })
+            .count();
+        RespFrame::Integer(removed as i64)
+    }
+}
+
+impl CommandExecutor for Scan {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let (cursor, keys) = backend.scan(
+            self.cursor,
+            self.count,
+            self.pattern.as_deref(),
+            self.key_type.as_deref(),
+        );
+        let keys = keys
+            .into_iter()
+            .map(|key| BulkString::from(key).into())
+            .collect::<Vec<_>>();
+        RespArray::new([
+            BulkString::from(cursor.to_string()).into(),
+            RespArray::new(keys).into(),
+        ])
+        .into()
+    }
+}
+
+impl CommandExecutor for Copy {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let db = match self.db {
+            None => backend.selected_db(),
+            Some(db) if db >= 0 && (db as usize) < backend.database_count() => db as usize,
+            Some(_) => return SimpleError::new("ERR DB index is out of range").into(),
+        };
+        if self.source == self.destination && db == backend.selected_db() {
+            return SimpleError::new("ERR source and destination objects are the same").into();
+        }
+        let copied = backend.copy(&self.source, &self.destination, db, self.replace);
+        // only seen by the subscribers of the selected database's channels when copying to it
+        if copied && db == backend.selected_db() {
+            self.notify(backend, NotifyClass::Generic, "copy_to", &self.destination);
+        }
+        RespFrame::Integer(copied as i64)
+    }
+}
+
+impl CommandExecutor for Touch {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let touched = self.keys.iter().filter(|key| backend.touch(key)).count();
+        RespFrame::Integer(touched as i64)
+    }
+}
+
+impl CommandExecutor for DbSize {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        RespFrame::Integer(backend.db_size() as i64)
+    }
+}
+
+impl CommandExecutor for RandomKey {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.random_key() {
+            Some(key) => BulkString::from(key).into(),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for FlushDb {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let lazy = self
+            .lazy
+            .unwrap_or_else(|| backend.config().read().lazyfree_lazy_user_flush);
+        backend.flush(lazy);
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for FlushAll {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let lazy = self
+            .lazy
+            .unwrap_or_else(|| backend.config().read().lazyfree_lazy_user_flush);
+        backend.flush_all(lazy);
+        RESP_OK.clone()
+    }
+}
+
+const OBJECT_HELP: [&str; 9] = [
+    "OBJECT <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+    "ENCODING <key>",
+    "    Return the kind of internal representation used in order to store the value",
+    "    associated with a <key>.",
+    "FREQ <key>",
+    "    Return the access frequency index of the <key>.",
+    "IDLETIME <key>",
+    "    Return the idle time of the <key>, that is the approximated number of",
+    "    seconds elapsed since the last access to the key.",
+];
+
+impl CommandExecutor for Object {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match self.subcommand {
+            ObjectSubcommand::Help => {
+                let lines = OBJECT_HELP
+                    .iter()
+                    .map(|line| SimpleString::new(*line).into())
+                    .collect::<Vec<_>>();
+                RespArray::new(lines).into()
+            }
+            ObjectSubcommand::Encoding(key) => match backend.encoding(&key) {
+                Some(encoding) => BulkString::from(encoding).into(),
+                None => RespFrame::Null(RespNull),
+            },
+            // both are tracked, but only the one the eviction policy goes by is reported
+            ObjectSubcommand::IdleTime(key) => match backend.idle_time(&key) {
+                None => RespFrame::Null(RespNull),
+                Some(_) if lfu_policy(backend) => SimpleError::new(
+                    "ERR An LFU maxmemory policy is selected, idle time not tracked.",
+                )
+                .into(),
+                Some(idle) => RespFrame::Integer(idle / 1000),
+            },
+            ObjectSubcommand::Freq(key) => match backend.freq(&key) {
+                None => RespFrame::Null(RespNull),
+                Some(freq) if lfu_policy(backend) => RespFrame::Integer(freq as i64),
+                Some(_) => SimpleError::new(
+                    "ERR An LFU maxmemory policy is not selected, access frequency not tracked.",
+                )
+                .into(),
+            },
+            // values are never shared between keys
+            ObjectSubcommand::RefCount(key) if backend.exists(&key) => RespFrame::Integer(1),
+            ObjectSubcommand::RefCount(_) => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+fn lfu_policy(backend: &crate::Backend) -> bool {
+    EvictionPolicy::parse(&backend.config().read().maxmemory_policy).is_lfu()
+}
+
+impl TryFrom<RespArray> for Del {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "del", 1)?;
+        Ok(Del {
+            keys: extract_keys(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Exists {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "exists", 1)?;
+        Ok(Exists {
+            keys: extract_keys(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Unlink {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "unlink", 1)?;
+        Ok(Unlink {
+            keys: extract_keys(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for DbSize {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["dbsize"], 0)?;
+        Ok(DbSize)
+    }
+}
+
+impl TryFrom<RespArray> for RandomKey {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["randomkey"], 0)?;
+        Ok(RandomKey)
+    }
+}
+
+impl TryFrom<RespArray> for FlushDb {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(FlushDb {
+            lazy: extract_flush_mode(value, "flushdb")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FlushAll {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(FlushAll {
+            lazy: extract_flush_mode(value, "flushall")?,
+        })
+    }
+}
+
+// [ASYNC | SYNC], None when neither is given
+fn extract_flush_mode(value: RespArray, name: &'static str) -> Result<Option<bool>, CommandError> {
+    validate_variadic_command(&value, name, 0)?;
+    let mut args = extract_args(value, 1)?.into_iter();
+    match (args.next(), args.next()) {
+        (None, _) => Ok(None),
+        (Some(mode), None) => match extract_string(Some(mode))?.to_ascii_lowercase().as_str() {
+            "async" => Ok(Some(true)),
+            "sync" => Ok(Some(false)),
+            _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+        },
+        _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+    }
+}
+
+impl TryFrom<RespArray> for Touch {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "touch", 1)?;
+        Ok(Touch {
+            keys: extract_keys(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Object {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "object", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_lowercase();
+        let subcommand = match (subcommand.as_str(), args.next(), args.next()) {
+            ("help", None, _) => ObjectSubcommand::Help,
+            ("encoding", Some(key), None) => ObjectSubcommand::Encoding(extract_key(Some(key))?),
+            ("idletime", Some(key), None) => ObjectSubcommand::IdleTime(extract_key(Some(key))?),
+            ("freq", Some(key), None) => ObjectSubcommand::Freq(extract_key(Some(key))?),
+            ("refcount", Some(key), None) => ObjectSubcommand::RefCount(extract_key(Some(key))?),
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for '{subcommand}'"
+                )))
+            }
+        };
+        Ok(Object { subcommand })
+    }
+}
+
+impl TryFrom<RespArray> for Scan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "scan", 1)?;
+
+        let scan = extract_scan_args(extract_args(value, 1)?.into_iter())?;
+        let key_type = match scan.flags.as_slice() {
+            [] => None,
+            [option, key_type] if option == "type" => Some(key_type.clone()),
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(Scan {
+            cursor: scan.cursor,
+            pattern: scan.pattern,
+            count: scan.count,
+            key_type,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Copy {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "copy", 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let mut cmd = Copy {
+            source: extract_key(args.next())?,
+            destination: extract_key(args.next())?,
+            db: None,
+            replace: false,
+        };
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg))?.to_ascii_lowercase().as_str() {
+                "db" => cmd.db = Some(extract_integer(args.next())?),
+                "replace" => cmd.replace = true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(cmd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, BulkString, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_del_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$3\r\nDEL\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Del = frame.try_into()?;
+        assert_eq!(result.keys, vec![b"key1".to_vec(), b"key2".to_vec()]);
+
+        buf.extend_from_slice(b"*1\r\n$6\r\nEXISTS\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Exists, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*8\r\n$4\r\nSCAN\r\n$2\r\n17\r\n$5\r\nMATCH\r\n$4\r\nkey*\r\n$5\r\nCOUNT\r\n$3\r\n100\r\n$4\r\nTYPE\r\n$4\r\nZSET\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Scan = frame.try_into()?;
+        assert_eq!(result.cursor, 17);
+        assert_eq!(result.pattern.as_deref(), Some(&b"key*"[..]));
+        assert_eq!(result.count, 100);
+        assert_eq!(result.key_type.as_deref(), Some("zset"));
+
+        buf.extend_from_slice(b"*3\r\n$4\r\nSCAN\r\n$1\r\n0\r\n$4\r\nTYPE\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Scan, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"key1".to_vec(), BulkString::from("Hello").into());
+        backend.sadd("key2", ["World".to_string()])?;
+
+        let cmd = Scan {
+            cursor: 0,
+            pattern: None,
+            count: 10,
+            key_type: Some("string".to_string()),
+        };
+        let expected = RespArray::new([
+            BulkString::from("0").into(),
+            RespArray::new([BulkString::from("key1").into()]).into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*6\r\n$4\r\nCOPY\r\n$5\r\ndolly\r\n$5\r\nclone\r\n$2\r\nDB\r\n$1\r\n0\r\n$7\r\nREPLACE\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Copy = frame.try_into()?;
+        assert_eq!(result.source, b"dolly");
+        assert_eq!(result.destination, b"clone");
+        assert_eq!(result.db, Some(0));
+        assert!(result.replace);
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"dolly".to_vec(), BulkString::from("sheep").into());
+
+        let cmd = Copy {
+            source: b"dolly".to_vec(),
+            destination: b"clone".to_vec(),
+            db: None,
+            replace: false,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(
+            backend.get(b"clone")?,
+            Some(BulkString::from("sheep").into())
+        );
+
+        let cmd = Copy {
+            source: b"dolly".to_vec(),
+            destination: b"clone".to_vec(),
+            db: Some(16),
+            replace: true,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR DB index is out of range").into()
+        );
+
+        let cmd = Copy {
+            source: b"dolly".to_vec(),
+            destination: b"dolly".to_vec(),
+            db: Some(1),
+            replace: false,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_object_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nOBJECT\r\n$8\r\nencoding\r\n$5\r\nmykey\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Object = frame.try_into()?;
+        assert_eq!(
+            result.subcommand,
+            ObjectSubcommand::Encoding(b"mykey".to_vec())
+        );
+
+        buf.extend_from_slice(b"*2\r\n$6\r\nOBJECT\r\n$8\r\nIDLETIME\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Object, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_object_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"mykey".to_vec(), BulkString::from("Hello").into());
+
+        let cmd = Object {
+            subcommand: ObjectSubcommand::Encoding(b"mykey".to_vec()),
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("embstr").into());
+
+        let cmd = Object {
+            subcommand: ObjectSubcommand::IdleTime(b"mykey".to_vec()),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+
+        let freq = Object {
+            subcommand: ObjectSubcommand::Freq(b"mykey".to_vec()),
+        };
+        assert!(matches!(freq.execute(&backend), RespFrame::Error(_)));
+        backend
+            .config()
+            .set(&[("maxmemory-policy".to_string(), "allkeys-lfu".to_string())])?;
+        let freq = Object {
+            subcommand: ObjectSubcommand::Freq(b"mykey".to_vec()),
+        };
+        assert_eq!(freq.execute(&backend), RespFrame::Integer(5));
+        let cmd = Object {
+            subcommand: ObjectSubcommand::IdleTime(b"mykey".to_vec()),
+        };
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+
+        let cmd = Object {
+            subcommand: ObjectSubcommand::RefCount(b"nokey".to_vec()),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+        Ok(())
+    }
+
+    #[test]
+    fn test_del_exists_commands() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"key1".to_vec(), BulkString::from("Hello").into());
+        backend.sadd("key2", ["World".to_string()])?;
+
+        let cmd = Exists {
+            keys: vec![b"key1".to_vec(), b"key1".to_vec(), b"key3".to_vec()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = Del {
+            keys: vec![b"key1".to_vec(), b"key2".to_vec(), b"key3".to_vec()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert!(!backend.exists(b"key1"));
+        assert!(!backend.exists(b"key2"));
+
+        backend.sadd("key1", (0..1000).map(|i| i.to_string()))?;
+        let cmd = Unlink {
+            keys: vec![b"key1".to_vec(), b"key2".to_vec()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert!(!backend.exists(b"key1"));
+
+        backend.set(b"key1".to_vec(), BulkString::from("Hello").into());
+        let cmd = Touch {
+            keys: vec![b"key1".to_vec(), b"key2".to_vec()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        backend.sadd("key2", ["World".to_string()])?;
+        assert_eq!(DbSize.execute(&backend), RespFrame::Integer(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$7\r\nFLUSHDB\r\n$5\r\nasync\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: FlushDb = frame.try_into()?;
+        assert_eq!(result.lazy, Some(true));
+
+        buf.extend_from_slice(b"*1\r\n$8\r\nFLUSHALL\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: FlushAll = frame.try_into()?;
+        assert_eq!(result.lazy, None);
+
+        buf.extend_from_slice(b"*2\r\n$8\r\nFLUSHALL\r\n$4\r\nNOW!\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<FlushAll, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_commands() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"key1".to_vec(), BulkString::from("Hello").into());
+        backend.sadd("key2", (0..1000).map(|i| i.to_string()))?;
+        let cmd = FlushDb { lazy: Some(true) };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(DbSize.execute(&backend), RespFrame::Integer(0));
+
+        backend.set(b"key1".to_vec(), BulkString::from("Hello").into());
+        let cmd = FlushAll { lazy: None };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert!(!backend.exists(b"key1"));
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use super::{
+    command::CommandSpec, extract_args, extract_string, validate_variadic_command, CommandError,
+    CommandExecutor, Latency, LatencySubcommand,
+};
+use crate::{BulkString, RespArray, RespFrame};
+
+pub(super) const COMMANDS: &[CommandSpec] = &[CommandSpec::new(
+    "latency",
+    -2,
+    "server",
+    "A container for latency diagnostics commands.",
+)
+.flags(&["admin", "noscript", "loading", "stale"])];
+
+impl CommandExecutor for Latency {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let monitor = backend.latency();
+        match self.subcommand {
+            LatencySubcommand::Latest => {
+                let events = monitor.latest().into_iter().map(|(event, sample, max)| {
+                    RespArray::new([
+                        BulkString::from(event).into(),
+                        RespFrame::Integer(sample.time),
+                        RespFrame::Integer(sample.latency as i64),
+                        RespFrame::Integer(max as i64),
+                    ])
+                    .into()
+                });
+                RespArray::new(events.collect::<Vec<_>>()).into()
+            }
+            LatencySubcommand::History(event) => {
+                let samples = monitor.history(&event).into_iter().map(|sample| {
+                    RespArray::new([
+                        RespFrame::Integer(sample.time),
+                        RespFrame::Integer(sample.latency as i64),
+                    ])
+                    .into()
+                });
+                RespArray::new(samples.collect::<Vec<_>>()).into()
+            }
+            LatencySubcommand::Reset(events) => RespFrame::Integer(monitor.reset(&events) as i64),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Latency {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "latency", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_lowercase();
+        let mut args = args
+            .map(|arg| Ok(extract_string(Some(arg))?.to_ascii_lowercase()))
+            .collect::<Result<Vec<_>, CommandError>>()?;
+        let subcommand = match subcommand.as_str() {
+            "latest" if args.is_empty() => LatencySubcommand::Latest,
+            "history" if args.len() == 1 => LatencySubcommand::History(args.remove(0)),
+            "reset" => LatencySubcommand::Reset(args),
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for '{subcommand}'"
+                )))
+            }
+        };
+        Ok(Latency { subcommand })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, ConfigValues, ManualClock, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_latency_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$7\r\nLATENCY\r\n$7\r\nHISTORY\r\n$7\r\nCommand\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Latency = frame.try_into()?;
+        assert_eq!(
+            result.subcommand,
+            LatencySubcommand::History("command".to_string())
+        );
+
+        buf.extend_from_slice(b"*2\r\n$7\r\nlatency\r\n$7\r\nhistory\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Latency, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_latency_command() -> Result<()> {
+        let clock = Arc::new(ManualClock::new(Duration::from_secs(1_000)));
+        let backend = Backend::with_clock(ConfigValues::default(), clock.clone());
+        // nothing is recorded until a threshold is set
+        backend.record_latency("command", Duration::from_millis(500));
+        assert!(backend.latency().latest().is_empty());
+
+        backend
+            .config()
+            .set(&[("latency-monitor-threshold".to_string(), "100".to_string())])?;
+        backend.record_latency("command", Duration::from_millis(99));
+        backend.record_latency("command", Duration::from_millis(300));
+        clock.advance(Duration::from_secs(1));
+        backend.record_latency("command", Duration::from_millis(150));
+
+        let cmd = Latency {
+            subcommand: LatencySubcommand::Latest,
+        };
+        let expected = RespArray::new([RespArray::new([
+            BulkString::from("command").into(),
+            RespFrame::Integer(1_001),
+            RespFrame::Integer(150),
+            RespFrame::Integer(300),
+        ])
+        .into()]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = Latency {
+            subcommand: LatencySubcommand::History("command".to_string()),
+        };
+        let expected = RespArray::new([
+            RespArray::new([RespFrame::Integer(1_000), RespFrame::Integer(300)]).into(),
+            RespArray::new([RespFrame::Integer(1_001), RespFrame::Integer(150)]).into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = Latency {
+            subcommand: LatencySubcommand::Reset(vec![]),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert!(backend.latency().latest().is_empty());
+        Ok(())
+    }
+}
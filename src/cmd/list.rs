@@ -0,0 +1,693 @@
+use super::{
+    command::{CommandSpec, KeySearch},
+    extract_args, extract_integer, extract_key, extract_string, extract_timeout, validate_command,
+    validate_variadic_command, BLMPop, BLMove, BPop, CommandError, CommandExecutor, LMPop, LPos,
+};
+use crate::{
+    Backend, BlockKey, BulkString, NotifyClass, RespArray, RespFrame, RespNull, SimpleError,
+    WrongType,
+};
+use std::time::Duration;
+use tokio::time::Instant;
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("lpos", -3, "list", "Returns the index of matching elements in a list.")
+        .flags(&["readonly"])
+        .keys(1, 1, 1),
+    CommandSpec::new("lmpop", -4, "list", "Returns multiple elements from a list after removing them.")
+        .flags(&["write", "movablekeys"])
+        .movable_keys(KeySearch::KeyNum(1)),
+    CommandSpec::new("blmpop", -5, "list", "Pops the first element from one of multiple lists. Blocks until an element is available otherwise.")
+        .flags(&["write", "blocking", "movablekeys"])
+        .movable_keys(KeySearch::KeyNum(2)),
+    CommandSpec::new("blpop", -3, "list", "Removes and returns the first element in a list. Blocks until an element is available otherwise.")
+        .flags(&["write", "blocking"])
+        .keys(1, -2, 1),
+    CommandSpec::new("brpop", -3, "list", "Removes and returns the last element in a list. Blocks until an element is available otherwise.")
+        .flags(&["write", "blocking"])
+        .keys(1, -2, 1),
+    CommandSpec::new("blmove", 6, "list", "Pops an element from a list, pushes it to another list and returns it. Blocks until an element is available otherwise.")
+        .flags(&["write", "denyoom", "blocking"])
+        .keys(1, 2, 1),
+];
+
+impl CommandExecutor for LPos {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let count = self.count.unwrap_or(1);
+        let indexes = match backend.lpos(&self.key, &self.element, self.rank, count, self.maxlen) {
+            Ok(indexes) => indexes,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        match self.count {
+            Some(_) => {
+                let indexes = indexes
+                    .into_iter()
+                    .map(|i| RespFrame::Integer(i as i64))
+                    .collect::<Vec<_>>();
+                RespArray::new(indexes).into()
+            }
+            None => match indexes.first() {
+                Some(&i) => RespFrame::Integer(i as i64),
+                None => RespFrame::Null(RespNull),
+            },
+        }
+    }
+}
+
+impl CommandExecutor for LMPop {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.lmpop(&self.keys, self.left, self.count) {
+            Ok(Some((key, elements))) => self.popped(backend, key, elements),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl LMPop {
+    // Reports the events of popping `elements` from `key` and replies with them.
+    fn popped(&self, backend: &Backend, key: Vec<u8>, elements: Vec<RespFrame>) -> RespFrame {
+        let event = if self.left { "lpop" } else { "rpop" };
+        self.notify(backend, NotifyClass::List, event, &key);
+        // the list went away with its last element
+        if !backend.exists(&key) {
+            self.notify(backend, NotifyClass::Generic, "del", &key);
+        }
+        RespArray::new([
+            BulkString::from(key).into(),
+            RespArray::new(elements).into(),
+        ])
+        .into()
+    }
+}
+
+// Executed directly (e.g. inside a transaction) BLMPOP never blocks and behaves like LMPOP.
+impl CommandExecutor for BLMPop {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        self.pop.execute(backend)
+    }
+}
+
+impl BLMPop {
+    // Waits until one of the lists can be popped or the timeout elapses.
+    pub async fn execute_blocking(self, backend: &Backend) -> RespFrame {
+        let deadline =
+            (self.timeout > 0.0).then(|| Instant::now() + Duration::from_secs_f64(self.timeout));
+        let db = backend.selected_db();
+        let keys = self
+            .pop
+            .keys
+            .iter()
+            .map(|key| BlockKey::Key(db, key.clone()));
+        let popped = backend.block_on(keys.collect(), deadline, || {
+            // a SWAPDB may have put other lists behind the selected database
+            backend.refresh_db();
+            let (keys, left, count) = (&self.pop.keys, self.pop.left, self.pop.count);
+            match backend.atomically(keys, || backend.lmpop(keys, left, count)) {
+                Ok(Some(popped)) => Some(Ok(popped)),
+                Ok(None) => None,
+                Err(e) => Some(Err(e)),
+            }
+        });
+        match popped.await {
+            Some(Ok((key, elements))) => self.pop.popped(backend, key, elements),
+            Some(Err(e)) => SimpleError::new(e.to_string()).into(),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+// Executed directly (e.g. inside a transaction) BLPOP and BRPOP never block.
+impl CommandExecutor for BPop {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.lmpop(&self.keys, self.left, 1) {
+            Ok(Some((key, elements))) => self.popped(backend, key, elements),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl BPop {
+    // Waits until one of the lists can be popped or the timeout elapses.
+    pub async fn execute_blocking(self, backend: &Backend) -> RespFrame {
+        let deadline =
+            (self.timeout > 0.0).then(|| Instant::now() + Duration::from_secs_f64(self.timeout));
+        let db = backend.selected_db();
+        let keys = self.keys.iter().map(|key| BlockKey::Key(db, key.clone()));
+        let popped = backend.block_on(keys.collect(), deadline, || {
+            // a SWAPDB may have put other lists behind the selected database
+            backend.refresh_db();
+            let keys = &self.keys;
+            match backend.atomically(keys, || backend.lmpop(keys, self.left, 1)) {
+                Ok(Some(popped)) => Some(Ok(popped)),
+                Ok(None) => None,
+                Err(e) => Some(Err(e)),
+            }
+        });
+        match popped.await {
+            Some(Ok((key, elements))) => self.popped(backend, key, elements),
+            Some(Err(e)) => SimpleError::new(e.to_string()).into(),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+
+    // Reports the events of popping from `key` and replies with the key and the element.
+    fn popped(&self, backend: &Backend, key: Vec<u8>, elements: Vec<RespFrame>) -> RespFrame {
+        let event = if self.left { "lpop" } else { "rpop" };
+        self.notify(backend, NotifyClass::List, event, &key);
+        if !backend.exists(&key) {
+            self.notify(backend, NotifyClass::Generic, "del", &key);
+        }
+        let element = elements
+            .into_iter()
+            .next()
+            .unwrap_or(RespFrame::Null(RespNull));
+        RespArray::new([BulkString::from(key).into(), element]).into()
+    }
+}
+
+// Executed directly (e.g. inside a transaction) BLMOVE never blocks and behaves like LMOVE.
+impl CommandExecutor for BLMove {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let moved = backend.lmove(
+            &self.source,
+            &self.destination,
+            self.from_left,
+            self.to_left,
+        );
+        self.moved(backend, moved)
+    }
+}
+
+impl BLMove {
+    // Waits until the source list can be popped or the timeout elapses.
+    pub async fn execute_blocking(self, backend: &Backend) -> RespFrame {
+        let deadline =
+            (self.timeout > 0.0).then(|| Instant::now() + Duration::from_secs_f64(self.timeout));
+        let keys = vec![BlockKey::Key(backend.selected_db(), self.source.clone())];
+        let moved = backend.block_on(keys, deadline, || {
+            // a SWAPDB may have put another list behind the selected database
+            backend.refresh_db();
+            let keys = [self.source.clone(), self.destination.clone()];
+            let (from_left, to_left) = (self.from_left, self.to_left);
+            let moved = backend.atomically(&keys, || {
+                backend.lmove(&self.source, &self.destination, from_left, to_left)
+            });
+            match moved {
+                Ok(None) => None,
+                moved => Some(moved),
+            }
+        });
+        match moved.await {
+            Some(moved) => self.moved(backend, moved),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+
+    // Reports the events of the move and replies with the element moved.
+    fn moved(&self, backend: &Backend, moved: Result<Option<RespFrame>, WrongType>) -> RespFrame {
+        let element = match moved {
+            Ok(Some(element)) => element,
+            Ok(None) => return RespFrame::Null(RespNull),
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        let event = if self.from_left { "lpop" } else { "rpop" };
+        self.notify(backend, NotifyClass::List, event, &self.source);
+        if !backend.exists(&self.source) {
+            self.notify(backend, NotifyClass::Generic, "del", &self.source);
+        }
+        let event = if self.to_left { "lpush" } else { "rpush" };
+        self.notify(backend, NotifyClass::List, event, &self.destination);
+        element
+    }
+}
+
+impl TryFrom<RespArray> for LPos {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let len = value.len();
+        if len < 3 || len.is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(format!(
+                "lpos command needs a key, an element and option pairs, got {} argument",
+                len.saturating_sub(1)
+            )));
+        }
+        validate_command(&value, &["lpos"], len - 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next())?;
+        let element = match args.next() {
+            Some(element @ RespFrame::BulkString(_)) => element,
+            _ => return Err(CommandError::InvalidArgument("Invalid element".to_string())),
+        };
+
+        let mut cmd = LPos {
+            key,
+            element,
+            rank: 1,
+            count: None,
+            maxlen: 0,
+        };
+        while let Some(option) = args.next() {
+            let option = extract_string(Some(option))?.to_ascii_lowercase();
+            let n = extract_integer(args.next())?;
+            match option.as_str() {
+                "rank" if n == 0 => {
+                    return Err(CommandError::InvalidArgument(
+                        "RANK can't be zero".to_string(),
+                    ))
+                }
+                "rank" => cmd.rank = n,
+                "count" if n >= 0 => cmd.count = Some(n as usize),
+                "maxlen" if n >= 0 => cmd.maxlen = n as usize,
+                "count" | "maxlen" => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "{} can't be negative",
+                        option.to_ascii_uppercase()
+                    )))
+                }
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "Unknown lpos option: {option}"
+                    )))
+                }
+            }
+        }
+        Ok(cmd)
+    }
+}
+
+impl TryFrom<RespArray> for LMPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let len = value.len();
+        if len < 4 {
+            return Err(CommandError::InvalidArgument(format!(
+                "lmpop command needs at least 3 argument, got {}",
+                len.saturating_sub(1)
+            )));
+        }
+        validate_command(&value, &["lmpop"], len - 1)?;
+
+        let args = extract_args(value, 1)?;
+        parse_lmpop_args(args.into_iter())
+    }
+}
+
+impl TryFrom<RespArray> for BLMPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let len = value.len();
+        if len < 5 {
+            return Err(CommandError::InvalidArgument(format!(
+                "blmpop command needs at least 4 argument, got {}",
+                len.saturating_sub(1)
+            )));
+        }
+        validate_command(&value, &["blmpop"], len - 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let timeout = extract_timeout(args.next())?;
+        let pop = parse_lmpop_args(args)?;
+        Ok(BLMPop { timeout, pop })
+    }
+}
+
+// BLPOP when `left` is set, BRPOP otherwise.
+pub(super) fn bpop(value: RespArray, left: bool) -> Result<BPop, CommandError> {
+    let name = if left { "blpop" } else { "brpop" };
+    validate_variadic_command(&value, name, 2)?;
+
+    let mut args = extract_args(value, 1)?;
+    let timeout = extract_timeout(args.pop())?;
+    let keys = args
+        .into_iter()
+        .map(|key| extract_key(Some(key)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(BPop {
+        keys,
+        left,
+        timeout,
+    })
+}
+
+impl TryFrom<RespArray> for BLMove {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["blmove"], 5)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let source = extract_key(args.next())?;
+        let destination = extract_key(args.next())?;
+        let from_left = extract_side(args.next())?;
+        let to_left = extract_side(args.next())?;
+        let timeout = extract_timeout(args.next())?;
+        Ok(BLMove {
+            source,
+            destination,
+            from_left,
+            to_left,
+            timeout,
+        })
+    }
+}
+
+// LEFT or RIGHT, true for LEFT
+fn extract_side(frame: Option<RespFrame>) -> Result<bool, CommandError> {
+    match extract_string(frame)?.to_ascii_lowercase().as_str() {
+        "left" => Ok(true),
+        "right" => Ok(false),
+        _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+    }
+}
+
+// numkeys key [key ...] <LEFT | RIGHT> [COUNT count]
+fn parse_lmpop_args(mut args: impl Iterator<Item = RespFrame>) -> Result<LMPop, CommandError> {
+    let numkeys = extract_integer(args.next())?;
+    if numkeys <= 0 {
+        return Err(CommandError::InvalidArgument(
+            "numkeys should be greater than 0".to_string(),
+        ));
+    }
+
+    let keys = (0..numkeys)
+        .map(|_| extract_key(args.next()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let left = extract_side(args.next())?;
+
+    let count = match args.next() {
+        Some(option) => {
+            if !extract_string(Some(option))?.eq_ignore_ascii_case("count") {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()));
+            }
+            match extract_integer(args.next())? {
+                n if n > 0 => n as usize,
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "count should be greater than 0".to_string(),
+                    ))
+                }
+            }
+        }
+        None => 1,
+    };
+
+    if args.next().is_some() {
+        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+    }
+
+    Ok(LMPop { keys, left, count })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecoder;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_lpos_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*7\r\n$4\r\nLPOS\r\n$6\r\nmylist\r\n$1\r\nc\r\n$4\r\nRANK\r\n$2\r\n-1\r\n$5\r\nCOUNT\r\n$1\r\n2\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: LPos = frame.try_into()?;
+        assert_eq!(result.key, b"mylist");
+        assert_eq!(result.element, RespFrame::BulkString(b"c".into()));
+        assert_eq!(result.rank, -1);
+        assert_eq!(result.count, Some(2));
+        assert_eq!(result.maxlen, 0);
+
+        buf.extend_from_slice(
+            b"*5\r\n$4\r\nLPOS\r\n$6\r\nmylist\r\n$1\r\nc\r\n$4\r\nRANK\r\n$1\r\n0\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<LPos, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lpos_command() -> Result<()> {
+        let backend = Backend::new();
+        let values = ["a", "b", "c", "1", "2", "3", "c", "c"];
+        backend.rpush("mylist", values.iter().map(|v| BulkString::from(*v).into()))?;
+
+        let cmd = LPos {
+            key: b"mylist".to_vec(),
+            element: BulkString::from("c").into(),
+            rank: 1,
+            count: None,
+            maxlen: 0,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = LPos {
+            key: b"mylist".to_vec(),
+            element: BulkString::from("c").into(),
+            rank: -1,
+            count: Some(0),
+            maxlen: 0,
+        };
+        let expected = RespArray::new([7.into(), 6.into(), 2.into()]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = LPos {
+            key: b"mylist".to_vec(),
+            element: BulkString::from("z").into(),
+            rank: 1,
+            count: None,
+            maxlen: 0,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmpop_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*7\r\n$5\r\nLMPOP\r\n$1\r\n2\r\n$6\r\nmylist\r\n$7\r\nmylist2\r\n$5\r\nRIGHT\r\n$5\r\nCOUNT\r\n$1\r\n2\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: LMPop = frame.try_into()?;
+        assert_eq!(result.keys, vec![b"mylist".to_vec(), b"mylist2".to_vec()]);
+        assert!(!result.left);
+        assert_eq!(result.count, 2);
+
+        buf.extend_from_slice(b"*6\r\n$6\r\nBLMPOP\r\n$3\r\n0.5\r\n$1\r\n2\r\n$6\r\nmylist\r\n$7\r\nmylist2\r\n$4\r\nLEFT\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: BLMPop = frame.try_into()?;
+        assert_eq!(result.timeout, 0.5);
+        assert_eq!(
+            result.pop.keys,
+            vec![b"mylist".to_vec(), b"mylist2".to_vec()]
+        );
+        assert!(result.pop.left);
+        assert_eq!(result.pop.count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmpop_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.rpush("mylist", [BulkString::from("a").into()])?;
+
+        let cmd = LMPop {
+            keys: vec![b"nolist".to_vec(), b"mylist".to_vec()],
+            left: true,
+            count: 2,
+        };
+        let expected = RespArray::new([
+            BulkString::from("mylist").into(),
+            RespArray::new([BulkString::from("a").into()]).into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blmpop_blocking() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = BLMPop {
+            timeout: 0.05,
+            pop: LMPop {
+                keys: vec![b"mylist".to_vec()],
+                left: true,
+                count: 1,
+            },
+        };
+        assert_eq!(
+            cmd.execute_blocking(&backend).await,
+            RespFrame::Null(RespNull)
+        );
+
+        let cmd = BLMPop {
+            timeout: 0.0,
+            pop: LMPop {
+                keys: vec![b"mylist".to_vec()],
+                left: true,
+                count: 1,
+            },
+        };
+        let cloned = backend.clone();
+        let handle = tokio::spawn(async move { cmd.execute_blocking(&cloned).await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        backend.rpush("mylist", [BulkString::from("a").into()])?;
+
+        let expected = RespArray::new([
+            BulkString::from("mylist").into(),
+            RespArray::new([BulkString::from("a").into()]).into(),
+        ]);
+        assert_eq!(handle.await?, expected.into());
+
+        // the client blocked first is served first, the other one as soon as there is more
+        let mut handles = vec![];
+        for _ in 0..2 {
+            let cmd = BLMPop {
+                timeout: 0.0,
+                pop: LMPop {
+                    keys: vec![b"mylist".to_vec()],
+                    left: true,
+                    count: 1,
+                },
+            };
+            let cloned = backend.clone();
+            handles.push(tokio::spawn(
+                async move { cmd.execute_blocking(&cloned).await },
+            ));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(backend.blocking().blocked_clients(), 2);
+        backend.rpush("mylist", [BulkString::from("a").into()])?;
+        backend.rpush("mylist", [BulkString::from("b").into()])?;
+        for (handle, element) in handles.into_iter().zip(["a", "b"]) {
+            let expected = RespArray::new([
+                BulkString::from("mylist").into(),
+                RespArray::new([BulkString::from(element).into()]).into(),
+            ]);
+            assert_eq!(handle.await?, expected.into());
+        }
+        assert_eq!(backend.blocking().blocked_clients(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bpop_blmove_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\nBRPOP\r\n$1\r\na\r\n$1\r\nb\r\n$3\r\n1.5\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result = bpop(frame, false)?;
+        assert_eq!(result.keys, vec![b"a".to_vec(), b"b".to_vec()]);
+        assert!(!result.left);
+        assert_eq!(result.timeout, 1.5);
+
+        buf.extend_from_slice(b"*3\r\n$5\r\nBLPOP\r\n$1\r\na\r\n$2\r\n-1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(bpop(frame, true).is_err());
+
+        buf.extend_from_slice(
+            b"*6\r\n$6\r\nBLMOVE\r\n$1\r\na\r\n$1\r\nb\r\n$5\r\nRIGHT\r\n$4\r\nleft\r\n$1\r\n0\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: BLMove = frame.try_into()?;
+        assert_eq!(result.source, b"a");
+        assert_eq!(result.destination, b"b");
+        assert!(!result.from_left);
+        assert!(result.to_left);
+        assert_eq!(result.timeout, 0.0);
+        Ok(())
+    }
+
+    // waits for `n` clients to be blocked
+    async fn wait_blocked(backend: &Backend, n: usize) {
+        while backend.blocking().blocked_clients() != n {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bpop_blocking() -> Result<()> {
+        let backend = Backend::new();
+        let bpop = |left| BPop {
+            keys: vec![b"list1".to_vec(), b"list2".to_vec()],
+            left,
+            timeout: 0.0,
+        };
+        let cmd = BPop {
+            timeout: 0.05,
+            ..bpop(true)
+        };
+        assert_eq!(
+            cmd.execute_blocking(&backend).await,
+            RespFrame::Null(RespNull)
+        );
+
+        // woken up by a push to any of the lists
+        let cloned = backend.clone();
+        let cmd = bpop(true);
+        let handle = tokio::spawn(async move { cmd.execute_blocking(&cloned).await });
+        wait_blocked(&backend, 1).await;
+        let elements = ["a", "b"].map(|e| BulkString::from(e).into());
+        backend.rpush("list2", elements)?;
+        let expected = RespArray::new([
+            BulkString::from("list2").into(),
+            BulkString::from("a").into(),
+        ]);
+        assert_eq!(handle.await?, expected.into());
+
+        // not blocked when there is already an element, BRPOP takes it from the tail
+        let expected = RespArray::new([
+            BulkString::from("list2").into(),
+            BulkString::from("b").into(),
+        ]);
+        assert_eq!(
+            bpop(false).execute_blocking(&backend).await,
+            expected.into()
+        );
+        assert!(!backend.exists(b"list2"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blmove_blocking() -> Result<()> {
+        let backend = Backend::new();
+        let blmove = |timeout| BLMove {
+            source: b"source".to_vec(),
+            destination: b"destination".to_vec(),
+            from_left: false,
+            to_left: true,
+            timeout,
+        };
+        assert_eq!(
+            blmove(0.05).execute_blocking(&backend).await,
+            RespFrame::Null(RespNull)
+        );
+
+        let cloned = backend.clone();
+        let handle = tokio::spawn(async move { blmove(0.0).execute_blocking(&cloned).await });
+        wait_blocked(&backend, 1).await;
+        backend.rpush("destination", [BulkString::from("x").into()])?;
+        assert_eq!(backend.blocking().blocked_clients(), 1);
+        let elements = ["a", "b"].map(|e| BulkString::from(e).into());
+        backend.rpush("source", elements)?;
+        assert_eq!(handle.await?, BulkString::from("b").into());
+
+        let destination = backend.lmpop(&[b"destination".to_vec()], true, 2)?;
+        let expected = vec![BulkString::from("b").into(), BulkString::from("x").into()];
+        assert_eq!(destination, Some((b"destination".to_vec(), expected)));
+
+        // a destination of another type leaves the source untouched
+        backend.set(b"destination".to_vec(), BulkString::from("v").into());
+        let reply = blmove(0.0).execute_blocking(&backend).await;
+        assert!(matches!(reply, RespFrame::Error(_)));
+        assert_eq!(
+            backend.lpos(b"source", &BulkString::from("a").into(), 1, 1, 0)?,
+            vec![0]
+        );
+        Ok(())
+    }
+}
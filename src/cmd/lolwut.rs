@@ -0,0 +1,265 @@
+use super::{
+    command::CommandSpec, extract_args, extract_integer, validate_variadic_command, CommandError,
+    CommandExecutor, Lolwut,
+};
+use crate::{BulkString, RespArray, RespFrame, VerbatimString};
+use rand::Rng;
+use std::f64::consts::PI;
+
+pub(super) const COMMANDS: &[CommandSpec] = &[CommandSpec::new(
+    "lolwut",
+    -1,
+    "server",
+    "Displays computer art and the Redis version.",
+)
+.flags(&["readonly", "fast"])];
+
+// the art of version 5, also drawn for any later version
+const ART_VERSION: i64 = 5;
+// columns of the output, squares per row and squares per column
+const DEFAULT_SIZE: [i64; 3] = [66, 8, 12];
+const MAX_SIZE: [i64; 3] = [1000, 200, 200];
+
+impl CommandExecutor for Lolwut {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let version = env!("CARGO_PKG_VERSION");
+        let text = if self.version >= ART_VERSION {
+            let [cols, squares_per_row, squares_per_col] = self.size;
+            let canvas = schotter(
+                cols as usize,
+                squares_per_row as usize,
+                squares_per_col as usize,
+            );
+            format!(
+                "{}\nGeorg Nees - schotter, plotter on paper, 1968. Redis ver. {version}\n",
+                canvas.render()
+            )
+        } else {
+            format!("Redis ver. {version}\n")
+        };
+        // verbatim strings are RESP3 only
+        if backend.protocol() >= 3 {
+            VerbatimString::text(text).into()
+        } else {
+            BulkString::new(text).into()
+        }
+    }
+}
+
+// A monochrome canvas rendered with braille characters, each covering 2x4 pixels.
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<bool>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![false; width * height],
+        }
+    }
+
+    fn set(&mut self, x: i64, y: i64) {
+        if (0..self.width as i64).contains(&x) && (0..self.height as i64).contains(&y) {
+            self.pixels[y as usize * self.width + x as usize] = true;
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.pixels[y * self.width + x]
+    }
+
+    // Bresenham's line algorithm
+    fn line(&mut self, (mut x1, mut y1): (i64, i64), (x2, y2): (i64, i64)) {
+        let (dx, dy) = ((x2 - x1).abs(), (y2 - y1).abs());
+        let (sx, sy) = (if x1 < x2 { 1 } else { -1 }, if y1 < y2 { 1 } else { -1 });
+        let mut err = dx - dy;
+        loop {
+            self.set(x1, y1);
+            if x1 == x2 && y1 == y2 {
+                break;
+            }
+            let e2 = err * 2;
+            if e2 > -dy {
+                err -= dy;
+                x1 += sx;
+            }
+            if e2 < dx {
+                err += dx;
+                y1 += sy;
+            }
+        }
+    }
+
+    // A square of side `size` centered on (x, y), rotated by `angle` radians.
+    fn square(&mut self, (x, y): (f64, f64), size: f64, angle: f64) {
+        // the corners lie on the circle through them, starting at 45 degrees
+        let radius = size / 2.0 * std::f64::consts::SQRT_2;
+        let corners = (0..4)
+            .map(|k| {
+                let a = PI / 4.0 + PI / 2.0 * k as f64 + angle;
+                (
+                    (x + a.sin() * radius).round() as i64,
+                    (y + a.cos() * radius).round() as i64,
+                )
+            })
+            .collect::<Vec<_>>();
+        for k in 0..4 {
+            self.line(corners[k], corners[(k + 1) % 4]);
+        }
+    }
+
+    fn render(&self) -> String {
+        // bit of each pixel of a 2x4 cell in the braille block, by row then column
+        const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+        let mut text = String::new();
+        for row in (0..self.height).step_by(4) {
+            if row > 0 {
+                text.push('\n');
+            }
+            for col in (0..self.width).step_by(2) {
+                let mut dots = 0;
+                for (dy, bits) in DOTS.iter().enumerate() {
+                    for (dx, bit) in bits.iter().enumerate() {
+                        if self.get(col + dx, row + dy) {
+                            dots |= bit;
+                        }
+                    }
+                }
+                text.push(char::from_u32(0x2800 + dots).unwrap_or(' '));
+            }
+        }
+        text
+    }
+}
+
+// Georg Nees' "Schotter": a grid of squares getting more and more disordered from top to bottom.
+fn schotter(cols: usize, squares_per_row: usize, squares_per_col: usize) -> Canvas {
+    let width = cols * 2;
+    let padding = if width > 4 { 2 } else { 0 };
+    let side = (width - padding * 2) as f64 / squares_per_row as f64;
+    let height = (side * squares_per_col as f64) as usize + padding * 2;
+    let mut canvas = Canvas::new(width, height);
+    let mut rng = rand::thread_rng();
+    for y in 0..squares_per_col {
+        for x in 0..squares_per_row {
+            let mut cx = x as f64 * side + side / 2.0 + padding as f64;
+            let mut cy = y as f64 * side + side / 2.0 + padding as f64;
+            let mut angle = 0.0;
+            // the first two rows stay in order
+            if y > 1 {
+                let disorder = y as f64 / squares_per_col as f64;
+                let sign = |rng: &mut rand::rngs::ThreadRng| if rng.gen() { 1.0 } else { -1.0 };
+                angle = sign(&mut rng) * rng.gen::<f64>() * disorder * PI / 4.0;
+                cx += sign(&mut rng) * rng.gen::<f64>() * disorder * side / 3.0;
+                cy += sign(&mut rng) * rng.gen::<f64>() * disorder * side / 3.0;
+            }
+            canvas.square((cx, cy), side, angle);
+        }
+    }
+    canvas
+}
+
+impl TryFrom<RespArray> for Lolwut {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "lolwut", 0)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let version = match args.peek() {
+            Some(RespFrame::BulkString(arg)) if arg.eq_ignore_ascii_case(b"version") => {
+                args.next();
+                extract_integer(args.next())?
+            }
+            _ => ART_VERSION,
+        };
+        let mut size = DEFAULT_SIZE;
+        for (i, arg) in args.enumerate() {
+            let Some(value) = size.get_mut(i) else {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()));
+            };
+            *value = extract_integer(Some(arg))?.clamp(1, MAX_SIZE[i]);
+        }
+        Ok(Lolwut { version, size })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_lolwut_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\nLOLWUT\r\n$7\r\nVERSION\r\n$1\r\n5\r\n$2\r\n40\r\n$4\r\n9999\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Lolwut = frame.try_into()?;
+        assert_eq!(result.version, 5);
+        assert_eq!(result.size, [40, 200, 12]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_canvas_render() {
+        let mut canvas = Canvas::new(4, 4);
+        canvas.line((0, 0), (3, 3));
+        // (0,0) (1,1) in the first cell, (2,2) (3,3) in the second
+        assert_eq!(canvas.render(), "\u{2811}\u{2884}");
+    }
+
+    #[test]
+    fn test_lolwut_command() {
+        let server = Backend::new();
+        let backend = server.connect("127.0.0.1:5000".to_string(), "127.0.0.1:6379".to_string());
+        backend.set_protocol(3);
+        let cmd = Lolwut {
+            version: 5,
+            size: [20, 4, 3],
+        };
+        let RespFrame::VerbatimString(art) = cmd.execute(&backend) else {
+            panic!("expected a verbatim string");
+        };
+        assert_eq!(art.format(), b"txt");
+        let text = String::from_utf8(art.to_vec()).expect("utf8");
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0].chars().count(), 20);
+        assert!(lines[lines.len() - 1].starts_with("Georg Nees - schotter"));
+
+        let cmd = Lolwut {
+            version: 1,
+            size: DEFAULT_SIZE,
+        };
+        let expected = format!("Redis ver. {}\n", env!("CARGO_PKG_VERSION"));
+        assert_eq!(cmd.execute(&backend), VerbatimString::text(expected).into());
+    }
+
+    #[test]
+    fn test_lolwut_resp2() {
+        let backend = Backend::new();
+        let cmd = Lolwut {
+            version: 1,
+            size: DEFAULT_SIZE,
+        };
+        let expected = format!("Redis ver. {}\n", env!("CARGO_PKG_VERSION"));
+        assert_eq!(cmd.execute(&backend), BulkString::new(expected).into());
+
+        let cmd = Lolwut {
+            version: 5,
+            size: [20, 4, 3],
+        };
+        let RespFrame::BulkString(art) = cmd.execute(&backend) else {
+            panic!("expected a bulk string");
+        };
+        let text = String::from_utf8(art.to_vec()).expect("utf8");
+        assert!(text.contains("Georg Nees - schotter"));
+    }
+}
@@ -1,20 +1,50 @@
 use super::{
-    extract_args, validate_command, CommandError, CommandExecutor, Echo, Get, Set, RESP_OK,
+    command::CommandSpec, extract_args, extract_integer, validate_command, CommandError,
+    CommandExecutor, Echo, Get, Ping, Select, Set, SwapDb, RESP_OK,
 };
-use crate::{BulkString, RespArray, RespFrame, RespNull};
+use crate::{BulkString, NotifyClass, RespArray, RespFrame, RespNull, SimpleError, SimpleString};
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("get", 2, "string", "Returns the string value of a key.")
+        .flags(&["readonly", "fast"])
+        .keys(1, 1, 1),
+    CommandSpec::new("set", 3, "string", "Sets the string value of a key.")
+        .flags(&["write"])
+        .keys(1, 1, 1),
+    CommandSpec::new("echo", 2, "connection", "Returns the given string.").flags(&["fast"]),
+    CommandSpec::new(
+        "ping",
+        -1,
+        "connection",
+        "Returns the server's liveliness response.",
+    )
+    .flags(&["fast"]),
+    CommandSpec::new(
+        "select",
+        2,
+        "connection",
+        "Changes the selected database of the connection.",
+    )
+    .flags(&["loading", "stale", "fast"]),
+    CommandSpec::new("swapdb", 3, "server", "Swaps two databases.").flags(&["write", "fast"]),
+];
 
 impl CommandExecutor for Get {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
         match backend.get(&self.key) {
-            Some(value) => value,
-            None => RespFrame::Null(RespNull),
+            Ok(Some(value)) => value,
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => SimpleError::new(e.to_string()).into(),
         }
     }
 }
 
 impl CommandExecutor for Set {
-    fn execute(self, backend: &crate::Backend) -> RespFrame {
-        backend.set(self.key, self.value);
+    fn execute(mut self, backend: &crate::Backend) -> RespFrame {
+        // the value is taken, the command is still needed to report the event
+        let value = std::mem::replace(&mut self.value, RespFrame::Null(RespNull));
+        backend.set(self.key.clone(), value);
+        self.notify(backend, NotifyClass::String, "set", &self.key);
         RESP_OK.clone()
     }
 }
@@ -25,6 +55,54 @@ impl CommandExecutor for Echo {
     }
 }
 
+impl CommandExecutor for Ping {
+    fn execute(self, _backend: &crate::Backend) -> RespFrame {
+        match self.message {
+            Some(message) => BulkString::new(message).into(),
+            None => SimpleString::new("PONG").into(),
+        }
+    }
+}
+
+impl Ping {
+    // A subscribed connection can only receive arrays, so PING replies with the "pong" push
+    // message and the given message (or an empty one) instead.
+    pub fn subscribed_reply(self) -> RespFrame {
+        let message = match self.message {
+            Some(message) => BulkString::new(message),
+            None => BulkString::from(""),
+        };
+        RespArray::new([BulkString::from("pong").into(), message.into()]).into()
+    }
+}
+
+impl CommandExecutor for Select {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        // a cluster only has database 0
+        if backend.cluster_enabled() && self.index != 0 {
+            return SimpleError::new("ERR SELECT is not allowed in cluster mode").into();
+        }
+        match usize::try_from(self.index) {
+            Ok(index) if backend.select(index) => RESP_OK.clone(),
+            _ => SimpleError::new("ERR DB index is out of range").into(),
+        }
+    }
+}
+
+impl CommandExecutor for SwapDb {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let (Ok(index1), Ok(index2)) = (usize::try_from(self.index1), usize::try_from(self.index2))
+        else {
+            return SimpleError::new("ERR invalid DB index").into();
+        };
+        if backend.swap_db(index1, index2) {
+            RESP_OK.clone()
+        } else {
+            SimpleError::new("ERR DB index is out of range").into()
+        }
+    }
+}
+
 impl TryFrom<RespArray> for Get {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
@@ -32,9 +110,7 @@ impl TryFrom<RespArray> for Get {
 
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(Get {
-                key: String::from_utf8(key.0)?,
-            }),
+            Some(RespFrame::BulkString(key)) => Ok(Get { key: key.0 }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
     }
@@ -47,10 +123,7 @@ impl TryFrom<RespArray> for Set {
 
         let mut args = extract_args(value, 1)?.into_iter();
         match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(value)) => Ok(Set {
-                key: String::from_utf8(key.0)?,
-                value,
-            }),
+            (Some(RespFrame::BulkString(key)), Some(value)) => Ok(Set { key: key.0, value }),
             _ => Err(CommandError::InvalidArgument(
                 "Invalid key or value".to_string(),
             )),
@@ -65,18 +138,63 @@ impl TryFrom<RespArray> for Echo {
 
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
-            Some(RespFrame::BulkString(msg)) => Ok(Echo {
-                message: String::from_utf8(msg.0)?,
+            Some(RespFrame::BulkString(msg)) => Ok(Echo { message: msg.0 }),
+            _ => Err(CommandError::InvalidArgument("Invalid message".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Ping {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = match value.len() {
+            1 | 2 => extract_args(value, 1)?.into_iter(),
+            n => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "ping command needs at most 1 argument, got {}",
+                    n - 1
+                )))
+            }
+        };
+        match args.next() {
+            Some(RespFrame::BulkString(msg)) => Ok(Ping {
+                message: Some(msg.0),
             }),
-            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+            Some(_) => Err(CommandError::InvalidArgument("Invalid message".to_string())),
+            None => Ok(Ping { message: None }),
         }
     }
 }
 
+impl TryFrom<RespArray> for Select {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["select"], 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(Select {
+            index: extract_integer(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SwapDb {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["swapdb"], 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(SwapDb {
+            index1: extract_integer(args.next())?,
+            index2: extract_integer(args.next())?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Backend, RespDecoder};
+    use crate::{Backend, RespDecoder, RespEncoder};
     use anyhow::Result;
     use bytes::BytesMut;
 
@@ -86,7 +204,7 @@ mod tests {
         buf.extend_from_slice(b"*2\r\n$3\r\nget\r\n$5\r\nhello\r\n");
         let frame = RespArray::decode(&mut buf)?;
         let result: Get = frame.try_into()?;
-        assert_eq!(result.key, "hello");
+        assert_eq!(result.key, b"hello");
         Ok(())
     }
 
@@ -96,7 +214,7 @@ mod tests {
         buf.extend_from_slice(b"*3\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n");
         let frame = RespArray::decode(&mut buf)?;
         let result: Set = frame.try_into()?;
-        assert_eq!(result.key, "hello");
+        assert_eq!(result.key, b"hello");
         assert_eq!(result.value, RespFrame::BulkString(b"world".into()));
         Ok(())
     }
@@ -105,14 +223,14 @@ mod tests {
     fn test_set_get_command() -> Result<()> {
         let backend = Backend::new();
         let cmd = Set {
-            key: "hello".to_string(),
+            key: b"hello".to_vec(),
             value: RespFrame::BulkString(b"world".into()),
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RESP_OK.clone());
 
         let cmd = Get {
-            key: "hello".to_string(),
+            key: b"hello".to_vec(),
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RespFrame::BulkString(b"world".into()));
@@ -126,7 +244,116 @@ mod tests {
         buf.extend_from_slice(b"*2\r\n$4\r\necho\r\n$12\r\nHello World!\r\n");
         let frame = RespArray::decode(&mut buf)?;
         let result: Echo = frame.try_into()?;
-        assert_eq!(result.message, "Hello World!");
+        assert_eq!(result.message, b"Hello World!");
+
+        let backend = Backend::new();
+        let cmd = Echo {
+            message: b"\xff\x00binary".to_vec(),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespFrame::BulkString(b"\xff\x00binary".into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_select() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$6\r\nSELECT\r\n$1\r\n3\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Select = frame.try_into()?;
+        assert_eq!(result.index, 3);
+
+        let backend = Backend::new();
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        let cmd = Select { index: 3 };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(backend.get(b"key")?, None);
+
+        for index in [-1, 16] {
+            let cmd = Select { index };
+            assert_eq!(
+                cmd.execute(&backend),
+                SimpleError::new("ERR DB index is out of range").into()
+            );
+        }
+        assert_eq!(backend.selected_db(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_swapdb() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nSWAPDB\r\n$1\r\n0\r\n$1\r\n1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: SwapDb = frame.try_into()?;
+        assert_eq!((result.index1, result.index2), (0, 1));
+
+        let backend = Backend::new();
+        let other = backend.clone();
+        assert!(other.select(1));
+        backend.set(b"key".to_vec(), BulkString::from("db0").into());
+
+        let cmd = SwapDb {
+            index1: 0,
+            index2: 1,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(backend.get(b"key")?, None);
+        // other connections see the swap from their next command on
+        other.refresh_db();
+        assert_eq!(other.get(b"key")?, Some(BulkString::from("db0").into()));
+
+        let cmd = SwapDb {
+            index1: 0,
+            index2: 16,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR DB index is out of range").into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ping() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$4\r\nPING\r\n*2\r\n$4\r\nping\r\n$5\r\nhello\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Ping = frame.try_into()?;
+        assert_eq!(result.message, None);
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Ping = frame.try_into()?;
+        assert_eq!(result.message.as_deref(), Some(&b"hello"[..]));
+
+        let backend = Backend::new();
+        let cmd = Ping { message: None };
+        assert_eq!(cmd.execute(&backend), SimpleString::new("PONG").into());
+        let cmd = Ping {
+            message: Some(b"hello".to_vec()),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespFrame::BulkString(b"hello".into())
+        );
+
+        // an empty string, not a null, stands for the missing message
+        let cmd = Ping { message: None };
+        let expected = RespArray::new([
+            RespFrame::BulkString(b"pong".into()),
+            RespFrame::BulkString(b"".into()),
+        ]);
+        let reply = cmd.subscribed_reply();
+        assert_eq!(reply, expected.into());
+        assert_eq!(reply.encode(), b"*2\r\n$4\r\npong\r\n$0\r\n\r\n");
+        let cmd = Ping {
+            message: Some(b"hello".to_vec()),
+        };
+        assert_eq!(
+            cmd.subscribed_reply().encode(),
+            b"*2\r\n$4\r\npong\r\n$5\r\nhello\r\n"
+        );
         Ok(())
     }
 }
@@ -0,0 +1,223 @@
+use super::{
+    command::CommandSpec, extract_args, extract_key, extract_string, validate_variadic_command,
+    CommandError, CommandExecutor, Memory, MemorySubcommand,
+};
+use crate::{
+    used_memory_rss, Backend, BulkString, RespArray, RespFrame, RespNull, DEFAULT_SAMPLES,
+};
+
+pub(super) const COMMANDS: &[CommandSpec] = &[CommandSpec::new(
+    "memory",
+    -2,
+    "server",
+    "A container for memory diagnostics commands.",
+)
+.flags(&["readonly"])];
+
+// below this MEMORY DOCTOR has too little to go on
+const DOCTOR_MIN_BYTES: usize = 5 * 1024 * 1024;
+
+impl CommandExecutor for Memory {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match self.subcommand {
+            MemorySubcommand::Usage { key, samples } => match backend.memory_usage(&key, samples) {
+                Some(bytes) => RespFrame::Integer(bytes as i64),
+                None => RespFrame::Null(RespNull),
+            },
+            MemorySubcommand::Stats => memory_stats(backend),
+            MemorySubcommand::Doctor => {
+                let report = if dataset_bytes(backend) < DOCTOR_MIN_BYTES {
+                    "Hi Sam, this instance is empty or is using very little memory, my issues \
+                     detector can't be used in these conditions. Please, leave for your mission on \
+                     Earth and fill it with some data. The new Sam and I will be back to our \
+                     programming as soon as I finished rebooting."
+                } else {
+                    "Hi Sam, I can't find any memory issue in your instance. I can only account \
+                     for what occurs on this base."
+                };
+                BulkString::from(report).into()
+            }
+        }
+    }
+}
+
+fn dataset_bytes(backend: &Backend) -> usize {
+    (0..backend.database_count())
+        .filter_map(|i| backend.database(i))
+        .map(|db| db.dataset_bytes())
+        .sum()
+}
+
+// name/value pairs, with a nested array of the hash table overheads of each non-empty database
+fn memory_stats(backend: &Backend) -> RespFrame {
+    let allocated = used_memory_rss();
+    let dataset = dataset_bytes(backend);
+    let mut reply: Vec<RespFrame> = vec![
+        BulkString::from("total.allocated").into(),
+        RespFrame::Integer(allocated as i64),
+    ];
+    let (mut keys, mut overhead) = (0, 0);
+    for index in 0..backend.database_count() {
+        let Some(db) = backend.database(index) else {
+            continue;
+        };
+        let count = db.key_count().0;
+        if count == 0 {
+            continue;
+        }
+        keys += count;
+        let (main, expires) = db.overhead();
+        overhead += main + expires;
+        reply.push(BulkString::from(format!("db.{index}")).into());
+        reply.push(
+            RespArray::new([
+                BulkString::from("overhead.hashtable.main").into(),
+                RespFrame::Integer(main as i64),
+                BulkString::from("overhead.hashtable.expires").into(),
+                RespFrame::Integer(expires as i64),
+            ])
+            .into(),
+        );
+    }
+    let percentage = if allocated == 0 {
+        0.0
+    } else {
+        dataset as f64 * 100.0 / allocated as f64
+    };
+    reply.extend([
+        BulkString::from("overhead.total").into(),
+        RespFrame::Integer(overhead as i64),
+        BulkString::from("keys.count").into(),
+        RespFrame::Integer(keys as i64),
+        BulkString::from("keys.bytes-per-key").into(),
+        RespFrame::Integer((dataset / keys.max(1)) as i64),
+        BulkString::from("dataset.bytes").into(),
+        RespFrame::Integer(dataset as i64),
+        BulkString::from("dataset.percentage").into(),
+        RespFrame::Double(percentage),
+    ]);
+    RespArray::new(reply).into()
+}
+
+impl TryFrom<RespArray> for Memory {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "memory", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_lowercase();
+        let args = args
+            .map(|arg| extract_key(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let subcommand = match (subcommand.as_str(), args.as_slice()) {
+            ("usage", [key]) => MemorySubcommand::Usage {
+                key: key.clone(),
+                samples: DEFAULT_SAMPLES,
+            },
+            ("usage", [key, option, samples]) if option.eq_ignore_ascii_case(b"samples") => {
+                match std::str::from_utf8(samples).map(str::parse::<usize>) {
+                    Ok(Ok(samples)) => MemorySubcommand::Usage {
+                        key: key.clone(),
+                        samples,
+                    },
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "value is not an integer or out of range".to_string(),
+                        ))
+                    }
+                }
+            }
+            ("usage", [_, ..]) => {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()))
+            }
+            ("stats", []) => MemorySubcommand::Stats,
+            ("doctor", []) => MemorySubcommand::Doctor,
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for '{subcommand}'"
+                )))
+            }
+        };
+        Ok(Memory { subcommand })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecoder;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_memory_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\nMEMORY\r\n$5\r\nUSAGE\r\n$3\r\nkey\r\n$7\r\nSAMPLES\r\n$1\r\n0\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Memory = frame.try_into()?;
+        assert_eq!(
+            result.subcommand,
+            MemorySubcommand::Usage {
+                key: b"key".to_vec(),
+                samples: 0,
+            }
+        );
+
+        buf.extend_from_slice(
+            b"*4\r\n$6\r\nmemory\r\n$5\r\nusage\r\n$3\r\nkey\r\n$7\r\nsamples\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Memory, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_command() {
+        let backend = Backend::new();
+        backend.set(b"key".to_vec(), BulkString::from("value").into());
+
+        let cmd = Memory {
+            subcommand: MemorySubcommand::Usage {
+                key: b"key".to_vec(),
+                samples: DEFAULT_SAMPLES,
+            },
+        };
+        let expected = backend
+            .memory_usage(b"key", DEFAULT_SAMPLES)
+            .expect("exists");
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(expected as i64));
+
+        let cmd = Memory {
+            subcommand: MemorySubcommand::Usage {
+                key: b"missing".to_vec(),
+                samples: 0,
+            },
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd = Memory {
+            subcommand: MemorySubcommand::Stats,
+        };
+        let RespFrame::Array(stats) = cmd.execute(&backend) else {
+            panic!("expected an array");
+        };
+        let position = stats
+            .iter()
+            .position(|f| *f == BulkString::from("keys.count").into())
+            .expect("keys.count is reported");
+        assert_eq!(stats[position + 1], RespFrame::Integer(1));
+        assert!(stats.contains(&BulkString::from("db.0").into()));
+
+        let cmd = Memory {
+            subcommand: MemorySubcommand::Doctor,
+        };
+        let RespFrame::BulkString(report) = cmd.execute(&backend) else {
+            panic!("expected a bulk string");
+        };
+        assert!(report.starts_with(b"Hi Sam, this instance is empty"));
+    }
+}
@@ -2,11 +2,49 @@ use enum_dispatch::enum_dispatch;
 use lazy_static::lazy_static;
 use thiserror::Error;
 
-use crate::{Backend, RespArray, RespError, RespFrame, SimpleString};
+use crate::{
+    Aggregate, Backend, BitOp, BitRange, BulkString, ExpireCondition, GeoQuery, GeoUnit,
+    NotifyClass, PauseMode, RespArray, RespError, RespFrame, SetOp, SimpleString, StreamFields,
+    StreamId, Subscription, TrimOptions, XAddId, ZAddFlags, ZRangeSpec,
+};
 
+mod bitmap;
+mod client;
+mod cluster;
+mod command;
+mod config;
+mod debug;
+mod dump;
+mod expire;
+mod geo;
 mod hmap;
-mod hset;
+mod hyperloglog;
+mod info;
+mod keys;
+mod latency;
+mod list;
+mod lolwut;
 mod map;
+mod memory;
+mod plugin;
+mod pubsub;
+mod replication;
+mod scripting;
+mod server;
+mod set;
+mod stream;
+mod transaction;
+mod zset;
+
+pub use command::{all_commands, lookup_command, CommandSpec, KeySearch};
+use geo::georadius;
+use list::bpop;
+pub use plugin::{CommandRegistry, PluginCall, PluginCommand};
+pub use replication::READONLY_ERROR;
+pub use server::load_append_log;
+use std::ops::Bound;
+pub use transaction::Transaction;
+use zset::zrange_by_score;
 
 lazy_static! {
     static ref RESP_OK: RespFrame = SimpleString::new("OK").into();
@@ -28,6 +66,13 @@ pub enum CommandError {
 #[enum_dispatch]
 pub trait CommandExecutor {
     fn execute(self, backend: &Backend) -> RespFrame;
+
+    // Reports the keyspace event `event` of `class` the command generated on `key`, which
+    // reaches the keyspace and keyevent channels when notify-keyspace-events enables it.
+    // Executors call it for every key they changed, once the change is made.
+    fn notify(&self, backend: &Backend, class: NotifyClass, event: &str, key: &[u8]) {
+        backend.notify_keyspace_event(class, event, key);
+    }
 }
 
 #[enum_dispatch(CommandExecutor)]
@@ -36,12 +81,125 @@ pub enum Command {
     Get(Get),
     Set(Set),
     Echo(Echo),
+    Ping(Ping),
+    Select(Select),
+    SwapDb(SwapDb),
     HGet(HGet),
     HSet(HSet),
     HGetAll(HGetAll),
     HMGet(HMGet),
     SAdd(SAdd),
+    SRem(SRem),
+    SMembers(SMembers),
+    SCard(SCard),
     SIsMember(SIsMember),
+    SMIsMember(SMIsMember),
+    SMove(SMove),
+    SPop(SPop),
+    SRandMember(SRandMember),
+    SCombine(SCombine),
+    SCombineStore(SCombineStore),
+    SInterCard(SInterCard),
+    LPos(LPos),
+    LMPop(LMPop),
+    BLMPop(BLMPop),
+    BPop(BPop),
+    BLMove(BLMove),
+    ZAdd(ZAdd),
+    ZScore(ZScore),
+    ZRem(ZRem),
+    ZCard(ZCard),
+    ZRange(ZRange),
+    ZIncrBy(ZIncrBy),
+    ZCount(ZCount),
+    ZCombine(ZCombine),
+    ZCombineStore(ZCombineStore),
+    ZScan(ZScan),
+    ZRemRange(ZRemRange),
+    BZPopMin(BZPopMin),
+    Del(Del),
+    Exists(Exists),
+    Unlink(Unlink),
+    Scan(Scan),
+    Expire(Expire),
+    Ttl(Ttl),
+    Persist(Persist),
+    Copy(Copy),
+    Touch(Touch),
+    DbSize(DbSize),
+    RandomKey(RandomKey),
+    FlushDb(FlushDb),
+    FlushAll(FlushAll),
+    Object(Object),
+    Dump(Dump),
+    Restore(Restore),
+    Migrate(Migrate),
+    Debug(DebugCommand),
+    Info(Info),
+    Command(CommandMeta),
+    Config(Config),
+    Time(Time),
+    Shutdown(Shutdown),
+    LastSave(LastSave),
+    Save(Save),
+    BgSave(BgSave),
+    BgRewriteAof(BgRewriteAof),
+    PSync(PSync),
+    ReplConf(ReplConf),
+    Wait(Wait),
+    Role(Role),
+    ReplicaOf(ReplicaOf),
+    Asking(Asking),
+    Cluster(Cluster),
+    Client(Client),
+    Hello(Hello),
+    Memory(Memory),
+    Latency(Latency),
+    Lolwut(Lolwut),
+    Monitor(Monitor),
+    Subscribe(Subscribe),
+    Unsubscribe(Unsubscribe),
+    Publish(Publish),
+    PSubscribe(PSubscribe),
+    PUnsubscribe(PUnsubscribe),
+    PubSub(PubSubMeta),
+    SSubscribe(SSubscribe),
+    SUnsubscribe(SUnsubscribe),
+    SPublish(SPublish),
+    Multi(Multi),
+    Exec(Exec),
+    Discard(Discard),
+    Eval(Eval),
+    EvalSha(EvalSha),
+    Script(Script),
+    Function(Function),
+    FCall(FCall),
+    FCallRo(FCallRo),
+    XAdd(XAdd),
+    XLen(XLen),
+    XRange(XRange),
+    XRevRange(XRevRange),
+    XRead(XRead),
+    XGroup(XGroup),
+    XReadGroup(XReadGroup),
+    XAck(XAck),
+    XTrim(XTrim),
+    XDel(XDel),
+    XInfo(XInfo),
+    BitCount(BitCount),
+    BitPos(BitPos),
+    BitOpStore(BitOpStore),
+    PfAdd(PfAdd),
+    PfCount(PfCount),
+    PfMerge(PfMerge),
+    GeoAdd(GeoAdd),
+    GeoPos(GeoPos),
+    GeoDist(GeoDist),
+    GeoSearch(GeoSearch),
+    GeoSearchStore(GeoSearchStore),
+
+    // a command registered by the embedding application, see CommandRegistry
+    PluginCall(PluginCall),
 
     // unrecognized command
     Unrecognized(Unrecognized),
@@ -49,36 +207,85 @@ pub enum Command {
 
 #[derive(Debug)]
 pub struct Get {
-    key: String,
+    key: Vec<u8>,
 }
 
 #[derive(Debug)]
 pub struct Set {
-    key: String,
+    key: Vec<u8>,
     value: RespFrame,
 }
 
+// ECHO message
+// the message is returned verbatim, it doesn't have to be valid UTF-8
+// "*2\r\n$4\r\nECHO\r\n$11\r\nHello World\r\n"
+// redis> ECHO "Hello World"
+// "Hello World"
 #[derive(Debug)]
 pub struct Echo {
-    message: String,
+    message: Vec<u8>,
+}
+
+// PING [message]
+// "*1\r\n$4\r\nPING\r\n"
+// redis> PING
+// PONG
+// redis> PING "hello world"
+// "hello world"
+#[derive(Debug)]
+pub struct Ping {
+    message: Option<Vec<u8>>,
+}
+
+// SELECT index
+// databases are numbered from 0 to the `databases` config parameter (16 by default) minus one
+// "*2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n"
+// redis> SET key "db0"
+// "OK"
+// redis> SELECT 1
+// "OK"
+// redis> GET key
+// (nil)
+#[derive(Debug)]
+pub struct Select {
+    index: i64,
+}
+
+// SWAPDB index1 index2
+// connections that selected one of the databases see the contents of the other one from then on
+// "*3\r\n$6\r\nSWAPDB\r\n$1\r\n0\r\n$1\r\n1\r\n"
+// redis> SET key "db0"
+// "OK"
+// redis> SWAPDB 0 1
+// "OK"
+// redis> GET key
+// (nil)
+// redis> SELECT 1
+// "OK"
+// redis> GET key
+// "db0"
+#[derive(Debug)]
+pub struct SwapDb {
+    index1: i64,
+    index2: i64,
 }
 
 #[derive(Debug)]
 pub struct HGet {
-    key: String,
+    key: Vec<u8>,
     field: String,
 }
 
 #[derive(Debug)]
 pub struct HSet {
-    key: String,
+    key: Vec<u8>,
     field: String,
     value: RespFrame,
 }
 
 #[derive(Debug)]
 pub struct HGetAll {
-    key: String,
+    key: Vec<u8>,
     sort: bool,
 }
 
@@ -91,7 +298,7 @@ pub struct HGetAll {
 // "*5\r\n$5\r\nHMGET\r\n$6\r\nmyhash\r\n$6\r\nfield1\r\n$6\r\nfield2\r\n$7\r\nnofield\r\n"
 #[derive(Debug)]
 pub struct HMGet {
-    hash: String,
+    hash: Vec<u8>,
     fields: Vec<String>,
 }
 
@@ -105,10 +312,40 @@ pub struct HMGet {
 // (integer) 0
 #[derive(Debug)]
 pub struct SAdd {
-    key: String,
+    key: Vec<u8>,
+    members: Vec<String>,
+}
+
+// SREM key member [member ...]
+// SREM myset "one" "four": "*4\r\n$4\r\nSREM\r\n$5\r\nmyset\r\n$3\r\none\r\n$4\r\nfour\r\n"
+// redis> SADD myset "one" "two" "three"
+// (integer) 3
+// redis> SREM myset "one" "four"
+// (integer) 1
+#[derive(Debug)]
+pub struct SRem {
+    key: Vec<u8>,
     members: Vec<String>,
 }
 
+// SMEMBERS key
+// SMEMBERS myset: "*2\r\n$8\r\nSMEMBERS\r\n$5\r\nmyset\r\n"
+#[derive(Debug)]
+pub struct SMembers {
+    key: Vec<u8>,
+}
+
+// SCARD key
+// SCARD myset: "*2\r\n$5\r\nSCARD\r\n$5\r\nmyset\r\n"
+// redis> SADD myset "Hello" "World"
+// (integer) 2
+// redis> SCARD myset
+// (integer) 2
+#[derive(Debug)]
+pub struct SCard {
+    key: Vec<u8>,
+}
+
 // SISMEMBER key member
 // SISMEMBER myset "one": "*3\r\n$9\r\nSISMEMBER\r\n$5\r\nmyset\r\n$3\r\none\r\n"
 // SISMEMBER myset "two": "*3\r\n$9\r\nSISMEMBER\r\n$5\r\nmyset\r\n$3\r\ntwo\r\n"
@@ -120,10 +357,1765 @@ pub struct SAdd {
 // (integer) 0
 #[derive(Debug)]
 pub struct SIsMember {
-    key: String,
+    key: Vec<u8>,
+    member: String,
+}
+
+// SMISMEMBER key member [member ...]
+// SMISMEMBER myset "one" "notamember": "*4\r\n$10\r\nSMISMEMBER\r\n$5\r\nmyset\r\n$3\r\none\r\n$10\r\nnotamember\r\n"
+// redis> SADD myset "one"
+// (integer) 1
+// redis> SMISMEMBER myset "one" "notamember"
+// 1) (integer) 1
+// 2) (integer) 0
+#[derive(Debug)]
+pub struct SMIsMember {
+    key: Vec<u8>,
+    members: Vec<String>,
+}
+
+// SMOVE source destination member
+// SMOVE myset myotherset "two": "*4\r\n$5\r\nSMOVE\r\n$5\r\nmyset\r\n$10\r\nmyotherset\r\n$3\r\ntwo\r\n"
+// redis> SADD myset "one" "two"
+// (integer) 2
+// redis> SMOVE myset myotherset "two"
+// (integer) 1
+// redis> SMOVE myset myotherset "four"
+// (integer) 0
+#[derive(Debug)]
+pub struct SMove {
+    source: Vec<u8>,
+    destination: Vec<u8>,
+    member: String,
+}
+
+// SPOP key [count]
+// SPOP myset: "*2\r\n$4\r\nSPOP\r\n$5\r\nmyset\r\n"
+// SPOP myset 3: "*3\r\n$4\r\nSPOP\r\n$5\r\nmyset\r\n$1\r\n3\r\n"
+// redis> SADD myset "one" "two" "three"
+// (integer) 3
+// redis> SPOP myset
+// "one"
+// redis> SPOP myset 3
+// 1) "two"
+// 2) "three"
+#[derive(Debug)]
+pub struct SPop {
+    key: Vec<u8>,
+    count: Option<usize>,
+}
+
+// SRANDMEMBER key [count]
+// SRANDMEMBER myset -5: "*3\r\n$11\r\nSRANDMEMBER\r\n$5\r\nmyset\r\n$2\r\n-5\r\n"
+// redis> SADD myset one two three
+// (integer) 3
+// redis> SRANDMEMBER myset
+// "three"
+// redis> SRANDMEMBER myset 2
+// 1) "two"
+// 2) "three"
+// redis> SRANDMEMBER myset -5
+// 1) "one"
+// 2) "two"
+// 3) "three"
+// 4) "one"
+// 5) "three"
+#[derive(Debug)]
+pub struct SRandMember {
+    key: Vec<u8>,
+    count: Option<i64>,
+}
+
+// SINTER key [key ...]
+// SUNION key [key ...]
+// SDIFF key [key ...]
+// SINTER key1 key2: "*3\r\n$6\r\nSINTER\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n"
+// redis> SADD key1 "a" "b" "c"
+// (integer) 3
+// redis> SADD key2 "c" "d" "e"
+// (integer) 3
+// redis> SINTER key1 key2
+// 1) "c"
+// redis> SDIFF key1 key2
+// 1) "a"
+// 2) "b"
+#[derive(Debug)]
+pub struct SCombine {
+    op: SetOp,
+    keys: Vec<Vec<u8>>,
+}
+
+// SINTERSTORE destination key [key ...]
+// SUNIONSTORE destination key [key ...]
+// SDIFFSTORE destination key [key ...]
+// redis> SUNIONSTORE key key1 key2
+// (integer) 5
+#[derive(Debug)]
+pub struct SCombineStore {
+    op: SetOp,
+    destination: Vec<u8>,
+    keys: Vec<Vec<u8>>,
+}
+
+// SINTERCARD numkeys key [key ...] [LIMIT limit]
+// SINTERCARD 2 key1 key2 LIMIT 1: "*6\r\n$10\r\nSINTERCARD\r\n$1\r\n2\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n$5\r\nLIMIT\r\n$1\r\n1\r\n"
+// redis> SADD key1 "a" "b" "c" "d"
+// (integer) 4
+// redis> SADD key2 "c" "d" "e"
+// (integer) 3
+// redis> SINTERCARD 2 key1 key2
+// (integer) 2
+// redis> SINTERCARD 2 key1 key2 LIMIT 1
+// (integer) 1
+#[derive(Debug)]
+pub struct SInterCard {
+    keys: Vec<Vec<u8>>,
+    limit: usize,
+}
+
+// LPOS key element [RANK rank] [COUNT num-matches] [MAXLEN len]
+// LPOS mylist c: "*3\r\n$4\r\nLPOS\r\n$6\r\nmylist\r\n$1\r\nc\r\n"
+// redis> RPUSH mylist a b c d 1 2 3 4 3 3 3
+// (integer) 11
+// redis> LPOS mylist 3
+// (integer) 6
+// redis> LPOS mylist 3 COUNT 0 RANK 2
+// 1) (integer) 8
+// 2) (integer) 9
+// 3) (integer) 10
+#[derive(Debug)]
+pub struct LPos {
+    key: Vec<u8>,
+    element: RespFrame,
+    rank: i64,
+    count: Option<usize>,
+    maxlen: usize,
+}
+
+// LMPOP numkeys key [key ...] <LEFT | RIGHT> [COUNT count]
+// LMPOP 2 mylist mylist2 LEFT: "*5\r\n$5\r\nLMPOP\r\n$1\r\n2\r\n$6\r\nmylist\r\n$7\r\nmylist2\r\n$4\r\nLEFT\r\n"
+// redis> LPUSH mylist "one" "two" "three" "four" "five"
+// (integer) 5
+// redis> LMPOP 1 mylist LEFT
+// 1) "mylist"
+// 2) 1) "five"
+// redis> LMPOP 2 nolist mylist RIGHT COUNT 2
+// 1) "mylist"
+// 2) 1) "one"
+//    2) "two"
+#[derive(Debug)]
+pub struct LMPop {
+    keys: Vec<Vec<u8>>,
+    left: bool,
+    count: usize,
+}
+
+// BLMPOP timeout numkeys key [key ...] <LEFT | RIGHT> [COUNT count]
+// Blocks up to `timeout` seconds (0 blocks forever) until one of the lists can be popped.
+#[derive(Debug)]
+pub struct BLMPop {
+    timeout: f64,
+    pop: LMPop,
+}
+
+// BLPOP key [key ...] timeout
+// BRPOP key [key ...] timeout
+// Pops an element from the head (BLPOP) or the tail (BRPOP) of the first non-empty list,
+// blocking up to `timeout` seconds (0 blocks forever) until there is one.
+// redis> RPUSH list1 a b c
+// (integer) 3
+// redis> BLPOP list2 list1 0
+// 1) "list1"
+// 2) "a"
+#[derive(Debug)]
+pub struct BPop {
+    keys: Vec<Vec<u8>>,
+    left: bool,
+    timeout: f64,
+}
+
+// BLMOVE source destination <LEFT | RIGHT> <LEFT | RIGHT> timeout
+// Moves an element from one end of `source` to one end of `destination`, blocking up to
+// `timeout` seconds (0 blocks forever) until `source` has one.
+// redis> RPUSH mylist one two
+// (integer) 2
+// redis> BLMOVE mylist myother RIGHT LEFT 0
+// "two"
+#[derive(Debug)]
+pub struct BLMove {
+    source: Vec<u8>,
+    destination: Vec<u8>,
+    from_left: bool,
+    to_left: bool,
+    timeout: f64,
+}
+
+// ZADD key [NX | XX] [GT | LT] [CH] [INCR] score member [score member ...]
+// ZADD myzset 1 "one": "*4\r\n$4\r\nZADD\r\n$6\r\nmyzset\r\n$1\r\n1\r\n$3\r\none\r\n"
+// redis> ZADD myzset 1 "one"
+// (integer) 1
+// redis> ZADD myzset 2 "two" 3 "three"
+// (integer) 2
+// redis> ZADD myzset XX CH GT 5 "one" 1 "two"
+// (integer) 1
+// redis> ZADD myzset INCR 1.5 "one"
+// "6.5"
+#[derive(Debug)]
+pub struct ZAdd {
+    key: Vec<u8>,
+    members: Vec<(f64, String)>,
+    flags: ZAddFlags,
+    // reply with the number of changed (added or updated) members
+    ch: bool,
+}
+
+// ZSCORE key member
+// ZSCORE myzset "one": "*3\r\n$6\r\nZSCORE\r\n$6\r\nmyzset\r\n$3\r\none\r\n"
+// redis> ZSCORE myzset "one"
+// "1"
+#[derive(Debug)]
+pub struct ZScore {
+    key: Vec<u8>,
     member: String,
 }
 
+// ZREM key member [member ...]
+// ZREM myzset "two": "*3\r\n$4\r\nZREM\r\n$6\r\nmyzset\r\n$3\r\ntwo\r\n"
+// redis> ZREM myzset "two"
+// (integer) 1
+#[derive(Debug)]
+pub struct ZRem {
+    key: Vec<u8>,
+    members: Vec<String>,
+}
+
+// ZCARD key
+// ZCARD myzset: "*2\r\n$5\r\nZCARD\r\n$6\r\nmyzset\r\n"
+// redis> ZCARD myzset
+// (integer) 2
+#[derive(Debug)]
+pub struct ZCard {
+    key: Vec<u8>,
+}
+
+// ZRANGE key start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count] [WITHSCORES]
+// ZRANGE myzset 0 -1 WITHSCORES: "*5\r\n$6\r\nZRANGE\r\n$6\r\nmyzset\r\n$1\r\n0\r\n$2\r\n-1\r\n$10\r\nWITHSCORES\r\n"
+// redis> ZADD myzset 1 "one" 2 "two" 3 "three"
+// (integer) 3
+// redis> ZRANGE myzset 0 -1 WITHSCORES
+// 1) "one"
+// 2) "1"
+// 3) "two"
+// 4) "2"
+// 5) "three"
+// 6) "3"
+// redis> ZRANGE myzset (1 +inf BYSCORE LIMIT 0 1
+// 1) "two"
+// redis> ZRANGE myzset [c - BYLEX REV
+// ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]
+// ZREVRANGEBYSCORE key max min [WITHSCORES] [LIMIT offset count]
+// Both are parsed into a ZRANGE ... BYSCORE [REV] command.
+// redis> ZRANGEBYSCORE myzset (1 2
+// 1) "two"
+// redis> ZREVRANGEBYSCORE myzset +inf -inf LIMIT 0 1
+// 1) "three"
+#[derive(Debug)]
+pub struct ZRange {
+    key: Vec<u8>,
+    range: ZRangeSpec,
+    rev: bool,
+    offset: usize,
+    count: Option<usize>,
+    withscores: bool,
+}
+
+// ZINCRBY key increment member
+// ZINCRBY myzset 2 "one": "*4\r\n$7\r\nZINCRBY\r\n$6\r\nmyzset\r\n$1\r\n2\r\n$3\r\none\r\n"
+// redis> ZADD myzset 1 "one"
+// (integer) 1
+// redis> ZINCRBY myzset 2 "one"
+// "3"
+#[derive(Debug)]
+pub struct ZIncrBy {
+    key: Vec<u8>,
+    increment: f64,
+    member: String,
+}
+
+// ZCOUNT key min max
+// ZCOUNT myzset (1 3: "*4\r\n$6\r\nZCOUNT\r\n$6\r\nmyzset\r\n$2\r\n(1\r\n$1\r\n3\r\n"
+// redis> ZADD myzset 1 "one" 2 "two" 3 "three"
+// (integer) 3
+// redis> ZCOUNT myzset -inf +inf
+// (integer) 3
+// redis> ZCOUNT myzset (1 3
+// (integer) 2
+#[derive(Debug)]
+pub struct ZCount {
+    key: Vec<u8>,
+    min: Bound<f64>,
+    max: Bound<f64>,
+}
+
+// ZUNION numkeys key [key ...] [WEIGHTS weight [weight ...]] [AGGREGATE <SUM | MIN | MAX>] [WITHSCORES]
+// ZINTER numkeys key [key ...] [WEIGHTS weight [weight ...]] [AGGREGATE <SUM | MIN | MAX>] [WITHSCORES]
+// ZDIFF numkeys key [key ...] [WITHSCORES]
+// ZUNION 2 zset1 zset2: "*4\r\n$6\r\nZUNION\r\n$1\r\n2\r\n$5\r\nzset1\r\n$5\r\nzset2\r\n"
+// redis> ZADD zset1 1 "one" 2 "two"
+// (integer) 2
+// redis> ZADD zset2 1 "one" 2 "two" 3 "three"
+// (integer) 3
+// redis> ZINTER 2 zset1 zset2 WEIGHTS 2 3 WITHSCORES
+// 1) "one"
+// 2) "5"
+// 3) "two"
+// 4) "10"
+#[derive(Debug)]
+pub struct ZCombine {
+    op: SetOp,
+    keys: Vec<Vec<u8>>,
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+    withscores: bool,
+}
+
+// ZUNIONSTORE destination numkeys key [key ...] [WEIGHTS weight [weight ...]] [AGGREGATE <SUM | MIN | MAX>]
+// ZINTERSTORE destination numkeys key [key ...] [WEIGHTS weight [weight ...]] [AGGREGATE <SUM | MIN | MAX>]
+// ZDIFFSTORE destination numkeys key [key ...]
+// redis> ZUNIONSTORE out 2 zset1 zset2 WEIGHTS 2 3
+// (integer) 3
+#[derive(Debug)]
+pub struct ZCombineStore {
+    op: SetOp,
+    destination: Vec<u8>,
+    keys: Vec<Vec<u8>>,
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+}
+
+// ZSCAN key cursor [MATCH pattern] [COUNT count] [NOSCORES]
+// "*6\r\n$5\r\nZSCAN\r\n$4\r\nzset\r\n$1\r\n0\r\n$5\r\nMATCH\r\n$2\r\nm*\r\n"
+// redis> ZADD zset 1 "one" 2 "two"
+// (integer) 2
+// redis> ZSCAN zset 0
+// 1) "0"
+// 2) 1) "one"
+//    2) "1"
+//    3) "two"
+//    4) "2"
+#[derive(Debug)]
+pub struct ZScan {
+    key: Vec<u8>,
+    cursor: u64,
+    pattern: Option<Vec<u8>>,
+    count: usize,
+    noscores: bool,
+}
+
+// ZREMRANGEBYRANK key start stop
+// ZREMRANGEBYSCORE key min max
+// ZREMRANGEBYLEX key min max
+// ZREMRANGEBYRANK myzset 0 1: "*4\r\n$15\r\nZREMRANGEBYRANK\r\n$6\r\nmyzset\r\n$1\r\n0\r\n$1\r\n1\r\n"
+// redis> ZADD myzset 1 "one" 2 "two" 3 "three"
+// (integer) 3
+// redis> ZREMRANGEBYRANK myzset 0 1
+// (integer) 2
+// redis> ZREMRANGEBYSCORE myzset -inf (3
+// (integer) 0
+// redis> ZREMRANGEBYLEX myzset [a [z
+// (integer) 1
+#[derive(Debug)]
+pub struct ZRemRange {
+    key: Vec<u8>,
+    range: ZRangeSpec,
+}
+
+// BZPOPMIN key [key ...] timeout
+// Pops the member with the lowest score from the first non-empty sorted set, blocking up to
+// `timeout` seconds (0 blocks forever) until there is one.
+// redis> ZADD zset1 0 a 1 b 2 c
+// (integer) 3
+// redis> BZPOPMIN zset1 zset2 0
+// 1) "zset1"
+// 2) "a"
+// 3) "0"
+#[derive(Debug)]
+pub struct BZPopMin {
+    keys: Vec<Vec<u8>>,
+    timeout: f64,
+}
+
+// DEL key [key ...]
+// "*3\r\n$3\r\nDEL\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n"
+// redis> SET key1 "Hello"
+// "OK"
+// redis> SET key2 "World"
+// "OK"
+// redis> DEL key1 key2 key3
+// (integer) 2
+#[derive(Debug)]
+pub struct Del {
+    keys: Vec<Vec<u8>>,
+}
+
+// EXISTS key [key ...]
+// a key mentioned several times is counted several times
+// "*3\r\n$6\r\nEXISTS\r\n$4\r\nkey1\r\n$4\r\nkey1\r\n"
+// redis> SET key1 "Hello"
+// "OK"
+// redis> EXISTS key1
+// (integer) 1
+// redis> EXISTS key1 key1 nosuchkey
+// (integer) 2
+#[derive(Debug)]
+pub struct Exists {
+    keys: Vec<Vec<u8>>,
+}
+
+// UNLINK key [key ...]
+// like DEL, but the memory of large values is reclaimed in the background
+// "*3\r\n$6\r\nUNLINK\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n"
+// redis> SET key1 "Hello"
+// "OK"
+// redis> UNLINK key1 key2
+// (integer) 1
+#[derive(Debug)]
+pub struct Unlink {
+    keys: Vec<Vec<u8>>,
+}
+
+// SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]
+// "*4\r\n$4\r\nSCAN\r\n$1\r\n0\r\n$4\r\nTYPE\r\n$4\r\nzset\r\n"
+// redis> SCAN 0 MATCH key* COUNT 100
+// 1) "0"
+// 2) 1) "key1"
+//    2) "key2"
+#[derive(Debug)]
+pub struct Scan {
+    cursor: u64,
+    pattern: Option<Vec<u8>>,
+    count: usize,
+    key_type: Option<String>,
+}
+
+// EXPIRE key seconds [NX | XX | GT | LT]
+// PEXPIRE key milliseconds [NX | XX | GT | LT]
+// EXPIREAT key unix-time-seconds [NX | XX | GT | LT]
+// PEXPIREAT key unix-time-milliseconds [NX | XX | GT | LT]
+// "*4\r\n$6\r\nEXPIRE\r\n$5\r\nmykey\r\n$2\r\n10\r\n$2\r\nNX\r\n"
+// redis> SET mykey "Hello"
+// "OK"
+// redis> EXPIRE mykey 10 XX
+// (integer) 0
+// redis> EXPIRE mykey 10 NX
+// (integer) 1
+// redis> PEXPIREAT mykey 1555555555005
+// (integer) 1
+#[derive(Debug)]
+pub struct Expire {
+    key: Vec<u8>,
+    // in milliseconds, relative to now unless `absolute`
+    time: i64,
+    absolute: bool,
+    condition: ExpireCondition,
+    name: &'static str,
+}
+
+// TTL key
+// PTTL key
+// EXPIRETIME key
+// PEXPIRETIME key
+// -2 if the key does not exist, -1 if it has no expiry
+// "*2\r\n$3\r\nTTL\r\n$5\r\nmykey\r\n"
+// redis> SET mykey "Hello"
+// "OK"
+// redis> TTL mykey
+// (integer) -1
+// redis> EXPIRE mykey 10
+// (integer) 1
+// redis> TTL mykey
+// (integer) 10
+// redis> PEXPIRETIME mykey
+// (integer) 1760700010000
+#[derive(Debug)]
+pub struct Ttl {
+    key: Vec<u8>,
+    // milliseconds per unit of the reply
+    unit: i64,
+    absolute: bool,
+}
+
+// PERSIST key
+// "*2\r\n$7\r\nPERSIST\r\n$5\r\nmykey\r\n"
+// redis> EXPIRE mykey 10
+// (integer) 1
+// redis> PERSIST mykey
+// (integer) 1
+// redis> TTL mykey
+// (integer) -1
+#[derive(Debug)]
+pub struct Persist {
+    key: Vec<u8>,
+}
+
+// COPY source destination [DB destination-db] [REPLACE]
+// "*4\r\n$4\r\nCOPY\r\n$7\r\ndolly\r\n$5\r\nclone\r\n$7\r\nREPLACE\r\n"
+// redis> SET dolly "sheep"
+// "OK"
+// redis> COPY dolly clone
+// (integer) 1
+// redis> GET clone
+// "sheep"
+#[derive(Debug)]
+pub struct Copy {
+    source: Vec<u8>,
+    destination: Vec<u8>,
+    db: Option<i64>,
+    replace: bool,
+}
+
+// TOUCH key [key ...]
+// "*3\r\n$5\r\nTOUCH\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n"
+// redis> SET key1 "Hello"
+// "OK"
+// redis> SET key2 "World"
+// "OK"
+// redis> TOUCH key1 key2
+// (integer) 2
+#[derive(Debug)]
+pub struct Touch {
+    keys: Vec<Vec<u8>>,
+}
+
+// DBSIZE
+// "*1\r\n$6\r\nDBSIZE\r\n"
+// redis> SET key1 "Hello"
+// "OK"
+// redis> DBSIZE
+// (integer) 1
+#[derive(Debug)]
+pub struct DbSize;
+
+// FLUSHDB [ASYNC | SYNC]
+// FLUSHALL [ASYNC | SYNC]
+// ASYNC frees the old values in the background, without either lazyfree-lazy-user-flush decides
+// "*2\r\n$7\r\nFLUSHDB\r\n$5\r\nASYNC\r\n"
+// redis> SET key1 "Hello"
+// "OK"
+// redis> FLUSHDB ASYNC
+// "OK"
+// redis> DBSIZE
+// (integer) 0
+#[derive(Debug)]
+pub struct FlushDb {
+    lazy: Option<bool>,
+}
+
+#[derive(Debug)]
+pub struct FlushAll {
+    lazy: Option<bool>,
+}
+
+// OBJECT ENCODING key
+// OBJECT IDLETIME key
+// OBJECT FREQ key
+// OBJECT REFCOUNT key
+// OBJECT HELP
+// "*3\r\n$6\r\nOBJECT\r\n$8\r\nENCODING\r\n$5\r\nmykey\r\n"
+// redis> SET mykey "Hello"
+// "OK"
+// redis> OBJECT ENCODING mykey
+// "embstr"
+// redis> OBJECT IDLETIME mykey
+// (integer) 3
+#[derive(Debug)]
+pub struct Object {
+    subcommand: ObjectSubcommand,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ObjectSubcommand {
+    Encoding(Vec<u8>),
+    IdleTime(Vec<u8>),
+    Freq(Vec<u8>),
+    RefCount(Vec<u8>),
+    Help,
+}
+
+// DUMP key
+// "*2\r\n$4\r\nDUMP\r\n$5\r\nmykey\r\n"
+// redis> SET mykey 10
+// "OK"
+// redis> DUMP mykey
+// "\x00$2\r\n10\r\n\x01\x00..."
+#[derive(Debug)]
+pub struct Dump {
+    key: Vec<u8>,
+}
+
+// RESTORE key ttl serialized-value [REPLACE] [ABSTTL] [IDLETIME seconds]
+// ttl is in milliseconds, 0 for no expiry, and an absolute unix time with ABSTTL
+// redis> DEL mykey
+// (integer) 1
+// redis> RESTORE mykey 0 "\x00$2\r\n10\r\n\x01\x00..."
+// "OK"
+// MIGRATE host port key|"" destination-db timeout [COPY] [REPLACE] [KEYS key [key ...]]
+// moves keys to another instance with RESTORE, deleting them here unless COPY; timeout is in
+// milliseconds and the empty key goes with KEYS
+// "*6\r\n$7\r\nMIGRATE\r\n$9\r\n127.0.0.1\r\n$4\r\n6380\r\n$3\r\nkey\r\n$1\r\n0\r\n$4\r\n5000\r\n"
+// redis> MIGRATE 127.0.0.1 6380 key 0 5000
+// OK
+#[derive(Debug)]
+pub struct Migrate {
+    host: String,
+    port: i64,
+    keys: Vec<Vec<u8>>,
+    db: i64,
+    timeout: i64,
+    copy: bool,
+    replace: bool,
+}
+
+#[derive(Debug)]
+pub struct Restore {
+    key: Vec<u8>,
+    ttl: i64,
+    payload: Vec<u8>,
+    replace: bool,
+    absttl: bool,
+    idletime: Option<i64>,
+}
+
+// DEBUG OBJECT key
+// DEBUG SLEEP seconds
+// DEBUG SET-ACTIVE-EXPIRE <0 | 1>
+// "*3\r\n$5\r\nDEBUG\r\n$6\r\nOBJECT\r\n$5\r\nmykey\r\n"
+// redis> DEBUG OBJECT mykey
+// refcount:1 encoding:embstr serializedlength:16 lru_seconds_idle:3
+// redis> DEBUG SLEEP 0.5
+// OK
+#[derive(Debug)]
+pub struct DebugCommand {
+    subcommand: DebugSubcommand,
+}
+
+#[derive(Debug, PartialEq)]
+enum DebugSubcommand {
+    Object(Vec<u8>),
+    // seconds
+    Sleep(f64),
+    SetActiveExpire(bool),
+}
+
+// INFO [section [section ...]]
+// sections: server, clients, memory, persistence, stats, replication, commandstats, keyspace,
+// plus "default" (all but commandstats), "all" and "everything"
+// "*2\r\n$4\r\nINFO\r\n$6\r\nserver\r\n"
+// redis> INFO keyspace
+// # Keyspace
+// db0:keys=1,expires=0,avg_ttl=0
+#[derive(Debug)]
+pub struct Info {
+    sections: Vec<String>,
+}
+
+// COMMAND
+// COMMAND COUNT
+// COMMAND INFO [command-name [command-name ...]]
+// COMMAND DOCS [command-name [command-name ...]]
+// COMMAND GETKEYS command [arg [arg ...]]
+// "*3\r\n$7\r\nCOMMAND\r\n$4\r\nINFO\r\n$3\r\nget\r\n"
+// redis> COMMAND COUNT
+// (integer) 134
+// redis> COMMAND INFO get
+// 1) 1) "get"
+//    2) (integer) 2
+//    3) 1) readonly
+//       2) fast
+//    4) (integer) 1
+//    5) (integer) 1
+//    6) (integer) 1
+//    7) 1) @string
+// redis> COMMAND GETKEYS ZUNIONSTORE out 2 zset1 zset2 WEIGHTS 1 2
+// 1) "out"
+// 2) "zset1"
+// 3) "zset2"
+#[derive(Debug)]
+pub struct CommandMeta {
+    query: CommandQuery,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum CommandQuery {
+    List,
+    Count,
+    Info(Vec<String>),
+    Docs(Vec<String>),
+    // the full command to find the keys of
+    GetKeys(Vec<String>),
+}
+
+// CONFIG GET parameter [parameter ...]
+// CONFIG SET parameter value [parameter value ...]
+// CONFIG RESETSTAT
+// parameters are matched as glob-style patterns by GET, SET applies all the values or none
+// "*3\r\n$6\r\nCONFIG\r\n$3\r\nGET\r\n$9\r\nmaxmemory\r\n"
+// redis> CONFIG SET maxmemory 100mb maxmemory-policy allkeys-lru
+// "OK"
+// redis> CONFIG GET maxmemory*
+// 1) "maxmemory"
+// 2) "104857600"
+// 3) "maxmemory-policy"
+// 4) "allkeys-lru"
+// 5) "maxmemory-samples"
+// 6) "5"
+#[derive(Debug)]
+pub struct Config {
+    subcommand: ConfigSubcommand,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ConfigSubcommand {
+    Get(Vec<String>),
+    Set(Vec<(String, String)>),
+    ResetStat,
+}
+
+// TIME
+// "*1\r\n$4\r\nTIME\r\n"
+// redis> TIME
+// 1) "1714000000"
+// 2) "123456"
+#[derive(Debug)]
+pub struct Time;
+
+// SHUTDOWN [NOSAVE | SAVE]
+// without either, a snapshot is saved only when save points are configured; when saving fails
+// the server keeps running, otherwise it replies OK, closes every connection and exits
+// "*2\r\n$8\r\nSHUTDOWN\r\n$6\r\nNOSAVE\r\n"
+// redis> SHUTDOWN NOSAVE
+// "OK"
+#[derive(Debug)]
+pub struct Shutdown {
+    save: Option<bool>,
+}
+
+// LASTSAVE
+// the startup time until the first successful save
+// "*1\r\n$8\r\nLASTSAVE\r\n"
+// redis> LASTSAVE
+// (integer) 1714000000
+#[derive(Debug)]
+pub struct LastSave;
+
+// CLIENT LIST [TYPE normal | master | replica | pubsub] [ID client-id [client-id ...]]
+// CLIENT INFO
+// CLIENT ID
+// CLIENT SETNAME connection-name
+// CLIENT GETNAME
+// CLIENT KILL ip:port
+// CLIENT KILL [ID client-id] [TYPE normal | master | replica | pubsub] [ADDR ip:port]
+//   [LADDR ip:port] [SKIPME yes | no] [MAXAGE maxage]
+// CLIENT PAUSE timeout [WRITE | ALL]
+// CLIENT UNPAUSE
+// "*2\r\n$6\r\nCLIENT\r\n$4\r\nLIST\r\n"
+// redis> CLIENT SETNAME worker-1
+// "OK"
+// redis> CLIENT ID
+// (integer) 3
+// redis> CLIENT INFO
+// "id=3 addr=127.0.0.1:53620 laddr=127.0.0.1:6379 name=worker-1 age=9 idle=0 flags=N db=0 cmd=client user=default resp=2\n"
+// redis> CLIENT KILL TYPE normal MAXAGE 60
+// (integer) 2
+// redis> CLIENT PAUSE 5000 WRITE
+// "OK"
+#[derive(Debug)]
+pub struct Client {
+    subcommand: ClientSubcommand,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ClientSubcommand {
+    List {
+        client_type: Option<String>,
+        ids: Vec<u64>,
+    },
+    Info,
+    Id,
+    // an empty name removes it
+    SetName(String),
+    GetName,
+    // the old `CLIENT KILL addr` form replies OK or an error instead of the number of clients killed
+    Kill {
+        filter: ClientKillFilter,
+        legacy: bool,
+    },
+    // timeout in milliseconds
+    Pause {
+        timeout: i64,
+        mode: PauseMode,
+    },
+    Unpause,
+}
+
+// Which clients CLIENT KILL closes, all the filters given must match.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ClientKillFilter {
+    id: Option<u64>,
+    addr: Option<String>,
+    laddr: Option<String>,
+    client_type: Option<String>,
+    // in seconds, only clients connected at least that long ago
+    max_age: Option<u64>,
+    skip_me: bool,
+}
+
+// HELLO [protover [SETNAME clientname]]
+// switches the connection to protocol version 2 or 3 and replies with what the server and the
+// connection are, as a map in RESP3 and as the same pairs in an array in RESP2
+// "*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n"
+// redis> HELLO 3
+// 1# "server" => "redis"
+// 2# "version" => "0.1.0"
+// 3# "proto" => (integer) 3
+// 4# "id" => (integer) 3
+// 5# "mode" => "standalone"
+// 6# "role" => "master"
+// 7# "modules" => (empty array)
+// redis> HELLO 4
+// (error) NOPROTO unsupported protocol version
+#[derive(Debug)]
+pub struct Hello {
+    // the version the connection keeps when None
+    protover: Option<i64>,
+    name: Option<String>,
+}
+
+// MEMORY USAGE key [SAMPLES count]
+// MEMORY STATS
+// MEMORY DOCTOR
+// "*3\r\n$6\r\nMEMORY\r\n$5\r\nUSAGE\r\n$3\r\nkey\r\n"
+// redis> SET key value
+// "OK"
+// redis> MEMORY USAGE key
+// (integer) 176
+// redis> MEMORY USAGE missing
+// (nil)
+#[derive(Debug)]
+pub struct Memory {
+    subcommand: MemorySubcommand,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum MemorySubcommand {
+    // 0 samples measures every element of a collection
+    Usage { key: Vec<u8>, samples: usize },
+    Stats,
+    Doctor,
+}
+
+// LATENCY LATEST
+// LATENCY HISTORY event
+// LATENCY RESET [event [event ...]]
+// "*2\r\n$7\r\nLATENCY\r\n$6\r\nLATEST\r\n"
+// redis> CONFIG SET latency-monitor-threshold 100
+// "OK"
+// redis> DEBUG SLEEP 0.2
+// "OK"
+// redis> LATENCY LATEST
+// 1) 1) "command"
+//    2) (integer) 1714000000
+//    3) (integer) 201
+//    4) (integer) 201
+// redis> LATENCY HISTORY command
+// 1) 1) (integer) 1714000000
+//    2) (integer) 201
+#[derive(Debug)]
+pub struct Latency {
+    subcommand: LatencySubcommand,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum LatencySubcommand {
+    Latest,
+    History(String),
+    // every event when empty
+    Reset(Vec<String>),
+}
+
+// LOLWUT [VERSION version] [columns [squares-per-row [squares-per-column]]]
+// "*1\r\n$6\r\nLOLWUT\r\n"
+// redis> LOLWUT VERSION 1
+// Redis ver. 0.1.0
+#[derive(Debug)]
+pub struct Lolwut {
+    version: i64,
+    // columns of the output, squares per row and squares per column
+    size: [i64; 3],
+}
+
+// MONITOR
+// "*1\r\n$7\r\nMONITOR\r\n"
+// redis> MONITOR
+// OK
+// 1339518083.107412 [0 127.0.0.1:60866] "keys" "*"
+// 1339518087.877697 [0 127.0.0.1:60866] "dbsize"
+#[derive(Debug)]
+pub struct Monitor;
+
+// SUBSCRIBE channel [channel ...]
+// "*3\r\n$9\r\nSUBSCRIBE\r\n$4\r\nnews\r\n$6\r\nsports\r\n"
+// redis> SUBSCRIBE news sports
+// 1) "subscribe"
+// 2) "news"
+// 3) (integer) 1
+// 1) "subscribe"
+// 2) "sports"
+// 3) (integer) 2
+// 1) "message"
+// 2) "news"
+// 3) "hello"
+#[derive(Debug)]
+pub struct Subscribe {
+    channels: Vec<String>,
+}
+
+// UNSUBSCRIBE [channel [channel ...]]
+// "*2\r\n$11\r\nUNSUBSCRIBE\r\n$4\r\nnews\r\n"
+// redis> UNSUBSCRIBE news
+// 1) "unsubscribe"
+// 2) "news"
+// 3) (integer) 1
+#[derive(Debug)]
+pub struct Unsubscribe {
+    // every channel the client subscribed to when empty
+    channels: Vec<String>,
+}
+
+// PUBLISH channel message
+// "*3\r\n$7\r\nPUBLISH\r\n$4\r\nnews\r\n$5\r\nhello\r\n"
+// redis> PUBLISH news hello
+// (integer) 1
+#[derive(Debug)]
+pub struct Publish {
+    channel: String,
+    message: BulkString,
+}
+
+// PSUBSCRIBE pattern [pattern ...]
+// "*2\r\n$10\r\nPSUBSCRIBE\r\n$6\r\nnews.*\r\n"
+// redis> PSUBSCRIBE news.*
+// 1) "psubscribe"
+// 2) "news.*"
+// 3) (integer) 1
+// 1) "pmessage"
+// 2) "news.*"
+// 3) "news.tech"
+// 4) "hello"
+#[derive(Debug)]
+pub struct PSubscribe {
+    patterns: Vec<String>,
+}
+
+// PUNSUBSCRIBE [pattern [pattern ...]]
+// "*2\r\n$12\r\nPUNSUBSCRIBE\r\n$6\r\nnews.*\r\n"
+// redis> PUNSUBSCRIBE news.*
+// 1) "punsubscribe"
+// 2) "news.*"
+// 3) (integer) 0
+#[derive(Debug)]
+pub struct PUnsubscribe {
+    // every pattern the client subscribed to when empty
+    patterns: Vec<String>,
+}
+
+// PUBSUB CHANNELS [pattern]
+// PUBSUB NUMSUB [channel [channel ...]]
+// PUBSUB NUMPAT
+// PUBSUB SHARDCHANNELS [pattern]
+// PUBSUB SHARDNUMSUB [shardchannel [shardchannel ...]]
+// "*2\r\n$6\r\nPUBSUB\r\n$8\r\nCHANNELS\r\n"
+// redis> PUBSUB CHANNELS news.*
+// 1) "news.tech"
+// redis> PUBSUB NUMSUB news.tech missing
+// 1) "news.tech"
+// 2) (integer) 2
+// 3) "missing"
+// 4) (integer) 0
+// redis> PUBSUB NUMPAT
+// (integer) 1
+#[derive(Debug)]
+pub struct PubSubMeta {
+    query: PubSubQuery,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum PubSubQuery {
+    // channels or shard channels, every active one when there is no pattern
+    Channels {
+        kind: Subscription,
+        pattern: Option<String>,
+    },
+    NumSub {
+        kind: Subscription,
+        channels: Vec<String>,
+    },
+    NumPat,
+}
+
+// SSUBSCRIBE shardchannel [shardchannel ...]
+// "*2\r\n$10\r\nSSUBSCRIBE\r\n$6\r\norders\r\n"
+// redis> SSUBSCRIBE orders
+// 1) "ssubscribe"
+// 2) "orders"
+// 3) (integer) 1
+// 1) "smessage"
+// 2) "orders"
+// 3) "hello"
+#[derive(Debug)]
+pub struct SSubscribe {
+    channels: Vec<String>,
+}
+
+// SUNSUBSCRIBE [shardchannel [shardchannel ...]]
+// "*2\r\n$12\r\nSUNSUBSCRIBE\r\n$6\r\norders\r\n"
+// redis> SUNSUBSCRIBE orders
+// 1) "sunsubscribe"
+// 2) "orders"
+// 3) (integer) 0
+#[derive(Debug)]
+pub struct SUnsubscribe {
+    // every shard channel the client subscribed to when empty
+    channels: Vec<String>,
+}
+
+// SPUBLISH shardchannel message
+// "*3\r\n$8\r\nSPUBLISH\r\n$6\r\norders\r\n$5\r\nhello\r\n"
+// redis> SPUBLISH orders hello
+// (integer) 1
+#[derive(Debug)]
+pub struct SPublish {
+    channel: String,
+    message: BulkString,
+}
+
+// MULTI
+// starts a transaction, the commands that follow are queued until EXEC or DISCARD
+// "*1\r\n$5\r\nMULTI\r\n"
+// redis> MULTI
+// "OK"
+// redis> INCR foo
+// QUEUED
+// redis> EXEC
+// 1) (integer) 1
+#[derive(Debug)]
+pub struct Multi;
+
+// EXEC
+// runs the queued commands of the transaction, no other client's command running meanwhile
+// "*1\r\n$4\r\nEXEC\r\n"
+// redis> MULTI
+// "OK"
+// redis> SET foo bar
+// QUEUED
+// redis> GET foo
+// QUEUED
+// redis> EXEC
+// 1) OK
+// 2) "bar"
+#[derive(Debug)]
+pub struct Exec;
+
+// DISCARD
+// drops the queued commands of the transaction
+// "*1\r\n$7\r\nDISCARD\r\n"
+// redis> MULTI
+// "OK"
+// redis> SET foo bar
+// QUEUED
+// redis> DISCARD
+// "OK"
+#[derive(Debug)]
+pub struct Discard;
+
+// EVAL script numkeys [key [key ...]] [arg [arg ...]]
+// runs a Lua script, which reads its keys from KEYS and its other arguments from ARGV and runs
+// commands with redis.call or redis.pcall, no other client's command running meanwhile
+// "*5\r\n$4\r\nEVAL\r\n$14\r\nreturn KEYS[1]\r\n$1\r\n1\r\n$3\r\nkey\r\n$3\r\narg\r\n"
+// redis> EVAL "return ARGV[1]" 0 hello
+// "hello"
+// redis> EVAL "return redis.call('SET', KEYS[1], ARGV[1])" 1 foo bar
+// OK
+#[derive(Debug)]
+pub struct Eval {
+    script: String,
+    keys: Vec<Vec<u8>>,
+    args: Vec<BulkString>,
+}
+
+// EVALSHA sha1 numkeys [key [key ...]] [arg [arg ...]]
+// runs a script EVAL ran before by the SHA1 digest of its body
+// "*3\r\n$7\r\nEVALSHA\r\n$40\r\ne0e1f9fabfc9d4800c877a703b823ac0578ff8db\r\n$1\r\n0\r\n"
+// redis> EVAL "return 1" 0
+// (integer) 1
+// redis> EVALSHA e0e1f9fabfc9d4800c877a703b823ac0578ff8db 0
+// (integer) 1
+#[derive(Debug)]
+pub struct EvalSha {
+    sha1: String,
+    keys: Vec<Vec<u8>>,
+    args: Vec<BulkString>,
+}
+
+// SCRIPT LOAD script
+// SCRIPT EXISTS sha1 [sha1 ...]
+// SCRIPT FLUSH [ASYNC | SYNC]
+// SCRIPT KILL
+// LOAD caches a script without running it, it has to compile, KILL stops the running script
+// if it didn't write yet
+// "*3\r\n$6\r\nSCRIPT\r\n$4\r\nLOAD\r\n$8\r\nreturn 1\r\n"
+// redis> SCRIPT LOAD "return 1"
+// "e0e1f9fabfc9d4800c877a703b823ac0578ff8db"
+// redis> SCRIPT EXISTS e0e1f9fabfc9d4800c877a703b823ac0578ff8db ffffffffffffffffffffffffffffffffffffffff
+// 1) (integer) 1
+// 2) (integer) 0
+// redis> SCRIPT FLUSH
+// OK
+#[derive(Debug)]
+pub struct Script {
+    subcommand: ScriptSubcommand,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ScriptSubcommand {
+    Load(String),
+    Exists(Vec<String>),
+    Flush,
+    Kill,
+}
+
+// FUNCTION LOAD [REPLACE] function-code
+// FUNCTION LIST [LIBRARYNAME library-name-pattern] [WITHCODE]
+// FUNCTION DELETE library-name
+// FUNCTION FLUSH [ASYNC | SYNC]
+// LOAD runs the code of a library, which starts with "#!lua name=<library>" and registers its
+// functions with redis.register_function, libraries are saved with the databases
+// "*3\r\n$8\r\nFUNCTION\r\n$6\r\nDELETE\r\n$5\r\nmylib\r\n"
+// redis> FUNCTION LOAD "#!lua name=mylib\n redis.register_function('hello', function(keys, args) return 'hello ' .. args[1] end)"
+// "mylib"
+// redis> FUNCTION LIST
+// 1) 1) "library_name"
+//    2) "mylib"
+//    3) "engine"
+//    4) "LUA"
+//    5) "functions"
+//    6) 1) 1) "name"
+//          2) "hello"
+//          3) "description"
+//          4) (nil)
+//          5) "flags"
+//          6) (empty array)
+// redis> FUNCTION DELETE mylib
+// OK
+#[derive(Debug)]
+pub struct Function {
+    subcommand: FunctionSubcommand,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum FunctionSubcommand {
+    Load {
+        code: String,
+        replace: bool,
+    },
+    List {
+        pattern: Option<String>,
+        with_code: bool,
+    },
+    Delete(String),
+    Flush,
+}
+
+// FCALL function numkeys [key [key ...]] [arg [arg ...]]
+// runs a function a library registered, passing it the keys and the other arguments as two
+// tables, no other client's command running meanwhile
+// "*4\r\n$5\r\nFCALL\r\n$5\r\nhello\r\n$1\r\n0\r\n$5\r\nworld\r\n"
+// redis> FCALL hello 0 world
+// "hello world"
+#[derive(Debug)]
+pub struct FCall {
+    function: String,
+    keys: Vec<Vec<u8>>,
+    args: Vec<BulkString>,
+}
+
+// FCALL_RO function numkeys [key [key ...]] [arg [arg ...]]
+// like FCALL, for functions registered with the no-writes flag only
+// "*3\r\n$8\r\nFCALL_RO\r\n$5\r\nhello\r\n$1\r\n0\r\n"
+// redis> FCALL_RO hello 0
+// (error) ERR Can not execute a script with write flag using *_ro command.
+#[derive(Debug)]
+pub struct FCallRo {
+    function: String,
+    keys: Vec<Vec<u8>>,
+    args: Vec<BulkString>,
+}
+
+// XADD key [NOMKSTREAM] [<MAXLEN | MINID> [= | ~] threshold [LIMIT count]] <* | id> field value
+//   [field value ...]
+// appends an entry to a stream, creating the stream unless NOMKSTREAM is given. The ID is
+// "*" to take the current time, "<ms>-*" to take the next sequence number of that millisecond,
+// or "<ms>-<seq>"; it has to be greater than the ID of any entry added before. The stream is
+// then trimmed like XTRIM does
+// "*5\r\n$4\r\nXADD\r\n$6\r\nmystream\r\n$1\r\n*\r\n$4\r\nname\r\n$4\r\nSara\r\n"
+// redis> XADD mystream * name Sara
+// "1526919030474-0"
+// redis> XADD mystream 1526919030474-0 name Sara
+// (error) ERR The ID specified in XADD is equal or smaller than the target stream top item
+#[derive(Debug)]
+pub struct XAdd {
+    key: Vec<u8>,
+    nomkstream: bool,
+    trim: Option<TrimOptions>,
+    id: XAddId,
+    fields: StreamFields,
+}
+
+// XLEN key
+// returns the number of entries of a stream, 0 if the key doesn't exist
+// "*2\r\n$4\r\nXLEN\r\n$6\r\nmystream\r\n"
+// redis> XLEN mystream
+// (integer) 2
+#[derive(Debug)]
+pub struct XLen {
+    key: Vec<u8>,
+}
+
+// XRANGE key start end [COUNT count]
+// returns the entries with IDs between start and end: "-" and "+" stand for the smallest and
+// greatest IDs, an ID without sequence number covers its whole millisecond and "(" before an
+// ID leaves it out
+// "*4\r\n$6\r\nXRANGE\r\n$6\r\nmystream\r\n$1\r\n-\r\n$1\r\n+\r\n"
+// redis> XRANGE mystream - + COUNT 1
+// 1) 1) "1526919030474-0"
+//    2) 1) "name"
+//       2) "Sara"
+#[derive(Debug)]
+pub struct XRange {
+    key: Vec<u8>,
+    start: Bound<StreamId>,
+    end: Bound<StreamId>,
+    count: Option<usize>,
+}
+
+// XREVRANGE key end start [COUNT count]
+// like XRANGE, from the greatest ID to the smallest
+// "*4\r\n$9\r\nXREVRANGE\r\n$6\r\nmystream\r\n$1\r\n+\r\n$1\r\n-\r\n"
+// redis> XREVRANGE mystream + - COUNT 1
+// 1) 1) "1526919030474-1"
+//    2) 1) "name"
+//       2) "Tom"
+#[derive(Debug)]
+pub struct XRevRange {
+    key: Vec<u8>,
+    start: Bound<StreamId>,
+    end: Bound<StreamId>,
+    count: Option<usize>,
+}
+
+// XREAD [COUNT count] [BLOCK milliseconds] STREAMS key [key ...] id [id ...]
+// returns the entries with IDs greater than the given ones from each stream that has any, "$"
+// standing for the last ID of its stream. With BLOCK it waits up to that many milliseconds (0
+// waits forever) until an entry is added to one of them
+// "*6\r\n$5\r\nXREAD\r\n$5\r\nCOUNT\r\n$1\r\n2\r\n$7\r\nSTREAMS\r\n$6\r\nmystream\r\n$1\r\n0\r\n"
+// redis> XREAD COUNT 2 STREAMS mystream 0
+// 1) 1) "mystream"
+//    2) 1) 1) "1526919030474-0"
+//          2) 1) "name"
+//             2) "Sara"
+// redis> XREAD BLOCK 1000 STREAMS mystream $
+// (nil)
+#[derive(Debug)]
+pub struct XRead {
+    count: Option<usize>,
+    // milliseconds
+    block: Option<u64>,
+    // None stands for "$"
+    streams: Vec<(Vec<u8>, Option<StreamId>)>,
+}
+
+// XGROUP CREATE key group <id | $> [MKSTREAM]
+// XGROUP SETID key group <id | $>
+// XGROUP DESTROY key group
+// XGROUP CREATECONSUMER key group consumer
+// XGROUP DELCONSUMER key group consumer
+// manages the consumer groups of a stream: a group delivers each entry after its last delivered
+// ID ("$" being the last ID of the stream) to one of its consumers. DELCONSUMER replies the
+// number of entries the consumer had pending
+// "*5\r\n$6\r\nXGROUP\r\n$6\r\nCREATE\r\n$6\r\nmystream\r\n$7\r\nmygroup\r\n$1\r\n$\r\n"
+// redis> XGROUP CREATE mystream mygroup $
+// OK
+// redis> XGROUP CREATE mystream mygroup 0
+// (error) BUSYGROUP Consumer Group name already exists
+// redis> XGROUP CREATECONSUMER mystream mygroup Alice
+// (integer) 1
+#[derive(Debug)]
+pub struct XGroup {
+    key: Vec<u8>,
+    group: String,
+    subcommand: XGroupSubcommand,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum XGroupSubcommand {
+    // None stands for "$"
+    Create {
+        id: Option<StreamId>,
+        mkstream: bool,
+    },
+    SetId(Option<StreamId>),
+    Destroy,
+    CreateConsumer(String),
+    DelConsumer(String),
+}
+
+// XREADGROUP GROUP group consumer [COUNT count] [BLOCK milliseconds] [NOACK] STREAMS key [key ...]
+//   id [id ...]
+// reads as a consumer of a group: ">" reads the entries never delivered to the group, which
+// become pending for the consumer until XACK unless NOACK is given; any other ID reads the
+// entries pending for the consumer after it. BLOCK waits like XREAD for new entries
+// "*7\r\n$10\r\nXREADGROUP\r\n$5\r\nGROUP\r\n$7\r\nmygroup\r\n$5\r\nAlice\r\n$7\r\nSTREAMS\r\n$8\r\nmystream\r\n$1\r\n>\r\n"
+// redis> XREADGROUP GROUP mygroup Alice COUNT 1 STREAMS mystream >
+// 1) 1) "mystream"
+//    2) 1) 1) "1526569495631-0"
+//          2) 1) "message"
+//             2) "apple"
+#[derive(Debug)]
+pub struct XReadGroup {
+    group: String,
+    consumer: String,
+    count: Option<usize>,
+    // milliseconds
+    block: Option<u64>,
+    noack: bool,
+    // None stands for ">"
+    streams: Vec<(Vec<u8>, Option<StreamId>)>,
+}
+
+// XACK key group id [id ...]
+// removes the entries from the pending entries list of the group, replying how many were in it
+// "*4\r\n$4\r\nXACK\r\n$8\r\nmystream\r\n$7\r\nmygroup\r\n$15\r\n1526569495631-0\r\n"
+// redis> XACK mystream mygroup 1526569495631-0
+// (integer) 1
+#[derive(Debug)]
+pub struct XAck {
+    key: Vec<u8>,
+    group: String,
+    ids: Vec<StreamId>,
+}
+
+// XTRIM key <MAXLEN | MINID> [= | ~] threshold [LIMIT count]
+// removes the oldest entries of a stream, down to `threshold` entries with MAXLEN or those with
+// IDs lower than `threshold` with MINID. With "~" only whole nodes of stream-node-max-entries
+// entries go, at most `count` entries (by default 100 nodes, 0 for no limit), leaving a few more
+// entries than asked for but trimming cheaply. Replies the number of entries removed
+// "*5\r\n$5\r\nXTRIM\r\n$8\r\nmystream\r\n$6\r\nMAXLEN\r\n$1\r\n~\r\n$4\r\n1000\r\n"
+// redis> XTRIM mystream MAXLEN 2
+// (integer) 1
+#[derive(Debug)]
+pub struct XTrim {
+    key: Vec<u8>,
+    options: TrimOptions,
+}
+
+// XDEL key id [id ...]
+// deletes entries of a stream, replying how many existed. Their IDs are never reused
+// "*3\r\n$4\r\nXDEL\r\n$8\r\nmystream\r\n$15\r\n1538561700640-0\r\n"
+// redis> XDEL mystream 1538561700640-0
+// (integer) 1
+#[derive(Debug)]
+pub struct XDel {
+    key: Vec<u8>,
+    ids: Vec<StreamId>,
+}
+
+// XINFO STREAM key
+// XINFO GROUPS key
+// XINFO CONSUMERS key group
+// tells about a stream: its length, IDs and first and last entries; its consumer groups with
+// their pending entries and lag, the number of entries they have yet to deliver; or the
+// consumers of a group with their pending entries and the milliseconds since they last read
+// "*3\r\n$5\r\nXINFO\r\n$6\r\nGROUPS\r\n$8\r\nmystream\r\n"
+// redis> XINFO GROUPS mystream
+// 1)  1) "name"
+//     2) "mygroup"
+//     3) "consumers"
+//     4) (integer) 2
+//     5) "pending"
+//     6) (integer) 2
+//     7) "last-delivered-id"
+//     8) "1638126030001-0"
+//     9) "lag"
+//    10) (integer) 0
+#[derive(Debug)]
+pub struct XInfo {
+    key: Vec<u8>,
+    subcommand: XInfoSubcommand,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum XInfoSubcommand {
+    Stream,
+    Groups,
+    Consumers(String),
+}
+
+// BITCOUNT key [start end [BYTE | BIT]]
+// counts the set bits of a string, optionally between the bytes (or bits) start and end included;
+// negative indexes count from the end of the string
+// "*4\r\n$8\r\nBITCOUNT\r\n$5\r\nmykey\r\n$1\r\n1\r\n$1\r\n1\r\n"
+// redis> SET mykey "foobar"
+// "OK"
+// redis> BITCOUNT mykey
+// (integer) 26
+// redis> BITCOUNT mykey 1 1
+// (integer) 6
+// redis> BITCOUNT mykey 5 30 BIT
+// (integer) 17
+#[derive(Debug)]
+pub struct BitCount {
+    key: Vec<u8>,
+    range: BitRange,
+}
+
+// BITPOS key bit [start [end [BYTE | BIT]]]
+// returns the position of the first bit set to 1 or 0 of a string, -1 if there is none; a string
+// looked for a clear bit without an end is considered padded with clear bits on the right
+// "*3\r\n$6\r\nBITPOS\r\n$5\r\nmykey\r\n$1\r\n0\r\n"
+// redis> SET mykey "\xff\xf0\x00"
+// "OK"
+// redis> BITPOS mykey 0
+// (integer) 12
+// redis> BITPOS mykey 1 2
+// (integer) -1
+// redis> BITPOS mykey 0 7 15 BIT
+// (integer) 12
+#[derive(Debug)]
+pub struct BitPos {
+    key: Vec<u8>,
+    bit: bool,
+    range: BitRange,
+}
+
+// BITOP AND | OR | XOR | NOT destkey key [key ...]
+// stores the bitwise operation of the strings in destkey, the shorter strings padded with zero
+// bytes; NOT takes a single key. Returns the length of the stored string
+// "*5\r\n$5\r\nBITOP\r\n$3\r\nAND\r\n$4\r\ndest\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n"
+// redis> SET key1 "foobar"
+// "OK"
+// redis> SET key2 "abcdef"
+// "OK"
+// redis> BITOP AND dest key1 key2
+// (integer) 6
+// redis> GET dest
+// "`bc`ab"
+#[derive(Debug)]
+pub struct BitOpStore {
+    op: BitOp,
+    destination: Vec<u8>,
+    keys: Vec<Vec<u8>>,
+}
+
+// PFADD key [element [element ...]]
+// adds the elements to the HyperLogLog at key, creating it if needed; returns 1 if its estimated
+// cardinality may have changed, 0 otherwise
+// "*4\r\n$5\r\nPFADD\r\n$3\r\nhll\r\n$1\r\na\r\n$1\r\nb\r\n"
+// redis> PFADD hll a b c d e f g
+// (integer) 1
+// redis> PFCOUNT hll
+// (integer) 7
+#[derive(Debug)]
+pub struct PfAdd {
+    key: Vec<u8>,
+    elements: Vec<String>,
+}
+
+// PFCOUNT key [key ...]
+// returns the estimated cardinality of the union of the HyperLogLogs, merged into a temporary one
+// when there are several keys; missing keys count as empty
+// "*3\r\n$7\r\nPFCOUNT\r\n$4\r\nhll1\r\n$4\r\nhll2\r\n"
+// redis> PFADD hll1 foo bar zap a
+// (integer) 1
+// redis> PFADD hll2 a b c foo
+// (integer) 1
+// redis> PFCOUNT hll1 hll2
+// (integer) 6
+#[derive(Debug)]
+pub struct PfCount {
+    keys: Vec<Vec<u8>>,
+}
+
+// PFMERGE destkey [sourcekey [sourcekey ...]]
+// stores the union of the HyperLogLogs of destkey (if it exists) and the source keys in destkey
+// "*4\r\n$7\r\nPFMERGE\r\n$4\r\nhll3\r\n$4\r\nhll1\r\n$4\r\nhll2\r\n"
+// redis> PFMERGE hll3 hll1 hll2
+// "OK"
+// redis> PFCOUNT hll3
+// (integer) 6
+#[derive(Debug)]
+pub struct PfMerge {
+    destination: Vec<u8>,
+    sources: Vec<Vec<u8>>,
+}
+
+// GEOADD key [NX | XX] [CH] longitude latitude member [longitude latitude member ...]
+// adds the members at their positions to the geospatial index at key, a sorted set scored by the
+// geohash of the positions; the options and the reply are the ones of ZADD
+// "*5\r\n$6\r\nGEOADD\r\n$6\r\nSicily\r\n$9\r\n13.361389\r\n$9\r\n38.115556\r\n$7\r\nPalermo\r\n"
+// redis> GEOADD Sicily 13.361389 38.115556 "Palermo" 15.087269 37.502669 "Catania"
+// (integer) 2
+// redis> GEOADD Sicily 200 38 "Nowhere"
+// (error) ERR invalid longitude,latitude pair 200.000000,38.000000
+#[derive(Debug)]
+pub struct GeoAdd {
+    key: Vec<u8>,
+    // only NX and XX apply
+    flags: ZAddFlags,
+    ch: bool,
+    // the longitude, latitude and name of each member
+    members: Vec<(f64, f64, String)>,
+}
+
+// GEOPOS key [member [member ...]]
+// returns the longitude and latitude of the members, null for the missing ones
+// "*3\r\n$6\r\nGEOPOS\r\n$6\r\nSicily\r\n$7\r\nPalermo\r\n"
+// redis> GEOPOS Sicily Palermo NonExisting
+// 1) 1) "13.361389338970184"
+//    2) "38.1155563954963"
+// 2) (nil)
+#[derive(Debug)]
+pub struct GeoPos {
+    key: Vec<u8>,
+    members: Vec<String>,
+}
+
+// GEODIST key member1 member2 [M | KM | FT | MI]
+// returns the distance between two members in the unit (meters by default), null if one of them
+// is missing
+// "*5\r\n$7\r\nGEODIST\r\n$6\r\nSicily\r\n$7\r\nPalermo\r\n$7\r\nCatania\r\n$2\r\nkm\r\n"
+// redis> GEODIST Sicily Palermo Catania
+// "166274.1516"
+// redis> GEODIST Sicily Palermo Catania km
+// "166.2742"
+#[derive(Debug)]
+pub struct GeoDist {
+    key: Vec<u8>,
+    member1: String,
+    member2: String,
+    unit: GeoUnit,
+}
+
+// GEOSEARCH key <FROMMEMBER member | FROMLONLAT longitude latitude>
+//   <BYRADIUS radius <M | KM | FT | MI> | BYBOX width height <M | KM | FT | MI>> [ASC | DESC]
+//   [COUNT count [ANY]] [WITHCOORD] [WITHDIST] [WITHHASH]
+// returns the members of the geospatial index within the circle or the box centered on a member
+// or a position. COUNT alone returns the nearest ones, with ANY the first ones found. The WITH
+// options turn each member into an array of the member, its distance in the unit of the shape,
+// its geohash and its position
+// "*8\r\n$9\r\nGEOSEARCH\r\n$6\r\nSicily\r\n$10\r\nFROMLONLAT\r\n$2\r\n15\r\n$2\r\n37\r\n$8\r\nBYRADIUS\r\n$3\r\n200\r\n$2\r\nkm\r\n"
+// redis> GEOSEARCH Sicily FROMLONLAT 15 37 BYRADIUS 200 km ASC
+// 1) "Catania"
+// 2) "Palermo"
+// redis> GEOSEARCH Sicily FROMLONLAT 15 37 BYBOX 400 400 km ASC WITHDIST
+// 1) 1) "Catania"
+//    2) "56.4413"
+// 2) 1) "Palermo"
+//    2) "190.4424"
+// GEORADIUS key longitude latitude radius <M | KM | FT | MI> [WITHCOORD] [WITHDIST] [WITHHASH]
+//   [COUNT count [ANY]] [ASC | DESC] [STORE key | STOREDIST key]
+// GEORADIUSBYMEMBER key member radius <M | KM | FT | MI> [WITHCOORD] [WITHDIST] [WITHHASH]
+//   [COUNT count [ANY]] [ASC | DESC] [STORE key | STOREDIST key]
+// Both are parsed into a GEOSEARCH ... BYRADIUS command, or a GEOSEARCHSTORE one with STORE
+// or STOREDIST.
+// redis> GEORADIUS Sicily 15 37 200 km WITHDIST ASC
+// 1) 1) "Catania"
+//    2) "56.4413"
+// 2) 1) "Palermo"
+//    2) "190.4424"
+#[derive(Debug)]
+pub struct GeoSearch {
+    key: Vec<u8>,
+    query: GeoQuery,
+    with_coord: bool,
+    with_dist: bool,
+    with_hash: bool,
+}
+
+// GEOSEARCHSTORE destination source <FROMMEMBER member | FROMLONLAT longitude latitude>
+//   <BYRADIUS radius <M | KM | FT | MI> | BYBOX width height <M | KM | FT | MI>> [ASC | DESC]
+//   [COUNT count [ANY]] [STOREDIST]
+// stores the members GEOSEARCH finds in source as a geospatial index at destination, or as a
+// sorted set scored by their distance with STOREDIST. Returns how many were stored
+// "*9\r\n$14\r\nGEOSEARCHSTORE\r\n$4\r\nnear\r\n$6\r\nSicily\r\n$10\r\nFROMLONLAT\r\n$2\r\n15\r\n$2\r\n37\r\n$8\r\nBYRADIUS\r\n$3\r\n200\r\n$2\r\nkm\r\n"
+// redis> GEOSEARCHSTORE near Sicily FROMLONLAT 15 37 BYRADIUS 200 km STOREDIST
+// (integer) 2
+// redis> ZRANGE near 0 -1
+// 1) "Catania"
+// 2) "Palermo"
+#[derive(Debug)]
+pub struct GeoSearchStore {
+    destination: Vec<u8>,
+    source: Vec<u8>,
+    query: GeoQuery,
+    store_dist: bool,
+}
+
+// RANDOMKEY
+// "*1\r\n$9\r\nRANDOMKEY\r\n"
+// redis> SET key1 "Hello"
+// "OK"
+// redis> RANDOMKEY
+// "key1"
+#[derive(Debug)]
+pub struct RandomKey;
+
+// SAVE
+// writes a snapshot of every database to dir/dbfilename, blocking the server until it is done
+// "*1\r\n$4\r\nSAVE\r\n"
+// redis> SAVE
+// "OK"
+#[derive(Debug)]
+pub struct Save;
+
+// BGSAVE [SCHEDULE]
+// like SAVE in the background; when a save is already running it fails, unless SCHEDULE is
+// given, which starts this one once the running save finished
+// "*1\r\n$6\r\nBGSAVE\r\n"
+// redis> BGSAVE
+// Background saving started
+#[derive(Debug)]
+pub struct BgSave {
+    schedule: bool,
+}
+
+// BGREWRITEAOF
+// rewrites the append only file in the background as the fewest commands recreating the
+// dataset, the writes made meanwhile are appended to the new file before it replaces the old one
+// "*1\r\n$12\r\nBGREWRITEAOF\r\n"
+// redis> BGREWRITEAOF
+// Background append only file rewriting started
+#[derive(Debug)]
+pub struct BgRewriteAof;
+
+// PSYNC replicationid offset
+// sent by a replica, which the connection then streams the dataset to: a snapshot first, then
+// every write made since; "?" and -1 ask for a full resynchronization
+// "*3\r\n$5\r\nPSYNC\r\n$1\r\n?\r\n$2\r\n-1\r\n"
+// redis> PSYNC ? -1
+// FULLRESYNC 8de1787ba490483314a4d30f1c628bc5025eb761 0
+#[derive(Debug)]
+pub struct PSync {
+    replid: String,
+    offset: i64,
+}
+
+// REPLCONF option value [option value ...]
+// configures the replication link during the handshake of a replica (listening-port,
+// ip-address, capa), ACK reports the offset of the stream the replica processed
+// "*3\r\n$8\r\nREPLCONF\r\n$14\r\nlistening-port\r\n$4\r\n6380\r\n"
+// redis> REPLCONF listening-port 6380
+// OK
+#[derive(Debug)]
+pub struct ReplConf {
+    options: Vec<(String, String)>,
+}
+
+// WAIT numreplicas timeout
+// blocks until `numreplicas` replicas acknowledged the writes made so far, or for `timeout`
+// milliseconds at most (0 blocks forever), and returns how many did
+// "*3\r\n$4\r\nWAIT\r\n$1\r\n1\r\n$1\r\n0\r\n"
+// redis> WAIT 1 0
+// (integer) 1
+#[derive(Debug)]
+pub struct Wait {
+    numreplicas: usize,
+    timeout: u64,
+}
+
+// ROLE
+// "*1\r\n$4\r\nROLE\r\n"
+// redis> ROLE
+// 1) "master"
+// 2) (integer) 3129659
+// 3) 1) 1) "127.0.0.1"
+//       2) "9001"
+//       3) "3129242"
+#[derive(Debug)]
+pub struct Role;
+
+// REPLICAOF host port | NO ONE
+// makes the server a replica of the master at host:port, which it connects to in the
+// background, or a master again with NO ONE; SLAVEOF is an alias
+// "*3\r\n$9\r\nREPLICAOF\r\n$9\r\n127.0.0.1\r\n$4\r\n6380\r\n"
+// redis> REPLICAOF 127.0.0.1 6380
+// OK
+#[derive(Debug)]
+pub struct ReplicaOf {
+    master: Option<(String, u16)>,
+}
+
+// ASKING
+// lets the next command of the client run on a hash slot this node is importing, as a client
+// redirected with ASK does
+// "*1\r\n$6\r\nASKING\r\n"
+// redis> ASKING
+// OK
+#[derive(Debug)]
+pub struct Asking;
+
+// CLUSTER INFO
+// CLUSTER MYID
+// CLUSTER SLOTS
+// CLUSTER SHARDS
+// CLUSTER NODES
+// CLUSTER MEET ip port
+// CLUSTER ADDSLOTS slot [slot ...]
+// CLUSTER SETSLOT slot IMPORTING node-id | MIGRATING node-id | NODE node-id | STABLE
+// "*2\r\n$7\r\nCLUSTER\r\n$4\r\nMYID\r\n"
+// redis> CLUSTER ADDSLOTS 0 1 2
+// OK
+// redis> CLUSTER SLOTS
+// 1) 1) (integer) 0
+//    2) (integer) 2
+//    3) 1) "127.0.0.1"
+//       2) (integer) 7000
+//       3) "e7d1eecce10fd6bb5eb35b9f99a514335d9ba9ca"
+#[derive(Debug)]
+pub struct Cluster {
+    subcommand: ClusterSubcommand,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ClusterSubcommand {
+    Info,
+    MyId,
+    Slots,
+    Shards,
+    Nodes,
+    // slots and ports are checked when run
+    Meet { host: String, port: i64 },
+    AddSlots(Vec<i64>),
+    SetSlot { slot: i64, state: SlotState },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SlotState {
+    Importing(String),
+    Migrating(String),
+    Node(String),
+    Stable,
+}
+
 #[derive(Debug)]
 pub struct Unrecognized;
 
@@ -139,6 +2131,25 @@ impl TryFrom<RespFrame> for Command {
     }
 }
 
+impl Command {
+    // Parses a request like TryFrom does, also recognizing the commands registered with the
+    // backend, which TryFrom leaves unrecognized.
+    pub fn parse(frame: impl Into<RespFrame>, backend: &Backend) -> Result<Self, CommandError> {
+        let frame = frame.into();
+        if let RespFrame::Array(array) = &frame {
+            if let Some(RespFrame::BulkString(name)) = array.first() {
+                if let Some(plugin) = backend.commands().get(&String::from_utf8_lossy(name)) {
+                    let RespFrame::Array(array) = frame else {
+                        unreachable!()
+                    };
+                    return Ok(PluginCall::new(plugin, array)?.into());
+                }
+            }
+        }
+        frame.try_into()
+    }
+}
+
 impl CommandExecutor for Unrecognized {
     fn execute(self, _: &Backend) -> RespFrame {
         RESP_OK.clone()
@@ -154,12 +2165,137 @@ impl TryFrom<RespArray> for Command {
                     b"get" => Ok(Get::try_from(v)?.into()),
                     b"set" => Ok(Set::try_from(v)?.into()),
                     b"echo" => Ok(Echo::try_from(v)?.into()),
+                    b"ping" => Ok(Ping::try_from(v)?.into()),
+                    b"select" => Ok(Select::try_from(v)?.into()),
+                    b"swapdb" => Ok(SwapDb::try_from(v)?.into()),
                     b"hget" => Ok(HGet::try_from(v)?.into()),
                     b"hset" => Ok(HSet::try_from(v)?.into()),
                     b"hmget" => Ok(HMGet::try_from(v)?.into()),
                     b"hgetall" => Ok(HGetAll::try_from(v)?.into()),
                     b"sadd" => Ok(SAdd::try_from(v)?.into()),
+                    b"srem" => Ok(SRem::try_from(v)?.into()),
+                    b"smembers" => Ok(SMembers::try_from(v)?.into()),
+                    b"scard" => Ok(SCard::try_from(v)?.into()),
                     b"sismember" => Ok(SIsMember::try_from(v)?.into()),
+                    b"smismember" => Ok(SMIsMember::try_from(v)?.into()),
+                    b"smove" => Ok(SMove::try_from(v)?.into()),
+                    b"spop" => Ok(SPop::try_from(v)?.into()),
+                    b"srandmember" => Ok(SRandMember::try_from(v)?.into()),
+                    b"sinter" | b"sunion" | b"sdiff" => Ok(SCombine::try_from(v)?.into()),
+                    b"sintercard" => Ok(SInterCard::try_from(v)?.into()),
+                    b"sinterstore" | b"sunionstore" | b"sdiffstore" => {
+                        Ok(SCombineStore::try_from(v)?.into())
+                    }
+                    b"lpos" => Ok(LPos::try_from(v)?.into()),
+                    b"lmpop" => Ok(LMPop::try_from(v)?.into()),
+                    b"blmpop" => Ok(BLMPop::try_from(v)?.into()),
+                    b"blpop" => Ok(bpop(v, true)?.into()),
+                    b"brpop" => Ok(bpop(v, false)?.into()),
+                    b"blmove" => Ok(BLMove::try_from(v)?.into()),
+                    b"zadd" => Ok(ZAdd::try_from(v)?.into()),
+                    b"zscore" => Ok(ZScore::try_from(v)?.into()),
+                    b"zrem" => Ok(ZRem::try_from(v)?.into()),
+                    b"zcard" => Ok(ZCard::try_from(v)?.into()),
+                    b"zrange" => Ok(ZRange::try_from(v)?.into()),
+                    b"zincrby" => Ok(ZIncrBy::try_from(v)?.into()),
+                    b"zcount" => Ok(ZCount::try_from(v)?.into()),
+                    b"zrangebyscore" => Ok(zrange_by_score(v, false)?.into()),
+                    b"zrevrangebyscore" => Ok(zrange_by_score(v, true)?.into()),
+                    b"zunion" | b"zinter" | b"zdiff" => Ok(ZCombine::try_from(v)?.into()),
+                    b"zunionstore" | b"zinterstore" | b"zdiffstore" => {
+                        Ok(ZCombineStore::try_from(v)?.into())
+                    }
+                    b"zscan" => Ok(ZScan::try_from(v)?.into()),
+                    b"zremrangebyrank" | b"zremrangebyscore" | b"zremrangebylex" => {
+                        Ok(ZRemRange::try_from(v)?.into())
+                    }
+                    b"bzpopmin" => Ok(BZPopMin::try_from(v)?.into()),
+                    b"del" => Ok(Del::try_from(v)?.into()),
+                    b"exists" => Ok(Exists::try_from(v)?.into()),
+                    b"unlink" => Ok(Unlink::try_from(v)?.into()),
+                    b"scan" => Ok(Scan::try_from(v)?.into()),
+                    b"expire" | b"pexpire" | b"expireat" | b"pexpireat" => {
+                        Ok(Expire::try_from(v)?.into())
+                    }
+                    b"ttl" | b"pttl" | b"expiretime" | b"pexpiretime" => {
+                        Ok(Ttl::try_from(v)?.into())
+                    }
+                    b"persist" => Ok(Persist::try_from(v)?.into()),
+                    b"copy" => Ok(Copy::try_from(v)?.into()),
+                    b"touch" => Ok(Touch::try_from(v)?.into()),
+                    b"dbsize" => Ok(DbSize::try_from(v)?.into()),
+                    b"flushdb" => Ok(FlushDb::try_from(v)?.into()),
+                    b"flushall" => Ok(FlushAll::try_from(v)?.into()),
+                    b"object" => Ok(Object::try_from(v)?.into()),
+                    b"dump" => Ok(Dump::try_from(v)?.into()),
+                    b"restore" => Ok(Restore::try_from(v)?.into()),
+                    b"migrate" => Ok(Migrate::try_from(v)?.into()),
+                    b"debug" => Ok(DebugCommand::try_from(v)?.into()),
+                    b"info" => Ok(Info::try_from(v)?.into()),
+                    b"command" => Ok(CommandMeta::try_from(v)?.into()),
+                    b"config" => Ok(Config::try_from(v)?.into()),
+                    b"time" => Ok(Time::try_from(v)?.into()),
+                    b"shutdown" => Ok(Shutdown::try_from(v)?.into()),
+                    b"lastsave" => Ok(LastSave::try_from(v)?.into()),
+                    b"client" => Ok(Client::try_from(v)?.into()),
+                    b"hello" => Ok(Hello::try_from(v)?.into()),
+                    b"memory" => Ok(Memory::try_from(v)?.into()),
+                    b"latency" => Ok(Latency::try_from(v)?.into()),
+                    b"lolwut" => Ok(Lolwut::try_from(v)?.into()),
+                    b"monitor" => Ok(Monitor::try_from(v)?.into()),
+                    b"subscribe" => Ok(Subscribe::try_from(v)?.into()),
+                    b"unsubscribe" => Ok(Unsubscribe::try_from(v)?.into()),
+                    b"publish" => Ok(Publish::try_from(v)?.into()),
+                    b"psubscribe" => Ok(PSubscribe::try_from(v)?.into()),
+                    b"punsubscribe" => Ok(PUnsubscribe::try_from(v)?.into()),
+                    b"pubsub" => Ok(PubSubMeta::try_from(v)?.into()),
+                    b"ssubscribe" => Ok(SSubscribe::try_from(v)?.into()),
+                    b"sunsubscribe" => Ok(SUnsubscribe::try_from(v)?.into()),
+                    b"spublish" => Ok(SPublish::try_from(v)?.into()),
+                    b"multi" => Ok(Multi::try_from(v)?.into()),
+                    b"exec" => Ok(Exec::try_from(v)?.into()),
+                    b"discard" => Ok(Discard::try_from(v)?.into()),
+                    b"eval" => Ok(Eval::try_from(v)?.into()),
+                    b"evalsha" => Ok(EvalSha::try_from(v)?.into()),
+                    b"script" => Ok(Script::try_from(v)?.into()),
+                    b"function" => Ok(Function::try_from(v)?.into()),
+                    b"fcall" => Ok(FCall::try_from(v)?.into()),
+                    b"fcall_ro" => Ok(FCallRo::try_from(v)?.into()),
+                    b"xadd" => Ok(XAdd::try_from(v)?.into()),
+                    b"xlen" => Ok(XLen::try_from(v)?.into()),
+                    b"xrange" => Ok(XRange::try_from(v)?.into()),
+                    b"xrevrange" => Ok(XRevRange::try_from(v)?.into()),
+                    b"xread" => Ok(XRead::try_from(v)?.into()),
+                    b"xgroup" => Ok(XGroup::try_from(v)?.into()),
+                    b"xreadgroup" => Ok(XReadGroup::try_from(v)?.into()),
+                    b"xack" => Ok(XAck::try_from(v)?.into()),
+                    b"xtrim" => Ok(XTrim::try_from(v)?.into()),
+                    b"xdel" => Ok(XDel::try_from(v)?.into()),
+                    b"xinfo" => Ok(XInfo::try_from(v)?.into()),
+                    b"bitcount" => Ok(BitCount::try_from(v)?.into()),
+                    b"bitpos" => Ok(BitPos::try_from(v)?.into()),
+                    b"bitop" => Ok(BitOpStore::try_from(v)?.into()),
+                    b"pfadd" => Ok(PfAdd::try_from(v)?.into()),
+                    b"pfcount" => Ok(PfCount::try_from(v)?.into()),
+                    b"pfmerge" => Ok(PfMerge::try_from(v)?.into()),
+                    b"geoadd" => Ok(GeoAdd::try_from(v)?.into()),
+                    b"geopos" => Ok(GeoPos::try_from(v)?.into()),
+                    b"geodist" => Ok(GeoDist::try_from(v)?.into()),
+                    b"geosearch" => Ok(GeoSearch::try_from(v)?.into()),
+                    b"geosearchstore" => Ok(GeoSearchStore::try_from(v)?.into()),
+                    b"georadius" => Ok(georadius(v, false)?),
+                    b"georadiusbymember" => Ok(georadius(v, true)?),
+                    b"randomkey" => Ok(RandomKey::try_from(v)?.into()),
+                    b"save" => Ok(Save::try_from(v)?.into()),
+                    b"bgsave" => Ok(BgSave::try_from(v)?.into()),
+                    b"bgrewriteaof" => Ok(BgRewriteAof::try_from(v)?.into()),
+                    b"psync" => Ok(PSync::try_from(v)?.into()),
+                    b"replconf" => Ok(ReplConf::try_from(v)?.into()),
+                    b"wait" => Ok(Wait::try_from(v)?.into()),
+                    b"role" => Ok(Role::try_from(v)?.into()),
+                    b"replicaof" | b"slaveof" => Ok(ReplicaOf::try_from(v)?.into()),
+                    b"asking" => Ok(Asking::try_from(v)?.into()),
+                    b"cluster" => Ok(Cluster::try_from(v)?.into()),
                     _ => Ok(Unrecognized.into()),
                 }
             }
@@ -204,10 +2340,125 @@ fn validate_command(
     Ok(())
 }
 
+fn validate_variadic_command(
+    value: &RespArray,
+    name: &'static str,
+    min_args: usize,
+) -> Result<(), CommandError> {
+    if value.len() < min_args + 1 {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} command needs at least {} argument, got {}",
+            name,
+            min_args,
+            value.len().saturating_sub(1)
+        )));
+    }
+    validate_command(value, &[name], value.len() - 1)
+}
+
+// Finds the set operation of a command (e.g. "sinter" or "zunionstore") from its name.
+fn extract_set_op(
+    value: &RespArray,
+    ops: &[(&'static str, SetOp)],
+) -> Result<(&'static str, SetOp), CommandError> {
+    let name = match value.first() {
+        Some(RespFrame::BulkString(cmd)) => String::from_utf8_lossy(cmd).to_ascii_lowercase(),
+        _ => String::new(),
+    };
+    ops.iter()
+        .find(|(expected, _)| name == *expected)
+        .copied()
+        .ok_or_else(|| CommandError::InvalidCommand(format!("Invalid set operation: {name}")))
+}
+
 fn extract_args(value: RespArray, start: usize) -> Result<Vec<RespFrame>, CommandError> {
     Ok(value.0.into_iter().skip(start).collect::<Vec<RespFrame>>())
 }
 
+fn extract_string(frame: Option<RespFrame>) -> Result<String, CommandError> {
+    match frame {
+        Some(RespFrame::BulkString(s)) => Ok(String::from_utf8(s.0)?),
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid argument".to_string(),
+        )),
+    }
+}
+
+// a key or any argument matched against keys as is, which doesn't have to be valid UTF-8
+fn extract_key(frame: Option<RespFrame>) -> Result<Vec<u8>, CommandError> {
+    match frame {
+        Some(RespFrame::BulkString(s)) => Ok(s.0),
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid argument".to_string(),
+        )),
+    }
+}
+
+// every argument after the command name, as keys
+fn extract_keys(value: RespArray) -> Result<Vec<Vec<u8>>, CommandError> {
+    extract_args(value, 1)?
+        .into_iter()
+        .map(|key| extract_key(Some(key)))
+        .collect()
+}
+
+// cursor [MATCH pattern] [COUNT count], followed by the options only some commands accept
+#[derive(Debug, Default)]
+struct ScanArgs {
+    cursor: u64,
+    pattern: Option<Vec<u8>>,
+    count: usize,
+    // any other option, lowercased, left for the command to interpret
+    flags: Vec<String>,
+}
+
+fn extract_scan_args(mut args: impl Iterator<Item = RespFrame>) -> Result<ScanArgs, CommandError> {
+    let cursor = extract_string(args.next())?
+        .parse::<u64>()
+        .map_err(|_| CommandError::InvalidArgument("invalid cursor".to_string()))?;
+    let mut scan = ScanArgs {
+        cursor,
+        count: 10,
+        ..Default::default()
+    };
+    while let Some(arg) = args.next() {
+        match extract_string(Some(arg))?.to_ascii_lowercase().as_str() {
+            "match" => scan.pattern = Some(extract_key(args.next())?),
+            "count" => match extract_integer(args.next())? {
+                n if n > 0 => scan.count = n as usize,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            flag => scan.flags.push(flag.to_string()),
+        }
+    }
+    Ok(scan)
+}
+
+fn extract_float(frame: Option<RespFrame>) -> Result<f64, CommandError> {
+    extract_string(frame)?
+        .parse()
+        .map_err(|_| CommandError::InvalidArgument("value is not a valid float".to_string()))
+}
+
+// The timeout of a blocking command, in seconds, 0 blocking forever.
+fn extract_timeout(frame: Option<RespFrame>) -> Result<f64, CommandError> {
+    let timeout = extract_string(frame)?.parse::<f64>().map_err(|_| {
+        CommandError::InvalidArgument("timeout is not a float or out of range".to_string())
+    })?;
+    if timeout < 0.0 || !timeout.is_finite() {
+        return Err(CommandError::InvalidArgument(
+            "timeout is negative".to_string(),
+        ));
+    }
+    Ok(timeout)
+}
+
+fn extract_integer(frame: Option<RespFrame>) -> Result<i64, CommandError> {
+    extract_string(frame)?.parse().map_err(|_| {
+        CommandError::InvalidArgument("value is not an integer or out of range".to_string())
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -238,4 +2489,28 @@ mod tests {
         assert_eq!(ret, RespFrame::Null(RespNull));
         Ok(())
     }
+
+    #[test]
+    fn test_binary_key() -> Result<()> {
+        let backend = Backend::new();
+        let run = |request: &[u8]| -> Result<RespFrame> {
+            let mut buf = BytesMut::from(request);
+            let cmd: Command = RespArray::decode(&mut buf)?.try_into()?;
+            Ok(cmd.execute(&backend))
+        };
+        // the key isn't valid UTF-8, it's stored and replied as is
+        let ret = run(b"*3\r\n$3\r\nSET\r\n$3\r\nk\xff\x00\r\n$1\r\nv\r\n")?;
+        assert_eq!(ret, RESP_OK.clone());
+        let ret = run(b"*2\r\n$3\r\nGET\r\n$3\r\nk\xff\x00\r\n")?;
+        assert_eq!(ret, BulkString::from("v").into());
+        let ret = run(b"*2\r\n$3\r\nGET\r\n$3\r\nk\xef\xbf\r\n")?;
+        assert_eq!(ret, RespFrame::Null(RespNull));
+        let ret = run(b"*4\r\n$4\r\nSCAN\r\n$1\r\n0\r\n$5\r\nMATCH\r\n$3\r\nk\xff*\r\n")?;
+        let keys = RespArray::new([BulkString::from(b"k\xff\x00").into()]);
+        assert_eq!(
+            ret,
+            RespArray::new([BulkString::from("0").into(), keys.into()]).into()
+        );
+        Ok(())
+    }
 }
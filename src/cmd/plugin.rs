@@ -0,0 +1,239 @@
+use super::{
+    command::{builtin_command, CommandSpec},
+    extract_args, CommandError, CommandExecutor,
+};
+use crate::{Backend, BulkString, RespArray, RespFrame};
+use std::fmt;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+
+// A command implemented by the application embedding the server, see CommandRegistry. Its
+// spec tells the name, arity and flags, which the server then treats like those of its own
+// commands: COMMAND reports them and they apply to scripts, transactions and busy scripts.
+pub trait PluginCommand: Send + Sync {
+    fn spec(&self) -> CommandSpec;
+
+    // Runs the command with the arguments after its name, already checked against the arity.
+    fn execute(&self, backend: &Backend, args: Vec<BulkString>) -> RespFrame;
+}
+
+// The commands registered on top of the builtin ones, owned by the backend so that servers
+// embedded in the same process each have their own. An application registers them at
+// startup, before serving connections:
+//
+//   backend.commands().register(MyCommand)?;
+#[derive(Default)]
+pub struct CommandRegistry {
+    plugins: RwLock<Vec<Plugin>>,
+}
+
+#[derive(Clone)]
+pub(super) struct Plugin {
+    spec: CommandSpec,
+    command: Arc<dyn PluginCommand>,
+}
+
+impl CommandRegistry {
+    // Adds `command`. Fails when its name is taken by a builtin or registered command.
+    pub fn register(&self, command: impl PluginCommand + 'static) -> Result<(), CommandError> {
+        let spec = command.spec();
+        if spec.arity == 0 || spec.name.is_empty() {
+            return Err(CommandError::InvalidCommand(format!(
+                "'{}' needs a name and an arity other than 0",
+                spec.name
+            )));
+        }
+        let mut plugins = self.plugins.write().unwrap_or_else(|e| e.into_inner());
+        let taken = plugins
+            .iter()
+            .any(|plugin| plugin.spec.name.eq_ignore_ascii_case(spec.name));
+        if taken || builtin_command(spec.name).is_some() {
+            return Err(CommandError::InvalidCommand(format!(
+                "'{}' already exists",
+                spec.name
+            )));
+        }
+        plugins.push(Plugin {
+            spec,
+            command: Arc::new(command),
+        });
+        Ok(())
+    }
+
+    // The spec of the registered command `name`, case-insensitively.
+    pub fn lookup(&self, name: &str) -> Option<CommandSpec> {
+        self.get(name).map(|plugin| plugin.spec)
+    }
+
+    // The specs of the registered commands, in the order they were registered.
+    pub fn specs(&self) -> Vec<CommandSpec> {
+        self.read()
+            .iter()
+            .map(|plugin| plugin.spec.clone())
+            .collect()
+    }
+
+    pub(super) fn get(&self, name: &str) -> Option<Plugin> {
+        self.read()
+            .iter()
+            .find(|plugin| plugin.spec.name.eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Vec<Plugin>> {
+        self.plugins.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl fmt::Debug for CommandRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = self
+            .read()
+            .iter()
+            .map(|plugin| plugin.spec.name)
+            .collect::<Vec<_>>();
+        f.debug_struct("CommandRegistry")
+            .field("commands", &names)
+            .finish()
+    }
+}
+
+// A call of a registered command, as dispatched by Command.
+pub struct PluginCall {
+    plugin: Plugin,
+    args: Vec<BulkString>,
+}
+
+impl PluginCall {
+    pub(super) fn new(plugin: Plugin, value: RespArray) -> Result<Self, CommandError> {
+        let spec = &plugin.spec;
+        let argc = value.len() as i64;
+        if (spec.arity > 0 && argc != spec.arity) || argc < -spec.arity {
+            return Err(CommandError::InvalidArgument(format!(
+                "wrong number of arguments for '{}' command",
+                spec.name
+            )));
+        }
+        let args = extract_args(value, 1)?
+            .into_iter()
+            .map(|arg| match arg {
+                RespFrame::BulkString(arg) => Ok(arg),
+                _ => Err(CommandError::InvalidArgument(
+                    "Invalid argument".to_string(),
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(PluginCall { plugin, args })
+    }
+}
+
+impl fmt::Debug for PluginCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PluginCall")
+            .field("name", &self.plugin.spec.name)
+            .field("args", &self.args)
+            .finish()
+    }
+}
+
+impl CommandExecutor for PluginCall {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.plugin.command.execute(backend, self.args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::{all_commands, lookup_command, Command};
+    use crate::{RespDecoder, SimpleError};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    // HELLO.ADD key increment: adds to an integer stored as a string, the way a module would
+    struct Add;
+
+    impl PluginCommand for Add {
+        fn spec(&self) -> CommandSpec {
+            CommandSpec::new("hello.add", 3, "module", "Adds to an integer.")
+                .flags(&["write", "denyoom"])
+                .keys(1, 1, 1)
+        }
+
+        fn execute(&self, backend: &Backend, args: Vec<BulkString>) -> RespFrame {
+            let key = args[0].to_vec();
+            let parse = |arg: &BulkString| String::from_utf8_lossy(arg).parse::<i64>().ok();
+            let current = match backend.get(&key) {
+                Ok(Some(RespFrame::BulkString(value))) => parse(&value),
+                Ok(Some(_)) => None,
+                Ok(None) => Some(0),
+                Err(e) => return SimpleError::new(e.to_string()).into(),
+            };
+            match (current, parse(&args[1])) {
+                (Some(current), Some(increment)) => {
+                    let sum = current + increment;
+                    backend.set(key, BulkString::from(sum.to_string()).into());
+                    RespFrame::Integer(sum)
+                }
+                _ => SimpleError::new("ERR value is not an integer").into(),
+            }
+        }
+    }
+
+    struct Get;
+
+    impl PluginCommand for Get {
+        fn spec(&self) -> CommandSpec {
+            CommandSpec::new("GET", 2, "module", "Shadows GET.").flags(&["readonly"])
+        }
+
+        fn execute(&self, _backend: &Backend, _args: Vec<BulkString>) -> RespFrame {
+            RespFrame::Integer(0)
+        }
+    }
+
+    fn command(backend: &Backend, buf: &[u8]) -> Result<Command> {
+        let mut buf = BytesMut::from(buf);
+        let frame = RespArray::decode(&mut buf)?;
+        Ok(Command::parse(frame, backend)?)
+    }
+
+    #[test]
+    fn test_plugin_command() -> Result<()> {
+        let backend = Backend::new();
+        let registry = backend.commands();
+        registry.register(Add)?;
+        assert!(registry.register(Add).is_err());
+        assert!(registry.register(Get).is_err());
+
+        let spec = lookup_command(&backend, "HELLO.ADD").expect("hello.add is registered");
+        assert_eq!(spec.arity, 3);
+        assert!(all_commands(&backend).contains(&spec));
+
+        let cmd = command(&backend, b"*3\r\n$9\r\nHELLO.ADD\r\n$1\r\nk\r\n$1\r\n5\r\n")?;
+        assert!(matches!(cmd, Command::PluginCall(_)));
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(5));
+        let cmd = command(
+            &backend,
+            b"*3\r\n$9\r\nhello.add\r\n$1\r\nk\r\n$2\r\n-7\r\n",
+        )?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(-2));
+        assert_eq!(backend.get(b"k")?, Some(BulkString::from("-2").into()));
+
+        assert!(command(&backend, b"*2\r\n$9\r\nhello.add\r\n$1\r\nk\r\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_plugin_command_per_backend() -> Result<()> {
+        let backend = Backend::new();
+        backend.commands().register(Add)?;
+
+        // another server in the same process doesn't see it
+        let other = Backend::new();
+        assert_eq!(lookup_command(&other, "hello.add"), None);
+        let cmd = command(&other, b"*3\r\n$9\r\nhello.add\r\n$1\r\nk\r\n$1\r\n5\r\n")?;
+        assert!(matches!(cmd, Command::Unrecognized(_)));
+        other.commands().register(Add)?;
+        Ok(())
+    }
+}
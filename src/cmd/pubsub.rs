@@ -0,0 +1,695 @@
+use super::{
+    command::CommandSpec, extract_args, extract_string, validate_command,
+    validate_variadic_command, CommandError, CommandExecutor, PSubscribe, PUnsubscribe, PubSubMeta,
+    PubSubQuery, Publish, SPublish, SSubscribe, SUnsubscribe, Subscribe, Unsubscribe,
+};
+use crate::{Backend, BulkString, RespArray, RespFrame, RespNull, Subscription};
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new(
+        "subscribe",
+        -2,
+        "pubsub",
+        "Listens for messages published to channels.",
+    )
+    .flags(&["pubsub", "noscript", "loading", "stale"]),
+    CommandSpec::new(
+        "unsubscribe",
+        -1,
+        "pubsub",
+        "Stops listening to messages posted to channels.",
+    )
+    .flags(&["pubsub", "noscript", "loading", "stale"]),
+    CommandSpec::new(
+        "psubscribe",
+        -2,
+        "pubsub",
+        "Listens for messages published to channels that match one or more patterns.",
+    )
+    .flags(&["pubsub", "noscript", "loading", "stale"]),
+    CommandSpec::new(
+        "punsubscribe",
+        -1,
+        "pubsub",
+        "Stops listening to messages published to channels that match one or more patterns.",
+    )
+    .flags(&["pubsub", "noscript", "loading", "stale"]),
+    CommandSpec::new("publish", 3, "pubsub", "Posts a message to a channel.")
+        .flags(&["pubsub", "loading", "stale", "fast"]),
+    CommandSpec::new(
+        "ssubscribe",
+        -2,
+        "pubsub",
+        "Listens for messages published to shard channels.",
+    )
+    .flags(&["pubsub", "noscript", "loading", "stale"])
+    .keys(1, -1, 1),
+    CommandSpec::new(
+        "sunsubscribe",
+        -1,
+        "pubsub",
+        "Stops listening to messages posted to shard channels.",
+    )
+    .flags(&["pubsub", "noscript", "loading", "stale"])
+    .keys(1, -1, 1),
+    CommandSpec::new(
+        "spublish",
+        3,
+        "pubsub",
+        "Posts a message to a shard channel.",
+    )
+    .flags(&["pubsub", "loading", "stale", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new("pubsub", -2, "pubsub", "A container for Pub/Sub commands.")
+        .flags(&["pubsub", "loading", "stale"]),
+];
+
+impl Subscribe {
+    // One confirmation per channel, each a reply of its own.
+    pub fn confirmations(self, backend: &Backend) -> Vec<RespFrame> {
+        subscribe(backend, Subscription::Channel, self.channels)
+    }
+}
+
+impl Unsubscribe {
+    // One confirmation per channel, each a reply of its own.
+    pub fn confirmations(self, backend: &Backend) -> Vec<RespFrame> {
+        unsubscribe(backend, Subscription::Channel, self.channels)
+    }
+}
+
+impl PSubscribe {
+    // One confirmation per pattern, each a reply of its own.
+    pub fn confirmations(self, backend: &Backend) -> Vec<RespFrame> {
+        subscribe(backend, Subscription::Pattern, self.patterns)
+    }
+}
+
+impl PUnsubscribe {
+    // One confirmation per pattern, each a reply of its own.
+    pub fn confirmations(self, backend: &Backend) -> Vec<RespFrame> {
+        unsubscribe(backend, Subscription::Pattern, self.patterns)
+    }
+}
+
+impl SSubscribe {
+    // One confirmation per shard channel, each a reply of its own.
+    pub fn confirmations(self, backend: &Backend) -> Vec<RespFrame> {
+        subscribe(backend, Subscription::ShardChannel, self.channels)
+    }
+}
+
+impl SUnsubscribe {
+    // One confirmation per shard channel, each a reply of its own.
+    pub fn confirmations(self, backend: &Backend) -> Vec<RespFrame> {
+        unsubscribe(backend, Subscription::ShardChannel, self.channels)
+    }
+}
+
+fn subscribe(backend: &Backend, kind: Subscription, names: Vec<String>) -> Vec<RespFrame> {
+    let id = backend.client_id();
+    names
+        .into_iter()
+        .map(|name| {
+            let count = backend.pubsub().subscribe(id, kind, &name);
+            confirmation(backend, kind, true, Some(name), count)
+        })
+        .collect()
+}
+
+// Unsubscribes from every channel or pattern of the kind when `names` is empty. A client
+// without any still gets a confirmation.
+fn unsubscribe(backend: &Backend, kind: Subscription, names: Vec<String>) -> Vec<RespFrame> {
+    let id = backend.client_id();
+    let names = match names {
+        names if names.is_empty() => backend.pubsub().subscriptions(id, kind),
+        names => names,
+    };
+    if names.is_empty() {
+        let count = backend.pubsub().subscription_count(id, kind);
+        return vec![confirmation(backend, kind, false, None, count)];
+    }
+    names
+        .into_iter()
+        .map(|name| {
+            let count = backend.pubsub().unsubscribe(id, kind, &name);
+            confirmation(backend, kind, false, Some(name), count)
+        })
+        .collect()
+}
+
+// [kind, channel or pattern, subscriptions of the kind left], the name being nil when there
+// was none
+fn confirmation(
+    backend: &Backend,
+    kind: Subscription,
+    subscribed: bool,
+    name: Option<String>,
+    count: usize,
+) -> RespFrame {
+    let kind = match (kind, subscribed) {
+        (Subscription::Channel, true) => "subscribe",
+        (Subscription::Channel, false) => "unsubscribe",
+        (Subscription::Pattern, true) => "psubscribe",
+        (Subscription::Pattern, false) => "punsubscribe",
+        (Subscription::ShardChannel, true) => "ssubscribe",
+        (Subscription::ShardChannel, false) => "sunsubscribe",
+    };
+    let name = name.map_or(RespFrame::Null(RespNull), |n| BulkString::from(n).into());
+    backend.pubsub_frame(vec![
+        BulkString::from(kind).into(),
+        name,
+        RespFrame::Integer(count as i64),
+    ])
+}
+
+// The connection sends each confirmation as a reply of its own, where a single reply is
+// expected they come together in one array.
+impl CommandExecutor for Subscribe {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespArray::new(self.confirmations(backend)).into()
+    }
+}
+
+impl CommandExecutor for Unsubscribe {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespArray::new(self.confirmations(backend)).into()
+    }
+}
+
+impl CommandExecutor for PSubscribe {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespArray::new(self.confirmations(backend)).into()
+    }
+}
+
+impl CommandExecutor for PUnsubscribe {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespArray::new(self.confirmations(backend)).into()
+    }
+}
+
+impl CommandExecutor for SSubscribe {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespArray::new(self.confirmations(backend)).into()
+    }
+}
+
+impl CommandExecutor for SUnsubscribe {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespArray::new(self.confirmations(backend)).into()
+    }
+}
+
+impl CommandExecutor for SPublish {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.pubsub().spublish(&self.channel, self.message) as i64)
+    }
+}
+
+impl CommandExecutor for Publish {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.pubsub().publish(&self.channel, self.message) as i64)
+    }
+}
+
+impl CommandExecutor for PubSubMeta {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let pubsub = backend.pubsub();
+        match self.query {
+            PubSubQuery::Channels { kind, pattern } => {
+                let channels = pubsub.active_channels(kind, pattern.as_deref());
+                let channels = channels.into_iter().map(|c| BulkString::from(c).into());
+                RespArray::new(channels.collect::<Vec<_>>()).into()
+            }
+            PubSubQuery::NumSub { kind, channels } => {
+                let counts = channels.into_iter().flat_map(|channel| {
+                    let count = pubsub.subscriber_count(kind, &channel) as i64;
+                    [BulkString::from(channel).into(), RespFrame::Integer(count)]
+                });
+                RespArray::new(counts.collect::<Vec<_>>()).into()
+            }
+            PubSubQuery::NumPat => RespFrame::Integer(pubsub.pattern_count() as i64),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Subscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "subscribe", 1)?;
+        let channels = extract_names(value)?;
+        Ok(Subscribe { channels })
+    }
+}
+
+impl TryFrom<RespArray> for Unsubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "unsubscribe", 0)?;
+        let channels = extract_names(value)?;
+        Ok(Unsubscribe { channels })
+    }
+}
+
+impl TryFrom<RespArray> for PSubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "psubscribe", 1)?;
+        let patterns = extract_names(value)?;
+        Ok(PSubscribe { patterns })
+    }
+}
+
+impl TryFrom<RespArray> for PUnsubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "punsubscribe", 0)?;
+        let patterns = extract_names(value)?;
+        Ok(PUnsubscribe { patterns })
+    }
+}
+
+impl TryFrom<RespArray> for PubSubMeta {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "pubsub", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_lowercase();
+        let mut args = args
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let query = match subcommand.as_str() {
+            "channels" if args.len() <= 1 => PubSubQuery::Channels {
+                kind: Subscription::Channel,
+                pattern: args.pop(),
+            },
+            "numsub" => PubSubQuery::NumSub {
+                kind: Subscription::Channel,
+                channels: args,
+            },
+            "shardchannels" if args.len() <= 1 => PubSubQuery::Channels {
+                kind: Subscription::ShardChannel,
+                pattern: args.pop(),
+            },
+            "shardnumsub" => PubSubQuery::NumSub {
+                kind: Subscription::ShardChannel,
+                channels: args,
+            },
+            "numpat" if args.is_empty() => PubSubQuery::NumPat,
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for '{subcommand}'"
+                )))
+            }
+        };
+        Ok(PubSubMeta { query })
+    }
+}
+
+impl TryFrom<RespArray> for SSubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "ssubscribe", 1)?;
+        let channels = extract_names(value)?;
+        Ok(SSubscribe { channels })
+    }
+}
+
+impl TryFrom<RespArray> for SUnsubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "sunsubscribe", 0)?;
+        let channels = extract_names(value)?;
+        Ok(SUnsubscribe { channels })
+    }
+}
+
+// the channels or patterns following the command name
+fn extract_names(value: RespArray) -> Result<Vec<String>, CommandError> {
+    extract_args(value, 1)?
+        .into_iter()
+        .map(|arg| extract_string(Some(arg)))
+        .collect()
+}
+
+impl TryFrom<RespArray> for Publish {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["publish"], 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let channel = extract_string(args.next())?;
+        match args.next() {
+            Some(RespFrame::BulkString(message)) => Ok(Publish { channel, message }),
+            _ => Err(CommandError::InvalidArgument("Invalid message".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for SPublish {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["spublish"], 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let channel = extract_string(args.next())?;
+        match args.next() {
+            Some(RespFrame::BulkString(message)) => Ok(SPublish { channel, message }),
+            _ => Err(CommandError::InvalidArgument("Invalid message".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecoder;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn frame(kind: &str, channel: Option<&str>, count: i64) -> RespFrame {
+        let channel = channel.map_or(RespFrame::Null(RespNull), |c| BulkString::from(c).into());
+        RespArray::new([BulkString::from(kind).into(), channel, count.into()]).into()
+    }
+
+    #[test]
+    fn test_subscribe_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$9\r\nSUBSCRIBE\r\n$4\r\nnews\r\n$6\r\nsports\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Subscribe = frame.try_into()?;
+        assert_eq!(result.channels, ["news", "sports"]);
+
+        buf.extend_from_slice(b"*1\r\n$9\r\nsubscribe\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Subscribe, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_publish_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$7\r\nPUBLISH\r\n$4\r\nnews\r\n$5\r\nhello\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Publish = frame.try_into()?;
+        assert_eq!(result.channel, "news");
+        assert_eq!(result.message, BulkString::from("hello"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pubsub_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nPUBSUB\r\n$8\r\nCHANNELS\r\n$2\r\nn*\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: PubSubMeta = frame.try_into()?;
+        assert_eq!(
+            result.query,
+            PubSubQuery::Channels {
+                kind: Subscription::Channel,
+                pattern: Some("n*".to_string())
+            }
+        );
+
+        buf.extend_from_slice(b"*3\r\n$6\r\npubsub\r\n$6\r\nnumpat\r\n$1\r\nx\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<PubSubMeta, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pubsub_command() {
+        let backend = Backend::new();
+        backend
+            .pubsub()
+            .subscribe(1, Subscription::Channel, "news.tech");
+        backend
+            .pubsub()
+            .subscribe(2, Subscription::Channel, "news.tech");
+        backend
+            .pubsub()
+            .subscribe(2, Subscription::Channel, "sports");
+        backend
+            .pubsub()
+            .subscribe(2, Subscription::Pattern, "news.*");
+
+        let cmd = PubSubMeta {
+            query: PubSubQuery::Channels {
+                kind: Subscription::Channel,
+                pattern: Some("news.*".to_string()),
+            },
+        };
+        let expected = RespArray::new([BulkString::from("news.tech").into()]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = PubSubMeta {
+            query: PubSubQuery::NumSub {
+                kind: Subscription::Channel,
+                channels: vec!["news.tech".to_string(), "missing".to_string()],
+            },
+        };
+        let expected = RespArray::new([
+            BulkString::from("news.tech").into(),
+            RespFrame::Integer(2),
+            BulkString::from("missing").into(),
+            RespFrame::Integer(0),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = PubSubMeta {
+            query: PubSubQuery::NumPat,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_commands() {
+        let server = Backend::new();
+        let backend = server.connect("127.0.0.1:6000".to_string(), "127.0.0.1:6379".to_string());
+        let mut messages = backend.pubsub().attach(backend.client_id());
+
+        let cmd = Subscribe {
+            channels: vec!["news".to_string(), "sports".to_string()],
+        };
+        let expected = vec![
+            frame("subscribe", Some("news"), 1),
+            frame("subscribe", Some("sports"), 2),
+        ];
+        assert_eq!(cmd.confirmations(&backend), expected);
+        assert!(backend.subscriber_mode());
+        assert!(!server.subscriber_mode());
+
+        let cmd = Publish {
+            channel: "news".to_string(),
+            message: BulkString::from("hello"),
+        };
+        assert_eq!(cmd.execute(&server), RespFrame::Integer(1));
+        let message = messages.recv().await.expect("a message");
+        let expected = RespArray::new([
+            BulkString::from("message").into(),
+            BulkString::from("news").into(),
+            BulkString::from("hello").into(),
+        ]);
+        assert_eq!(backend.pubsub_frame(message), expected.into());
+
+        let cmd = Unsubscribe {
+            channels: vec!["news".to_string()],
+        };
+        assert_eq!(
+            cmd.confirmations(&backend),
+            [frame("unsubscribe", Some("news"), 1)]
+        );
+        let cmd = Unsubscribe { channels: vec![] };
+        assert_eq!(
+            cmd.confirmations(&backend),
+            [frame("unsubscribe", Some("sports"), 0)]
+        );
+        assert!(!backend.subscriber_mode());
+        let cmd = Unsubscribe { channels: vec![] };
+        assert_eq!(cmd.confirmations(&backend), [frame("unsubscribe", None, 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_psubscribe_commands() {
+        let server = Backend::new();
+        let backend = server.connect("127.0.0.1:6000".to_string(), "127.0.0.1:6379".to_string());
+        let mut messages = backend.pubsub().attach(backend.client_id());
+
+        let cmd = Subscribe {
+            channels: vec!["news.tech".to_string()],
+        };
+        cmd.confirmations(&backend);
+        let cmd = PSubscribe {
+            patterns: vec!["news.*".to_string(), "sports.*".to_string()],
+        };
+        let expected = vec![
+            frame("psubscribe", Some("news.*"), 2),
+            frame("psubscribe", Some("sports.*"), 3),
+        ];
+        assert_eq!(cmd.confirmations(&backend), expected);
+
+        let cmd = Publish {
+            channel: "news.tech".to_string(),
+            message: BulkString::from("hello"),
+        };
+        assert_eq!(cmd.execute(&server), RespFrame::Integer(2));
+        messages.recv().await.expect("a message");
+        let message = messages.recv().await.expect("a pattern message");
+        let expected = RespArray::new([
+            BulkString::from("pmessage").into(),
+            BulkString::from("news.*").into(),
+            BulkString::from("news.tech").into(),
+            BulkString::from("hello").into(),
+        ]);
+        assert_eq!(backend.pubsub_frame(message), expected.into());
+
+        // the channel subscription is left alone
+        let cmd = PUnsubscribe { patterns: vec![] };
+        let expected = vec![
+            frame("punsubscribe", Some("news.*"), 2),
+            frame("punsubscribe", Some("sports.*"), 1),
+        ];
+        assert_eq!(cmd.confirmations(&backend), expected);
+        let cmd = PUnsubscribe { patterns: vec![] };
+        assert_eq!(
+            cmd.confirmations(&backend),
+            [frame("punsubscribe", None, 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ssubscribe_commands() {
+        let server = Backend::new();
+        let backend = server.connect("127.0.0.1:6000".to_string(), "127.0.0.1:6379".to_string());
+        let mut messages = backend.pubsub().attach(backend.client_id());
+
+        let cmd = Subscribe {
+            channels: vec!["orders".to_string()],
+        };
+        cmd.confirmations(&backend);
+        // shard channels are counted apart from channels and patterns
+        let cmd = SSubscribe {
+            channels: vec!["orders".to_string(), "{user}:1".to_string()],
+        };
+        let expected = vec![
+            frame("ssubscribe", Some("orders"), 1),
+            frame("ssubscribe", Some("{user}:1"), 2),
+        ];
+        assert_eq!(cmd.confirmations(&backend), expected);
+
+        let cmd = SPublish {
+            channel: "orders".to_string(),
+            message: BulkString::from("hello"),
+        };
+        assert_eq!(cmd.execute(&server), RespFrame::Integer(1));
+        let message = messages.recv().await.expect("a shard message");
+        let expected = RespArray::new([
+            BulkString::from("smessage").into(),
+            BulkString::from("orders").into(),
+            BulkString::from("hello").into(),
+        ]);
+        assert_eq!(backend.pubsub_frame(message), expected.into());
+
+        let cmd = PubSubMeta {
+            query: PubSubQuery::Channels {
+                kind: Subscription::ShardChannel,
+                pattern: None,
+            },
+        };
+        let expected = RespArray::new([
+            BulkString::from("orders").into(),
+            BulkString::from("{user}:1").into(),
+        ]);
+        assert_eq!(cmd.execute(&server), expected.into());
+
+        let cmd = SUnsubscribe { channels: vec![] };
+        let expected = vec![
+            frame("sunsubscribe", Some("orders"), 1),
+            frame("sunsubscribe", Some("{user}:1"), 0),
+        ];
+        assert_eq!(cmd.confirmations(&backend), expected);
+        assert_eq!(
+            backend
+                .pubsub()
+                .subscription_count(backend.client_id(), Subscription::Channel),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_keyspace_notifications() -> Result<()> {
+        let server = Backend::new();
+        let backend = server.connect("127.0.0.1:6000".to_string(), "127.0.0.1:6379".to_string());
+        let mut messages = backend.pubsub().attach(backend.client_id());
+        let cmd = PSubscribe {
+            patterns: vec!["__key*__:*".to_string()],
+        };
+        cmd.confirmations(&backend);
+
+        // nothing is published until notify-keyspace-events enables it
+        let cmd = crate::cmd::Set {
+            key: b"greeting".to_vec(),
+            value: BulkString::from("hello").into(),
+        };
+        cmd.execute(&backend);
+        assert!(messages.try_recv().is_err());
+
+        backend
+            .config()
+            .set(&[("notify-keyspace-events".to_string(), "KEA".to_string())])?;
+        let cmd = crate::cmd::Del {
+            keys: vec![b"greeting".to_vec(), b"missing".to_vec()],
+        };
+        cmd.execute(&backend);
+        let expected = RespArray::new([
+            BulkString::from("pmessage").into(),
+            BulkString::from("__key*__:*").into(),
+            BulkString::from("__keyspace@0__:greeting").into(),
+            BulkString::from("del").into(),
+        ]);
+        let message = messages.recv().await.expect("a keyspace event");
+        assert_eq!(backend.pubsub_frame(message), expected.into());
+        let expected = RespArray::new([
+            BulkString::from("pmessage").into(),
+            BulkString::from("__key*__:*").into(),
+            BulkString::from("__keyevent@0__:del").into(),
+            BulkString::from("greeting").into(),
+        ]);
+        let message = messages.recv().await.expect("a keyevent event");
+        assert_eq!(backend.pubsub_frame(message), expected.into());
+        // missing keys generate no event
+        assert!(messages.try_recv().is_err());
+
+        // only the keyevent channel, only for sets
+        backend
+            .config()
+            .set(&[("notify-keyspace-events".to_string(), "Es".to_string())])?;
+        let cmd = crate::cmd::SAdd {
+            key: b"myset".to_vec(),
+            members: vec!["one".to_string()],
+        };
+        cmd.execute(&backend);
+        let message = messages.recv().await.expect("a keyevent event");
+        assert_eq!(message[2], BulkString::from("__keyevent@0__:sadd").into());
+        let cmd = crate::cmd::Del {
+            keys: vec![b"myset".to_vec()],
+        };
+        cmd.execute(&backend);
+        assert!(messages.try_recv().is_err());
+
+        Ok(())
+    }
+}
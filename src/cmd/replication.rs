@@ -0,0 +1,490 @@
+use super::{
+    command::CommandSpec, extract_args, extract_integer, extract_string, validate_command,
+    validate_variadic_command, CommandError, CommandExecutor, PSync, ReplConf, ReplicaOf, Role,
+    Wait, RESP_OK,
+};
+use crate::{
+    Backend, BlockKey, BulkString, DatasetCopy, ReplicaStream, RespArray, RespFrame, SimpleError,
+    SimpleString,
+};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::info;
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new(
+        "psync",
+        -3,
+        "server",
+        "An internal command used in replication.",
+    )
+    .flags(&["admin", "noscript", "no_multi"]),
+    CommandSpec::new(
+        "replconf",
+        -1,
+        "server",
+        "An internal command for configuring the replication stream.",
+    )
+    .flags(&["admin", "noscript", "loading", "stale", "allow_busy"]),
+    CommandSpec::new(
+        "wait",
+        3,
+        "generic",
+        "Blocks until the asynchronous replication of all preceding write commands sent by the connection is completed.",
+    )
+    .flags(&["noscript"]),
+    CommandSpec::new(
+        "role",
+        1,
+        "server",
+        "Returns the replication role.",
+    )
+    .flags(&["noscript", "loading", "stale", "fast"]),
+    CommandSpec::new(
+        "replicaof",
+        3,
+        "server",
+        "Configures a server as replica of another, or promotes it to a master.",
+    )
+    .flags(&["admin", "noscript", "stale"]),
+    CommandSpec::new(
+        "slaveof",
+        3,
+        "server",
+        "Sets a Redis server as a replica of another, or promotes it to being a master.",
+    )
+    .flags(&["admin", "noscript", "stale"]),
+];
+
+// what a read only replica replies to writes from its clients
+pub const READONLY_ERROR: &str = "READONLY You can't write against a read only replica.";
+
+const WAIT_REPLICA_ERROR: &str =
+    "ERR WAIT cannot be used with replica instances. Please also note that since Redis 4.0 if a replica is configured to be writable (which is not the default) writes to replicas are just local and are not propagated.";
+
+// The network layer runs PSYNC itself, with exclusive access to the backend, see resync.
+impl CommandExecutor for PSync {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        SimpleError::new("ERR Command not allowed inside a transaction").into()
+    }
+}
+
+impl PSync {
+    // Makes the client a replica. One that asks to resume a history the backlog still covers is
+    // told to CONTINUE, and streamed what it missed. Others get the replication ID and the
+    // offset their stream starts at, and the copy of the dataset to send them first. Fails
+    // with the reply to send when a replica not yet in sync with its own master is asked.
+    pub fn resync(
+        self,
+        backend: &Backend,
+    ) -> Result<(RespFrame, ReplicaStream, Option<DatasetCopy>), RespFrame> {
+        let replication = backend.replication();
+        let linked = replication.master_info(backend.now_ms());
+        if linked.is_some_and(|master| master.state != "connected") {
+            let error = "NOMASTERLINK Can't SYNC while not connected with my master";
+            return Err(SimpleError::new(error).into());
+        }
+        let replid = replication.replid();
+        if self.replid != "?" {
+            let resumed = u64::try_from(self.offset)
+                .ok()
+                .and_then(|offset| backend.resume_replica(&self.replid, offset));
+            if let Some(stream) = resumed {
+                let reply = SimpleString::new(format!("CONTINUE {replid}"));
+                return Ok((reply.into(), stream, None));
+            }
+            info!(
+                "Partial resynchronization from {}:{} not accepted, starting a full one",
+                self.replid, self.offset
+            );
+        }
+        let (offset, stream, copy) = backend.attach_replica();
+        let reply = SimpleString::new(format!("FULLRESYNC {replid} {offset}"));
+        Ok((reply.into(), stream, Some(copy)))
+    }
+}
+
+impl CommandExecutor for ReplConf {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        for (option, value) in self.options {
+            match option.as_str() {
+                "listening-port" => match value.parse() {
+                    Ok(port) => backend.replication().announce(backend.client_id(), port),
+                    Err(_) => {
+                        return SimpleError::new("ERR value is not an integer or out of range")
+                            .into()
+                    }
+                },
+                "ack" => match value.parse() {
+                    Ok(offset) => backend.replica_ack(offset),
+                    Err(_) => {
+                        return SimpleError::new("ERR value is not an integer or out of range")
+                            .into()
+                    }
+                },
+                // nothing depends on them
+                "ip-address" | "capa" => {}
+                option => {
+                    return SimpleError::new(format!("ERR Unrecognized REPLCONF option: {option}"))
+                        .into()
+                }
+            }
+        }
+        RESP_OK.clone()
+    }
+}
+
+// Executed directly, inside a transaction, WAIT doesn't block.
+impl CommandExecutor for Wait {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.replication().master().is_some() {
+            return SimpleError::new(WAIT_REPLICA_ERROR).into();
+        }
+        let replication = backend.replication();
+        RespFrame::Integer(replication.acked(replication.offset()) as i64)
+    }
+}
+
+impl Wait {
+    // Waits until enough replicas acknowledged the current offset of the replication stream or
+    // the timeout elapses. The replicas are asked for their offset right away.
+    pub async fn execute_blocking(self, backend: &Backend) -> RespFrame {
+        if backend.replication().master().is_some() {
+            return SimpleError::new(WAIT_REPLICA_ERROR).into();
+        }
+        let replication = backend.replication();
+        let offset = replication.offset();
+        let deadline =
+            (self.timeout > 0).then(|| Instant::now() + Duration::from_millis(self.timeout));
+        let mut requested = false;
+        let acked = backend.block_on(vec![BlockKey::ReplicaAcks], deadline, || {
+            let acked = replication.acked(offset);
+            if acked >= self.numreplicas {
+                return Some(acked);
+            }
+            if !requested {
+                backend.request_replica_acks();
+                requested = true;
+            }
+            None
+        });
+        let acked = acked.await.unwrap_or_else(|| replication.acked(offset));
+        RespFrame::Integer(acked as i64)
+    }
+}
+
+impl CommandExecutor for Role {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let replication = backend.replication();
+        if let Some(master) = replication.master_info(backend.now_ms()) {
+            return RespArray::new([
+                BulkString::from("slave").into(),
+                BulkString::from(master.host).into(),
+                RespFrame::Integer(master.port as i64),
+                BulkString::from(master.state).into(),
+                RespFrame::Integer(replication.offset() as i64),
+            ])
+            .into();
+        }
+        let replicas = replication
+            .replicas(backend.now_ms())
+            .into_iter()
+            .map(|replica| {
+                RespArray::new([
+                    BulkString::from(replica.ip).into(),
+                    BulkString::from(replica.port.to_string()).into(),
+                    BulkString::from(replica.offset.to_string()).into(),
+                ])
+                .into()
+            });
+        RespArray::new([
+            BulkString::from("master").into(),
+            RespFrame::Integer(replication.offset() as i64),
+            RespArray::new(replicas.collect::<Vec<_>>()).into(),
+        ])
+        .into()
+    }
+}
+
+// The link to the master is established in the background, see the replica module.
+impl CommandExecutor for ReplicaOf {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let replication = backend.replication();
+        match self.master {
+            Some((host, port)) => {
+                if !replication.set_master(Some((host.clone(), port))) {
+                    return SimpleString::new("OK Already connected to specified master").into();
+                }
+                info!("REPLICAOF {}:{} enabled", host, port);
+            }
+            None => {
+                if replication.set_master(None) {
+                    info!("MASTER MODE enabled");
+                }
+            }
+        }
+        RESP_OK.clone()
+    }
+}
+
+impl TryFrom<RespArray> for PSync {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["psync"], 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let replid = extract_string(args.next())?;
+        let offset = extract_integer(args.next())?;
+        Ok(PSync { replid, offset })
+    }
+}
+
+impl TryFrom<RespArray> for ReplConf {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "replconf", 0)?;
+
+        let args = extract_args(value, 1)?
+            .into_iter()
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if args.len() % 2 != 0 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let options = args
+            .chunks(2)
+            .map(|pair| (pair[0].to_ascii_lowercase(), pair[1].clone()))
+            .collect();
+        Ok(ReplConf { options })
+    }
+}
+
+impl TryFrom<RespArray> for Wait {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["wait"], 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let numreplicas = extract_integer(args.next())?;
+        let timeout = extract_integer(args.next())?;
+        if timeout < 0 {
+            return Err(CommandError::InvalidArgument(
+                "timeout is negative".to_string(),
+            ));
+        }
+        Ok(Wait {
+            numreplicas: numreplicas.max(0) as usize,
+            timeout: timeout as u64,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ReplicaOf {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = match value.first() {
+            Some(RespFrame::BulkString(name)) if name.eq_ignore_ascii_case(b"slaveof") => "slaveof",
+            _ => "replicaof",
+        };
+        validate_command(&value, &[name], 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let host = extract_string(args.next())?;
+        let port = extract_string(args.next())?;
+        if host.eq_ignore_ascii_case("no") && port.eq_ignore_ascii_case("one") {
+            return Ok(ReplicaOf { master: None });
+        }
+        let port = port.parse().map_err(|_| {
+            CommandError::InvalidArgument("value is not an integer or out of range".to_string())
+        })?;
+        Ok(ReplicaOf {
+            master: Some((host, port)),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Role {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["role"], 0)?;
+        Ok(Role)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecoder;
+    use anyhow::{anyhow, Result};
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_replconf_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$8\r\nREPLCONF\r\n$14\r\nlistening-port\r\n$4\r\n6380\r\n$4\r\ncapa\r\n$6\r\npsync2\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: ReplConf = frame.try_into()?;
+        assert_eq!(
+            cmd.options,
+            [
+                ("listening-port".to_string(), "6380".to_string()),
+                ("capa".to_string(), "psync2".to_string()),
+            ]
+        );
+        buf.extend_from_slice(b"*3\r\n$5\r\nPSYNC\r\n$1\r\n?\r\n$2\r\n-1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: PSync = frame.try_into()?;
+        assert_eq!((cmd.replid.as_str(), cmd.offset), ("?", -1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_replicaof() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$7\r\nSLAVEOF\r\n$2\r\nno\r\n$3\r\nONE\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: ReplicaOf = frame.try_into()?;
+        assert_eq!(cmd.master, None);
+
+        let backend = Backend::new();
+        let master = Some(("127.0.0.1".to_string(), 6380));
+        let replicaof = ReplicaOf {
+            master: master.clone(),
+        };
+        assert_eq!(replicaof.execute(&backend), RESP_OK.clone());
+        let replicaof = ReplicaOf { master };
+        assert_eq!(
+            replicaof.execute(&backend),
+            SimpleString::new("OK Already connected to specified master").into()
+        );
+        let expected = RespArray::new([
+            BulkString::from("slave").into(),
+            BulkString::from("127.0.0.1").into(),
+            RespFrame::Integer(6380),
+            BulkString::from("connect").into(),
+            RespFrame::Integer(0),
+        ]);
+        assert_eq!(Role.execute(&backend), expected.into());
+        let wait = Wait {
+            numreplicas: 0,
+            timeout: 0,
+        };
+        assert_eq!(
+            wait.execute(&backend),
+            SimpleError::new(WAIT_REPLICA_ERROR).into()
+        );
+
+        assert_eq!(
+            ReplicaOf { master: None }.execute(&backend),
+            RESP_OK.clone()
+        );
+        assert_eq!(backend.replication().master(), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_psync_wait_role() -> Result<()> {
+        let backend = Backend::new();
+        let replica = backend.connect("127.0.0.1:5000".to_string(), "127.0.0.1:6379".to_string());
+        let options = vec![("listening-port".to_string(), "6380".to_string())];
+        assert_eq!(ReplConf { options }.execute(&replica), RESP_OK.clone());
+
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        let psync = PSync {
+            replid: "?".to_string(),
+            offset: -1,
+        };
+        let (reply, mut stream, copy) = psync.resync(&replica).map_err(|e| anyhow!("{e:?}"))?;
+        let replid = backend.replication().replid();
+        assert_eq!(
+            reply,
+            SimpleString::new(format!("FULLRESYNC {replid} 0")).into()
+        );
+        let mut keys = 0;
+        copy.expect("a full resync sends the dataset")
+            .for_each_key(|_, _, _, _| keys += 1);
+        assert_eq!(keys, 1);
+        backend.replication().set_online(replica.client_id());
+
+        // writes are streamed once a replica attached
+        backend.propagate(
+            vec![b"DEL".to_vec(), b"key".to_vec()],
+            &RespFrame::Integer(1),
+        );
+        let data = stream.recv().await.unwrap_or_default();
+        assert!(data.ends_with(b"*2\r\n$3\r\nDEL\r\n$3\r\nkey\r\n"));
+        let offset = backend.replication().offset();
+        assert_eq!(offset, data.len() as u64);
+
+        let wait = Wait {
+            numreplicas: 1,
+            timeout: 10,
+        };
+        assert_eq!(wait.execute_blocking(&backend).await, RespFrame::Integer(0));
+        // which asked the replica for its offset
+        let getack = stream.recv().await.unwrap_or_default();
+        assert_eq!(
+            getack,
+            b"*3\r\n$8\r\nREPLCONF\r\n$6\r\nGETACK\r\n$1\r\n*\r\n"
+        );
+        // the replica processed the request too
+        let offset = backend.replication().offset();
+        assert_eq!(offset, (data.len() + getack.len()) as u64);
+        let ack = vec![("ack".to_string(), offset.to_string())];
+        assert_eq!(ReplConf { options: ack }.execute(&replica), RESP_OK.clone());
+        let wait = Wait {
+            numreplicas: 1,
+            timeout: 0,
+        };
+        assert_eq!(wait.execute_blocking(&backend).await, RespFrame::Integer(1));
+
+        let expected = RespArray::new([
+            BulkString::from("master").into(),
+            RespFrame::Integer(offset as i64),
+            RespArray::new([RespArray::new([
+                BulkString::from("127.0.0.1").into(),
+                BulkString::from("6380").into(),
+                BulkString::from(offset.to_string()).into(),
+            ])
+            .into()])
+            .into(),
+        ]);
+        assert_eq!(Role.execute(&backend), expected.into());
+
+        // a replica that lost its link resumes from the first byte it missed
+        backend.replication().detach(replica.client_id());
+        backend.propagate(
+            vec![b"DEL".to_vec(), b"other".to_vec()],
+            &RespFrame::Integer(1),
+        );
+        let psync = PSync {
+            replid: replid.clone(),
+            offset: offset as i64 + 1,
+        };
+        let (reply, mut stream, copy) = psync.resync(&replica).map_err(|e| anyhow!("{e:?}"))?;
+        assert_eq!(
+            reply,
+            SimpleString::new(format!("CONTINUE {replid}")).into()
+        );
+        assert!(copy.is_none());
+        let missed = stream.recv().await.unwrap_or_default();
+        assert_eq!(missed, b"*2\r\n$3\r\nDEL\r\n$5\r\nother\r\n");
+
+        // but not from another history
+        let psync = PSync {
+            replid: "0".repeat(40),
+            offset: offset as i64 + 1,
+        };
+        let (reply, _, copy) = psync.resync(&replica).map_err(|e| anyhow!("{e:?}"))?;
+        assert!(matches!(reply, RespFrame::SimpleString(s) if s.0.starts_with("FULLRESYNC")));
+        assert!(copy.is_some());
+        Ok(())
+    }
+}
@@ -0,0 +1,1326 @@
+use super::{
+    command::{CommandSpec, KeySearch},
+    extract_args, extract_integer, extract_key, extract_string, validate_command,
+    validate_variadic_command, CommandError, CommandExecutor, Eval, EvalSha, FCall, FCallRo,
+    Function, FunctionSubcommand, Script, ScriptSubcommand, RESP_OK,
+};
+use crate::{
+    Backend, BulkString, FunctionInfo, FunctionLibrary, RespArray, RespFrame, RespNull, SimpleError,
+};
+
+const SCRIPT_FLAGS: &[&str] = &[
+    "noscript",
+    "stale",
+    "skip_monitor",
+    "may_replicate",
+    "no_mandatory_keys",
+    "movablekeys",
+];
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new(
+        "eval",
+        -3,
+        "scripting",
+        "Executes a server-side Lua script.",
+    )
+    .flags(SCRIPT_FLAGS)
+    .movable_keys(KeySearch::KeyNum(2)),
+    CommandSpec::new(
+        "evalsha",
+        -3,
+        "scripting",
+        "Executes a server-side Lua script by SHA1 digest.",
+    )
+    .flags(SCRIPT_FLAGS)
+    .movable_keys(KeySearch::KeyNum(2)),
+    CommandSpec::new(
+        "script",
+        -2,
+        "scripting",
+        "A container for Lua scripts management commands.",
+    )
+    .flags(&["noscript", "allow_busy"]),
+    CommandSpec::new(
+        "function",
+        -2,
+        "scripting",
+        "A container for function commands.",
+    )
+    .flags(&["noscript"]),
+    CommandSpec::new("fcall", -3, "scripting", "Invokes a function.")
+        .flags(SCRIPT_FLAGS)
+        .movable_keys(KeySearch::KeyNum(2)),
+    CommandSpec::new("fcall_ro", -3, "scripting", "Invokes a read-only function.")
+        .flags(&[
+            "noscript",
+            "stale",
+            "skip_monitor",
+            "no_mandatory_keys",
+            "movablekeys",
+        ])
+        .movable_keys(KeySearch::KeyNum(2)),
+];
+
+// Scripts run atomically: the connection runs EVAL and EVALSHA with exclusive access to the
+// backend, like EXEC, see Backend::shared_access. One running for longer than
+// busy-reply-threshold gets other clients BUSY errors until it ends or SCRIPT KILL stops it.
+impl CommandExecutor for Eval {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend.scripts().insert(&self.script);
+        run_script(backend, &self.script, self.keys, self.args)
+    }
+}
+
+impl CommandExecutor for EvalSha {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.scripts().get(&self.sha1) {
+            Some(script) => run_script(backend, &script, self.keys, self.args),
+            None => SimpleError::new("NOSCRIPT No matching script. Please use EVAL.").into(),
+        }
+    }
+}
+
+impl CommandExecutor for Script {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.subcommand {
+            ScriptSubcommand::Load(script) => match compile_script(&script) {
+                Ok(()) => BulkString::from(backend.scripts().insert(&script)).into(),
+                Err(e) => e.into(),
+            },
+            ScriptSubcommand::Exists(digests) => {
+                let exists = digests
+                    .iter()
+                    .map(|sha1| RespFrame::Integer(backend.scripts().contains(sha1) as i64))
+                    .collect::<Vec<_>>();
+                RespArray::new(exists).into()
+            }
+            ScriptSubcommand::Flush => {
+                backend.scripts().flush();
+                RESP_OK.clone()
+            }
+            ScriptSubcommand::Kill => match backend.running_script() {
+                Some(run) if run.wrote() => SimpleError::new(
+                    "UNKILLABLE Sorry the script already executed write commands against the dataset. You can either wait the script termination or kill the server in a hard way using the SHUTDOWN NOSAVE command.",
+                )
+                .into(),
+                Some(run) => {
+                    run.kill();
+                    RESP_OK.clone()
+                }
+                None => SimpleError::new("NOTBUSY No scripts in execution right now.").into(),
+            },
+        }
+    }
+}
+
+// A function runs like a script, its library code registering the functions again each time.
+impl CommandExecutor for Function {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.subcommand {
+            FunctionSubcommand::Load { code, replace } => {
+                let library = match load_library(&code) {
+                    Ok(library) => library,
+                    Err(e) => return e.into(),
+                };
+                let name = library.name.clone();
+                match backend.functions().insert(library, replace) {
+                    Ok(()) => BulkString::from(name).into(),
+                    Err(e) => SimpleError::new(format!("ERR {e}")).into(),
+                }
+            }
+            FunctionSubcommand::List { pattern, with_code } => {
+                let libraries = backend
+                    .functions()
+                    .list(pattern.as_deref())
+                    .into_iter()
+                    .map(|library| library_frame(library, with_code))
+                    .collect::<Vec<_>>();
+                RespArray::new(libraries).into()
+            }
+            FunctionSubcommand::Delete(name) => {
+                if backend.functions().delete(&name) {
+                    RESP_OK.clone()
+                } else {
+                    SimpleError::new("ERR Library not found").into()
+                }
+            }
+            FunctionSubcommand::Flush => {
+                backend.functions().flush();
+                RESP_OK.clone()
+            }
+        }
+    }
+}
+
+impl CommandExecutor for FCall {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        call_function(backend, &self.function, self.keys, self.args, false)
+    }
+}
+
+impl CommandExecutor for FCallRo {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        call_function(backend, &self.function, self.keys, self.args, true)
+    }
+}
+
+impl TryFrom<RespArray> for Eval {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (script, keys, args) = extract_script_args(value, "eval")?;
+        Ok(Eval { script, keys, args })
+    }
+}
+
+impl TryFrom<RespArray> for EvalSha {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (sha1, keys, args) = extract_script_args(value, "evalsha")?;
+        Ok(EvalSha { sha1, keys, args })
+    }
+}
+
+impl TryFrom<RespArray> for Script {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "script", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_lowercase();
+        let mut args = args
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<Vec<_>, CommandError>>()?;
+        let subcommand = match subcommand.as_str() {
+            "load" if args.len() == 1 => ScriptSubcommand::Load(args.remove(0)),
+            "exists" if !args.is_empty() => ScriptSubcommand::Exists(args),
+            "kill" if args.is_empty() => ScriptSubcommand::Kill,
+            "flush" if args.len() <= 1 => {
+                validate_flush_mode(&args, "SCRIPT")?;
+                ScriptSubcommand::Flush
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for '{subcommand}'"
+                )))
+            }
+        };
+        Ok(Script { subcommand })
+    }
+}
+
+impl TryFrom<RespArray> for Function {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "function", 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = extract_string(args.next())?.to_ascii_lowercase();
+        let mut args = args
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<Vec<_>, CommandError>>()?;
+        let subcommand = match subcommand.as_str() {
+            "load" if args.len() == 1 => FunctionSubcommand::Load {
+                code: args.remove(0),
+                replace: false,
+            },
+            "load" if args.len() == 2 && args[0].eq_ignore_ascii_case("replace") => {
+                FunctionSubcommand::Load {
+                    code: args.remove(1),
+                    replace: true,
+                }
+            }
+            "list" => {
+                let mut pattern = None;
+                let mut with_code = false;
+                let mut args = args.into_iter();
+                while let Some(arg) = args.next() {
+                    match arg.to_ascii_lowercase().as_str() {
+                        "withcode" if !with_code => with_code = true,
+                        "libraryname" if pattern.is_none() => {
+                            pattern = Some(args.next().ok_or_else(|| {
+                                CommandError::InvalidArgument(
+                                    "library name argument was not given".to_string(),
+                                )
+                            })?)
+                        }
+                        _ => {
+                            return Err(CommandError::InvalidArgument(format!(
+                                "Unknown argument {arg}"
+                            )))
+                        }
+                    }
+                }
+                FunctionSubcommand::List { pattern, with_code }
+            }
+            "delete" if args.len() == 1 => FunctionSubcommand::Delete(args.remove(0)),
+            "flush" if args.len() <= 1 => {
+                validate_flush_mode(&args, "FUNCTION")?;
+                FunctionSubcommand::Flush
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for '{subcommand}'"
+                )))
+            }
+        };
+        Ok(Function { subcommand })
+    }
+}
+
+impl TryFrom<RespArray> for FCall {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (function, keys, args) = extract_script_args(value, "fcall")?;
+        Ok(FCall {
+            function,
+            keys,
+            args,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FCallRo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (function, keys, args) = extract_script_args(value, "fcall_ro")?;
+        Ok(FCallRo {
+            function,
+            keys,
+            args,
+        })
+    }
+}
+
+// The optional ASYNC or SYNC of SCRIPT FLUSH and FUNCTION FLUSH, dropping right away either way.
+fn validate_flush_mode(args: &[String], command: &str) -> Result<(), CommandError> {
+    match args.first().map(|arg| arg.to_ascii_lowercase()) {
+        None => Ok(()),
+        Some(mode) if mode == "async" || mode == "sync" => Ok(()),
+        Some(_) => Err(CommandError::InvalidArgument(format!(
+            "{command} FLUSH only support SYNC|ASYNC option"
+        ))),
+    }
+}
+
+// The script (or its digest, or the function name), the keys and the arguments of `EVAL script
+// numkeys [key ...] [arg ...]`, of EVALSHA and of FCALL.
+type ScriptArgs = (String, Vec<Vec<u8>>, Vec<BulkString>);
+
+fn extract_script_args(value: RespArray, name: &'static str) -> Result<ScriptArgs, CommandError> {
+    let len = value.len();
+    if len < 3 {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} command needs at least 2 argument, got {}",
+            name,
+            len.saturating_sub(1)
+        )));
+    }
+    validate_command(&value, &[name], len - 1)?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let script = extract_string(args.next())?;
+    let numkeys = extract_integer(args.next())?;
+    let numkeys = usize::try_from(numkeys).map_err(|_| {
+        CommandError::InvalidArgument("Number of keys can't be negative".to_string())
+    })?;
+    if numkeys > args.len() {
+        return Err(CommandError::InvalidArgument(
+            "Number of keys can't be greater than number of args".to_string(),
+        ));
+    }
+    let keys = args
+        .by_ref()
+        .take(numkeys)
+        .map(|key| extract_key(Some(key)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let args = args
+        .map(|arg| match arg {
+            RespFrame::BulkString(arg) => Ok(arg),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid argument".to_string(),
+            )),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((script, keys, args))
+}
+
+// Runs the library code of FUNCTION LOAD to learn the functions it registers. The first line
+// is the metadata: "#!lua name=<library>".
+fn load_library(code: &str) -> Result<FunctionLibrary, SimpleError> {
+    let (metadata, body) = code.split_at(code.find('\n').unwrap_or(code.len()));
+    let mut metadata = metadata
+        .strip_prefix("#!")
+        .ok_or_else(|| SimpleError::new("ERR Missing library metadata"))?
+        .split_whitespace();
+    match metadata.next() {
+        Some(engine) if engine.eq_ignore_ascii_case("lua") => {}
+        engine => {
+            let engine = engine.unwrap_or_default();
+            return Err(SimpleError::new(format!("ERR Engine '{engine}' not found")));
+        }
+    }
+    let mut name = None;
+    for value in metadata {
+        match value.strip_prefix("name=") {
+            Some(value) if name.is_none() => name = Some(value.to_string()),
+            _ => {
+                return Err(SimpleError::new(format!(
+                    "ERR Invalid metadata value given: {value}"
+                )))
+            }
+        }
+    }
+    let name = name.ok_or_else(|| SimpleError::new("ERR Library name was not given"))?;
+    if !is_valid_name(&name) {
+        return Err(SimpleError::new("ERR Library names can only contain letters, numbers, or underscores(_) and must be at least one character long"));
+    }
+
+    // the body keeps the newline ending the metadata, so that errors tell the right line
+    let functions = register_functions(body)?;
+    if functions.is_empty() {
+        return Err(SimpleError::new("ERR No functions registered"));
+    }
+    Ok(FunctionLibrary {
+        name,
+        code: code.to_string(),
+        functions,
+    })
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn library_frame(library: FunctionLibrary, with_code: bool) -> RespFrame {
+    let functions = library
+        .functions
+        .into_iter()
+        .map(|function| {
+            let flags = function
+                .flags
+                .into_iter()
+                .map(|flag| BulkString::from(flag).into())
+                .collect::<Vec<RespFrame>>();
+            let description = function
+                .description
+                .map_or(RespFrame::Null(RespNull), |d| BulkString::from(d).into());
+            RespArray::new(vec![
+                BulkString::from("name").into(),
+                BulkString::from(function.name).into(),
+                BulkString::from("description").into(),
+                description,
+                BulkString::from("flags").into(),
+                RespArray::new(flags).into(),
+            ])
+            .into()
+        })
+        .collect::<Vec<RespFrame>>();
+    let mut frames = vec![
+        BulkString::from("library_name").into(),
+        BulkString::from(library.name).into(),
+        BulkString::from("engine").into(),
+        BulkString::from("LUA").into(),
+        BulkString::from("functions").into(),
+        RespArray::new(functions).into(),
+    ];
+    if with_code {
+        frames.push(BulkString::from("library_code").into());
+        frames.push(BulkString::from(library.code).into());
+    }
+    RespArray::new(frames).into()
+}
+
+// Runs the function `name` of FCALL, or of FCALL_RO when `read_only` is set. A function
+// registered with the no-writes flag can't write either way.
+fn call_function(
+    backend: &Backend,
+    name: &str,
+    keys: Vec<Vec<u8>>,
+    args: Vec<BulkString>,
+    read_only: bool,
+) -> RespFrame {
+    let Some((library, function)) = backend.functions().find(name) else {
+        return SimpleError::new("ERR Function not found").into();
+    };
+    if read_only && !function.is_read_only() {
+        return SimpleError::new(
+            "ERR Can not execute a script with write flag using *_ro command.",
+        )
+        .into();
+    }
+    run_function(backend, &library, &function, keys, args)
+}
+
+#[cfg(feature = "scripting")]
+fn run_script(
+    backend: &Backend,
+    script: &str,
+    keys: Vec<Vec<u8>>,
+    args: Vec<BulkString>,
+) -> RespFrame {
+    let run = backend.start_script();
+    let reply = match lua::run(backend, &run, script, keys, args) {
+        Ok(reply) => reply,
+        Err(e) => SimpleError::new(lua::error_message(&e)).into(),
+    };
+    backend.end_script();
+    reply
+}
+
+#[cfg(feature = "scripting")]
+fn run_function(
+    backend: &Backend,
+    library: &FunctionLibrary,
+    function: &FunctionInfo,
+    keys: Vec<Vec<u8>>,
+    args: Vec<BulkString>,
+) -> RespFrame {
+    let body = &library.code[library.code.find('\n').unwrap_or(library.code.len())..];
+    let run = backend.start_script();
+    let reply = match lua::fcall(backend, &run, body, function, keys, args) {
+        Ok(reply) => reply,
+        Err(e) => SimpleError::new(lua::error_message(&e)).into(),
+    };
+    backend.end_script();
+    reply
+}
+
+#[cfg(feature = "scripting")]
+fn register_functions(body: &str) -> Result<Vec<FunctionInfo>, SimpleError> {
+    lua::register(body).map_err(|e| SimpleError::new(lua::error_message(&e)))
+}
+
+// Checks that `script` compiles, without running it.
+#[cfg(feature = "scripting")]
+fn compile_script(script: &str) -> Result<(), SimpleError> {
+    lua::compile(script).map_err(|e| SimpleError::new(lua::error_message(&e)))
+}
+
+#[cfg(not(feature = "scripting"))]
+fn compile_script(_: &str) -> Result<(), SimpleError> {
+    Err(SimpleError::new(
+        "ERR this server was built without scripting support",
+    ))
+}
+
+#[cfg(not(feature = "scripting"))]
+fn run_script(_: &Backend, _: &str, _: Vec<Vec<u8>>, _: Vec<BulkString>) -> RespFrame {
+    SimpleError::new("ERR this server was built without scripting support").into()
+}
+
+#[cfg(not(feature = "scripting"))]
+fn run_function(
+    _: &Backend,
+    _: &FunctionLibrary,
+    _: &FunctionInfo,
+    _: Vec<Vec<u8>>,
+    _: Vec<BulkString>,
+) -> RespFrame {
+    SimpleError::new("ERR this server was built without scripting support").into()
+}
+
+#[cfg(not(feature = "scripting"))]
+fn register_functions(_: &str) -> Result<Vec<FunctionInfo>, SimpleError> {
+    Err(SimpleError::new(
+        "ERR this server was built without scripting support",
+    ))
+}
+
+// The Lua side of scripting: a fresh interpreter per script, with the KEYS and ARGV tables and
+// the redis library calling back into the commands.
+#[cfg(feature = "scripting")]
+mod lua {
+    use crate::{
+        cmd::{lookup_command, Command, CommandExecutor, READONLY_ERROR},
+        Backend, BulkString, FunctionInfo, RespArray, RespFrame, RespNull, ScriptRun, SimpleError,
+        SimpleString, FUNCTION_FLAGS,
+    };
+    use mlua::{HookTriggers, IntoLua, Lua, LuaOptions, MultiValue, StdLib, Table, Value};
+    use std::cell::RefCell;
+    use std::sync::Arc;
+
+    // how often a running script checks whether SCRIPT KILL stopped it
+    const KILL_CHECK_INSTRUCTIONS: u32 = 1000;
+
+    pub(super) fn run(
+        backend: &Backend,
+        run: &Arc<ScriptRun>,
+        script: &str,
+        keys: Vec<Vec<u8>>,
+        args: Vec<BulkString>,
+    ) -> mlua::Result<RespFrame> {
+        let lua = sandbox(run)?;
+        let globals = lua.globals();
+        globals.set("KEYS", argv(&lua, &keys)?)?;
+        globals.set("ARGV", argv(&lua, &args)?)?;
+        with_redis(&lua, backend, run, false, || {
+            lua.load(script).set_name("@user_script").eval::<Value>()
+        })
+    }
+
+    // Runs `function` of the library with the code `body`, passing it the keys and the
+    // arguments. Functions registered with no-writes can't run write commands.
+    pub(super) fn fcall(
+        backend: &Backend,
+        run: &Arc<ScriptRun>,
+        body: &str,
+        function: &FunctionInfo,
+        keys: Vec<Vec<u8>>,
+        args: Vec<BulkString>,
+    ) -> mlua::Result<RespFrame> {
+        let lua = sandbox(run)?;
+        let (callbacks, _) = register_functions(&lua, body)?;
+        let callback = callbacks.get::<_, mlua::Function>(function.name.as_str())?;
+        let keys = argv(&lua, &keys)?;
+        let args = argv(&lua, &args)?;
+        with_redis(&lua, backend, run, function.is_read_only(), || {
+            callback.call::<_, Value>((keys, args))
+        })
+    }
+
+    // The functions the library with the code `body` registers, without running any.
+    pub(super) fn register(body: &str) -> mlua::Result<Vec<FunctionInfo>> {
+        let lua = sandbox(&Arc::new(ScriptRun::new(0)))?;
+        let (_, registered) = register_functions(&lua, body)?;
+        Ok(registered)
+    }
+
+    // An interpreter without access to the file system or the process, stopping once
+    // SCRIPT KILL killed `run`. The base library is always loaded, the functions of it reading
+    // files, compiling code or writing to stdout are removed from it.
+    fn sandbox(run: &Arc<ScriptRun>) -> mlua::Result<Lua> {
+        let lua = Lua::new_with(
+            StdLib::TABLE | StdLib::STRING | StdLib::MATH,
+            LuaOptions::default(),
+        )?;
+        for name in ["dofile", "loadfile", "load", "loadstring", "print"] {
+            lua.globals().set(name, Value::Nil)?;
+        }
+        let killable = run.clone();
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(KILL_CHECK_INSTRUCTIONS),
+            move |_, _| {
+                if killable.killed() {
+                    return Err(mlua::Error::RuntimeError(
+                        "ERR Script killed by user with SCRIPT KILL...".to_string(),
+                    ));
+                }
+                Ok(())
+            },
+        );
+        let redis = lua.create_table()?;
+        redis.set(
+            "status_reply",
+            lua.create_function(|lua, status: mlua::String| reply_table(lua, "ok", status))?,
+        )?;
+        redis.set(
+            "error_reply",
+            lua.create_function(|lua, error: mlua::String| reply_table(lua, "err", error))?,
+        )?;
+        lua.globals().set("redis", redis)?;
+        Ok(lua)
+    }
+
+    fn argv<'lua>(lua: &'lua Lua, args: &[impl AsRef<[u8]>]) -> mlua::Result<Table<'lua>> {
+        let args = args
+            .iter()
+            .map(|arg| lua.create_string(arg.as_ref()))
+            .collect::<mlua::Result<Vec<_>>>()?;
+        lua.create_sequence_from(args)
+    }
+
+    // Runs `body` with redis.call and redis.pcall running commands, and converts what it
+    // returned into the reply.
+    fn with_redis<'lua>(
+        lua: &'lua Lua,
+        backend: &Backend,
+        run: &ScriptRun,
+        read_only: bool,
+        body: impl FnOnce() -> mlua::Result<Value<'lua>>,
+    ) -> mlua::Result<RespFrame> {
+        let redis = lua.globals().get::<_, Table>("redis")?;
+        lua.scope(|scope| {
+            // raises the error a command replies with
+            redis.set(
+                "call",
+                scope.create_function(|lua, args: MultiValue| {
+                    match call(backend, run, read_only, args) {
+                        RespFrame::Error(e) => Err(mlua::Error::RuntimeError(e.0)),
+                        reply => to_lua(lua, reply),
+                    }
+                })?,
+            )?;
+            // returns the error a command replies with as a table like {err = "..."}
+            redis.set(
+                "pcall",
+                scope.create_function(|lua, args: MultiValue| {
+                    to_lua(lua, call(backend, run, read_only, args))
+                })?,
+            )?;
+            Ok(to_resp(body()?))
+        })
+    }
+
+    // Runs the library code `body`, which registers its functions with
+    // redis.register_function. Returns the callbacks by name along with what was registered.
+    fn register_functions<'lua>(
+        lua: &'lua Lua,
+        body: &str,
+    ) -> mlua::Result<(Table<'lua>, Vec<FunctionInfo>)> {
+        let callbacks = lua.create_table()?;
+        let registered = RefCell::new(Vec::new());
+        let redis = lua.globals().get::<_, Table>("redis")?;
+        lua.scope(|scope| {
+            redis.set(
+                "register_function",
+                scope.create_function(|_, args: MultiValue| {
+                    let (function, callback) = register_args(args)?;
+                    if !super::is_valid_name(&function.name) {
+                        return Err(raise("Function names can only contain letters, numbers, or underscores(_) and must be at least one character long"));
+                    }
+                    if callbacks.contains_key(function.name.as_str())? {
+                        return Err(raise("Function already exists in the library"));
+                    }
+                    callbacks.set(function.name.as_str(), callback)?;
+                    registered.borrow_mut().push(function);
+                    Ok(())
+                })?,
+            )?;
+            lua.load(body).set_name("@user_function").exec()
+        })?;
+        // only the library code registers functions
+        redis.set("register_function", Value::Nil)?;
+        Ok((callbacks, registered.into_inner()))
+    }
+
+    // The arguments of redis.register_function: a name and a callback, or a table with the
+    // named arguments function_name, callback, flags and description.
+    fn register_args(args: MultiValue) -> mlua::Result<(FunctionInfo, mlua::Function)> {
+        let mut args = args.into_iter();
+        let (name, callback, flags, description) = match (args.next(), args.next(), args.next()) {
+            (Some(Value::String(name)), Some(Value::Function(callback)), None) => {
+                (Some(name), Some(callback), None, None)
+            }
+            (Some(Value::Table(table)), None, None) => {
+                let (mut name, mut callback, mut flags, mut description) = (None, None, None, None);
+                for pair in table.pairs::<String, Value>() {
+                    match pair? {
+                        (key, Value::String(value)) if key == "function_name" => name = Some(value),
+                        (key, Value::Function(value)) if key == "callback" => callback = Some(value),
+                        (key, Value::Table(value)) if key == "flags" => flags = Some(value),
+                        (key, Value::String(value)) if key == "description" => {
+                            description = Some(value)
+                        }
+                        _ => return Err(raise("unknown argument given to redis.register_function")),
+                    }
+                }
+                (name, callback, flags, description)
+            }
+            (_, None, None) => {
+                return Err(raise("calling redis.register_function with a single argument is only applicable to Lua table (representing named arguments)"))
+            }
+            _ => return Err(raise("wrong arguments given to redis.register_function")),
+        };
+        let name =
+            name.ok_or_else(|| raise("redis.register_function must get a function name argument"))?;
+        let callback = callback
+            .ok_or_else(|| raise("redis.register_function must get a callback argument"))?;
+        let mut function = FunctionInfo {
+            name: name.to_str()?.to_string(),
+            description: description.map(|d| d.to_string_lossy().into_owned()),
+            flags: vec![],
+        };
+        for flag in flags
+            .iter()
+            .flat_map(|flags| flags.clone().sequence_values::<String>())
+        {
+            let flag = flag?;
+            if !FUNCTION_FLAGS.contains(&flag.as_str()) {
+                return Err(raise("unknown flag given"));
+            }
+            function.flags.push(flag);
+        }
+        Ok((function, callback))
+    }
+
+    // An error raised from Rust, replied as is.
+    fn raise(message: &str) -> mlua::Error {
+        mlua::Error::RuntimeError(format!("ERR {message}"))
+    }
+
+    pub(super) fn compile(script: &str) -> mlua::Result<()> {
+        let lua = Lua::new_with(StdLib::NONE, LuaOptions::default())?;
+        lua.load(script).set_name("@user_script").into_function()?;
+        Ok(())
+    }
+
+    // The message of the error a script failed with, as replied by EVAL.
+    pub(super) fn error_message(e: &mlua::Error) -> String {
+        match e {
+            mlua::Error::CallbackError { cause, .. } => match cause.as_ref() {
+                // raised by redis.call, already with its error code
+                mlua::Error::RuntimeError(message) => message.clone(),
+                cause => error_message(cause),
+            },
+            mlua::Error::SyntaxError { message, .. } => {
+                format!("ERR Error compiling script (new function): {message}")
+            }
+            // without the stack traceback on the lines after
+            mlua::Error::RuntimeError(message) => {
+                format!("ERR {}", message.lines().next().unwrap_or_default())
+            }
+            e => format!("ERR {e}"),
+        }
+    }
+
+    // Runs the command `redis.call` or `redis.pcall` was given and returns its reply.
+    fn call(backend: &Backend, run: &ScriptRun, read_only: bool, args: MultiValue) -> RespFrame {
+        let mut frames = Vec::with_capacity(args.len());
+        for arg in args {
+            let arg = match arg {
+                Value::String(s) => BulkString::new(s.as_bytes().to_vec()),
+                Value::Integer(_) | Value::Number(_) => match arg.to_string() {
+                    Ok(s) => BulkString::from(s),
+                    Err(_) => return SimpleError::new("ERR Invalid number").into(),
+                },
+                _ => {
+                    return SimpleError::new(
+                        "ERR Lua redis lib command arguments must be strings or integers",
+                    )
+                    .into()
+                }
+            };
+            frames.push(RespFrame::BulkString(arg));
+        }
+        let name = match frames.first() {
+            Some(RespFrame::BulkString(name)) => String::from_utf8_lossy(name).to_lowercase(),
+            _ => {
+                return SimpleError::new(
+                    "ERR Please specify at least one argument for this redis lib call",
+                )
+                .into()
+            }
+        };
+        let spec = lookup_command(backend, &name);
+        let write = spec
+            .as_ref()
+            .is_some_and(|spec| spec.flags.contains(&"write"));
+        match spec {
+            Some(spec) if spec.flags.contains(&"noscript") => {
+                return SimpleError::new("ERR This Redis command is not allowed from script").into()
+            }
+            Some(spec) if spec.flags.contains(&"write") && read_only => {
+                return SimpleError::new(
+                    "ERR Write commands are not allowed from read-only scripts.",
+                )
+                .into()
+            }
+            Some(spec) if spec.flags.contains(&"write") && backend.read_only_replica() => {
+                return SimpleError::new(READONLY_ERROR).into()
+            }
+            // a script that wrote can't be killed without breaking atomicity
+            Some(spec) if spec.flags.contains(&"write") => run.record_write(),
+            Some(_) => {}
+            None => return SimpleError::new("ERR Unknown Redis command called from script").into(),
+        }
+        // the script itself isn't propagated, the writes it made are
+        let logged = (write && backend.propagating()).then(|| {
+            frames
+                .iter()
+                .map(|frame| match frame {
+                    RespFrame::BulkString(arg) => arg.to_vec(),
+                    _ => vec![],
+                })
+                .collect()
+        });
+        match Command::parse(RespArray::new(frames), backend) {
+            Ok(cmd) => {
+                let reply = cmd.execute(backend);
+                if write {
+                    backend.record_write(&reply);
+                }
+                if let Some(args) = logged {
+                    backend.propagate(args, &reply);
+                }
+                reply
+            }
+            Err(e) => SimpleError::new(format!("ERR {e}")).into(),
+        }
+    }
+
+    fn reply_table<'lua>(
+        lua: &'lua Lua,
+        field: &str,
+        message: mlua::String<'lua>,
+    ) -> mlua::Result<Table<'lua>> {
+        let table = lua.create_table()?;
+        table.set(field, message)?;
+        Ok(table)
+    }
+
+    // A command reply as a script sees it, the way Redis converts RESP2 replies.
+    fn to_lua(lua: &Lua, frame: RespFrame) -> mlua::Result<Value<'_>> {
+        let value = match frame {
+            RespFrame::SimpleString(s) => {
+                Value::Table(reply_table(lua, "ok", lua.create_string(s.0)?)?)
+            }
+            RespFrame::Error(e) => Value::Table(reply_table(lua, "err", lua.create_string(e.0)?)?),
+            RespFrame::Integer(i) => i.into_lua(lua)?,
+            RespFrame::BulkString(s) => Value::String(lua.create_string(s.0)?),
+            RespFrame::VerbatimString(s) => Value::String(lua.create_string(s.data)?),
+            RespFrame::Double(d) => Value::String(lua.create_string(d.to_string())?),
+            RespFrame::Boolean(true) => 1.into_lua(lua)?,
+            RespFrame::Boolean(false) | RespFrame::Null(_) => Value::Boolean(false),
+            RespFrame::Array(RespArray(frames))
+            | RespFrame::Set(crate::RespSet(frames))
+            | RespFrame::Push(crate::RespPush(frames)) => {
+                let values = frames
+                    .into_iter()
+                    .map(|frame| to_lua(lua, frame))
+                    .collect::<mlua::Result<Vec<_>>>()?;
+                Value::Table(lua.create_sequence_from(values)?)
+            }
+            RespFrame::Map(map) => {
+                let mut values = Vec::with_capacity(map.0.len() * 2);
+                for (key, value) in map.0 {
+                    values.push(Value::String(lua.create_string(key)?));
+                    values.push(to_lua(lua, value)?);
+                }
+                Value::Table(lua.create_sequence_from(values)?)
+            }
+        };
+        Ok(value)
+    }
+
+    // What a script returned as the reply of EVAL. Numbers are truncated to integers and a
+    // table stops at its first nil.
+    fn to_resp(value: Value) -> RespFrame {
+        match value {
+            Value::Boolean(true) => RespFrame::Integer(1),
+            Value::Integer(_) | Value::Number(_) => {
+                let n = value.as_i64().or_else(|| value.as_f64().map(|n| n as i64));
+                RespFrame::Integer(n.unwrap_or_default())
+            }
+            Value::String(s) => BulkString::new(s.as_bytes().to_vec()).into(),
+            Value::Table(table) => {
+                if let Ok(Value::String(e)) = table.raw_get::<_, Value>("err") {
+                    return SimpleError::new(e.to_string_lossy().into_owned()).into();
+                }
+                if let Ok(Value::String(s)) = table.raw_get::<_, Value>("ok") {
+                    return SimpleString::new(s.to_string_lossy().into_owned()).into();
+                }
+                let frames = table
+                    .sequence_values::<Value>()
+                    .map_while(Result::ok)
+                    .map(to_resp)
+                    .collect::<Vec<_>>();
+                RespArray::new(frames).into()
+            }
+            _ => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Command, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[cfg(feature = "scripting")]
+    fn eval(backend: &Backend, script: &str, keys: &[&str], args: &[&str]) -> RespFrame {
+        let cmd = Eval {
+            script: script.to_string(),
+            keys: keys.iter().map(|key| key.as_bytes().to_vec()).collect(),
+            args: args.iter().map(|arg| BulkString::from(*arg)).collect(),
+        };
+        cmd.execute(backend)
+    }
+
+    #[test]
+    fn test_eval_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$4\r\nEVAL\r\n$14\r\nreturn KEYS[1]\r\n$1\r\n1\r\n$3\r\nkey\r\n$3\r\narg\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: Eval = frame.try_into()?;
+        assert_eq!(cmd.script, "return KEYS[1]");
+        assert_eq!(cmd.keys, [b"key".to_vec()]);
+        assert_eq!(cmd.args, [BulkString::from("arg")]);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$7\r\nEVALSHA\r\n$3\r\nabc\r\n$1\r\n2\r\n$3\r\nkey\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(Command::try_from(frame).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "scripting")]
+    #[test]
+    fn test_eval() {
+        let backend = Backend::new();
+        let script = "return {KEYS[1], ARGV[1], 1.5, true, false, nil, 'after nil'}";
+        let expected = RespArray::new([
+            BulkString::from("key").into(),
+            BulkString::from("arg").into(),
+            RespFrame::Integer(1),
+            RespFrame::Integer(1),
+            RespFrame::Null(crate::RespNull),
+        ]);
+        assert_eq!(eval(&backend, script, &["key"], &["arg"]), expected.into());
+
+        let script = "redis.call('SET', KEYS[1], ARGV[1]); return redis.call('GET', KEYS[1])";
+        assert_eq!(
+            eval(&backend, script, &["greeting"], &["hello"]),
+            BulkString::from("hello").into()
+        );
+        assert_eq!(
+            eval(&backend, "return redis.call('SET', 'k', 'v')", &[], &[]),
+            crate::SimpleString::new("OK").into()
+        );
+
+        // errors of redis.call end the script, redis.pcall returns them
+        let script = "redis.call('SELECT', 100); return 1";
+        assert_eq!(
+            eval(&backend, script, &[], &[]),
+            SimpleError::new("ERR DB index is out of range").into()
+        );
+        let script = "return redis.pcall('SELECT', 100)['err']";
+        assert_eq!(
+            eval(&backend, script, &[], &[]),
+            BulkString::from("ERR DB index is out of range").into()
+        );
+        assert_eq!(
+            eval(
+                &backend,
+                "return redis.call('EVAL', 'return 1', 0)",
+                &[],
+                &[]
+            ),
+            SimpleError::new("ERR This Redis command is not allowed from script").into()
+        );
+        assert_eq!(
+            eval(&backend, "return redis.error_reply('MY error')", &[], &[]),
+            SimpleError::new("MY error").into()
+        );
+        assert!(matches!(
+            eval(&backend, "return (", &[], &[]),
+            RespFrame::Error(e) if e.0.starts_with("ERR Error compiling script")
+        ));
+        assert_eq!(
+            eval(&backend, "return os.exit()", &[], &[]),
+            SimpleError::new("ERR user_script:1: attempt to index global 'os' (a nil value)")
+                .into()
+        );
+        for script in [
+            "return dofile('/etc/hostname')",
+            "return loadfile('/etc/hostname')",
+            "return load(function() return nil end)",
+            "return loadstring('return 1')",
+        ] {
+            assert!(matches!(
+                eval(&backend, script, &[], &[]),
+                RespFrame::Error(e) if e.0.contains("(a nil value)")
+            ));
+        }
+    }
+
+    #[cfg(feature = "scripting")]
+    #[test]
+    fn test_evalsha() {
+        let backend = Backend::new();
+        let cmd = EvalSha {
+            sha1: "e0e1f9fabfc9d4800c877a703b823ac0578ff8db".to_string(),
+            keys: vec![],
+            args: vec![],
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("NOSCRIPT No matching script. Please use EVAL.").into()
+        );
+
+        eval(&backend, "return 1", &[], &[]);
+        let cmd = EvalSha {
+            sha1: "E0E1F9FABFC9D4800C877A703B823AC0578FF8DB".to_string(),
+            keys: vec![],
+            args: vec![],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+    }
+
+    #[test]
+    fn test_script_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nSCRIPT\r\n$4\r\nload\r\n$8\r\nreturn 1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: Script = frame.try_into()?;
+        assert_eq!(
+            cmd.subcommand,
+            ScriptSubcommand::Load("return 1".to_string())
+        );
+
+        buf.extend_from_slice(b"*3\r\n$6\r\nSCRIPT\r\n$5\r\nFLUSH\r\n$5\r\nASYNC\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: Script = frame.try_into()?;
+        assert_eq!(cmd.subcommand, ScriptSubcommand::Flush);
+
+        buf.extend_from_slice(b"*2\r\n$6\r\nSCRIPT\r\n$6\r\nEXISTS\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(Script::try_from(frame).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "scripting")]
+    #[test]
+    fn test_script() {
+        let backend = Backend::new();
+        let cmd = Script {
+            subcommand: ScriptSubcommand::Load("return 1".to_string()),
+        };
+        let sha1 = "e0e1f9fabfc9d4800c877a703b823ac0578ff8db";
+        assert_eq!(cmd.execute(&backend), BulkString::from(sha1).into());
+        // loading doesn't run the script
+        let cmd = Script {
+            subcommand: ScriptSubcommand::Load("redis.call('SET', 'k', 'v')".to_string()),
+        };
+        cmd.execute(&backend);
+        assert!(!backend.exists(b"k"));
+        let cmd = Script {
+            subcommand: ScriptSubcommand::Load("return (".to_string()),
+        };
+        assert!(matches!(
+            cmd.execute(&backend),
+            RespFrame::Error(e) if e.0.starts_with("ERR Error compiling script")
+        ));
+        assert_eq!(backend.scripts().len(), 2);
+
+        let cmd = Script {
+            subcommand: ScriptSubcommand::Exists(vec![sha1.to_uppercase(), "ffff".to_string()]),
+        };
+        let expected = RespArray::new([RespFrame::Integer(1), RespFrame::Integer(0)]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = Script {
+            subcommand: ScriptSubcommand::Flush,
+        };
+        cmd.execute(&backend);
+        assert!(backend.scripts().is_empty());
+    }
+
+    #[cfg(feature = "scripting")]
+    #[test]
+    fn test_script_kill() {
+        let backend = Backend::new();
+        let kill = || {
+            Script {
+                subcommand: ScriptSubcommand::Kill,
+            }
+            .execute(&backend)
+        };
+        assert_eq!(
+            kill(),
+            SimpleError::new("NOTBUSY No scripts in execution right now.").into()
+        );
+
+        let runner = backend.clone();
+        let script = std::thread::spawn(move || eval(&runner, "while true do end", &[], &[]));
+        while backend.running_script().is_none() {
+            std::thread::yield_now();
+        }
+        assert_eq!(kill(), crate::SimpleString::new("OK").into());
+        assert_eq!(
+            script.join().expect("the script thread"),
+            SimpleError::new("ERR Script killed by user with SCRIPT KILL...").into()
+        );
+        assert!(backend.running_script().is_none());
+
+        // a script that wrote runs to its end
+        backend.start_script().record_write();
+        assert!(matches!(kill(), RespFrame::Error(e) if e.0.starts_with("UNKILLABLE")));
+        backend.end_script();
+    }
+
+    #[test]
+    fn test_function_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$8\r\nFUNCTION\r\n$4\r\nLOAD\r\n$7\r\nREPLACE\r\n$4\r\ncode\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: Function = frame.try_into()?;
+        assert_eq!(
+            cmd.subcommand,
+            FunctionSubcommand::Load {
+                code: "code".to_string(),
+                replace: true
+            }
+        );
+
+        buf.extend_from_slice(
+            b"*5\r\n$8\r\nFUNCTION\r\n$4\r\nlist\r\n$8\r\nWITHCODE\r\n$11\r\nLIBRARYNAME\r\n$2\r\nl*\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: Function = frame.try_into()?;
+        assert_eq!(
+            cmd.subcommand,
+            FunctionSubcommand::List {
+                pattern: Some("l*".to_string()),
+                with_code: true
+            }
+        );
+
+        buf.extend_from_slice(b"*3\r\n$8\r\nFUNCTION\r\n$4\r\nLIST\r\n$11\r\nLIBRARYNAME\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(Function::try_from(frame).is_err());
+
+        buf.extend_from_slice(b"*5\r\n$5\r\nFCALL\r\n$1\r\nf\r\n$1\r\n1\r\n$1\r\nk\r\n$1\r\na\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: FCall = frame.try_into()?;
+        assert_eq!(cmd.function, "f");
+        assert_eq!(cmd.keys, [b"k".to_vec()]);
+        assert_eq!(cmd.args, [BulkString::from("a")]);
+        Ok(())
+    }
+
+    #[cfg(feature = "scripting")]
+    #[test]
+    fn test_function() {
+        let backend = Backend::new();
+        let load = |code: &str, replace| {
+            Function {
+                subcommand: FunctionSubcommand::Load {
+                    code: code.to_string(),
+                    replace,
+                },
+            }
+            .execute(&backend)
+        };
+        let fcall = |function: &str, keys: &[&str], args: &[&str], read_only| {
+            let keys = keys.iter().map(|key| key.as_bytes().to_vec()).collect();
+            let args = args.iter().map(|arg| BulkString::from(*arg)).collect();
+            call_function(&backend, function, keys, args, read_only)
+        };
+        let code = "#!lua name=mylib
+            redis.register_function('set', function(keys, args)
+                return redis.call('SET', keys[1], args[1])
+            end)
+            redis.register_function{
+                function_name = 'get',
+                callback = function(keys) return redis.call('GET', keys[1]) end,
+                flags = {'no-writes'},
+                description = 'reads a key',
+            }";
+        assert_eq!(load(code, false), BulkString::from("mylib").into());
+        assert_eq!(
+            load(code, false),
+            SimpleError::new("ERR Library 'mylib' already exists").into()
+        );
+        assert_eq!(load(code, true), BulkString::from("mylib").into());
+        assert_eq!(
+            load(
+                "#!lua name=other\nredis.register_function('get', function() end)",
+                false
+            ),
+            SimpleError::new("ERR Function get already exists").into()
+        );
+        assert_eq!(
+            load("return 1", false),
+            SimpleError::new("ERR Missing library metadata").into()
+        );
+        assert_eq!(
+            load("#!js name=lib\n", false),
+            SimpleError::new("ERR Engine 'js' not found").into()
+        );
+        assert_eq!(
+            load("#!lua name=empty\nlocal x = 1", false),
+            SimpleError::new("ERR No functions registered").into()
+        );
+        assert_eq!(
+            load(
+                "#!lua name=lib\nredis.register_function{function_name='f', callback=function() end, flags={'fast'}}",
+                false
+            ),
+            SimpleError::new("ERR unknown flag given").into()
+        );
+        // the library code only registers functions
+        assert!(matches!(
+            load("#!lua name=lib\nredis.call('SET', 'k', 'v')", false),
+            RespFrame::Error(e) if e.0.starts_with("ERR user_function:2:")
+        ));
+
+        assert_eq!(fcall("set", &["k"], &["v"], false), RESP_OK.clone());
+        assert_eq!(
+            fcall("get", &["k"], &[], true),
+            BulkString::from("v").into()
+        );
+        assert_eq!(
+            fcall("set", &["k"], &["v"], true),
+            SimpleError::new("ERR Can not execute a script with write flag using *_ro command.")
+                .into()
+        );
+        assert_eq!(
+            fcall("nope", &[], &[], false),
+            SimpleError::new("ERR Function not found").into()
+        );
+        let code = "#!lua name=rolib
+            redis.register_function{
+                function_name = 'sneaky',
+                callback = function() return redis.call('DEL', 'k') end,
+                flags = {'no-writes'},
+            }";
+        load(code, false);
+        assert_eq!(
+            fcall("sneaky", &[], &[], false),
+            SimpleError::new("ERR Write commands are not allowed from read-only scripts.").into()
+        );
+        assert!(backend.exists(b"k"));
+
+        let list = Function {
+            subcommand: FunctionSubcommand::List {
+                pattern: Some("my*".to_string()),
+                with_code: false,
+            },
+        };
+        let get = RespArray::new(vec![
+            BulkString::from("name").into(),
+            BulkString::from("get").into(),
+            BulkString::from("description").into(),
+            BulkString::from("reads a key").into(),
+            BulkString::from("flags").into(),
+            RespArray::new(vec![BulkString::from("no-writes").into()]).into(),
+        ]);
+        match list.execute(&backend) {
+            RespFrame::Array(RespArray(libraries)) => match &libraries[..] {
+                [RespFrame::Array(RespArray(library))] => {
+                    assert_eq!(library[1], BulkString::from("mylib").into());
+                    assert!(matches!(&library[5], RespFrame::Array(RespArray(functions))
+                        if functions.len() == 2 && functions[1] == get.clone().into()));
+                }
+                libraries => panic!("unexpected libraries {libraries:?}"),
+            },
+            reply => panic!("unexpected reply {reply:?}"),
+        }
+
+        let delete = |name: &str| {
+            Function {
+                subcommand: FunctionSubcommand::Delete(name.to_string()),
+            }
+            .execute(&backend)
+        };
+        assert_eq!(delete("mylib"), RESP_OK.clone());
+        assert_eq!(
+            delete("mylib"),
+            SimpleError::new("ERR Library not found").into()
+        );
+        assert_eq!(backend.functions().len(), 1);
+    }
+}
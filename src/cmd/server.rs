@@ -0,0 +1,411 @@
+use super::{
+    command::CommandSpec, extract_args, extract_string, validate_command,
+    validate_variadic_command, BgRewriteAof, BgSave, Command, CommandError, CommandExecutor,
+    LastSave, Save, Shutdown, Time, RESP_OK,
+};
+use crate::{
+    Backend, BulkString, RespArray, RespDecoder, RespError, RespFrame, SimpleError, SimpleString,
+};
+use bytes::BytesMut;
+use std::io;
+use tracing::warn;
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("time", 1, "server", "Returns the server time.")
+        .flags(&["loading", "stale", "fast"]),
+    CommandSpec::new(
+        "shutdown",
+        -1,
+        "server",
+        "Synchronously saves the database(s) to disk and shuts down the Redis server.",
+    )
+    .flags(&["admin", "noscript", "loading", "stale", "allow_busy"]),
+    CommandSpec::new(
+        "lastsave",
+        1,
+        "server",
+        "Returns the Unix timestamp of the last successful save to disk.",
+    )
+    .flags(&["loading", "stale", "fast"]),
+    CommandSpec::new(
+        "save",
+        1,
+        "server",
+        "Synchronously saves the database(s) to disk.",
+    )
+    .flags(&["admin", "noscript"]),
+    CommandSpec::new(
+        "bgsave",
+        -1,
+        "server",
+        "Asynchronously saves the database(s) to disk.",
+    )
+    .flags(&["admin", "noscript"]),
+    CommandSpec::new(
+        "bgrewriteaof",
+        1,
+        "server",
+        "Asynchronously rewrites the append-only file to disk.",
+    )
+    .flags(&["admin", "noscript"]),
+];
+
+impl CommandExecutor for Time {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let now = backend.now();
+        RespArray::new([
+            BulkString::from(now.as_secs().to_string()).into(),
+            BulkString::from(now.subsec_micros().to_string()).into(),
+        ])
+        .into()
+    }
+}
+
+impl CommandExecutor for Shutdown {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        // without SAVE or NOSAVE, save only when snapshotting is configured
+        let save = self
+            .save
+            .unwrap_or_else(|| !backend.config().read().save.is_empty());
+        if save {
+            if let Err(e) = backend.save() {
+                warn!("Error trying to save the DB, can't exit: {}", e);
+                return SimpleError::new("ERR Errors trying to SHUTDOWN. Check logs.").into();
+            }
+        }
+        backend.shutdown();
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for LastSave {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        RespFrame::Integer(backend.save_status().last_save())
+    }
+}
+
+impl CommandExecutor for Save {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        if backend.save_status().in_progress() {
+            return SimpleError::new("ERR Background save already in progress").into();
+        }
+        match backend.save() {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => {
+                warn!("Error saving the DB: {}", e);
+                SimpleError::new("ERR").into()
+            }
+        }
+    }
+}
+
+// The network layer runs it with exclusive access to the backend, which the dataset is frozen
+// with, see Backend::bgsave.
+impl CommandExecutor for BgSave {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let running = backend.save_status().in_progress();
+        if !backend.bgsave(self.schedule) {
+            return SimpleError::new("ERR Background save already in progress").into();
+        }
+        if running && self.schedule {
+            SimpleString::new("Background saving scheduled").into()
+        } else {
+            SimpleString::new("Background saving started").into()
+        }
+    }
+}
+
+// The network layer runs it with exclusive access to the backend, which the dataset is frozen
+// with, see Backend::rewrite_append_log.
+impl CommandExecutor for BgRewriteAof {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        if !backend.rewrite_append_log() {
+            return SimpleError::new(
+                "ERR Background append only file rewriting already in progress",
+            )
+            .into();
+        }
+        SimpleString::new("Background append only file rewriting started").into()
+    }
+}
+
+// Rebuilds the dataset at startup by running the commands of the append only file, and returns
+// how many ran, None if there is no such file. An incomplete last command, left by a crash in
+// the middle of writing it, is dropped from the file, which the server then keeps logging to.
+pub fn load_append_log(backend: &Backend) -> io::Result<Option<usize>> {
+    let Some(data) = backend.read_append_log()? else {
+        return Ok(None);
+    };
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+    let mut buf = BytesMut::from(&data[..]);
+    // SELECT changes the database of this handle only
+    let handle = backend.clone();
+    let mut count = 0;
+    while !buf.is_empty() {
+        match RespArray::decode(&mut buf) {
+            Ok(args) => {
+                let cmd = Command::parse(args, &handle).map_err(|e| invalid(e.to_string()))?;
+                cmd.execute(&handle);
+                count += 1;
+            }
+            Err(RespError::NotComplete) => {
+                warn!("The append only file ends with an incomplete command, dropping it");
+                break;
+            }
+            Err(e) => return Err(invalid(e.to_string())),
+        }
+    }
+    let len = data.len() - buf.len();
+    backend.open_append_log(len as u64, handle.selected_db())?;
+    Ok(Some(count))
+}
+
+impl TryFrom<RespArray> for Time {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["time"], 0)?;
+        Ok(Time)
+    }
+}
+
+impl TryFrom<RespArray> for LastSave {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lastsave"], 0)?;
+        Ok(LastSave)
+    }
+}
+
+impl TryFrom<RespArray> for Save {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["save"], 0)?;
+        Ok(Save)
+    }
+}
+
+impl TryFrom<RespArray> for BgSave {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "bgsave", 0)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let schedule = match (args.next(), args.next()) {
+            (None, _) => false,
+            (Some(arg), None) => match extract_string(Some(arg))?.to_ascii_lowercase().as_str() {
+                "schedule" => true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(BgSave { schedule })
+    }
+}
+
+impl TryFrom<RespArray> for BgRewriteAof {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bgrewriteaof"], 0)?;
+        Ok(BgRewriteAof)
+    }
+}
+
+impl TryFrom<RespArray> for Shutdown {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "shutdown", 0)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let save = match (args.next(), args.next()) {
+            (None, _) => None,
+            (Some(arg), None) => match extract_string(Some(arg))?.to_ascii_lowercase().as_str() {
+                "save" => Some(true),
+                "nosave" => Some(false),
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(Shutdown { save })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, ConfigValues, ManualClock, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_time() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$4\r\nTIME\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let _: Time = frame.try_into()?;
+
+        let clock = Arc::new(ManualClock::new(Duration::from_micros(
+            1_700_000_000_123_456,
+        )));
+        let backend = Backend::with_clock(ConfigValues::default(), clock.clone());
+        let expected = RespArray::new([
+            BulkString::from("1700000000").into(),
+            BulkString::from("123456").into(),
+        ]);
+        assert_eq!(Time.execute(&backend), expected.into());
+
+        clock.advance(Duration::from_secs(1));
+        let expected = RespArray::new([
+            BulkString::from("1700000001").into(),
+            BulkString::from("123456").into(),
+        ]);
+        assert_eq!(Time.execute(&backend), expected.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_shutdown() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$8\r\nSHUTDOWN\r\n$6\r\nNOSAVE\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Shutdown = frame.try_into()?;
+        assert_eq!(result.save, Some(false));
+
+        let backend = Backend::new();
+        let signal = backend.shutdown_signal();
+        backend
+            .config()
+            .set(&[("dir".to_string(), "/nonexistent/directory".to_string())])?;
+        let cmd = Shutdown { save: None };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR Errors trying to SHUTDOWN. Check logs.").into()
+        );
+        assert!(!backend.is_shutting_down());
+
+        let cmd = Shutdown { save: Some(false) };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert!(backend.is_shutting_down());
+        assert!(signal.has_changed()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lastsave() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$8\r\nLASTSAVE\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let _: LastSave = frame.try_into()?;
+
+        let clock = Arc::new(ManualClock::new(Duration::from_secs(1_000)));
+        let backend = Backend::with_clock(ConfigValues::default(), clock.clone());
+        assert_eq!(LastSave.execute(&backend), RespFrame::Integer(1_000));
+
+        // a failed save leaves it unchanged
+        clock.advance(Duration::from_secs(5));
+        backend
+            .config()
+            .set(&[("dir".to_string(), "/nonexistent/directory".to_string())])?;
+        assert!(backend.save().is_err());
+        assert_eq!(LastSave.execute(&backend), RespFrame::Integer(1_000));
+        assert!(backend.save_status().last_error().is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_bgsave() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$6\r\nBGSAVE\r\n$8\r\nSCHEDULE\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: BgSave = frame.try_into()?;
+        assert!(cmd.schedule);
+        buf.extend_from_slice(b"*2\r\n$6\r\nBGSAVE\r\n$3\r\nNOW\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(BgSave::try_from(frame).is_err());
+
+        let backend = Backend::new();
+        backend
+            .config()
+            .set(&[("dir".to_string(), "/nonexistent/directory".to_string())])?;
+        assert_eq!(Save.execute(&backend), SimpleError::new("ERR").into());
+
+        // both refuse to run alongside another save, unless BGSAVE is scheduled
+        assert!(backend.save_status().start());
+        let in_progress = SimpleError::new("ERR Background save already in progress").into();
+        assert_eq!(Save.execute(&backend), in_progress);
+        assert_eq!(BgSave { schedule: false }.execute(&backend), in_progress);
+        assert_eq!(
+            BgSave { schedule: true }.execute(&backend),
+            SimpleString::new("Background saving scheduled").into()
+        );
+        backend.save_status().finish(None, 0, 0, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bgrewriteaof() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$12\r\nBGREWRITEAOF\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let _: BgRewriteAof = frame.try_into()?;
+
+        let dir = std::env::temp_dir().join(format!("simple-redis-aof-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let config = [
+            ("dir".to_string(), dir.to_string_lossy().to_string()),
+            ("appendonly".to_string(), "yes".to_string()),
+            ("appendfsync".to_string(), "always".to_string()),
+        ];
+        let backend = Backend::new();
+        backend.config().set(&config)?;
+        let args = |args: &[&str]| args.iter().map(|a| a.as_bytes().to_vec()).collect();
+        backend.set(b"key".to_vec(), BulkString::from("v").into());
+        backend.sadd("set", ["a".to_string(), "b".to_string()])?;
+        let far = backend.now_ms() + 100_000;
+        backend.expire_at(b"set", far, crate::ExpireCondition::Always);
+        // logged before the file exists, the rewrite writes the whole dataset instead
+        backend.propagate(args(&["SET", "key", "v"]), &RESP_OK);
+        assert!(backend.append_log().rewrite_due());
+
+        assert_eq!(
+            BgRewriteAof.execute(&backend),
+            SimpleString::new("Background append only file rewriting started").into()
+        );
+        while backend.append_log().rewrite_status().in_progress() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(backend.append_log().rewrite_status().last_error(), None);
+        backend.select(2);
+        backend.set(b"other".to_vec(), BulkString::from("").into());
+        backend.propagate(args(&["SET", "other", ""]), &RESP_OK);
+        backend.sadd("popped", ["x".to_string()])?;
+        backend.propagate(args(&["SADD", "popped", "x"]), &RespFrame::Integer(1));
+        backend.propagate(args(&["SPOP", "popped"]), &BulkString::from("x").into());
+
+        // a restarted server replays the log
+        let restarted = Backend::new();
+        restarted.config().set(&config)?;
+        assert!(load_append_log(&restarted)?.is_some());
+        assert_eq!(restarted.get(b"key")?, Some(BulkString::from("v").into()));
+        assert_eq!(restarted.scard(b"set")?, 2);
+        assert_eq!(restarted.expiry(b"set"), crate::KeyExpiry::At(far));
+        assert_eq!(restarted.selected_db(), 0);
+        restarted.select(2);
+        assert_eq!(restarted.get(b"other")?, Some(BulkString::from("").into()));
+        assert!(!restarted.exists(b"popped"));
+
+        // and keeps logging after the commands it loaded
+        restarted.propagate(args(&["DEL", "other"]), &RespFrame::Integer(1));
+        let data = std::fs::read(dir.join("appendonly.aof"))?;
+        assert!(data.ends_with(b"*2\r\n$3\r\nDEL\r\n$5\r\nother\r\n"));
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,772 @@
+use super::{
+    command::{CommandSpec, KeySearch},
+    extract_args, extract_integer, extract_key, extract_keys, extract_set_op, extract_string,
+    validate_command, validate_variadic_command, CommandError, CommandExecutor, SAdd, SCard,
+    SCombine, SCombineStore, SInterCard, SIsMember, SMIsMember, SMembers, SMove, SPop, SRandMember,
+    SRem,
+};
+use crate::{
+    BulkString, NotifyClass, RespArray, RespFrame, RespNull, RespSet, SetOp, SimpleError, WrongType,
+};
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("sadd", -3, "set", "Adds one or more members to a set.")
+        .flags(&["write", "fast"])
+        .keys(1, 1, 1),
+    CommandSpec::new("srem", -3, "set", "Removes one or more members from a set.")
+        .flags(&["write", "fast"])
+        .keys(1, 1, 1),
+    CommandSpec::new("smembers", 2, "set", "Returns all members of a set.")
+        .flags(&["readonly"])
+        .keys(1, 1, 1),
+    CommandSpec::new("scard", 2, "set", "Returns the number of members in a set.")
+        .flags(&["readonly", "fast"])
+        .keys(1, 1, 1),
+    CommandSpec::new(
+        "sismember",
+        3,
+        "set",
+        "Determines whether a member belongs to a set.",
+    )
+    .flags(&["readonly", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "smismember",
+        -3,
+        "set",
+        "Determines whether multiple members belong to a set.",
+    )
+    .flags(&["readonly", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new("smove", 4, "set", "Moves a member from one set to another.")
+        .flags(&["write", "fast"])
+        .keys(1, 2, 1),
+    CommandSpec::new(
+        "spop",
+        -2,
+        "set",
+        "Returns one or more random members from a set after removing them.",
+    )
+    .flags(&["write", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "srandmember",
+        -2,
+        "set",
+        "Gets one or more random members from a set.",
+    )
+    .flags(&["readonly"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "sinter",
+        -2,
+        "set",
+        "Returns the intersect of multiple sets.",
+    )
+    .flags(&["readonly"])
+    .keys(1, -1, 1),
+    CommandSpec::new("sunion", -2, "set", "Returns the union of multiple sets.")
+        .flags(&["readonly"])
+        .keys(1, -1, 1),
+    CommandSpec::new(
+        "sdiff",
+        -2,
+        "set",
+        "Returns the difference of multiple sets.",
+    )
+    .flags(&["readonly"])
+    .keys(1, -1, 1),
+    CommandSpec::new(
+        "sintercard",
+        -3,
+        "set",
+        "Returns the number of members of the intersect of multiple sets.",
+    )
+    .flags(&["readonly", "movablekeys"])
+    .movable_keys(KeySearch::KeyNum(1)),
+    CommandSpec::new(
+        "sinterstore",
+        -3,
+        "set",
+        "Stores the intersect of multiple sets in a key.",
+    )
+    .flags(&["write"])
+    .keys(1, -1, 1),
+    CommandSpec::new(
+        "sunionstore",
+        -3,
+        "set",
+        "Stores the union of multiple sets in a key.",
+    )
+    .flags(&["write"])
+    .keys(1, -1, 1),
+    CommandSpec::new(
+        "sdiffstore",
+        -3,
+        "set",
+        "Stores the difference of multiple sets in a key.",
+    )
+    .flags(&["write"])
+    .keys(1, -1, 1),
+];
+
+const SET_OPS: [(&str, SetOp); 3] = [
+    ("sinter", SetOp::Inter),
+    ("sunion", SetOp::Union),
+    ("sdiff", SetOp::Diff),
+];
+
+const SET_STORE_OPS: [(&str, SetOp); 3] = [
+    ("sinterstore", SetOp::Inter),
+    ("sunionstore", SetOp::Union),
+    ("sdiffstore", SetOp::Diff),
+];
+
+impl CommandExecutor for SAdd {
+    fn execute(mut self, backend: &crate::Backend) -> RespFrame {
+        let added = match backend.sadd(self.key.as_slice(), std::mem::take(&mut self.members)) {
+            Ok(added) => added,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        if added > 0 {
+            self.notify(backend, NotifyClass::Set, "sadd", &self.key);
+        }
+        RespFrame::Integer(added as i64)
+    }
+}
+
+impl CommandExecutor for SRem {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let removed = match backend.srem(&self.key, &self.members) {
+            Ok(removed) => removed,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        if removed > 0 {
+            self.notify(backend, NotifyClass::Set, "srem", &self.key);
+            if !backend.exists(&self.key) {
+                self.notify(backend, NotifyClass::Generic, "del", &self.key);
+            }
+        }
+        RespFrame::Integer(removed as i64)
+    }
+}
+
+impl CommandExecutor for SMembers {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.smembers(&self.key) {
+            Ok(members) => set_frame(members),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for SCard {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.scard(&self.key) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for SIsMember {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.sismember(&self.key, &self.member) {
+            Ok(found) => RespFrame::Integer(found as i64),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for SMIsMember {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let result = self
+            .members
+            .iter()
+            .map(|m| Ok(RespFrame::Integer(backend.sismember(&self.key, m)? as i64)))
+            .collect::<Result<Vec<_>, WrongType>>();
+        match result {
+            Ok(result) => RespArray::new(result).into(),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for SMove {
+    fn execute(mut self, backend: &crate::Backend) -> RespFrame {
+        let member = std::mem::take(&mut self.member);
+        let moved = match backend.smove(&self.source, self.destination.clone(), member) {
+            Ok(moved) => moved,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        if moved {
+            self.notify(backend, NotifyClass::Set, "srem", &self.source);
+            if !backend.exists(&self.source) {
+                self.notify(backend, NotifyClass::Generic, "del", &self.source);
+            }
+            self.notify(backend, NotifyClass::Set, "sadd", &self.destination);
+        }
+        RespFrame::Integer(moved as i64)
+    }
+}
+
+impl CommandExecutor for SPop {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let members = match backend.spop(&self.key, self.count.unwrap_or(1)) {
+            Ok(members) => members,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        if !members.is_empty() {
+            self.notify(backend, NotifyClass::Set, "spop", &self.key);
+            if !backend.exists(&self.key) {
+                self.notify(backend, NotifyClass::Generic, "del", &self.key);
+            }
+        }
+        single_or_array(members, self.count.is_some())
+    }
+}
+
+impl CommandExecutor for SRandMember {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.srandmember(&self.key, self.count.unwrap_or(1)) {
+            Ok(members) => single_or_array(members, self.count.is_some()),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+// without a count argument the reply is a single bulk string (or null), otherwise an array
+fn single_or_array(members: Vec<String>, with_count: bool) -> RespFrame {
+    let mut members = members.into_iter().map(|m| BulkString::from(m).into());
+    if with_count {
+        RespArray::new(members.collect::<Vec<RespFrame>>()).into()
+    } else {
+        members.next().unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl CommandExecutor for SCombine {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.scombine(self.op, &self.keys) {
+            Ok(members) => set_frame(members),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+fn set_frame(members: impl IntoIterator<Item = String>) -> RespFrame {
+    let members = members
+        .into_iter()
+        .map(|m| BulkString::from(m).into())
+        .collect::<Vec<RespFrame>>();
+    RespSet::new(members).into()
+}
+
+impl CommandExecutor for SCombineStore {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let existed = backend.exists(&self.destination);
+        let len = match backend.scombine_store(self.op, self.destination.clone(), &self.keys) {
+            Ok(len) => len,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        let event = match self.op {
+            SetOp::Inter => "sinterstore",
+            SetOp::Union => "sunionstore",
+            SetOp::Diff => "sdiffstore",
+        };
+        if len > 0 {
+            self.notify(backend, NotifyClass::Set, event, &self.destination);
+        } else if existed && !backend.exists(&self.destination) {
+            // an empty result removes the destination
+            self.notify(backend, NotifyClass::Generic, "del", &self.destination);
+        }
+        RespFrame::Integer(len as i64)
+    }
+}
+
+impl CommandExecutor for SInterCard {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.sintercard(&self.keys, self.limit) {
+            Ok(count) => RespFrame::Integer(count as i64),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for SAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "sadd", 2)?;
+
+        let (key, members) = extract_key_members(value)?;
+        Ok(SAdd { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SRem {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "srem", 2)?;
+
+        let (key, members) = extract_key_members(value)?;
+        Ok(SRem { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SMembers {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["smembers"], 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(SMembers {
+            key: extract_key(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["scard"], 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(SCard {
+            key: extract_key(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SIsMember {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["sismember"], 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(member))) => {
+                Ok(SIsMember {
+                    key: key.0,
+                    member: String::from_utf8(member.0)?,
+                })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or value".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for SMIsMember {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "smismember", 2)?;
+
+        let (key, members) = extract_key_members(value)?;
+        Ok(SMIsMember { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SMove {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["smove"], 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(SMove {
+            source: extract_key(args.next())?,
+            destination: extract_key(args.next())?,
+            member: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = extract_key_count(value, "spop")?;
+        let count = match count {
+            Some(n) if n < 0 => {
+                return Err(CommandError::InvalidArgument(
+                    "value is out of range, must be positive".to_string(),
+                ))
+            }
+            count => count.map(|n| n as usize),
+        };
+        Ok(SPop { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for SRandMember {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = extract_key_count(value, "srandmember")?;
+        // a negative count replies that many members, so Redis refuses the ones it can't allocate
+        if count.is_some_and(|n| n < -(i64::MAX / 2)) {
+            return Err(CommandError::InvalidArgument(
+                "value is out of range".to_string(),
+            ));
+        }
+        Ok(SRandMember { key, count })
+    }
+}
+
+// key [count]
+fn extract_key_count(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<u8>, Option<i64>), CommandError> {
+    match value.len() {
+        2 => validate_command(&value, &[name], 1)?,
+        _ => validate_command(&value, &[name], 2)?,
+    }
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next())?;
+    let count = match args.next() {
+        Some(count) => Some(extract_integer(Some(count))?),
+        None => None,
+    };
+    Ok((key, count))
+}
+
+impl TryFrom<RespArray> for SCombine {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, op) = extract_set_op(&value, &SET_OPS)?;
+        validate_variadic_command(&value, name, 1)?;
+
+        let keys = extract_keys(value)?;
+        Ok(SCombine { op, keys })
+    }
+}
+
+impl TryFrom<RespArray> for SCombineStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, op) = extract_set_op(&value, &SET_STORE_OPS)?;
+        validate_variadic_command(&value, name, 2)?;
+
+        let mut keys = extract_keys(value)?;
+        let destination = keys.remove(0);
+        Ok(SCombineStore {
+            op,
+            destination,
+            keys,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SInterCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "sintercard", 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let numkeys = extract_integer(args.next())?;
+        if numkeys <= 0 {
+            return Err(CommandError::InvalidArgument(
+                "numkeys should be greater than 0".to_string(),
+            ));
+        }
+        let keys = (0..numkeys)
+            .map(|_| extract_key(args.next()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| {
+                CommandError::InvalidArgument(
+                    "Number of keys can't be greater than number of args".to_string(),
+                )
+            })?;
+
+        let limit = match args.next() {
+            Some(option) => {
+                if !extract_string(Some(option))?.eq_ignore_ascii_case("limit") {
+                    return Err(CommandError::InvalidArgument("syntax error".to_string()));
+                }
+                match extract_integer(args.next())? {
+                    n if n >= 0 => n as usize,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "LIMIT can't be negative".to_string(),
+                        ))
+                    }
+                }
+            }
+            None => 0,
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+
+        Ok(SInterCard { keys, limit })
+    }
+}
+
+// key member [member ...]
+fn extract_key_members(value: RespArray) -> Result<(Vec<u8>, Vec<String>), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next())?;
+    let members = args
+        .map(|arg| extract_string(Some(arg)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((key, members))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_sadd_from_resp_array2() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$4\r\nSADD\r\n$5\r\nmyset\r\n$5\r\nHello\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: SAdd = frame.try_into()?;
+        assert_eq!(result.key, b"myset");
+        assert_eq!(result.members.len(), 1);
+        assert_eq!(result.members[0], "Hello");
+        Ok(())
+    }
+
+    #[test]
+    fn test_sadd_from_resp_array3() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$4\r\nSADD\r\n$5\r\nmyset\r\n$5\r\nHello\r\n$5\r\nWorld\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: SAdd = frame.try_into()?;
+        assert_eq!(result.key, b"myset");
+        assert_eq!(result.members.len(), 2);
+        assert_eq!(result.members[0], "Hello");
+        assert_eq!(result.members[1], "World");
+        Ok(())
+    }
+
+    #[test]
+    fn test_srem_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$4\r\nSREM\r\n$5\r\nmyset\r\n$3\r\none\r\n$4\r\nfour\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: SRem = frame.try_into()?;
+        assert_eq!(result.key, b"myset");
+        assert_eq!(result.members, vec!["one", "four"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sismember_from_resp() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$9\r\nSISMEMBER\r\n$5\r\nmyset\r\n$3\r\none\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: SIsMember = frame.try_into()?;
+        assert_eq!(result.key, b"myset");
+        assert_eq!(result.member, "one");
+        Ok(())
+    }
+
+    #[test]
+    fn test_scombine_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nSINTER\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: SCombine = frame.try_into()?;
+        assert_eq!(result.op, SetOp::Inter);
+        assert_eq!(result.keys, vec![b"key1".to_vec(), b"key2".to_vec()]);
+
+        buf.extend_from_slice(
+            b"*4\r\n$10\r\nSDIFFSTORE\r\n$3\r\ndst\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: SCombineStore = frame.try_into()?;
+        assert_eq!(result.op, SetOp::Diff);
+        assert_eq!(result.destination, b"dst");
+        assert_eq!(result.keys, vec![b"key1".to_vec(), b"key2".to_vec()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_smove_smismember_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$5\r\nSMOVE\r\n$5\r\nmyset\r\n$10\r\nmyotherset\r\n$3\r\ntwo\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: SMove = frame.try_into()?;
+        assert_eq!(result.source, b"myset");
+        assert_eq!(result.destination, b"myotherset");
+        assert_eq!(result.member, "two");
+
+        buf.extend_from_slice(
+            b"*4\r\n$10\r\nSMISMEMBER\r\n$5\r\nmyset\r\n$3\r\none\r\n$10\r\nnotamember\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: SMIsMember = frame.try_into()?;
+        assert_eq!(result.key, b"myset");
+        assert_eq!(result.members, vec!["one", "notamember"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_smove_smismember_commands() -> Result<()> {
+        let backend = Backend::new();
+        backend.sadd("myset", ["one", "two"].map(String::from))?;
+
+        let cmd = SMove {
+            source: b"myset".to_vec(),
+            destination: b"myotherset".to_vec(),
+            member: "two".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = SMIsMember {
+            key: b"myotherset".to_vec(),
+            members: vec!["one".to_string(), "two".to_string()],
+        };
+        let expected = RespArray::new([RespFrame::Integer(0), RespFrame::Integer(1)]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_spop_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$4\r\nSPOP\r\n$5\r\nmyset\r\n$1\r\n3\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: SPop = frame.try_into()?;
+        assert_eq!(result.key, b"myset");
+        assert_eq!(result.count, Some(3));
+
+        buf.extend_from_slice(b"*3\r\n$11\r\nSRANDMEMBER\r\n$5\r\nmyset\r\n$2\r\n-5\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: SRandMember = frame.try_into()?;
+        assert_eq!(result.key, b"myset");
+        assert_eq!(result.count, Some(-5));
+
+        buf.extend_from_slice(
+            b"*3\r\n$11\r\nSRANDMEMBER\r\n$5\r\nmyset\r\n$20\r\n-9223372036854775808\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<SRandMember, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_spop_srandmember_commands() -> Result<()> {
+        let backend = Backend::new();
+        backend.sadd("myset", ["one".to_string()])?;
+
+        let cmd = SRandMember {
+            key: b"myset".to_vec(),
+            count: Some(-3),
+        };
+        let one: RespFrame = BulkString::from("one").into();
+        let expected = RespArray::new([one.clone(), one.clone(), one.clone()]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = SPop {
+            key: b"myset".to_vec(),
+            count: None,
+        };
+        assert_eq!(cmd.execute(&backend), one);
+
+        let cmd = SPop {
+            key: b"myset".to_vec(),
+            count: None,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sintercard_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*6\r\n$10\r\nSINTERCARD\r\n$1\r\n2\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n$5\r\nLIMIT\r\n$1\r\n1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: SInterCard = frame.try_into()?;
+        assert_eq!(result.keys, vec![b"key1".to_vec(), b"key2".to_vec()]);
+        assert_eq!(result.limit, 1);
+
+        buf.extend_from_slice(b"*3\r\n$10\r\nSINTERCARD\r\n$1\r\n2\r\n$4\r\nkey1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<SInterCard, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scombine_commands() -> Result<()> {
+        let backend = Backend::new();
+        backend.sadd("key1", ["a", "b", "c"].map(String::from))?;
+        backend.sadd("key2", ["c", "d", "e"].map(String::from))?;
+
+        let cmd = SCombine {
+            op: SetOp::Inter,
+            keys: vec![b"key1".to_vec(), b"key2".to_vec()],
+        };
+        let expected = RespSet::new([BulkString::from("c").into()]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = SCombineStore {
+            op: SetOp::Union,
+            destination: b"dst".to_vec(),
+            keys: vec![b"key1".to_vec(), b"key2".to_vec()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(5));
+        assert_eq!(backend.scard(b"dst")?, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = SAdd {
+            key: b"myset".to_vec(),
+            members: vec!["one".to_string(), "two".to_string(), "one".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = SCard {
+            key: b"myset".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = SRem {
+            key: b"myset".to_vec(),
+            members: vec!["one".to_string(), "four".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = SMembers {
+            key: b"myset".to_vec(),
+        };
+        let expected = RespSet::new([BulkString::from("two").into()]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = SIsMember {
+            key: b"myset".to_vec(),
+            member: "one".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+
+        let cmd = SCard {
+            key: b"nokey".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        Ok(())
+    }
+}
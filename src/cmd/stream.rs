@@ -0,0 +1,1429 @@
+use super::{
+    command::{CommandSpec, KeySearch},
+    extract_args, extract_integer, extract_key, extract_string, validate_command,
+    validate_variadic_command, CommandError, CommandExecutor, XAck, XAdd, XDel, XGroup,
+    XGroupSubcommand, XInfo, XInfoSubcommand, XLen, XRange, XRead, XReadGroup, XRevRange, XTrim,
+    RESP_OK,
+};
+use crate::{
+    Backend, BlockKey, BulkString, NotifyClass, RespArray, RespFrame, RespNull, SimpleError,
+    StreamError, StreamFields, StreamId, TrimOptions, TrimStrategy, WrongType, XAddId,
+};
+use std::iter::Peekable;
+use std::ops::Bound;
+use std::time::Duration;
+use tokio::time::Instant;
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new(
+        "xadd",
+        -5,
+        "stream",
+        "Appends a new message to a stream. Creates the key if it doesn't exist.",
+    )
+    .flags(&["write", "denyoom", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "xlen",
+        2,
+        "stream",
+        "Return the number of messages in a stream.",
+    )
+    .flags(&["readonly", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "xrange",
+        -4,
+        "stream",
+        "Returns the messages from a stream within a range of IDs.",
+    )
+    .flags(&["readonly"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "xrevrange",
+        -4,
+        "stream",
+        "Returns the messages from a stream within a range of IDs in reverse order.",
+    )
+    .flags(&["readonly"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "xread",
+        -4,
+        "stream",
+        "Returns messages from multiple streams with IDs greater than the ones requested. Blocks until a message is available otherwise.",
+    )
+    .flags(&["readonly", "blocking", "movablekeys"])
+    .movable_keys(KeySearch::KeywordHalf("streams")),
+    CommandSpec::new("xgroup", -2, "stream", "A container for consumer groups commands.")
+        .flags(&["write"])
+        .keys(2, 2, 1),
+    CommandSpec::new(
+        "xreadgroup",
+        -7,
+        "stream",
+        "Returns new or historical messages from a stream for a consumer in a group. Blocks until a message is available otherwise.",
+    )
+    .flags(&["write", "blocking", "movablekeys"])
+    .movable_keys(KeySearch::KeywordHalf("streams")),
+    CommandSpec::new(
+        "xack",
+        -4,
+        "stream",
+        "Returns the number of messages that were successfully acknowledged by the consumer group member of a stream.",
+    )
+    .flags(&["write", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "xtrim",
+        -4,
+        "stream",
+        "Deletes messages from the beginning of a stream.",
+    )
+    .flags(&["write"])
+    .keys(1, 1, 1),
+    CommandSpec::new(
+        "xdel",
+        -3,
+        "stream",
+        "Returns the number of messages after removing them from a stream.",
+    )
+    .flags(&["write", "fast"])
+    .keys(1, 1, 1),
+    CommandSpec::new("xinfo", -2, "stream", "A container for stream introspection commands.")
+        .flags(&["readonly"])
+        .keys(2, 2, 1),
+];
+
+const INVALID_ID: &str = "Invalid stream ID specified as stream command argument";
+
+impl CommandExecutor for XAdd {
+    fn execute(mut self, backend: &crate::Backend) -> RespFrame {
+        let fields = std::mem::take(&mut self.fields);
+        match backend.xadd(&self.key, self.id, fields, self.nomkstream) {
+            Ok(Some(id)) => {
+                self.notify(backend, NotifyClass::Stream, "xadd", &self.key);
+                if let Some(options) = self.trim {
+                    if backend.xtrim(&self.key, options).is_ok_and(|n| n > 0) {
+                        self.notify(backend, NotifyClass::Stream, "xtrim", &self.key);
+                    }
+                }
+                BulkString::from(id.to_string()).into()
+            }
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for XLen {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.xlen(&self.key) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for XRange {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.xrange(&self.key, (self.start, self.end), self.count, false) {
+            Ok(entries) => entries_frame(entries),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for XRevRange {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.xrange(&self.key, (self.start, self.end), self.count, true) {
+            Ok(entries) => entries_frame(entries),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+// Executed directly (e.g. inside a transaction) XREAD never blocks.
+impl CommandExecutor for XRead {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match self
+            .last_ids(backend)
+            .and_then(|ids| self.read(backend, &ids))
+        {
+            Ok(frame) => frame.unwrap_or(RespFrame::Null(RespNull)),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl XRead {
+    // With BLOCK, waits until an entry is added after the IDs to one of the streams or the
+    // timeout elapses.
+    pub async fn execute_blocking(self, backend: &Backend) -> RespFrame {
+        let Some(block) = self.block else {
+            return self.execute(backend);
+        };
+        // "$" means the entries added from now on, however long it takes
+        let ids = match self.last_ids(backend) {
+            Ok(ids) => ids,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        let deadline = (block > 0).then(|| Instant::now() + Duration::from_millis(block));
+        let keys = block_keys(backend, &self.streams);
+        let read = backend.block_on(keys, deadline, || {
+            // a SWAPDB may have put other streams behind the selected database
+            backend.refresh_db();
+            match self.read(backend, &ids) {
+                Ok(frame) => frame,
+                // another type of value took the place of a stream meanwhile
+                Err(e) => Some(SimpleError::new(e.to_string()).into()),
+            }
+        });
+        read.await.unwrap_or(RespFrame::Null(RespNull))
+    }
+
+    // The IDs to read after, with "$" replaced by the last ID of its stream.
+    fn last_ids(&self, backend: &Backend) -> Result<Vec<StreamId>, WrongType> {
+        self.streams
+            .iter()
+            .map(|(key, id)| match id {
+                Some(id) => Ok(*id),
+                None => Ok(backend.xlast_id(key)?.unwrap_or(StreamId::MIN)),
+            })
+            .collect()
+    }
+
+    // [key, entries] for every stream with entries after its ID, None if none has any.
+    fn read(&self, backend: &Backend, ids: &[StreamId]) -> Result<Option<RespFrame>, WrongType> {
+        let mut streams = vec![];
+        for ((key, _), id) in self.streams.iter().zip(ids) {
+            let range = (Bound::Excluded(*id), Bound::Unbounded);
+            let entries = backend.xrange(key, range, self.count, false)?;
+            if entries.is_empty() {
+                continue;
+            }
+            streams.push(
+                RespArray::new(vec![
+                    BulkString::from(key.as_slice()).into(),
+                    entries_frame(entries),
+                ])
+                .into(),
+            );
+        }
+        Ok((!streams.is_empty()).then(|| RespArray::new(streams).into()))
+    }
+}
+
+impl CommandExecutor for XGroup {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let (key, group) = (&self.key, &self.group);
+        let (result, event) = match &self.subcommand {
+            XGroupSubcommand::Create { id, mkstream } => (
+                backend
+                    .xgroup_create(key, group, *id, *mkstream)
+                    .map(|_| RESP_OK.clone()),
+                Some("xgroup-create"),
+            ),
+            XGroupSubcommand::SetId(id) => (
+                backend
+                    .xgroup_setid(key, group, *id)
+                    .map(|_| RESP_OK.clone()),
+                Some("xgroup-setid"),
+            ),
+            XGroupSubcommand::Destroy => {
+                let destroyed = backend.xgroup_destroy(key, group);
+                let event = destroyed
+                    .as_ref()
+                    .is_ok_and(|d| *d)
+                    .then_some("xgroup-destroy");
+                (destroyed.map(|d| RespFrame::Integer(d as i64)), event)
+            }
+            XGroupSubcommand::CreateConsumer(consumer) => {
+                let created = backend.xgroup_create_consumer(key, group, consumer);
+                let event = created
+                    .as_ref()
+                    .is_ok_and(|c| *c)
+                    .then_some("xgroup-createconsumer");
+                (created.map(|c| RespFrame::Integer(c as i64)), event)
+            }
+            XGroupSubcommand::DelConsumer(consumer) => (
+                backend
+                    .xgroup_delete_consumer(key, group, consumer)
+                    .map(|pending| RespFrame::Integer(pending as i64)),
+                Some("xgroup-delconsumer"),
+            ),
+        };
+        match result {
+            Ok(frame) => {
+                if let Some(event) = event {
+                    self.notify(backend, NotifyClass::Stream, event, key);
+                }
+                frame
+            }
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+// Executed directly (e.g. inside a transaction) XREADGROUP never blocks.
+impl CommandExecutor for XReadGroup {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match self.read(backend) {
+            Ok(frame) => frame.unwrap_or(RespFrame::Null(RespNull)),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl XReadGroup {
+    // With BLOCK, waits until an entry is added to one of the streams or the timeout elapses,
+    // unless a consumer's history is read which never waits.
+    pub async fn execute_blocking(self, backend: &Backend) -> RespFrame {
+        let Some(block) = self.block else {
+            return self.execute(backend);
+        };
+        let deadline = (block > 0).then(|| Instant::now() + Duration::from_millis(block));
+        let keys = block_keys(backend, &self.streams);
+        let names = self
+            .streams
+            .iter()
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        let read = backend.block_on(keys, deadline, || {
+            // a SWAPDB may have put other streams behind the selected database
+            backend.refresh_db();
+            // reading moves the entries to the pending lists of the group
+            match backend.atomically(&names, || self.read(backend)) {
+                Ok(frame) => frame,
+                // the stream or the group went away meanwhile
+                Err(e) => Some(SimpleError::new(e.to_string()).into()),
+            }
+        });
+        read.await.unwrap_or(RespFrame::Null(RespNull))
+    }
+
+    // [key, entries] for every stream read, None if there were no new entries to read. The
+    // history of the consumer is replied even when empty.
+    fn read(&self, backend: &Backend) -> Result<Option<RespFrame>, StreamError> {
+        let mut streams = vec![];
+        for (key, after) in &self.streams {
+            let entries = backend.xreadgroup(
+                key,
+                &self.group,
+                &self.consumer,
+                *after,
+                self.count,
+                self.noack,
+            )?;
+            if after.is_none() && entries.is_empty() {
+                continue;
+            }
+            let entries = entries
+                .into_iter()
+                .map(|(id, fields)| entry_frame(id, fields))
+                .collect::<Vec<_>>();
+            streams.push(
+                RespArray::new(vec![
+                    BulkString::from(key.as_slice()).into(),
+                    RespArray::new(entries).into(),
+                ])
+                .into(),
+            );
+        }
+        Ok((!streams.is_empty()).then(|| RespArray::new(streams).into()))
+    }
+}
+
+impl CommandExecutor for XTrim {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let trimmed = match backend.xtrim(&self.key, self.options) {
+            Ok(trimmed) => trimmed,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        if trimmed > 0 {
+            self.notify(backend, NotifyClass::Stream, "xtrim", &self.key);
+        }
+        RespFrame::Integer(trimmed as i64)
+    }
+}
+
+impl CommandExecutor for XDel {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let deleted = match backend.xdel(&self.key, &self.ids) {
+            Ok(deleted) => deleted,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        if deleted > 0 {
+            self.notify(backend, NotifyClass::Stream, "xdel", &self.key);
+        }
+        RespFrame::Integer(deleted as i64)
+    }
+}
+
+impl CommandExecutor for XInfo {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let bulk = |s: String| -> RespFrame { BulkString::from(s).into() };
+        let reply = match self.subcommand {
+            XInfoSubcommand::Stream => backend.xinfo_stream(&self.key).map(|info| {
+                let entry = |entry: Option<(StreamId, StreamFields)>| match entry {
+                    Some((id, fields)) => entry_frame(id, Some(fields)),
+                    None => RespFrame::Null(RespNull),
+                };
+                let first_id = info.first_entry.as_ref().map_or(StreamId::MIN, |e| e.0);
+                RespArray::new(vec![
+                    bulk("length".into()),
+                    RespFrame::Integer(info.length as i64),
+                    bulk("last-generated-id".into()),
+                    bulk(info.last_id.to_string()),
+                    bulk("max-deleted-entry-id".into()),
+                    bulk(info.max_deleted_id.to_string()),
+                    bulk("entries-added".into()),
+                    RespFrame::Integer(info.entries_added as i64),
+                    bulk("recorded-first-entry-id".into()),
+                    bulk(first_id.to_string()),
+                    bulk("groups".into()),
+                    RespFrame::Integer(info.groups as i64),
+                    bulk("first-entry".into()),
+                    entry(info.first_entry),
+                    bulk("last-entry".into()),
+                    entry(info.last_entry),
+                ])
+                .into()
+            }),
+            XInfoSubcommand::Groups => backend.xinfo_groups(&self.key).map(|groups| {
+                let groups = groups.into_iter().map(|group| {
+                    RespArray::new(vec![
+                        bulk("name".into()),
+                        bulk(group.name),
+                        bulk("consumers".into()),
+                        RespFrame::Integer(group.consumers as i64),
+                        bulk("pending".into()),
+                        RespFrame::Integer(group.pending as i64),
+                        bulk("last-delivered-id".into()),
+                        bulk(group.last_delivered.to_string()),
+                        bulk("lag".into()),
+                        RespFrame::Integer(group.lag as i64),
+                    ])
+                    .into()
+                });
+                RespArray::new(groups.collect::<Vec<_>>()).into()
+            }),
+            XInfoSubcommand::Consumers(group) => {
+                backend.xinfo_consumers(&self.key, &group).map(|consumers| {
+                    let consumers = consumers.into_iter().map(|consumer| {
+                        RespArray::new(vec![
+                            bulk("name".into()),
+                            bulk(consumer.name),
+                            bulk("pending".into()),
+                            RespFrame::Integer(consumer.pending as i64),
+                            bulk("idle".into()),
+                            RespFrame::Integer(consumer.idle as i64),
+                        ])
+                        .into()
+                    });
+                    RespArray::new(consumers.collect::<Vec<_>>()).into()
+                })
+            }
+        };
+        reply.unwrap_or_else(|e| SimpleError::new(e.to_string()).into())
+    }
+}
+
+impl CommandExecutor for XAck {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.xack(&self.key, &self.group, &self.ids) {
+            Ok(acked) => RespFrame::Integer(acked as i64),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for XAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "xadd", 4)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_key(args.next())?;
+        let mut nomkstream = false;
+        let mut trim = None;
+        let id = loop {
+            let arg = extract_string(args.next())?;
+            match arg.to_ascii_lowercase().as_str() {
+                "nomkstream" => nomkstream = true,
+                strategy @ ("maxlen" | "minid") => trim = Some(extract_trim(strategy, &mut args)?),
+                _ => break parse_xadd_id(&arg)?,
+            }
+        };
+        let mut fields = vec![];
+        while let Some(field) = args.next() {
+            let value = args.next().ok_or_else(|| {
+                CommandError::InvalidArgument(
+                    "wrong number of arguments for 'xadd' command".to_string(),
+                )
+            })?;
+            fields.push((extract_string(Some(field))?, value));
+        }
+        if fields.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'xadd' command".to_string(),
+            ));
+        }
+        Ok(XAdd {
+            key,
+            nomkstream,
+            trim,
+            id,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XLen {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xlen"], 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(XLen {
+            key: extract_key(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, start, end, count) = extract_range_args(value, "xrange", false)?;
+        Ok(XRange {
+            key,
+            start,
+            end,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XRevRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, start, end, count) = extract_range_args(value, "xrevrange", true)?;
+        Ok(XRevRange {
+            key,
+            start,
+            end,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XRead {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "xread", 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let read = extract_read_args(&mut args, "xread", "$")?;
+        if read.noack {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(XRead {
+            count: read.count,
+            block: read.block,
+            streams: read.streams,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XReadGroup {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "xreadgroup", 6)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        if !extract_string(args.next())?.eq_ignore_ascii_case("group") {
+            return Err(CommandError::InvalidArgument(
+                "Missing GROUP option for XREADGROUP".to_string(),
+            ));
+        }
+        let group = extract_string(args.next())?;
+        let consumer = extract_string(args.next())?;
+        let read = extract_read_args(&mut args, "xreadgroup", ">")?;
+        Ok(XReadGroup {
+            group,
+            consumer,
+            count: read.count,
+            block: read.block,
+            noack: read.noack,
+            streams: read.streams,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XTrim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "xtrim", 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_key(args.next())?;
+        let strategy = extract_string(args.next())?.to_ascii_lowercase();
+        if strategy != "maxlen" && strategy != "minid" {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let options = extract_trim(&strategy, &mut args)?;
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(XTrim { key, options })
+    }
+}
+
+impl TryFrom<RespArray> for XDel {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "xdel", 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next())?;
+        let ids = args
+            .map(|arg| parse_id(&extract_string(Some(arg))?))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XDel { key, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XInfo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "xinfo", 1)?;
+
+        let args = extract_args(value, 1)?
+            .into_iter()
+            .map(|arg| extract_key(Some(arg)))
+            .collect::<Result<Vec<_>, CommandError>>()?;
+        let subcommand = String::from_utf8_lossy(&args[0]).to_ascii_lowercase();
+        let (key, subcommand) = match (subcommand.as_str(), &args[1..]) {
+            ("stream", [key]) => (key, XInfoSubcommand::Stream),
+            ("groups", [key]) => (key, XInfoSubcommand::Groups),
+            ("consumers", [key, group]) => (
+                key,
+                XInfoSubcommand::Consumers(String::from_utf8(group.clone())?),
+            ),
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for '{subcommand}'"
+                )))
+            }
+        };
+        Ok(XInfo {
+            key: key.clone(),
+            subcommand,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XAck {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "xack", 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next())?;
+        let group = extract_string(args.next())?;
+        let ids = args
+            .map(|arg| parse_id(&extract_string(Some(arg))?))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XAck { key, group, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XGroup {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "xgroup", 1)?;
+
+        let args = extract_args(value, 1)?
+            .into_iter()
+            .map(|arg| extract_key(Some(arg)))
+            .collect::<Result<Vec<_>, CommandError>>()?;
+        let subcommand = String::from_utf8_lossy(&args[0]).to_ascii_lowercase();
+        // "$" stands for the last ID of the stream
+        let group_id = |id: &[u8]| match id {
+            b"$" => Ok(None),
+            id => parse_id(&String::from_utf8_lossy(id)).map(Some),
+        };
+        let subcommand = match (subcommand.as_str(), &args[1..]) {
+            ("create", [_, _, id, options @ ..]) => {
+                let mkstream = match options {
+                    [] => false,
+                    [option] if option.eq_ignore_ascii_case(b"mkstream") => true,
+                    _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+                };
+                XGroupSubcommand::Create {
+                    id: group_id(id)?,
+                    mkstream,
+                }
+            }
+            ("setid", [_, _, id]) => XGroupSubcommand::SetId(group_id(id)?),
+            ("destroy", [_, _]) => XGroupSubcommand::Destroy,
+            ("createconsumer", [_, _, consumer]) => {
+                XGroupSubcommand::CreateConsumer(String::from_utf8(consumer.clone())?)
+            }
+            ("delconsumer", [_, _, consumer]) => {
+                XGroupSubcommand::DelConsumer(String::from_utf8(consumer.clone())?)
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for '{subcommand}'"
+                )))
+            }
+        };
+        Ok(XGroup {
+            key: args[1].clone(),
+            group: String::from_utf8(args[2].clone())?,
+            subcommand,
+        })
+    }
+}
+
+// `[= | ~] threshold [LIMIT count]` after MAXLEN or MINID, as XTRIM and XADD take them.
+fn extract_trim(
+    strategy: &str,
+    args: &mut Peekable<impl Iterator<Item = RespFrame>>,
+) -> Result<TrimOptions, CommandError> {
+    let mut threshold = extract_string(args.next())?;
+    let approximate = threshold == "~";
+    if approximate || threshold == "=" {
+        threshold = extract_string(args.next())?;
+    }
+    let strategy = if strategy == "maxlen" {
+        match threshold.parse::<i64>() {
+            Ok(len) if len >= 0 => TrimStrategy::MaxLen(len as usize),
+            Ok(_) => {
+                return Err(CommandError::InvalidArgument(
+                    "The MAXLEN argument must be >= 0.".to_string(),
+                ))
+            }
+            Err(_) => {
+                return Err(CommandError::InvalidArgument(
+                    "value is not an integer or out of range".to_string(),
+                ))
+            }
+        }
+    } else {
+        TrimStrategy::MinId(parse_id(&threshold)?)
+    };
+    let mut limit = None;
+    if matches!(args.peek(), Some(RespFrame::BulkString(arg)) if arg.eq_ignore_ascii_case(b"limit"))
+    {
+        args.next();
+        if !approximate {
+            return Err(CommandError::InvalidArgument(
+                "syntax error, LIMIT cannot be used without the special ~ option".to_string(),
+            ));
+        }
+        let count = extract_integer(args.next())?;
+        if count < 0 {
+            return Err(CommandError::InvalidArgument(
+                "The LIMIT argument must be >= 0.".to_string(),
+            ));
+        }
+        limit = Some(count as usize);
+    }
+    Ok(TrimOptions {
+        strategy,
+        approximate,
+        limit,
+    })
+}
+
+// The options of XREAD and XREADGROUP, and the streams to read after STREAMS.
+struct ReadArgs {
+    count: Option<usize>,
+    block: Option<u64>,
+    noack: bool,
+    streams: Vec<(Vec<u8>, Option<StreamId>)>,
+}
+
+// Reads `[COUNT count] [BLOCK milliseconds] [NOACK] STREAMS key [key ...] id [id ...]`, where
+// `special` stands for an ID the command works out itself, None in the streams.
+fn extract_read_args(
+    args: &mut impl Iterator<Item = RespFrame>,
+    name: &str,
+    special: &str,
+) -> Result<ReadArgs, CommandError> {
+    let mut count = None;
+    let mut block = None;
+    let mut noack = false;
+    loop {
+        let option = extract_string(args.next())?.to_ascii_lowercase();
+        match option.as_str() {
+            "count" => {
+                // 0 or less reads every entry
+                let n = extract_integer(args.next())?;
+                count = (n > 0).then_some(n as usize);
+            }
+            "block" => {
+                let ms = extract_integer(args.next())?;
+                if ms < 0 {
+                    return Err(CommandError::InvalidArgument(
+                        "timeout is negative".to_string(),
+                    ));
+                }
+                block = Some(ms as u64);
+            }
+            "noack" => noack = true,
+            "streams" => break,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+    let rest = args
+        .map(|arg| extract_key(Some(arg)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if rest.is_empty() || !rest.len().is_multiple_of(2) {
+        return Err(CommandError::InvalidArgument(format!(
+            "Unbalanced '{name}' list of streams: for each stream key an ID or '{special}' must be specified."
+        )));
+    }
+    let (keys, ids) = rest.split_at(rest.len() / 2);
+    let streams = keys
+        .iter()
+        .zip(ids)
+        .map(|(key, id)| {
+            let id = if id == special.as_bytes() {
+                None
+            } else {
+                Some(parse_id(&String::from_utf8_lossy(id))?)
+            };
+            Ok((key.clone(), id))
+        })
+        .collect::<Result<Vec<_>, CommandError>>()?;
+    Ok(ReadArgs {
+        count,
+        block,
+        noack,
+        streams,
+    })
+}
+
+// What a blocked XREAD or XREADGROUP waits on: entries added to its streams.
+fn block_keys(backend: &Backend, streams: &[(Vec<u8>, Option<StreamId>)]) -> Vec<BlockKey> {
+    let db = backend.selected_db();
+    streams
+        .iter()
+        .map(|(key, _)| BlockKey::Key(db, key.clone()))
+        .collect()
+}
+
+// The entries of a stream as XRANGE replies them: an array of [id, [field, value, ...]].
+pub(super) fn entries_frame(entries: Vec<(StreamId, StreamFields)>) -> RespFrame {
+    let entries = entries
+        .into_iter()
+        .map(|(id, fields)| entry_frame(id, Some(fields)))
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(entries).into()
+}
+
+// [id, [field, value, ...]], with a null for the fields of an entry that was deleted.
+fn entry_frame(id: StreamId, fields: Option<StreamFields>) -> RespFrame {
+    let fields = match fields {
+        Some(fields) => {
+            let mut pairs = Vec::with_capacity(fields.len() * 2);
+            for (field, value) in fields {
+                pairs.push(BulkString::from(field).into());
+                pairs.push(value);
+            }
+            RespArray::new(pairs).into()
+        }
+        None => RespFrame::Null(RespNull),
+    };
+    RespArray::new(vec![BulkString::from(id.to_string()).into(), fields]).into()
+}
+
+fn parse_id(arg: &str) -> Result<StreamId, CommandError> {
+    arg.parse()
+        .map_err(|_| CommandError::InvalidArgument(INVALID_ID.to_string()))
+}
+
+// "*", "<ms>-*" or an explicit ID.
+fn parse_xadd_id(arg: &str) -> Result<XAddId, CommandError> {
+    let invalid = || CommandError::InvalidArgument(INVALID_ID.to_string());
+    if arg == "*" {
+        return Ok(XAddId::Auto);
+    }
+    if let Some(ms) = arg.strip_suffix("-*") {
+        return Ok(XAddId::AutoSeq(ms.parse().map_err(|_| invalid())?));
+    }
+    arg.parse().map(XAddId::Explicit).map_err(|_| invalid())
+}
+
+// An end of an XRANGE interval: "-" or "+" for the smallest or greatest ID, an ID, or an ID
+// after "(" to leave it out. An ID without its sequence number covers that whole millisecond.
+pub(super) fn parse_range_bound(arg: &str, start: bool) -> Result<Bound<StreamId>, CommandError> {
+    let (exclusive, id) = match arg.strip_prefix('(') {
+        Some(id) => (true, id),
+        None => (false, arg),
+    };
+    let id = match id {
+        "-" | "+" if exclusive => {
+            let end = if start { "start" } else { "end" };
+            return Err(CommandError::InvalidArgument(format!(
+                "invalid {end} ID for the interval"
+            )));
+        }
+        "-" => StreamId::MIN,
+        "+" => StreamId::MAX,
+        id => {
+            let mut parsed = id
+                .parse::<StreamId>()
+                .map_err(|_| CommandError::InvalidArgument(INVALID_ID.to_string()))?;
+            if !start && !id.contains('-') {
+                parsed.seq = u64::MAX;
+            }
+            parsed
+        }
+    };
+    Ok(if exclusive {
+        Bound::Excluded(id)
+    } else {
+        Bound::Included(id)
+    })
+}
+
+// The key, the interval and the COUNT of `XRANGE key start end [COUNT count]`, and of
+// XREVRANGE which takes the end of the interval first.
+#[allow(clippy::type_complexity)]
+fn extract_range_args(
+    value: RespArray,
+    name: &'static str,
+    rev: bool,
+) -> Result<(Vec<u8>, Bound<StreamId>, Bound<StreamId>, Option<usize>), CommandError> {
+    validate_variadic_command(&value, name, 3)?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next())?;
+    let first = extract_string(args.next())?;
+    let second = extract_string(args.next())?;
+    let (start, end) = if rev {
+        (
+            parse_range_bound(&second, true)?,
+            parse_range_bound(&first, false)?,
+        )
+    } else {
+        (
+            parse_range_bound(&first, true)?,
+            parse_range_bound(&second, false)?,
+        )
+    };
+    let count = match args.next() {
+        None => None,
+        Some(option) if extract_string(Some(option.clone()))?.eq_ignore_ascii_case("count") => {
+            // a negative count returns nothing, like 0
+            Some(extract_integer(args.next())?.max(0) as usize)
+        }
+        Some(_) => {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+    };
+    if args.next().is_some() {
+        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+    }
+    Ok((key, start, end, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Command, Backend, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn command(buf: &[u8]) -> Result<Command> {
+        let mut buf = BytesMut::from(buf);
+        let frame = RespArray::decode(&mut buf)?;
+        Ok(frame.try_into()?)
+    }
+
+    fn xadd(backend: &Backend, id: XAddId, value: &str) -> RespFrame {
+        XAdd {
+            key: b"s".to_vec(),
+            nomkstream: false,
+            trim: None,
+            id,
+            fields: vec![("f".to_string(), BulkString::from(value).into())],
+        }
+        .execute(backend)
+    }
+
+    #[test]
+    fn test_xadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$4\r\nXADD\r\n$1\r\ns\r\n$10\r\nNOMKSTREAM\r\n$3\r\n5-*\r\n$1\r\nf\r\n$1\r\nv\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: XAdd = frame.try_into()?;
+        assert_eq!(cmd.key, b"s");
+        assert!(cmd.nomkstream);
+        assert_eq!(cmd.id, XAddId::AutoSeq(5));
+        assert_eq!(
+            cmd.fields,
+            [("f".to_string(), BulkString::from("v").into())]
+        );
+
+        // fields come in pairs
+        assert!(command(
+            b"*6\r\n$4\r\nXADD\r\n$1\r\ns\r\n$1\r\n*\r\n$1\r\nf\r\n$1\r\nv\r\n$1\r\ng\r\n"
+        )
+        .is_err());
+        assert!(
+            command(b"*5\r\n$4\r\nXADD\r\n$1\r\ns\r\n$3\r\n1-x\r\n$1\r\nf\r\n$1\r\nv\r\n").is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_xrange_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$6\r\nXRANGE\r\n$1\r\ns\r\n$2\r\n(5\r\n$1\r\n7\r\n$5\r\nCOUNT\r\n$1\r\n2\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: XRange = frame.try_into()?;
+        assert_eq!(cmd.start, Bound::Excluded(StreamId::new(5, 0)));
+        assert_eq!(cmd.end, Bound::Included(StreamId::new(7, u64::MAX)));
+        assert_eq!(cmd.count, Some(2));
+
+        buf.extend_from_slice(b"*4\r\n$9\r\nXREVRANGE\r\n$1\r\ns\r\n$1\r\n+\r\n$1\r\n-\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: XRevRange = frame.try_into()?;
+        assert_eq!(cmd.start, Bound::Included(StreamId::MIN));
+        assert_eq!(cmd.end, Bound::Included(StreamId::MAX));
+
+        assert!(command(b"*4\r\n$6\r\nXRANGE\r\n$1\r\ns\r\n$2\r\n(-\r\n$1\r\n+\r\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xadd_xlen_xrange() {
+        let backend = Backend::new();
+        assert_eq!(
+            xadd(&backend, XAddId::Explicit(StreamId::new(1, 1)), "a"),
+            BulkString::from("1-1").into()
+        );
+        assert_eq!(
+            xadd(&backend, XAddId::AutoSeq(1), "b"),
+            BulkString::from("1-2").into()
+        );
+        assert_eq!(
+            xadd(&backend, XAddId::Explicit(StreamId::new(1, 2)), "c"),
+            SimpleError::new(
+                "ERR The ID specified in XADD is equal or smaller than the target stream top item"
+            )
+            .into()
+        );
+        assert!(matches!(
+            xadd(&backend, XAddId::Auto, "c"),
+            RespFrame::BulkString(_)
+        ));
+        assert_eq!(
+            XLen { key: b"s".to_vec() }.execute(&backend),
+            RespFrame::Integer(3)
+        );
+
+        let cmd = XAdd {
+            key: b"missing".to_vec(),
+            nomkstream: true,
+            trim: None,
+            id: XAddId::Auto,
+            fields: vec![("f".to_string(), BulkString::from("v").into())],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+        assert!(!backend.exists(b"missing"));
+        assert_eq!(backend.key_type(b"s"), Some("stream"));
+
+        let cmd = XRange {
+            key: b"s".to_vec(),
+            start: Bound::Included(StreamId::MIN),
+            end: Bound::Included(StreamId::new(1, u64::MAX)),
+            count: None,
+        };
+        let entry = |id: &str, value: &str| -> RespFrame {
+            RespArray::new(vec![
+                BulkString::from(id).into(),
+                RespArray::new(vec![
+                    BulkString::from("f").into(),
+                    BulkString::from(value).into(),
+                ])
+                .into(),
+            ])
+            .into()
+        };
+        let expected = RespArray::new(vec![entry("1-1", "a"), entry("1-2", "b")]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = XRevRange {
+            key: b"s".to_vec(),
+            start: Bound::Excluded(StreamId::new(1, 1)),
+            end: Bound::Included(StreamId::MAX),
+            count: Some(1),
+        };
+        assert!(matches!(cmd.execute(&backend),
+            RespFrame::Array(RespArray(entries)) if entries.len() == 1 && entries[0] != entry("1-2", "b")));
+
+        // the stream survives DUMP and RESTORE with its last ID
+        let payload = backend.dump(b"s").expect("s exists");
+        backend
+            .restore(b"copy", &payload, None, false, None)
+            .expect("valid payload");
+        assert_eq!(backend.dump(b"copy"), Some(payload));
+    }
+
+    #[test]
+    fn test_xread_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*8\r\n$5\r\nXREAD\r\n$5\r\ncount\r\n$1\r\n2\r\n$5\r\nBLOCK\r\n$1\r\n0\r\n$7\r\nSTREAMS\r\n$1\r\ns\r\n$1\r\n$\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: XRead = frame.try_into()?;
+        assert_eq!(cmd.count, Some(2));
+        assert_eq!(cmd.block, Some(0));
+        assert_eq!(cmd.streams, [(b"s".to_vec(), None)]);
+
+        // one ID for each key
+        assert!(command(
+            b"*5\r\n$5\r\nXREAD\r\n$7\r\nSTREAMS\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\n0\r\n"
+        )
+        .is_err());
+        assert!(command(
+            b"*5\r\n$5\r\nXREAD\r\n$5\r\nBLOCK\r\n$2\r\n-1\r\n$7\r\nSTREAMS\r\n$1\r\na\r\n"
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_xread_blocking() -> Result<()> {
+        let backend = Backend::new();
+        xadd(&backend, XAddId::Explicit(StreamId::new(1, 0)), "a");
+        let cmd = XRead {
+            count: None,
+            block: None,
+            streams: vec![
+                (b"s".to_vec(), Some(StreamId::MIN)),
+                (b"missing".to_vec(), None),
+            ],
+        };
+        let expected = RespArray::new(vec![RespArray::new(vec![
+            BulkString::from("s").into(),
+            entries_frame(vec![(
+                StreamId::new(1, 0),
+                vec![("f".to_string(), BulkString::from("a").into())],
+            )]),
+        ])
+        .into()]);
+        assert_eq!(cmd.execute_blocking(&backend).await, expected.into());
+
+        // nothing was added after the last ID
+        let cmd = XRead {
+            count: None,
+            block: Some(50),
+            streams: vec![(b"s".to_vec(), None)],
+        };
+        assert_eq!(
+            cmd.execute_blocking(&backend).await,
+            RespFrame::Null(RespNull)
+        );
+
+        let cmd = XRead {
+            count: None,
+            block: Some(0),
+            streams: vec![(b"s".to_vec(), None)],
+        };
+        let cloned = backend.clone();
+        let handle = tokio::spawn(async move { cmd.execute_blocking(&cloned).await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        xadd(&backend, XAddId::Explicit(StreamId::new(2, 0)), "b");
+
+        let expected = RespArray::new(vec![RespArray::new(vec![
+            BulkString::from("s").into(),
+            entries_frame(vec![(
+                StreamId::new(2, 0),
+                vec![("f".to_string(), BulkString::from("b").into())],
+            )]),
+        ])
+        .into()]);
+        assert_eq!(handle.await?, expected.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xgroup_from_resp_array() -> Result<()> {
+        let cmd = command(b"*6\r\n$6\r\nXGROUP\r\n$6\r\ncreate\r\n$1\r\ns\r\n$1\r\ng\r\n$1\r\n$\r\n$8\r\nMKSTREAM\r\n")?;
+        let Command::XGroup(cmd) = cmd else {
+            panic!("expected XGROUP, got {cmd:?}");
+        };
+        assert_eq!((cmd.key.as_slice(), cmd.group.as_str()), (&b"s"[..], "g"));
+        assert_eq!(
+            cmd.subcommand,
+            XGroupSubcommand::Create {
+                id: None,
+                mkstream: true
+            }
+        );
+        assert!(command(b"*4\r\n$6\r\nXGROUP\r\n$5\r\nSETID\r\n$1\r\ns\r\n$1\r\ng\r\n").is_err());
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*9\r\n$10\r\nXREADGROUP\r\n$5\r\nGROUP\r\n$1\r\ng\r\n$1\r\nc\r\n$5\r\nNOACK\r\n$7\r\nSTREAMS\r\n$1\r\na\r\n$1\r\n>\r\n$3\r\n0-1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(XReadGroup::try_from(frame).is_err());
+        buf.extend_from_slice(b"*10\r\n$10\r\nXREADGROUP\r\n$5\r\nGROUP\r\n$1\r\ng\r\n$1\r\nc\r\n$5\r\nNOACK\r\n$7\r\nSTREAMS\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\n>\r\n$3\r\n0-1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: XReadGroup = frame.try_into()?;
+        assert!(cmd.noack);
+        assert_eq!(
+            cmd.streams,
+            [
+                (b"a".to_vec(), None),
+                (b"b".to_vec(), Some(StreamId::new(0, 1)))
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_consumer_group() -> Result<()> {
+        let backend = Backend::new();
+        let xgroup = |subcommand| XGroup {
+            key: b"s".to_vec(),
+            group: "g".to_string(),
+            subcommand,
+        };
+        let create = |mkstream| xgroup(XGroupSubcommand::Create { id: None, mkstream });
+        assert!(matches!(
+            create(false).execute(&backend),
+            RespFrame::Error(_)
+        ));
+        assert_eq!(create(true).execute(&backend), RESP_OK.clone());
+        assert_eq!(
+            create(true).execute(&backend),
+            SimpleError::new("BUSYGROUP Consumer Group name already exists").into()
+        );
+
+        let read = |consumer: &str, after, block| XReadGroup {
+            group: "g".to_string(),
+            consumer: consumer.to_string(),
+            count: None,
+            block,
+            noack: false,
+            streams: vec![(b"s".to_vec(), after)],
+        };
+        let reply = |id: &str, value: &str| -> RespFrame {
+            RespArray::new(vec![RespArray::new(vec![
+                BulkString::from("s").into(),
+                RespArray::new(vec![entry_frame(
+                    id.parse().expect("valid ID"),
+                    Some(vec![("f".to_string(), BulkString::from(value).into())]),
+                )])
+                .into(),
+            ])
+            .into()])
+            .into()
+        };
+        // the group was created at the end of the empty stream
+        let cmd = read("alice", None, Some(0));
+        let cloned = backend.clone();
+        let handle = tokio::spawn(async move { cmd.execute_blocking(&cloned).await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        xadd(&backend, XAddId::Explicit(StreamId::new(1, 0)), "a");
+        assert_eq!(handle.await?, reply("1-0", "a"));
+        assert_eq!(
+            read("bob", None, None).execute(&backend),
+            RespFrame::Null(RespNull)
+        );
+        assert_eq!(
+            read("alice", Some(StreamId::MIN), None).execute(&backend),
+            reply("1-0", "a")
+        );
+
+        let ack = XAck {
+            key: b"s".to_vec(),
+            group: "g".to_string(),
+            ids: vec![StreamId::new(1, 0), StreamId::new(2, 0)],
+        };
+        assert_eq!(ack.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = xgroup(XGroupSubcommand::DelConsumer("alice".to_string()));
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        let cmd = xgroup(XGroupSubcommand::SetId(Some(StreamId::MIN)));
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(read("bob", None, None).execute(&backend), reply("1-0", "a"));
+
+        // the group and its pending entries survive DUMP and RESTORE
+        let payload = backend.dump(b"s").expect("s exists");
+        backend.restore(b"copy", &payload, None, false, None)?;
+        assert_eq!(backend.dump(b"copy"), Some(payload));
+
+        let cmd = xgroup(XGroupSubcommand::Destroy);
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(
+            read("bob", None, None).execute(&backend),
+            SimpleError::new("NOGROUP No such key 's' or consumer group 'g'").into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_xtrim_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*7\r\n$5\r\nXTRIM\r\n$1\r\ns\r\n$5\r\nMINID\r\n$1\r\n~\r\n$1\r\n5\r\n$5\r\nLIMIT\r\n$2\r\n10\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: XTrim = frame.try_into()?;
+        assert_eq!(
+            cmd.options,
+            TrimOptions {
+                strategy: TrimStrategy::MinId(StreamId::new(5, 0)),
+                approximate: true,
+                limit: Some(10),
+            }
+        );
+
+        // LIMIT needs "~"
+        assert!(command(b"*6\r\n$5\r\nXTRIM\r\n$1\r\ns\r\n$6\r\nMAXLEN\r\n$1\r\n5\r\n$5\r\nLIMIT\r\n$2\r\n10\r\n").is_err());
+        assert!(command(b"*4\r\n$5\r\nXTRIM\r\n$1\r\ns\r\n$6\r\nMAXLEN\r\n$2\r\n-1\r\n").is_err());
+
+        buf.extend_from_slice(b"*7\r\n$4\r\nXADD\r\n$1\r\ns\r\n$6\r\nMAXLEN\r\n$1\r\n=\r\n$1\r\n2\r\n$1\r\n*\r\n$1\r\nf\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(XAdd::try_from(frame).is_err());
+        buf.extend_from_slice(b"*8\r\n$4\r\nXADD\r\n$1\r\ns\r\n$6\r\nMAXLEN\r\n$1\r\n=\r\n$1\r\n2\r\n$1\r\n*\r\n$1\r\nf\r\n$1\r\nv\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: XAdd = frame.try_into()?;
+        assert_eq!(
+            cmd.trim.map(|trim| trim.strategy),
+            Some(TrimStrategy::MaxLen(2))
+        );
+        assert_eq!(cmd.id, XAddId::Auto);
+        Ok(())
+    }
+
+    #[test]
+    fn test_xtrim_xdel() -> Result<()> {
+        let backend = Backend::new();
+        for ms in 1..=5 {
+            xadd(&backend, XAddId::Explicit(StreamId::new(ms, 0)), "v");
+        }
+        let cmd = XTrim {
+            key: b"s".to_vec(),
+            options: TrimOptions {
+                strategy: TrimStrategy::MaxLen(3),
+                approximate: false,
+                limit: None,
+            },
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = XDel {
+            key: b"s".to_vec(),
+            ids: vec![StreamId::new(1, 0), StreamId::new(5, 0)],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(backend.xlen(b"s")?, 2);
+        // the last ID stays even though its entry is gone
+        assert!(matches!(
+            xadd(&backend, XAddId::Explicit(StreamId::new(5, 0)), "v"),
+            RespFrame::Error(_)
+        ));
+
+        let cmd = XAdd {
+            key: b"s".to_vec(),
+            nomkstream: false,
+            trim: Some(TrimOptions {
+                strategy: TrimStrategy::MinId(StreamId::new(4, 0)),
+                approximate: false,
+                limit: None,
+            }),
+            id: XAddId::Explicit(StreamId::new(6, 0)),
+            fields: vec![("f".to_string(), BulkString::from("v").into())],
+        };
+        cmd.execute(&backend);
+        let ids = backend.xrange(b"s", (Bound::Unbounded, Bound::Unbounded), None, false)?;
+        let ids = ids.into_iter().map(|(id, _)| id.ms).collect::<Vec<_>>();
+        assert_eq!(ids, [4, 6]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_xinfo() -> Result<()> {
+        let cmd = command(b"*4\r\n$5\r\nXINFO\r\n$9\r\nconsumers\r\n$1\r\ns\r\n$1\r\ng\r\n")?;
+        let Command::XInfo(cmd) = cmd else {
+            panic!("expected XINFO, got {cmd:?}");
+        };
+        assert_eq!(cmd.subcommand, XInfoSubcommand::Consumers("g".to_string()));
+        assert!(command(b"*3\r\n$5\r\nXINFO\r\n$9\r\nconsumers\r\n$1\r\ns\r\n").is_err());
+
+        let backend = Backend::new();
+        let info = |subcommand| {
+            XInfo {
+                key: b"s".to_vec(),
+                subcommand,
+            }
+            .execute(&backend)
+        };
+        assert_eq!(
+            info(XInfoSubcommand::Stream),
+            SimpleError::new("ERR no such key").into()
+        );
+        for ms in 1..=3 {
+            xadd(&backend, XAddId::Explicit(StreamId::new(ms, 0)), "v");
+        }
+        backend.xgroup_create(b"s", "g", Some(StreamId::MIN), false)?;
+        backend.xreadgroup(b"s", "g", "alice", None, Some(1), false)?;
+
+        let RespFrame::Array(RespArray(stream)) = info(XInfoSubcommand::Stream) else {
+            panic!("XINFO STREAM replies an array");
+        };
+        assert_eq!(stream[1], RespFrame::Integer(3));
+        assert_eq!(stream[3], BulkString::from("3-0").into());
+        assert_eq!(stream[11], RespFrame::Integer(1));
+
+        let group = RespArray::new(vec![
+            BulkString::from("name").into(),
+            BulkString::from("g").into(),
+            BulkString::from("consumers").into(),
+            RespFrame::Integer(1),
+            BulkString::from("pending").into(),
+            RespFrame::Integer(1),
+            BulkString::from("last-delivered-id").into(),
+            BulkString::from("1-0").into(),
+            BulkString::from("lag").into(),
+            RespFrame::Integer(2),
+        ]);
+        assert_eq!(
+            info(XInfoSubcommand::Groups),
+            RespArray::new(vec![group.into()]).into()
+        );
+
+        let RespFrame::Array(RespArray(consumers)) =
+            info(XInfoSubcommand::Consumers("g".to_string()))
+        else {
+            panic!("XINFO CONSUMERS replies an array");
+        };
+        assert_eq!(consumers.len(), 1);
+        assert_eq!(
+            info(XInfoSubcommand::Consumers("other".to_string())),
+            SimpleError::new("NOGROUP No such key 's' or consumer group 'other'").into()
+        );
+        Ok(())
+    }
+}
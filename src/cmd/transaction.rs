@@ -0,0 +1,259 @@
+use super::{
+    command::{lookup_command, CommandSpec},
+    validate_command, Command, CommandError, CommandExecutor, Discard, Exec, Multi, RESP_OK,
+};
+use crate::{backend::error_message, Backend, RespArray, RespFrame, SimpleError, SimpleString};
+use std::fmt::Display;
+use std::time::Instant;
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("multi", 1, "transactions", "Starts a transaction.").flags(&[
+        "noscript",
+        "loading",
+        "stale",
+        "fast",
+        "allow_busy",
+    ]),
+    CommandSpec::new(
+        "exec",
+        1,
+        "transactions",
+        "Executes all commands in a transaction.",
+    )
+    .flags(&["noscript", "loading", "stale", "skip_slowlog"]),
+    CommandSpec::new("discard", 1, "transactions", "Discards a transaction.").flags(&[
+        "noscript",
+        "loading",
+        "stale",
+        "fast",
+        "allow_busy",
+    ]),
+];
+
+// A queued command with its lowercase name, for the command statistics, and its arguments if it
+// is a write to log to the append only file.
+type Queued = (String, Command, Option<Vec<Vec<u8>>>);
+
+// The commands a connection queued since MULTI, run together by EXEC.
+#[derive(Debug, Default)]
+pub struct Transaction {
+    commands: Vec<Queued>,
+    // set once a command failed to queue, EXEC then discards the transaction
+    aborted: bool,
+}
+
+impl Transaction {
+    // Queues the command `name` for EXEC, with its arguments if it is logged once it ran. An
+    // unknown command aborts the transaction like one that doesn't parse, while a nested MULTI
+    // fails without aborting it.
+    pub fn queue(&mut self, name: &str, cmd: Command, logged: Option<Vec<Vec<u8>>>) -> RespFrame {
+        match cmd {
+            Command::Multi(_) => SimpleError::new("ERR MULTI calls can not be nested").into(),
+            Command::Unrecognized(_) => self.abort(format!("unknown command '{name}'")),
+            cmd => {
+                self.commands.push((name.to_string(), cmd, logged));
+                SimpleString::new("QUEUED").into()
+            }
+        }
+    }
+
+    // Records that a command failed to queue because of `error`, which is replied to it.
+    pub fn abort(&mut self, error: impl Display) -> RespFrame {
+        self.reject(SimpleError::new(format!("ERR {error}")))
+    }
+
+    // Like abort, for a command refused with `error` as is.
+    pub fn reject(&mut self, error: SimpleError) -> RespFrame {
+        self.aborted = true;
+        error.into()
+    }
+
+    // Runs the queued commands and replies with the array of their replies. The caller holds
+    // exclusive access to the backend so that no command of another client runs meanwhile. A
+    // command failing at runtime has its error in the array and doesn't stop the commands after
+    // it, nothing is rolled back.
+    pub fn exec(self, backend: &Backend) -> RespFrame {
+        if self.aborted {
+            return SimpleError::new("EXECABORT Transaction discarded because of previous errors.")
+                .into();
+        }
+        let replies = self
+            .commands
+            .into_iter()
+            .map(|(name, cmd, logged)| {
+                let start = Instant::now();
+                let reply = match cmd {
+                    // the connection expects a reply per command, it can't turn into a monitor
+                    Command::Monitor(_) => {
+                        SimpleError::new("ERR MONITOR isn't allowed for DENY BLOCKING client")
+                            .into()
+                    }
+                    cmd => cmd.execute(backend),
+                };
+                backend
+                    .stats()
+                    .command_executed(&name, start.elapsed(), error_message(&reply));
+                if lookup_command(backend, &name).is_some_and(|spec| spec.flags.contains(&"write"))
+                {
+                    backend.record_write(&reply);
+                }
+                if let Some(args) = logged {
+                    backend.propagate(args, &reply);
+                }
+                reply
+            })
+            .collect::<Vec<_>>();
+        RespArray::new(replies).into()
+    }
+
+    // Whether a queued command may add data, which EXEC then refuses while over maxmemory.
+    pub fn denies_oom(&self, backend: &Backend) -> bool {
+        self.commands.iter().any(|(name, _, _)| {
+            lookup_command(backend, name).is_some_and(|spec| spec.flags.contains(&"denyoom"))
+        })
+    }
+
+    // Drops the queued commands.
+    pub fn discard(self) -> RespFrame {
+        RESP_OK.clone()
+    }
+}
+
+// The connection starts queueing commands once it replied, see Transaction.
+impl CommandExecutor for Multi {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        RESP_OK.clone()
+    }
+}
+
+// Executed directly, outside of a transaction, EXEC and DISCARD have nothing to work on.
+impl CommandExecutor for Exec {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        SimpleError::new("ERR EXEC without MULTI").into()
+    }
+}
+
+impl CommandExecutor for Discard {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        SimpleError::new("ERR DISCARD without MULTI").into()
+    }
+}
+
+impl TryFrom<RespArray> for Multi {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["multi"], 0)?;
+        Ok(Multi)
+    }
+}
+
+impl TryFrom<RespArray> for Exec {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["exec"], 0)?;
+        Ok(Exec)
+    }
+}
+
+impl TryFrom<RespArray> for Discard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["discard"], 0)?;
+        Ok(Discard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Unrecognized, BulkString, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn command(buf: &[u8]) -> Result<Command> {
+        let mut buf = BytesMut::from(buf);
+        let frame = RespArray::decode(&mut buf)?;
+        Ok(frame.try_into()?)
+    }
+
+    #[test]
+    fn test_transaction_from_resp_array() -> Result<()> {
+        assert!(matches!(
+            command(b"*1\r\n$5\r\nMULTI\r\n")?,
+            Command::Multi(_)
+        ));
+        assert!(matches!(
+            command(b"*1\r\n$4\r\nexec\r\n")?,
+            Command::Exec(_)
+        ));
+        assert!(matches!(
+            command(b"*1\r\n$7\r\nDISCARD\r\n")?,
+            Command::Discard(_)
+        ));
+        assert!(command(b"*2\r\n$4\r\nEXEC\r\n$1\r\nx\r\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction() -> Result<()> {
+        let backend = Backend::new();
+        let mut transaction = Transaction::default();
+        let queued: RespFrame = SimpleString::new("QUEUED").into();
+        let set = command(b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$1\r\nv\r\n")?;
+        assert_eq!(transaction.queue("set", set, None), queued);
+        assert_eq!(
+            transaction.queue("multi", Multi.into(), None),
+            SimpleError::new("ERR MULTI calls can not be nested").into()
+        );
+        let get = command(b"*2\r\n$3\r\nGET\r\n$1\r\nk\r\n")?;
+        assert_eq!(transaction.queue("get", get, None), queued);
+        // nothing runs before EXEC
+        assert!(!backend.exists(b"k"));
+
+        let expected = RespArray::new([RESP_OK.clone(), BulkString::from("v").into()]);
+        assert_eq!(transaction.exec(&backend), expected.into());
+        assert_eq!(
+            Exec.execute(&backend),
+            SimpleError::new("ERR EXEC without MULTI").into()
+        );
+
+        let mut transaction = Transaction::default();
+        let del = command(b"*2\r\n$3\r\nDEL\r\n$1\r\nk\r\n")?;
+        transaction.queue("del", del, None);
+        transaction.abort("wrong number of arguments");
+        assert_eq!(
+            transaction.exec(&backend),
+            SimpleError::new("EXECABORT Transaction discarded because of previous errors.").into()
+        );
+        assert!(backend.exists(b"k"));
+
+        let mut transaction = Transaction::default();
+        let del = command(b"*2\r\n$3\r\nDEL\r\n$1\r\nk\r\n")?;
+        transaction.queue("del", del, None);
+        assert_eq!(
+            transaction.queue("foo", Unrecognized.into(), None),
+            SimpleError::new("ERR unknown command 'foo'").into()
+        );
+        assert_eq!(
+            transaction.exec(&backend),
+            SimpleError::new("EXECABORT Transaction discarded because of previous errors.").into()
+        );
+
+        // runtime errors don't stop the transaction
+        let mut transaction = Transaction::default();
+        let select = command(b"*2\r\n$6\r\nSELECT\r\n$3\r\n100\r\n")?;
+        transaction.queue("select", select, None);
+        let del = command(b"*2\r\n$3\r\nDEL\r\n$1\r\nk\r\n")?;
+        transaction.queue("del", del, None);
+        let expected = RespArray::new([
+            SimpleError::new("ERR DB index is out of range").into(),
+            RespFrame::Integer(1),
+        ]);
+        assert_eq!(transaction.exec(&backend), expected.into());
+        assert!(!backend.exists(b"k"));
+        Ok(())
+    }
+}
@@ -0,0 +1,1397 @@
+use super::{
+    command::{CommandSpec, KeySearch},
+    extract_args, extract_float, extract_integer, extract_key, extract_scan_args, extract_set_op,
+    extract_string, extract_timeout, validate_command, validate_variadic_command, BZPopMin,
+    CommandError, CommandExecutor, ZAdd, ZCard, ZCombine, ZCombineStore, ZCount, ZIncrBy, ZRange,
+    ZRem, ZRemRange, ZScan, ZScore,
+};
+use crate::{
+    Aggregate, Backend, BlockKey, BulkString, LexBound, NotifyClass, RespArray, RespFrame,
+    RespNull, SetOp, SimpleError, WrongType, ZAddFlags, ZAddOutcome, ZPopped, ZRangeSpec,
+};
+use std::ops::Bound;
+use std::time::Duration;
+use tokio::time::Instant;
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("zadd", -4, "sorted_set", "Adds one or more members to a sorted set, or updates their scores.")
+        .flags(&["write", "fast"])
+        .keys(1, 1, 1),
+    CommandSpec::new("zscore", 3, "sorted_set", "Returns the score of a member in a sorted set.")
+        .flags(&["readonly", "fast"])
+        .keys(1, 1, 1),
+    CommandSpec::new("zrem", -3, "sorted_set", "Removes one or more members from a sorted set.")
+        .flags(&["write", "fast"])
+        .keys(1, 1, 1),
+    CommandSpec::new("zcard", 2, "sorted_set", "Returns the number of members in a sorted set.")
+        .flags(&["readonly", "fast"])
+        .keys(1, 1, 1),
+    CommandSpec::new("zrange", -4, "sorted_set", "Returns members in a sorted set within a range of indexes, scores or lexicographical values.")
+        .flags(&["readonly"])
+        .keys(1, 1, 1),
+    CommandSpec::new("zrangebyscore", -4, "sorted_set", "Returns members in a sorted set within a range of scores.")
+        .flags(&["readonly"])
+        .keys(1, 1, 1),
+    CommandSpec::new("zrevrangebyscore", -4, "sorted_set", "Returns members in a sorted set within a range of scores in reverse order.")
+        .flags(&["readonly"])
+        .keys(1, 1, 1),
+    CommandSpec::new("zincrby", 4, "sorted_set", "Increments the score of a member in a sorted set.")
+        .flags(&["write", "fast"])
+        .keys(1, 1, 1),
+    CommandSpec::new("zcount", 4, "sorted_set", "Returns the count of members in a sorted set that have scores within a range.")
+        .flags(&["readonly", "fast"])
+        .keys(1, 1, 1),
+    CommandSpec::new("zunion", -3, "sorted_set", "Returns the union of multiple sorted sets.")
+        .flags(&["readonly", "movablekeys"])
+        .movable_keys(KeySearch::KeyNum(1)),
+    CommandSpec::new("zinter", -3, "sorted_set", "Returns the intersect of multiple sorted sets.")
+        .flags(&["readonly", "movablekeys"])
+        .movable_keys(KeySearch::KeyNum(1)),
+    CommandSpec::new("zdiff", -3, "sorted_set", "Returns the difference between multiple sorted sets.")
+        .flags(&["readonly", "movablekeys"])
+        .movable_keys(KeySearch::KeyNum(1)),
+    CommandSpec::new("zunionstore", -4, "sorted_set", "Stores the union of multiple sorted sets in a key.")
+        .flags(&["write", "movablekeys"])
+        .keys(1, 1, 1)
+        .movable_keys(KeySearch::KeyNum(2)),
+    CommandSpec::new("zinterstore", -4, "sorted_set", "Stores the intersect of multiple sorted sets in a key.")
+        .flags(&["write", "movablekeys"])
+        .keys(1, 1, 1)
+        .movable_keys(KeySearch::KeyNum(2)),
+    CommandSpec::new("zdiffstore", -4, "sorted_set", "Stores the difference of multiple sorted sets in a key.")
+        .flags(&["write", "movablekeys"])
+        .keys(1, 1, 1)
+        .movable_keys(KeySearch::KeyNum(2)),
+    CommandSpec::new("zscan", -3, "sorted_set", "Iterates over members and scores of a sorted set.")
+        .flags(&["readonly"])
+        .keys(1, 1, 1),
+    CommandSpec::new("zremrangebyrank", 4, "sorted_set", "Removes members in a sorted set within a range of indexes.")
+        .flags(&["write"])
+        .keys(1, 1, 1),
+    CommandSpec::new("zremrangebyscore", 4, "sorted_set", "Removes members in a sorted set within a range of scores.")
+        .flags(&["write"])
+        .keys(1, 1, 1),
+    CommandSpec::new("zremrangebylex", 4, "sorted_set", "Removes members in a sorted set within a lexicographical range.")
+        .flags(&["write"])
+        .keys(1, 1, 1),
+    CommandSpec::new("bzpopmin", -3, "sorted_set", "Removes and returns the member with the lowest score from one or more sorted sets. Blocks until a member is available otherwise.")
+        .flags(&["write", "fast", "blocking"])
+        .keys(1, -2, 1),
+];
+
+impl CommandExecutor for ZAdd {
+    fn execute(mut self, backend: &crate::Backend) -> RespFrame {
+        let members = std::mem::take(&mut self.members);
+        let outcomes = match backend.zadd(self.key.clone(), members, self.flags) {
+            Ok(outcomes) => outcomes,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        let changed = outcomes
+            .iter()
+            .any(|outcome| matches!(outcome, ZAddOutcome::Added(_) | ZAddOutcome::Updated(_)));
+        if changed {
+            let event = if self.flags.incr { "zincr" } else { "zadd" };
+            self.notify(backend, NotifyClass::ZSet, event, &self.key);
+        }
+        if self.flags.incr {
+            // INCR replies with the new score, or null when the update was skipped
+            return match outcomes.first() {
+                Some(ZAddOutcome::Added(score))
+                | Some(ZAddOutcome::Updated(score))
+                | Some(ZAddOutcome::Unchanged(score)) => score_reply(backend, *score),
+                Some(ZAddOutcome::NotANumber) => {
+                    SimpleError::new("ERR resulting score is not a number (NaN)").into()
+                }
+                _ => RespFrame::Null(RespNull),
+            };
+        }
+
+        let count = outcomes
+            .iter()
+            .filter(|outcome| match outcome {
+                ZAddOutcome::Added(_) => true,
+                ZAddOutcome::Updated(_) => self.ch,
+                _ => false,
+            })
+            .count();
+        RespFrame::Integer(count as i64)
+    }
+}
+
+impl CommandExecutor for ZCombine {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let result = match backend.zcombine(self.op, &self.keys, &self.weights, self.aggregate) {
+            Ok(result) => result,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        let members = result.iter().map(|(m, s)| (m.to_string(), s)).collect();
+        members_reply(backend, members, self.withscores)
+    }
+}
+
+impl CommandExecutor for ZCombineStore {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let existed = backend.exists(&self.destination);
+        let len = match backend.zcombine_store(
+            self.op,
+            self.destination.clone(),
+            &self.keys,
+            &self.weights,
+            self.aggregate,
+        ) {
+            Ok(len) => len,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        let event = match self.op {
+            SetOp::Inter => "zinterstore",
+            SetOp::Union => "zunionstore",
+            SetOp::Diff => "zdiffstore",
+        };
+        if len > 0 {
+            self.notify(backend, NotifyClass::ZSet, event, &self.destination);
+        } else if existed && !backend.exists(&self.destination) {
+            // an empty result removes the destination
+            self.notify(backend, NotifyClass::Generic, "del", &self.destination);
+        }
+        RespFrame::Integer(len as i64)
+    }
+}
+
+impl CommandExecutor for ZScan {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let (cursor, members) =
+            match backend.zscan(&self.key, self.cursor, self.count, self.pattern.as_deref()) {
+                Ok(page) => page,
+                Err(e) => return SimpleError::new(e.to_string()).into(),
+            };
+        RespArray::new([
+            BulkString::from(cursor.to_string()).into(),
+            members_reply(backend, members, !self.noscores),
+        ])
+        .into()
+    }
+}
+
+impl CommandExecutor for ZRemRange {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let removed = match backend.zremrange(&self.key, &self.range) {
+            Ok(removed) => removed,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        if removed > 0 {
+            let event = match self.range {
+                ZRangeSpec::Rank(..) => "zremrangebyrank",
+                ZRangeSpec::Score(..) => "zremrangebyscore",
+                ZRangeSpec::Lex(..) => "zremrangebylex",
+            };
+            self.notify(backend, NotifyClass::ZSet, event, &self.key);
+            if !backend.exists(&self.key) {
+                self.notify(backend, NotifyClass::Generic, "del", &self.key);
+            }
+        }
+        RespFrame::Integer(removed as i64)
+    }
+}
+
+// Executed directly (e.g. inside a transaction) BZPOPMIN never blocks.
+impl CommandExecutor for BZPopMin {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let popped = backend.zmpop(&self.keys, true, 1);
+        self.popped(backend, popped)
+    }
+}
+
+impl BZPopMin {
+    // Waits until one of the sorted sets can be popped or the timeout elapses.
+    pub async fn execute_blocking(self, backend: &Backend) -> RespFrame {
+        let deadline =
+            (self.timeout > 0.0).then(|| Instant::now() + Duration::from_secs_f64(self.timeout));
+        let db = backend.selected_db();
+        let keys = self.keys.iter().map(|key| BlockKey::Key(db, key.clone()));
+        let popped = backend.block_on(keys.collect(), deadline, || {
+            // a SWAPDB may have put other sorted sets behind the selected database
+            backend.refresh_db();
+            let keys = &self.keys;
+            match backend.atomically(keys, || backend.zmpop(keys, true, 1)) {
+                Ok(None) => None,
+                popped => Some(popped),
+            }
+        });
+        match popped.await {
+            Some(popped) => self.popped(backend, popped),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+
+    // Reports the events of the pop and replies with the key, the member and its score.
+    fn popped(&self, backend: &Backend, popped: Result<Option<ZPopped>, WrongType>) -> RespFrame {
+        let (key, members) = match popped {
+            Ok(Some(popped)) => popped,
+            Ok(None) => return RespFrame::Null(RespNull),
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        self.notify(backend, NotifyClass::ZSet, "zpopmin", &key);
+        if !backend.exists(&key) {
+            self.notify(backend, NotifyClass::Generic, "del", &key);
+        }
+        let mut reply = vec![BulkString::from(key).into()];
+        for (member, score) in members {
+            reply.push(BulkString::from(member).into());
+            reply.push(score_reply(backend, score));
+        }
+        RespArray::new(reply).into()
+    }
+}
+
+impl CommandExecutor for ZCount {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let range = ZRangeSpec::Score(self.min, self.max);
+        match backend.zcount(&self.key, &range) {
+            Ok(count) => RespFrame::Integer(count as i64),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+// A score replied on its own: RESP2 has no double type, the score goes as a string there.
+fn score_reply(backend: &Backend, score: f64) -> RespFrame {
+    if backend.protocol() >= 3 {
+        RespFrame::Double(score)
+    } else {
+        BulkString::new(score.to_string()).into()
+    }
+}
+
+impl CommandExecutor for ZIncrBy {
+    fn execute(mut self, backend: &crate::Backend) -> RespFrame {
+        let flags = ZAddFlags {
+            incr: true,
+            ..Default::default()
+        };
+        let member = std::mem::take(&mut self.member);
+        let outcomes = match backend.zadd(self.key.clone(), vec![(self.increment, member)], flags) {
+            Ok(outcomes) => outcomes,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        match outcomes.first() {
+            Some(ZAddOutcome::Added(score))
+            | Some(ZAddOutcome::Updated(score))
+            | Some(ZAddOutcome::Unchanged(score)) => {
+                self.notify(backend, NotifyClass::ZSet, "zincr", &self.key);
+                score_reply(backend, *score)
+            }
+            _ => SimpleError::new("ERR resulting score is not a number (NaN)").into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZScore {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.zscore(&self.key, &self.member) {
+            Ok(Some(score)) => score_reply(backend, score),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRem {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let removed = match backend.zrem(&self.key, &self.members) {
+            Ok(removed) => removed,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        if removed > 0 {
+            self.notify(backend, NotifyClass::ZSet, "zrem", &self.key);
+            if !backend.exists(&self.key) {
+                self.notify(backend, NotifyClass::Generic, "del", &self.key);
+            }
+        }
+        RespFrame::Integer(removed as i64)
+    }
+}
+
+impl CommandExecutor for ZCard {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.zcard(&self.key) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRange {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.zrange(&self.key, &self.range, self.rev, self.offset, self.count) {
+            Ok(members) => members_reply(backend, members, self.withscores),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+// a flat array of members, each followed by its score when `withscores` is set
+fn members_reply(backend: &Backend, members: Vec<(String, f64)>, withscores: bool) -> RespFrame {
+    let mut data = Vec::with_capacity(members.len() * 2);
+    for (member, score) in members {
+        data.push(BulkString::from(member).into());
+        if withscores {
+            data.push(score_reply(backend, score));
+        }
+    }
+    RespArray::new(data).into()
+}
+
+impl TryFrom<RespArray> for ZAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "zadd", 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_key(args.next())?;
+
+        let mut flags = ZAddFlags::default();
+        let mut ch = false;
+        while let Some(RespFrame::BulkString(arg)) = args.peek() {
+            match arg.to_ascii_lowercase().as_slice() {
+                b"nx" => flags.nx = true,
+                b"xx" => flags.xx = true,
+                b"gt" => flags.gt = true,
+                b"lt" => flags.lt = true,
+                b"ch" => ch = true,
+                b"incr" => flags.incr = true,
+                _ => break,
+            }
+            args.next();
+        }
+
+        if flags.nx && flags.xx {
+            return Err(CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        if [flags.nx, flags.gt, flags.lt]
+            .iter()
+            .filter(|f| **f)
+            .count()
+            > 1
+        {
+            return Err(CommandError::InvalidArgument(
+                "GT, LT, and/or NX options at the same time are not compatible".to_string(),
+            ));
+        }
+
+        let args = args.collect::<Vec<_>>();
+        if args.is_empty() || args.len() % 2 != 0 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        if flags.incr && args.len() != 2 {
+            return Err(CommandError::InvalidArgument(
+                "INCR option supports a single increment-element pair".to_string(),
+            ));
+        }
+
+        let mut members = Vec::with_capacity(args.len() / 2);
+        let mut args = args.into_iter();
+        while let Some(score) = args.next() {
+            let score = extract_score(Some(score))?;
+            members.push((score, extract_string(args.next())?));
+        }
+        Ok(ZAdd {
+            key,
+            members,
+            flags,
+            ch,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZIncrBy {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zincrby"], 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZIncrBy {
+            key: extract_key(args.next())?,
+            increment: extract_score(args.next())?,
+            member: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZScore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zscore"], 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZScore {
+            key: extract_key(args.next())?,
+            member: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRem {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "zrem", 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next())?;
+        let members = args
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ZRem { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for ZCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zcard"], 1)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZCard {
+            key: extract_key(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "zrange", 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next())?;
+        let (start, stop) = (args.next(), args.next());
+
+        let (mut by_score, mut by_lex, mut rev, mut withscores) = (false, false, false, false);
+        let mut limit = None;
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg))?.to_ascii_lowercase().as_str() {
+                "byscore" => by_score = true,
+                "bylex" => by_lex = true,
+                "rev" => rev = true,
+                "withscores" => withscores = true,
+                "limit" => limit = Some(extract_limit(&mut args)?),
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+
+        if by_score && by_lex {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        if limit.is_some() && !by_score && !by_lex {
+            return Err(CommandError::InvalidArgument(
+                "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                    .to_string(),
+            ));
+        }
+        if withscores && by_lex {
+            return Err(CommandError::InvalidArgument(
+                "syntax error, WITHSCORES not supported in combination with BYLEX".to_string(),
+            ));
+        }
+
+        // with REV, score and lex ranges are given as max then min
+        let (min, max) = if rev { (stop, start) } else { (start, stop) };
+        let range = if by_score {
+            ZRangeSpec::Score(extract_score_bound(min)?, extract_score_bound(max)?)
+        } else if by_lex {
+            ZRangeSpec::Lex(extract_lex_bound(min)?, extract_lex_bound(max)?)
+        } else {
+            let (start, stop) = if rev { (max, min) } else { (min, max) };
+            ZRangeSpec::Rank(extract_integer(start)?, extract_integer(stop)?)
+        };
+
+        let (offset, count) = limit.unwrap_or((0, None));
+        Ok(ZRange {
+            key,
+            range,
+            rev,
+            offset,
+            count,
+            withscores,
+        })
+    }
+}
+
+const ZSET_OPS: [(&str, SetOp); 3] = [
+    ("zinter", SetOp::Inter),
+    ("zunion", SetOp::Union),
+    ("zdiff", SetOp::Diff),
+];
+
+const ZSET_STORE_OPS: [(&str, SetOp); 3] = [
+    ("zinterstore", SetOp::Inter),
+    ("zunionstore", SetOp::Union),
+    ("zdiffstore", SetOp::Diff),
+];
+
+impl TryFrom<RespArray> for ZCombine {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, op) = extract_set_op(&value, &ZSET_OPS)?;
+        validate_variadic_command(&value, name, 2)?;
+
+        let args = extract_args(value, 1)?.into_iter();
+        let (keys, options) = extract_combine_args(args, op, true)?;
+        Ok(ZCombine {
+            op,
+            keys,
+            weights: options.weights,
+            aggregate: options.aggregate,
+            withscores: options.withscores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZCombineStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, op) = extract_set_op(&value, &ZSET_STORE_OPS)?;
+        validate_variadic_command(&value, name, 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let destination = extract_key(args.next())?;
+        let (keys, options) = extract_combine_args(args, op, false)?;
+        Ok(ZCombineStore {
+            op,
+            destination,
+            keys,
+            weights: options.weights,
+            aggregate: options.aggregate,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct CombineOptions {
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+    withscores: bool,
+}
+
+// numkeys key [key ...] [WEIGHTS weight [weight ...]] [AGGREGATE <SUM | MIN | MAX>] [WITHSCORES]
+// WEIGHTS and AGGREGATE are not accepted by the diff commands, WITHSCORES only by the non-store ones.
+fn extract_combine_args(
+    mut args: impl Iterator<Item = RespFrame>,
+    op: SetOp,
+    allow_withscores: bool,
+) -> Result<(Vec<Vec<u8>>, CombineOptions), CommandError> {
+    let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+    let numkeys = extract_integer(args.next())?;
+    if numkeys <= 0 {
+        return Err(CommandError::InvalidArgument(
+            "at least 1 input key is needed".to_string(),
+        ));
+    }
+    let keys = (0..numkeys)
+        .map(|_| extract_key(args.next()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| syntax_error())?;
+
+    let mut options = CombineOptions::default();
+    while let Some(arg) = args.next() {
+        match extract_string(Some(arg))?.to_ascii_lowercase().as_str() {
+            "weights" if op != SetOp::Diff => {
+                options.weights = (0..keys.len())
+                    .map(|_| extract_float(args.next()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| {
+                        CommandError::InvalidArgument("weight value is not a float".to_string())
+                    })?;
+            }
+            "aggregate" if op != SetOp::Diff => {
+                options.aggregate = match extract_string(args.next())?.to_ascii_lowercase().as_str()
+                {
+                    "sum" => Aggregate::Sum,
+                    "min" => Aggregate::Min,
+                    "max" => Aggregate::Max,
+                    _ => return Err(syntax_error()),
+                };
+            }
+            "withscores" if allow_withscores => options.withscores = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok((keys, options))
+}
+
+impl TryFrom<RespArray> for ZScan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "zscan", 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next())?;
+        let scan = extract_scan_args(args)?;
+        let mut noscores = false;
+        for flag in scan.flags {
+            match flag.as_str() {
+                "noscores" => noscores = true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(ZScan {
+            key,
+            cursor: scan.cursor,
+            pattern: scan.pattern,
+            count: scan.count,
+            noscores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = match value.first() {
+            Some(RespFrame::BulkString(cmd)) => cmd.to_ascii_lowercase(),
+            _ => vec![],
+        };
+        let name = match name.as_slice() {
+            b"zremrangebyrank" => "zremrangebyrank",
+            b"zremrangebyscore" => "zremrangebyscore",
+            _ => "zremrangebylex",
+        };
+        validate_command(&value, &[name], 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next())?;
+        let range = match name {
+            "zremrangebyrank" => {
+                ZRangeSpec::Rank(extract_integer(args.next())?, extract_integer(args.next())?)
+            }
+            "zremrangebyscore" => ZRangeSpec::Score(
+                extract_score_bound(args.next())?,
+                extract_score_bound(args.next())?,
+            ),
+            _ => ZRangeSpec::Lex(
+                extract_lex_bound(args.next())?,
+                extract_lex_bound(args.next())?,
+            ),
+        };
+        Ok(ZRemRange { key, range })
+    }
+}
+
+impl TryFrom<RespArray> for BZPopMin {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_variadic_command(&value, "bzpopmin", 2)?;
+
+        let mut args = extract_args(value, 1)?;
+        let timeout = extract_timeout(args.pop())?;
+        let keys = args
+            .into_iter()
+            .map(|key| extract_key(Some(key)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BZPopMin { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for ZCount {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zcount"], 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZCount {
+            key: extract_key(args.next())?,
+            min: extract_score_bound(args.next())?,
+            max: extract_score_bound(args.next())?,
+        })
+    }
+}
+
+// ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]
+// ZREVRANGEBYSCORE key max min [WITHSCORES] [LIMIT offset count]
+pub(super) fn zrange_by_score(value: RespArray, rev: bool) -> Result<ZRange, CommandError> {
+    let name = if rev {
+        "zrevrangebyscore"
+    } else {
+        "zrangebyscore"
+    };
+    validate_variadic_command(&value, name, 3)?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next())?;
+    let (first, second) = (
+        extract_score_bound(args.next())?,
+        extract_score_bound(args.next())?,
+    );
+    let (min, max) = if rev {
+        (second, first)
+    } else {
+        (first, second)
+    };
+
+    let (mut withscores, mut limit) = (false, None);
+    while let Some(arg) = args.next() {
+        match extract_string(Some(arg))?.to_ascii_lowercase().as_str() {
+            "withscores" => withscores = true,
+            "limit" => limit = Some(extract_limit(&mut args)?),
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+
+    let (offset, count) = limit.unwrap_or((0, None));
+    Ok(ZRange {
+        key,
+        range: ZRangeSpec::Score(min, max),
+        rev,
+        offset,
+        count,
+        withscores,
+    })
+}
+
+// LIMIT offset count, a negative count returns all remaining members
+fn extract_limit(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<(usize, Option<usize>), CommandError> {
+    let offset = extract_integer(args.next())?;
+    let count = extract_integer(args.next())?;
+    // a negative offset matches nothing
+    let offset = if offset < 0 {
+        usize::MAX
+    } else {
+        offset as usize
+    };
+    let count = if count < 0 {
+        None
+    } else {
+        Some(count as usize)
+    };
+    Ok((offset, count))
+}
+
+// a score bound is a float, optionally prefixed by "(" to make it exclusive
+fn extract_score_bound(frame: Option<RespFrame>) -> Result<Bound<f64>, CommandError> {
+    let err = || CommandError::InvalidArgument("min or max is not a float".to_string());
+    let s = extract_string(frame)?;
+    let (exclusive, s) = match s.strip_prefix('(') {
+        Some(s) => (true, s),
+        None => (false, s.as_str()),
+    };
+    let score: f64 = s.parse().map_err(|_| err())?;
+    if score.is_nan() {
+        return Err(err());
+    }
+    Ok(if exclusive {
+        Bound::Excluded(score)
+    } else {
+        Bound::Included(score)
+    })
+}
+
+// a lex bound is "-", "+", or a member prefixed by "[" (inclusive) or "(" (exclusive)
+fn extract_lex_bound(frame: Option<RespFrame>) -> Result<LexBound, CommandError> {
+    let s = extract_string(frame)?;
+    match s.as_bytes().first() {
+        Some(b'-') if s.len() == 1 => Ok(LexBound::Min),
+        Some(b'+') if s.len() == 1 => Ok(LexBound::Max),
+        Some(b'[') => Ok(LexBound::Inclusive(s[1..].to_string())),
+        Some(b'(') => Ok(LexBound::Exclusive(s[1..].to_string())),
+        _ => Err(CommandError::InvalidArgument(
+            "min or max not valid string range item".to_string(),
+        )),
+    }
+}
+
+// scores accept "inf", "+inf" and "-inf" but never NaN
+fn extract_score(frame: Option<RespFrame>) -> Result<f64, CommandError> {
+    match extract_float(frame)? {
+        score if score.is_nan() => Err(CommandError::InvalidArgument(
+            "value is not a valid float".to_string(),
+        )),
+        score => Ok(score),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_zadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*6\r\n$4\r\nZADD\r\n$6\r\nmyzset\r\n$1\r\n1\r\n$3\r\none\r\n$4\r\n-inf\r\n$3\r\ntwo\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZAdd = frame.try_into()?;
+        assert_eq!(result.key, b"myzset");
+        assert_eq!(
+            result.members,
+            vec![
+                (1.0, "one".to_string()),
+                (f64::NEG_INFINITY, "two".to_string())
+            ]
+        );
+
+        buf.extend_from_slice(b"*4\r\n$4\r\nZADD\r\n$6\r\nmyzset\r\n$3\r\nnan\r\n$3\r\none\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<ZAdd, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zadd_flags_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*7\r\n$4\r\nZADD\r\n$6\r\nmyzset\r\n$2\r\nXX\r\n$2\r\nGT\r\n$2\r\nCH\r\n$1\r\n1\r\n$3\r\none\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZAdd = frame.try_into()?;
+        assert!(result.flags.xx && result.flags.gt && result.ch);
+        assert!(!result.flags.nx && !result.flags.lt && !result.flags.incr);
+        assert_eq!(result.members, vec![(1.0, "one".to_string())]);
+
+        buf.extend_from_slice(
+            b"*6\r\n$4\r\nZADD\r\n$6\r\nmyzset\r\n$2\r\nNX\r\n$2\r\nXX\r\n$1\r\n1\r\n$3\r\none\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<ZAdd, _> = frame.try_into();
+        assert!(result.is_err());
+
+        buf.extend_from_slice(b"*7\r\n$4\r\nZADD\r\n$6\r\nmyzset\r\n$4\r\nINCR\r\n$1\r\n1\r\n$3\r\none\r\n$1\r\n2\r\n$3\r\ntwo\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<ZAdd, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zadd_flags_command() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = ZAdd {
+            key: b"myzset".to_vec(),
+            members: vec![(1.0, "one".to_string()), (2.0, "two".to_string())],
+            flags: ZAddFlags::default(),
+            ch: false,
+        };
+        cmd.execute(&backend);
+
+        let cmd = ZAdd {
+            key: b"myzset".to_vec(),
+            members: vec![
+                (5.0, "one".to_string()),
+                (1.0, "two".to_string()),
+                (3.0, "three".to_string()),
+            ],
+            flags: ZAddFlags {
+                gt: true,
+                ..Default::default()
+            },
+            ch: true,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(backend.zscore(b"myzset", "two")?, Some(2.0));
+
+        let cmd = ZAdd {
+            key: b"myzset".to_vec(),
+            members: vec![(2.5, "one".to_string())],
+            flags: ZAddFlags {
+                incr: true,
+                ..Default::default()
+            },
+            ch: false,
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("7.5").into());
+        // RESP3 has the new score as a double
+        let client = backend.connect("127.0.0.1:5000".to_string(), "127.0.0.1:6379".to_string());
+        client.set_protocol(3);
+        let cmd = ZAdd {
+            key: b"myzset".to_vec(),
+            members: vec![(0.5, "one".to_string())],
+            flags: ZAddFlags {
+                incr: true,
+                ..Default::default()
+            },
+            ch: false,
+        };
+        assert_eq!(cmd.execute(&client), RespFrame::Double(8.0));
+
+        let cmd = ZAdd {
+            key: b"myzset".to_vec(),
+            members: vec![(1.0, "four".to_string())],
+            flags: ZAddFlags {
+                xx: true,
+                incr: true,
+                ..Default::default()
+            },
+            ch: false,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrange_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\nZRANGE\r\n$6\r\nmyzset\r\n$1\r\n0\r\n$2\r\n-1\r\n$10\r\nWITHSCORES\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZRange = frame.try_into()?;
+        assert_eq!(result.key, b"myzset");
+        assert_eq!(result.range, ZRangeSpec::Rank(0, -1));
+        assert!(result.withscores && !result.rev);
+
+        buf.extend_from_slice(b"*9\r\n$6\r\nZRANGE\r\n$6\r\nmyzset\r\n$4\r\n+inf\r\n$2\r\n(1\r\n$7\r\nBYSCORE\r\n$3\r\nREV\r\n$5\r\nLIMIT\r\n$1\r\n1\r\n$2\r\n-1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZRange = frame.try_into()?;
+        assert_eq!(
+            result.range,
+            ZRangeSpec::Score(Bound::Excluded(1.0), Bound::Included(f64::INFINITY))
+        );
+        assert!(result.rev);
+        assert_eq!((result.offset, result.count), (1, None));
+
+        buf.extend_from_slice(b"*6\r\n$6\r\nZRANGE\r\n$6\r\nmyzset\r\n$1\r\n-\r\n$2\r\n[c\r\n$5\r\nBYLEX\r\n$10\r\nWITHSCORES\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<ZRange, _> = frame.try_into();
+        assert!(result.is_err());
+
+        buf.extend_from_slice(b"*7\r\n$6\r\nZRANGE\r\n$6\r\nmyzset\r\n$1\r\n0\r\n$1\r\n1\r\n$5\r\nLIMIT\r\n$1\r\n0\r\n$1\r\n1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<ZRange, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrange_command() -> Result<()> {
+        let backend = Backend::new();
+        let members = vec![(1.0, "one".to_string()), (2.0, "two".to_string())];
+        backend.zadd("myzset", members, ZAddFlags::default())?;
+
+        let cmd = ZRange {
+            key: b"myzset".to_vec(),
+            range: ZRangeSpec::Rank(0, -1),
+            rev: true,
+            offset: 0,
+            count: None,
+            withscores: true,
+        };
+        let expected = RespArray::new([
+            BulkString::from("two").into(),
+            BulkString::from("2").into(),
+            BulkString::from("one").into(),
+            BulkString::from("1").into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+        // RESP3 has the scores as doubles
+        let client = backend.connect("127.0.0.1:5000".to_string(), "127.0.0.1:6379".to_string());
+        client.set_protocol(3);
+        let expected = RespArray::new([
+            BulkString::from("two").into(),
+            RespFrame::Double(2.0),
+            BulkString::from("one").into(),
+            RespFrame::Double(1.0),
+        ]);
+        let cmd = ZRange {
+            key: b"myzset".to_vec(),
+            range: ZRangeSpec::Rank(0, -1),
+            rev: true,
+            offset: 0,
+            count: None,
+            withscores: true,
+        };
+        assert_eq!(cmd.execute(&client), expected.into());
+
+        let cmd = ZRange {
+            key: b"myzset".to_vec(),
+            range: ZRangeSpec::Score(Bound::Excluded(1.0), Bound::Unbounded),
+            rev: false,
+            offset: 0,
+            count: None,
+            withscores: false,
+        };
+        let expected = RespArray::new([BulkString::from("two").into()]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zcombine_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*10\r\n$6\r\nZINTER\r\n$1\r\n2\r\n$5\r\nzset1\r\n$5\r\nzset2\r\n$7\r\nWEIGHTS\r\n$1\r\n2\r\n$1\r\n3\r\n$9\r\nAGGREGATE\r\n$3\r\nMAX\r\n$10\r\nWITHSCORES\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZCombine = frame.try_into()?;
+        assert_eq!(result.op, SetOp::Inter);
+        assert_eq!(result.keys, vec![b"zset1".to_vec(), b"zset2".to_vec()]);
+        assert_eq!(result.weights, vec![2.0, 3.0]);
+        assert_eq!(result.aggregate, Aggregate::Max);
+        assert!(result.withscores);
+
+        buf.extend_from_slice(
+            b"*5\r\n$10\r\nZDIFFSTORE\r\n$3\r\nout\r\n$1\r\n2\r\n$5\r\nzset1\r\n$5\r\nzset2\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZCombineStore = frame.try_into()?;
+        assert_eq!(result.op, SetOp::Diff);
+        assert_eq!(result.destination, b"out");
+        assert_eq!(result.keys, vec![b"zset1".to_vec(), b"zset2".to_vec()]);
+
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nZDIFF\r\n$1\r\n1\r\n$5\r\nzset1\r\n$7\r\nWEIGHTS\r\n$1\r\n2\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<ZCombine, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zcombine_commands() -> Result<()> {
+        let backend = Backend::new();
+        let members = vec![(1.0, "one".to_string()), (2.0, "two".to_string())];
+        backend.zadd("zset1", members, ZAddFlags::default())?;
+        let members = vec![(1.0, "one".to_string()), (3.0, "three".to_string())];
+        backend.zadd("zset2", members, ZAddFlags::default())?;
+
+        let cmd = ZCombine {
+            op: SetOp::Union,
+            keys: vec![b"zset1".to_vec(), b"zset2".to_vec()],
+            weights: vec![],
+            aggregate: Aggregate::Sum,
+            withscores: true,
+        };
+        let expected = RespArray::new([
+            BulkString::from("one").into(),
+            BulkString::from("2").into(),
+            BulkString::from("two").into(),
+            BulkString::from("2").into(),
+            BulkString::from("three").into(),
+            BulkString::from("3").into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = ZCombineStore {
+            op: SetOp::Inter,
+            destination: b"out".to_vec(),
+            keys: vec![b"zset1".to_vec(), b"zset2".to_vec()],
+            weights: vec![1.0, 5.0],
+            aggregate: Aggregate::Min,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(backend.zscore(b"out", "one")?, Some(1.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zscan_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*8\r\n$5\r\nZSCAN\r\n$4\r\nzset\r\n$2\r\n42\r\n$5\r\nMATCH\r\n$2\r\nm*\r\n$5\r\nCOUNT\r\n$2\r\n20\r\n$8\r\nNOSCORES\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZScan = frame.try_into()?;
+        assert_eq!(result.key, b"zset");
+        assert_eq!(result.cursor, 42);
+        assert_eq!(result.pattern.as_deref(), Some(&b"m*"[..]));
+        assert_eq!(result.count, 20);
+        assert!(result.noscores);
+
+        buf.extend_from_slice(b"*3\r\n$5\r\nZSCAN\r\n$4\r\nzset\r\n$2\r\n-1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<ZScan, _> = frame.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zscan_command() -> Result<()> {
+        let backend = Backend::new();
+        let members = vec![(1.0, "one".to_string()), (2.0, "two".to_string())];
+        backend.zadd("zset", members, ZAddFlags::default())?;
+
+        let cmd = ZScan {
+            key: b"zset".to_vec(),
+            cursor: 0,
+            pattern: Some(b"t*".to_vec()),
+            count: 10,
+            noscores: false,
+        };
+        let expected = RespArray::new([
+            BulkString::from("0").into(),
+            RespArray::new([BulkString::from("two").into(), BulkString::from("2").into()]).into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zcount_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nZCOUNT\r\n$6\r\nmyzset\r\n$2\r\n(1\r\n$4\r\n+inf\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZCount = frame.try_into()?;
+        assert_eq!(result.key, b"myzset");
+        assert_eq!(result.min, Bound::Excluded(1.0));
+        assert_eq!(result.max, Bound::Included(f64::INFINITY));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrangebyscore_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*8\r\n$16\r\nZREVRANGEBYSCORE\r\n$6\r\nmyzset\r\n$1\r\n5\r\n$2\r\n(1\r\n$10\r\nWITHSCORES\r\n$5\r\nLIMIT\r\n$1\r\n0\r\n$1\r\n2\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result = zrange_by_score(frame, true)?;
+        assert_eq!(result.key, b"myzset");
+        assert_eq!(
+            result.range,
+            ZRangeSpec::Score(Bound::Excluded(1.0), Bound::Included(5.0))
+        );
+        assert!(result.rev && result.withscores);
+        assert_eq!((result.offset, result.count), (0, Some(2)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zremrange_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$16\r\nZREMRANGEBYSCORE\r\n$6\r\nmyzset\r\n$4\r\n-inf\r\n$2\r\n(3\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZRemRange = frame.try_into()?;
+        assert_eq!(result.key, b"myzset");
+        assert_eq!(
+            result.range,
+            ZRangeSpec::Score(Bound::Included(f64::NEG_INFINITY), Bound::Excluded(3.0))
+        );
+
+        buf.extend_from_slice(
+            b"*4\r\n$14\r\nZREMRANGEBYLEX\r\n$6\r\nmyzset\r\n$2\r\n[a\r\n$1\r\n+\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZRemRange = frame.try_into()?;
+        assert_eq!(
+            result.range,
+            ZRangeSpec::Lex(LexBound::Inclusive("a".to_string()), LexBound::Max)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zremrange_command() -> Result<()> {
+        let backend = Backend::new();
+        let members = vec![
+            (1.0, "one".to_string()),
+            (2.0, "two".to_string()),
+            (3.0, "three".to_string()),
+        ];
+        backend.zadd("myzset", members, ZAddFlags::default())?;
+
+        let cmd = ZRemRange {
+            key: b"myzset".to_vec(),
+            range: ZRangeSpec::Rank(0, 1),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(backend.zscore(b"myzset", "three")?, Some(3.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zcount_command() -> Result<()> {
+        let backend = Backend::new();
+        let members = vec![
+            (1.0, "one".to_string()),
+            (2.0, "two".to_string()),
+            (3.0, "three".to_string()),
+        ];
+        backend.zadd("myzset", members, ZAddFlags::default())?;
+
+        let cmd = ZCount {
+            key: b"myzset".to_vec(),
+            min: Bound::Excluded(1.0),
+            max: Bound::Included(3.0),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = ZCount {
+            key: b"nokey".to_vec(),
+            min: Bound::Unbounded,
+            max: Bound::Unbounded,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zincrby_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$7\r\nZINCRBY\r\n$6\r\nmyzset\r\n$3\r\n2.5\r\n$3\r\none\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZIncrBy = frame.try_into()?;
+        assert_eq!(result.key, b"myzset");
+        assert_eq!(result.increment, 2.5);
+        assert_eq!(result.member, "one");
+        Ok(())
+    }
+
+    #[test]
+    fn test_zincrby_command() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = ZIncrBy {
+            key: b"myzset".to_vec(),
+            increment: 2.0,
+            member: "one".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("2").into());
+
+        let cmd = ZIncrBy {
+            key: b"myzset".to_vec(),
+            increment: -0.5,
+            member: "one".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("1.5").into());
+
+        backend.zadd(
+            "myzset",
+            vec![(f64::INFINITY, "two".to_string())],
+            ZAddFlags::default(),
+        )?;
+        let cmd = ZIncrBy {
+            key: b"myzset".to_vec(),
+            increment: f64::NEG_INFINITY,
+            member: "two".to_string(),
+        };
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+        assert_eq!(backend.zscore(b"myzset", "two")?, Some(f64::INFINITY));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zincrby_resp3() -> Result<()> {
+        let server = Backend::new();
+        let backend = server.connect("127.0.0.1:5000".to_string(), "127.0.0.1:6379".to_string());
+        let cmd = ZIncrBy {
+            key: b"myzset".to_vec(),
+            increment: 2.5,
+            member: "one".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("2.5").into());
+
+        backend.set_protocol(3);
+        let cmd = ZIncrBy {
+            key: b"myzset".to_vec(),
+            increment: 2.5,
+            member: "one".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Double(5.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zscore_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nZSCORE\r\n$6\r\nmyzset\r\n$3\r\none\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZScore = frame.try_into()?;
+        assert_eq!(result.key, b"myzset");
+        assert_eq!(result.member, "one");
+        Ok(())
+    }
+
+    #[test]
+    fn test_zset_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = ZAdd {
+            key: b"myzset".to_vec(),
+            members: vec![(1.0, "one".to_string()), (2.0, "two".to_string())],
+            flags: ZAddFlags::default(),
+            ch: false,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = ZScore {
+            key: b"myzset".to_vec(),
+            member: "two".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("2").into());
+        let cmd = ZScore {
+            key: b"myzset".to_vec(),
+            member: "two".to_string(),
+        };
+        let client = backend.connect("127.0.0.1:5000".to_string(), "127.0.0.1:6379".to_string());
+        client.set_protocol(3);
+        assert_eq!(cmd.execute(&client), RespFrame::Double(2.0));
+
+        let cmd = ZRem {
+            key: b"myzset".to_vec(),
+            members: vec!["two".to_string(), "three".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = ZCard {
+            key: b"myzset".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = ZScore {
+            key: b"myzset".to_vec(),
+            member: "two".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bzpopmin_blocking() -> Result<()> {
+        let backend = Backend::new();
+        let bzpopmin = |timeout| BZPopMin {
+            keys: vec![b"zset1".to_vec(), b"zset2".to_vec()],
+            timeout,
+        };
+        assert_eq!(
+            bzpopmin(0.05).execute_blocking(&backend).await,
+            RespFrame::Null(RespNull)
+        );
+
+        // woken up by a member added to any of the sorted sets
+        let cloned = backend.clone();
+        let handle = tokio::spawn(async move { bzpopmin(0.0).execute_blocking(&cloned).await });
+        // added once it blocked
+        while backend.blocking().blocked_clients() != 1 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let members = vec![(2.0, "b".to_string()), (1.5, "a".to_string())];
+        backend.zadd("zset2", members, ZAddFlags::default())?;
+        let expected = RespArray::new([
+            BulkString::from("zset2").into(),
+            BulkString::from("a").into(),
+            BulkString::from("1.5").into(),
+        ]);
+        assert_eq!(handle.await?, expected.into());
+
+        // RESP3 has the score as a double
+        let client = backend.connect("127.0.0.1:5000".to_string(), "127.0.0.1:6379".to_string());
+        client.set_protocol(3);
+        let expected = RespArray::new([
+            BulkString::from("zset2").into(),
+            BulkString::from("b").into(),
+            RespFrame::Double(2.0),
+        ]);
+        assert_eq!(bzpopmin(0.0).execute(&client), expected.into());
+        assert!(!backend.exists(b"zset2"));
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{network::RespFrameCodec, replica::command, Backend, ClusterNode, RespFrame};
+use anyhow::{bail, Result};
+use futures::SinkExt;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_stream::StreamExt;
+use tokio_util::codec::Framed;
+use tracing::{info, warn};
+
+// how often a cluster node exchanges what it knows with every other node
+const GOSSIP_INTERVAL: Duration = Duration::from_secs(1);
+// how long an exchange may take before the other node counts as unreachable
+const GOSSIP_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Keeps a cluster node's view of the cluster up to date until the server shuts down. There is
+// no cluster bus, the nodes talk over the port of their clients: each one in turn is sent
+// CLUSTER MEET, so that it knows this node, then CLUSTER NODES, whose reply is merged in, see
+// Cluster::merge. The state is saved whenever it changes.
+pub async fn gossip(backend: Backend) {
+    if !backend.cluster_enabled() {
+        return;
+    }
+    let mut shutdown = backend.shutdown_signal();
+    let mut tick = tokio::time::interval(GOSSIP_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => return,
+            _ = tick.tick() => {}
+        }
+        let cluster = backend.cluster();
+        let myself = cluster.myself().id;
+        let mut changed = false;
+        for node in cluster.nodes().into_iter().filter(|node| node.id != myself) {
+            let exchanged = tokio::time::timeout(GOSSIP_TIMEOUT, exchange(&backend, &node)).await;
+            match exchanged {
+                Ok(Ok(merged)) => {
+                    if !node.connected {
+                        info!("Cluster node {} at {} is reachable", node.id, node.addr());
+                    }
+                    changed |= merged;
+                }
+                Ok(Err(e)) if node.connected => {
+                    warn!("Lost cluster node {} at {}: {:?}", node.id, node.addr(), e);
+                    cluster.set_connected(&node.id, false);
+                    changed = true;
+                }
+                Err(_) if node.connected => {
+                    warn!("Cluster node {} at {} timed out", node.id, node.addr());
+                    cluster.set_connected(&node.id, false);
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+        if changed {
+            if let Err(e) = backend.save_cluster_config() {
+                warn!("Failed to save the cluster config: {:?}", e);
+            }
+        }
+    }
+}
+
+// Introduces this node to `node` and merges in what `node` knows, returns whether anything
+// changed.
+async fn exchange(backend: &Backend, node: &ClusterNode) -> Result<bool> {
+    let stream = TcpStream::connect((node.host.as_str(), node.port)).await?;
+    // the address the other node reaches this one at
+    let ip = stream.local_addr()?.ip().to_string();
+    let port = backend.config().read().port.to_string();
+    let mut framed = Framed::new(stream, RespFrameCodec);
+    request(&mut framed, &["CLUSTER", "MEET", &ip, &port]).await?;
+    let RespFrame::BulkString(nodes) = request(&mut framed, &["CLUSTER", "NODES"]).await? else {
+        bail!("unexpected reply to CLUSTER NODES");
+    };
+    let nodes = String::from_utf8_lossy(&nodes);
+    Ok(backend.cluster().merge(&node.host, node.port, &nodes)?)
+}
+
+async fn request(
+    framed: &mut Framed<TcpStream, RespFrameCodec>,
+    args: &[&str],
+) -> Result<RespFrame> {
+    framed.send(command(args)).await?;
+    match framed.next().await {
+        Some(Ok(RespFrame::Error(e))) => bail!("{} {} replied {}", args[0], args[1], e.0),
+        Some(Ok(reply)) => Ok(reply),
+        Some(Err(e)) => Err(e),
+        None => bail!("the node closed the connection"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{network, BulkString, ClusterRedirect, ConfigValues};
+    use tokio::net::TcpListener;
+
+    // A cluster node serving clients on a port of its own, and gossiping.
+    async fn start_node(dir: &std::path::Path) -> Result<Backend> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let backend = Backend::with_config(ConfigValues {
+            cluster_enabled: true,
+            port: listener.local_addr()?.port(),
+            dir: dir.to_string_lossy().into_owned(),
+            cluster_config_file: format!("nodes-{}.conf", listener.local_addr()?.port()),
+            ..Default::default()
+        });
+        let accepting = backend.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(network::handle_stream(stream, accepting.clone()));
+            }
+        });
+        tokio::spawn(gossip(backend.clone()));
+        Ok(backend)
+    }
+
+    async fn wait_until(condition: impl Fn() -> bool) {
+        for _ in 0..500 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("timed out");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_gossip() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("simple-redis-gossip-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let a = start_node(&dir).await?;
+        let b = start_node(&dir).await?;
+        let c = start_node(&dir).await?;
+        let (a_id, b_id, c_id) = (
+            a.cluster().myself().id,
+            b.cluster().myself().id,
+            c.cluster().myself().id,
+        );
+        a.cluster().assign_slots(&[0, 1, 2], &a_id);
+        b.cluster().assign_slots(&[3], &b_id);
+
+        // a meets b, which meets c, and all end up knowing each other
+        let b_port = b.config().read().port;
+        let c_port = c.config().read().port;
+        assert!(a.cluster().meet("127.0.0.1", b_port));
+        assert!(b.cluster().meet("127.0.0.1", c_port));
+        let knows_all = |node: &Backend| {
+            let nodes = node.cluster().nodes();
+            nodes.len() == 3 && nodes.iter().all(|node| !node.handshake)
+        };
+        wait_until(|| knows_all(&a) && knows_all(&b) && knows_all(&c)).await;
+        let owner = |node: &Backend, slot| node.cluster().slot_owner(slot).map(|n| n.id);
+        wait_until(|| owner(&c, 0) == Some(a_id.clone()) && owner(&c, 3) == Some(b_id.clone()))
+            .await;
+        assert_eq!(owner(&a, 3), Some(b_id.clone()));
+        assert_eq!(a.cluster().myself().host, "127.0.0.1");
+        let redirect = c.cluster_route(&[b"{a}".to_vec()], false);
+        let slot = crate::key_hash_slot(b"a");
+        assert_eq!(redirect, Err(ClusterRedirect::Unassigned));
+        c.cluster().assign_slots(&[slot], &c_id);
+        wait_until(|| owner(&a, slot) == Some(c_id.clone())).await;
+        a.set(b"k".to_vec(), BulkString::from("1").into());
+        assert!(matches!(
+            a.cluster_route(&[b"{a}".to_vec()], false),
+            Err(ClusterRedirect::Moved(_, addr)) if addr == format!("127.0.0.1:{c_port}")
+        ));
+
+        // a slot handed over with a new epoch changes hands everywhere
+        let bumped = b.cluster().bump_epoch();
+        b.cluster().assign_slots(&[0], &b_id);
+        wait_until(|| owner(&a, 0) == Some(b_id.clone()) && owner(&c, 0) == Some(b_id.clone()))
+            .await;
+        assert_eq!(c.cluster().current_epoch(), bumped);
+
+        // which every node remembers
+        let path = dir.join(format!("nodes-{c_port}.conf"));
+        let line = format!("{b_id} 127.0.0.1:{b_port}@0 master - 0 0 {bumped} connected 0 3\n");
+        let saved = || std::fs::read_to_string(&path).is_ok_and(|text| text.contains(&line));
+        wait_until(saved).await;
+        for node in [a, b, c] {
+            node.shutdown();
+        }
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
 mod backend;
 pub mod cmd;
+pub mod gossip;
 pub mod network;
+pub mod replica;
 mod resp;
+pub mod tls;
 
 pub use backend::*;
 pub use network::*;
@@ -1,23 +1,100 @@
 use anyhow::Result;
-use simple_redis_server::{network, Backend};
-use tokio::net::TcpListener;
+use simple_redis_server::tls::{self, TlsAcceptor};
+use simple_redis_server::{cmd, gossip, network, replica, Backend};
+use std::io;
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tokio::task::JoinSet;
 use tracing::{info, warn};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
 
-    let addr = "0.0.0.0:6379";
-    let listener = TcpListener::bind(addr).await?;
+    let backend = Backend::new();
+    // the append only file has the latest writes, the snapshot is only loaded without it
+    let replayed = if backend.append_only() {
+        cmd::load_append_log(&backend)?
+    } else {
+        None
+    };
+    match replayed {
+        Some(count) => info!("Replayed {} commands from the append only file", count),
+        None => {
+            let loaded = backend.load()?;
+            info!("Loaded {} keys from the snapshot", loaded);
+        }
+    }
+    // a cluster node keeps its id and what it knows of the cluster across restarts
+    if backend.cluster_enabled() && !backend.load_cluster_config()? {
+        backend.save_cluster_config()?;
+    }
+    let addr = {
+        let config = backend.config().read();
+        format!("{}:{}", config.bind, config.port)
+    };
+    let listener = TcpListener::bind(&addr).await?;
     info!("Simple-Redis-Server is listening on {}", addr);
+    // the TLS listener, served along with the plaintext one
+    let tls_listener = {
+        let config = backend.config().read().clone();
+        match config.tls_port {
+            0 => None,
+            port => {
+                let acceptor = tls::acceptor(&config)?;
+                let addr = format!("{}:{}", config.bind, port);
+                let listener = TcpListener::bind(&addr).await?;
+                info!("Simple-Redis-Server is listening for TLS on {}", addr);
+                Some((listener, acceptor))
+            }
+        }
+    };
 
-    let backend = Backend::new();
+    let expiring_backend = backend.clone();
+    let active_expire = tokio::spawn(async move { expiring_backend.run_active_expire().await });
+    let replicating = tokio::spawn(replica::follow_master(backend.clone()));
+    let gossiping = tokio::spawn(gossip::gossip(backend.clone()));
+    let saving_backend = backend.clone();
+    let saving = tokio::spawn(async move { saving_backend.run_save_rules().await });
+    let timeouts_backend = backend.clone();
+    let timeouts = tokio::spawn(async move { timeouts_backend.run_client_timeouts().await });
+
+    let mut shutdown = backend.shutdown_signal();
+    // a signal shuts the server down like SHUTDOWN, saving once the connections closed
+    let mut signals = ShutdownSignals::new()?;
+    let mut signaled = false;
+    let mut connections = JoinSet::new();
     loop {
-        let (stream, raddr) = listener.accept().await?;
+        let (stream, raddr, acceptor) = tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, raddr) = accepted?;
+                (stream, raddr, None)
+            }
+            accepted = accept_tls(tls_listener.as_ref()) => {
+                let (stream, raddr, acceptor) = accepted?;
+                (stream, raddr, Some(acceptor))
+            }
+            // reap the tasks of closed connections
+            Some(_) = connections.join_next() => continue,
+            _ = shutdown.changed() => break,
+            name = signals.recv() => {
+                info!("Received {}, scheduling shutdown...", name);
+                signaled = true;
+                break;
+            }
+        };
         info!("Accepted connection from: {}", raddr);
         let cloned_backend = backend.clone();
-        tokio::spawn(async move {
-            match network::handle_stream(stream, cloned_backend).await {
+        connections.spawn(async move {
+            let result = match acceptor {
+                Some(acceptor) => {
+                    network::handle_tls_stream(stream, acceptor, cloned_backend).await
+                }
+                None => network::handle_stream(stream, cloned_backend).await,
+            };
+            match result {
                 Ok(_) => {
                     info!("Connection from {} exited", raddr);
                 }
@@ -27,4 +104,95 @@ async fn main() -> Result<()> {
             }
         });
     }
+
+    // stop accepting, then let every connection complete the request in flight, send its
+    // pending responses and close
+    backend.shutdown();
+    drop(listener);
+    drop(tls_listener);
+    info!("Shutting down, closing {} connections", connections.len());
+    while connections.join_next().await.is_some() {}
+    if signaled {
+        save_on_exit(&backend).await;
+    }
+    if let Err(e) = backend.append_log().sync() {
+        warn!("Error flushing the append only file: {}", e);
+    }
+    active_expire.await?;
+    replicating.await?;
+    gossiping.await?;
+    saving.await?;
+    timeouts.await?;
+    info!("Simple-Redis-Server is now ready to exit, bye bye...");
+    Ok(())
+}
+
+// The signals that shut the server down: SIGINT and SIGTERM on Unix, Ctrl-C elsewhere.
+struct ShutdownSignals {
+    #[cfg(unix)]
+    interrupt: Signal,
+    #[cfg(unix)]
+    terminate: Signal,
+}
+
+impl ShutdownSignals {
+    #[cfg(unix)]
+    fn new() -> io::Result<Self> {
+        Ok(Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn new() -> io::Result<Self> {
+        Ok(Self {})
+    }
+
+    // Waits for the next signal and returns its name.
+    #[cfg(unix)]
+    async fn recv(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.interrupt.recv() => "SIGINT",
+            _ = self.terminate.recv() => "SIGTERM",
+        }
+    }
+
+    #[cfg(not(unix))]
+    async fn recv(&mut self) -> &'static str {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Error listening for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+        "Ctrl-C"
+    }
+}
+
+// Saves the dataset on a shutdown by signal when snapshotting is configured, as SHUTDOWN does
+// without SAVE or NOSAVE. Once every connection closed, nothing writes meanwhile.
+async fn save_on_exit(backend: &Backend) {
+    if backend.config().read().save.is_empty() {
+        return;
+    }
+    let Some(_access) = backend.exclusive_access().await else {
+        warn!("A script is still running, not saving the DB");
+        return;
+    };
+    match backend.save() {
+        Ok(()) => info!("DB saved on disk"),
+        Err(e) => warn!("Error trying to save the DB: {}", e),
+    }
+}
+
+// Accepts the next connection to the TLS listener, never when there is none.
+async fn accept_tls(
+    tls_listener: Option<&(TcpListener, TlsAcceptor)>,
+) -> io::Result<(TcpStream, SocketAddr, TlsAcceptor)> {
+    match tls_listener {
+        Some((listener, acceptor)) => {
+            let (stream, raddr) = listener.accept().await?;
+            Ok((stream, raddr, acceptor.clone()))
+        }
+        None => std::future::pending().await,
+    }
 }
@@ -1,42 +1,209 @@
 use crate::{
-    cmd::{Command, CommandExecutor},
-    Backend, RespDecoder, RespEncoder, RespError, RespFrame,
+    backend::error_message,
+    cmd::{lookup_command, Command, CommandExecutor, Transaction, READONLY_ERROR},
+    Backend, DatasetCopy, ReplicaStream, RespDecoder, RespEncoder, RespError, RespFrame,
+    SimpleError, SimpleString,
 };
-use anyhow::Result;
+use anyhow::{bail, Result};
 use futures::SinkExt;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
+use tokio::sync::{broadcast, watch};
 use tokio_stream::StreamExt;
 use tokio_util::codec::{Decoder, Encoder, Framed};
 use tracing::info;
 
+// what a RESP2 connection in subscriber mode can still run
+const SUBSCRIBER_COMMANDS: &[&str] = &[
+    "subscribe",
+    "unsubscribe",
+    "psubscribe",
+    "punsubscribe",
+    "ssubscribe",
+    "sunsubscribe",
+    "ping",
+    "quit",
+    "reset",
+];
+
+const OOM_ERROR: &str = "command not allowed when used memory > 'maxmemory'.";
+
 #[derive(Debug)]
-struct RespFrameCodec;
+pub(crate) struct RespFrameCodec;
 
 #[derive(Debug)]
-struct RedisRequest {
+struct RedisRequest<'a> {
     frame: RespFrame,
-    backend: Backend,
+    // the connection's handle, which keeps track of its selected database
+    backend: &'a Backend,
+    // the commands queued since MULTI, None outside of a transaction
+    transaction: &'a mut Option<Transaction>,
 }
 
 #[derive(Debug)]
 struct RedisResponse {
-    frame: RespFrame,
+    // usually a single reply, SUBSCRIBE and UNSUBSCRIBE reply once per channel
+    frames: Vec<RespFrame>,
+    // set by MONITOR, the connection then only streams the commands processed by the server
+    monitor: bool,
+    // set by PSYNC, the connection then streams the dataset to a replica, starting with a copy
+    // of it unless the replica resumes its stream
+    replica: Option<(ReplicaStream, Option<DatasetCopy>)>,
+}
+
+impl RedisResponse {
+    fn reply(frame: RespFrame) -> Self {
+        RedisResponse {
+            frames: vec![frame],
+            monitor: false,
+            replica: None,
+        }
+    }
 }
 
 pub async fn handle_stream(stream: TcpStream, backend: Backend) -> Result<()> {
+    let (peer, local) = addrs(&stream);
+    serve_client(stream, peer, local, None, backend).await
+}
+
+// Like handle_stream, for a connection to the TLS listener: the handshake comes first, a client
+// failing it never shows up in the client list. A client presenting a certificate may be
+// authenticated as the user it names, see tls-auth-clients-user, and is disconnected when that
+// isn't one of tls-auth-clients-users.
+pub async fn handle_tls_stream(
+    stream: TcpStream,
+    acceptor: crate::tls::TlsAcceptor,
+    backend: Backend,
+) -> Result<()> {
+    let (peer, local) = addrs(&stream);
+    // a client stalling its handshake would otherwise hold the connection open forever
+    let handshake = acceptor.accept(stream);
+    let limit = match backend.config().read().tls_handshake_timeout {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    };
+    let stream = match limit {
+        Some(limit) => match tokio::time::timeout(limit, handshake).await {
+            Ok(stream) => stream?,
+            Err(_) => bail!("TLS handshake with {} timed out", peer),
+        },
+        None => handshake.await?,
+    };
+    let user = crate::tls::client_user(stream.get_ref().1, &backend.config().read())?;
+    serve_client(stream, peer, local, user, backend).await
+}
+
+// the addresses of the client and of the server's end of the connection
+fn addrs(stream: &TcpStream) -> (String, String) {
+    let addr = |addr: std::io::Result<SocketAddr>| addr.map_or("?".to_string(), |a| a.to_string());
+    (addr(stream.peer_addr()), addr(stream.local_addr()))
+}
+
+async fn serve_client<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    peer: String,
+    local: String,
+    user: Option<String>,
+    backend: Backend,
+) -> Result<()> {
+    let backend = backend.connect(peer, local);
+    if let Some(user) = user {
+        backend
+            .clients()
+            .update(backend.client_id(), |info| info.user = user);
+    }
+    backend.stats().client_connected();
+    let result = serve_stream(stream, &backend).await;
+    backend.stats().client_disconnected();
+    backend.disconnect();
+    result
+}
+
+async fn serve_stream<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    backend: &Backend,
+) -> Result<()> {
     // how to get a frame from the stream?
     let mut framed = Framed::new(stream, RespFrameCodec);
+    serve_requests(&mut framed, backend).await?;
+    // the replies of the last requests, when the connection stops in the middle of a pipeline
+    SinkExt::<RespFrame>::flush(&mut framed).await?;
+    Ok(())
+}
+
+// Serves the requests of a client until it disconnects, is killed or the server shuts down.
+// Replies are only buffered, and written once no further request is buffered: the replies to a
+// pipeline go out together, in as few writes as the codec needs.
+async fn serve_requests<S: AsyncRead + AsyncWrite + Unpin>(
+    framed: &mut Framed<S, RespFrameCodec>,
+    backend: &Backend,
+) -> Result<()> {
+    let mut shutdown = backend.shutdown_signal();
+    let Some(mut killed) = backend.clients().kill_signal(backend.client_id()) else {
+        return Ok(());
+    };
+    // messages published to the channels the client subscribed to
+    let mut messages = backend.pubsub().attach(backend.client_id());
+    let mut transaction = None;
     loop {
-        match framed.next().await {
+        if !request_buffered(framed.read_buffer()) {
+            SinkExt::<RespFrame>::flush(framed).await?;
+        }
+        // a connection waiting for its next request is closed right away when the server shuts
+        // down or the client is killed, responses already produced are sent first
+        let frame = tokio::select! {
+            biased;
+            _ = killed.changed() => {
+                info!("Closing killed {}", client_label(backend));
+                return Ok(());
+            }
+            _ = shutdown.changed() => return Ok(()),
+            Some(message) = messages.recv() => {
+                framed.feed(backend.pubsub_frame(message)).await?;
+                continue;
+            }
+            frame = framed.next() => frame,
+        };
+        match frame {
             Some(Ok(frame)) => {
-                info!("Received frame: {:?}", frame);
+                info!("Received frame from {}: {:?}", client_label(backend), frame);
+                backend.record_client_interaction();
                 let request = RedisRequest {
                     frame,
-                    backend: backend.clone(),
+                    backend,
+                    transaction: &mut transaction,
+                };
+                // the request goes first so that a client killing itself still gets its reply,
+                // one in flight when the server shuts down gets shutdown-timeout to complete
+                let response = tokio::select! {
+                    biased;
+                    response = handle_request(request) => response?,
+                    _ = killed.changed() => return Ok(()),
+                    _ = drained(backend) => return Ok(()),
                 };
-                let response = handle_request(request).await?;
-                info!("Sending response: {:?}", response.frame);
-                framed.send(response.frame).await?;
+                info!(
+                    "Sending response to {}: {:?}",
+                    client_label(backend),
+                    response.frames
+                );
+                if response.monitor {
+                    let feed = backend.monitors().subscribe();
+                    for frame in response.frames {
+                        framed.send(frame).await?;
+                    }
+                    return serve_monitor(framed, feed, shutdown, killed).await;
+                }
+                if let Some((stream, copy)) = response.replica {
+                    for frame in response.frames {
+                        framed.send(frame).await?;
+                    }
+                    return serve_replica(framed, backend, stream, copy, shutdown, killed).await;
+                }
+                for frame in response.frames {
+                    framed.feed(frame).await?;
+                }
             }
             Some(Err(e)) => return Err(e),
             None => return Ok(()),
@@ -44,12 +211,399 @@ pub async fn handle_stream(stream: TcpStream, backend: Backend) -> Result<()> {
     }
 }
 
-async fn handle_request(request: RedisRequest) -> Result<RedisResponse> {
-    let (frame, backend) = (request.frame, request.backend);
-    let cmd = Command::try_from(frame)?;
+// Completes shutdown-timeout seconds after the server started shutting down.
+async fn drained(backend: &Backend) {
+    let mut shutdown = backend.shutdown_signal();
+    if shutdown
+        .wait_for(|shutting_down| *shutting_down)
+        .await
+        .is_ok()
+    {
+        let timeout = backend.config().read().shutdown_timeout;
+        tokio::time::sleep(Duration::from_secs(timeout)).await;
+    }
+}
+
+// Whether the read buffer holds a whole request, which is decoded without waiting for the client.
+// A malformed one counts too, decoding it fails right away.
+fn request_buffered(buf: &[u8]) -> bool {
+    match RespFrame::expect_length(buf) {
+        Ok(len) => len <= buf.len(),
+        Err(RespError::NotComplete) => false,
+        Err(_) => true,
+    }
+}
+
+// Streams every command processed by the server to a client that issued MONITOR, until it
+// disconnects, is killed or the server shuts down. Requests it sends meanwhile are ignored.
+async fn serve_monitor<S: AsyncRead + AsyncWrite + Unpin>(
+    framed: &mut Framed<S, RespFrameCodec>,
+    mut feed: broadcast::Receiver<String>,
+    mut shutdown: watch::Receiver<bool>,
+    mut killed: watch::Receiver<bool>,
+) -> Result<()> {
+    loop {
+        tokio::select! {
+            biased;
+            _ = killed.changed() => return Ok(()),
+            _ = shutdown.changed() => return Ok(()),
+            line = feed.recv() => match line {
+                Ok(line) => framed.send(RespFrame::from(SimpleString::new(line))).await?,
+                // a monitor too slow to keep up misses the oldest lines
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            frame = framed.next() => match frame {
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e),
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+// Sends a replica the snapshot of the dataset, as the RDB payload of a bulk string without the
+// trailing CRLF, unless it resumes its stream. Then streams it the writes made since until it
+// disconnects, is killed, falls too far behind or the server shuts down. The replica only sends
+// REPLCONF ACK meanwhile.
+async fn serve_replica<S: AsyncRead + AsyncWrite + Unpin>(
+    framed: &mut Framed<S, RespFrameCodec>,
+    backend: &Backend,
+    mut stream: ReplicaStream,
+    copy: Option<DatasetCopy>,
+    mut shutdown: watch::Receiver<bool>,
+    mut killed: watch::Receiver<bool>,
+) -> Result<()> {
+    if let Some(copy) = copy {
+        let payload = tokio::task::spawn_blocking(move || copy.encode()).await?;
+        let mut snapshot = format!("${}\r\n", payload.len()).into_bytes();
+        snapshot.extend_from_slice(&payload);
+        framed.send(snapshot).await?;
+        backend.replication().set_online(backend.client_id());
+    }
+    info!("Synchronized replica {}", client_label(backend));
+    loop {
+        tokio::select! {
+            biased;
+            _ = killed.changed() => return Ok(()),
+            _ = shutdown.changed() => return Ok(()),
+            data = stream.recv() => match data {
+                Some(data) => framed.send(data).await?,
+                None => {
+                    info!("Dropping replica {}, which fell behind", client_label(backend));
+                    return Ok(());
+                }
+            },
+            frame = framed.next() => match frame {
+                Some(Ok(frame)) => {
+                    if let Ok(Command::ReplConf(cmd)) = Command::try_from(frame) {
+                        // replicas aren't replied to
+                        cmd.execute(backend);
+                    }
+                }
+                Some(Err(e)) => return Err(e),
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+async fn handle_request(request: RedisRequest<'_>) -> Result<RedisResponse> {
+    let (frame, backend, transaction) = (request.frame, request.backend, request.transaction);
+    let name = command_name(&frame);
+    // the arguments as monitors see them, taken before parsing consumes the frame
+    let monitored = backend
+        .monitors()
+        .has_monitors()
+        .then(|| command_args(&frame));
+    let write = lookup_command(backend, &name).is_some_and(|spec| spec.flags.contains(&"write"));
+    // and as they are propagated, for writes
+    let logged = (backend.propagating() && write).then(|| command_args(&frame));
+    // and the keys it is on, which a cluster node may not serve and a write locks
+    let keys = if backend.cluster_enabled() || write {
+        command_keys(backend, &name, &command_args(&frame))
+    } else {
+        vec![]
+    };
+    let cmd = match Command::parse(frame, backend) {
+        Ok(cmd) => cmd,
+        // a command that can't be queued fails the whole transaction
+        Err(e) => match transaction.as_mut() {
+            Some(transaction) => return Ok(reject(backend, &name, transaction.abort(e))),
+            None => return Err(e.into()),
+        },
+    };
     info!("Executing command: {:?}", cmd);
-    let frame = cmd.execute(&backend);
-    Ok(RedisResponse { frame })
+    let recognized = !matches!(cmd, Command::Unrecognized(_));
+    let subscribed = backend.subscriber_mode();
+    if recognized && subscribed && !SUBSCRIBER_COMMANDS.contains(&name.as_str()) {
+        let error = SimpleError::new(format!(
+            "ERR Can't execute '{name}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context"
+        ));
+        return Ok(reject(backend, &name, error.into()));
+    }
+    let flags = lookup_command(backend, &name).map_or(&[][..], |spec| spec.flags);
+    // a read only replica only takes writes from its master, and fails a transaction with one
+    if recognized && flags.contains(&"write") && backend.read_only_replica() {
+        let error = SimpleError::new(READONLY_ERROR);
+        let reply = match transaction.as_mut() {
+            Some(transaction) => transaction.reject(error),
+            None => error.into(),
+        };
+        return Ok(reject(backend, &name, reply));
+    }
+    // a cluster node redirects the requests on keys of the slots it doesn't serve, ASKING holds
+    // for the next command, or for the transaction that command is queued in
+    if recognized && backend.cluster_enabled() {
+        let ends_asking = !matches!(cmd, Command::Asking(_))
+            && (transaction.is_none() || matches!(cmd, Command::Exec(_) | Command::Discard(_)));
+        let asking = backend.asking(ends_asking);
+        if let Err(redirect) = backend.cluster_route(&keys, asking) {
+            let error = SimpleError::new(redirect.to_string());
+            let reply = match transaction.as_mut() {
+                Some(transaction) => transaction.reject(error),
+                None => error.into(),
+            };
+            return Ok(reject(backend, &name, reply));
+        }
+    }
+    let cmd = match (transaction.as_mut(), cmd) {
+        // inside MULTI, every command but EXEC and DISCARD is queued
+        (Some(transaction), cmd) if !matches!(cmd, Command::Exec(_) | Command::Discard(_)) => {
+            backend.record_client_command(&name);
+            let reply = transaction.queue(&name, cmd, logged);
+            if let Some(error) = error_message(&reply) {
+                backend.stats().error_replied(error);
+            }
+            return Ok(RedisResponse::reply(reply));
+        }
+        (_, cmd) => cmd,
+    };
+    // time spent blocked waiting for data isn't latency
+    let blocking = matches!(
+        cmd,
+        Command::BLMPop(_)
+            | Command::BPop(_)
+            | Command::BLMove(_)
+            | Command::BZPopMin(_)
+            | Command::XRead(_)
+            | Command::XReadGroup(_)
+            | Command::Wait(_)
+    );
+    // CLIENT PAUSE holds the command back, a pause of all commands includes CLIENT UNPAUSE
+    backend.wait_unpaused(write).await;
+    backend.refresh_db();
+    // admin commands, MONITOR included, are kept from monitors
+    if let Some(args) = monitored.filter(|_| recognized && !flags.contains(&"admin")) {
+        backend.feed_monitors(&args);
+    }
+    let monitor = matches!(cmd, Command::Monitor(_));
+    let mut replica = None;
+    let start = Instant::now();
+    // an Err is the reply of a command refused before it ran
+    let frames = match cmd {
+        Command::BLMPop(cmd) => Ok(vec![cmd.execute_blocking(backend).await]),
+        Command::BPop(cmd) => Ok(vec![cmd.execute_blocking(backend).await]),
+        Command::BLMove(cmd) => Ok(vec![cmd.execute_blocking(backend).await]),
+        Command::BZPopMin(cmd) => Ok(vec![cmd.execute_blocking(backend).await]),
+        Command::XRead(cmd) => Ok(vec![cmd.execute_blocking(backend).await]),
+        Command::XReadGroup(cmd) => Ok(vec![cmd.execute_blocking(backend).await]),
+        Command::Wait(cmd) => Ok(vec![cmd.execute_blocking(backend).await]),
+        // the dataset is copied with no write in between, as of the offset the replica's stream
+        // starts at
+        Command::PSync(cmd) => match backend.exclusive_access().await {
+            Some(_exclusive) => match tokio::task::block_in_place(|| cmd.resync(backend)) {
+                Ok((reply, stream, copy)) => {
+                    replica = Some((stream, copy));
+                    Ok(vec![reply])
+                }
+                Err(reply) => Ok(vec![reply]),
+            },
+            None => Err(busy_error()),
+        },
+        // no other command runs until the target has the keys and they are deleted here
+        Command::Migrate(cmd) => match backend.exclusive_access().await {
+            Some(_exclusive) => Ok(vec![cmd.execute_blocking(backend).await]),
+            None => Err(busy_error()),
+        },
+        // like SCRIPT KILL, nothing a running script does is in their way
+        cmd if flags.contains(&"allow_busy") => {
+            dispatch(cmd, backend, flags, subscribed, transaction)
+        }
+        // EXEC, scripts and functions run alone, for as long as they take without holding up
+        // the tasks of other connections (and the SCRIPT KILL among them), BGSAVE and
+        // BGREWRITEAOF while they freeze the dataset, SAVE and SHUTDOWN while they write it
+        cmd @ (Command::Exec(_)
+        | Command::BgSave(_)
+        | Command::Save(_)
+        | Command::Shutdown(_)
+        | Command::BgRewriteAof(_)
+        | Command::Eval(_)
+        | Command::EvalSha(_)
+        | Command::FCall(_)
+        | Command::FCallRo(_)) => match backend.exclusive_access().await {
+            Some(_exclusive) => tokio::task::block_in_place(|| {
+                dispatch(cmd, backend, flags, subscribed, transaction)
+            }),
+            None => Err(busy_error()),
+        },
+        // other writes run at the same time, but none on the same keys
+        cmd => match backend.shared_access().await {
+            Some(_shared) if write => backend.atomically(&keys, || {
+                dispatch(cmd, backend, flags, subscribed, transaction)
+            }),
+            Some(_shared) => dispatch(cmd, backend, flags, subscribed, transaction),
+            None => Err(busy_error()),
+        },
+    };
+    let frames = match frames {
+        Ok(frames) => frames,
+        Err(error) => return Ok(reject(backend, &name, error)),
+    };
+    let error = frames.iter().find_map(error_message);
+    if recognized {
+        backend
+            .stats()
+            .command_executed(&name, start.elapsed(), error);
+    } else if let Some(error) = error {
+        // an unknown command has no stats of its own, its error still counts
+        backend.stats().error_replied(error);
+    }
+    match frames.as_slice() {
+        [reply] if recognized && write => backend.record_write(reply),
+        _ => {}
+    }
+    if let (Some(args), [reply]) = (logged, frames.as_slice()) {
+        backend.propagate(args, reply);
+        // writes logged before the file was created or once it grew too much start a rewrite
+        if backend.append_log().rewrite_due() {
+            if let Some(_exclusive) = backend.exclusive_access().await {
+                backend.rewrite_append_log();
+            }
+        }
+    }
+    if recognized && !blocking {
+        let event = if flags.contains(&"fast") {
+            "fast-command"
+        } else {
+            "command"
+        };
+        backend.record_latency(event, start.elapsed());
+    }
+    backend.record_client_command(&name);
+    Ok(RedisResponse {
+        frames,
+        monitor,
+        replica,
+    })
+}
+
+// Replies with `error` to the command `name` refused before it ran, which counts as a rejected
+// call.
+fn reject(backend: &Backend, name: &str, error: RespFrame) -> RedisResponse {
+    if let Some(message) = error_message(&error) {
+        backend.stats().command_rejected(name, message);
+    }
+    backend.record_client_command(name);
+    RedisResponse::reply(error)
+}
+
+// Runs a command with the access it needs, and replies to it. Keys are evicted first if the
+// used memory exceeds maxmemory, commands that may add data are refused when that's not enough.
+fn dispatch(
+    cmd: Command,
+    backend: &Backend,
+    flags: &[&str],
+    subscribed: bool,
+    transaction: &mut Option<Transaction>,
+) -> Result<Vec<RespFrame>, RespFrame> {
+    let denyoom = match &cmd {
+        Command::Exec(_) => transaction.as_ref().is_some_and(|t| t.denies_oom(backend)),
+        _ => flags.contains(&"denyoom"),
+    };
+    if !backend.perform_evictions() && denyoom {
+        // the transaction is gone too
+        if transaction.take().is_some() {
+            let error = format!("EXECABORT Transaction discarded because of: {OOM_ERROR}");
+            return Err(SimpleError::new(error).into());
+        }
+        return Err(SimpleError::new(format!("OOM {OOM_ERROR}")).into());
+    }
+    let frames = match cmd {
+        Command::Subscribe(cmd) => cmd.confirmations(backend),
+        Command::Unsubscribe(cmd) => cmd.confirmations(backend),
+        Command::PSubscribe(cmd) => cmd.confirmations(backend),
+        Command::PUnsubscribe(cmd) => cmd.confirmations(backend),
+        Command::SSubscribe(cmd) => cmd.confirmations(backend),
+        Command::SUnsubscribe(cmd) => cmd.confirmations(backend),
+        Command::Ping(cmd) if subscribed => vec![cmd.subscribed_reply()],
+        Command::Multi(cmd) => {
+            *transaction = Some(Transaction::default());
+            vec![cmd.execute(backend)]
+        }
+        Command::Exec(cmd) => match transaction.take() {
+            Some(transaction) => vec![transaction.exec(backend)],
+            None => vec![cmd.execute(backend)],
+        },
+        Command::Discard(cmd) => match transaction.take() {
+            Some(transaction) => vec![transaction.discard()],
+            None => vec![cmd.execute(backend)],
+        },
+        cmd => vec![cmd.execute(backend)],
+    };
+    Ok(frames)
+}
+
+fn busy_error() -> RespFrame {
+    SimpleError::new(
+        "BUSY Redis is busy running a script. You can only call SCRIPT KILL or SHUTDOWN NOSAVE.",
+    )
+    .into()
+}
+
+// how a connection shows up in the logs, its id and the name set with CLIENT SETNAME if any
+fn client_label(backend: &Backend) -> String {
+    let id = backend.client_id();
+    match backend.clients().get(id) {
+        Some(info) if !info.name.is_empty() => format!("client {id} ({})", info.name),
+        _ => format!("client {id}"),
+    }
+}
+
+// the arguments of a request frame, command name included
+pub(crate) fn command_args(frame: &RespFrame) -> Vec<Vec<u8>> {
+    match frame {
+        RespFrame::Array(array) => array
+            .iter()
+            .map(|arg| match arg {
+                RespFrame::BulkString(arg) => arg.to_vec(),
+                arg => arg.clone().encode(),
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+// the keys in the arguments of command `name`, none if they don't hold the keys they should
+fn command_keys(backend: &Backend, name: &str, args: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    let names = args
+        .iter()
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect::<Vec<_>>();
+    let positions = lookup_command(backend, name).and_then(|spec| spec.key_positions(&names));
+    let keys = positions.unwrap_or_default().into_iter();
+    keys.map(|i| args[i].clone()).collect()
+}
+
+// lowercase name of the command in a request frame, empty if there is none
+pub(crate) fn command_name(frame: &RespFrame) -> String {
+    match frame {
+        RespFrame::Array(array) => match array.first() {
+            Some(RespFrame::BulkString(name)) => String::from_utf8_lossy(name).to_ascii_lowercase(),
+            _ => String::new(),
+        },
+        _ => String::new(),
+    }
 }
 
 impl Encoder<RespFrame> for RespFrameCodec {
@@ -62,15 +616,177 @@ impl Encoder<RespFrame> for RespFrameCodec {
     }
 }
 
+// raw bytes, as replicas are sent their snapshot and stream
+impl Encoder<Vec<u8>> for RespFrameCodec {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, item: Vec<u8>, dst: &mut bytes::BytesMut) -> Result<()> {
+        dst.extend_from_slice(&item);
+        Ok(())
+    }
+}
+
 impl Decoder for RespFrameCodec {
     type Item = RespFrame;
     type Error = anyhow::Error;
 
+    // A frame is only decoded once all of it was read, until then what was read of it is kept
+    // for the next reads to complete. Any number of frames may be read at once.
     fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<RespFrame>> {
-        match RespFrame::decode(src) {
-            Ok(frame) => Ok(Some(frame)),
+        match RespFrame::expect_length(src) {
+            Ok(len) if len <= src.len() => Ok(Some(RespFrame::decode(src)?)),
+            // room for the rest of the frame
+            Ok(len) => {
+                src.reserve(len - src.len());
+                Ok(None)
+            }
             Err(RespError::NotComplete) => Ok(None),
             Err(e) => Err(e.into()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, ConfigValues};
+    use std::collections::VecDeque;
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use tokio::io::ReadBuf;
+
+    // A connection a client sends `reads` on, one read each, before closing it unless `open`.
+    // Keeps what the server writes, and how many writes it took.
+    #[derive(Debug, Default)]
+    struct MockStream {
+        reads: VecDeque<Vec<u8>>,
+        open: bool,
+        written: Vec<u8>,
+        writes: usize,
+    }
+
+    impl MockStream {
+        fn new<const N: usize>(reads: [&str; N]) -> Self {
+            Self {
+                reads: reads.iter().map(|read| read.as_bytes().to_vec()).collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    impl AsyncRead for MockStream {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            match self.reads.pop_front() {
+                Some(read) => buf.put_slice(&read),
+                None if self.open => return Poll::Pending,
+                None => {}
+            }
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl AsyncWrite for MockStream {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.written.extend_from_slice(buf);
+            self.writes += 1;
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    // the reply to a BLMPOP on `list` of a client connected to `backend`, which shuts down while
+    // it is blocked and gets `list` pushed to once `pushed_after` elapsed
+    async fn blocked_at_shutdown(backend: &Backend, pushed_after: Duration) -> Result<Vec<u8>> {
+        let client = backend.connect("127.0.0.1:5000".to_string(), "".to_string());
+        let mut stream = MockStream::new([
+            "*5\r\n$6\r\nBLMPOP\r\n$1\r\n0\r\n$1\r\n1\r\n$4\r\nlist\r\n$4\r\nLEFT\r\n",
+        ]);
+        stream.open = true;
+        let serving = serve_stream(&mut stream, &client);
+        let pushing = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            backend.shutdown();
+            tokio::time::sleep(pushed_after).await;
+            let _ = backend.rpush(b"list".to_vec(), [BulkString::from("v").into()]);
+        };
+        let (served, _) = tokio::join!(serving, pushing);
+        served?;
+        Ok(stream.written)
+    }
+
+    async fn serve(stream: &mut MockStream) -> Result<()> {
+        let backend = Backend::new().connect("127.0.0.1:5000".to_string(), "".to_string());
+        serve_stream(stream, &backend).await
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_replies() -> Result<()> {
+        let mut stream = MockStream::new([
+            "*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$1\r\nv\r\n*2\r\n$3\r\nGET\r\n$1\r\nk\r\n*1\r\n$4\r\nPING\r\n",
+            "*2\r\n$4\r\nECHO\r\n$2\r\nhi\r\n",
+        ]);
+        serve(&mut stream).await?;
+        assert_eq!(stream.written, b"+OK\r\n$1\r\nv\r\n+PONG\r\n$2\r\nhi\r\n");
+        // one write for the pipeline of the first read, one for the request of the second
+        assert_eq!(stream.writes, 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fragmented_requests() -> Result<()> {
+        let requests = "*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$10\r\nsplit\r\nval\r\n\
+             *2\r\n$3\r\nGET\r\n$1\r\nk\r\n*1\r\n$4\r\nPING\r\n";
+        let replies = b"+OK\r\n$10\r\nsplit\r\nval\r\n+PONG\r\n";
+        // split in two at every position
+        for at in 1..requests.len() {
+            let (first, second) = requests.split_at(at);
+            let mut stream = MockStream::new([first, second]);
+            serve(&mut stream).await?;
+            assert_eq!(stream.written, replies, "split at {at}");
+        }
+        // one byte per read
+        let bytes = (0..requests.len())
+            .map(|i| &requests[i..i + 1])
+            .collect::<Vec<_>>();
+        let mut stream = MockStream {
+            reads: bytes.iter().map(|b| b.as_bytes().to_vec()).collect(),
+            ..Default::default()
+        };
+        serve(&mut stream).await?;
+        assert_eq!(stream.written, replies);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drain() -> Result<()> {
+        // a blocked client still gets its reply when it comes within shutdown-timeout
+        let backend = Backend::new();
+        let written = blocked_at_shutdown(&backend, Duration::from_millis(20)).await?;
+        assert_eq!(written, b"*2\r\n$4\r\nlist\r\n*1\r\n$1\r\nv\r\n");
+
+        // otherwise it's disconnected without one
+        let backend = Backend::with_config(ConfigValues {
+            shutdown_timeout: 0,
+            ..Default::default()
+        });
+        let written = blocked_at_shutdown(&backend, Duration::from_millis(20)).await?;
+        assert_eq!(written, b"");
+        Ok(())
+    }
+}
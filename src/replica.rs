@@ -0,0 +1,402 @@
+use crate::{
+    cmd::{Command, CommandExecutor},
+    network::{command_args, command_name},
+    Backend, BulkString, RespArray, RespDecoder, RespEncoder, RespError, RespFrame,
+};
+use anyhow::{bail, Result};
+use bytes::{Buf, BytesMut};
+use futures::SinkExt;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_stream::StreamExt;
+use tokio_util::codec::{Decoder, Encoder, Framed};
+use tracing::{info, warn};
+
+// how long to wait before connecting to the master again once the link failed
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+// how often a replica tells its master the offset it processed
+const ACK_INTERVAL: Duration = Duration::from_secs(1);
+
+// What a master sends its replica: replies during the handshake, the snapshot after a full
+// resynchronization, then the stream of commands, each frame with the bytes it took.
+#[derive(Debug)]
+enum MasterMessage {
+    Frame(RespFrame, Vec<u8>),
+    Snapshot(Vec<u8>),
+}
+
+#[derive(Debug, Default)]
+struct MasterCodec {
+    // set once the master accepted PSYNC, the snapshot comes next
+    snapshot_next: bool,
+}
+
+// Keeps the server in sync with the master REPLICAOF set, connecting again whenever the link
+// fails, until the server shuts down.
+pub async fn follow_master(backend: Backend) {
+    let mut master = backend.replication().watch_master();
+    let mut shutdown = backend.shutdown_signal();
+    loop {
+        let target = master.borrow_and_update().clone();
+        let Some((host, port)) = target else {
+            tokio::select! {
+                _ = master.changed() => continue,
+                _ = shutdown.changed() => return,
+            }
+        };
+        // the master shows up among the clients, it runs the commands of its stream
+        let link = backend.connect(format!("{host}:{port}"), String::new());
+        // the link to a master REPLICAOF replaced is dropped before anything else it received
+        // is applied
+        let stop = tokio::select! {
+            biased;
+            _ = shutdown.changed() => true,
+            _ = master.changed() => false,
+            _ = follow(&link, &host, port) => false,
+        };
+        link.disconnect();
+        if stop {
+            return;
+        }
+    }
+}
+
+async fn follow(link: &Backend, host: &str, port: u16) {
+    loop {
+        link.replication().set_link_state("connecting");
+        if let Err(e) = sync(link, host, port).await {
+            warn!("Lost the link to master {}:{}: {:?}", host, port, e);
+        }
+        link.replication().set_link_state("connect");
+        tokio::time::sleep(RECONNECT_INTERVAL).await;
+    }
+}
+
+// Connects to the master, resumes its stream where the replica left it or replaces the dataset
+// with its snapshot, then applies the stream until the link fails.
+async fn sync(link: &Backend, host: &str, port: u16) -> Result<()> {
+    let stream = TcpStream::connect((host, port)).await?;
+    let mut framed = Framed::new(stream, MasterCodec::default());
+    info!("Connected to master {}:{}", host, port);
+    let listening_port = link.config().read().port.to_string();
+    request(&mut framed, &["PING"]).await?;
+    request(
+        &mut framed,
+        &["REPLCONF", "listening-port", &listening_port],
+    )
+    .await?;
+    request(&mut framed, &["REPLCONF", "capa", "psync2"]).await?;
+    // a server that never streamed has no history to resume
+    let replication = link.replication();
+    let (replid, psync_offset) = match replication.is_active() {
+        true => (replication.replid(), (replication.offset() + 1).to_string()),
+        false => ("?".to_string(), "-1".to_string()),
+    };
+    let reply = request(&mut framed, &["PSYNC", &replid, &psync_offset]).await?;
+    let RespFrame::SimpleString(reply) = reply else {
+        bail!("unexpected reply to PSYNC: {:?}", reply);
+    };
+    let mut parts = reply.0.split(' ');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("FULLRESYNC"), Some(replid), Some(offset)) => {
+            let offset = offset.parse()?;
+            full_sync(link, &mut framed, replid.to_string(), offset).await?;
+            info!(
+                "Loaded the snapshot of master {}:{} at offset {}",
+                host, port, offset
+            );
+        }
+        (Some("CONTINUE"), replid, None) => {
+            let replid = replid.map_or_else(|| replication.replid(), str::to_string);
+            replication.partial_synced(replid, link.now_ms());
+            info!(
+                "Resumed the stream of master {}:{} at offset {}",
+                host,
+                port,
+                replication.offset() + 1
+            );
+        }
+        _ => bail!("unexpected reply to PSYNC: {:?}", reply),
+    }
+
+    let mut ack = tokio::time::interval(ACK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = ack.tick() => send_ack(&mut framed, replication.offset()).await?,
+            message = framed.next() => match message {
+                Some(Ok(MasterMessage::Frame(frame, raw))) => {
+                    apply(link, &mut framed, frame).await?;
+                    let backlog_size = link.config().read().repl_backlog_size as usize;
+                    replication.feed_raw(raw, backlog_size, link.now_ms());
+                }
+                Some(Ok(MasterMessage::Snapshot(_))) => bail!("unexpected snapshot"),
+                Some(Err(e)) => return Err(e),
+                None => bail!("the master closed the connection"),
+            },
+        }
+    }
+}
+
+// Replaces the dataset with the snapshot the master sends after FULLRESYNC, and takes on the
+// history `replid` of the master at `offset`.
+async fn full_sync(
+    link: &Backend,
+    framed: &mut Framed<TcpStream, MasterCodec>,
+    replid: String,
+    offset: u64,
+) -> Result<()> {
+    link.replication().set_link_state("sync");
+    framed.codec_mut().snapshot_next = true;
+    let snapshot = match framed.next().await {
+        Some(Ok(MasterMessage::Snapshot(snapshot))) => snapshot,
+        Some(Ok(MasterMessage::Frame(frame, _))) => bail!("expected a snapshot, got {:?}", frame),
+        Some(Err(e)) => return Err(e),
+        None => bail!("the master closed the connection"),
+    };
+    // clients see the old dataset or the new one, never part of both
+    let Some(exclusive) = link.exclusive_access().await else {
+        bail!("a busy script is in the way of loading the snapshot");
+    };
+    tokio::task::block_in_place(|| {
+        link.flush_all(true);
+        link.functions().flush();
+        link.load_snapshot(&snapshot)
+    })?;
+    drop(exclusive);
+    // the master selects a database before the first command that needs one
+    link.select(0);
+    link.replication()
+        .full_synced(replid, offset, link.now_ms());
+    Ok(())
+}
+
+// Sends a command of the handshake and returns the reply, unless it is an error.
+async fn request(framed: &mut Framed<TcpStream, MasterCodec>, args: &[&str]) -> Result<RespFrame> {
+    framed.send(command(args)).await?;
+    match framed.next().await {
+        Some(Ok(MasterMessage::Frame(RespFrame::Error(e), _))) => {
+            bail!("{} replied {}", args[0], e.0)
+        }
+        Some(Ok(MasterMessage::Frame(reply, _))) => Ok(reply),
+        Some(Ok(MasterMessage::Snapshot(_))) => bail!("unexpected snapshot"),
+        Some(Err(e)) => Err(e),
+        None => bail!("the master closed the connection"),
+    }
+}
+
+async fn send_ack(framed: &mut Framed<TcpStream, MasterCodec>, offset: u64) -> Result<()> {
+    framed
+        .send(command(&["REPLCONF", "ACK", &offset.to_string()]))
+        .await
+}
+
+// Runs a command of the master's stream. Writes are logged to the append only file, the
+// replicas of this server are passed on the stream as is, see Replication::feed_raw.
+async fn apply(
+    link: &Backend,
+    framed: &mut Framed<TcpStream, MasterCodec>,
+    frame: RespFrame,
+) -> Result<()> {
+    let name = command_name(&frame);
+    let args = command_args(&frame);
+    match name.as_str() {
+        // the master asks for the offset, before the request itself
+        "replconf" => {
+            if args
+                .get(1)
+                .is_some_and(|arg| arg.eq_ignore_ascii_case(b"getack"))
+            {
+                send_ack(framed, link.replication().offset()).await?;
+            }
+            return Ok(());
+        }
+        "ping" => return Ok(()),
+        _ => {}
+    }
+    let cmd = match Command::parse(frame, link) {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            warn!("Skipping a command of the master's stream: {}", e);
+            return Ok(());
+        }
+    };
+    let Some(_shared) = link.shared_access().await else {
+        bail!("a busy script is in the way of the master's stream");
+    };
+    link.refresh_db();
+    let reply = cmd.execute(link);
+    if link.propagating() {
+        link.propagate(args, &reply);
+    }
+    Ok(())
+}
+
+// the request frame of a command sent to another server
+pub(crate) fn command(args: &[&str]) -> RespFrame {
+    let args = args.iter().map(|arg| BulkString::from(*arg).into());
+    RespArray::new(args.collect::<Vec<_>>()).into()
+}
+
+impl Encoder<RespFrame> for MasterCodec {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, item: RespFrame, dst: &mut BytesMut) -> Result<()> {
+        dst.extend_from_slice(&item.encode());
+        Ok(())
+    }
+}
+
+impl Decoder for MasterCodec {
+    type Item = MasterMessage;
+    type Error = anyhow::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<MasterMessage>> {
+        if self.snapshot_next {
+            // newlines keep the link alive while the master prepares the snapshot
+            while src.first() == Some(&b'\n') {
+                src.advance(1);
+            }
+            let Some(end) = src.windows(2).position(|w| w == b"\r\n") else {
+                return Ok(None);
+            };
+            if src[0] != b'$' {
+                bail!("expected a snapshot");
+            }
+            let len: usize = std::str::from_utf8(&src[1..end])?.parse()?;
+            // the payload isn't followed by CRLF like a bulk string
+            if src.len() < end + 2 + len {
+                src.reserve(end + 2 + len - src.len());
+                return Ok(None);
+            }
+            src.advance(end + 2);
+            self.snapshot_next = false;
+            return Ok(Some(MasterMessage::Snapshot(src.split_to(len).to_vec())));
+        }
+        let len = match RespFrame::expect_length(src) {
+            Ok(len) if len <= src.len() => len,
+            Ok(_) | Err(RespError::NotComplete) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let raw = src.split_to(len);
+        let frame = RespFrame::decode(&mut raw.clone())?;
+        Ok(Some(MasterMessage::Frame(frame, raw.to_vec())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{network, SimpleString};
+    use anyhow::Result;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_master_codec() -> Result<()> {
+        let mut codec = MasterCodec::default();
+        let mut buf = BytesMut::from(&b"+FULLRESYNC abc 0\r\n\n$5\r\nhel"[..]);
+        assert!(matches!(
+            codec.decode(&mut buf)?,
+            Some(MasterMessage::Frame(RespFrame::SimpleString(_), raw)) if raw.len() == 19
+        ));
+        codec.snapshot_next = true;
+        assert!(codec.decode(&mut buf)?.is_none());
+        buf.extend_from_slice(b"lo*1\r\n$4\r\nPING\r\n");
+        match codec.decode(&mut buf)? {
+            Some(MasterMessage::Snapshot(snapshot)) => assert_eq!(snapshot, b"hello"),
+            message => panic!("expected the snapshot, got {message:?}"),
+        }
+        match codec.decode(&mut buf)? {
+            Some(MasterMessage::Frame(RespFrame::Array(_), raw)) => {
+                assert_eq!(raw, b"*1\r\n$4\r\nPING\r\n")
+            }
+            message => panic!("expected PING, got {message:?}"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_follow_master() -> Result<()> {
+        let master = Backend::new();
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let accepting = master.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(network::handle_stream(stream, accepting.clone()));
+            }
+        });
+        master.set(b"before".to_vec(), BulkString::from("1").into());
+
+        let replica = Backend::new();
+        replica.set(b"stale".to_vec(), BulkString::from("1").into());
+        tokio::spawn(follow_master(replica.clone()));
+        assert!(replica
+            .replication()
+            .set_master(Some(("127.0.0.1".to_string(), port))));
+        assert!(!replica
+            .replication()
+            .set_master(Some(("127.0.0.1".to_string(), port))));
+
+        let synced = |replica: &Backend| {
+            let info = replica.replication().master_info(replica.now_ms());
+            info.is_some_and(|info| info.state == "connected")
+        };
+        wait_until(|| synced(&replica)).await;
+        assert_eq!(replica.get(b"stale")?, None);
+        assert_eq!(replica.get(b"before")?, Some(BulkString::from("1").into()));
+        assert_eq!(
+            replica.replication().replid(),
+            master.replication().replid()
+        );
+        assert!(replica.read_only_replica());
+
+        // writes made on the master from now on are streamed
+        set(&master, "after", "2");
+        wait_until(|| replica.get(b"after").is_ok_and(|v| v.is_some())).await;
+        assert_eq!(
+            replica.replication().offset(),
+            master.replication().offset()
+        );
+
+        // a replica that lost its link resumes the stream, it misses nothing and keeps its data
+        replica.set(b"local".to_vec(), BulkString::from("1").into());
+        let psync = master.clients().list().into_iter();
+        for client in psync.filter(|client| client.last_command == "psync") {
+            master.clients().kill(client.id);
+        }
+        wait_until(|| !synced(&replica)).await;
+        set(&master, "missed", "3");
+        wait_until(|| replica.get(b"missed").is_ok_and(|v| v.is_some())).await;
+        assert_eq!(replica.get(b"local")?, Some(BulkString::from("1").into()));
+        assert_eq!(
+            replica.replication().offset(),
+            master.replication().offset()
+        );
+
+        // a promoted replica starts its own history, which continues the one of its master
+        let replid = master.replication().replid();
+        replica.replication().set_master(None);
+        assert!(!replica.read_only_replica());
+        let offset = replica.replication().offset();
+        assert_eq!(replica.replication().replid2(), Some((replid, offset + 1)));
+        master.shutdown();
+        replica.shutdown();
+        Ok(())
+    }
+
+    fn set(master: &Backend, key: &str, value: &str) {
+        master.set(key.as_bytes().to_vec(), BulkString::from(value).into());
+        let args = vec![b"SET".to_vec(), key.into(), value.into()];
+        master.propagate(args, &SimpleString::new("OK").into());
+    }
+
+    async fn wait_until(condition: impl Fn() -> bool) {
+        for _ in 0..300 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("timed out");
+    }
+}
@@ -38,7 +38,6 @@ impl RespEncoder for RespArray {
 // - array: "*<number-of-elements>\r\n<element-1>...<element-n>"
 // - null array: "*-1\r\n"
 // - "*2\r\n$3\r\nget\r\n$5\r\nhello\r\n"
-// FIXME: need to handle incomplete
 impl RespDecoder for RespArray {
     const PREFIX: &'static str = "*";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
@@ -65,6 +64,9 @@ impl RespDecoder for RespArray {
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        if buf.starts_with(NULL_ARRAY) {
+            return Ok(NULL_ARRAY.len());
+        }
         let (end, len) = parse_length(buf, Self::PREFIX)?;
         calc_total_length(buf, end, len, Self::PREFIX)
     }
@@ -96,6 +98,14 @@ mod tests {
         buf.extend_from_slice(NULL_ARRAY);
         let frame = RespArray::decode(&mut buf)?;
         assert_eq!(frame, RespArray::new(vec![]));
+
+        // nested in another array
+        buf.extend_from_slice(b"*2\r\n*-1\r\n:1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            RespArray::new([RespArray::new(vec![]).into(), 1.into()])
+        );
         Ok(())
     }
 
@@ -154,6 +164,12 @@ mod tests {
         let ret = calc_total_length(buf, end, len, "*");
         assert_eq!(ret.unwrap_err(), RespError::NotComplete);
 
+        // an element only partly there
+        let buf = b"*2\r\n$3\r\nset\r\n$5\r\nhel";
+        let (end, len) = parse_length(buf, "*")?;
+        let ret = calc_total_length(buf, end, len, "*");
+        assert_eq!(ret.unwrap_err(), RespError::NotComplete);
+
         Ok(())
     }
 }
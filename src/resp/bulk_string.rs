@@ -15,13 +15,9 @@ impl BulkString {
 }
 
 // - bulk string: "$<length>\r\n<data>\r\n"
-// - null bulk string: "$-1\r\n"
+// an empty one is "$0\r\n\r\n", nulls are RespNull
 impl RespEncoder for BulkString {
     fn encode(self) -> Vec<u8> {
-        if self.is_empty() {
-            return NULL_BULK_STRING.to_vec();
-        }
-
         let mut buf = Vec::with_capacity(self.len() + 16);
         buf.extend_from_slice(&format!("${}\r\n", self.len()).into_bytes());
         buf.extend_from_slice(&self.0);
@@ -71,6 +67,12 @@ impl From<&[u8]> for BulkString {
     }
 }
 
+impl From<Vec<u8>> for BulkString {
+    fn from(s: Vec<u8>) -> Self {
+        BulkString(s)
+    }
+}
+
 impl From<String> for BulkString {
     fn from(s: String) -> Self {
         BulkString(s.into_bytes())
@@ -104,9 +106,9 @@ mod tests {
     use anyhow::Result;
 
     #[test]
-    fn test_encode_null_bulk_string() {
+    fn test_encode_empty_bulk_string() {
         let frame: RespFrame = b"".into();
-        assert_eq!(frame.encode(), NULL_BULK_STRING.to_vec());
+        assert_eq!(frame.encode(), b"$0\r\n\r\n".to_vec());
     }
 
     #[test]
@@ -2,8 +2,8 @@ use bytes::BytesMut;
 use enum_dispatch::enum_dispatch;
 
 use super::{
-    BulkString, RespArray, RespDecoder, RespError, RespMap, RespNull, RespSet, SimpleError,
-    SimpleString,
+    BulkString, RespArray, RespDecoder, RespError, RespMap, RespNull, RespPush, RespSet,
+    SimpleError, SimpleString, VerbatimString,
 };
 
 #[enum_dispatch(RespEncoder)]
@@ -19,6 +19,8 @@ pub enum RespFrame {
     Double(f64),
     Map(RespMap),
     Set(RespSet),
+    VerbatimString(VerbatimString),
+    Push(RespPush),
 }
 
 impl RespDecoder for RespFrame {
@@ -66,6 +68,14 @@ impl RespDecoder for RespFrame {
                 let frame = RespSet::decode(buf)?;
                 Ok(frame.into())
             }
+            Some(b'=') => {
+                let frame = VerbatimString::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'>') => {
+                let frame = RespPush::decode(buf)?;
+                Ok(frame.into())
+            }
             None => Err(RespError::NotComplete),
             _ => Err(RespError::InvalidFrameType(format!(
                 "expect_length: unknown frame type: {:?}",
@@ -87,6 +97,8 @@ impl RespDecoder for RespFrame {
             Some(b'#') => bool::expect_length(buf),
             Some(b',') => f64::expect_length(buf),
             Some(b'_') => RespNull::expect_length(buf),
+            Some(b'=') => VerbatimString::expect_length(buf),
+            Some(b'>') => RespPush::expect_length(buf),
             _ => Err(RespError::NotComplete),
         }
     }
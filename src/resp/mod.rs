@@ -14,6 +14,8 @@
     - double: ",[<+|->]<integral>[.<fractional>][<E|e>[sign]<exponent>]\r\n"
     - map: "%<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>"
     - set: "~<number-of-elements>\r\n<element-1>...<element-n>"
+    - verbatim string: "=<length>\r\n<format>:<data>\r\n"
+    - push: "><number-of-elements>\r\n<element-1>...<element-n>"
  */
 
 mod array;
@@ -24,9 +26,11 @@ mod frame;
 mod integer;
 mod map;
 mod null;
+mod push;
 mod set;
 mod simple_error;
 mod simple_string;
+mod verbatim_string;
 
 use bytes::{Buf, BytesMut};
 use enum_dispatch::enum_dispatch;
@@ -34,7 +38,8 @@ use thiserror::Error;
 
 pub use self::{
     array::RespArray, bulk_string::BulkString, frame::RespFrame, map::RespMap, null::RespNull,
-    set::RespSet, simple_error::SimpleError, simple_string::SimpleString,
+    push::RespPush, set::RespSet, simple_error::SimpleError, simple_string::SimpleString,
+    verbatim_string::VerbatimString,
 };
 
 const BUFFER_CAP: usize = 4096;
@@ -132,11 +137,12 @@ fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result
     let mut total = end + CRLF_LEN;
     let mut data = &buf[total..];
     match prefix {
-        "*" | "~" => {
-            // find nth CRLF in the buffer, for array and set, we need to find 1 CRLF for each element
+        "*" | "~" | ">" => {
+            // find nth CRLF in the buffer, for array, set and push, we need to find 1 CRLF for each element
             for _ in 0..len {
                 let len = RespFrame::expect_length(data)?;
-                data = &data[len..];
+                // the element is only partly there
+                data = data.get(len..).ok_or(RespError::NotComplete)?;
                 total += len;
             }
             Ok(total)
@@ -146,11 +152,11 @@ fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result
             for _ in 0..len {
                 let len = SimpleString::expect_length(data)?;
 
-                data = &data[len..];
+                data = data.get(len..).ok_or(RespError::NotComplete)?;
                 total += len;
 
                 let len = RespFrame::expect_length(data)?;
-                data = &data[len..];
+                data = data.get(len..).ok_or(RespError::NotComplete)?;
                 total += len;
             }
             Ok(total)
@@ -0,0 +1,121 @@
+use bytes::{Buf, BytesMut};
+use std::ops::Deref;
+
+use super::{parse_length, RespDecoder, RespEncoder, RespError, CRLF_LEN};
+
+// separates the format from the data, after the 3 bytes of the format
+const FORMAT_LEN: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
+pub struct VerbatimString {
+    // "txt" for plain text, "mkd" for markdown
+    pub(crate) format: [u8; FORMAT_LEN],
+    pub(crate) data: Vec<u8>,
+}
+
+impl VerbatimString {
+    pub fn new(format: [u8; FORMAT_LEN], data: impl Into<Vec<u8>>) -> Self {
+        VerbatimString {
+            format,
+            data: data.into(),
+        }
+    }
+
+    // plain text, meant to be shown as is
+    pub fn text(data: impl Into<Vec<u8>>) -> Self {
+        Self::new(*b"txt", data)
+    }
+
+    pub fn format(&self) -> &[u8] {
+        &self.format
+    }
+}
+
+// - verbatim string: "=<length>\r\n<format>:<data>\r\n", the length counting the format and colon
+impl RespEncoder for VerbatimString {
+    fn encode(self) -> Vec<u8> {
+        let len = FORMAT_LEN + 1 + self.data.len();
+        let mut buf = Vec::with_capacity(len + 16);
+        buf.extend_from_slice(&format!("={}\r\n", len).into_bytes());
+        buf.extend_from_slice(&self.format);
+        buf.push(b':');
+        buf.extend_from_slice(&self.data);
+        buf.extend_from_slice(b"\r\n");
+        buf
+    }
+}
+
+impl RespDecoder for VerbatimString {
+    const PREFIX: &'static str = "=";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let remained = &buf[end + CRLF_LEN..];
+        if remained.len() < len + CRLF_LEN {
+            return Err(RespError::NotComplete);
+        }
+        if len <= FORMAT_LEN || remained[FORMAT_LEN] != b':' {
+            return Err(RespError::InvalidFrame(format!(
+                "expect: VerbatimString(<format>:<data>), got: {:?}",
+                &remained[..len]
+            )));
+        }
+
+        buf.advance(end + CRLF_LEN);
+
+        let data = buf.split_to(len + CRLF_LEN);
+        let mut format = [0; FORMAT_LEN];
+        format.copy_from_slice(&data[..FORMAT_LEN]);
+        Ok(VerbatimString::new(format, &data[FORMAT_LEN + 1..len]))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let total = end + CRLF_LEN + len + CRLF_LEN;
+        if buf.len() < total {
+            return Err(RespError::NotComplete);
+        }
+        Ok(total)
+    }
+}
+
+impl Deref for VerbatimString {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespFrame;
+    use anyhow::Result;
+
+    #[test]
+    fn test_encode_verbatim_string() {
+        let frame: RespFrame = VerbatimString::text("Some string").into();
+        assert_eq!(frame.encode(), b"=15\r\ntxt:Some string\r\n".to_vec());
+    }
+
+    #[test]
+    fn test_decode_verbatim_string() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"=15\r\ntxt:Some string\r\n");
+        assert_eq!(VerbatimString::expect_length(&buf)?, buf.len());
+
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(frame, VerbatimString::text("Some string").into());
+        assert!(buf.is_empty());
+
+        buf.extend_from_slice(b"=15\r\ntxt:Some");
+        let ret = VerbatimString::decode(&mut buf);
+        assert_eq!(ret.unwrap_err(), RespError::NotComplete);
+
+        buf.clear();
+        buf.extend_from_slice(b"=5\r\nhello\r\n");
+        let ret = VerbatimString::decode(&mut buf);
+        assert!(matches!(ret, Err(RespError::InvalidFrame(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+use crate::ConfigValues;
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig as TlsConfig, ServerConnection};
+pub use tokio_rustls::TlsAcceptor;
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+// The acceptor of the TLS listener, presenting the certificate of tls-cert-file and
+// tls-key-file. Depending on tls-auth-clients, a client has to or may present a certificate of
+// its own, which has to be signed by one of the CAs of tls-ca-cert-file.
+pub fn acceptor(config: &ConfigValues) -> Result<TlsAcceptor> {
+    let provider = Arc::new(ring::default_provider());
+    let builder =
+        TlsConfig::builder_with_provider(provider.clone()).with_safe_default_protocol_versions()?;
+    let builder = match config.tls_auth_clients.as_str() {
+        "yes" if config.tls_ca_cert_file.is_empty() => {
+            bail!("tls-auth-clients yes requires tls-ca-cert-file")
+        }
+        "yes" | "optional" if !config.tls_ca_cert_file.is_empty() => {
+            let mut roots = RootCertStore::empty();
+            for cert in certs(&config.tls_ca_cert_file)? {
+                roots.add(cert)?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            let verifier = match config.tls_auth_clients.as_str() {
+                "yes" => verifier.build()?,
+                _ => verifier.allow_unauthenticated().build()?,
+            };
+            builder.with_client_cert_verifier(verifier)
+        }
+        _ => builder.with_no_client_auth(),
+    };
+    let tls = builder.with_single_cert(
+        certs(&config.tls_cert_file)?,
+        private_key(&config.tls_key_file)?,
+    )?;
+    Ok(TlsAcceptor::from(Arc::new(tls)))
+}
+
+// The user a client is authenticated as by the certificate it presented, following
+// tls-auth-clients-user, None when it presented none or tls-auth-clients-user is off. Fails
+// when the certificate names no user, or one missing from tls-auth-clients-users.
+pub fn client_user(connection: &ServerConnection, config: &ConfigValues) -> Result<Option<String>> {
+    let Some(der) = connection
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+    else {
+        return Ok(None);
+    };
+    let mode = config.tls_auth_clients_user.as_str();
+    if mode != "cn" && mode != "san" {
+        return Ok(None);
+    }
+    let (_, cert) = X509Certificate::from_der(der)?;
+    let user = match mode {
+        "cn" => cert
+            .subject()
+            .iter_common_name()
+            .find_map(|cn| cn.as_str().ok().map(str::to_string)),
+        _ => cert.subject_alternative_name()?.and_then(|san| {
+            san.value.general_names.iter().find_map(|name| match name {
+                GeneralName::DNSName(name) => Some(name.to_string()),
+                _ => None,
+            })
+        }),
+    };
+    match user {
+        Some(user) if config.tls_auth_clients_users.contains(&user) => Ok(Some(user)),
+        Some(user) => bail!("the client certificate names unknown user {user:?}"),
+        None => bail!("the client certificate names no user"),
+    }
+}
+
+// the certificates of a PEM file
+fn certs(path: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let file = File::open(path).with_context(|| format!("can't open {path:?}"))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file)).collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        bail!("no certificate in {path:?}");
+    }
+    Ok(certs)
+}
+
+// the first private key of a PEM file
+fn private_key(path: &str) -> Result<PrivateKeyDer<'static>> {
+    let file = File::open(path).with_context(|| format!("can't open {path:?}"))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))?
+        .ok_or_else(|| anyhow!("no private key in {path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{network, replica::command, Backend, RespFrame, SimpleString};
+    use futures::SinkExt;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_rustls::client::TlsStream;
+    use tokio_rustls::rustls::pki_types::ServerName;
+    use tokio_rustls::rustls::ClientConfig;
+    use tokio_rustls::TlsConnector;
+    use tokio_stream::StreamExt;
+    use tokio_util::codec::Framed;
+
+    type Connection = Framed<TlsStream<TcpStream>, network::RespFrameCodec>;
+
+    // the certificates of the tests, all signed by the CA of ca.crt
+    fn fixture(name: &str) -> String {
+        format!("{}/testdata/tls/{name}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn tls_config() -> ConfigValues {
+        ConfigValues {
+            tls_cert_file: fixture("server.crt"),
+            tls_key_file: fixture("server.key"),
+            tls_ca_cert_file: fixture("ca.crt"),
+            ..Default::default()
+        }
+    }
+
+    // a TLS listener serving every connection with `config`
+    async fn serve(config: ConfigValues) -> Result<SocketAddr> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let acceptor = acceptor(&config)?;
+        let backend = Backend::with_config(config);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (acceptor, backend) = (acceptor.clone(), backend.clone());
+                tokio::spawn(network::handle_tls_stream(stream, acceptor, backend));
+            }
+        });
+        Ok(addr)
+    }
+
+    // a connection presenting client.crt when `authenticated`
+    async fn connect(addr: SocketAddr, authenticated: bool) -> Result<Connection> {
+        let mut roots = RootCertStore::empty();
+        roots.add(certs(&fixture("ca.crt"))?.remove(0))?;
+        let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots);
+        let config = if authenticated {
+            builder.with_client_auth_cert(
+                certs(&fixture("client.crt"))?,
+                private_key(&fixture("client.key"))?,
+            )?
+        } else {
+            builder.with_no_client_auth()
+        };
+        let stream = TcpStream::connect(addr).await?;
+        let stream = TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from("localhost")?, stream)
+            .await?;
+        Ok(Framed::new(stream, network::RespFrameCodec))
+    }
+
+    async fn request(framed: &mut Connection, args: &[&str]) -> Result<Option<RespFrame>> {
+        framed.send(command(args)).await?;
+        framed.next().await.transpose()
+    }
+
+    // the user of the connection, as reported by CLIENT INFO
+    async fn user(framed: &mut Connection) -> Result<Option<String>> {
+        let Some(RespFrame::BulkString(info)) = request(framed, &["CLIENT", "INFO"]).await? else {
+            return Ok(None);
+        };
+        let info = String::from_utf8_lossy(&info);
+        Ok(info
+            .split_whitespace()
+            .find_map(|field| field.strip_prefix("user="))
+            .map(str::to_string))
+    }
+
+    #[test]
+    fn test_acceptor() {
+        assert!(acceptor(&tls_config()).is_ok());
+        let config = ConfigValues {
+            tls_key_file: fixture("server.crt"),
+            ..tls_config()
+        };
+        let error = acceptor(&config).err().map(|e| e.to_string());
+        assert!(error.is_some_and(|e| e.starts_with("no private key in")));
+        assert!(acceptor(&ConfigValues::default()).is_err());
+
+        let config = ConfigValues {
+            tls_auth_clients: "yes".to_string(),
+            tls_ca_cert_file: String::new(),
+            ..tls_config()
+        };
+        let error = acceptor(&config).err().map(|e| e.to_string());
+        assert_eq!(
+            error.as_deref(),
+            Some("tls-auth-clients yes requires tls-ca-cert-file")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tls_connection() -> Result<()> {
+        let addr = serve(tls_config()).await?;
+        let mut framed = connect(addr, false).await?;
+        let reply = request(&mut framed, &["PING"]).await?;
+        assert_eq!(
+            reply,
+            Some(RespFrame::SimpleString(SimpleString::new("PONG")))
+        );
+        assert_eq!(user(&mut framed).await?.as_deref(), Some("default"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_handshake_timeout() -> Result<()> {
+        let addr = serve(ConfigValues {
+            tls_handshake_timeout: 100,
+            ..tls_config()
+        })
+        .await?;
+        // a client that connects and never starts the handshake is disconnected
+        let stream = TcpStream::connect(addr).await?;
+        let mut framed = Framed::new(stream, network::RespFrameCodec);
+        let closed = tokio::time::timeout(Duration::from_secs(5), framed.next()).await;
+        assert!(matches!(closed, Ok(None)));
+
+        // one completing it in time is served
+        let mut framed = connect(addr, false).await?;
+        let reply = request(&mut framed, &["PING"]).await?;
+        assert_eq!(
+            reply,
+            Some(RespFrame::SimpleString(SimpleString::new("PONG")))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_certificates() -> Result<()> {
+        let config = ConfigValues {
+            tls_auth_clients: "yes".to_string(),
+            tls_auth_clients_user: "cn".to_string(),
+            tls_auth_clients_users: vec!["alice".to_string(), "bob.clients.local".to_string()],
+            ..tls_config()
+        };
+        let addr = serve(config.clone()).await?;
+        // the server rejects the handshake once it finds no certificate, which TLS 1.3 clients
+        // only learn when reading
+        let rejected = match connect(addr, false).await {
+            Ok(mut framed) => request(&mut framed, &["PING"]).await.ok().flatten(),
+            Err(_) => None,
+        };
+        assert_eq!(rejected, None);
+        let mut framed = connect(addr, true).await?;
+        assert_eq!(user(&mut framed).await?.as_deref(), Some("alice"));
+
+        let addr = serve(ConfigValues {
+            tls_auth_clients_user: "san".to_string(),
+            ..config.clone()
+        })
+        .await?;
+        let mut framed = connect(addr, true).await?;
+        assert_eq!(
+            user(&mut framed).await?.as_deref(),
+            Some("bob.clients.local")
+        );
+
+        // a certificate naming a user that isn't configured is rejected
+        let addr = serve(ConfigValues {
+            tls_auth_clients_users: vec!["bob.clients.local".to_string()],
+            ..config
+        })
+        .await?;
+        let mut framed = connect(addr, true).await?;
+        assert_eq!(request(&mut framed, &["PING"]).await.ok().flatten(), None);
+        Ok(())
+    }
+}